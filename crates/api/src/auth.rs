@@ -5,12 +5,12 @@ use axum::{
     response::Response,
 };
 use blacklake_core::AuthContext;
-use jsonwebtoken::{decode, DecodingKey, Validation};
+use jsonwebtoken::{decode, errors::ErrorKind, Algorithm, DecodingKey, Validation};
 use serde::{Deserialize, Serialize};
 use std::{
     collections::HashMap,
     sync::Arc,
-    time::{Duration, SystemTime, UNIX_EPOCH},
+    time::{Duration, SystemTime},
 };
 use tokio::sync::RwLock;
 use tower::ServiceBuilder;
@@ -18,27 +18,43 @@ use tower_http::trace::TraceLayer;
 use tracing::{error, info, warn};
 use uuid::Uuid;
 
+/// Minimum time between forced JWKS refreshes triggered by unknown key IDs
+const MIN_JWKS_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JwtClaims {
     pub sub: String,
     pub iss: String,
-    pub aud: String,
     pub exp: u64,
     pub iat: u64,
     pub scope: Option<String>,
+    pub roles: Option<Vec<String>>,
     pub groups: Option<Vec<String>>,
 }
 
-#[derive(Debug, Clone)]
+impl JwtClaims {
+    /// Roles granted by the token, falling back to `groups` for issuers
+    /// that don't emit a `roles` claim
+    pub fn effective_roles(&self) -> Vec<String> {
+        self.roles
+            .clone()
+            .or_else(|| self.groups.clone())
+            .unwrap_or_else(|| vec!["user".to_string()])
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
 pub struct JwksKey {
-    pub kid: String,
+    pub kid: Option<String>,
     pub kty: String,
-    pub alg: String,
-    pub n: String,
-    pub e: String,
+    pub alg: Option<String>,
+    #[serde(rename = "use")]
+    pub key_use: Option<String>,
+    pub n: Option<String>,
+    pub e: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct JwksResponse {
     pub keys: Vec<JwksKey>,
 }
@@ -51,13 +67,14 @@ pub struct OidcConfig {
     pub cache_ttl: Duration,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct CachedJwks {
     pub keys: HashMap<String, DecodingKey>,
+    pub fetched_at: SystemTime,
     pub expires_at: SystemTime,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct AuthLayer {
     pub config: OidcConfig,
     pub jwks_cache: Arc<RwLock<Option<CachedJwks>>>,
@@ -73,41 +90,78 @@ impl AuthLayer {
         }
     }
 
+    /// Authenticate a request from its `Authorization: Bearer` header
+    pub async fn authenticate(&self, headers: &HeaderMap) -> Result<AuthContext, AuthError> {
+        let token = bearer_token(headers)?;
+        let claims = self.verify_token(token).await?;
+
+        Ok(AuthContext {
+            roles: claims.effective_roles(),
+            sub: claims.sub,
+        })
+    }
+
     pub async fn verify_token(&self, token: &str) -> Result<JwtClaims, AuthError> {
         // Decode header to get kid
         let header = jsonwebtoken::decode_header(token)
             .map_err(|_| AuthError::InvalidToken("Invalid JWT header".to_string()))?;
 
+        if !matches!(header.alg, Algorithm::RS256 | Algorithm::RS384 | Algorithm::RS512) {
+            return Err(AuthError::InvalidToken(format!(
+                "Unsupported signing algorithm: {:?}",
+                header.alg
+            )));
+        }
+
         let kid = header.kid.ok_or_else(|| {
             AuthError::InvalidToken("Missing key ID in JWT header".to_string())
         })?;
 
-        // Get JWKS
-        let jwks = self.get_jwks().await?;
-        let decoding_key = jwks.get(&kid).ok_or_else(|| {
-            AuthError::InvalidToken(format!("Key ID {} not found in JWKS", kid))
-        })?;
+        let decoding_key = self.decoding_key(&kid).await?;
 
-        // Validate token
+        // Validate signature, issuer, audience and expiry
         let mut validation = Validation::new(header.alg);
         validation.set_issuer(&[&self.config.issuer]);
         validation.set_audience(&[&self.config.audience]);
-        validation.set_required_spec_claims(&["sub", "iss", "aud", "exp", "iat"]);
+        validation.set_required_spec_claims(&["sub", "iss", "aud", "exp"]);
 
-        let token_data = decode::<JwtClaims>(token, decoding_key, &validation)
-            .map_err(|e| AuthError::InvalidToken(format!("Token validation failed: {}", e)))?;
+        let token_data = decode::<JwtClaims>(token, &decoding_key, &validation)
+            .map_err(|e| match e.kind() {
+                ErrorKind::ExpiredSignature => AuthError::ExpiredToken,
+                ErrorKind::InvalidIssuer => AuthError::InvalidToken("Invalid token issuer".to_string()),
+                ErrorKind::InvalidAudience => AuthError::InvalidToken("Invalid token audience".to_string()),
+                _ => AuthError::InvalidToken(format!("Token validation failed: {}", e)),
+            })?;
 
-        // Check expiration
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
+        Ok(token_data.claims)
+    }
 
-        if token_data.claims.exp < now {
-            return Err(AuthError::ExpiredToken);
+    /// Look up the decoding key for `kid`, refreshing the JWKS when the key
+    /// is unknown so that issuer key rotation is picked up without a restart
+    async fn decoding_key(&self, kid: &str) -> Result<DecodingKey, AuthError> {
+        let jwks = self.get_jwks().await?;
+        if let Some(key) = jwks.get(kid) {
+            return Ok(key.clone());
         }
 
-        Ok(token_data.claims)
+        let recently_fetched = {
+            let cache = self.jwks_cache.read().await;
+            cache.as_ref().map_or(false, |cached| {
+                cached
+                    .fetched_at
+                    .elapsed()
+                    .map_or(false, |age| age < MIN_JWKS_REFRESH_INTERVAL)
+            })
+        };
+
+        if !recently_fetched {
+            let jwks = self.refresh_jwks().await?;
+            if let Some(key) = jwks.get(kid) {
+                return Ok(key.clone());
+            }
+        }
+
+        Err(AuthError::InvalidToken(format!("Key ID {} not found in JWKS", kid)))
     }
 
     async fn get_jwks(&self) -> Result<HashMap<String, DecodingKey>, AuthError> {
@@ -121,21 +175,21 @@ impl AuthLayer {
             }
         }
 
-        // Fetch fresh JWKS
-        let jwks_response = self.fetch_jwks().await?;
-        let mut keys = HashMap::new();
+        self.refresh_jwks().await
+    }
 
-        for key in jwks_response.keys {
-            if key.kty == "RSA" && key.alg == "RS256" {
-                let decoding_key = DecodingKey::from_rsa_components(&key.n, &key.e)
-                    .map_err(|e| AuthError::JwksError(format!("Invalid RSA key: {}", e)))?;
-                keys.insert(key.kid, decoding_key);
-            }
-        }
+    /// Fetch the JWKS and replace the cached key set
+    pub async fn refresh_jwks(&self) -> Result<HashMap<String, DecodingKey>, AuthError> {
+        let jwks_response = self.fetch_jwks().await?;
+        let keys = parse_jwks(jwks_response)?;
 
         // Update cache
-        let expires_at = SystemTime::now() + self.config.cache_ttl;
-        let cached_jwks = CachedJwks { keys: keys.clone(), expires_at };
+        let now = SystemTime::now();
+        let cached_jwks = CachedJwks {
+            keys: keys.clone(),
+            fetched_at: now,
+            expires_at: now + self.config.cache_ttl,
+        };
         {
             let mut cache = self.jwks_cache.write().await;
             *cache = Some(cached_jwks);
@@ -169,6 +223,65 @@ impl AuthLayer {
     }
 }
 
+/// Build decoding keys for the RSA signing keys in a JWKS, skipping
+/// encryption keys and key types we can't verify
+fn parse_jwks(jwks: JwksResponse) -> Result<HashMap<String, DecodingKey>, AuthError> {
+    let mut keys = HashMap::new();
+
+    for key in jwks.keys {
+        if key.kty != "RSA" || key.key_use.as_deref().map_or(false, |u| u != "sig") {
+            continue;
+        }
+        if let Some(alg) = key.alg.as_deref() {
+            if !matches!(alg, "RS256" | "RS384" | "RS512") {
+                continue;
+            }
+        }
+        let (Some(kid), Some(n), Some(e)) = (key.kid, key.n, key.e) else {
+            continue;
+        };
+
+        let decoding_key = DecodingKey::from_rsa_components(&n, &e)
+            .map_err(|e| AuthError::JwksError(format!("Invalid RSA key {}: {}", kid, e)))?;
+        keys.insert(kid, decoding_key);
+    }
+
+    if keys.is_empty() {
+        return Err(AuthError::JwksError("JWKS contains no usable signing keys".to_string()));
+    }
+
+    Ok(keys)
+}
+
+/// Extract the bearer token from an `Authorization` header
+pub fn bearer_token(headers: &HeaderMap) -> Result<&str, AuthError> {
+    let auth_header = headers
+        .get("Authorization")
+        .ok_or(AuthError::MissingAuth)?
+        .to_str()
+        .map_err(|_| AuthError::InvalidAuthFormat)?;
+
+    auth_header
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+        .ok_or(AuthError::InvalidAuthFormat)
+}
+
+/// Whether the caller holds the global `admin` role
+pub(crate) fn is_admin(auth: &AuthContext) -> bool {
+    auth.roles.iter().any(|role| role == "admin")
+}
+
+/// Refuse callers without the global `admin` role
+pub(crate) fn require_admin(auth: &AuthContext) -> Result<(), crate::ApiError> {
+    if is_admin(auth) {
+        Ok(())
+    } else {
+        Err(crate::ApiError::Forbidden("This operation requires the admin role".to_string()))
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum AuthError {
     #[error("Invalid token: {0}")]
@@ -202,36 +315,13 @@ pub async fn auth_middleware(
     mut request: Request,
     next: Next,
 ) -> Result<Response, AuthError> {
-    let headers = request.headers();
-    
-    // Extract token from Authorization header
-    let auth_header = headers
-        .get("Authorization")
-        .ok_or(AuthError::MissingAuth)?
-        .to_str()
-        .map_err(|_| AuthError::InvalidAuthFormat)?;
+    let auth_context = auth_layer.authenticate(request.headers()).await?;
 
-    if !auth_header.starts_with("Bearer ") {
-        return Err(AuthError::InvalidAuthFormat);
-    }
-
-    let token = &auth_header[7..]; // Remove "Bearer " prefix
-
-    // Verify token
-    let claims = auth_layer.verify_token(token).await?;
-
-    // Create auth context
-    let auth_context = AuthContext {
-        user_id: claims.sub.clone(),
-        groups: claims.groups.unwrap_or_default(),
-        scope: claims.scope.unwrap_or_default(),
-    };
+    info!("Authenticated user: {}", auth_context.sub);
 
     // Add to request extensions
     request.extensions_mut().insert(auth_context);
 
-    info!("Authenticated user: {}", claims.sub);
-
     Ok(next.run(request).await)
 }
 
@@ -275,14 +365,211 @@ pub fn create_auth_layer() -> Result<AuthLayer, AuthError> {
     let audience = std::env::var("OIDC_AUDIENCE")
         .map_err(|_| AuthError::JwksError("OIDC_AUDIENCE not set".to_string()))?;
 
-    let jwks_uri = format!("{}/.well-known/jwks.json", issuer.trim_end_matches('/'));
+    let jwks_uri = std::env::var("OIDC_JWKS_URL").unwrap_or_else(|_| {
+        format!("{}/.well-known/jwks.json", issuer.trim_end_matches('/'))
+    });
+
+    let cache_ttl = std::env::var("OIDC_JWKS_CACHE_TTL_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(Duration::from_secs(3600)); // 1 hour
 
     let config = OidcConfig {
         issuer,
         audience,
         jwks_uri,
-        cache_ttl: Duration::from_secs(3600), // 1 hour
+        cache_ttl,
     };
 
     Ok(AuthLayer::new(config))
 }
+
+/// Periodically refresh the JWKS so rotated keys are available before the
+/// first token signed with them arrives
+pub async fn start_jwks_refresh(auth_layer: AuthLayer) {
+    let mut interval = tokio::time::interval(auth_layer.config.cache_ttl);
+
+    loop {
+        interval.tick().await;
+        if let Err(e) = auth_layer.refresh_jwks().await {
+            warn!("JWKS refresh failed: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::get, Json, Router};
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use serde_json::{json, Value};
+
+    const KEY_A_PEM: &str = include_str!("../tests/fixtures/jwt/key_a.pem");
+    const KEY_A_JWK: &str = include_str!("../tests/fixtures/jwt/key_a.jwk.json");
+    const KEY_B_PEM: &str = include_str!("../tests/fixtures/jwt/key_b.pem");
+    const KEY_B_JWK: &str = include_str!("../tests/fixtures/jwt/key_b.jwk.json");
+
+    const ISSUER: &str = "https://issuer.example.com";
+    const AUDIENCE: &str = "blacklake";
+
+    fn jwks(keys: &[&str]) -> Value {
+        let keys: Vec<Value> = keys
+            .iter()
+            .map(|k| serde_json::from_str(k).unwrap())
+            .collect();
+        json!({ "keys": keys })
+    }
+
+    /// Serve `jwks` from a local HTTP server, returning the JWKS URL
+    async fn serve_jwks(jwks: Arc<RwLock<Value>>) -> String {
+        let app = Router::new().route(
+            "/jwks.json",
+            get(move || {
+                let jwks = jwks.clone();
+                async move { Json(jwks.read().await.clone()) }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        format!("http://{}/jwks.json", addr)
+    }
+
+    fn auth_layer(jwks_uri: String) -> AuthLayer {
+        AuthLayer::new(OidcConfig {
+            issuer: ISSUER.to_string(),
+            audience: AUDIENCE.to_string(),
+            jwks_uri,
+            cache_ttl: Duration::from_secs(3600),
+        })
+    }
+
+    fn sign(pem: &str, kid: &str, claims: Value) -> String {
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = Some(kid.to_string());
+        encode(&header, &claims, &EncodingKey::from_rsa_pem(pem.as_bytes()).unwrap()).unwrap()
+    }
+
+    fn claims(exp_offset: i64) -> Value {
+        let now = chrono::Utc::now().timestamp();
+        json!({
+            "sub": "alice@example.com",
+            "iss": ISSUER,
+            "aud": AUDIENCE,
+            "iat": now,
+            "exp": now + exp_offset,
+            "roles": ["admin", "user"],
+        })
+    }
+
+    fn bearer(token: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("Authorization", format!("Bearer {}", token).parse().unwrap());
+        headers
+    }
+
+    #[tokio::test]
+    async fn test_authenticate_locally_signed_token() {
+        let uri = serve_jwks(Arc::new(RwLock::new(jwks(&[KEY_A_JWK])))).await;
+        let layer = auth_layer(uri);
+
+        let token = sign(KEY_A_PEM, "key-a", claims(300));
+        let auth = layer.authenticate(&bearer(&token)).await.unwrap();
+
+        assert_eq!(auth.sub, "alice@example.com");
+        assert_eq!(auth.roles, vec!["admin".to_string(), "user".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_expired_and_misaddressed_tokens() {
+        let uri = serve_jwks(Arc::new(RwLock::new(jwks(&[KEY_A_JWK])))).await;
+        let layer = auth_layer(uri);
+
+        let expired = sign(KEY_A_PEM, "key-a", claims(-3600));
+        assert!(matches!(
+            layer.verify_token(&expired).await,
+            Err(AuthError::ExpiredToken)
+        ));
+
+        let mut wrong_aud = claims(300);
+        wrong_aud["aud"] = json!("someone-else");
+        let token = sign(KEY_A_PEM, "key-a", wrong_aud);
+        assert!(matches!(
+            layer.verify_token(&token).await,
+            Err(AuthError::InvalidToken(_))
+        ));
+
+        let mut wrong_iss = claims(300);
+        wrong_iss["iss"] = json!("https://evil.example.com");
+        let token = sign(KEY_A_PEM, "key-a", wrong_iss);
+        assert!(matches!(
+            layer.verify_token(&token).await,
+            Err(AuthError::InvalidToken(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_rejects_token_signed_with_unpublished_key() {
+        let uri = serve_jwks(Arc::new(RwLock::new(jwks(&[KEY_A_JWK])))).await;
+        let layer = auth_layer(uri);
+
+        // Claims to be key-a but is signed with key-b
+        let forged = sign(KEY_B_PEM, "key-a", claims(300));
+        assert!(matches!(
+            layer.verify_token(&forged).await,
+            Err(AuthError::InvalidToken(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_picks_up_rotated_key() {
+        let published = Arc::new(RwLock::new(jwks(&[KEY_A_JWK])));
+        let uri = serve_jwks(published.clone()).await;
+        let layer = auth_layer(uri);
+
+        let token_a = sign(KEY_A_PEM, "key-a", claims(300));
+        layer.verify_token(&token_a).await.unwrap();
+
+        // Issuer rotates to key-b; backdate the cache so the unknown kid
+        // triggers a refresh instead of being throttled
+        *published.write().await = jwks(&[KEY_B_JWK]);
+        if let Some(cached) = layer.jwks_cache.write().await.as_mut() {
+            cached.fetched_at -= MIN_JWKS_REFRESH_INTERVAL;
+        }
+
+        let token_b = sign(KEY_B_PEM, "key-b", claims(300));
+        layer.verify_token(&token_b).await.unwrap();
+
+        // The retired key is no longer trusted
+        assert!(layer.verify_token(&token_a).await.is_err());
+    }
+
+    #[test]
+    fn test_bearer_token() {
+        assert!(matches!(bearer_token(&HeaderMap::new()), Err(AuthError::MissingAuth)));
+
+        let mut headers = HeaderMap::new();
+        headers.insert("Authorization", "Basic dXNlcjpwYXNz".parse().unwrap());
+        assert!(matches!(bearer_token(&headers), Err(AuthError::InvalidAuthFormat)));
+
+        assert_eq!(bearer_token(&bearer("abc.def.ghi")).unwrap(), "abc.def.ghi");
+    }
+
+    #[test]
+    fn test_require_admin() {
+        use axum::response::IntoResponse;
+
+        let caller = |roles: &[&str]| AuthContext {
+            sub: "someone".to_string(),
+            roles: roles.iter().map(|role| role.to_string()).collect(),
+        };
+        assert!(require_admin(&caller(&["user", "admin"])).is_ok());
+        for roles in [&[][..], &["user"], &["administrator"]] {
+            let err = require_admin(&caller(roles)).unwrap_err();
+            assert_eq!(err.into_response().status(), StatusCode::FORBIDDEN);
+        }
+    }
+}
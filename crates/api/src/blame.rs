@@ -0,0 +1,158 @@
+// Metadata blame
+// Which commit last set each top-level metadata field of an entry, found by
+// walking the ref's history back from its head
+
+use axum::{
+    extract::{Path, Query, State},
+    http::HeaderMap,
+    response::Json,
+    routing::get,
+    Router,
+};
+use blacklake_core::{blame_fields, MetaBlameResponse, Permission};
+use blacklake_index::IndexClient;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::{ApiError, ApiResult, AppState};
+
+/// Commits walked when the request does not pass `depth`
+const DEFAULT_BLAME_DEPTH: usize = 100;
+/// Most commits a single blame may walk
+const MAX_BLAME_DEPTH: usize = 1000;
+
+pub fn create_blame_routes() -> Router<AppState> {
+    Router::new().route("/v1/repos/:repo/blame/:ref/*path", get(blame))
+}
+
+/// Per-field attribution for the entry at `path` on `ref`. `?depth=` caps
+/// how many commits are walked (default 100, at most 1000).
+async fn blame(
+    State(state): State<AppState>,
+    Path((repo, r#ref, path)): Path<(String, String, String)>,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> ApiResult<Json<MetaBlameResponse>> {
+    let auth = crate::extract_auth(&state.auth_layer, &headers).await?;
+
+    let repo_info = state.index.get_repo_by_name(&repo).await?;
+    crate::require_permission(&state, repo_info.id.0, &auth, Permission::Read).await?;
+
+    let depth = match params.get("depth") {
+        None => DEFAULT_BLAME_DEPTH,
+        Some(raw) => raw
+            .parse::<usize>()
+            .ok()
+            .filter(|depth| (1..=MAX_BLAME_DEPTH).contains(depth))
+            .ok_or_else(|| {
+                ApiError::InvalidRequest(format!("depth must be between 1 and {}, got '{}'", MAX_BLAME_DEPTH, raw))
+            })?,
+    };
+
+    let head = state.index.get_ref(repo_info.id.0, &r#ref).await?.commit_id.0;
+    Ok(Json(blame_path(&state.index, head, path, depth).await?))
+}
+
+/// Blame `path` as of commit `head`, walking at most `depth` commits
+pub async fn blame_path(index: &IndexClient, head: Uuid, path: String, depth: usize) -> ApiResult<MetaBlameResponse> {
+    let history = index.get_entry_meta_history(head, &path, depth).await?;
+    if !matches!(history.first(), Some((_, Some(_)))) {
+        return Err(ApiError::Repo(format!("Path not found: {}", path)));
+    }
+
+    let truncated = history.len() == depth && history.last().is_some_and(|(commit, _)| commit.parent_id.is_some());
+    Ok(MetaBlameResponse {
+        fields: blame_fields(&history),
+        depth: history.len(),
+        truncated,
+        path,
+        head,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use blacklake_index::test_support::test_index;
+    use serde_json::{json, Value};
+
+    /// A repo whose `main` history is one commit per `(author, meta)`, oldest
+    /// first, each holding `data/readings.csv` with that metadata. Returns
+    /// the commit ids in the same order.
+    async fn seed_history(index: &IndexClient, commits: &[(&str, Value)]) -> Vec<Uuid> {
+        let repo_id = Uuid::new_v4();
+        sqlx::query("INSERT INTO repo (id, name, created_by) VALUES ($1, $2, 'test')")
+            .bind(repo_id)
+            .bind(format!("blame-{}", repo_id))
+            .execute(index.pool())
+            .await
+            .unwrap();
+
+        let mut ids: Vec<Uuid> = Vec::new();
+        for (author, meta) in commits {
+            let commit_id = Uuid::new_v4();
+            sqlx::query("INSERT INTO commit (id, repo_id, parent_id, author) VALUES ($1, $2, $3, $4)")
+                .bind(commit_id)
+                .bind(repo_id)
+                .bind(ids.last().copied())
+                .bind(author)
+                .execute(index.pool())
+                .await
+                .unwrap();
+            sqlx::query("INSERT INTO entry (commit_id, path, object_sha256, meta) VALUES ($1, 'data/readings.csv', $2, $3)")
+                .bind(commit_id)
+                .bind(format!("{:064x}", 1))
+                .bind(meta)
+                .execute(index.pool())
+                .await
+                .unwrap();
+            ids.push(commit_id);
+        }
+        sqlx::query("INSERT INTO ref (repo_id, name, kind, commit_id) VALUES ($1, 'main', 'branch', $2)")
+            .bind(repo_id)
+            .bind(ids.last().copied())
+            .execute(index.pool())
+            .await
+            .unwrap();
+        ids
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_description_and_tags_are_attributed_to_the_commits_that_changed_them() {
+        let index = test_index().await;
+        let ids = seed_history(
+            &index,
+            &[
+                ("alice", json!({"title": "Readings", "description": "", "tags": ["raw"]})),
+                ("bob", json!({"title": "Readings", "description": "Hourly", "tags": ["raw"]})),
+                ("carol", json!({"title": "Readings", "description": "Hourly", "tags": ["raw", "qc"]})),
+                ("dave", json!({"title": "Readings", "description": "Hourly", "tags": ["raw", "qc"]})),
+            ],
+        )
+        .await;
+        let head = ids[3];
+
+        let blame = blame_path(&index, head, "data/readings.csv".to_string(), DEFAULT_BLAME_DEPTH).await.unwrap();
+
+        assert_eq!((blame.head, blame.depth, blame.truncated), (head, 4, false));
+        let by_field: HashMap<&str, (Uuid, &str)> = blame
+            .fields
+            .iter()
+            .map(|field| (field.field.as_str(), (field.commit_id, field.author.as_str())))
+            .collect();
+        assert_eq!(by_field["description"], (ids[1], "bob"));
+        assert_eq!(by_field["tags"], (ids[2], "carol"));
+        assert_eq!(by_field["title"], (ids[0], "alice"));
+
+        // With the walk capped at two commits, the unchanged title can only
+        // be traced back to the oldest commit walked
+        let capped = blame_path(&index, head, "data/readings.csv".to_string(), 2).await.unwrap();
+        assert!(capped.truncated);
+        let title = capped.fields.iter().find(|field| field.field == "title").unwrap();
+        assert_eq!(title.commit_id, ids[2]);
+
+        let err = blame_path(&index, head, "data/missing.csv".to_string(), DEFAULT_BLAME_DEPTH).await.unwrap_err();
+        assert!(matches!(err, ApiError::Repo(_)), "{:?}", err);
+    }
+}
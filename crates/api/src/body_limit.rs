@@ -0,0 +1,196 @@
+//! Request body size limits.
+//!
+//! Every request body is capped at `MAX_REQUEST_BODY_BYTES` (16 MiB by
+//! default). A body that declares a larger `Content-Length` is refused before
+//! it is read; a chunked one is cut off once it passes the limit. Either way
+//! the client gets a JSON `413`.
+//!
+//! The largest payloads, commits and meta batches with thousands of changes,
+//! are read with [`StreamingJson`], which deserializes straight from the body
+//! stream instead of buffering the raw bytes first.
+
+use std::error::Error as StdError;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use axum::{
+    async_trait,
+    extract::{DefaultBodyLimit, FromRequest, Request},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Router,
+};
+use futures_util::TryStreamExt;
+use http_body_util::LengthLimitError;
+use serde::de::DeserializeOwned;
+use tokio_util::io::{StreamReader, SyncIoBridge};
+use tower_http::limit::RequestBodyLimitLayer;
+
+use crate::ApiError;
+
+/// Body limit when `MAX_REQUEST_BODY_BYTES` is unset
+pub const DEFAULT_MAX_BODY_BYTES: usize = 16 * 1024 * 1024;
+
+/// `MAX_REQUEST_BODY_BYTES`, or the default when it is unset or not a positive number
+pub fn max_body_bytes_from_env() -> usize {
+    std::env::var("MAX_REQUEST_BODY_BYTES")
+        .ok()
+        .and_then(|v| v.trim().parse::<usize>().ok())
+        .filter(|&bytes| bytes > 0)
+        .unwrap_or(DEFAULT_MAX_BODY_BYTES)
+}
+
+/// Cap the request bodies `router` accepts at `max_bytes`
+pub fn limited<S>(router: Router<S>, max_bytes: usize) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    router
+        // The limit layer is the only cap; axum's own 2 MiB default would undercut it
+        .layer(DefaultBodyLimit::disable())
+        .layer(RequestBodyLimitLayer::new(max_bytes))
+        .layer(axum::middleware::map_response(move |response: Response| async move {
+            payload_too_large_as_json(response, max_bytes)
+        }))
+}
+
+/// Replace the limit layer's plain-text `413` with the API's JSON error
+fn payload_too_large_as_json(response: Response, max_bytes: usize) -> Response {
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+    if response.status() != StatusCode::PAYLOAD_TOO_LARGE || is_json {
+        return response;
+    }
+    ApiError::PayloadTooLarge(format!("Request body exceeds the {} byte limit", max_bytes)).into_response()
+}
+
+/// JSON body deserialized as it streams in, for payloads too large to
+/// comfortably hold twice
+pub struct StreamingJson<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for StreamingJson<T>
+where
+    T: DeserializeOwned + Send + 'static,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request, _state: &S) -> Result<Self, Self::Rejection> {
+        let exceeded = Arc::new(AtomicBool::new(false));
+        let flag = exceeded.clone();
+        let stream = req.into_body().into_data_stream().map_err(move |e| {
+            if is_length_limit(&e) {
+                flag.store(true, Ordering::Relaxed);
+            }
+            io::Error::new(io::ErrorKind::Other, e)
+        });
+        let reader = SyncIoBridge::new(StreamReader::new(stream));
+
+        let parsed = tokio::task::spawn_blocking(move || serde_json::from_reader::<_, T>(reader))
+            .await
+            .map_err(|e| ApiError::Internal(format!("JSON body reader failed: {}", e)))?;
+        match parsed {
+            Ok(value) => Ok(StreamingJson(value)),
+            Err(_) if exceeded.load(Ordering::Relaxed) => {
+                Err(ApiError::PayloadTooLarge("Request body exceeds the configured limit".to_string()))
+            }
+            Err(e) => Err(ApiError::InvalidRequest(format!("Invalid JSON body: {}", e))),
+        }
+    }
+}
+
+/// Whether a body error came from the limit layer cutting the body off
+fn is_length_limit(err: &axum::Error) -> bool {
+    let mut source: Option<&(dyn StdError + 'static)> = Some(err);
+    while let Some(e) = source {
+        if e.is::<LengthLimitError>() {
+            return true;
+        }
+        source = e.source();
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request as HttpRequest;
+    use axum::routing::post;
+    use axum::Json;
+    use serde_json::{json, Value};
+    use tower::ServiceExt;
+
+    const LIMIT: usize = 1024;
+
+    fn app() -> Router {
+        limited(
+            Router::new()
+                .route("/streamed", post(|StreamingJson(body): StreamingJson<Value>| async move { Json(body) }))
+                .route("/buffered", post(|Json(body): Json<Value>| async move { Json(body) })),
+            LIMIT,
+        )
+    }
+
+    async fn post_body(uri: &str, body: Body, content_length: Option<usize>) -> (StatusCode, Value) {
+        let mut request = HttpRequest::builder().method("POST").uri(uri).header(header::CONTENT_TYPE, "application/json");
+        if let Some(length) = content_length {
+            request = request.header(header::CONTENT_LENGTH, length);
+        }
+        let response = app().oneshot(request.body(body).unwrap()).await.unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+    }
+
+    /// A body sent in pieces, without a Content-Length
+    fn chunked(chunks: Vec<String>) -> Body {
+        Body::from_stream(futures_util::stream::iter(chunks.into_iter().map(Ok::<_, io::Error>)))
+    }
+
+    fn changes(n: usize) -> String {
+        json!({ "changes": (0..n).map(|i| format!("data/{}.csv", i)).collect::<Vec<_>>() }).to_string()
+    }
+
+    #[tokio::test]
+    async fn test_oversized_body_is_rejected_with_413() {
+        let oversized = changes(200);
+        assert!(oversized.len() > LIMIT);
+
+        for uri in ["/streamed", "/buffered"] {
+            let (status, body) = post_body(uri, Body::from(oversized.clone()), Some(oversized.len())).await;
+            assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE, "{}", uri);
+            assert!(body["error"].as_str().unwrap().contains("1024 byte limit"), "{}", body);
+        }
+
+        // Without a Content-Length the body is cut off while it streams in
+        let (head, tail) = oversized.split_at(oversized.len() / 2);
+        let (status, body) = post_body("/streamed", chunked(vec![head.to_string(), tail.to_string()]), None).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        assert!(body["error"].is_string(), "{}", body);
+    }
+
+    #[tokio::test]
+    async fn test_body_within_limit_is_accepted() {
+        let small = changes(5);
+        assert!(small.len() < LIMIT);
+
+        for uri in ["/streamed", "/buffered"] {
+            let (status, body) = post_body(uri, Body::from(small.clone()), Some(small.len())).await;
+            assert_eq!(status, StatusCode::OK, "{}", uri);
+            assert_eq!(body["changes"][4], "data/4.csv");
+        }
+
+        let (status, body) = post_body("/streamed", chunked(vec!["{\"changes\":".to_string(), "[\"a\"]}".to_string()]), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, json!({"changes": ["a"]}));
+
+        let (status, _) = post_body("/streamed", Body::from("{\"changes\":"), None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,597 @@
+// Commit planning
+// The checks and metadata merges a commit performs, shared by real commits
+// and `?dry_run=true`, which reports them without writing anything
+
+use blacklake_core::content_policy::ContentTypePolicy;
+use blacklake_core::governance::{CheckResult, CheckStatus, PolicyEvaluation};
+use blacklake_core::policy_backend::{CommitPolicyInput, PolicyBackend};
+use blacklake_core::signing::SignatureVerifier;
+use blacklake_core::{
+    deep_merge, validate_changes, AuthContext, Change, ChangeOp, ChangeValidationError, CommitPlan, CommitRequest,
+    ReferenceKind, SchemaRegistry,
+};
+use blacklake_core::sniff::SNIFF_PREFIX_BYTES;
+use blacklake_index::{IndexClient, IndexError};
+use blacklake_storage::{StorageClient, StorageError};
+use uuid::Uuid;
+
+use crate::{ApiError, ApiResult};
+
+/// The services a write to a ref is checked against
+pub struct CommitGate<'a> {
+    pub index: &'a IndexClient,
+    pub storage: &'a StorageClient,
+    pub policy: &'a dyn PolicyBackend,
+    pub signatures: &'a SignatureVerifier,
+}
+
+impl CommitGate<'_> {
+    /// Refuse writing `changes` to `request.r#ref` unless the repo is
+    /// writable, the commit policy allows it, the hard quota is not exceeded
+    /// and every new object passes the content type lists and signature
+    /// requirements. Commits and merges both go through here, so neither can
+    /// land on a ref the other could not. Signature status is recorded on
+    /// `changes`.
+    pub async fn enforce(
+        &self,
+        (repo_id, repo): (Uuid, &str),
+        request: &CommitRequest,
+        head: Option<Uuid>,
+        auth: &AuthContext,
+        changes: &mut [Change],
+    ) -> ApiResult<()> {
+        crate::virtual_repo::ensure_writable(self.index, repo_id, repo).await?;
+
+        // Check branch protection rules, or the external commit policy
+        let evaluation = evaluate_policy(self.index, self.policy, repo_id, request, head, auth).await?;
+        if !evaluation.allowed {
+            self.index
+                .log_audit(
+                    &auth.sub,
+                    "policy_violation",
+                    Some(repo),
+                    Some(&request.r#ref),
+                    None,
+                    Some(&serde_json::json!({
+                        "policy_name": "branch_protection",
+                        "violation_reason": evaluation.reason,
+                        "required_checks": evaluation.required_checks,
+                        "missing_reviewers": evaluation.missing_reviewers
+                    })),
+                    None,
+                )
+                .await?;
+            return Err(ApiError::Forbidden(
+                evaluation
+                    .reason
+                    .unwrap_or_else(|| "Branch protection policy violation".to_string()),
+            ));
+        }
+
+        if let Some(quota) = self.index.get_quota_status(repo_id).await? {
+            if quota.hard_exceeded {
+                return Err(ApiError::PayloadTooLarge(format!(
+                    "Repository quota exceeded: {} bytes (limit: {} bytes)",
+                    quota.current_bytes, quota.hard_limit
+                )));
+            }
+        }
+
+        // Repos with content type lists refuse disallowed objects, judged by
+        // their sniffed bytes as well as the type they were uploaded with
+        let features = self.index.get_repo_features(repo_id).await?;
+        let content_types = ContentTypePolicy::from_features(&features);
+        let disallowed = check_content_types(self.index, self.storage, &content_types, changes).await?;
+        if !disallowed.is_empty() {
+            return Err(ApiError::InvalidRequest(disallowed.join("; ")));
+        }
+
+        // Record model signature status; repos with require_signed_models refuse untrusted ones
+        let unsigned = check_signatures(self.index, self.signatures, changes, features.require_signed_models()).await?;
+        if !unsigned.is_empty() {
+            return Err(ApiError::Forbidden(unsigned.join("; ")));
+        }
+
+        Ok(())
+    }
+}
+
+/// Commit policy verdict for committing `request` on top of `head`, from the
+/// configured policy backend
+pub async fn evaluate_policy(
+    index: &IndexClient,
+    backend: &dyn PolicyBackend,
+    repo_id: Uuid,
+    request: &CommitRequest,
+    head: Option<Uuid>,
+    auth: &AuthContext,
+) -> ApiResult<PolicyEvaluation> {
+    let protected_ref = index.get_protected_ref(repo_id, &request.r#ref).await?;
+    let commit_id = head.unwrap_or_else(Uuid::new_v4);
+    let check_results = match protected_ref {
+        Some(_) => index.get_check_results(repo_id, &request.r#ref, commit_id).await?,
+        None => Vec::new(),
+    };
+
+    let input = CommitPolicyInput {
+        repo_id,
+        ref_name: request.r#ref.clone(),
+        commit_id,
+        user: auth.sub.clone(),
+        roles: auth.roles.clone(),
+        is_admin: auth.roles.iter().any(|r| r == "admin"),
+        message: request.message.clone(),
+        changes: request.changes.clone(),
+        protected_ref,
+        check_results,
+    };
+    backend
+        .evaluate_commit(&input)
+        .await
+        .map_err(|e| ApiError::Unavailable(e.to_string()))
+}
+
+/// The changes a commit writes. With `merge_metadata`, modify and meta changes
+/// are deep-merged over the metadata the path has at `head`.
+pub async fn resolve_changes(
+    index: &IndexClient,
+    head: Option<Uuid>,
+    changes: &[Change],
+    merge_metadata: bool,
+) -> ApiResult<Vec<Change>> {
+    let merges = merge_metadata && changes.iter().any(|c| matches!(c.op, ChangeOp::Modify | ChangeOp::Meta));
+    let current = match (head, merges) {
+        (Some(head), true) => index.get_merge_sides(head).await?,
+        _ => return Ok(changes.to_vec()),
+    };
+
+    changes
+        .iter()
+        .map(|change| {
+            let mut resolved = change.clone();
+            if matches!(change.op, ChangeOp::Modify | ChangeOp::Meta) {
+                if let Some(side) = current.get(&change.path) {
+                    resolved.meta = deep_merge(&side.meta, &change.meta).map_err(|e| {
+                        ApiError::InvalidRequest(format!("Cannot merge metadata for '{}': {}", change.path, e))
+                    })?;
+                }
+            }
+            Ok(resolved)
+        })
+        .collect()
+}
+
+/// Record the signature status of the model files in `changes`; returns why
+/// the commit must be refused when `require_signed` is on
+pub async fn check_signatures(
+    index: &IndexClient,
+    verifier: &SignatureVerifier,
+    changes: &mut [Change],
+    require_signed: bool,
+) -> ApiResult<Vec<String>> {
+    let sha256s: Vec<String> = changes
+        .iter()
+        .filter(|c| matches!(c.op, ChangeOp::Add | ChangeOp::Modify))
+        .filter_map(|c| c.sha256.clone())
+        .collect();
+    if sha256s.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let media_types = index.object_media_types(&sha256s).await?;
+    verifier
+        .check_changes(changes, &media_types, require_signed)
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))
+}
+
+/// Check the objects `changes` add or modify against the repo's content type
+/// lists, using both their recorded media type and the type sniffed from
+/// their leading bytes; returns why the commit must be refused
+pub async fn check_content_types(
+    index: &IndexClient,
+    storage: &StorageClient,
+    policy: &ContentTypePolicy,
+    changes: &[Change],
+) -> ApiResult<Vec<String>> {
+    if !policy.is_enforced() {
+        return Ok(Vec::new());
+    }
+    let objects: Vec<(&str, &str)> = changes
+        .iter()
+        .filter(|c| matches!(c.op, ChangeOp::Add | ChangeOp::Modify))
+        .filter_map(|c| Some((c.path.as_str(), c.sha256.as_deref()?)))
+        .collect();
+    if objects.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let sha256s: Vec<String> = objects.iter().map(|(_, sha256)| sha256.to_string()).collect();
+    let media_types = index.object_media_types(&sha256s).await?;
+    let range = format!("bytes=0-{}", SNIFF_PREFIX_BYTES - 1);
+
+    let mut rejected = Vec::new();
+    for (path, sha256) in objects {
+        let key = StorageClient::content_address_key(sha256);
+        let prefix = match storage.get_object_range(&key, Some(&range)).await {
+            Ok(Some(object)) => object
+                .body
+                .collect()
+                .await
+                .map_err(|e| ApiError::Internal(format!("Failed to read {}: {}", key, e)))?
+                .into_bytes(),
+            // Missing objects are reported by the commit itself; empty ones have nothing to sniff
+            Ok(None) | Err(StorageError::RangeNotSatisfiable(_)) => Default::default(),
+            Err(e) => return Err(e.into()),
+        };
+        let declared = media_types.get(sha256).map(String::as_str);
+        if let Err(e) = policy.check_object(declared, &prefix) {
+            rejected.push(format!("{}: {}", path, e));
+        }
+    }
+    Ok(rejected)
+}
+
+/// Name of the check recorded for refs whose protection sets `require_schema_pass`
+pub const SCHEMA_CHECK_NAME: &str = "schema";
+
+/// Schema validation of a commit's changes, for a ref that requires it to pass
+#[derive(Debug)]
+pub struct SchemaGate {
+    pub repo_id: Uuid,
+    pub ref_name: String,
+    pub errors: Vec<ChangeValidationError>,
+}
+
+impl SchemaGate {
+    fn check_result(&self, commit_id: Uuid) -> CheckResult {
+        let output = self
+            .errors
+            .iter()
+            .map(|e| format!("{} [{}]: {}", e.path, e.field, e.message))
+            .collect::<Vec<_>>()
+            .join("\n");
+        CheckResult {
+            id: Uuid::new_v4(),
+            repo_id: self.repo_id,
+            ref_name: self.ref_name.clone(),
+            commit_id,
+            check_name: SCHEMA_CHECK_NAME.to_string(),
+            status: if self.errors.is_empty() { CheckStatus::Success } else { CheckStatus::Failure },
+            details_url: None,
+            output: (!output.is_empty()).then_some(output),
+        }
+    }
+
+    /// Record the passing check against the commit that was written
+    pub async fn record_pass(&self, index: &IndexClient, commit_id: Uuid) -> ApiResult<()> {
+        index.submit_check_result(&self.check_result(commit_id)).await?;
+        Ok(())
+    }
+}
+
+/// Validate every change against the repository schema when the ref's
+/// protection sets `require_schema_pass`; `None` when it does not.
+///
+/// A failing validation is recorded as a failed `schema` check on `head`,
+/// the commit the changes were proposed on, and refuses the commit.
+pub async fn enforce_schema_check(
+    index: &IndexClient,
+    registry: &SchemaRegistry,
+    (schema_collection, schema_version): (&str, &str),
+    repo_id: Uuid,
+    ref_name: &str,
+    head: Option<Uuid>,
+    changes: &[Change],
+) -> ApiResult<Option<SchemaGate>> {
+    let required = index
+        .get_protected_ref(repo_id, ref_name)
+        .await?
+        .is_some_and(|rule| rule.require_schema_pass);
+    if !required {
+        return Ok(None);
+    }
+
+    let errors = validate_changes(registry, schema_collection, schema_version, changes)
+        .map_err(|e| ApiError::InvalidRequest(e.to_string()))?;
+    let gate = SchemaGate { repo_id, ref_name: ref_name.to_string(), errors };
+    if gate.errors.is_empty() {
+        return Ok(Some(gate));
+    }
+
+    if let Some(head) = head {
+        index.submit_check_result(&gate.check_result(head)).await?;
+    }
+    Err(ApiError::Forbidden(format!(
+        "'{}' requires a passing schema check: {} validation error(s), first at '{}' [{}]: {}",
+        ref_name,
+        gate.errors.len(),
+        gate.errors[0].path,
+        gate.errors[0].field,
+        gate.errors[0].message
+    )))
+}
+
+/// Run every check a commit of `request` would face and report the outcome.
+///
+/// Unlike a real commit this does not stop at the first problem: all change
+/// errors and every reason the commit would be refused are collected.
+pub async fn plan_commit(
+    index: &IndexClient,
+    policy: &dyn PolicyBackend,
+    registry: &SchemaRegistry,
+    (schema_collection, schema_version): (&str, &str),
+    repo_id: Uuid,
+    auth: &AuthContext,
+    request: &CommitRequest,
+    merge_metadata: bool,
+) -> ApiResult<CommitPlan> {
+    let mut blocking_reasons = Vec::new();
+
+    let head = match index.get_ref(repo_id, &request.r#ref).await {
+        Ok(reference) => {
+            if matches!(reference.kind, ReferenceKind::Tag) {
+                blocking_reasons.push(format!("'{}' is a tag and cannot be committed to", request.r#ref));
+            }
+            Some(reference.commit_id.0)
+        }
+        Err(IndexError::RefNotFound(_)) => None,
+        Err(e) => return Err(e.into()),
+    };
+
+    if let Some(expected) = request.expected_parent {
+        if head != Some(expected.0) {
+            blocking_reasons.push(match head {
+                Some(actual) => format!("'{}' is at {}, not the expected parent {}", request.r#ref, actual, expected.0),
+                None => format!("'{}' does not exist, so it cannot be at {}", request.r#ref, expected.0),
+            });
+        }
+    }
+
+    let evaluation = evaluate_policy(index, policy, repo_id, request, head, auth).await?;
+    if !evaluation.allowed {
+        blocking_reasons.push(
+            evaluation
+                .reason
+                .unwrap_or_else(|| "Branch protection policy violation".to_string()),
+        );
+    }
+
+    if let Some(quota) = index.get_quota_status(repo_id).await? {
+        if quota.hard_exceeded {
+            blocking_reasons.push(format!(
+                "Repository quota exceeded: {} bytes (limit: {} bytes)",
+                quota.current_bytes, quota.hard_limit
+            ));
+        }
+    }
+
+    let errors = validate_changes(registry, schema_collection, schema_version, &request.changes)
+        .map_err(|e| ApiError::InvalidRequest(e.to_string()))?;
+    let changes = resolve_changes(index, head, &request.changes, merge_metadata).await?;
+
+    Ok(CommitPlan::new(head, changes, errors, blocking_reasons))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use blacklake_core::policy_backend::{BuiltinPolicyBackend, FailureMode, OpaPolicyBackend};
+    use blacklake_index::test_support::test_index;
+    use serde_json::{json, Value};
+
+    /// A repo whose `main` points at one commit holding `data/a.csv`
+    async fn seed_repo(index: &IndexClient) -> (Uuid, Uuid) {
+        let repo_id = Uuid::new_v4();
+        let commit_id = Uuid::new_v4();
+        sqlx::query("INSERT INTO repo (id, name, created_by) VALUES ($1, $2, 'test')")
+            .bind(repo_id)
+            .bind(format!("plan-{}", repo_id))
+            .execute(index.pool())
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO commit (id, repo_id, author) VALUES ($1, $2, 'test')")
+            .bind(commit_id)
+            .bind(repo_id)
+            .execute(index.pool())
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO entry (commit_id, path, meta) VALUES ($1, 'data/a.csv', $2)")
+            .bind(commit_id)
+            .bind(json!({"name": "a", "creator": "a@example.com", "tags": ["raw"]}))
+            .execute(index.pool())
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO ref (repo_id, name, kind, commit_id) VALUES ($1, 'main', 'branch', $2)")
+            .bind(repo_id)
+            .bind(commit_id)
+            .execute(index.pool())
+            .await
+            .unwrap();
+        (repo_id, commit_id)
+    }
+
+    fn auth() -> AuthContext {
+        AuthContext { sub: "tester".to_string(), roles: vec![] }
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_dry_run_reports_schema_errors_and_writes_no_commit() {
+        let index = test_index().await;
+        let (repo_id, head) = seed_repo(&index).await;
+        let registry = SchemaRegistry::default();
+        let request = CommitRequest {
+            r#ref: "main".to_string(),
+            message: Some("dry run".to_string()),
+            changes: vec![Change {
+                op: ChangeOp::Add,
+                path: "data/b.csv".to_string(),
+                sha256: Some("a".repeat(64)),
+                meta: json!({"name": "b"}),
+            }],
+            expected_parent: None,
+        };
+
+        let plan = plan_commit(&index, &BuiltinPolicyBackend, &registry, ("default", "1.0"), repo_id, &auth(), &request, false)
+            .await
+            .unwrap();
+
+        assert!(!plan.allowed);
+        assert_eq!(plan.parent_id, Some(head));
+        assert!(plan.errors.iter().any(|e| e.path == "data/b.csv" && e.field == "creator"));
+        let commits: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM commit WHERE repo_id = $1")
+            .bind(repo_id)
+            .fetch_one(index.pool())
+            .await
+            .unwrap();
+        assert_eq!(commits, 1);
+        assert_eq!(index.get_ref(repo_id, "main").await.unwrap().commit_id.0, head);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_dry_run_merges_metadata_and_flags_stale_parent() {
+        let index = test_index().await;
+        let (repo_id, head) = seed_repo(&index).await;
+        let stale = Uuid::new_v4();
+        let request = CommitRequest {
+            r#ref: "main".to_string(),
+            message: None,
+            changes: vec![Change {
+                op: ChangeOp::Meta,
+                path: "data/a.csv".to_string(),
+                sha256: None,
+                meta: json!({"description": "updated"}),
+            }],
+            expected_parent: Some(blacklake_core::UuidWrapper(stale)),
+        };
+
+        let plan = plan_commit(&index, &BuiltinPolicyBackend, &SchemaRegistry::default(), ("default", "1.0"), repo_id, &auth(), &request, true)
+            .await
+            .unwrap();
+
+        assert_eq!(plan.changes[0].meta["creator"], "a@example.com");
+        assert_eq!(plan.changes[0].meta["description"], "updated");
+        assert!(plan.blocking_reasons.iter().any(|r| r.contains(&head.to_string())));
+        assert!(!plan.allowed);
+    }
+
+    async fn require_schema_pass(index: &IndexClient, repo_id: Uuid) {
+        index
+            .set_protected_ref(&blacklake_core::governance::ProtectedRef {
+                id: Uuid::new_v4(),
+                repo_id,
+                ref_name: "main".to_string(),
+                require_admin: false,
+                allow_fast_forward: true,
+                allow_delete: false,
+                required_checks: vec![],
+                required_reviewers: 0,
+                require_schema_pass: true,
+            })
+            .await
+            .unwrap();
+    }
+
+    fn add(path: &str, meta: Value) -> Change {
+        Change { op: ChangeOp::Add, path: path.to_string(), sha256: Some("a".repeat(64)), meta }
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_schema_check_blocks_bad_commit_and_records_failure() {
+        let index = test_index().await;
+        let (repo_id, head) = seed_repo(&index).await;
+        require_schema_pass(&index, repo_id).await;
+        let registry = SchemaRegistry::default();
+        let changes = vec![add("data/b.csv", json!({"name": "b"}))];
+
+        let result =
+            enforce_schema_check(&index, &registry, ("default", "1.0"), repo_id, "main", Some(head), &changes).await;
+
+        assert!(matches!(result, Err(ApiError::Forbidden(reason)) if reason.contains("schema check")));
+        let checks = index.get_check_results(repo_id, "main", head).await.unwrap();
+        assert_eq!(checks.len(), 1);
+        assert_eq!(checks[0].check_name, SCHEMA_CHECK_NAME);
+        assert_eq!(checks[0].status, CheckStatus::Failure);
+        assert!(checks[0].output.as_deref().unwrap().contains("data/b.csv [creator]"));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_schema_check_passes_valid_commit_and_skips_unflagged_refs() {
+        let index = test_index().await;
+        let (repo_id, head) = seed_repo(&index).await;
+        let registry = SchemaRegistry::default();
+        let bad = vec![add("data/b.csv", json!({"name": "b"}))];
+        let good = vec![add(
+            "data/b.csv",
+            json!({
+                "creation_dt": "2024-01-01T00:00:00Z",
+                "creator": "b@example.com",
+                "file_name": "b.csv",
+                "file_type": "text/csv",
+                "file_size": 100,
+                "org_lab": "TestLab",
+                "description": "Second sample",
+                "data_source": "lab",
+                "data_collection_method": "instrument",
+                "version": "1.0"
+            }),
+        )];
+
+        // Without the flag the gate stays out of the way
+        let unflagged = enforce_schema_check(&index, &registry, ("default", "1.0"), repo_id, "main", Some(head), &bad).await;
+        assert!(unflagged.unwrap().is_none());
+
+        require_schema_pass(&index, repo_id).await;
+        let gate = enforce_schema_check(&index, &registry, ("default", "1.0"), repo_id, "main", Some(head), &good)
+            .await
+            .unwrap()
+            .unwrap();
+        let new_commit = Uuid::new_v4();
+        gate.record_pass(&index, new_commit).await.unwrap();
+
+        let checks = index.get_check_results(repo_id, "main", new_commit).await.unwrap();
+        assert_eq!(checks[0].status, CheckStatus::Success);
+        assert_eq!(checks[0].output, None);
+    }
+
+    /// An OPA stand-in answering every decision query with `decision`
+    async fn stub_opa(decision: Value) -> String {
+        let app = axum::Router::new().route(
+            "/v1/data/blacklake/commit",
+            axum::routing::post(move || {
+                let decision = decision.clone();
+                async move { axum::Json(decision) }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/v1/data/blacklake/commit", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        url
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_opa_denial_blocks_the_commit() {
+        let index = test_index().await;
+        let (repo_id, _) = seed_repo(&index).await;
+        let opa = stub_opa(json!({"result": {"allow": false, "reasons": ["main is frozen for release"]}})).await;
+        let backend = OpaPolicyBackend::new(opa, FailureMode::Closed, std::time::Duration::from_secs(5)).unwrap();
+        let request = CommitRequest {
+            r#ref: "main".to_string(),
+            message: Some("late change".to_string()),
+            changes: vec![],
+            expected_parent: None,
+        };
+
+        let plan = plan_commit(&index, &backend, &SchemaRegistry::default(), ("default", "1.0"), repo_id, &auth(), &request, false)
+            .await
+            .unwrap();
+
+        assert!(!plan.allowed);
+        assert_eq!(plan.blocking_reasons, vec!["main is frozen for release".to_string()]);
+    }
+}
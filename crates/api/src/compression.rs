@@ -0,0 +1,116 @@
+//! Response compression for the large read endpoints.
+//!
+//! Search results, tree listings and RDF graphs are sent gzip- or
+//! zstd-encoded when the client's `Accept-Encoding` allows it. ETags name the
+//! uncompressed representation, so an encoded response carries the weak form
+//! of its tag: `If-None-Match` still matches it, since that comparison is
+//! weak, but caches do not mistake the encoded bytes for the identity ones.
+
+use axum::{
+    body::Body,
+    http::{header, HeaderValue, Response},
+    Router,
+};
+use tower::ServiceBuilder;
+use tower_http::compression::{CompressionBody, CompressionLayer};
+
+/// Compress `router`'s responses as the client's `Accept-Encoding` allows
+pub fn compressed<S>(router: Router<S>) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    router.layer(
+        ServiceBuilder::new()
+            .map_response(weaken_encoded_etag::<CompressionBody<Body>>)
+            .layer(CompressionLayer::new().gzip(true).zstd(true)),
+    )
+}
+
+/// Mark the ETag of an encoded response weak
+fn weaken_encoded_etag<B>(mut response: Response<B>) -> Response<B> {
+    if !response.headers().contains_key(header::CONTENT_ENCODING) {
+        return response;
+    }
+    let weak = response
+        .headers()
+        .get(header::ETAG)
+        .and_then(|value| value.to_str().ok())
+        .filter(|etag| !etag.starts_with("W/"))
+        .and_then(|etag| HeaderValue::from_str(&format!("W/{}", etag)).ok());
+    if let Some(weak) = weak {
+        response.headers_mut().insert(header::ETAG, weak);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conditional::{respond_with_etag, strong_etag};
+    use axum::http::{HeaderMap, Request, StatusCode};
+    use axum::routing::get;
+    use tower::ServiceExt;
+
+    fn turtle() -> String {
+        (0..200)
+            .map(|i| format!("<https://blacklake.local/repo/main/data/{i}.csv> <http://purl.org/dc/terms/title> \"File {i}\" .\n"))
+            .collect()
+    }
+
+    fn app() -> Router {
+        compressed(Router::new().route(
+            "/rdf",
+            get(|headers: HeaderMap| async move {
+                let graph = turtle();
+                let etag = strong_etag(&blacklake_core::hash_bytes(graph.as_bytes()));
+                respond_with_etag(&headers, &etag, "text/turtle", graph)
+            }),
+        ))
+    }
+
+    async fn get_rdf(headers: &[(&str, &str)]) -> (StatusCode, HeaderMap, Vec<u8>) {
+        let mut request = Request::builder().uri("/rdf");
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        let response = app().oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+        let (parts, body) = response.into_parts();
+        let bytes = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+        (parts.status, parts.headers, bytes.to_vec())
+    }
+
+    #[tokio::test]
+    async fn test_zstd_response_decodes_to_the_original_body() {
+        let graph = turtle();
+        let etag = strong_etag(&blacklake_core::hash_bytes(graph.as_bytes()));
+
+        let (status, headers, body) = get_rdf(&[("accept-encoding", "zstd")]).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers[header::CONTENT_ENCODING], "zstd");
+        assert!(headers[header::VARY].to_str().unwrap().to_ascii_lowercase().contains("accept-encoding"));
+        assert_eq!(headers[header::ETAG], format!("W/{}", etag).as_str());
+        assert!(body.len() < graph.len());
+        assert_eq!(zstd::decode_all(body.as_slice()).unwrap(), graph.as_bytes());
+    }
+
+    #[tokio::test]
+    async fn test_etag_names_the_uncompressed_representation() {
+        let graph = turtle();
+        let etag = strong_etag(&blacklake_core::hash_bytes(graph.as_bytes()));
+
+        // Without Accept-Encoding the body and its strong tag are untouched
+        let (_, headers, body) = get_rdf(&[]).await;
+        assert!(headers.get(header::CONTENT_ENCODING).is_none());
+        assert_eq!(headers[header::ETAG], etag.as_str());
+        assert_eq!(body, graph.as_bytes());
+
+        // The weak tag of a compressed response still revalidates, with either encoding
+        let weak = format!("W/{}", etag);
+        for encoding in ["zstd", "gzip"] {
+            let (status, _, body) = get_rdf(&[("accept-encoding", encoding), ("if-none-match", &weak)]).await;
+            assert_eq!(status, StatusCode::NOT_MODIFIED);
+            assert!(body.is_empty());
+        }
+    }
+}
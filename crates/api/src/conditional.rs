@@ -0,0 +1,196 @@
+//! Conditional GET support for content-addressed responses.
+//!
+//! Blobs and RDF graphs are identified by their sha256, which makes it a
+//! strong ETag: the same tag always means the same bytes. Raw blob downloads
+//! are streamed from S3 here too, including ranged (`206`) responses.
+
+use axum::{
+    body::Body,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::Response,
+};
+use blacklake_storage::ObjectBody;
+
+/// Quote a sha256 digest as a strong entity tag
+pub fn strong_etag(sha256: &str) -> String {
+    format!("\"{}\"", sha256)
+}
+
+/// Whether the request's `If-None-Match` header matches `etag`.
+///
+/// Uses the weak comparison required for `If-None-Match`, so `W/"x"` matches `"x"`.
+pub fn if_none_match(headers: &HeaderMap, etag: &str) -> bool {
+    let etag = etag.trim_start_matches("W/");
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .any(|candidate| candidate == "*" || candidate.trim_start_matches("W/") == etag)
+}
+
+/// `304 Not Modified` carrying the current ETag and no body
+pub fn not_modified(etag: &str) -> Response {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = StatusCode::NOT_MODIFIED;
+    if let Ok(value) = HeaderValue::from_str(etag) {
+        response.headers_mut().insert(header::ETAG, value);
+    }
+    response
+}
+
+/// Answer with `304` if the client already holds `etag`, otherwise `200` with `body`
+pub fn respond_with_etag(headers: &HeaderMap, etag: &str, content_type: &str, body: impl Into<Body>) -> Response {
+    if if_none_match(headers, etag) {
+        return not_modified(etag);
+    }
+
+    let mut response = Response::new(body.into());
+    if let Ok(value) = HeaderValue::from_str(content_type) {
+        response.headers_mut().insert(header::CONTENT_TYPE, value);
+    }
+    if let Ok(value) = HeaderValue::from_str(etag) {
+        response.headers_mut().insert(header::ETAG, value);
+    }
+    response
+}
+
+/// `Content-Disposition` naming the download after the last path segment.
+///
+/// The quoted `filename` is an ASCII fallback; `filename*` carries the exact name.
+pub fn attachment_disposition(path: &str) -> String {
+    let name = path.rsplit('/').next().filter(|name| !name.is_empty()).unwrap_or("download");
+    let fallback: String = name
+        .chars()
+        .map(|c| if c.is_ascii_graphic() && c != '"' && c != '\\' || c == ' ' { c } else { '_' })
+        .collect();
+    format!("attachment; filename=\"{}\"; filename*=UTF-8''{}", fallback, urlencoding::encode(name))
+}
+
+/// Stream an object's bytes as a download.
+///
+/// A body fetched with a `Range` is answered with `206 Partial Content` and
+/// the `Content-Range` S3 returned; otherwise `200`.
+pub fn stream_object(etag: &str, path: &str, object: ObjectBody) -> Response {
+    let content_type = object.content_type.as_deref().unwrap_or("application/octet-stream").to_string();
+    let stream = tokio_util::io::ReaderStream::new(object.body.into_async_read());
+    let mut response = respond_with_etag(&HeaderMap::new(), etag, &content_type, Body::from_stream(stream));
+
+    let headers = response.headers_mut();
+    headers.insert(header::CONTENT_LENGTH, object.content_length.into());
+    headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+    if let Ok(value) = HeaderValue::from_str(&attachment_disposition(path)) {
+        headers.insert(header::CONTENT_DISPOSITION, value);
+    }
+    if let Some(value) = object.content_range.as_deref().and_then(|range| HeaderValue::from_str(range).ok()) {
+        headers.insert(header::CONTENT_RANGE, value);
+        *response.status_mut() = StatusCode::PARTIAL_CONTENT;
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHA: &str = "a665a45920422f9d417e4867efdc4fb8a04a1f3fff1fa07e998e86f7f7a27ae3";
+
+    fn request_headers(if_none_match: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_NONE_MATCH, HeaderValue::from_str(if_none_match).unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_matching_etag_returns_304() {
+        let etag = strong_etag(SHA);
+        let response = respond_with_etag(&request_headers(&etag), &etag, "text/turtle", "graph");
+
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[header::ETAG], etag.as_str());
+        assert!(response.headers().get(header::CONTENT_TYPE).is_none());
+    }
+
+    #[test]
+    fn test_other_etag_returns_200() {
+        let etag = strong_etag(SHA);
+        let response = respond_with_etag(&request_headers("\"stale\""), &etag, "text/turtle", "graph");
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::ETAG], etag.as_str());
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "text/turtle");
+
+        let response = respond_with_etag(&HeaderMap::new(), &etag, "text/turtle", "graph");
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_if_none_match_lists_wildcards_and_weak_tags() {
+        let etag = strong_etag(SHA);
+
+        assert!(if_none_match(&request_headers(&format!("\"other\", {}", etag)), &etag));
+        assert!(if_none_match(&request_headers(&format!("W/{}", etag)), &etag));
+        assert!(if_none_match(&request_headers("*"), &etag));
+        assert!(!if_none_match(&request_headers(SHA), &etag));
+    }
+
+    async fn body_bytes(response: Response) -> Vec<u8> {
+        axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap().to_vec()
+    }
+
+    #[tokio::test]
+    async fn test_stream_object_full_download() {
+        let object = ObjectBody {
+            body: aws_sdk_s3::primitives::ByteStream::from_static(b"day,temp\n1,12.5\n"),
+            content_length: 16,
+            content_type: Some("text/csv".to_string()),
+            content_range: None,
+        };
+        let etag = strong_etag(SHA);
+
+        let response = stream_object(&etag, "data/2024/temps.csv", object);
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "text/csv");
+        assert_eq!(response.headers()[header::CONTENT_LENGTH], "16");
+        assert_eq!(response.headers()[header::ETAG], etag.as_str());
+        assert_eq!(response.headers()[header::ACCEPT_RANGES], "bytes");
+        assert_eq!(
+            response.headers()[header::CONTENT_DISPOSITION],
+            "attachment; filename=\"temps.csv\"; filename*=UTF-8''temps.csv"
+        );
+        assert_eq!(body_bytes(response).await, b"day,temp\n1,12.5\n");
+    }
+
+    #[tokio::test]
+    async fn test_stream_object_ranged_download_is_partial_content() {
+        let object = ObjectBody {
+            body: aws_sdk_s3::primitives::ByteStream::from_static(b"temp"),
+            content_length: 4,
+            content_type: None,
+            content_range: Some("bytes 4-7/16".to_string()),
+        };
+
+        let response = stream_object(&strong_etag(SHA), "temps.csv", object);
+
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.headers()[header::CONTENT_RANGE], "bytes 4-7/16");
+        assert_eq!(response.headers()[header::CONTENT_LENGTH], "4");
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/octet-stream");
+        assert_eq!(body_bytes(response).await, b"temp");
+    }
+
+    #[test]
+    fn test_attachment_disposition_escapes_names() {
+        assert_eq!(
+            attachment_disposition("reports/q1 \"final\".pdf"),
+            "attachment; filename=\"q1 _final_.pdf\"; filename*=UTF-8''q1%20%22final%22.pdf"
+        );
+        assert_eq!(
+            attachment_disposition("données.csv"),
+            "attachment; filename=\"donn_es.csv\"; filename*=UTF-8''donn%C3%A9es.csv"
+        );
+        assert!(attachment_disposition("dir/").contains("filename=\"download\""));
+    }
+}
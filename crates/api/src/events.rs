@@ -0,0 +1,381 @@
+//! Live event stream.
+//!
+//! The commit handler, background jobs, the webhook worker and quota checks
+//! publish to one in-process broadcast channel. `GET /v1/events` relays it
+//! as Server-Sent Events, limited to repositories the caller can read and
+//! optionally to the repos named in `?repo=a,b`. Events that belong to no
+//! repository only reach admins. A client that falls too far behind gets a
+//! `lagged` event with the number it missed and should re-read what it shows.
+
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    response::sse::{Event as SseEvent, KeepAlive, Sse},
+    routing::get,
+    Router,
+};
+use blacklake_core::jobs::{BlackLakeJob, JobContext, JobError, JobResponse};
+use blacklake_core::{Commit, Permission};
+use chrono::{DateTime, Utc};
+use futures_util::stream::{self, Stream, StreamExt};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
+use std::future::Future;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use crate::health::process_job_with_metrics;
+use crate::auth::is_admin;
+use crate::{ApiError, ApiResult, AppState};
+
+const DEFAULT_BUFFER_SIZE: usize = 1024;
+
+pub fn create_event_routes() -> Router<AppState> {
+    Router::new().route("/v1/events", get(stream_events))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum EventKind {
+    #[serde(rename = "commit.created")]
+    CommitCreated,
+    #[serde(rename = "webhook.delivered")]
+    WebhookDelivered,
+    #[serde(rename = "job.completed")]
+    JobCompleted,
+    #[serde(rename = "quota.warning")]
+    QuotaWarning,
+}
+
+impl EventKind {
+    /// The SSE `event:` name
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventKind::CommitCreated => "commit.created",
+            EventKind::WebhookDelivered => "webhook.delivered",
+            EventKind::JobCompleted => "job.completed",
+            EventKind::QuotaWarning => "quota.warning",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Event {
+    pub id: Uuid,
+    pub kind: EventKind,
+    /// None for events that belong to no repository
+    pub repo_id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repo: Option<String>,
+    pub at: DateTime<Utc>,
+    pub data: Value,
+}
+
+impl Event {
+    pub fn for_repo(kind: EventKind, repo_id: Uuid, repo: Option<&str>, data: Value) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            kind,
+            repo_id: Some(repo_id),
+            repo: repo.map(str::to_string),
+            at: Utc::now(),
+            data,
+        }
+    }
+
+    pub fn global(kind: EventKind, data: Value) -> Self {
+        Self { id: Uuid::new_v4(), kind, repo_id: None, repo: None, at: Utc::now(), data }
+    }
+}
+
+/// `commit.created` for a commit that just landed on `ref_name`
+pub fn commit_created(repo: &str, ref_name: &str, commit: &Commit, changes: usize) -> Event {
+    Event::for_repo(
+        EventKind::CommitCreated,
+        commit.repo_id.0,
+        Some(repo),
+        json!({
+            "commit_id": commit.id,
+            "ref": ref_name,
+            "parent_id": commit.parent_id,
+            "author": commit.author,
+            "message": commit.message,
+            "changes": changes,
+        }),
+    )
+}
+
+/// Broadcast channel events are published to; cheap to clone
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<Arc<Event>>,
+}
+
+impl EventBus {
+    /// A bus that keeps up to `capacity` events for slow subscribers
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity.max(1));
+        Self { sender }
+    }
+
+    /// Buffer size from `EVENT_BUFFER_SIZE`, default 1024
+    pub fn from_env() -> Self {
+        let capacity = std::env::var("EVENT_BUFFER_SIZE")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_BUFFER_SIZE);
+        Self::new(capacity)
+    }
+
+    /// Send `event` to current subscribers; with none it is dropped
+    pub fn publish(&self, event: Event) {
+        let _ = self.sender.send(Arc::new(event));
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<Event>> {
+        self.sender.subscribe()
+    }
+}
+
+/// What a subscriber is sent
+#[derive(Debug, Clone)]
+pub enum Delivery {
+    Event(Arc<Event>),
+    /// This many events were dropped because the subscriber fell behind
+    Lagged(u64),
+}
+
+/// Which events a subscriber may see
+pub struct Visibility<F> {
+    /// Only these repositories, when set
+    pub repos: Option<HashSet<Uuid>>,
+    /// Whether events outside any repository are shown
+    pub admin: bool,
+    /// Read permission on a repository; asked once per repository
+    pub can_read: F,
+}
+
+/// The events from `receiver` that `visibility` allows, ending when the bus goes away
+pub fn visible_events<F, Fut>(
+    receiver: broadcast::Receiver<Arc<Event>>,
+    visibility: Visibility<F>,
+) -> impl Stream<Item = Delivery>
+where
+    F: Fn(Uuid) -> Fut,
+    Fut: Future<Output = bool>,
+{
+    let readable: HashMap<Uuid, bool> = HashMap::new();
+    stream::unfold((receiver, visibility, readable), |(mut receiver, visibility, mut readable)| async move {
+        loop {
+            let event = match receiver.recv().await {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    return Some((Delivery::Lagged(missed), (receiver, visibility, readable)));
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            };
+
+            let visible = match event.repo_id {
+                None => visibility.admin && visibility.repos.is_none(),
+                Some(repo_id) if visibility.repos.as_ref().is_some_and(|repos| !repos.contains(&repo_id)) => false,
+                Some(repo_id) => match readable.get(&repo_id) {
+                    Some(allowed) => *allowed,
+                    None => {
+                        let allowed = (visibility.can_read)(repo_id).await;
+                        readable.insert(repo_id, allowed);
+                        allowed
+                    }
+                },
+            };
+            if visible {
+                return Some((Delivery::Event(event), (receiver, visibility, readable)));
+            }
+        }
+    })
+}
+
+/// Stream events as SSE. `?repo=a,b` limits it to those repositories, which
+/// the caller must be able to read.
+async fn stream_events(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> ApiResult<Sse<impl Stream<Item = Result<SseEvent, Infallible>>>> {
+    let auth = crate::extract_auth(&state.auth_layer, &headers).await?;
+    let admin = is_admin(&auth);
+
+    let repos = match params.get("repo") {
+        Some(names) => {
+            let mut repos = HashSet::new();
+            for name in names.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+                let repo_id = state.index.get_repo_by_name(name).await?.id.0;
+                crate::require_permission(&state, repo_id, &auth, Permission::Read).await?;
+                repos.insert(repo_id);
+            }
+            if repos.is_empty() {
+                return Err(ApiError::InvalidRequest("repo must name at least one repository".to_string()));
+            }
+            Some(repos)
+        }
+        None => None,
+    };
+
+    let receiver = state.events.subscribe();
+    let can_read = move |repo_id: Uuid| {
+        let state = state.clone();
+        let auth = auth.clone();
+        async move {
+            crate::has_permission(&state, repo_id, &auth, Permission::Read)
+                .await
+                .unwrap_or(false)
+        }
+    };
+    let stream = visible_events(receiver, Visibility { repos, admin, can_read }).map(|delivery| {
+        Ok(match delivery {
+            Delivery::Event(event) => SseEvent::default()
+                .id(event.id.to_string())
+                .event(event.kind.as_str())
+                .json_data(&*event)
+                .unwrap_or_else(|_| SseEvent::default().comment("unserializable event")),
+            Delivery::Lagged(missed) => SseEvent::default().event("lagged").data(missed.to_string()),
+        })
+    });
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+/// Run `job` for repository `repo` and publish `job.completed` with its outcome
+pub async fn run_job<J: BlackLakeJob>(
+    events: &EventBus,
+    repo: Option<(Uuid, &str)>,
+    job: &J,
+    ctx: &JobContext,
+) -> Result<JobResponse, JobError> {
+    let result = process_job_with_metrics(job, ctx).await;
+
+    let error = match &result {
+        Ok(JobResponse::Success) => None,
+        Ok(JobResponse::Failure(reason)) => Some(reason.clone()),
+        Err(e) => Some(e.to_string()),
+    };
+    let data = json!({
+        "job_id": ctx.job_id,
+        "job_type": job.job_type(),
+        "status": if error.is_none() { "succeeded" } else { "failed" },
+        "error": error,
+    });
+    events.publish(match repo {
+        Some((repo_id, name)) => Event::for_repo(EventKind::JobCompleted, repo_id, Some(name), data),
+        None => Event::global(EventKind::JobCompleted, data),
+    });
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use blacklake_core::{Change, ChangeOp};
+    use blacklake_index::{test_support::test_index, CommitWrite, IndexClient};
+    use std::time::Duration;
+
+    fn quota_warning(repo_id: Uuid) -> Event {
+        Event::for_repo(EventKind::QuotaWarning, repo_id, None, json!({"current_bytes": 900}))
+    }
+
+    async fn next(stream: &mut (impl Stream<Item = Delivery> + Unpin)) -> Delivery {
+        tokio::time::timeout(Duration::from_secs(5), stream.next())
+            .await
+            .expect("no event within 5s")
+            .expect("stream ended")
+    }
+
+    #[tokio::test]
+    async fn test_subscriber_only_sees_repos_it_can_read() {
+        let bus = EventBus::new(16);
+        let (readable, hidden) = (Uuid::new_v4(), Uuid::new_v4());
+        let visibility = Visibility { repos: None, admin: false, can_read: move |repo_id| async move { repo_id == readable } };
+        let mut stream = Box::pin(visible_events(bus.subscribe(), visibility));
+
+        bus.publish(quota_warning(hidden));
+        bus.publish(Event::global(EventKind::JobCompleted, json!({})));
+        bus.publish(quota_warning(readable));
+
+        let Delivery::Event(event) = next(&mut stream).await else { panic!("expected an event") };
+        assert_eq!(event.repo_id, Some(readable));
+        assert_eq!(event.kind, EventKind::QuotaWarning);
+    }
+
+    #[tokio::test]
+    async fn test_repo_filter_and_lag_notice() {
+        let bus = EventBus::new(2);
+        let (wanted, other) = (Uuid::new_v4(), Uuid::new_v4());
+        let visibility = Visibility {
+            repos: Some(HashSet::from([wanted])),
+            admin: true,
+            can_read: |_| async { true },
+        };
+        let mut stream = Box::pin(visible_events(bus.subscribe(), visibility));
+
+        // Three events into a buffer of two: the oldest is dropped
+        bus.publish(quota_warning(wanted));
+        bus.publish(quota_warning(other));
+        bus.publish(quota_warning(wanted));
+
+        assert!(matches!(next(&mut stream).await, Delivery::Lagged(1)));
+        let Delivery::Event(event) = next(&mut stream).await else { panic!("expected an event") };
+        assert_eq!(event.repo_id, Some(wanted));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_subscriber_receives_the_commit_event() {
+        let index = test_index().await;
+        let repo_id = Uuid::new_v4();
+        let repo = format!("events-{}", repo_id);
+        sqlx::query("INSERT INTO repo (id, name, created_by) VALUES ($1, $2, 'test')")
+            .bind(repo_id)
+            .bind(&repo)
+            .execute(index.pool())
+            .await
+            .unwrap();
+
+        let bus = EventBus::new(16);
+        let visibility = Visibility { repos: Some(HashSet::from([repo_id])), admin: false, can_read: |_| async { true } };
+        let mut stream = Box::pin(visible_events(bus.subscribe(), visibility));
+
+        let sha256 = Uuid::new_v4().simple().to_string();
+        index.upsert_object(&sha256, 3, None, &sha256).await.unwrap();
+        let changes = vec![Change {
+            op: ChangeOp::Add,
+            path: "data/a.csv".to_string(),
+            sha256: Some(sha256.clone()),
+            meta: json!({"name": "a"}),
+        }];
+        let (commit, _) = index
+            .commit_atomic(&CommitWrite {
+                repo_id,
+                ref_name: "main",
+                author: "tester",
+                message: Some("first"),
+                expected_parent: None,
+                changes: &changes,
+                schema: None,
+                merge_parent: None,
+            })
+            .await
+            .unwrap();
+        bus.publish(commit_created(&repo, "main", &commit, changes.len()));
+
+        let Delivery::Event(event) = next(&mut stream).await else { panic!("expected an event") };
+        assert_eq!(event.kind, EventKind::CommitCreated);
+        assert_eq!(event.repo.as_deref(), Some(repo.as_str()));
+        assert_eq!(event.data["commit_id"], json!(commit.id));
+        assert_eq!(event.data["ref"], "main");
+
+        index.delete_repo(repo_id).await.unwrap();
+        index.delete_orphaned_objects(&[sha256]).await.unwrap();
+    }
+}
@@ -142,7 +142,7 @@ impl ExportProcessor {
         let mut job = self.index.get_export_job(job_id).await?;
 
         // Update job status to processing
-        job.status = ExportJobStatus::Processing;
+        job.status = ExportJobStatus::Running;
         job.started_at = Some(
             SystemTime::now()
                 .duration_since(UNIX_EPOCH)
@@ -418,13 +418,8 @@ impl ExportProcessor {
 
     /// Cleanup expired export jobs
     pub async fn cleanup_expired_jobs(&self) -> Result<(), ApiError> {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-
         // Get expired jobs
-        let expired_jobs = self.index.get_expired_export_jobs(now).await?;
+        let expired_jobs = self.index.get_expired_export_jobs(chrono::Utc::now()).await?;
 
         for job in expired_jobs {
             // Delete from S3
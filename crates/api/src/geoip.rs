@@ -0,0 +1,169 @@
+//! GeoIP resolution for geographic signed URL constraints.
+//!
+//! Backed by a MaxMind GeoLite2/GeoIP2 City database whose path comes from
+//! `GEOIP_DB_PATH`. Addresses that cannot be placed (private, loopback, or
+//! simply absent from the database) resolve to `None`, and callers decide
+//! what "unknown" means with [`UnknownLocationPolicy`].
+
+use maxminddb::{geoip2, Reader};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::path::Path;
+use tokio::sync::RwLock;
+
+use crate::signed_url_constraints::GeographicInfo;
+
+/// What to do with a request whose location cannot be resolved
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnknownLocationPolicy {
+    Allow,
+    Deny,
+}
+
+impl UnknownLocationPolicy {
+    /// Read `GEOIP_UNKNOWN_POLICY` (`allow` or `deny`), defaulting to allow
+    pub fn from_env() -> Self {
+        match std::env::var("GEOIP_UNKNOWN_POLICY").as_deref() {
+            Ok("deny") => Self::Deny,
+            _ => Self::Allow,
+        }
+    }
+}
+
+pub struct GeoIpResolver {
+    reader: Option<Reader<Vec<u8>>>,
+    unknown_policy: UnknownLocationPolicy,
+    cache: RwLock<HashMap<IpAddr, Option<GeographicInfo>>>,
+}
+
+impl GeoIpResolver {
+    /// Open a City database
+    pub fn open(path: impl AsRef<Path>, unknown_policy: UnknownLocationPolicy) -> Result<Self, maxminddb::MaxMindDBError> {
+        Ok(Self {
+            reader: Some(Reader::open_readfile(path)?),
+            unknown_policy,
+            cache: RwLock::new(HashMap::new()),
+        })
+    }
+
+    /// Resolver without a database; every address is unknown
+    pub fn disabled(unknown_policy: UnknownLocationPolicy) -> Self {
+        Self {
+            reader: None,
+            unknown_policy,
+            cache: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Build from `GEOIP_DB_PATH` and `GEOIP_UNKNOWN_POLICY`.
+    ///
+    /// A missing or unreadable database disables lookups rather than failing startup.
+    pub fn from_env() -> Self {
+        let unknown_policy = UnknownLocationPolicy::from_env();
+        match std::env::var("GEOIP_DB_PATH") {
+            Ok(path) => Self::open(&path, unknown_policy).unwrap_or_else(|e| {
+                tracing::warn!("Failed to open GeoIP database {}: {}", path, e);
+                Self::disabled(unknown_policy)
+            }),
+            Err(_) => Self::disabled(unknown_policy),
+        }
+    }
+
+    pub fn unknown_policy(&self) -> UnknownLocationPolicy {
+        self.unknown_policy
+    }
+
+    /// Resolve an address to its location, caching the answer
+    pub async fn lookup(&self, ip: &str) -> Option<GeographicInfo> {
+        let ip: IpAddr = ip.trim().parse().ok()?;
+        if !is_routable(&ip) {
+            return None;
+        }
+
+        if let Some(cached) = self.cache.read().await.get(&ip) {
+            return cached.clone();
+        }
+
+        let info = self.reader.as_ref().and_then(|reader| {
+            let city: geoip2::City = reader.lookup(ip).ok()?;
+            let english = |names: Option<std::collections::BTreeMap<&str, &str>>| {
+                names.and_then(|names| names.get("en").map(|name| name.to_string()))
+            };
+            let subdivision = city.subdivisions.and_then(|subdivisions| subdivisions.into_iter().next());
+            let location = city.location;
+
+            Some(GeographicInfo {
+                country: city.country?.iso_code?.to_string(),
+                region: subdivision.and_then(|s| s.iso_code).unwrap_or_default().to_string(),
+                city: english(city.city.and_then(|c| c.names)).unwrap_or_default(),
+                isp: String::new(),
+                latitude: location.as_ref().and_then(|l| l.latitude).unwrap_or_default(),
+                longitude: location.as_ref().and_then(|l| l.longitude).unwrap_or_default(),
+            })
+        });
+
+        self.cache.write().await.insert(ip, info.clone());
+        info
+    }
+}
+
+/// Whether an address could appear in a GeoIP database
+fn is_routable(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => !(v4.is_private() || v4.is_loopback() || v4.is_link_local() || v4.is_unspecified() || v4.is_broadcast()),
+        IpAddr::V6(v6) => {
+            let unique_local = (v6.segments()[0] & 0xfe00) == 0xfc00;
+            let link_local = (v6.segments()[0] & 0xffc0) == 0xfe80;
+            !(v6.is_loopback() || v6.is_unspecified() || unique_local || link_local)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_DB: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/GeoIP2-City-Test.mmdb");
+
+    fn resolver() -> GeoIpResolver {
+        GeoIpResolver::open(TEST_DB, UnknownLocationPolicy::Allow).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_lookup_resolves_country_and_region() {
+        let resolver = resolver();
+
+        let london = resolver.lookup("81.2.69.142").await.unwrap();
+        assert_eq!(london.country, "GB");
+        assert_eq!(london.region, "ENG");
+        assert_eq!(london.city, "London");
+
+        assert_eq!(resolver.lookup("216.160.83.56").await.unwrap().region, "WA");
+        assert_eq!(resolver.lookup("2001:db8::1").await.unwrap().country, "US");
+    }
+
+    #[tokio::test]
+    async fn test_private_loopback_and_absent_addresses_are_unknown() {
+        let resolver = resolver();
+
+        for ip in ["10.0.0.1", "192.168.1.1", "127.0.0.1", "::1", "fd00::1", "8.8.8.8", "not-an-ip"] {
+            assert!(resolver.lookup(ip).await.is_none(), "{} should be unknown", ip);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_lookups_are_cached() {
+        let resolver = resolver();
+        resolver.lookup("89.160.20.112").await;
+
+        let cache = resolver.cache.read().await;
+        assert_eq!(cache.get(&"89.160.20.112".parse().unwrap()).unwrap().as_ref().unwrap().country, "SE");
+    }
+
+    #[tokio::test]
+    async fn test_disabled_resolver_knows_nothing() {
+        let resolver = GeoIpResolver::disabled(UnknownLocationPolicy::Deny);
+        assert!(resolver.lookup("81.2.69.142").await.is_none());
+        assert_eq!(resolver.unknown_policy(), UnknownLocationPolicy::Deny);
+    }
+}
@@ -13,7 +13,7 @@ use blacklake_core::{
     Repository, Uuid,
 };
 use blacklake_core::governance::{ProtectedRef, RepoQuota, RepoUsage, RepoRetention, Webhook, WebhookDelivery, 
-    ExportJob, ExportManifest, ExportJobStatus, CheckResult, CheckStatus, QuotaStatus,
+    ExportFormat, ExportJob, ExportManifest, ExportJobStatus, CheckResult, CheckStatus, QuotaStatus,
     WebhookEvent, RetentionPolicy, PolicyEvaluation};
 use crate::{ApiError, ApiResponse};
 use blacklake_index::IndexClient;
@@ -457,13 +457,19 @@ async fn get_webhook_deliveries(
 }
 
 /// Create an export job
+///
+/// `?format=zip` packages the export as a zip archive instead of `tar.gz`.
 async fn create_export(
     State(state): State<AppState>,
     Path(repo_name): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
     headers: HeaderMap,
-    Json(payload): Json<CreateExportRequest>,
+    Json(mut payload): Json<CreateExportRequest>,
 ) -> ApiResult<Json<ExportJobResponse>> {
     let auth = extract_auth(&headers).await?;
+    if let Some(format) = params.get("format") {
+        payload.manifest.format = format.parse::<ExportFormat>().map_err(ApiError::InvalidRequest)?;
+    }
     
     // Get repository
     let repo = state.index.get_repo(&repo_name).await?
@@ -479,6 +485,7 @@ async fn create_export(
         s3_key: None,
         download_url: None,
         error_message: None,
+        expires_at: None,
     };
 
     state.index.create_export_job(&job).await?;
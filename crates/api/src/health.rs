@@ -1,33 +1,59 @@
 use axum::{
-    extract::State,
+    extract::{MatchedPath, Request, State},
     http::StatusCode,
-    response::Json,
+    middleware::Next,
+    response::{Json, Response},
 };
+use async_trait::async_trait;
+use blacklake_core::jobs::{BlackLakeJob, JobContext, JobError, JobResponse};
+use blacklake_core::search::SolrClient;
 use blacklake_index::IndexClient;
 use blacklake_storage::StorageClient;
-use prometheus::{Encoder, TextEncoder, Registry, Counter, Histogram, Gauge};
+use prometheus::{Encoder, TextEncoder, Registry, Counter, CounterVec, Histogram, HistogramOpts, HistogramVec, Gauge, Opts};
 use serde_json::json;
+use std::collections::HashSet;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::time::timeout;
 use tracing::{error, info};
 
+/// Time a single readiness probe may take before it counts as failed
+pub const DEFAULT_PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Dependencies `/ready` tolerates being down unless configured otherwise
+pub const DEFAULT_OPTIONAL_DEPENDENCIES: &str = "solr,redis";
+
 #[derive(Clone)]
 pub struct HealthState {
-    pub index: IndexClient,
-    pub storage: StorageClient,
+    pub dependencies: Arc<Vec<Dependency>>,
+    pub probe_timeout: Duration,
     pub metrics: Arc<Registry>,
 }
 
+/// A downstream service `/ready` checks
+#[async_trait]
+pub trait DependencyProbe: Send + Sync {
+    async fn probe(&self) -> Result<(), String>;
+}
+
+/// A probed dependency and whether readiness hinges on it
+#[derive(Clone)]
+pub struct Dependency {
+    pub name: &'static str,
+    pub required: bool,
+    pub probe: Arc<dyn DependencyProbe>,
+}
+
 // Prometheus metrics
 lazy_static::lazy_static! {
-    pub static ref HTTP_REQUESTS_TOTAL: Counter = Counter::new(
-        "http_requests_total",
-        "Total number of HTTP requests"
+    pub static ref HTTP_REQUESTS_TOTAL: CounterVec = CounterVec::new(
+        Opts::new("http_requests_total", "Total number of HTTP requests"),
+        &["method", "route", "status"]
     ).unwrap();
     
-    pub static ref HTTP_REQUEST_DURATION: Histogram = Histogram::new(
-        "http_request_duration_seconds",
-        "HTTP request duration in seconds"
+    pub static ref HTTP_REQUEST_DURATION: HistogramVec = HistogramVec::new(
+        HistogramOpts::new("http_request_duration_seconds", "HTTP request duration in seconds"),
+        &["method", "route", "status"]
     ).unwrap();
     
     pub static ref ACTIVE_CONNECTIONS: Gauge = Gauge::new(
@@ -40,9 +66,20 @@ lazy_static::lazy_static! {
         "Number of active database connections"
     ).unwrap();
     
-    pub static ref S3_OPERATIONS_TOTAL: Counter = Counter::new(
-        "s3_operations_total",
-        "Total number of S3 operations"
+    // Repository metrics
+    pub static ref COMMITS_TOTAL: CounterVec = CounterVec::new(
+        Opts::new("commits_total", "Total number of commits created"),
+        &["repo"]
+    ).unwrap();
+    
+    pub static ref BYTES_UPLOADED_TOTAL: CounterVec = CounterVec::new(
+        Opts::new("bytes_uploaded_total", "Bytes of uploads verified in storage"),
+        &["repo"]
+    ).unwrap();
+    
+    pub static ref BYTES_DOWNLOADED_TOTAL: CounterVec = CounterVec::new(
+        Opts::new("bytes_downloaded_total", "Bytes of blobs streamed or handed out as download URLs"),
+        &["repo"]
     ).unwrap();
     
     // Search metrics
@@ -108,19 +145,19 @@ lazy_static::lazy_static! {
         "Total number of jobs enqueued"
     ).unwrap();
     
-    pub static ref JOB_PROCESSED_TOTAL: Counter = Counter::new(
-        "job_processed_total",
-        "Total number of jobs processed"
+    pub static ref JOB_PROCESSED_TOTAL: CounterVec = CounterVec::new(
+        Opts::new("job_processed_total", "Total number of jobs processed"),
+        &["job_type"]
     ).unwrap();
     
-    pub static ref JOB_FAILED_TOTAL: Counter = Counter::new(
-        "job_failed_total",
-        "Total number of failed jobs"
+    pub static ref JOB_FAILED_TOTAL: CounterVec = CounterVec::new(
+        Opts::new("job_failed_total", "Total number of failed jobs"),
+        &["job_type"]
     ).unwrap();
     
-    pub static ref JOB_PROCESSING_DURATION: Histogram = Histogram::new(
-        "job_processing_duration_seconds",
-        "Job processing duration in seconds"
+    pub static ref JOB_PROCESSING_DURATION: HistogramVec = HistogramVec::new(
+        HistogramOpts::new("job_processing_duration_seconds", "Job processing duration in seconds"),
+        &["job_type"]
     ).unwrap();
     
     pub static ref QUEUE_SIZE: Gauge = Gauge::new(
@@ -145,83 +182,61 @@ pub async fn readiness_check(
     State(state): State<HealthState>,
 ) -> (StatusCode, Json<serde_json::Value>) {
     info!("Readiness check requested");
-    
-    let mut checks = json!({
-        "status": "ready",
-        "timestamp": chrono::Utc::now().to_rfc3339(),
-        "version": env!("CARGO_PKG_VERSION"),
-        "checks": {}
-    });
 
-    // Check database connectivity
-    let db_check = timeout(
-        std::time::Duration::from_secs(5),
-        check_database(&state.index)
-    ).await;
-
-    match db_check {
-        Ok(Ok(_)) => {
-            checks["checks"]["database"] = json!({
-                "status": "healthy",
-                "message": "Database connection successful"
-            });
-        }
-        Ok(Err(e)) => {
-            error!("Database health check failed: {}", e);
-            checks["checks"]["database"] = json!({
-                "status": "unhealthy",
-                "message": format!("Database error: {}", e)
-            });
-            checks["status"] = json!("not_ready");
-        }
-        Err(_) => {
-            error!("Database health check timeout");
-            checks["checks"]["database"] = json!({
-                "status": "unhealthy",
-                "message": "Database connection timeout"
-            });
-            checks["status"] = json!("not_ready");
-        }
-    }
+    let (status, body) = probe_dependencies(&state.dependencies, state.probe_timeout).await;
+    (status, Json(body))
+}
 
-    // Check S3 connectivity
-    let s3_check = timeout(
-        std::time::Duration::from_secs(5),
-        check_storage(&state.storage)
-    ).await;
-
-    match s3_check {
-        Ok(Ok(_)) => {
-            checks["checks"]["storage"] = json!({
-                "status": "healthy",
-                "message": "S3 connection successful"
-            });
-        }
-        Ok(Err(e)) => {
-            error!("Storage health check failed: {}", e);
-            checks["checks"]["storage"] = json!({
-                "status": "unhealthy",
-                "message": format!("Storage error: {}", e)
-            });
-            checks["status"] = json!("not_ready");
-        }
-        Err(_) => {
-            error!("Storage health check timeout");
-            checks["checks"]["storage"] = json!({
-                "status": "unhealthy",
-                "message": "Storage connection timeout"
-            });
-            checks["status"] = json!("not_ready");
+/// Probe every dependency concurrently, each bounded by `probe_timeout`.
+///
+/// `200` when every required dependency answers; `503` otherwise. The body
+/// reports each dependency either way.
+pub async fn probe_dependencies(
+    dependencies: &[Dependency],
+    probe_timeout: Duration,
+) -> (StatusCode, serde_json::Value) {
+    let handles: Vec<_> = dependencies
+        .iter()
+        .map(|dependency| {
+            let probe = dependency.probe.clone();
+            tokio::spawn(async move {
+                let started = Instant::now();
+                let result = match timeout(probe_timeout, probe.probe()).await {
+                    Ok(result) => result,
+                    Err(_) => Err(format!("No response within {}ms", probe_timeout.as_millis())),
+                };
+                (result, started.elapsed())
+            })
+        })
+        .collect();
+
+    let mut ready = true;
+    let mut checks = serde_json::Map::new();
+    for (dependency, handle) in dependencies.iter().zip(handles) {
+        let (result, elapsed) = handle
+            .await
+            .unwrap_or_else(|e| (Err(format!("Probe panicked: {}", e)), Duration::ZERO));
+        let mut check = json!({
+            "status": if result.is_ok() { "healthy" } else { "unhealthy" },
+            "required": dependency.required,
+            "latency_ms": elapsed.as_millis() as u64,
+        });
+        if let Err(message) = result {
+            error!("Readiness probe for {} failed: {}", dependency.name, message);
+            check["message"] = json!(message);
+            ready &= !dependency.required;
         }
+        checks.insert(dependency.name.to_string(), check);
     }
 
-    let status = if checks["status"] == "ready" {
-        StatusCode::OK
-    } else {
-        StatusCode::SERVICE_UNAVAILABLE
-    };
-
-    (status, Json(checks))
+    let body = json!({
+        "status": if ready { "ready" } else { "not_ready" },
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "version": env!("CARGO_PKG_VERSION"),
+        "checks": checks,
+    });
+    let status = if ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (status, body)
 }
 
 pub async fn metrics(
@@ -239,22 +254,138 @@ pub async fn metrics(
     }
 }
 
-async fn check_database(index: &IndexClient) -> Result<(), String> {
-    // Simple query to check database connectivity
-    match index.list_repos().await {
-        Ok(_) => Ok(()),
-        Err(e) => Err(format!("Database query failed: {}", e)),
+/// Record the duration and status of every request, labelled by route.
+///
+/// Requests that matched no route share the `unmatched` label so stray paths
+/// can't blow up the series count.
+pub async fn http_metrics_middleware(request: Request, next: Next) -> Response {
+    let started = Instant::now();
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+
+    let response = next.run(request).await;
+
+    let status = response.status().as_u16().to_string();
+    let labels = [method.as_str(), route.as_str(), status.as_str()];
+    HTTP_REQUESTS_TOTAL.with_label_values(&labels).inc();
+    HTTP_REQUEST_DURATION
+        .with_label_values(&labels)
+        .observe(started.elapsed().as_secs_f64());
+    response
+}
+
+/// Process a job, recording its duration and outcome under its job type
+pub async fn process_job_with_metrics<J: BlackLakeJob>(job: &J, ctx: &JobContext) -> Result<JobResponse, JobError> {
+    let job_type = job.job_type();
+    let started = Instant::now();
+    let result = job.process(ctx).await;
+    JOB_PROCESSING_DURATION
+        .with_label_values(&[job_type])
+        .observe(started.elapsed().as_secs_f64());
+    JOB_PROCESSED_TOTAL.with_label_values(&[job_type]).inc();
+    if !matches!(result, Ok(JobResponse::Success)) {
+        JOB_FAILED_TOTAL.with_label_values(&[job_type]).inc();
+    }
+    result
+}
+
+/// `SELECT 1` against Postgres
+pub struct PostgresProbe(pub IndexClient);
+
+#[async_trait]
+impl DependencyProbe for PostgresProbe {
+    async fn probe(&self) -> Result<(), String> {
+        self.0.ping().await.map_err(|e| format!("Database query failed: {}", e))
     }
 }
 
-async fn check_storage(storage: &StorageClient) -> Result<(), String> {
-    // Check if bucket exists and is accessible
-    match storage.ensure_bucket_exists().await {
-        Ok(_) => Ok(()),
-        Err(e) => Err(format!("Storage check failed: {}", e)),
+/// `HeadBucket` on the configured bucket
+pub struct S3Probe(pub StorageClient);
+
+#[async_trait]
+impl DependencyProbe for S3Probe {
+    async fn probe(&self) -> Result<(), String> {
+        self.0.head_bucket().await.map_err(|e| format!("Bucket check failed: {}", e))
     }
 }
 
+/// The Solr collection's ping handler
+pub struct SolrProbe(pub SolrClient);
+
+#[async_trait]
+impl DependencyProbe for SolrProbe {
+    async fn probe(&self) -> Result<(), String> {
+        self.0.ping().await.map_err(|e| format!("Solr ping failed: {}", e))
+    }
+}
+
+/// Redis `PING` over a fresh connection
+pub struct RedisProbe(pub redis::Client);
+
+#[async_trait]
+impl DependencyProbe for RedisProbe {
+    async fn probe(&self) -> Result<(), String> {
+        let mut connection = self
+            .0
+            .get_multiplexed_async_connection()
+            .await
+            .map_err(|e| format!("Redis connection failed: {}", e))?;
+        let _pong: String = redis::cmd("PING")
+            .query_async(&mut connection)
+            .await
+            .map_err(|e| format!("Redis ping failed: {}", e))?;
+        Ok(())
+    }
+}
+
+/// Names in a comma-separated `READINESS_OPTIONAL_DEPENDENCIES` value
+fn parse_optional_dependencies(raw: &str) -> HashSet<String> {
+    raw.split(',')
+        .map(|name| name.trim().to_ascii_lowercase())
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// The dependencies `/ready` probes.
+///
+/// Each is required unless named in `READINESS_OPTIONAL_DEPENDENCIES`
+/// (default `solr,redis`).
+pub fn readiness_dependencies(
+    index: &IndexClient,
+    storage: &StorageClient,
+    solr: &SolrClient,
+    redis_url: &str,
+) -> Result<Vec<Dependency>, redis::RedisError> {
+    let optional = parse_optional_dependencies(
+        &std::env::var("READINESS_OPTIONAL_DEPENDENCIES").unwrap_or_else(|_| DEFAULT_OPTIONAL_DEPENDENCIES.to_string()),
+    );
+    let dependency = |name: &'static str, probe: Arc<dyn DependencyProbe>| Dependency {
+        name,
+        required: !optional.contains(name),
+        probe,
+    };
+
+    Ok(vec![
+        dependency("postgres", Arc::new(PostgresProbe(index.clone()))),
+        dependency("s3", Arc::new(S3Probe(storage.clone()))),
+        dependency("solr", Arc::new(SolrProbe(solr.clone()))),
+        dependency("redis", Arc::new(RedisProbe(redis::Client::open(redis_url)?))),
+    ])
+}
+
+/// Per-probe time limit from `READINESS_PROBE_TIMEOUT_MS`
+pub fn readiness_probe_timeout() -> Duration {
+    std::env::var("READINESS_PROBE_TIMEOUT_MS")
+        .ok()
+        .and_then(|ms| ms.parse().ok())
+        .map(Duration::from_millis)
+        .unwrap_or(DEFAULT_PROBE_TIMEOUT)
+}
+
 pub fn create_metrics_registry() -> Registry {
     let registry = Registry::new();
     
@@ -263,7 +394,13 @@ pub fn create_metrics_registry() -> Registry {
     registry.register(Box::new(HTTP_REQUEST_DURATION.clone())).unwrap();
     registry.register(Box::new(ACTIVE_CONNECTIONS.clone())).unwrap();
     registry.register(Box::new(DATABASE_CONNECTIONS.clone())).unwrap();
-    registry.register(Box::new(S3_OPERATIONS_TOTAL.clone())).unwrap();
+    
+    // Register repository and storage metrics
+    registry.register(Box::new(COMMITS_TOTAL.clone())).unwrap();
+    registry.register(Box::new(BYTES_UPLOADED_TOTAL.clone())).unwrap();
+    registry.register(Box::new(BYTES_DOWNLOADED_TOTAL.clone())).unwrap();
+    registry.register(Box::new(blacklake_storage::metrics::S3_OPERATIONS_TOTAL.clone())).unwrap();
+    registry.register(Box::new(blacklake_storage::metrics::S3_OPERATION_DURATION.clone())).unwrap();
     
     // Register search metrics
     registry.register(Box::new(SEARCH_REQUESTS_TOTAL.clone())).unwrap();
@@ -289,3 +426,133 @@ pub fn create_metrics_registry() -> Registry {
     
     registry
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    enum Stub {
+        Up,
+        Down(&'static str),
+        Hung,
+    }
+
+    #[async_trait]
+    impl DependencyProbe for Stub {
+        async fn probe(&self) -> Result<(), String> {
+            match self {
+                Stub::Up => Ok(()),
+                Stub::Down(message) => Err(message.to_string()),
+                Stub::Hung => {
+                    tokio::time::sleep(Duration::from_secs(60)).await;
+                    Ok(())
+                }
+            }
+        }
+    }
+
+    fn dependency(name: &'static str, required: bool, stub: Stub) -> Dependency {
+        Dependency { name, required, probe: Arc::new(stub) }
+    }
+
+    #[tokio::test]
+    async fn test_ready_when_all_required_dependencies_answer() {
+        let dependencies = vec![dependency("postgres", true, Stub::Up), dependency("s3", true, Stub::Up)];
+
+        let (status, body) = probe_dependencies(&dependencies, DEFAULT_PROBE_TIMEOUT).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "ready");
+        assert_eq!(body["checks"]["postgres"]["status"], "healthy");
+        assert_eq!(body["checks"]["s3"]["status"], "healthy");
+    }
+
+    #[tokio::test]
+    async fn test_failing_required_dependency_is_listed_in_503() {
+        let dependencies = vec![
+            dependency("postgres", true, Stub::Up),
+            dependency("s3", true, Stub::Down("Bucket check failed: access denied")),
+        ];
+
+        let (status, body) = probe_dependencies(&dependencies, DEFAULT_PROBE_TIMEOUT).await;
+
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["status"], "not_ready");
+        assert_eq!(body["checks"]["postgres"]["status"], "healthy");
+        assert_eq!(body["checks"]["s3"]["status"], "unhealthy");
+        assert_eq!(body["checks"]["s3"]["required"], true);
+        assert_eq!(body["checks"]["s3"]["message"], "Bucket check failed: access denied");
+    }
+
+    #[tokio::test]
+    async fn test_hung_dependency_is_cut_off_at_the_probe_timeout() {
+        let dependencies = vec![dependency("postgres", true, Stub::Hung), dependency("s3", true, Stub::Up)];
+
+        let started = Instant::now();
+        let (status, body) = probe_dependencies(&dependencies, Duration::from_millis(50)).await;
+
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["checks"]["postgres"]["message"], "No response within 50ms");
+        assert_eq!(body["checks"]["s3"]["status"], "healthy");
+    }
+
+    #[tokio::test]
+    async fn test_failing_optional_dependency_does_not_block_readiness() {
+        let dependencies = vec![
+            dependency("postgres", true, Stub::Up),
+            dependency("solr", false, Stub::Down("Solr ping failed: connection refused")),
+        ];
+
+        let (status, body) = probe_dependencies(&dependencies, DEFAULT_PROBE_TIMEOUT).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "ready");
+        assert_eq!(body["checks"]["solr"]["status"], "unhealthy");
+        assert_eq!(body["checks"]["solr"]["required"], false);
+    }
+
+    #[tokio::test]
+    async fn test_metrics_scrape_reports_request_duration_by_route() {
+        use axum::{body::Body, http::Request as HttpRequest, routing::get, Router};
+        use tower::ServiceExt;
+
+        let state = HealthState {
+            dependencies: Arc::new(Vec::new()),
+            probe_timeout: DEFAULT_PROBE_TIMEOUT,
+            metrics: Arc::new(create_metrics_registry()),
+        };
+        let app = Router::new()
+            .route("/v1/repos/:repo/stats", get(|| async { "ok" }))
+            .route("/metrics", get(metrics))
+            .layer(axum::middleware::from_fn(http_metrics_middleware))
+            .with_state(state);
+
+        let response = app
+            .clone()
+            .oneshot(HttpRequest::builder().uri("/v1/repos/climate/stats").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app
+            .oneshot(HttpRequest::builder().uri("/metrics").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let scrape = String::from_utf8(body.to_vec()).unwrap();
+
+        assert!(scrape.contains(
+            r#"http_request_duration_seconds_count{method="GET",route="/v1/repos/:repo/stats",status="200"}"#
+        ));
+        assert!(scrape.contains(r#"http_requests_total{method="GET",route="/v1/repos/:repo/stats",status="200"}"#));
+    }
+
+    #[test]
+    fn test_parse_optional_dependencies() {
+        let optional = parse_optional_dependencies(" Solr, ,redis ");
+        assert_eq!(optional, HashSet::from(["solr".to_string(), "redis".to_string()]));
+        assert!(parse_optional_dependencies("").is_empty());
+    }
+}
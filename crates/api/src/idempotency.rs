@@ -0,0 +1,338 @@
+//! `Idempotency-Key` support for commit and upload-init.
+//!
+//! The first request with a key runs normally and its response is stored
+//! against (repo, endpoint, key) for `IDEMPOTENCY_KEY_TTL_HOURS`. A retry
+//! with the same key and the same request gets that response back, marked
+//! with `Idempotent-Replayed: true`, instead of running again. Failed
+//! requests are not stored, so retrying them runs them again. A key whose
+//! request is still unfinished after `IDEMPOTENCY_LEASE_MINUTES` is taken
+//! to be from a replica that died, and a retry of the same request runs.
+
+use axum::{
+    body::Body,
+    http::{HeaderMap, HeaderName, HeaderValue, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use blacklake_core::QUOTA_WARNING_HEADER;
+use blacklake_index::{IdempotentResponse, IndexClient};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::future::Future;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::{ApiError, ApiResult};
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+pub const IDEMPOTENT_REPLAYED_HEADER: &str = "idempotent-replayed";
+
+const MAX_KEY_LENGTH: usize = 255;
+const DEFAULT_TTL_HOURS: i64 = 24;
+const DEFAULT_LEASE_MINUTES: i64 = 5;
+/// Response headers stored and replayed along with the body
+const REPLAYED_HEADERS: &[&str] = &[QUOTA_WARNING_HEADER];
+/// Commit and upload-init responses are small; anything bigger is not kept
+const MAX_STORED_BODY_BYTES: usize = 1024 * 1024;
+
+/// The request's idempotency key, if it sent one
+pub fn idempotency_key(headers: &HeaderMap) -> ApiResult<Option<String>> {
+    let Some(value) = headers.get(IDEMPOTENCY_KEY_HEADER) else {
+        return Ok(None);
+    };
+    let key = value
+        .to_str()
+        .map_err(|_| ApiError::InvalidRequest("Idempotency-Key must be visible ASCII".to_string()))?
+        .trim();
+    if key.is_empty() || key.len() > MAX_KEY_LENGTH {
+        return Err(ApiError::InvalidRequest(format!(
+            "Idempotency-Key must be 1 to {} characters",
+            MAX_KEY_LENGTH
+        )));
+    }
+    Ok(Some(key.to_string()))
+}
+
+/// How long a key's response is kept, from `IDEMPOTENCY_KEY_TTL_HOURS`
+pub fn key_ttl() -> chrono::Duration {
+    let hours = std::env::var("IDEMPOTENCY_KEY_TTL_HOURS")
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .filter(|hours| *hours > 0)
+        .unwrap_or(DEFAULT_TTL_HOURS);
+    chrono::Duration::hours(hours)
+}
+
+/// How long a running request holds its key, from `IDEMPOTENCY_LEASE_MINUTES`
+pub fn key_lease() -> chrono::Duration {
+    let minutes = std::env::var("IDEMPOTENCY_LEASE_MINUTES")
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .filter(|minutes| *minutes > 0)
+        .unwrap_or(DEFAULT_LEASE_MINUTES);
+    chrono::Duration::minutes(minutes)
+}
+
+/// Hash of who sent a request and what it asked for; a reused key must match it
+pub fn request_fingerprint(actor: &str, request: &impl Serialize) -> ApiResult<String> {
+    let body = serde_json::to_vec(request).map_err(|e| ApiError::Internal(e.to_string()))?;
+    let mut hasher = Sha256::new();
+    hasher.update(actor.as_bytes());
+    hasher.update([0]);
+    hasher.update(&body);
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// One keyed call to an endpoint of a repository
+pub struct IdempotentCall<'a> {
+    pub repo_id: Uuid,
+    pub endpoint: &'a str,
+    pub key: &'a str,
+    pub request_sha256: String,
+}
+
+/// Run `request` unless `call`'s key already has a stored response, and
+/// store the response of a successful run
+pub async fn run_once(
+    index: &IndexClient,
+    call: &IdempotentCall<'_>,
+    request: impl Future<Output = ApiResult<Response>>,
+) -> ApiResult<Response> {
+    if let Some(stored) = index
+        .claim_idempotency_key(
+            call.repo_id,
+            call.endpoint,
+            call.key,
+            &call.request_sha256,
+            key_ttl(),
+            chrono::Utc::now() - key_lease(),
+        )
+        .await?
+    {
+        return Ok(replay(stored));
+    }
+
+    let response = match request.await {
+        Ok(response) => response,
+        Err(e) => {
+            release(index, call).await;
+            return Err(e);
+        }
+    };
+
+    let (parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, MAX_STORED_BODY_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            release(index, call).await;
+            return Err(ApiError::Internal(format!("Failed to read response body: {}", e)));
+        }
+    };
+
+    match serde_json::from_slice(&bytes) {
+        Ok(body) if parts.status.is_success() => {
+            let stored = IdempotentResponse {
+                status: parts.status.as_u16(),
+                headers: replayed_headers(&parts.headers),
+                body,
+            };
+            index.complete_idempotency_key(call.repo_id, call.endpoint, call.key, &stored).await?;
+        }
+        _ => release(index, call).await,
+    }
+
+    Ok(Response::from_parts(parts, Body::from(bytes)))
+}
+
+async fn release(index: &IndexClient, call: &IdempotentCall<'_>) {
+    if let Err(e) = index.release_idempotency_key(call.repo_id, call.endpoint, call.key).await {
+        warn!("Failed to release idempotency key {}: {}", call.key, e);
+    }
+}
+
+fn replayed_headers(headers: &HeaderMap) -> Vec<(String, String)> {
+    REPLAYED_HEADERS
+        .iter()
+        .filter_map(|name| Some((name.to_string(), headers.get(*name)?.to_str().ok()?.to_string())))
+        .collect()
+}
+
+fn replay(stored: IdempotentResponse) -> Response {
+    let status = StatusCode::from_u16(stored.status).unwrap_or(StatusCode::OK);
+    let mut response = (status, [(IDEMPOTENT_REPLAYED_HEADER, "true")], Json(stored.body)).into_response();
+    for (name, value) in stored.headers {
+        match (HeaderName::try_from(name), HeaderValue::try_from(value)) {
+            (Ok(name), Ok(value)) => {
+                response.headers_mut().insert(name, value);
+            }
+            _ => warn!("Skipping unreplayable stored response header"),
+        }
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use blacklake_core::{Change, ChangeOp};
+    use blacklake_index::{test_support::test_index, CommitWrite, IndexError};
+    use serde_json::{json, Value};
+
+    #[test]
+    fn test_idempotency_key_is_optional_and_bounded() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(idempotency_key(&headers).unwrap(), None);
+
+        headers.insert(IDEMPOTENCY_KEY_HEADER, HeaderValue::from_static(" retry-1 "));
+        assert_eq!(idempotency_key(&headers).unwrap().as_deref(), Some("retry-1"));
+
+        headers.insert(IDEMPOTENCY_KEY_HEADER, HeaderValue::from_str(&"k".repeat(256)).unwrap());
+        assert!(matches!(idempotency_key(&headers), Err(ApiError::InvalidRequest(_))));
+    }
+
+    #[test]
+    fn test_fingerprint_covers_actor_and_request() {
+        let request = json!({"ref": "main", "changes": []});
+        let fingerprint = request_fingerprint("alice", &request).unwrap();
+
+        assert_eq!(fingerprint, request_fingerprint("alice", &request).unwrap());
+        assert_ne!(fingerprint, request_fingerprint("bob", &request).unwrap());
+        assert_ne!(fingerprint, request_fingerprint("alice", &json!({"ref": "dev", "changes": []})).unwrap());
+    }
+
+    #[test]
+    fn test_replay_restores_the_stored_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(QUOTA_WARNING_HEADER, HeaderValue::from_static("current=1500; soft=1000; hard=2000; percent=75.0"));
+        headers.insert("x-request-id", HeaderValue::from_static("req-1"));
+        let stored = IdempotentResponse {
+            status: 201,
+            headers: replayed_headers(&headers),
+            body: json!({"commit_id": "c1"}),
+        };
+        assert_eq!(stored.headers.len(), 1);
+
+        let replayed = replay(stored);
+
+        assert_eq!(replayed.status(), StatusCode::CREATED);
+        assert_eq!(replayed.headers()[IDEMPOTENT_REPLAYED_HEADER], "true");
+        assert_eq!(
+            replayed.headers()[QUOTA_WARNING_HEADER],
+            "current=1500; soft=1000; hard=2000; percent=75.0"
+        );
+        assert!(replayed.headers().get("x-request-id").is_none());
+    }
+
+    async fn commit_once(index: &IndexClient, repo_id: Uuid, changes: &[Change]) -> ApiResult<Response> {
+        let (commit, _) = index
+            .commit_atomic(&CommitWrite {
+                repo_id,
+                ref_name: "main",
+                author: "tester",
+                message: Some("retried"),
+                expected_parent: None,
+                changes,
+                schema: None,
+                merge_parent: None,
+            })
+            .await?;
+        Ok((StatusCode::CREATED, Json(json!({ "commit_id": commit.id }))).into_response())
+    }
+
+    async fn body(response: Response) -> Value {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_repeated_key_commits_once_and_replays_the_response() {
+        let index = test_index().await;
+        let repo_id = Uuid::new_v4();
+        sqlx::query("INSERT INTO repo (id, name, created_by) VALUES ($1, $2, 'test')")
+            .bind(repo_id)
+            .bind(format!("idempotency-{}", repo_id))
+            .execute(index.pool())
+            .await
+            .unwrap();
+        let sha256 = Uuid::new_v4().simple().to_string();
+        index.upsert_object(&sha256, 7, None, &sha256).await.unwrap();
+        let changes = vec![Change {
+            op: ChangeOp::Add,
+            path: "data/a.csv".to_string(),
+            sha256: Some(sha256.clone()),
+            meta: json!({"name": "a"}),
+        }];
+        let request = json!({"ref": "main", "changes": changes});
+        let call = IdempotentCall {
+            repo_id,
+            endpoint: "commit",
+            key: "retry-1",
+            request_sha256: request_fingerprint("tester", &request).unwrap(),
+        };
+
+        let first = run_once(&index, &call, commit_once(&index, repo_id, &changes)).await.unwrap();
+        assert_eq!(first.status(), StatusCode::CREATED);
+        assert!(first.headers().get(IDEMPOTENT_REPLAYED_HEADER).is_none());
+        let first = body(first).await;
+
+        let second = run_once(&index, &call, commit_once(&index, repo_id, &changes)).await.unwrap();
+        assert_eq!(second.status(), StatusCode::CREATED);
+        assert_eq!(second.headers()[IDEMPOTENT_REPLAYED_HEADER], "true");
+        assert_eq!(body(second).await, first);
+
+        let commits: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM commit WHERE repo_id = $1")
+            .bind(repo_id)
+            .fetch_one(index.pool())
+            .await
+            .unwrap();
+        assert_eq!(commits, 1);
+
+        // The same key with a different request is refused rather than replayed
+        let other = IdempotentCall {
+            request_sha256: request_fingerprint("tester", &json!({"ref": "dev"})).unwrap(),
+            ..call
+        };
+        let reused = run_once(&index, &other, commit_once(&index, repo_id, &changes)).await;
+        assert!(matches!(reused, Err(ApiError::Index(IndexError::IdempotencyConflict(_)))));
+
+        index.delete_repo(repo_id).await.unwrap();
+        index.delete_orphaned_objects(&[sha256]).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_unfinished_claim_is_retaken_after_its_lease() {
+        let index = test_index().await;
+        let repo_id = Uuid::new_v4();
+        sqlx::query("INSERT INTO repo (id, name, created_by) VALUES ($1, $2, 'test')")
+            .bind(repo_id)
+            .bind(format!("idempotency-{}", repo_id))
+            .execute(index.pool())
+            .await
+            .unwrap();
+        let lease = chrono::Duration::minutes(5);
+        let claim = |request_sha256: &'static str| {
+            let index = &index;
+            async move {
+                index
+                    .claim_idempotency_key(repo_id, "commit", "crashed", request_sha256, key_ttl(), chrono::Utc::now() - lease)
+                    .await
+            }
+        };
+
+        assert_eq!(claim("first").await.unwrap(), None);
+        assert!(matches!(claim("first").await, Err(IndexError::IdempotencyConflict(_))));
+
+        // The replica holding the key stopped without completing or releasing it
+        sqlx::query("UPDATE idempotency_key SET claimed_at = NOW() - INTERVAL '1 hour' WHERE repo_id = $1")
+            .bind(repo_id)
+            .execute(index.pool())
+            .await
+            .unwrap();
+        assert!(matches!(claim("other").await, Err(IndexError::IdempotencyConflict(_))));
+        assert_eq!(claim("first").await.unwrap(), None);
+        assert!(matches!(claim("first").await, Err(IndexError::IdempotencyConflict(_))));
+
+        index.delete_repo(repo_id).await.unwrap();
+    }
+}
@@ -0,0 +1,242 @@
+// Content-integrity verification for admins
+// Starts VerifyIntegrityJob runs and reports the objects they flagged
+
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+    routing::{get, post},
+    Router,
+};
+use blacklake_core::integrity::{IntegrityOptions, IntegrityReport, IntegrityStore, PgIntegrityStore};
+use blacklake_core::jobs::VerifyIntegrityJob;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::auth::require_admin;
+use crate::{ApiError, ApiResult, AppState};
+
+const MAX_BATCH_SIZE: u32 = 1000;
+
+/// Body of `POST /v1/admin/integrity`; every field is optional
+#[derive(Debug, Default, Deserialize)]
+pub struct IntegrityRequest {
+    /// Repository name; omit to verify every repository
+    pub repo: Option<String>,
+    pub batch_size: Option<u32>,
+    /// Upper bound on S3 reads per second; 0 disables pacing
+    pub objects_per_second: Option<u32>,
+}
+
+pub fn create_integrity_routes() -> Router<AppState> {
+    Router::new()
+        .route("/v1/admin/integrity", post(start_verification))
+        .route("/v1/admin/integrity/:report_id", get(get_report))
+}
+
+/// Start verifying the objects one repository, or every repository,
+/// references and return the id of the report the run fills in
+async fn start_verification(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<IntegrityRequest>,
+) -> ApiResult<(StatusCode, Json<Value>)> {
+    let auth = crate::extract_auth(&state.auth_layer, &headers).await?;
+    require_admin(&auth)?;
+
+    let repo_id = match &request.repo {
+        Some(repo) => Some(state.index.get_repo_by_name(repo).await?.id.0),
+        None => None,
+    };
+    let job = integrity_job(&request, repo_id)?;
+
+    // The report exists before the run starts, so it can be polled at once
+    let store = PgIntegrityStore::new(state.index.pool().clone());
+    store
+        .resume_report(job.report_id, job.repo_id)
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+
+    state
+        .index
+        .append_audit_log(
+            &auth.sub,
+            "integrity_verification_triggered",
+            request.repo.as_deref(),
+            None,
+            None,
+            None,
+            Some(json!({
+                "report_id": job.report_id,
+                "batch_size": job.batch_size,
+                "objects_per_second": job.objects_per_second
+            })),
+        )
+        .await?;
+
+    let report_id = job.report_id;
+    let repo = repo_id.zip(request.repo.as_deref());
+    crate::spawn_job(&state, repo, job);
+
+    Ok((StatusCode::ACCEPTED, Json(json!({ "report_id": report_id, "status": "running" }))))
+}
+
+/// Progress of a verification run and every object it has flagged so far
+async fn get_report(
+    State(state): State<AppState>,
+    Path(report_id): Path<Uuid>,
+    headers: HeaderMap,
+) -> ApiResult<Json<IntegrityReport>> {
+    let auth = crate::extract_auth(&state.auth_layer, &headers).await?;
+    require_admin(&auth)?;
+
+    let report = PgIntegrityStore::new(state.index.pool().clone())
+        .load_report(report_id)
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?
+        .ok_or_else(|| ApiError::Repo(format!("Integrity report not found: {}", report_id)))?;
+
+    Ok(Json(report))
+}
+
+/// The job a request describes, under a fresh report id
+fn integrity_job(request: &IntegrityRequest, repo_id: Option<Uuid>) -> ApiResult<VerifyIntegrityJob> {
+    let defaults = IntegrityOptions::default();
+    let batch_size = request.batch_size.unwrap_or(defaults.batch_size as u32);
+    if !(1..=MAX_BATCH_SIZE).contains(&batch_size) {
+        return Err(ApiError::InvalidRequest(format!(
+            "batch_size must be between 1 and {}",
+            MAX_BATCH_SIZE
+        )));
+    }
+
+    Ok(VerifyIntegrityJob {
+        report_id: Uuid::new_v4(),
+        repo_id,
+        batch_size,
+        objects_per_second: request.objects_per_second.unwrap_or(defaults.objects_per_second),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::response::IntoResponse;
+    use blacklake_core::integrity::FindingKind;
+    use blacklake_core::jobs::{BlackLakeJob, JobContext};
+    use blacklake_index::test_support::test_index;
+    use sha2::{Digest, Sha256};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_out_of_range_batch_size_is_rejected() {
+        for batch_size in [0, MAX_BATCH_SIZE + 1] {
+            let request = IntegrityRequest { batch_size: Some(batch_size), ..Default::default() };
+            let err = integrity_job(&request, None).unwrap_err();
+            assert_eq!(err.into_response().status(), StatusCode::BAD_REQUEST);
+        }
+
+        let job = integrity_job(&IntegrityRequest::default(), None).unwrap();
+        assert_eq!((job.repo_id, job.batch_size), (None, 100));
+    }
+
+    /// An S3 stand-in serving `objects` by key from any bucket
+    async fn stub_s3(objects: HashMap<String, Vec<u8>>) -> aws_sdk_s3::Client {
+        let app = Router::new().route(
+            "/:bucket/*key",
+            get(move |Path((_, key)): Path<(String, String)>| {
+                let body = objects.get(&key).cloned();
+                async move {
+                    match body {
+                        Some(body) => body.into_response(),
+                        None => StatusCode::NOT_FOUND.into_response(),
+                    }
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let config = aws_sdk_s3::Config::builder()
+            .behavior_version(aws_sdk_s3::config::BehaviorVersion::latest())
+            .region(aws_sdk_s3::config::Region::new("us-east-1"))
+            .credentials_provider(aws_sdk_s3::config::Credentials::new("test", "test", None, None, "test"))
+            .endpoint_url(endpoint)
+            .force_path_style(true)
+            .build();
+        aws_sdk_s3::Client::from_conf(config)
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_tampered_object_is_reported() {
+        let pool = test_index().await.pool().clone();
+        let repo_id = Uuid::new_v4();
+        let commit_id = Uuid::new_v4();
+        sqlx::query("INSERT INTO repo (id, name, created_by) VALUES ($1, $2, 'test')")
+            .bind(repo_id)
+            .bind(format!("integrity-{}", repo_id))
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO commit (id, repo_id, author) VALUES ($1, $2, 'test')")
+            .bind(commit_id)
+            .bind(repo_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let sha256 = |data: &[u8]| hex::encode(Sha256::digest(data));
+        let intact = sha256(format!("intact {}", repo_id).as_bytes());
+        let tampered = sha256(format!("original {}", repo_id).as_bytes());
+        for (path, sha256) in [("data/intact.csv", &intact), ("data/tampered.csv", &tampered)] {
+            sqlx::query("INSERT INTO object (sha256, size, s3_key) VALUES ($1, 1, $1)")
+                .bind(sha256)
+                .execute(&pool)
+                .await
+                .unwrap();
+            sqlx::query("INSERT INTO entry (commit_id, path, object_sha256, meta) VALUES ($1, $2, $3, '{}')")
+                .bind(commit_id)
+                .bind(path)
+                .bind(sha256)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+
+        // The tampered object's bytes were replaced after it was stored
+        let s3_client = stub_s3(HashMap::from([
+            (intact.clone(), format!("intact {}", repo_id).into_bytes()),
+            (tampered.clone(), b"replaced".to_vec()),
+        ]))
+        .await;
+
+        let request = IntegrityRequest { objects_per_second: Some(0), ..Default::default() };
+        let job = integrity_job(&request, Some(repo_id)).unwrap();
+        let ctx = JobContext {
+            job_id: job.report_id,
+            worker_id: "test".to_string(),
+            s3_client: Some(s3_client),
+            db_pool: Some(pool.clone()),
+            solr: None,
+        };
+        job.process(&ctx).await.unwrap();
+
+        let report = PgIntegrityStore::new(pool.clone()).load_report(job.report_id).await.unwrap().unwrap();
+        assert_eq!(report.status, "completed");
+        assert_eq!((report.objects_checked, report.mismatches), (2, 1));
+        assert_eq!(report.findings.len(), 1);
+        let finding = &report.findings[0];
+        assert_eq!((finding.sha256.as_str(), finding.kind), (tampered.as_str(), FindingKind::Mismatch));
+        assert_eq!(finding.actual_sha256, Some(sha256(b"replaced")));
+
+        sqlx::query("DELETE FROM repo WHERE id = $1").bind(repo_id).execute(&pool).await.unwrap();
+        sqlx::query("DELETE FROM object WHERE sha256 = ANY($1)")
+            .bind(vec![intact, tampered])
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+}
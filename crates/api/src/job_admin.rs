@@ -0,0 +1,120 @@
+// Dead-letter job administration
+// Lists jobs that ran out of attempts and puts them back on their queues
+
+use axum::{
+    extract::{Path, Query, State},
+    http::HeaderMap,
+    response::Json,
+    routing::{get, post},
+    Router,
+};
+use blacklake_core::jobs::{DeadLetterJob, JobError};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::auth::require_admin;
+use crate::{ApiError, ApiResult, AppState};
+
+/// A dead-lettered job as listed to admins
+#[derive(Debug, Serialize)]
+pub struct DeadLetterView {
+    pub job_id: String,
+    pub job_type: String,
+    pub error_message: String,
+    pub failed_at: chrono::DateTime<chrono::Utc>,
+    pub retry_count: u32,
+    pub payload: Value,
+}
+
+impl From<DeadLetterJob> for DeadLetterView {
+    fn from(job: DeadLetterJob) -> Self {
+        Self {
+            job_id: job.job_id,
+            job_type: job.job_data.job_type,
+            error_message: job.error_message,
+            failed_at: job.failed_at,
+            retry_count: job.retry_count,
+            payload: job.job_data.payload,
+        }
+    }
+}
+
+/// Query of `POST /v1/admin/jobs/dead-letter/requeue`; without `type` every job is requeued
+#[derive(Debug, Default, Deserialize)]
+pub struct RequeueParams {
+    #[serde(rename = "type")]
+    pub job_type: Option<String>,
+}
+
+pub fn create_job_admin_routes() -> Router<AppState> {
+    Router::new()
+        .route("/v1/admin/jobs/dead-letter", get(list_dead_letter))
+        .route("/v1/admin/jobs/dead-letter/requeue", post(requeue_dead_letter))
+        .route("/v1/admin/jobs/:id/retry", post(retry_dead_letter))
+}
+
+fn job_error(err: JobError) -> ApiError {
+    match err {
+        JobError::Storage(msg) => ApiError::Unavailable(msg),
+        other => ApiError::Internal(other.to_string()),
+    }
+}
+
+/// Every dead-lettered job with its type and the error it last failed with
+async fn list_dead_letter(State(state): State<AppState>, headers: HeaderMap) -> ApiResult<Json<Value>> {
+    let auth = crate::extract_auth(&state.auth_layer, &headers).await?;
+    require_admin(&auth)?;
+
+    let jobs: Vec<DeadLetterView> = state
+        .job_manager
+        .get_dead_letter_jobs()
+        .await
+        .map_err(job_error)?
+        .into_iter()
+        .map(DeadLetterView::from)
+        .collect();
+    Ok(Json(json!({ "count": jobs.len(), "jobs": jobs })))
+}
+
+/// Put one dead-lettered job back on its queue as pending
+async fn retry_dead_letter(
+    State(state): State<AppState>,
+    Path(job_id): Path<String>,
+    headers: HeaderMap,
+) -> ApiResult<Json<Value>> {
+    let auth = crate::extract_auth(&state.auth_layer, &headers).await?;
+    require_admin(&auth)?;
+
+    if !state.job_manager.requeue_dead_letter_job(&job_id).await.map_err(job_error)? {
+        return Err(ApiError::Repo(format!("No dead-lettered job {}", job_id)));
+    }
+    audit_requeue(&state, &auth.sub, json!({ "job_id": job_id, "requeued": 1 })).await?;
+    Ok(Json(json!({ "job_id": job_id, "requeued": 1 })))
+}
+
+/// Requeue every dead-lettered job, or only those of `?type=`
+async fn requeue_dead_letter(
+    State(state): State<AppState>,
+    Query(params): Query<RequeueParams>,
+    headers: HeaderMap,
+) -> ApiResult<Json<Value>> {
+    let auth = crate::extract_auth(&state.auth_layer, &headers).await?;
+    require_admin(&auth)?;
+
+    let job_type = params.job_type.as_deref().filter(|job_type| !job_type.is_empty());
+    let requeued = state
+        .job_manager
+        .requeue_dead_letter_jobs(job_type)
+        .await
+        .map_err(job_error)?;
+    audit_requeue(&state, &auth.sub, json!({ "job_type": job_type, "requeued": requeued })).await?;
+    Ok(Json(json!({ "job_type": job_type, "requeued": requeued })))
+}
+
+async fn audit_requeue(state: &AppState, actor: &str, details: Value) -> ApiResult<()> {
+    state
+        .index
+        .append_audit_log(actor, "dead_letter_requeued", None, None, None, None, Some(details))
+        .await?;
+    Ok(())
+}
@@ -1,35 +1,44 @@
 use axum::{
-    extract::{Path, Query, State, Request},
+    extract::{ConnectInfo, Path, Query, State, Request},
     http::{HeaderMap, StatusCode},
-    response::Json,
-    routing::{get, post},
+    response::{IntoResponse, Json, Response},
+    routing::{delete, get, post, put},
     Router, middleware,
 };
 use blacklake_core::{
-    AuthContext, CanonicalMeta, Change, ChangeOp, CommitRequest, CommitResponse, CreateRepoRequest,
-    CreateRepoResponse, generate_subject_iri, JwtClaims, MetadataSchema, project_to_index,
-    RdfFormat, SearchRequest, SearchResponse, TreeResponse, TreeEntry, UploadInitRequest, 
-    UploadInitResponse, canonical_to_dc_jsonld, canonical_to_turtle, validate_repo_name,
-    normalize_path, validate_meta, validate_content_type, validate_file_size,
-    SchemaRegistry, create_dublin_core_schema, deep_merge, get_metadata_changes,
+    AuditLogFilter, AuditLogPage, AuthContext, CanonicalMeta, Change, ChangeOp, CommitPlan, CommitRequest, CommitResponse, CreateRepoRequest,
+    CreateRepoResponse, CreateTagRequest, SetRepoFeatureRequest, UpdateRefRequest, DedupStats, EntrySample, RepoDeletion, generate_subject_iri, SubjectIriBase, Permission, Reference, ReferenceKind, MetadataSchema,
+    RdfFormat, SearchRequest, SearchResponse, SearchSort, SortOrder, TreeResponse, TreeEntry, UploadInitRequest, 
+    UploadInitResponse, UploadInitBatchRequest, UploadInitBatchResponse, BatchUploadItem, BatchUploadUrl, plan_batch_upload, MultipartUploadPlan, UploadPartUrl, UploadPart, UploadCompleteRequest,
+    UploadCompleteResponse, UploadVerification, UploadVerifyRequest, UploadVerifyResponse, canonical_to_dc_jsonld, canonical_to_ntriples, canonical_to_rdfxml, canonical_to_turtle,
+    validate_repo_name,
+    normalize_path, validate_meta, validate_content_type, validate_file_size, validate_changes, validate_sha256, ValidateCommitResponse,
+    three_way_merge, MergeRequest, MergeResponse, MergeStrategy, json_patch, MetaDiffResponse, QuotaWarning, QUOTA_WARNING_HEADER, FEATURES_VERSION_HEADER,
+    SchemaRegistry, SchemaViolation, create_dublin_core_schema, get_metadata_changes,
 };
-use blacklake_core::search::SolrClient;
+use blacklake_core::search::{SolrClient, SolrConfig};
 use blacklake_core::sessions::SessionManager;
-use blacklake_core::jobs::{JobContext, run_all_workers};
-use blacklake_index::{IndexClient, IndexError};
+use blacklake_core::governance::RefMutation;
+use blacklake_core::content_policy::ContentTypePolicy;
+use blacklake_core::features::{RepoFeature, RepoFeatures};
+use blacklake_core::signing::{SignatureVerifier, TrustedKeys};
+use blacklake_core::policy_backend::{policy_backend_from_env, PolicyBackend};
+use blacklake_core::role_permissions::RolePermissionMap;
+use blacklake_core::jobs::{BlackLakeJob, ConvertToParquetJob, EmbedEntryJob, JobContext, JobManager, SniffMediaTypeJob, ThumbnailJob, VerifyUploadJob, run_all_workers};
+use blacklake_index::{CommitWrite, IndexClient, IndexError};
+use body_limit::StreamingJson;
 use blacklake_storage::{StorageClient, StorageError};
 use chrono::{Duration, Utc};
-use jsonwebtoken::{decode, DecodingKey, Validation};
 use serde_json::{json, Value};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
 use tower::ServiceBuilder;
 use tower_http::{
     cors::{Any, CorsLayer},
     trace::{TraceLayer, DefaultMakeSpan, DefaultOnRequest, DefaultOnResponse},
 };
-use jsonschema::{JSONSchema, ValidationError};
-use tracing::{info, warn, instrument, Span};
+use tracing::{error, info, warn, instrument, Span};
 use uuid::Uuid;
 
 // Import new modules
@@ -50,9 +59,30 @@ mod openapi;
 mod connectors;
 mod semantic_search;
 mod compliance;
-
-use auth::{AuthLayer, auth_middleware, request_id_middleware, create_auth_layer};
-use health::{HealthState, liveness_check, readiness_check, metrics, create_metrics_registry};
+mod compliance_report;
+mod conditional;
+mod signed_url_constraints;
+mod geoip;
+mod signed_url_rate_limit;
+mod objects;
+mod reindex;
+mod search_export;
+mod commit_plan;
+mod virtual_repo;
+mod idempotency;
+mod compression;
+mod events;
+mod body_limit;
+mod meta_batch;
+mod job_admin;
+mod blame;
+mod integrity;
+
+use auth::{AuthError, AuthLayer, auth_middleware, is_admin, require_admin, request_id_middleware, create_auth_layer, start_jwks_refresh};
+use health::{HealthState, liveness_check, readiness_check, readiness_dependencies, readiness_probe_timeout, metrics, create_metrics_registry, http_metrics_middleware, BYTES_DOWNLOADED_TOTAL, BYTES_UPLOADED_TOTAL, COMMITS_TOTAL};
+use conditional::{if_none_match, not_modified, respond_with_etag, strong_etag};
+use signed_url_constraints::{AccessTokenClaims, Enforcement, SignedUrlConstraintService, SignedUrlRequest};
+use search_export::{render_search, search_sort, SearchFormat};
 use rate_limit::{RateLimitState, rate_limit_middleware, create_rate_limit_config, start_rate_limit_cleanup};
 
 #[derive(Clone)]
@@ -66,6 +96,34 @@ pub struct AppState {
     pub solr_client: SolrClient,
     pub session_manager: tower_sessions::SessionManagerLayer<tower_sessions_redis_store::RedisStore>,
     pub job_context: JobContext,
+    /// Redis-backed job queues, for dead-letter inspection and requeueing
+    pub job_manager: Arc<JobManager>,
+    pub signed_url_constraints: Arc<SignedUrlConstraintService>,
+    pub role_permissions: Arc<RolePermissionMap>,
+    pub signature_verifier: SignatureVerifier,
+    pub policy_backend: Arc<dyn PolicyBackend>,
+    pub events: events::EventBus,
+    pub object_stats_cache: objects::StorageClassCache,
+    /// Base of generated RDF subject IRIs (`RDF_BASE_IRI`)
+    pub rdf_base: SubjectIriBase,
+}
+
+impl AppState {
+    /// Checks every write to a ref goes through
+    fn commit_gate(&self) -> commit_plan::CommitGate<'_> {
+        commit_plan::CommitGate {
+            index: &self.index,
+            storage: &self.storage,
+            policy: self.policy_backend.as_ref(),
+            signatures: &self.signature_verifier,
+        }
+    }
+}
+
+impl axum::extract::FromRef<AppState> for HealthState {
+    fn from_ref(state: &AppState) -> Self {
+        state.health_state.clone()
+    }
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -80,19 +138,76 @@ pub enum ApiError {
     Index(#[from] IndexError),
     #[error("Invalid request: {0}")]
     InvalidRequest(String),
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
     #[error("Internal error: {0}")]
     Internal(String),
+    #[error("Service unavailable: {0}")]
+    Unavailable(String),
+    #[error("Payload too large: {0}")]
+    PayloadTooLarge(String),
+    #[error("Metadata validation failed for '{path}'")]
+    SchemaViolation {
+        path: String,
+        violations: Vec<SchemaViolation>,
+    },
 }
 
 impl axum::response::IntoResponse for ApiError {
     fn into_response(self) -> axum::response::Response {
+        if let ApiError::SchemaViolation { path, violations } = &self {
+            let body = Json(json!({
+                "error": self.to_string(),
+                "path": path,
+                "violations": violations,
+                "timestamp": Utc::now()
+            }));
+            return (StatusCode::BAD_REQUEST, body).into_response();
+        }
+
+        // Losing a commit race is a conflict; the current head lets the client rebase
+        if let ApiError::Index(IndexError::ParentMismatch { ref_name, expected, actual }) = &self {
+            let body = Json(json!({
+                "error": self.to_string(),
+                "ref": ref_name,
+                "expected_parent": expected,
+                "actual_parent": actual,
+                "timestamp": Utc::now()
+            }));
+            return (StatusCode::CONFLICT, body).into_response();
+        }
+
+        // So is a stale feature flag write; the client re-reads and retries
+        if let ApiError::Index(IndexError::FeaturesVersionMismatch { expected, actual }) = &self {
+            let body = Json(json!({
+                "error": self.to_string(),
+                "expected_version": expected,
+                "actual_version": actual,
+                "timestamp": Utc::now()
+            }));
+            return (StatusCode::CONFLICT, body).into_response();
+        }
+
         let (status, error_message) = match self {
             ApiError::Auth(msg) => (StatusCode::UNAUTHORIZED, msg),
             ApiError::Repo(msg) => (StatusCode::NOT_FOUND, msg),
+            ApiError::Storage(StorageError::RangeNotSatisfiable(range)) => {
+                (StatusCode::RANGE_NOT_SATISFIABLE, format!("Range not satisfiable: {}", range))
+            }
             ApiError::Storage(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg.to_string()),
+            ApiError::Index(e @ (IndexError::RefExists(_) | IndexError::TagImmutable(_) | IndexError::IdempotencyConflict(_))) => {
+                (StatusCode::CONFLICT, e.to_string())
+            }
+            ApiError::Index(e @ (IndexError::InvalidFeature(_) | IndexError::InvalidFilter(_) | IndexError::ConnectorNotFound(_))) => {
+                (StatusCode::BAD_REQUEST, e.to_string())
+            }
             ApiError::Index(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg.to_string()),
             ApiError::InvalidRequest(msg) => (StatusCode::BAD_REQUEST, msg),
+            ApiError::Forbidden(msg) => (StatusCode::FORBIDDEN, msg),
             ApiError::Internal(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
+            ApiError::Unavailable(msg) => (StatusCode::SERVICE_UNAVAILABLE, msg),
+            ApiError::PayloadTooLarge(msg) => (StatusCode::PAYLOAD_TOO_LARGE, msg),
+            ApiError::SchemaViolation { .. } => unreachable!("handled above"),
         };
 
         let body = Json(json!({
@@ -104,6 +219,15 @@ impl axum::response::IntoResponse for ApiError {
     }
 }
 
+impl From<AuthError> for ApiError {
+    fn from(err: AuthError) -> Self {
+        match err {
+            AuthError::JwksError(msg) => ApiError::Unavailable(msg),
+            other => ApiError::Auth(other.to_string()),
+        }
+    }
+}
+
 type ApiResult<T> = Result<T, ApiError>;
 
 #[tokio::main]
@@ -124,9 +248,8 @@ async fn main() -> anyhow::Result<()> {
     let index = IndexClient::from_env().await?;
     let storage = StorageClient::from_env().await?;
     
-    // Initialize Solr client
-    let solr_url = std::env::var("SOLR_URL").unwrap_or_else(|_| "http://localhost:8983".to_string());
-    let solr_client = SolrClient::new(&solr_url, "blacklake")?;
+    // Initialize Solr client; SOLR_PER_REPO_COLLECTIONS gives each repo its own collection
+    let solr_client = SolrClient::new(SolrConfig::from_env());
     
     // Initialize Redis client for sessions
     let redis_url = std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://localhost:6379".to_string());
@@ -147,10 +270,44 @@ async fn main() -> anyhow::Result<()> {
     let job_context = JobContext {
         db_pool: index.get_pool().clone(),
         s3_client: storage.get_s3_client().clone(),
+        solr: Some(solr_client.clone()),
     };
     
+    let job_manager = Arc::new(JobManager::connect(&redis_url).await?);
+    
+    // Constraints checked before any presigned URL is issued
+    let signed_url_constraints = Arc::new(
+        SignedUrlConstraintService::new(index.get_pool().clone())
+            .with_geoip(geoip::GeoIpResolver::from_env())
+            .with_rate_limit_store(signed_url_rate_limit::RedisRateLimitStore::connect(&redis_url).await?)
+            .with_token_secret(signed_url_constraints::token_secret_from_env())
+            .with_trusted_proxies(signed_url_constraints::TrustedProxies::from_env()),
+    );
+    
     // Initialize auth layer
     let auth_layer = create_auth_layer()?;
+    tokio::spawn(start_jwks_refresh(auth_layer.clone()));
+
+    // Repository permissions granted by IdP roles
+    let role_permissions = Arc::new(
+        RolePermissionMap::from_env().map_err(|e| anyhow::anyhow!("Invalid OIDC_ROLE_PERMISSIONS: {}", e))?,
+    );
+    
+    // Model signatures are checked against the keys in COSIGN_PUBLIC_KEYS
+    let signature_verifier = SignatureVerifier::new(
+        storage.get_s3_client().clone(),
+        storage.bucket(),
+        TrustedKeys::from_env().map_err(|e| anyhow::anyhow!("Invalid COSIGN_PUBLIC_KEYS: {}", e))?,
+    );
+    
+    // Commit policy: built-in branch protection or OPA (POLICY_BACKEND)
+    let policy_backend = policy_backend_from_env().map_err(|e| anyhow::anyhow!("{}", e))?;
+    
+    // Generated RDF names its subjects under the deployment's public URL
+    let rdf_base = SubjectIriBase::from_env().map_err(|e| anyhow::anyhow!("Invalid RDF_BASE_IRI: {}", e))?;
+    
+    // Commit, job, webhook and quota events for /v1/events subscribers
+    let events = events::EventBus::from_env();
     
     // Initialize rate limiting
     let rate_limit_config = create_rate_limit_config();
@@ -159,8 +316,8 @@ async fn main() -> anyhow::Result<()> {
     // Initialize metrics
     let metrics_registry = create_metrics_registry();
     let health_state = HealthState {
-        index: index.clone(),
-        storage: storage.clone(),
+        dependencies: Arc::new(readiness_dependencies(&index, &storage, &solr_client, &redis_url)?),
+        probe_timeout: readiness_probe_timeout(),
         metrics: Arc::new(metrics_registry),
     };
 
@@ -179,28 +336,57 @@ async fn main() -> anyhow::Result<()> {
         solr_client,
         session_manager,
         job_context,
+        job_manager,
+        signed_url_constraints,
+        role_permissions,
+        signature_verifier,
+        policy_backend,
+        events: events.clone(),
+        object_stats_cache: objects::StorageClassCache::from_env(),
+        rdf_base,
     };
 
     // Build the application
-    let app = Router::new()
+    let routes = Router::new()
         // Health endpoints (no auth required)
         .route("/live", get(liveness_check))
         .route("/ready", get(readiness_check))
         .route("/metrics", get(metrics))
         // API endpoints
         .route("/v1/repos", post(create_repo).get(list_repos))
+        .route("/v1/repos/:repo", delete(delete_repo))
+        .route("/v1/audit", get(query_audit_log))
         .route("/v1/repos/:repo/upload-init", post(upload_init))
+        .route("/v1/repos/:repo/upload-init-batch", post(upload_init_batch))
+        .route("/v1/repos/:repo/upload-complete", post(upload_complete))
+        .route("/v1/repos/:repo/upload-verify", post(upload_verify))
         .route("/v1/repos/:repo/commit", post(commit))
+        .route("/v1/repos/:repo/validate", post(validate_commit))
+        .route("/v1/repos/:repo/merge", post(merge_refs))
         .route("/v1/repos/:repo/blob/:ref/*path", get(get_blob))
-        .route("/v1/repos/:repo/tree/:ref", get(get_tree))
-        .route("/v1/repos/:repo/search", get(search))
-        .route("/v1/repos/:repo/rdf/:ref/*path", get(get_rdf))
+        .route("/v1/repos/:repo/refs", get(list_refs))
+        .route("/v1/repos/:repo/tags", post(create_tag))
+        .route("/v1/repos/:repo/stats", get(get_repo_stats))
+        .route("/v1/repos/:repo/features", get(get_repo_features).post(set_repo_feature))
+        .route("/v1/repos/:repo/refs/*name", put(update_ref).delete(delete_ref))
+        .route("/v1/repos/:repo/sample/:ref/*path", get(get_sample))
+        .route("/v1/repos/:repo/meta-diff/:ref/*path", get(meta_diff))
+        .route("/v1/repos/:repo/thumbnail/:ref/*path", get(get_thumbnail))
         .route("/v1/schemas/:collection", get(get_schema))
         .route("/v1/schemas/default", get(get_default_schema))
+        // Live commit, job, webhook and quota events
+        .merge(events::create_event_routes())
+        // Large listings and graphs, compressed as Accept-Encoding allows
+        .merge(compression::compressed(
+            Router::new()
+                .route("/v1/repos/:repo/tree/:ref", get(get_tree))
+                .route("/v1/repos/:repo/search", get(search))
+                .route("/v1/repos/:repo/rdf/:ref/*path", get(get_rdf)),
+        ))
         // Governance routes
         .merge(governance::create_governance_routes())
-        // Webhook routes
-        .merge(webhooks::create_webhook_routes())
+        // Webhook replay routes
+        .merge(webhooks::create_webhook_replay_routes())
         // Export routes
         .merge(exports::create_export_routes())
         // UI API routes
@@ -219,8 +405,32 @@ async fn main() -> anyhow::Result<()> {
         .merge(semantic_search::create_semantic_search_routes())
         // Compliance routes
         .merge(compliance::create_compliance_routes())
+        // Per-repository compliance report
+        .merge(compliance_report::create_compliance_report_routes())
+        // Signed URL constraint routes
+        .merge(signed_url_constraints::signed_url_constraints_router())
+        // Content-addressed object routes
+        .merge(objects::create_object_routes())
+        // Search reindex routes
+        .merge(reindex::create_reindex_routes())
+        // Content-integrity verification runs
+        .merge(integrity::create_integrity_routes())
+        // Connector-backed repository routes
+        .merge(virtual_repo::create_virtual_repo_routes())
+        // Bulk metadata patches
+        .merge(meta_batch::create_meta_batch_routes())
+        // Dead-letter job administration
+        .merge(job_admin::create_job_admin_routes())
+        // Per-field metadata blame
+        .merge(blame::create_blame_routes());
+
+    // Every request body is capped (MAX_REQUEST_BODY_BYTES) and refused with a 413 past it
+    let max_body_bytes = body_limit::max_body_bytes_from_env();
+    info!("Request bodies limited to {} bytes", max_body_bytes);
+    let app = body_limit::limited(routes, max_body_bytes)
         .layer(
             ServiceBuilder::new()
+                .layer(middleware::from_fn(http_metrics_middleware))
                 .layer(state.session_manager.clone())
                 .layer(middleware::from_fn_with_state(
                     state.rate_limit_state.clone(),
@@ -251,7 +461,8 @@ async fn main() -> anyhow::Result<()> {
     info!("Server listening on {}:{}", host, port);
 
     // Start background workers
-    let worker_manager = workers::WorkerManager::new(index.clone(), storage.clone(), solr_client.clone());
+    let worker_manager = workers::WorkerManager::new(index.clone(), storage.clone(), solr_client.clone())
+        .with_events(events);
     worker_manager.start_all().await;
 
     // Setup graceful shutdown
@@ -262,7 +473,8 @@ async fn main() -> anyhow::Result<()> {
         info!("Received shutdown signal");
     };
 
-    axum::serve(listener, app)
+    // Signed URL constraints check the socket peer, not client-supplied headers
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
         .with_graceful_shutdown(shutdown_signal)
         .await?;
 
@@ -296,54 +508,126 @@ async fn request_id_middleware(
 }
 
 // Authentication middleware
-async fn extract_auth(headers: &HeaderMap) -> ApiResult<AuthContext> {
-    // Extract token from Authorization header
-    let auth_header = headers
-        .get("Authorization")
-        .ok_or_else(|| ApiError::Auth("Missing authorization header".to_string()))?
-        .to_str()
-        .map_err(|_| ApiError::Auth("Invalid authorization header".to_string()))?;
-
-    if !auth_header.starts_with("Bearer ") {
-        return Err(ApiError::Auth("Invalid authorization format".to_string()));
-    }
-
-    let token = &auth_header[7..]; // Remove "Bearer " prefix
+async fn extract_auth(auth_layer: &AuthLayer, headers: &HeaderMap) -> ApiResult<AuthContext> {
+    Ok(auth_layer.authenticate(headers).await?)
+}
 
-    // Validate JWT token with OIDC
-    let jwt_secret = std::env::var("JWT_SECRET")
-        .unwrap_or_else(|_| "your-jwt-secret-key".to_string());
-    
-    let decoding_key = DecodingKey::from_secret(jwt_secret.as_ref());
-    let validation = Validation::default();
+/// Run the signed URL constraints registered for a repository before presigning.
+///
+/// Returns the ids of the constraints that were checked so callers can report
+/// them, and an access token pinning those constraints to the URL until
+/// `expires_at` for the gateway to re-check.
+async fn enforce_presign_constraints(
+    state: &AppState,
+    repo_id: Uuid,
+    method: &str,
+    s3_key: &str,
+    peer: IpAddr,
+    headers: &HeaderMap,
+    expires_at: chrono::DateTime<Utc>,
+) -> ApiResult<PresignGrant> {
+    let request = SignedUrlRequest {
+        // Constraints are registered against the repository id
+        url_id: repo_id,
+        url: s3_key.to_string(),
+        method: method.to_string(),
+        headers: headers
+            .iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect(),
+        client_ip: state.signed_url_constraints.client_ip(peer, headers).to_string(),
+        user_agent: headers
+            .get(axum::http::header::USER_AGENT)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default()
+            .to_string(),
+        timestamp: Utc::now(),
+        constraints: Vec::new(),
+    };
 
-    // Decode and validate JWT token
-    let token_data = decode::<JwtClaims>(token, &decoding_key, &validation)
-        .map_err(|e| ApiError::Auth(format!("Invalid token: {}", e)))?;
+    let result = state
+        .signed_url_constraints
+        .validate_request(&request)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Failed to validate signed URL constraints: {}", e)))?;
+
+    match result.enforcement() {
+        Enforcement::Allow => {
+            let access_token = state.signed_url_constraints.mint_access_token(&AccessTokenClaims {
+                url_id: repo_id,
+                key: s3_key.to_string(),
+                method: method.to_string(),
+                constraints: result.applied_constraints.clone(),
+                expires_at,
+            });
+            Ok(PresignGrant { constraints_applied: result.applied_constraints, access_token })
+        }
+        Enforcement::Throttle(reason) => Err(ApiError::RateLimited(reason)),
+        Enforcement::Deny(reason) => Err(ApiError::Forbidden(format!("Signed URL constraint violated: {}", reason))),
+    }
+}
 
-    let claims = token_data.claims;
+/// What `enforce_presign_constraints` hands back with an allowed presign
+struct PresignGrant {
+    constraints_applied: Vec<Uuid>,
+    access_token: String,
+}
 
-    // Validate token expiration
-    let now = Utc::now().timestamp() as usize;
-    if claims.exp < now {
-        return Err(ApiError::Auth("Token has expired".to_string()));
+/// Require the caller to hold at least `required` on a repository.
+///
+/// Holders of the global `admin` role bypass repository ACLs; other roles
+/// count for whatever `OIDC_ROLE_PERMISSIONS` maps them to.
+async fn require_permission(
+    state: &AppState,
+    repo_id: Uuid,
+    auth: &AuthContext,
+    required: Permission,
+) -> ApiResult<()> {
+    if has_permission(state, repo_id, auth, required).await? {
+        Ok(())
+    } else {
+        Err(ApiError::Forbidden(format!(
+            "{} permission required on this repository",
+            required.as_str()
+        )))
     }
+}
 
-    // Validate token issuer (if configured)
-    if let Some(expected_issuer) = std::env::var("JWT_ISSUER").ok() {
-        if claims.iss != expected_issuer {
-            return Err(ApiError::Auth("Invalid token issuer".to_string()));
-        }
+/// Whether the caller holds at least `required` on a repository
+async fn has_permission(
+    state: &AppState,
+    repo_id: Uuid,
+    auth: &AuthContext,
+    required: Permission,
+) -> ApiResult<bool> {
+    if is_admin(auth) {
+        return Ok(true);
     }
 
-    // Extract user info from JWT claims
-    let user_id = claims.sub;
-    let roles = claims.roles.unwrap_or_else(|| vec!["user".to_string()]);
+    Ok(state
+        .index
+        .check_permission(repo_id, &auth.sub, &auth.roles, &state.role_permissions, required)
+        .await?)
+}
 
-    Ok(AuthContext {
-        sub: user_id,
-        roles,
-    })
+/// Run `job` in the background on behalf of repository `repo`, publishing
+/// `job.completed` when it finishes and logging a failure
+fn spawn_job<J: BlackLakeJob + std::fmt::Debug>(state: &AppState, repo: Option<(Uuid, &str)>, job: J) {
+    let ctx = JobContext {
+        job_id: Uuid::new_v4(),
+        worker_id: "api".to_string(),
+        s3_client: state.job_context.s3_client.clone(),
+        db_pool: Some(state.index.pool().clone()),
+        solr: None,
+    };
+    let events = state.events.clone();
+    let repo = repo.map(|(id, name)| (id, name.to_string()));
+    tokio::spawn(async move {
+        let repo = repo.as_ref().map(|(id, name)| (*id, name.as_str()));
+        if let Err(e) = events::run_job(&events, repo, &job, &ctx).await {
+            warn!("{} job {} failed for {:?}: {}", job.job_type(), ctx.job_id, job, e);
+        }
+    });
 }
 
 // Repository endpoints
@@ -353,7 +637,7 @@ async fn create_repo(
     headers: HeaderMap,
     Json(payload): Json<CreateRepoRequest>,
 ) -> ApiResult<Json<CreateRepoResponse>> {
-    let auth = extract_auth(&headers).await?;
+    let auth = extract_auth(&state.auth_layer, &headers).await?;
     
     // Validate repository name
     validate_repo_name(&payload.name)
@@ -435,7 +719,7 @@ async fn list_repos(
     State(state): State<AppState>,
     headers: HeaderMap,
 ) -> ApiResult<Json<Vec<CreateRepoResponse>>> {
-    let _auth = extract_auth(&headers).await?;
+    let _auth = extract_auth(&state.auth_layer, &headers).await?;
 
     let repos = state.index.list_repos().await?;
 
@@ -451,15 +735,132 @@ async fn list_repos(
     Ok(Json(response))
 }
 
+/// Read the audit log back, newest first; admins only.
+///
+/// Filters: `actor`, `action`, `repo`, `ref`, and RFC 3339 `since`/`until`.
+async fn query_audit_log(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> ApiResult<Json<AuditLogPage>> {
+    let auth = extract_auth(&state.auth_layer, &headers).await?;
+    require_admin(&auth)?;
+
+    let time_param = |name: &str| -> ApiResult<Option<chrono::DateTime<Utc>>> {
+        params
+            .get(name)
+            .map(|raw| {
+                chrono::DateTime::parse_from_rfc3339(raw)
+                    .map(|at| at.with_timezone(&Utc))
+                    .map_err(|_| ApiError::InvalidRequest(format!("Invalid {} timestamp: {}", name, raw)))
+            })
+            .transpose()
+    };
+    let count_param = |name: &str| -> ApiResult<Option<u32>> {
+        params
+            .get(name)
+            .map(|raw| raw.parse().map_err(|_| ApiError::InvalidRequest(format!("Invalid {}: {}", name, raw))))
+            .transpose()
+    };
+
+    let filter = AuditLogFilter {
+        actor: params.get("actor").cloned(),
+        action: params.get("action").cloned(),
+        repo_name: params.get("repo").cloned(),
+        ref_name: params.get("ref").cloned(),
+        since: time_param("since")?,
+        until: time_param("until")?,
+    };
+    let limit = count_param("limit")?;
+    let offset = count_param("offset")?;
+
+    let (entries, total) = state.index.query_audit_log(&filter, limit, offset).await?;
+
+    Ok(Json(AuditLogPage {
+        entries,
+        total,
+        limit: blacklake_index::audit_page_limit(limit),
+        offset: offset.unwrap_or(0),
+    }))
+}
+
+/// Delete a repository; `?confirm=` must repeat its name
+async fn delete_repo(
+    State(state): State<AppState>,
+    Path(repo): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> ApiResult<Json<RepoDeletion>> {
+    let auth = extract_auth(&state.auth_layer, &headers).await?;
+
+    let repo_info = state.index.get_repo_by_name(&repo).await?;
+    require_permission(&state, repo_info.id.0, &auth, Permission::Admin).await?;
+
+    if params.get("confirm") != Some(&repo) {
+        return Err(ApiError::InvalidRequest(format!(
+            "Deleting a repository is permanent; pass ?confirm={} to proceed",
+            repo
+        )));
+    }
+
+    let deletion = match state.index.delete_repo(repo_info.id.0).await {
+        Ok(deletion) => deletion,
+        Err(IndexError::LegalHold(name)) => {
+            return Err(ApiError::Forbidden(format!("Repository {} is under legal hold", name)))
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    state
+        .index
+        .append_audit_log(
+            &auth.sub,
+            "repo_delete",
+            Some(&repo),
+            None,
+            None,
+            None,
+            Some(json!({
+                "unreferenced_objects": deletion.unreferenced_objects,
+                "gc_not_before": deletion.gc_not_before,
+            })),
+        )
+        .await?;
+
+    Ok(Json(deletion))
+}
+
 // Upload endpoints
 
 async fn upload_init(
     State(state): State<AppState>,
     Path(repo): Path<String>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
     headers: HeaderMap,
     Json(payload): Json<UploadInitRequest>,
-) -> ApiResult<Json<UploadInitResponse>> {
-    let auth = extract_auth(&headers).await?;
+) -> ApiResult<axum::response::Response> {
+    let Some(key) = idempotency::idempotency_key(&headers)? else {
+        return run_upload_init(state, repo, peer.ip(), headers, payload).await;
+    };
+    let auth = extract_auth(&state.auth_layer, &headers).await?;
+    let repo_info = state.index.get_repo_by_name(&repo).await?;
+    let call = idempotency::IdempotentCall {
+        repo_id: repo_info.id.0,
+        endpoint: "upload_init",
+        key: &key,
+        request_sha256: idempotency::request_fingerprint(&auth.sub, &payload)?,
+    };
+    idempotency::run_once(&state.index, &call, run_upload_init(state.clone(), repo, peer.ip(), headers, payload)).await
+}
+
+async fn run_upload_init(
+    state: AppState,
+    repo: String,
+    peer: IpAddr,
+    headers: HeaderMap,
+    payload: UploadInitRequest,
+) -> ApiResult<axum::response::Response> {
+    let auth = extract_auth(&state.auth_layer, &headers).await?;
     
     // Validate path
     let normalized_path = normalize_path(&payload.path)
@@ -517,45 +918,129 @@ async fn upload_init(
 
     // Get repository
     let repo_info = state.index.get_repo_by_name(&repo).await?;
-
-    // ===== QUOTA ENFORCEMENT =====
-    
-    // Check quota limits before allowing upload
-    let quota_status = state.index.get_quota_status(repo_info.id).await?;
-    if let Some(quota) = quota_status {
-        // Check if adding this file would exceed hard limit
-        if quota.current_bytes + payload.size > quota.hard_limit {
-            return Err(ApiError::PayloadTooLarge(
-                format!("Upload would exceed repository quota: {} bytes (limit: {} bytes)", 
-                    quota.current_bytes + payload.size, quota.hard_limit)
-            ));
-        }
-        
-        // Add warning header if soft limit would be exceeded
-        if quota.current_bytes + payload.size > quota.soft_limit {
-            // Note: In a real implementation, we'd add this as a response header
-            // For now, we'll just log it
-            tracing::warn!(
-                "Upload would exceed soft quota limit: {} bytes (soft limit: {} bytes, hard limit: {} bytes)",
-                quota.current_bytes + payload.size, quota.soft_limit, quota.hard_limit
-            );
+    require_permission(&state, repo_info.id.0, &auth, Permission::Write).await?;
+    virtual_repo::ensure_writable(&state.index, repo_info.id.0, &repo).await?;
+
+    // Repos with content type lists refuse disallowed uploads up front; the
+    // commit sniffs the bytes in case the declared type was a lie
+    let features = state.index.get_repo_features(repo_info.id.0).await?;
+    ContentTypePolicy::from_features(&features)
+        .check(payload.media_type.as_deref())
+        .map_err(|e| ApiError::InvalidRequest(e.to_string()))?;
+
+    // A client-supplied content hash is the real content address, and S3
+    // refuses a PUT whose bytes do not match it. Without one the key is only
+    // a placeholder derived from the path and size.
+    let claimed_sha256 = payload
+        .sha256
+        .as_deref()
+        .map(validate_sha256)
+        .transpose()
+        .map_err(|e| ApiError::InvalidRequest(format!("Invalid sha256: {}", e)))?;
+
+    // Verified bytes are never uploaded again, so nobody gets a URL that
+    // could replace them
+    if let Some(sha256) = &claimed_sha256 {
+        if state.index.is_object_verified(sha256).await? {
+            return Ok(Json(UploadInitResponse {
+                upload_url: String::new(),
+                sha256: sha256.clone(),
+                s3_key: StorageClient::content_address_key(sha256),
+                expires_at: Utc::now(),
+                multipart: None,
+                constraints_applied: Vec::new(),
+                access_token: None,
+                upload_headers: HashMap::new(),
+                quota_warning: None,
+                already_stored: true,
+            })
+            .into_response());
         }
     }
 
-    // Generate SHA256 hash (in real implementation, this would be computed from file content)
-    let sha256 = blacklake_core::hash_bytes(&format!("{}{}", payload.path, payload.size).as_bytes());
+    let quota_warning = check_upload_quota(&state, &repo_info, payload.size).await?;
+    let sha256 = match &claimed_sha256 {
+        Some(sha256) => sha256.clone(),
+        None => blacklake_core::hash_bytes(&format!("{}{}", payload.path, payload.size).as_bytes()),
+    };
     let s3_key = blacklake_storage::StorageClient::content_address_key(&sha256);
-
-    // Generate presigned URL
-    let upload_url = state
+    let checksum = claimed_sha256
+        .as_deref()
+        .and_then(blacklake_storage::StorageClient::checksum_sha256_base64);
+    // Regulated repositories name their own KMS key in the "kms_key_id" feature
+    let kms_key_id = state
         .storage
-        .presign_put(
-            &s3_key,
-            payload.size,
-            &payload.media_type.unwrap_or_else(|| "application/octet-stream".to_string()),
-            Duration::hours(1),
-        )
-        .await?;
+        .kms_key_id(features.kms_key_id())
+        .map(|key| key.to_string());
+
+    let expires = state.storage.upload_url_ttl(repo_url_ttl_seconds(&features, RepoFeature::UploadUrlTtl)?)?;
+    let expires_at = Utc::now() + Duration::seconds(expires.as_secs() as i64);
+    // Large uploads get one presigned URL per part instead of a single PUT.
+    // S3 cannot check a multipart upload against the sha256, so the parts
+    // are assembled under a key of their own and reach the content address
+    // only once the assembled bytes are verified.
+    let use_multipart = state.storage.requires_multipart(payload.size);
+    let upload_key = if use_multipart {
+        StorageClient::staging_key(&sha256)
+    } else {
+        s3_key.clone()
+    };
+    let grant = enforce_presign_constraints(&state, repo_info.id.0, "PUT", &upload_key, peer, &headers, expires_at).await?;
+
+    let content_type = payload
+        .media_type
+        .clone()
+        .unwrap_or_else(|| "application/octet-stream".to_string());
+
+    let (upload_url, multipart) = if use_multipart {
+        let upload_id = state
+            .storage
+            .create_multipart_upload(&upload_key, &content_type, kms_key_id.as_deref())
+            .await?;
+        let part_size = state.storage.multipart_part_size(payload.size);
+        let part_count = blacklake_storage::part_count(payload.size, part_size) as i32;
+
+        let mut parts = Vec::with_capacity(part_count as usize);
+        for part_number in 1..=part_count {
+            match state
+                .storage
+                .presign_upload_part(&upload_key, &upload_id, part_number, expires)
+                .await
+            {
+                Ok(url) => parts.push(UploadPartUrl {
+                    part_number,
+                    upload_url: url.to_string(),
+                }),
+                Err(e) => {
+                    if let Err(abort_err) = state.storage.abort_multipart_upload(&upload_key, &upload_id).await {
+                        warn!("Failed to abort multipart upload {}: {}", upload_id, abort_err);
+                    }
+                    return Err(e.into());
+                }
+            }
+        }
+
+        let plan = MultipartUploadPlan { upload_id, staging_key: upload_key, part_size, parts };
+        (String::new(), Some(plan))
+    } else {
+        let url = state
+            .storage
+            .presign_put(&s3_key, payload.size, &content_type, checksum.as_deref(), kms_key_id.as_deref(), expires)
+            .await?;
+        (url.to_string(), None)
+    };
+
+    // Checksum and SSE-KMS headers are signed into the single PUT URL, so the
+    // client must send them
+    let mut upload_headers = HashMap::new();
+    if multipart.is_none() {
+        if let Some(checksum) = &checksum {
+            upload_headers.insert("x-amz-checksum-sha256".to_string(), checksum.clone());
+        }
+        if let Some(kms_key_id) = &kms_key_id {
+            upload_headers.extend(blacklake_storage::StorageClient::sse_kms_headers(kms_key_id));
+        }
+    }
 
     // Store object metadata
     state
@@ -568,224 +1053,851 @@ async fn upload_init(
         )
         .await?;
 
-    Ok(Json(UploadInitResponse {
-        upload_url: upload_url.to_string(),
+    let response = Json(UploadInitResponse {
+        upload_url,
         sha256,
         s3_key,
-        expires_at: Utc::now() + Duration::hours(1),
-    }))
+        expires_at,
+        multipart,
+        constraints_applied: grant.constraints_applied,
+        access_token: Some(grant.access_token),
+        upload_headers,
+        quota_warning: quota_warning.clone(),
+        already_stored: false,
+    })
+    .into_response();
+    Ok(with_quota_warning(response, quota_warning.as_ref()))
 }
 
-// Commit endpoints
+/// A repository's own presigned URL lifetime from its `feature`, in seconds.
+///
+/// Values S3 would not honour are rejected rather than silently clamped.
+fn repo_url_ttl_seconds(features: &RepoFeatures, feature: RepoFeature) -> ApiResult<Option<u64>> {
+    let Some(value) = features.get(feature) else {
+        return Ok(None);
+    };
+    let seconds = value
+        .as_u64()
+        .ok_or_else(|| ApiError::InvalidRequest(format!("Repository feature {} must be a whole number of seconds", feature.key())))?;
+    blacklake_storage::presign_ttl(seconds)
+        .map_err(|e| ApiError::InvalidRequest(format!("Repository feature {}: {}", feature.key(), e)))?;
+    Ok(Some(seconds))
+}
 
-async fn commit(
+/// Base for a repository's RDF subject IRIs: its `rdf_base_iri` feature, or
+/// the server's `RDF_BASE_IRI`
+fn repo_rdf_base(state: &AppState, features: &RepoFeatures) -> ApiResult<SubjectIriBase> {
+    match features.rdf_base_iri() {
+        Some(base) => SubjectIriBase::parse(base)
+            .map_err(|e| ApiError::InvalidRequest(format!("Repository feature {}: {}", RepoFeature::RdfBaseIri.key(), e))),
+        None => Ok(state.rdf_base.clone()),
+    }
+}
+
+/// Refuse an upload of `bytes` that would pass the repository's hard quota.
+///
+/// Past the soft limit the upload goes ahead, but the returned warning
+/// should be passed on to the client.
+async fn check_upload_quota(
+    state: &AppState,
+    repo_info: &blacklake_core::Repository,
+    bytes: u64,
+) -> ApiResult<Option<QuotaWarning>> {
+    let Some(quota) = state.index.get_quota_status(repo_info.id).await? else {
+        return Ok(None);
+    };
+
+    if quota.current_bytes + bytes > quota.hard_limit {
+        return Err(ApiError::PayloadTooLarge(
+            format!("Upload would exceed repository quota: {} bytes (limit: {} bytes)", 
+                quota.current_bytes + bytes, quota.hard_limit)
+        ));
+    }
+
+    let quota_warning = quota.soft_warning_after(bytes);
+    if let Some(warning) = &quota_warning {
+        tracing::warn!(
+            "Upload would exceed soft quota limit: {} bytes (soft limit: {} bytes, hard limit: {} bytes)",
+            warning.current_bytes, warning.soft_limit, warning.hard_limit
+        );
+        state.events.publish(events::Event::for_repo(
+            events::EventKind::QuotaWarning,
+            repo_info.id.0,
+            Some(&repo_info.name),
+            serde_json::to_value(warning).map_err(|e| ApiError::Internal(e.to_string()))?,
+        ));
+    }
+    Ok(quota_warning)
+}
+
+/// Presign PUTs for many files at once.
+///
+/// Objects already stored, and repeats of a hash earlier in the batch, are
+/// skipped rather than re-uploaded; the quota is checked against the bytes
+/// the remaining uploads add. Files large enough to need multipart must go
+/// through `upload-init`.
+async fn upload_init_batch(
     State(state): State<AppState>,
     Path(repo): Path<String>,
-    Query(params): Query<HashMap<String, String>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
     headers: HeaderMap,
-    Json(payload): Json<CommitRequest>,
-) -> ApiResult<Json<CommitResponse>> {
-    let auth = extract_auth(&headers).await?;
+    Json(payload): Json<UploadInitBatchRequest>,
+) -> ApiResult<axum::response::Response> {
+    let auth = extract_auth(&state.auth_layer, &headers).await?;
 
-    // Implement commit message validation and sanitization
-    let sanitized_message = validate_and_sanitize_commit_message(&payload.message)?;
-    
-    // Implement commit size limits and validation
-    let total_commit_size = calculate_commit_size(&payload.changes)?;
-    validate_commit_size(total_commit_size)?;
-    
-    // Implement atomic commit operations with proper rollback
-    let transaction = state.index.begin_transaction().await?;
+    if payload.items.is_empty() {
+        return Err(ApiError::InvalidRequest("Batch has no items".to_string()));
+    }
 
-    // Check for RDF emission flag
-    let emit_rdf = params.get("emit_rdf")
-        .map(|v| v == "true")
-        .unwrap_or(false);
+    let mut items = Vec::with_capacity(payload.items.len());
+    for item in payload.items {
+        let path = normalize_path(&item.path)
+            .map_err(|e| ApiError::InvalidRequest(format!("Invalid path {}: {}", item.path, e)))?;
+        validate_file_size(item.size, None)
+            .map_err(|e| ApiError::InvalidRequest(format!("Invalid file size for {}: {}", path, e)))?;
+        if let Some(ref content_type) = item.media_type {
+            validate_content_type(content_type)
+                .map_err(|e| ApiError::InvalidRequest(format!("Invalid content type for {}: {}", path, e)))?;
+        }
+        let sha256 = validate_sha256(&item.sha256)
+            .map_err(|e| ApiError::InvalidRequest(format!("Invalid sha256 for {}: {}", path, e)))?;
+        if state.storage.requires_multipart(item.size) {
+            return Err(ApiError::InvalidRequest(format!(
+                "{} is large enough to need a multipart upload; use upload-init",
+                path
+            )));
+        }
+        items.push(BatchUploadItem { path, sha256, ..item });
+    }
 
-    // Get repository
     let repo_info = state.index.get_repo_by_name(&repo).await?;
+    require_permission(&state, repo_info.id.0, &auth, Permission::Write).await?;
+
+    let features = state.index.get_repo_features(repo_info.id.0).await?;
+    let content_types = ContentTypePolicy::from_features(&features);
+    for item in &items {
+        content_types
+            .check(item.media_type.as_deref())
+            .map_err(|e| ApiError::InvalidRequest(format!("{}: {}", item.path, e)))?;
+    }
 
-    // ===== GOVERNANCE ENFORCEMENT =====
-    
-    // Check branch protection rules
-    if let Some(protected_ref) = state.index.get_protected_ref(repo_info.id, &payload.r#ref).await? {
-        // Get current commit for check results
-        let current_commit = state.index.get_ref(repo_info.id, &payload.r#ref).await.ok();
-        let commit_id = current_commit.as_ref().map(|c| c.commit_id).unwrap_or(Uuid::new_v4());
-        
-        // Get check results for current commit
-        let check_results = state.index.get_check_results(repo_info.id, &payload.r#ref, commit_id).await?;
-        
-        // Evaluate policy
-        let is_admin = auth.roles.contains(&"admin".to_string());
-        let evaluation = blacklake_core::governance::PolicyEngine::evaluate_branch_protection(
-            &protected_ref,
-            commit_id,
-            &auth.sub,
-            is_admin,
-            &check_results,
-        );
-        
-        if !evaluation.allowed {
-            // Log policy violation
-            state.index.log_audit(
-                &auth.sub,
-                "policy_violation",
-                Some(&repo),
-                Some(&payload.r#ref),
-                None,
-                Some(&serde_json::json!({
-                    "policy_name": "branch_protection",
-                    "violation_reason": evaluation.reason,
-                    "required_checks": evaluation.required_checks,
-                    "missing_reviewers": evaluation.missing_reviewers
-                })),
-                None,
-            ).await?;
-            
-            return Err(ApiError::Forbidden(
-                evaluation.reason.unwrap_or_else(|| "Branch protection policy violation".to_string())
-            ));
+    let hashes: Vec<String> = items.iter().map(|item| item.sha256.clone()).collect();
+    let existing = state.index.existing_objects(&hashes).await?;
+    let plan = plan_batch_upload(&items, &existing);
+
+    let quota_warning = check_upload_quota(&state, &repo_info, plan.new_bytes).await?;
+
+    let kms_key_id = state
+        .storage
+        .kms_key_id(features.kms_key_id())
+        .map(|key| key.to_string());
+    let expires = state.storage.upload_url_ttl(repo_url_ttl_seconds(&features, RepoFeature::UploadUrlTtl)?)?;
+    let expires_at = Utc::now() + Duration::seconds(expires.as_secs() as i64);
+
+    let mut uploads = Vec::with_capacity(plan.uploads.len());
+    for &index in &plan.uploads {
+        let item = &items[index];
+        let s3_key = blacklake_storage::StorageClient::content_address_key(&item.sha256);
+        let checksum = blacklake_storage::StorageClient::checksum_sha256_base64(&item.sha256);
+        let grant = enforce_presign_constraints(&state, repo_info.id.0, "PUT", &s3_key, peer.ip(), &headers, expires_at).await?;
+
+        let content_type = item.media_type.as_deref().unwrap_or("application/octet-stream");
+        let url = state
+            .storage
+            .presign_put(&s3_key, item.size, content_type, checksum.as_deref(), kms_key_id.as_deref(), expires)
+            .await?;
+
+        let mut upload_headers = HashMap::new();
+        if let Some(checksum) = &checksum {
+            upload_headers.insert("x-amz-checksum-sha256".to_string(), checksum.clone());
         }
-    }
-    
-    // Check quota limits before processing changes
-    let quota_status = state.index.get_quota_status(repo_info.id).await?;
-    if let Some(quota) = quota_status {
-        if quota.hard_exceeded {
-            return Err(ApiError::PayloadTooLarge(
-                format!("Repository quota exceeded: {} bytes (limit: {} bytes)", 
-                    quota.current_bytes, quota.hard_limit)
-            ));
+        if let Some(kms_key_id) = &kms_key_id {
+            upload_headers.extend(blacklake_storage::StorageClient::sse_kms_headers(kms_key_id));
         }
+
+        state
+            .index
+            .upsert_object(&item.sha256, item.size as i64, item.media_type.as_deref(), &s3_key)
+            .await?;
+
+        uploads.push(BatchUploadUrl {
+            path: item.path.clone(),
+            sha256: item.sha256.clone(),
+            s3_key,
+            upload_url: url.to_string(),
+            access_token: Some(grant.access_token),
+            upload_headers,
+        });
     }
 
-    // Validate metadata against schema
-    for change in &payload.changes {
-        // Validate path
-        let _normalized_path = normalize_path(&change.path)
-            .map_err(|e| ApiError::InvalidRequest(format!("Invalid path '{}': {}", change.path, e)))?;
-        
-        // Validate metadata
-        validate_meta(&change.meta, Some("1.0"))
-            .map_err(|e| ApiError::InvalidRequest(format!("Invalid metadata for path '{}': {}", change.path, e)))?;
+    let response = Json(UploadInitBatchResponse {
+        uploads,
+        skipped: plan.skipped,
+        new_bytes: plan.new_bytes,
+        expires_at,
+        quota_warning: quota_warning.clone(),
+    })
+    .into_response();
+    Ok(with_quota_warning(response, quota_warning.as_ref()))
+}
+
+/// Attach `QUOTA_WARNING_HEADER` when the repository is past its soft quota
+fn with_quota_warning(
+    mut response: axum::response::Response,
+    warning: Option<&QuotaWarning>,
+) -> axum::response::Response {
+    if let Some(value) = warning.and_then(|w| w.header_value().parse().ok()) {
+        response.headers_mut().insert(QUOTA_WARNING_HEADER, value);
     }
+    response
+}
 
-    // Check for merge flag
-    let merge_metadata = headers.get("X-Blacklake-Merge")
-        .and_then(|h| h.to_str().ok())
-        .map(|s| s == "true")
-        .unwrap_or(false);
+async fn upload_complete(
+    State(state): State<AppState>,
+    Path(repo): Path<String>,
+    headers: HeaderMap,
+    Json(payload): Json<UploadCompleteRequest>,
+) -> ApiResult<Json<UploadCompleteResponse>> {
+    let auth = extract_auth(&state.auth_layer, &headers).await?;
 
-    // Get current commit for the reference
-    let current_commit = state.index.get_ref(repo_info.id, &payload.r#ref).await.ok();
+    // Make sure the repository exists before touching storage
+    let repo_info = state.index.get_repo_by_name(&repo).await?;
+    require_permission(&state, repo_info.id.0, &auth, Permission::Write).await?;
+
+    let (verify, completed) = complete_multipart(&state.storage, payload).await?;
 
-    // Create new commit
-    let commit = state
+    state
         .index
-        .create_commit(
-            repo_info.id,
-            current_commit.as_ref().map(|r| r.commit_id),
+        .append_audit_log(
             &auth.sub,
-            payload.message.as_deref(),
-            payload.expected_parent,
+            "upload_complete",
+            Some(&repo),
+            None,
+            None,
+            Some(json!({"s3_key": verify.staging_key, "parts": completed.parts.len()})),
+            Some(json!({"etag": completed.etag})),
         )
         .await?;
 
-    // Prepare changes with merged metadata
-    let mut final_changes = Vec::new();
-    for change in &payload.changes {
-        let mut final_change = change.clone();
-        
-        // Handle metadata merging for existing entries
-        if merge_metadata && (change.op == ChangeOp::Modify || change.op == ChangeOp::Meta) {
-            if let Some(current_commit) = &current_commit {
-                // Get current metadata for the path
-                if let Ok(current_entries) = state.index.get_entries(current_commit.commit_id, Some(&change.path)).await {
-                    if let Some(current_entry) = current_entries.entries.first() {
-                        if let Some(current_meta) = &current_entry.meta {
-                            // Perform deep merge
-                            final_change.meta = deep_merge(current_meta, &change.meta)?;
-                        }
-                    }
-                }
-            }
-        }
-        
-        final_changes.push(final_change);
-    }
+    let s3_key = verify.s3_key.clone();
+    spawn_job(&state, Some((repo_info.id.0, &repo)), verify);
 
-    // Bind entries to commit
-    state
-        .index
-        .bind_entries(commit.id, &final_changes)
-        .await?;
+    Ok(Json(UploadCompleteResponse {
+        s3_key,
+        etag: completed.etag,
+        parts: completed
+            .parts
+            .into_iter()
+            .map(|p| UploadPart {
+                part_number: p.part_number,
+                etag: p.etag,
+            })
+            .collect(),
+    }))
+}
 
-    // Process metadata indexing and RDF generation for each change
-    for change in &final_changes {
-        if change.op == ChangeOp::Add || change.op == ChangeOp::Modify || change.op == ChangeOp::Meta {
-            // Update metadata index
-            let index_row = project_to_index(commit.id, &change.path, &change.meta);
-            state
-                .index
-                .upsert_entry_meta_index(&index_row)
-                .await?;
-
-            // Generate RDF if requested
-            if emit_rdf {
-                if let Ok(canonical_meta) = serde_json::from_value::<CanonicalMeta>(change.meta.clone()) {
-                    let subject_iri = generate_subject_iri(&repo, &payload.r#ref, &change.path);
-                    
-                    // Generate JSON-LD
-                    let jsonld = canonical_to_dc_jsonld(&subject_iri, &canonical_meta);
-                    let jsonld_text = serde_json::to_string_pretty(&jsonld)?;
-                    let jsonld_sha256 = blacklake_core::hash_bytes(jsonld_text.as_bytes());
-                    
-                    // Store JSON-LD
-                    state
-                        .index
-                        .store_artifact_rdf(
-                            commit.id,
-                            &change.path,
-                            &RdfFormat::Jsonld,
-                            &jsonld_text,
-                            &jsonld_sha256,
-                        )
-                        .await?;
-
-                    // Generate and store Turtle
-                    if let Ok(turtle_text) = canonical_to_turtle(&subject_iri, &canonical_meta) {
-                        let turtle_sha256 = blacklake_core::hash_bytes(turtle_text.as_bytes());
-                        
-                        state
-                            .index
-                            .store_artifact_rdf(
-                                commit.id,
-                                &change.path,
-                                &RdfFormat::Turtle,
-                                &turtle_text,
-                                &turtle_sha256,
-                            )
-                            .await?;
-                    }
+/// Assemble the multipart upload `upload-init` started for `payload.sha256`
+/// under its staging key, and return the job that verifies the assembled
+/// bytes and moves them to the content address.
+///
+/// The staging key must be one handed out for that sha256, and S3 lists the
+/// upload's parts only if `upload_id` was started on that key, so a caller
+/// cannot complete an upload at an arbitrary location. Client-reported parts
+/// must match what S3 received.
+async fn complete_multipart(
+    storage: &StorageClient,
+    payload: UploadCompleteRequest,
+) -> ApiResult<(VerifyUploadJob, blacklake_storage::CompletedUpload)> {
+    let sha256 = validate_sha256(&payload.sha256)
+        .map_err(|e| ApiError::InvalidRequest(format!("Invalid sha256: {}", e)))?;
+    if payload.upload_id.trim().is_empty() {
+        return Err(ApiError::InvalidRequest("upload_id is required".to_string()));
+    }
+    if !StorageClient::is_staging_key_for(&payload.staging_key, &sha256) {
+        return Err(ApiError::InvalidRequest(format!(
+            "{} is not a staging key for sha256 {}",
+            payload.staging_key, sha256
+        )));
+    }
+    let staging_key = payload.staging_key;
+
+    let uploaded = match storage.list_uploaded_parts(&staging_key, &payload.upload_id).await {
+        Ok(parts) => parts,
+        Err(e) if e.is_retryable() => return Err(e.into()),
+        Err(e) => {
+            return Err(ApiError::InvalidRequest(format!(
+                "No multipart upload {} in progress for {}: {}",
+                payload.upload_id, sha256, e
+            )))
+        }
+    };
+
+    let parts = match payload.parts {
+        Some(parts) => {
+            for part in &parts {
+                if !uploaded.iter().any(|u| u.part_number == part.part_number && u.etag.trim_matches('"') == part.etag.trim_matches('"')) {
+                    return Err(ApiError::InvalidRequest(format!(
+                        "Part {} with ETag {} was not received by storage",
+                        part.part_number, part.etag
+                    )));
                 }
             }
+            parts
+                .into_iter()
+                .map(|p| blacklake_storage::UploadedPart {
+                    part_number: p.part_number,
+                    etag: p.etag,
+                })
+                .collect()
         }
+        None => uploaded,
+    };
+
+    if parts.is_empty() {
+        return Err(ApiError::InvalidRequest("No uploaded parts to complete".to_string()));
     }
 
-    // Update reference
+    let completed = storage
+        .complete_multipart_upload(&staging_key, &payload.upload_id, &parts)
+        .await?;
+    let verify = VerifyUploadJob {
+        s3_key: StorageClient::content_address_key(&sha256),
+        sha256,
+        staging_key: Some(staging_key),
+    };
+    Ok((verify, completed))
+}
+
+/// Confirm an uploaded object matches the sha256 given to `upload-init`.
+///
+/// S3 checked single PUTs against the signed checksum as they were stored,
+/// so a HEAD settles those. Objects without a whole-object sha256 are
+/// re-hashed by a background job that deletes them on mismatch; multipart
+/// uploads are already being checked under their staging key by the job
+/// `upload-complete` started.
+async fn upload_verify(
+    State(state): State<AppState>,
+    Path(repo): Path<String>,
+    headers: HeaderMap,
+    Json(payload): Json<UploadVerifyRequest>,
+) -> ApiResult<Json<UploadVerifyResponse>> {
+    let auth = extract_auth(&state.auth_layer, &headers).await?;
+
+    let repo_info = state.index.get_repo_by_name(&repo).await?;
+    require_permission(&state, repo_info.id.0, &auth, Permission::Write).await?;
+
+    let sha256 = validate_sha256(&payload.sha256)
+        .map_err(|e| ApiError::InvalidRequest(format!("Invalid sha256: {}", e)))?;
+    let s3_key = StorageClient::content_address_key(&sha256);
+    let head = match state.storage.head_object(&s3_key).await? {
+        Some(head) => head,
+        None if state.storage.prefix_exists(&StorageClient::staging_prefix(&sha256)).await? => {
+            let signature = record_upload_signature(&state, &sha256, payload.signature.as_ref()).await?;
+            return Ok(Json(UploadVerifyResponse {
+                sha256,
+                verification: UploadVerification::Scheduled,
+                signature,
+            }));
+        }
+        None => return Err(ApiError::InvalidRequest(format!("No upload found for sha256 {}", sha256))),
+    };
+
+    let expected_size = state.index.get_object(&sha256).await?.map(|object| object.size);
+    let expected_checksum = StorageClient::checksum_sha256_base64(&sha256);
+    let mismatch = match (expected_size, head.checksum_sha256.as_deref()) {
+        (Some(size), _) if size != head.content_length => {
+            Some(format!("expected {} bytes, found {}", size, head.content_length))
+        }
+        // Multipart checksums end in -N and cover the parts, not the object
+        (_, Some(checksum)) if !checksum.contains('-') && Some(checksum) != expected_checksum.as_deref() => {
+            Some(format!("stored checksum is {}", checksum))
+        }
+        _ => None,
+    };
+    if let Some(reason) = mismatch {
+        // The content address is shared: bytes an earlier upload verified, or
+        // that a commit already references, are not this upload's to delete
+        if !state.index.discard_unverified_object(&sha256).await? {
+            error!("Object {} no longer matches its sha256 ({}) but is verified or in use; kept", sha256, reason);
+            return Err(ApiError::InvalidRequest(format!(
+                "Upload does not match sha256 {}: {}; the stored object is verified or in use and was kept",
+                sha256, reason
+            )));
+        }
+        state.storage.delete_object(&s3_key, None).await?;
+        return Err(ApiError::InvalidRequest(format!(
+            "Upload does not match sha256 {}: {}; the object was deleted",
+            sha256, reason
+        )));
+    }
+    BYTES_UPLOADED_TOTAL.with_label_values(&[repo.as_str()]).inc_by(head.content_length as f64);
+
+    let signature = record_upload_signature(&state, &sha256, payload.signature.as_ref()).await?;
+
+    let verification = match head.checksum_sha256.as_deref() {
+        Some(checksum) if !checksum.contains('-') => {
+            state.index.mark_object_verified(&sha256).await?;
+            UploadVerification::Verified
+        }
+        _ => {
+            let job = VerifyUploadJob { sha256: sha256.clone(), s3_key: s3_key.clone(), staging_key: None };
+            spawn_job(&state, Some((repo_info.id.0, &repo)), job);
+            UploadVerification::Scheduled
+        }
+    };
+
+    Ok(Json(UploadVerifyResponse { sha256, verification, signature }))
+}
+
+/// Keep a detached signature next to the object and report whether it is
+/// trusted; commits check it again
+async fn record_upload_signature(
+    state: &AppState,
+    sha256: &str,
+    bundle: Option<&blacklake_core::signing::SignatureBundle>,
+) -> ApiResult<Option<blacklake_core::signing::SignatureVerification>> {
+    let Some(bundle) = bundle else {
+        return Ok(None);
+    };
     state
+        .signature_verifier
+        .store_signature(sha256, bundle)
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+    Ok(Some(state.signature_verifier.verify_bundle(sha256, bundle)))
+}
+
+// Commit endpoints
+
+/// Schema collection and version a repository's commits are validated against.
+///
+/// The collection comes from the "schema" feature (default "default"). Pin a
+/// version with the "schema_version" feature; otherwise the collection's
+/// current schema is used.
+async fn resolve_repo_schema(state: &AppState, repo_id: Uuid) -> ApiResult<(String, String)> {
+    let features = state.index.get_repo_features(repo_id).await?;
+    let schema_collection = features.schema().to_string();
+    let schema_version = match features.schema_version() {
+        Some(version) => version.to_string(),
+        None => state.schema_registry.get_schema(&schema_collection)
+            .map(|schema| schema.version.clone())
+            .ok_or_else(|| ApiError::InvalidRequest(format!("Schema not found: {}", schema_collection)))?,
+    };
+    Ok((schema_collection, schema_version))
+}
+
+/// Run a commit's path and schema checks without writing anything
+async fn validate_commit(
+    State(state): State<AppState>,
+    Path(repo): Path<String>,
+    headers: HeaderMap,
+    Json(payload): Json<CommitRequest>,
+) -> ApiResult<Json<ValidateCommitResponse>> {
+    let auth = extract_auth(&state.auth_layer, &headers).await?;
+
+    let repo_info = state.index.get_repo_by_name(&repo).await?;
+    require_permission(&state, repo_info.id.0, &auth, Permission::Write).await?;
+
+    let (schema_collection, schema_version) = resolve_repo_schema(&state, repo_info.id.0).await?;
+    let errors = validate_changes(&state.schema_registry, &schema_collection, &schema_version, &payload.changes)
+        .map_err(|e| ApiError::InvalidRequest(e.to_string()))?;
+
+    Ok(Json(ValidateCommitResponse {
+        valid: errors.is_empty(),
+        errors,
+    }))
+}
+
+/// Three-way merge `from_ref` into `into_ref`.
+///
+/// Conflicts are returned with 409 and nothing is committed unless
+/// `?strategy=ours|theirs` says how to settle them.
+async fn merge_refs(
+    State(state): State<AppState>,
+    Path(repo): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    Json(payload): Json<MergeRequest>,
+) -> ApiResult<axum::response::Response> {
+    let auth = extract_auth(&state.auth_layer, &headers).await?;
+
+    let repo_info = state.index.get_repo_by_name(&repo).await?;
+    require_permission(&state, repo_info.id.0, &auth, Permission::Write).await?;
+
+    let strategy = params
+        .get("strategy")
+        .map(|s| s.parse::<MergeStrategy>())
+        .transpose()
+        .map_err(ApiError::InvalidRequest)?;
+
+    let (schema_collection, schema_version) = resolve_repo_schema(&state, repo_info.id.0).await?;
+    merge_into(
+        &state.commit_gate(),
+        &state.schema_registry,
+        (&schema_collection, &schema_version),
+        (repo_info.id.0, &repo),
+        &auth,
+        strategy,
+        &payload,
+    )
+    .await
+}
+
+/// Merge `payload.from_ref` into `payload.into_ref`, committing through the
+/// same gate as an ordinary commit to that ref
+async fn merge_into(
+    gate: &commit_plan::CommitGate<'_>,
+    registry: &SchemaRegistry,
+    (schema_collection, schema_version): (&str, &str),
+    (repo_id, repo): (Uuid, &str),
+    auth: &AuthContext,
+    strategy: Option<MergeStrategy>,
+    payload: &MergeRequest,
+) -> ApiResult<axum::response::Response> {
+    let theirs_head = gate.index.get_ref(repo_id, &payload.from_ref).await?.commit_id.0;
+    let ours_head = gate.index.get_ref(repo_id, &payload.into_ref).await?.commit_id.0;
+    let base = gate.index.find_merge_base(ours_head, theirs_head).await?;
+
+    // Everything on from_ref is already on into_ref
+    if base == Some(theirs_head) {
+        return Ok(Json(MergeResponse {
+            merged: false,
+            commit_id: None,
+            base_commit_id: base,
+            changes: 0,
+            conflicts: Vec::new(),
+        })
+        .into_response());
+    }
+
+    let base_sides = match base {
+        Some(base) => gate.index.get_merge_sides(base).await?,
+        None => HashMap::new(),
+    };
+    let ours = gate.index.get_merge_sides(ours_head).await?;
+    let theirs = gate.index.get_merge_sides(theirs_head).await?;
+    let plan = three_way_merge(&base_sides, &ours, &theirs, strategy)
+        .map_err(|e| ApiError::Internal(format!("Merge failed: {}", e)))?;
+
+    if !plan.conflicts.is_empty() && strategy.is_none() {
+        let report = MergeResponse {
+            merged: false,
+            commit_id: None,
+            base_commit_id: base,
+            changes: plan.changes.len(),
+            conflicts: plan.conflicts,
+        };
+        return Ok((StatusCode::CONFLICT, Json(report)).into_response());
+    }
+
+    // Merged metadata must still satisfy the repository's schema
+    let errors = validate_changes(registry, schema_collection, schema_version, &plan.changes)
+        .map_err(|e| ApiError::InvalidRequest(e.to_string()))?;
+    if !errors.is_empty() {
+        return Ok((StatusCode::UNPROCESSABLE_ENTITY, Json(ValidateCommitResponse { valid: false, errors })).into_response());
+    }
+
+    let request = CommitRequest {
+        r#ref: payload.into_ref.clone(),
+        message: Some(
+            payload
+                .message
+                .clone()
+                .unwrap_or_else(|| format!("Merge {} into {}", payload.from_ref, payload.into_ref)),
+        ),
+        changes: plan.changes,
+        expected_parent: Some(ours_head.into()),
+    };
+    let mut changes = request.changes.clone();
+    gate.enforce((repo_id, repo), &request, Some(ours_head), auth, &mut changes).await?;
+
+    let (commit, _stats) = gate
         .index
-        .set_ref(
-            repo_info.id,
-            &payload.r#ref,
-            blacklake_core::ReferenceKind::Branch,
-            commit.id,
+        .commit_atomic(&CommitWrite {
+            repo_id,
+            ref_name: &payload.into_ref,
+            author: &auth.sub,
+            message: request.message.as_deref(),
+            expected_parent: Some(ours_head),
+            changes: &changes,
+            schema: Some((schema_collection, schema_version)),
+            merge_parent: Some(theirs_head),
+        })
+        .await?;
+
+    gate.index
+        .append_audit_log(
+            &auth.sub,
+            "merge",
+            Some(repo),
+            Some(&payload.into_ref),
+            None,
+            Some(json!({"from_ref": payload.from_ref, "strategy": strategy})),
+            Some(json!({"commit_id": commit.id, "conflicts_resolved": plan.conflicts.len()})),
         )
         .await?;
 
-    // ===== POST-COMMIT GOVERNANCE ACTIONS =====
+    Ok(Json(MergeResponse {
+        merged: true,
+        commit_id: Some(commit.id.0),
+        base_commit_id: base,
+        changes: changes.len(),
+        conflicts: plan.conflicts,
+    })
+    .into_response())
+}
+
+async fn commit(
+    State(state): State<AppState>,
+    Path(repo): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    StreamingJson(payload): StreamingJson<CommitRequest>,
+) -> ApiResult<axum::response::Response> {
+    // With an Idempotency-Key, a retried commit gets the first one's response
+    let Some(key) = idempotency::idempotency_key(&headers)? else {
+        return run_commit(state, repo, params, headers, payload).await;
+    };
+    let auth = extract_auth(&state.auth_layer, &headers).await?;
+    let repo_info = state.index.get_repo_by_name(&repo).await?;
+    let call = idempotency::IdempotentCall {
+        repo_id: repo_info.id.0,
+        endpoint: "commit",
+        key: &key,
+        request_sha256: idempotency::request_fingerprint(&auth.sub, &(params.iter().collect::<BTreeMap<_, _>>(), &payload))?,
+    };
+    idempotency::run_once(&state.index, &call, run_commit(state.clone(), repo, params, headers, payload)).await
+}
+
+async fn run_commit(
+    state: AppState,
+    repo: String,
+    params: HashMap<String, String>,
+    headers: HeaderMap,
+    payload: CommitRequest,
+) -> ApiResult<axum::response::Response> {
+    let auth = extract_auth(&state.auth_layer, &headers).await?;
+
+    // Implement commit message validation and sanitization
+    let sanitized_message = validate_and_sanitize_commit_message(&payload.message)?;
     
+    // Implement commit size limits and validation
+    let total_commit_size = calculate_commit_size(&payload.changes)?;
+    validate_commit_size(total_commit_size)?;
+    
+    // Check for RDF emission flag
+    let emit_rdf = params.get("emit_rdf")
+        .map(|v| v == "true")
+        .unwrap_or(false);
+    let dry_run = params.get("dry_run")
+        .map(|v| v == "true")
+        .unwrap_or(false);
+
+    // Get repository
+    let repo_info = state.index.get_repo_by_name(&repo).await?;
+    require_permission(&state, repo_info.id.0, &auth, Permission::Write).await?;
+
+    // Check for merge flag
+    let merge_metadata = headers.get("X-Blacklake-Merge")
+        .and_then(|h| h.to_str().ok())
+        .map(|s| s == "true")
+        .unwrap_or(false);
+
+    // Dry run: report what the commit would do and what would stop it, writing nothing
+    if dry_run {
+        virtual_repo::ensure_writable(&state.index, repo_info.id.0, &repo).await?;
+        let (schema_collection, schema_version) = resolve_repo_schema(&state, repo_info.id.0).await?;
+        let plan = commit_plan::plan_commit(
+            &state.index,
+            state.policy_backend.as_ref(),
+            &state.schema_registry,
+            (&schema_collection, &schema_version),
+            repo_info.id.0,
+            &auth,
+            &payload,
+            merge_metadata,
+        )
+        .await?;
+        let CommitPlan { parent_id, mut changes, errors, mut blocking_reasons, .. } = plan;
+        let features = state.index.get_repo_features(repo_info.id.0).await?;
+        let content_types = ContentTypePolicy::from_features(&features);
+        blocking_reasons.extend(
+            commit_plan::check_content_types(&state.index, &state.storage, &content_types, &changes).await?,
+        );
+        blocking_reasons.extend(
+            commit_plan::check_signatures(
+                &state.index,
+                &state.signature_verifier,
+                &mut changes,
+                features.require_signed_models(),
+            )
+            .await?,
+        );
+        return Ok(Json(CommitPlan::new(parent_id, changes, errors, blocking_reasons)).into_response());
+    }
+
+    // Get current commit for the reference
+    let current_commit = state.index.get_ref(repo_info.id, &payload.r#ref).await.ok();
+    let head = current_commit.as_ref().map(|c| c.commit_id.0);
+
+    // Prepare changes with merged metadata
+    let mut final_changes = commit_plan::resolve_changes(&state.index, head, &payload.changes, merge_metadata).await?;
+
+    // ===== GOVERNANCE ENFORCEMENT =====
+    state
+        .commit_gate()
+        .enforce((repo_info.id.0, &repo), &payload, head, &auth, &mut final_changes)
+        .await?;
+    let features = state.index.get_repo_features(repo_info.id.0).await?;
+
+    // Validate metadata against the repository's configured schema
+    let (schema_collection, schema_version) = resolve_repo_schema(&state, repo_info.id.0).await?;
+
+    // Refs protected with require_schema_pass check every change up front and
+    // record the outcome as the `schema` check
+    let schema_gate = commit_plan::enforce_schema_check(
+        &state.index,
+        &state.schema_registry,
+        (&schema_collection, &schema_version),
+        repo_info.id.0,
+        &payload.r#ref,
+        head,
+        &payload.changes,
+    )
+    .await?;
+
+    for change in &payload.changes {
+        // Validate path
+        let _normalized_path = normalize_path(&change.path)
+            .map_err(|e| ApiError::InvalidRequest(format!("Invalid path '{}': {}", change.path, e)))?;
+        
+        // Validate metadata
+        validate_meta(&change.meta, Some("1.0"))
+            .map_err(|e| ApiError::InvalidRequest(format!("Invalid metadata for path '{}': {}", change.path, e)))?;
+
+        if change.op != ChangeOp::Delete {
+            validate_metadata(&state.schema_registry, &schema_collection, &schema_version, &change.path, &change.meta)?;
+        }
+    }
+
+    // Commit, entries, metadata index and ref land together or not at all
+    let (commit, stats) = state
+        .index
+        .commit_atomic(&CommitWrite {
+            repo_id: repo_info.id.0,
+            ref_name: &payload.r#ref,
+            author: &auth.sub,
+            message: payload.message.as_deref(),
+            expected_parent: payload.expected_parent,
+            changes: &final_changes,
+            schema: Some((&schema_collection, &schema_version)),
+            merge_parent: None,
+        })
+        .await?;
+
+    // ===== POST-COMMIT ACTIONS =====
+    // These run after the commit is durable and are safe to repeat: RDF rows
+    // upsert on (commit, path, format) and webhook deliveries have stable ids
+
+    if let Some(gate) = &schema_gate {
+        gate.record_pass(&state.index, commit.id.0).await?;
+    }
+
+    // Generate RDF for each change if requested
+    let rdf_base = repo_rdf_base(&state, &features)?;
+    for change in &final_changes {
+        if emit_rdf && change.op != ChangeOp::Delete {
+            if let Ok(canonical_meta) = serde_json::from_value::<CanonicalMeta>(change.meta.clone()) {
+                let subject_iri = generate_subject_iri(&rdf_base, &repo, &payload.r#ref, &change.path);
+                
+                // Generate JSON-LD
+                let jsonld = canonical_to_dc_jsonld(&subject_iri, &canonical_meta);
+                let jsonld_text = serde_json::to_string_pretty(&jsonld)?;
+                let jsonld_sha256 = blacklake_core::hash_bytes(jsonld_text.as_bytes());
+                
+                // Store JSON-LD
+                state
+                    .index
+                    .store_artifact_rdf(
+                        commit.id,
+                        &change.path,
+                        &RdfFormat::Jsonld,
+                        &jsonld_text,
+                        &jsonld_sha256,
+                    )
+                    .await?;
+
+                // Generate and store the triple-based serializations
+                let serializations = [
+                    (RdfFormat::Turtle, canonical_to_turtle(&subject_iri, &canonical_meta)),
+                    (RdfFormat::NTriples, canonical_to_ntriples(&subject_iri, &canonical_meta)),
+                    (RdfFormat::RdfXml, canonical_to_rdfxml(&subject_iri, &canonical_meta)),
+                ];
+                for (format, rendered) in serializations {
+                    if let Ok(graph_text) = rendered {
+                        let graph_sha256 = blacklake_core::hash_bytes(graph_text.as_bytes());
+
+                        state
+                            .index
+                            .store_artifact_rdf(
+                                commit.id,
+                                &change.path,
+                                &format,
+                                &graph_text,
+                                &graph_sha256,
+                            )
+                            .await?;
+                    }
+                }
+            }
+        }
+    }
+
+    // Clients often declare octet-stream; detect the real type from the new objects
+    for change in &final_changes {
+        if let (ChangeOp::Add | ChangeOp::Modify, Some(sha256)) = (&change.op, &change.sha256) {
+            let job = SniffMediaTypeJob { commit_id: commit.id.0, path: change.path.clone(), sha256: sha256.clone() };
+            spawn_job(&state, Some((repo_info.id.0, &repo)), job);
+        }
+    }
+
+    let features = state.index.get_repo_features(repo_info.id.0).await?;
+
+    // Previews for pictures and PDFs, unless the repo turns them off; the job
+    // skips anything it can't render
+    for change in &final_changes {
+        let declared = change.meta.get("file_type").and_then(|v| v.as_str());
+        let previewable = features.thumbnails_enabled()
+            && blacklake_core::thumbnail::preview_source(declared, &change.path).is_some();
+        if let (ChangeOp::Add | ChangeOp::Modify, Some(sha256), true) = (&change.op, &change.sha256, previewable) {
+            let job = ThumbnailJob { commit_id: commit.id.0, path: change.path.clone(), sha256: sha256.clone() };
+            spawn_job(&state, Some((repo_info.id.0, &repo)), job);
+        }
+    }
+
+    // Repos with the parquet_conversion feature get a Parquet copy of each committed CSV
+    if features.parquet_conversion() {
+        for change in &final_changes {
+            let is_csv = change.path.to_ascii_lowercase().ends_with(".csv")
+                || change.meta.get("file_type").and_then(|v| v.as_str()) == Some("text/csv");
+            if let (ChangeOp::Add | ChangeOp::Modify, Some(sha256), true) = (&change.op, &change.sha256, is_csv) {
+                let job = ConvertToParquetJob { commit_id: commit.id.0, path: change.path.clone(), sha256: sha256.clone() };
+                spawn_job(&state, Some((repo_info.id.0, &repo)), job);
+            }
+        }
+    }
+
+    // Embeddings of new and changed entries feed semantic search
+    for change in &final_changes {
+        let object = match &change.sha256 {
+            Some(sha256) if matches!(change.op, ChangeOp::Add | ChangeOp::Modify) => state.index.get_object(sha256).await?,
+            _ => None,
+        };
+        if let Some(job) = EmbedEntryJob::for_change(repo_info.id.0, commit.id.0, change, object.as_ref()) {
+            spawn_job(&state, Some((repo_info.id.0, &repo)), job);
+        }
+    }
+
     // Update repository usage
     let mut total_size_change: i64 = 0;
     for change in &final_changes {
@@ -825,6 +1937,13 @@ async fn commit(
             state.index.update_repo_usage(repo_info.id, new_usage).await?;
         }
     }
+
+    // Warn once the committed usage is past the soft quota
+    let quota_warning = state
+        .index
+        .get_quota_status(repo_info.id)
+        .await?
+        .and_then(|quota| quota.soft_warning_after(0));
     
     // Trigger webhooks for commit events
     let webhooks = state.index.get_webhooks(repo_info.id).await?;
@@ -842,7 +1961,7 @@ async fn commit(
             };
             
             let delivery = blacklake_core::governance::WebhookDelivery {
-                id: Uuid::new_v4(),
+                id: blacklake_core::governance::WebhookDelivery::idempotent_id(webhook.id, "commit.created", commit.id.0),
                 webhook_id: webhook.id,
                 event_type: "commit.created".to_string(),
                 payload: serde_json::to_value(&payload)?,
@@ -858,87 +1977,455 @@ async fn commit(
         }
     }
 
-    // Log audit
+    COMMITS_TOTAL.with_label_values(&[repo.as_str()]).inc();
+    state.events.publish(events::commit_created(&repo, &payload.r#ref, &commit, final_changes.len()));
+
+    // Log audit
+    state
+        .index
+        .append_audit_log(
+            &auth.sub,
+            "commit",
+            Some(&repo),
+            Some(&payload.r#ref),
+            None,
+            Some(json!({"changes": payload.changes.len()})),
+            Some(json!({"commit_id": commit.id})),
+        )
+        .await?;
+
+    let response = Json(CommitResponse {
+        commit_id: commit.id,
+        parent_id: commit.parent_id,
+        created_at: commit.created_at,
+        stats: Some(stats),
+        quota_warning: quota_warning.clone(),
+    })
+    .into_response();
+    Ok(with_quota_warning(response, quota_warning.as_ref()))
+}
+
+// Blob endpoints
+
+async fn get_blob(
+    State(state): State<AppState>,
+    Path((repo, r#ref, path)): Path<(String, String, String)>,
+    Query(params): Query<HashMap<String, String>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> ApiResult<axum::response::Response> {
+    let _auth = extract_auth(&state.auth_layer, &headers).await?;
+
+    // raw=true streams the bytes instead of returning a presigned URL
+    let raw = params.get("raw").map(|v| v == "true").unwrap_or(false);
+
+    // Get repository
+    let repo_info = state.index.get_repo_by_name(&repo).await?;
+
+    // Connector-backed repos have no refs; the connector hands out the URL
+    if let Some(source) = state.index.get_repo_connector(repo_info.id.0).await? {
+        if raw {
+            return Err(ApiError::InvalidRequest("raw=true is not supported for connector-backed repositories".to_string()));
+        }
+        let features = state.index.get_repo_features(repo_info.id.0).await?;
+        let expires = state.storage.download_url_ttl(repo_url_ttl_seconds(&features, RepoFeature::DownloadUrlTtl)?)?;
+        let connector = virtual_repo::open_connector(&source).await?;
+        let blob = virtual_repo::get_blob(connector.as_ref(), &path, expires.as_secs() as u32).await?;
+        state
+            .index
+            .append_audit_log(
+                &_auth.sub,
+                "blob_access",
+                Some(&repo),
+                Some(&r#ref),
+                Some(&path),
+                None,
+                Some(json!({"connector_id": source.connector_id, "raw": false})),
+            )
+            .await?;
+        return Ok(Json(blob).into_response());
+    }
+
+    // Get reference
+    let ref_info = state.index.get_ref(repo_info.id, &r#ref).await?;
+
+    // Get tree entries for the commit
+    let entries = state
+        .index
+        .get_tree_entries(ref_info.commit_id, Some(&path))
+        .await?;
+
+    if entries.is_empty() {
+        return Err(ApiError::Repo(format!("Path not found: {}", path)));
+    }
+
+    let entry = &entries[0];
+    if let Some(sha256) = &entry.object_sha256 {
+        // Content addressing makes the sha256 a strong ETag
+        let etag = strong_etag(sha256);
+        if if_none_match(&headers, &etag) {
+            return Ok(not_modified(&etag));
+        }
+
+        // Generate presigned URL for download
+        let s3_key = blacklake_storage::StorageClient::content_address_key(sha256);
+
+        // Don't hand out a URL for an object that was never uploaded
+        let head = state
+            .storage
+            .head_object(&s3_key)
+            .await?
+            .ok_or_else(|| ApiError::Repo(format!("Object missing from storage for path: {}", path)))?;
+
+        // Log audit
+        state
+            .index
+            .append_audit_log(
+                &_auth.sub,
+                "blob_access",
+                Some(&repo),
+                Some(&r#ref),
+                Some(&path),
+                None,
+                Some(json!({"sha256": sha256, "raw": raw})),
+            )
+            .await?;
+
+        if raw {
+            // Forward Range so S3 does the slicing and we answer 206
+            let range = headers.get(axum::http::header::RANGE).and_then(|v| v.to_str().ok());
+            let object = state
+                .storage
+                .get_object_range(&s3_key, range)
+                .await?
+                .ok_or_else(|| ApiError::Repo(format!("Object missing from storage for path: {}", path)))?;
+            BYTES_DOWNLOADED_TOTAL.with_label_values(&[repo.as_str()]).inc_by(object.content_length as f64);
+            return Ok(conditional::stream_object(&etag, &path, object));
+        }
+
+        let features = state.index.get_repo_features(repo_info.id.0).await?;
+        let expires = state.storage.download_url_ttl(repo_url_ttl_seconds(&features, RepoFeature::DownloadUrlTtl)?)?;
+        let expires_at = Utc::now() + Duration::seconds(expires.as_secs() as i64);
+        let grant = enforce_presign_constraints(&state, repo_info.id.0, "GET", &s3_key, peer.ip(), &headers, expires_at).await?;
+        let download_url = state
+            .storage
+            .presign_get(&s3_key, expires)
+            .await?;
+        BYTES_DOWNLOADED_TOTAL.with_label_values(&[repo.as_str()]).inc_by(head.content_length as f64);
+
+        let mut response = Json(json!({
+            "download_url": download_url.to_string(),
+            "sha256": sha256,
+            "path": path,
+            "size": head.content_length,
+            "media_type": head.content_type,
+            "etag": head.etag,
+            "meta": entry.meta,
+            "expires_at": expires_at,
+            "constraints_applied": grant.constraints_applied,
+            "access_token": grant.access_token
+        }))
+        .into_response();
+        if let Ok(value) = etag.parse() {
+            response.headers_mut().insert(axum::http::header::ETAG, value);
+        }
+        Ok(response)
+    } else {
+        Err(ApiError::Repo(format!("No object found for path: {}", path)))
+    }
+}
+
+// Tree endpoints
+
+async fn list_refs(
+    State(state): State<AppState>,
+    Path(repo): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> ApiResult<Json<Vec<Reference>>> {
+    let _auth = extract_auth(&state.auth_layer, &headers).await?;
+
+    let kind = match params.get("kind").map(|k| k.as_str()) {
+        None => None,
+        Some("branch") => Some(ReferenceKind::Branch),
+        Some("tag") => Some(ReferenceKind::Tag),
+        Some(other) => {
+            return Err(ApiError::InvalidRequest(format!("Invalid reference kind: {}", other)))
+        }
+    };
+
+    let repo_info = state.index.get_repo_by_name(&repo).await?;
+    let refs = state.index.list_refs(repo_info.id.0, kind).await?;
+
+    Ok(Json(refs))
+}
+
+/// Tag a branch, tag or commit.
+///
+/// Existing tags are immutable; only admins may move one with `force`.
+/// Commit a branch, tag or commit id in the repository points at
+async fn resolve_ref_target(state: &AppState, repo_id: Uuid, target: &str) -> ApiResult<Uuid> {
+    match state.index.get_ref(repo_id, target).await {
+        Ok(reference) => Ok(reference.commit_id.0),
+        Err(IndexError::RefNotFound(_)) => {
+            let commit = match Uuid::parse_str(target) {
+                Ok(id) => state.index.get_commit(id).await?,
+                Err(_) => return Err(ApiError::Repo(format!("Reference not found: {}", target))),
+            };
+            if commit.repo_id.0 != repo_id {
+                return Err(ApiError::Repo(format!("Commit not found in repository: {}", target)));
+            }
+            Ok(commit.id.0)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Refuse a move or delete of a protected ref, recording the violation
+async fn enforce_ref_protection(
+    state: &AppState,
+    repo: &str,
+    repo_id: Uuid,
+    name: &str,
+    auth: &AuthContext,
+    mutation: RefMutation,
+) -> ApiResult<()> {
+    let Some(protected_ref) = state.index.get_protected_ref(repo_id, name).await? else {
+        return Ok(());
+    };
+
+    let evaluation = blacklake_core::governance::PolicyEngine::evaluate_ref_mutation(&protected_ref, mutation, is_admin(auth));
+    if evaluation.allowed {
+        return Ok(());
+    }
+
+    state.index.log_audit(
+        &auth.sub,
+        "policy_violation",
+        Some(repo),
+        Some(name),
+        None,
+        Some(&serde_json::json!({
+            "policy_name": "branch_protection",
+            "violation_reason": evaluation.reason,
+            "operation": match mutation {
+                RefMutation::Update { .. } => "ref_update",
+                RefMutation::Delete => "ref_delete",
+            },
+        })),
+        None,
+    ).await?;
+
+    Err(ApiError::Forbidden(
+        evaluation.reason.unwrap_or_else(|| "Branch protection policy violation".to_string())
+    ))
+}
+
+async fn create_tag(
+    State(state): State<AppState>,
+    Path(repo): Path<String>,
+    headers: HeaderMap,
+    Json(payload): Json<CreateTagRequest>,
+) -> ApiResult<(StatusCode, Json<Reference>)> {
+    let auth = extract_auth(&state.auth_layer, &headers).await?;
+
+    let repo_info = state.index.get_repo_by_name(&repo).await?;
+    require_permission(&state, repo_info.id.0, &auth, Permission::Write).await?;
+
+    if payload.force && !is_admin(&auth) {
+        return Err(ApiError::Forbidden("Only admins may move an existing tag".to_string()));
+    }
+
+    let commit_id = resolve_ref_target(&state, repo_info.id.0, &payload.target).await?;
+
+    let tag = state
+        .index
+        .create_tag(repo_info.id.0, &payload.name, commit_id, &auth.sub, payload.message.as_deref(), payload.force)
+        .await?;
+
+    state
+        .index
+        .append_audit_log(&auth.sub, "tag_create", Some(&repo), Some(&payload.name), None, None, None)
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(tag)))
+}
+
+/// Dedup statistics: logical bytes across all commits versus bytes actually stored
+async fn get_repo_stats(
+    State(state): State<AppState>,
+    Path(repo): Path<String>,
+    headers: HeaderMap,
+) -> ApiResult<Json<DedupStats>> {
+    let auth = extract_auth(&state.auth_layer, &headers).await?;
+
+    let repo_info = state.index.get_repo_by_name(&repo).await?;
+    require_permission(&state, repo_info.id.0, &auth, Permission::Read).await?;
+
+    Ok(Json(state.index.repo_dedup_stats(repo_info.id.0).await?))
+}
+
+/// A repository's feature flags as stored, with their version in
+/// `FEATURES_VERSION_HEADER`
+async fn get_repo_features(
+    State(state): State<AppState>,
+    Path(repo): Path<String>,
+    headers: HeaderMap,
+) -> ApiResult<Response> {
+    let auth = extract_auth(&state.auth_layer, &headers).await?;
+
+    let repo_info = state.index.get_repo_by_name(&repo).await?;
+    require_permission(&state, repo_info.id.0, &auth, Permission::Read).await?;
+
+    let (features, version) = state.index.get_repo_features_versioned(repo_info.id.0).await?;
+    Ok(features_response(features, version))
+}
+
+/// Set one feature flag; unknown flags and values of the wrong type are a 400.
+/// With `expected_version` or `If-Match`, a write based on an outdated read is a 409.
+async fn set_repo_feature(
+    State(state): State<AppState>,
+    Path(repo): Path<String>,
+    headers: HeaderMap,
+    Json(payload): Json<SetRepoFeatureRequest>,
+) -> ApiResult<Response> {
+    let auth = extract_auth(&state.auth_layer, &headers).await?;
+
+    let repo_info = state.index.get_repo_by_name(&repo).await?;
+    require_permission(&state, repo_info.id.0, &auth, Permission::Admin).await?;
+
+    let (features, version) = write_repo_feature(&state.index, repo_info.id.0, &headers, &payload).await?;
     state
         .index
         .append_audit_log(
             &auth.sub,
-            "commit",
+            "repo_feature_set",
             Some(&repo),
-            Some(&payload.r#ref),
             None,
-            Some(json!({"changes": payload.changes.len()})),
-            Some(json!({"commit_id": commit.id})),
+            None,
+            None,
+            Some(json!({"key": payload.key, "value": payload.value})),
         )
         .await?;
 
-    Ok(Json(CommitResponse {
-        commit_id: commit.id,
-        parent_id: commit.parent_id,
-        created_at: commit.created_at,
-    }))
+    Ok(features_response(features, version))
 }
 
-// Blob endpoints
+/// Write one feature flag, conditional on the version in the body's
+/// `expected_version` or else the request's `If-Match`. Returns the features
+/// as written and their new version.
+async fn write_repo_feature(
+    index: &IndexClient,
+    repo_id: Uuid,
+    headers: &HeaderMap,
+    payload: &SetRepoFeatureRequest,
+) -> ApiResult<(RepoFeatures, i64)> {
+    let expected_version = expected_features_version(headers, payload.expected_version)?;
+    Ok(index.set_repo_feature(repo_id, &payload.key, &payload.value, expected_version).await?)
+}
 
-async fn get_blob(
+/// The features version a write is based on. `If-Match` carries the `ETag`
+/// of a previous read, which is the version in quotes.
+fn expected_features_version(headers: &HeaderMap, expected_version: Option<i64>) -> ApiResult<Option<i64>> {
+    let Some(if_match) = headers.get(axum::http::header::IF_MATCH) else {
+        return Ok(expected_version);
+    };
+    let version = if_match
+        .to_str()
+        .ok()
+        .and_then(|value| value.trim().trim_matches('"').parse::<i64>().ok())
+        .ok_or_else(|| ApiError::InvalidRequest(format!("If-Match must be a features version, got {:?}", if_match)))?;
+    match expected_version {
+        Some(expected) if expected != version => Err(ApiError::InvalidRequest(format!(
+            "expected_version {} does not match If-Match {}",
+            expected, version
+        ))),
+        _ => Ok(Some(version)),
+    }
+}
+
+fn features_response(features: RepoFeatures, version: i64) -> Response {
+    let mut response = Json(features.0).into_response();
+    response.headers_mut().insert(FEATURES_VERSION_HEADER, version.into());
+    if let Ok(etag) = format!("\"{}\"", version).parse() {
+        response.headers_mut().insert(axum::http::header::ETAG, etag);
+    }
+    response
+}
+
+/// Point a branch at a commit, creating it if needed
+async fn update_ref(
     State(state): State<AppState>,
-    Path((repo, r#ref, path)): Path<(String, String, String)>,
+    Path((repo, name)): Path<(String, String)>,
     headers: HeaderMap,
-) -> ApiResult<Json<Value>> {
-    let _auth = extract_auth(&headers).await?;
+    Json(payload): Json<UpdateRefRequest>,
+) -> ApiResult<Json<Reference>> {
+    let auth = extract_auth(&state.auth_layer, &headers).await?;
 
-    // Get repository
     let repo_info = state.index.get_repo_by_name(&repo).await?;
+    require_permission(&state, repo_info.id.0, &auth, Permission::Write).await?;
 
-    // Get reference
-    let ref_info = state.index.get_ref(repo_info.id, &r#ref).await?;
+    let commit_id = resolve_ref_target(&state, repo_info.id.0, &payload.target).await?;
 
-    // Get tree entries for the commit
-    let entries = state
+    let current = match state.index.get_ref(repo_info.id.0, &name).await {
+        Ok(current) => Some(current.commit_id.0),
+        Err(IndexError::RefNotFound(_)) => None,
+        Err(e) => return Err(e.into()),
+    };
+    if let Some(current) = current {
+        // The new commit descends from the head exactly when the head is their merge base
+        let fast_forward = state.index.find_merge_base(current, commit_id).await? == Some(current);
+        enforce_ref_protection(&state, &repo, repo_info.id.0, &name, &auth, RefMutation::Update { fast_forward }).await?;
+    }
+
+    // Protection was judged against `current`; a ref that moved since is a 409
+    state.index.compare_and_set_ref(repo_info.id.0, &name, current, commit_id).await?;
+
+    state
         .index
-        .get_tree_entries(ref_info.commit_id, Some(&path))
+        .append_audit_log(
+            &auth.sub,
+            "ref_update",
+            Some(&repo),
+            Some(&name),
+            None,
+            None,
+            Some(json!({"from": current, "to": commit_id})),
+        )
         .await?;
 
-    if entries.is_empty() {
-        return Err(ApiError::Repo(format!("Path not found: {}", path)));
-    }
+    Ok(Json(state.index.get_ref(repo_info.id.0, &name).await?))
+}
 
-    let entry = &entries[0];
-    if let Some(sha256) = &entry.object_sha256 {
-        // Generate presigned URL for download
-        let s3_key = blacklake_storage::StorageClient::content_address_key(sha256);
-        let download_url = state
-            .storage
-            .presign_get(&s3_key, Duration::hours(1))
-            .await?;
+async fn delete_ref(
+    State(state): State<AppState>,
+    Path((repo, name)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> ApiResult<StatusCode> {
+    let auth = extract_auth(&state.auth_layer, &headers).await?;
 
-        // Log audit
-        state
-            .index
-            .append_audit_log(
-                &_auth.sub,
-                "blob_access",
-                Some(&repo),
-                Some(&r#ref),
-                Some(&path),
-                None,
-                Some(json!({"sha256": sha256})),
-            )
-            .await?;
+    let repo_info = state.index.get_repo_by_name(&repo).await?;
+    require_permission(&state, repo_info.id.0, &auth, Permission::Write).await?;
 
-        Ok(Json(json!({
-            "download_url": download_url.to_string(),
-            "sha256": sha256,
-            "path": path,
-            "meta": entry.meta
-        })))
-    } else {
-        Err(ApiError::Repo(format!("No object found for path: {}", path)))
+    // Protection is evaluated here, where the actor is known, so the
+    // admin override in evaluate_ref_mutation can apply
+    enforce_ref_protection(&state, &repo, repo_info.id.0, &name, &auth, RefMutation::Delete).await?;
+
+    match state.index.force_delete_ref(repo_info.id.0, &name).await {
+        Ok(()) => {}
+        Err(IndexError::RefNotFound(name)) => {
+            return Err(ApiError::Repo(format!("Reference not found: {}", name)))
+        }
+        Err(e) => return Err(e.into()),
     }
-}
 
-// Tree endpoints
+    state
+        .index
+        .append_audit_log(&auth.sub, "ref_delete", Some(&repo), Some(&name), None, None, None)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
 
 async fn get_tree(
     State(state): State<AppState>,
@@ -946,36 +2433,71 @@ async fn get_tree(
     Query(params): Query<HashMap<String, String>>,
     headers: HeaderMap,
 ) -> ApiResult<Json<TreeResponse>> {
-    let _auth = extract_auth(&headers).await?;
+    let _auth = extract_auth(&state.auth_layer, &headers).await?;
 
     // Get repository
     let repo_info = state.index.get_repo_by_name(&repo).await?;
 
+    // Get path prefix from query params; `delimiter=/` collapses subdirectories
+    let path_prefix = params.get("p");
+    let delimiter = params.get("delimiter");
+    let page_param = |name: &str| -> ApiResult<Option<u32>> {
+        params
+            .get(name)
+            .map(|raw| raw.parse().map_err(|_| ApiError::InvalidRequest(format!("Invalid {}: {}", name, raw))))
+            .transpose()
+    };
+    let limit = page_param("limit")?;
+    let offset = page_param("offset")?.unwrap_or(0);
+
+    // Connector-backed repos list the connector's source whatever the ref
+    if let Some(source) = state.index.get_repo_connector(repo_info.id.0).await? {
+        let connector = virtual_repo::open_connector(&source).await?;
+        let tree = virtual_repo::list_tree(
+            connector.as_ref(),
+            path_prefix.map(|s| s.as_str()),
+            delimiter.map(|s| s.as_str()),
+            limit,
+            offset,
+        )
+        .await?;
+        return Ok(Json(tree));
+    }
+
     // Get reference
     let ref_info = state.index.get_ref(repo_info.id, &r#ref).await?;
 
-    // Get path prefix from query params
-    let path_prefix = params.get("p");
-
     // Get tree entries
-    let entries = state
+    let (entries, total) = state
         .index
-        .get_tree_entries(ref_info.commit_id, path_prefix.map(|s| s.as_str()))
+        .list_tree(
+            ref_info.commit_id.0,
+            path_prefix.map(|s| s.as_str()),
+            delimiter.map(|s| s.as_str()),
+            limit,
+            Some(offset),
+        )
         .await?;
+    let end = offset as u64 + entries.len() as u64;
+    let next_offset = (!entries.is_empty() && end < total).then_some(end as u32);
 
+    // Size, media type and storage time come from the index's object rows
     let tree_entries: Vec<TreeEntry> = entries
         .into_iter()
         .map(|entry| TreeEntry {
+            last_modified: (!entry.is_dir).then_some(entry.created_at),
             path: entry.path,
             is_dir: entry.is_dir,
-            size: None, // TODO: get from object metadata
-            media_type: None, // TODO: get from object metadata
+            size: entry.size,
+            media_type: entry.media_type,
             meta: entry.meta,
         })
         .collect();
 
     Ok(Json(TreeResponse {
         entries: tree_entries,
+        total,
+        next_offset,
     }))
 }
 
@@ -986,29 +2508,73 @@ async fn search(
     Path(repo): Path<String>,
     Query(params): Query<HashMap<String, String>>,
     headers: HeaderMap,
-) -> ApiResult<Json<SearchResponse>> {
-    let _auth = extract_auth(&headers).await?;
+) -> ApiResult<axum::response::Response> {
+    let _auth = extract_auth(&state.auth_layer, &headers).await?;
+
+    let format: SearchFormat = params
+        .get("format")
+        .map(|f| f.parse())
+        .transpose()
+        .map_err(ApiError::InvalidRequest)?
+        .unwrap_or(SearchFormat::Json);
 
     // Get repository
     let repo_info = state.index.get_repo_by_name(&repo).await?;
 
     // Parse search parameters
     let mut filters = HashMap::new();
-    for (key, value) in params {
-        if key != "sort" && key != "limit" && key != "offset" {
-            filters.insert(key, serde_json::Value::String(value));
+    for (key, value) in &params {
+        if !matches!(key.as_str(), "q" | "sort" | "limit" | "offset" | "cursor" | "order" | "format") {
+            filters.insert(key.clone(), serde_json::Value::String(value.clone()));
         }
     }
 
-    let sort = params.get("sort").map(|s| s.as_str());
+    let requested_sort = params.get("sort").map(|s| s.as_str());
+    let features = state.index.get_repo_features(repo_info.id.0).await?;
+    let sort = search_sort(requested_sort, &features)?;
     let limit = params.get("limit").and_then(|s| s.parse().ok());
     let offset = params.get("offset").and_then(|s| s.parse().ok());
 
-    // Search entries
-    let (entries, total) = state
-        .index
-        .search_entries(repo_info.id, &filters, sort, limit, offset)
-        .await?;
+    // Search entries; any `cursor` parameter (empty for the first page) selects keyset pagination
+    let (entries, total, next_cursor) = if let Some(cursor) = params.get("cursor") {
+        if requested_sort.is_some() && sort != SearchSort::CreatedAt {
+            return Err(ApiError::InvalidRequest(
+                "Cursor pagination is always by created_at; use order, or drop cursor to sort by another field".to_string(),
+            ));
+        }
+        let order = match params.get("order").map(|s| s.as_str()) {
+            Some("asc") => SortOrder::Asc,
+            None | Some("desc") => SortOrder::Desc,
+            Some(other) => return Err(ApiError::InvalidRequest(format!("Invalid order: {}. Use 'asc' or 'desc'", other))),
+        };
+        let cursor = Some(cursor.as_str()).filter(|c| !c.is_empty());
+
+        let (entries, next_cursor) = match state
+            .index
+            .search_entries_keyset(repo_info.id.0, &filters, &order, cursor, limit)
+            .await
+        {
+            Err(IndexError::InvalidCursor(_)) => {
+                return Err(ApiError::InvalidRequest("Invalid search cursor".to_string()));
+            }
+            result => result?,
+        };
+        let total = state.index.count_search_entries(repo_info.id.0, &filters).await?;
+        (entries, total, next_cursor)
+    } else if let Some(q) = params.get("q") {
+        // Free-text search over the Postgres metadata index, most relevant first
+        let (entries, total) = state
+            .index
+            .search_entries_fulltext(repo_info.id.0, q, limit, offset)
+            .await?;
+        (entries, total, None)
+    } else {
+        let (entries, total) = state
+            .index
+            .search_entries(repo_info.id.0, &filters, sort, limit, offset)
+            .await?;
+        (entries, total, None)
+    };
 
     // Convert entries to SearchEntry format
     let search_entries = entries.into_iter().map(|entry| {
@@ -1037,9 +2603,10 @@ async fn search(
         }
     }).collect();
 
-    Ok(Json(SearchResponse {
+    Ok(render_search(format, SearchResponse {
         entries: search_entries,
         total,
+        next_cursor,
     }))
 }
 
@@ -1051,14 +2618,21 @@ async fn get_rdf(
     Query(params): Query<HashMap<String, String>>,
     headers: HeaderMap,
 ) -> ApiResult<axum::response::Response> {
-    let _auth = extract_auth(&headers).await?;
+    let _auth = extract_auth(&state.auth_layer, &headers).await?;
 
     // Get format parameter (default to turtle)
     let format_str = params.get("format").map(|s| s.as_str()).unwrap_or("turtle");
-    let format = match format_str {
-        "turtle" => RdfFormat::Turtle,
-        "jsonld" => RdfFormat::Jsonld,
-        _ => return Err(ApiError::InvalidRequest("Invalid format. Use 'turtle' or 'jsonld'".to_string())),
+    let format: RdfFormat = format_str.parse().map_err(ApiError::InvalidRequest)?;
+    // JSON-LD is compacted against the Dublin Core context unless expanded form is asked for
+    let expand_jsonld = match params.get("jsonld").map(|s| s.as_str()) {
+        None | Some("compacted") => false,
+        Some("expanded") => true,
+        Some(other) => {
+            return Err(ApiError::InvalidRequest(format!(
+                "Invalid JSON-LD form: {}. Use 'compacted' or 'expanded'",
+                other
+            )))
+        }
     };
 
     // Get repository
@@ -1073,25 +2647,12 @@ async fn get_rdf(
         .get_artifact_rdf(ref_info.commit_id, &path, &format)
         .await?
     {
-        let content_type = match format {
-            RdfFormat::Turtle => "text/turtle",
-            RdfFormat::Jsonld => "application/ld+json",
-        };
-
-        return Ok(axum::response::Response::builder()
-            .status(StatusCode::OK)
-            .header("Content-Type", content_type)
-            .body(rdf.graph.into())
-            .unwrap());
+        return rdf_response(&headers, &format, expand_jsonld, rdf.graph, &rdf.graph_sha256);
     }
 
     // Check if auto_rdf feature is enabled
     let features = state.index.get_repo_features(repo_info.id).await?;
-    let auto_rdf = features.get("auto_rdf")
-        .and_then(|v| v.as_bool())
-        .unwrap_or(false);
-
-    if auto_rdf {
+    if features.auto_rdf() {
         // Get entry metadata and generate RDF on the fly
         let entries = state
             .index
@@ -1100,10 +2661,12 @@ async fn get_rdf(
 
         if let Some(entry) = entries.first() {
             if let Ok(canonical_meta) = serde_json::from_value::<CanonicalMeta>(entry.meta.clone()) {
-                let subject_iri = generate_subject_iri(&repo, &r#ref, &path);
+                let subject_iri = generate_subject_iri(&repo_rdf_base(&state, &features)?, &repo, &r#ref, &path);
                 
                 let rdf_text = match format {
                     RdfFormat::Turtle => canonical_to_turtle(&subject_iri, &canonical_meta)?,
+                    RdfFormat::NTriples => canonical_to_ntriples(&subject_iri, &canonical_meta)?,
+                    RdfFormat::RdfXml => canonical_to_rdfxml(&subject_iri, &canonical_meta)?,
                     RdfFormat::Jsonld => {
                         let jsonld = canonical_to_dc_jsonld(&subject_iri, &canonical_meta);
                         serde_json::to_string_pretty(&jsonld)?
@@ -1124,16 +2687,7 @@ async fn get_rdf(
                     )
                     .await?;
 
-                let content_type = match format {
-                    RdfFormat::Turtle => "text/turtle",
-                    RdfFormat::Jsonld => "application/ld+json",
-                };
-
-                return Ok(axum::response::Response::builder()
-                    .status(StatusCode::OK)
-                    .header("Content-Type", content_type)
-                    .body(rdf_text.into())
-                    .unwrap());
+                return rdf_response(&headers, &format, expand_jsonld, rdf_text, &rdf_sha256);
             }
         }
     }
@@ -1141,12 +2695,162 @@ async fn get_rdf(
     Err(ApiError::Repo(format!("RDF not found for path: {}", path)))
 }
 
+/// Respond with stored or generated RDF. With `expand_jsonld`, JSON-LD is
+/// run through the JSON-LD processor into expanded form first.
+fn rdf_response(
+    headers: &HeaderMap,
+    format: &RdfFormat,
+    expand_jsonld: bool,
+    text: String,
+    sha256: &str,
+) -> ApiResult<axum::response::Response> {
+    if !(expand_jsonld && matches!(format, RdfFormat::Jsonld)) {
+        return Ok(respond_with_etag(headers, &strong_etag(sha256), format.content_type(), text));
+    }
+
+    let compacted: serde_json::Value = serde_json::from_str(&text)
+        .map_err(|e| ApiError::Internal(format!("Stored JSON-LD is not valid JSON: {}", e)))?;
+    let expanded = blacklake_core::dc_jsonld_expand(&compacted)
+        .and_then(|doc| Ok(serde_json::to_string_pretty(&doc)?))
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+    let etag = strong_etag(&blacklake_core::hash_bytes(expanded.as_bytes()));
+    Ok(respond_with_etag(headers, &etag, format.content_type(), expanded))
+}
+
+// Sample endpoints
+
+async fn get_sample(
+    State(state): State<AppState>,
+    Path((repo, r#ref, path)): Path<(String, String, String)>,
+    headers: HeaderMap,
+) -> ApiResult<Json<EntrySample>> {
+    let _auth = extract_auth(&state.auth_layer, &headers).await?;
+
+    // Get repository
+    let repo_info = state.index.get_repo_by_name(&repo).await?;
+
+    // Get reference
+    let ref_info = state.index.get_ref(repo_info.id.0, &r#ref).await?;
+
+    // Samples are written by the sampling job after upload
+    let sample = state
+        .index
+        .get_entry_sample(ref_info.commit_id.0, &path)
+        .await?
+        .ok_or_else(|| ApiError::Repo(format!("Sample not found for path: {}", path)))?;
+
+    Ok(Json(sample))
+}
+
+/// JSON Patch between an entry's metadata in two commits.
+///
+/// `head` defaults to the ref's current commit and `base` to head's parent.
+/// A path missing from one side diffs as an empty object.
+async fn meta_diff(
+    State(state): State<AppState>,
+    Path((repo, r#ref, path)): Path<(String, String, String)>,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> ApiResult<Json<MetaDiffResponse>> {
+    let auth = extract_auth(&state.auth_layer, &headers).await?;
+
+    let repo_info = state.index.get_repo_by_name(&repo).await?;
+    require_permission(&state, repo_info.id.0, &auth, Permission::Read).await?;
+
+    let commit_param = |name: &str| -> ApiResult<Option<Uuid>> {
+        params
+            .get(name)
+            .map(|raw| Uuid::parse_str(raw).map_err(|_| ApiError::InvalidRequest(format!("Invalid {} commit: {}", name, raw))))
+            .transpose()
+    };
+
+    let head = match commit_param("head")? {
+        Some(id) => state.index.get_commit(id).await?,
+        None => {
+            let ref_info = state.index.get_ref(repo_info.id.0, &r#ref).await?;
+            state.index.get_commit(ref_info.commit_id.0).await?
+        }
+    };
+    let base_id = match commit_param("base")? {
+        Some(id) => id,
+        None => head
+            .parent_id
+            .map(|id| id.0)
+            .ok_or_else(|| ApiError::InvalidRequest("Head commit has no parent; pass base explicitly".to_string()))?,
+    };
+    let base = state.index.get_commit(base_id).await?;
+
+    if base.repo_id.0 != repo_info.id.0 || head.repo_id.0 != repo_info.id.0 {
+        return Err(ApiError::Repo(format!("Commit not found in repository: {}", repo)));
+    }
+
+    let base_meta = state.index.get_entry_meta(base.id.0, &path).await?;
+    let head_meta = state.index.get_entry_meta(head.id.0, &path).await?;
+    if base_meta.is_none() && head_meta.is_none() {
+        return Err(ApiError::Repo(format!("Path not found in either commit: {}", path)));
+    }
+
+    Ok(Json(MetaDiffResponse {
+        patch: json_patch(base_meta.as_ref(), head_meta.as_ref()),
+        path,
+        base: base.id.0,
+        head: head.id.0,
+    }))
+}
+
+/// Preview image generated for an entry by the thumbnail job
+async fn get_thumbnail(
+    State(state): State<AppState>,
+    Path((repo, r#ref, path)): Path<(String, String, String)>,
+    headers: HeaderMap,
+) -> ApiResult<axum::response::Response> {
+    let auth = extract_auth(&state.auth_layer, &headers).await?;
+
+    let repo_info = state.index.get_repo_by_name(&repo).await?;
+    require_permission(&state, repo_info.id.0, &auth, Permission::Read).await?;
+    let ref_info = state.index.get_ref(repo_info.id.0, &r#ref).await?;
+
+    let object = state
+        .index
+        .get_derived_object(ref_info.commit_id.0, &path, blacklake_core::thumbnail::THUMBNAIL_ARTIFACT_KIND)
+        .await?
+        .ok_or_else(|| ApiError::Repo(format!("No thumbnail for path: {}", path)))?;
+
+    let etag = strong_etag(&object.sha256);
+    if if_none_match(&headers, &etag) {
+        return Ok(not_modified(&etag));
+    }
+
+    let body = state
+        .storage
+        .get_object_range(&object.s3_key, None)
+        .await?
+        .ok_or_else(|| ApiError::Repo(format!("Thumbnail missing from storage for path: {}", path)))?;
+    Ok(conditional::stream_object(&etag, &format!("{}.thumbnail.png", path), body))
+}
+
 // Helper functions
 
-fn validate_metadata(meta: &Value, schema: &MetadataSchema) -> bool {
-    // TODO: Implement proper JSON Schema validation
-    // For now, just check if it's an object
-    meta.is_object()
+/// Validate entry metadata against version `version` of the schema registered for `collection`
+fn validate_metadata(
+    registry: &SchemaRegistry,
+    collection: &str,
+    version: &str,
+    path: &str,
+    meta: &Value,
+) -> ApiResult<()> {
+    let violations = registry
+        .validate_version(collection, version, meta)
+        .map_err(|e| ApiError::InvalidRequest(e.to_string()))?;
+
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(ApiError::SchemaViolation {
+            path: path.to_string(),
+            violations,
+        })
+    }
 }
 
 // Schema handlers
@@ -1154,12 +2858,17 @@ fn validate_metadata(meta: &Value, schema: &MetadataSchema) -> bool {
 async fn get_schema(
     State(state): State<AppState>,
     Path(collection): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
     headers: HeaderMap,
 ) -> ApiResult<Json<MetadataSchema>> {
-    let _auth = extract_auth(&headers).await?;
+    let _auth = extract_auth(&state.auth_layer, &headers).await?;
 
-    let schema = state.schema_registry.get_schema(&collection)
-        .ok_or_else(|| ApiError::Repo(format!("Schema not found: {}", collection)))?;
+    let schema = match params.get("version") {
+        Some(version) => state.schema_registry.get_schema_version(&collection, version)
+            .ok_or_else(|| ApiError::Repo(format!("Schema not found: {}@{}", collection, version)))?,
+        None => state.schema_registry.get_schema(&collection)
+            .ok_or_else(|| ApiError::Repo(format!("Schema not found: {}", collection)))?,
+    };
 
     Ok(Json(schema.clone()))
 }
@@ -1168,7 +2877,7 @@ async fn get_default_schema(
     State(state): State<AppState>,
     headers: HeaderMap,
 ) -> ApiResult<Json<MetadataSchema>> {
-    let _auth = extract_auth(&headers).await?;
+    let _auth = extract_auth(&state.auth_layer, &headers).await?;
 
     let schema = state.schema_registry.get_default_schema()
         .ok_or_else(|| ApiError::Repo("Default schema not found".to_string()))?;
@@ -1327,36 +3036,21 @@ fn infer_media_type_from_path(path: &str) -> String {
     }.to_string()
 }
 
-/// Implement proper JSON Schema validation
-fn validate_json_schema(data: &Value, schema: &JSONSchema) -> ApiResult<()> {
-    let validation_result = schema.validate(data);
-    
-    match validation_result {
-        Ok(_) => Ok(()),
-        Err(errors) => {
-            let error_messages: Vec<String> = errors
-                .map(|error| format!("{}: {}", error.instance_path, error.to_string()))
-                .collect();
-            
-            Err(ApiError::InvalidRequest(format!(
-                "JSON Schema validation failed: {}",
-                error_messages.join(", ")
-            )))
-        }
-    }
-}
-
-/// Validate metadata against schema
+/// Validate metadata against a named built-in schema
 fn validate_metadata_schema(metadata: &Value, schema_name: &str) -> ApiResult<()> {
-    // Get schema from registry
     let schema = get_schema_by_name(schema_name)?;
-    
-    // Compile schema
-    let compiled_schema = JSONSchema::compile(&schema)
+
+    let violations = blacklake_core::validate_json_schema(&schema, metadata)
         .map_err(|e| ApiError::InvalidRequest(format!("Invalid schema: {}", e)))?;
-    
-    // Validate metadata
-    validate_json_schema(metadata, &compiled_schema)
+
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(ApiError::SchemaViolation {
+            path: schema_name.to_string(),
+            violations,
+        })
+    }
 }
 
 /// Get schema by name from registry
@@ -1405,3 +3099,325 @@ fn get_schema_by_name(schema_name: &str) -> ApiResult<Value> {
         _ => Err(ApiError::InvalidRequest(format!("Unknown schema: {}", schema_name)))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use blacklake_index::test_support::test_index;
+
+    fn if_match(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(axum::http::header::IF_MATCH, value.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_if_match_sets_the_expected_features_version() {
+        assert_eq!(expected_features_version(&HeaderMap::new(), None).unwrap(), None);
+        assert_eq!(expected_features_version(&HeaderMap::new(), Some(3)).unwrap(), Some(3));
+        assert_eq!(expected_features_version(&if_match("\"4\""), None).unwrap(), Some(4));
+        assert_eq!(expected_features_version(&if_match("4"), Some(4)).unwrap(), Some(4));
+
+        for (headers, expected) in [(if_match("*"), None), (if_match("\"4\""), Some(3))] {
+            let err = expected_features_version(&headers, expected).unwrap_err();
+            assert_eq!(err.into_response().status(), StatusCode::BAD_REQUEST);
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_feature_write_with_stale_if_match_is_a_conflict() {
+        let index = test_index().await;
+        let repo_id = Uuid::new_v4();
+        sqlx::query("INSERT INTO repo (id, name, created_by) VALUES ($1, $2, 'test')")
+            .bind(repo_id)
+            .bind(format!("features-{}", repo_id))
+            .execute(index.pool())
+            .await
+            .unwrap();
+
+        let (_, read_version) = index.get_repo_features_versioned(repo_id).await.unwrap();
+        let etag = format!("\"{}\"", read_version);
+        let write = |value: bool| SetRepoFeatureRequest {
+            key: "auto_rdf".to_string(),
+            value: json!(value),
+            expected_version: None,
+        };
+
+        // Two writers read the same version; the first write lands
+        let (written, version) = write_repo_feature(&index, repo_id, &if_match(&etag), &write(true)).await.unwrap();
+        assert_eq!(version, read_version + 1);
+        assert_eq!(written.0["auto_rdf"], json!(true));
+
+        // The second is based on the outdated read and is refused
+        let err = write_repo_feature(&index, repo_id, &if_match(&etag), &write(false)).await.unwrap_err();
+        assert!(
+            matches!(err, ApiError::Index(IndexError::FeaturesVersionMismatch { actual, .. }) if actual == version),
+            "{:?}",
+            err
+        );
+        assert_eq!(err.into_response().status(), StatusCode::CONFLICT);
+
+        let (features, current) = index.get_repo_features_versioned(repo_id).await.unwrap();
+        assert_eq!((features.0["auto_rdf"].clone(), current), (json!(true), version));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_merge_into_a_protected_ref_is_refused() {
+        let index = test_index().await;
+        let (repo_id, base, feature) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let repo = format!("merge-{}", repo_id);
+        sqlx::query("INSERT INTO repo (id, name, created_by) VALUES ($1, $2, 'test')")
+            .bind(repo_id)
+            .bind(&repo)
+            .execute(index.pool())
+            .await
+            .unwrap();
+        for (commit_id, parent_id) in [(base, None), (feature, Some(base))] {
+            sqlx::query("INSERT INTO commit (id, repo_id, parent_id, author) VALUES ($1, $2, $3, 'test')")
+                .bind(commit_id)
+                .bind(repo_id)
+                .bind(parent_id)
+                .execute(index.pool())
+                .await
+                .unwrap();
+        }
+        sqlx::query("INSERT INTO entry (commit_id, path, meta) VALUES ($1, 'data/b.csv', $2)")
+            .bind(feature)
+            .bind(json!({
+                "creation_dt": "2024-01-01T00:00:00Z",
+                "creator": "b@example.com",
+                "file_name": "b.csv",
+                "file_type": "text/csv",
+                "file_size": 100,
+                "org_lab": "TestLab",
+                "description": "Merged sample",
+                "data_source": "lab",
+                "data_collection_method": "instrument",
+                "version": "1.0"
+            }))
+            .execute(index.pool())
+            .await
+            .unwrap();
+        for (name, commit_id) in [("main", base), ("feature", feature)] {
+            sqlx::query("INSERT INTO ref (repo_id, name, kind, commit_id) VALUES ($1, $2, 'branch', $3)")
+                .bind(repo_id)
+                .bind(name)
+                .bind(commit_id)
+                .execute(index.pool())
+                .await
+                .unwrap();
+        }
+        index
+            .set_protected_ref(&blacklake_core::governance::ProtectedRef {
+                id: Uuid::new_v4(),
+                repo_id,
+                ref_name: "main".to_string(),
+                require_admin: true,
+                allow_fast_forward: true,
+                allow_delete: false,
+                required_checks: vec![],
+                required_reviewers: 0,
+                require_schema_pass: false,
+            })
+            .await
+            .unwrap();
+
+        let s3 = aws_sdk_s3::Client::from_conf(
+            aws_sdk_s3::Config::builder()
+                .behavior_version(aws_sdk_s3::config::BehaviorVersion::latest())
+                .region(aws_sdk_s3::config::Region::new("us-east-1"))
+                .build(),
+        );
+        let storage = StorageClient::from_client(s3.clone(), "blacklake");
+        let signatures = SignatureVerifier::new(s3, "blacklake", TrustedKeys::default());
+        let gate = commit_plan::CommitGate {
+            index: &index,
+            storage: &storage,
+            policy: &blacklake_core::policy_backend::BuiltinPolicyBackend,
+            signatures: &signatures,
+        };
+        let payload = MergeRequest {
+            from_ref: "feature".to_string(),
+            into_ref: "main".to_string(),
+            message: None,
+        };
+        let auth = AuthContext { sub: "tester".to_string(), roles: vec![] };
+
+        let registry = SchemaRegistry::default();
+        let err = merge_into(&gate, &registry, ("default", "1.0"), (repo_id, &repo), &auth, None, &payload)
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.into_response().status(), StatusCode::FORBIDDEN);
+        assert_eq!(index.get_ref(repo_id, "main").await.unwrap().commit_id.0, base);
+        index.delete_repo(repo_id).await.unwrap();
+    }
+
+    /// Multipart uploads held by `stub_multipart_s3`: upload id to key and
+    /// received parts, and the objects assembled from them
+    #[derive(Default)]
+    struct MultipartStore {
+        uploads: HashMap<String, (String, std::collections::BTreeMap<i32, Vec<u8>>)>,
+        objects: HashMap<String, Vec<u8>>,
+    }
+
+    /// An S3 stand-in answering the multipart upload calls for bucket `blacklake`
+    async fn stub_multipart_s3() -> (StorageClient, Arc<std::sync::Mutex<MultipartStore>>) {
+        use axum::{body::Bytes, extract::Query, routing::any};
+        use sha2::{Digest, Sha256};
+
+        let store = Arc::new(std::sync::Mutex::new(MultipartStore::default()));
+        let shared = store.clone();
+        let app = Router::new().route(
+            "/blacklake/*key",
+            any(
+                move |method: axum::http::Method,
+                      Path(key): Path<String>,
+                      Query(query): Query<HashMap<String, String>>,
+                      body: Bytes| {
+                    let store = shared.clone();
+                    async move {
+                        let mut store = store.lock().unwrap();
+                        let xml = |body: String| ([(axum::http::header::CONTENT_TYPE, "application/xml")], body).into_response();
+                        let no_such_upload = || {
+                            (StatusCode::NOT_FOUND, "<Error><Code>NoSuchUpload</Code><Message>no such upload</Message></Error>")
+                                .into_response()
+                        };
+                        let upload_id = query.get("uploadId").cloned();
+                        let known = upload_id
+                            .as_ref()
+                            .is_some_and(|id| store.uploads.get(id).is_some_and(|(k, _)| *k == key));
+
+                        match (method.as_str(), upload_id) {
+                            ("POST", None) if query.contains_key("uploads") => {
+                                let id = format!("upload-{}", store.uploads.len() + 1);
+                                store.uploads.insert(id.clone(), (key.clone(), Default::default()));
+                                xml(format!(
+                                    "<InitiateMultipartUploadResult><Bucket>blacklake</Bucket><Key>{}</Key><UploadId>{}</UploadId></InitiateMultipartUploadResult>",
+                                    key, id
+                                ))
+                            }
+                            (_, Some(_)) if !known => no_such_upload(),
+                            ("PUT", Some(id)) => {
+                                let part_number: i32 = query["partNumber"].parse().unwrap();
+                                let etag = format!("\"{}\"", hex::encode(Sha256::digest(&body)));
+                                store.uploads.get_mut(&id).unwrap().1.insert(part_number, body.to_vec());
+                                ([(axum::http::header::ETAG, etag)], "").into_response()
+                            }
+                            ("GET", Some(id)) => {
+                                let parts: String = store.uploads[&id]
+                                    .1
+                                    .iter()
+                                    .map(|(number, bytes)| {
+                                        format!(
+                                            "<Part><PartNumber>{}</PartNumber><ETag>&quot;{}&quot;</ETag><Size>{}</Size></Part>",
+                                            number,
+                                            hex::encode(Sha256::digest(bytes)),
+                                            bytes.len()
+                                        )
+                                    })
+                                    .collect();
+                                xml(format!(
+                                    "<ListPartsResult><Bucket>blacklake</Bucket><Key>{}</Key><UploadId>{}</UploadId><IsTruncated>false</IsTruncated>{}</ListPartsResult>",
+                                    key, id, parts
+                                ))
+                            }
+                            ("POST", Some(id)) => {
+                                let (_, parts) = store.uploads.remove(&id).unwrap();
+                                store.objects.insert(key.clone(), parts.into_values().flatten().collect());
+                                xml(format!(
+                                    "<CompleteMultipartUploadResult><Bucket>blacklake</Bucket><Key>{}</Key><ETag>&quot;assembled&quot;</ETag></CompleteMultipartUploadResult>",
+                                    key
+                                ))
+                            }
+                            ("DELETE", Some(id)) => {
+                                store.uploads.remove(&id);
+                                StatusCode::NO_CONTENT.into_response()
+                            }
+                            _ => StatusCode::METHOD_NOT_ALLOWED.into_response(),
+                        }
+                    }
+                },
+            ),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let config = aws_sdk_s3::Config::builder()
+            .behavior_version(aws_sdk_s3::config::BehaviorVersion::latest())
+            .region(aws_sdk_s3::config::Region::new("us-east-1"))
+            .credentials_provider(aws_sdk_s3::config::Credentials::new("test", "test", None, None, "test"))
+            .endpoint_url(endpoint)
+            .force_path_style(true)
+            .build();
+        (StorageClient::from_client(aws_sdk_s3::Client::from_conf(config), "blacklake"), store)
+    }
+
+    #[tokio::test]
+    async fn test_multipart_upload_completes_only_at_its_staging_key() {
+        let (storage, store) = stub_multipart_s3().await;
+        let chunks: [&[u8]; 2] = [b"station,reading\n", b"north,4.2\n"];
+        let sha256 = blacklake_core::hash_bytes(&chunks.concat());
+        let s3_key = StorageClient::content_address_key(&sha256);
+
+        // upload-init starts the upload under a staging key of its own
+        let staging_key = StorageClient::staging_key(&sha256);
+        let upload_id = storage.create_multipart_upload(&staging_key, "text/csv", None).await.unwrap();
+
+        // The client sends each part to its presigned URL
+        let http = reqwest::Client::new();
+        let mut parts = Vec::new();
+        for (part_number, chunk) in (1..).zip(chunks) {
+            let url = storage
+                .presign_upload_part(&staging_key, &upload_id, part_number, std::time::Duration::from_secs(60))
+                .await
+                .unwrap();
+            let response = http.put(url).body(chunk.to_vec()).send().await.unwrap();
+            assert!(response.status().is_success());
+            let etag = response.headers()["etag"].to_str().unwrap().to_string();
+            parts.push(UploadPart { part_number, etag });
+        }
+
+        let request = |sha256: &str, staging_key: &str, upload_id: &str, parts: Vec<UploadPart>| UploadCompleteRequest {
+            sha256: sha256.to_string(),
+            upload_id: upload_id.to_string(),
+            staging_key: staging_key.to_string(),
+            parts: Some(parts),
+        };
+        let other_sha256 = blacklake_core::hash_bytes(b"some other object");
+        let mut forged = parts.clone();
+        forged[1].etag = "\"forged\"".to_string();
+        for bad in [
+            request("sha256/../../etc/passwd", &staging_key, &upload_id, parts.clone()),
+            request(&sha256, &staging_key, "", parts.clone()),
+            request(&sha256, &staging_key, "upload-unknown", parts.clone()),
+            // Parts can never be assembled straight onto the content address
+            request(&sha256, &s3_key, &upload_id, parts.clone()),
+            // The staging key belongs to another object
+            request(&other_sha256, &staging_key, &upload_id, parts.clone()),
+            // A well-formed staging key, but not the one the upload was started on
+            request(&sha256, &StorageClient::staging_key(&sha256), &upload_id, parts.clone()),
+            request(&sha256, &staging_key, &upload_id, forged),
+        ] {
+            let err = complete_multipart(&storage, bad).await.unwrap_err();
+            assert_eq!(err.into_response().status(), StatusCode::BAD_REQUEST);
+        }
+        assert!(store.lock().unwrap().objects.is_empty());
+
+        let (verify, completed) = complete_multipart(&storage, request(&sha256, &staging_key, &upload_id, parts))
+            .await
+            .unwrap();
+        assert_eq!(completed.parts.len(), 2);
+        assert_eq!(
+            (verify.sha256.as_str(), verify.s3_key.as_str(), verify.staging_key.as_deref()),
+            (sha256.as_str(), s3_key.as_str(), Some(staging_key.as_str()))
+        );
+        // The content address stays untouched until the job verifies the bytes
+        let store = store.lock().unwrap();
+        assert_eq!(store.objects[&staging_key], chunks.concat());
+        assert!(!store.objects.contains_key(&s3_key));
+    }
+}
@@ -0,0 +1,255 @@
+// Bulk metadata updates
+// Merge-patch the metadata of many entries and record the result as one
+// commit of `meta` changes; the entries keep pointing at the same objects
+
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    response::Response,
+    routing::post,
+    Router,
+};
+use blacklake_core::{
+    json_merge_patch, normalize_path, Change, ChangeOp, CommitRequest, MetaBatchRequest, MetaPatch, SchemaRegistry,
+    UuidWrapper,
+};
+use blacklake_index::{IndexClient, IndexError};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+use crate::body_limit::StreamingJson;
+use crate::{ApiError, ApiResult, AppState};
+
+/// Most entries a single batch may patch
+const MAX_META_BATCH_UPDATES: usize = 10_000;
+
+pub fn create_meta_batch_routes() -> Router<AppState> {
+    Router::new().route("/v1/repos/:repo/meta-batch", post(meta_batch))
+}
+
+/// Patch the metadata of every listed entry in one commit on `ref`.
+///
+/// Every patched result must pass the repo's schema before anything is
+/// written, so one bad patch fails the whole batch.
+async fn meta_batch(
+    State(state): State<AppState>,
+    Path(repo): Path<String>,
+    headers: HeaderMap,
+    StreamingJson(payload): StreamingJson<MetaBatchRequest>,
+) -> ApiResult<Response> {
+    if payload.updates.is_empty() {
+        return Err(ApiError::InvalidRequest("A meta batch needs at least one update".to_string()));
+    }
+    if payload.updates.len() > MAX_META_BATCH_UPDATES {
+        return Err(ApiError::InvalidRequest(format!(
+            "A meta batch may patch at most {} entries",
+            MAX_META_BATCH_UPDATES
+        )));
+    }
+
+    let repo_info = state.index.get_repo_by_name(&repo).await?;
+    let head = state.index.get_ref(repo_info.id, &payload.r#ref).await?.commit_id.0;
+    if let Some(expected) = &payload.expected_parent {
+        if expected.0 != head {
+            return Err(ApiError::Index(IndexError::ParentMismatch {
+                ref_name: payload.r#ref.clone(),
+                expected: expected.0,
+                actual: Some(head),
+            }));
+        }
+    }
+
+    let (collection, version) = crate::resolve_repo_schema(&state, repo_info.id.0).await?;
+    let changes =
+        plan_meta_batch(&state.index, &state.schema_registry, (&collection, &version), head, &payload.updates).await?;
+
+    // The commit itself goes through the ordinary path for permissions,
+    // branch protection and post-commit events; pinning the parent keeps a
+    // concurrent commit from slipping in between planning and writing
+    let message = payload
+        .message
+        .unwrap_or_else(|| format!("Update metadata of {} entries", changes.len()));
+    let request = CommitRequest {
+        r#ref: payload.r#ref,
+        message: Some(message),
+        changes,
+        expected_parent: Some(UuidWrapper(head)),
+    };
+    crate::run_commit(state, repo, HashMap::new(), headers, request).await
+}
+
+/// Apply each update's merge patch to the entry's metadata at `head`,
+/// producing `meta` changes that keep the entry's object. Fails on the first
+/// missing path, duplicate path or patched result the schema rejects.
+pub async fn plan_meta_batch(
+    index: &IndexClient,
+    registry: &SchemaRegistry,
+    (collection, version): (&str, &str),
+    head: Uuid,
+    updates: &[MetaPatch],
+) -> ApiResult<Vec<Change>> {
+    let entries = index.get_merge_sides(head).await?;
+    let mut seen = HashSet::new();
+
+    updates
+        .iter()
+        .map(|update| {
+            let path = normalize_path(&update.path)
+                .map_err(|e| ApiError::InvalidRequest(format!("Invalid path '{}': {}", update.path, e)))?;
+            if !seen.insert(path.clone()) {
+                return Err(ApiError::InvalidRequest(format!("'{}' is patched more than once", path)));
+            }
+            if !update.meta_patch.is_object() {
+                return Err(ApiError::InvalidRequest(format!("meta_patch for '{}' must be an object", path)));
+            }
+            let entry = entries
+                .get(&path)
+                .ok_or_else(|| ApiError::Repo(format!("No entry at '{}'", path)))?;
+
+            let meta = json_merge_patch(&entry.meta, &update.meta_patch);
+            crate::validate_metadata(registry, collection, version, &path, &meta)?;
+            Ok(Change {
+                op: ChangeOp::Meta,
+                path,
+                sha256: entry.sha256.clone(),
+                meta,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use blacklake_index::{test_support::test_index, CommitWrite};
+    use serde_json::{json, Value};
+
+    fn meta(name: &str) -> Value {
+        json!({
+            "creation_dt": "2024-01-01T00:00:00Z",
+            "creator": "lab@example.com",
+            "file_name": name,
+            "file_type": "text/csv",
+            "file_size": 100,
+            "org_lab": "TestLab",
+            "description": "Sample",
+            "data_source": "lab",
+            "data_collection_method": "instrument",
+            "version": "1.0",
+            "tags": ["raw"]
+        })
+    }
+
+    /// A repo whose `main` points at one commit holding `data/0.csv` .. `data/{n-1}.csv`
+    async fn seed_repo(index: &IndexClient, n: usize) -> (Uuid, Uuid) {
+        let repo_id = Uuid::new_v4();
+        let commit_id = Uuid::new_v4();
+        sqlx::query("INSERT INTO repo (id, name, created_by) VALUES ($1, $2, 'test')")
+            .bind(repo_id)
+            .bind(format!("meta-batch-{}", repo_id))
+            .execute(index.pool())
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO commit (id, repo_id, author) VALUES ($1, $2, 'test')")
+            .bind(commit_id)
+            .bind(repo_id)
+            .execute(index.pool())
+            .await
+            .unwrap();
+        for i in 0..n {
+            let name = format!("{}.csv", i);
+            sqlx::query("INSERT INTO entry (commit_id, path, object_sha256, meta) VALUES ($1, $2, $3, $4)")
+                .bind(commit_id)
+                .bind(format!("data/{}", name))
+                .bind(format!("{:064x}", i))
+                .bind(meta(&name))
+                .execute(index.pool())
+                .await
+                .unwrap();
+        }
+        sqlx::query("INSERT INTO ref (repo_id, name, kind, commit_id) VALUES ($1, 'main', 'branch', $2)")
+            .bind(repo_id)
+            .bind(commit_id)
+            .execute(index.pool())
+            .await
+            .unwrap();
+        (repo_id, commit_id)
+    }
+
+    async fn commit_count(index: &IndexClient, repo_id: Uuid) -> i64 {
+        sqlx::query_scalar("SELECT COUNT(*) FROM commit WHERE repo_id = $1")
+            .bind(repo_id)
+            .fetch_one(index.pool())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_batch_patch_retags_entries_in_one_commit() {
+        let index = test_index().await;
+        let (repo_id, head) = seed_repo(&index, 3).await;
+        let updates: Vec<MetaPatch> = (0..3)
+            .map(|i| MetaPatch {
+                path: format!("data/{}.csv", i),
+                meta_patch: json!({"tags": ["curated"]}),
+            })
+            .collect();
+
+        let changes = plan_meta_batch(&index, &SchemaRegistry::default(), ("default", "1.0"), head, &updates)
+            .await
+            .unwrap();
+        assert!(changes.iter().all(|c| c.op == ChangeOp::Meta));
+        let (commit, _) = index
+            .commit_atomic(&CommitWrite {
+                repo_id,
+                ref_name: "main",
+                author: "tester",
+                message: Some("retag"),
+                expected_parent: Some(head),
+                changes: &changes,
+                schema: Some(("default", "1.0")),
+                merge_parent: None,
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(commit_count(&index, repo_id).await, 2);
+        let entries = index.get_merge_sides(commit.id.0).await.unwrap();
+        for i in 0..3 {
+            let entry = &entries[&format!("data/{}.csv", i)];
+            // Tags are replaced, not unioned, and the object is untouched
+            assert_eq!(entry.meta["tags"], json!(["curated"]));
+            assert_eq!(entry.meta["creator"], "lab@example.com");
+            assert_eq!(entry.sha256, Some(format!("{:064x}", i)));
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_invalid_patch_rejects_whole_batch() {
+        let index = test_index().await;
+        let (repo_id, head) = seed_repo(&index, 2).await;
+        let updates = vec![
+            MetaPatch { path: "data/0.csv".to_string(), meta_patch: json!({"tags": ["curated"]}) },
+            // Removing a required field
+            MetaPatch { path: "data/1.csv".to_string(), meta_patch: json!({"creator": null}) },
+        ];
+
+        let err = plan_meta_batch(&index, &SchemaRegistry::default(), ("default", "1.0"), head, &updates)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, ApiError::SchemaViolation { ref path, .. } if path == "data/1.csv"), "{:?}", err);
+        assert_eq!(commit_count(&index, repo_id).await, 1);
+        assert_eq!(index.get_ref(repo_id, "main").await.unwrap().commit_id.0, head);
+        let entries = index.get_merge_sides(head).await.unwrap();
+        assert_eq!(entries["data/0.csv"].meta["tags"], json!(["raw"]));
+
+        let missing = vec![MetaPatch { path: "data/9.csv".to_string(), meta_patch: json!({"tags": []}) }];
+        let err = plan_meta_batch(&index, &SchemaRegistry::default(), ("default", "1.0"), head, &missing)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ApiError::Repo(_)), "{:?}", err);
+    }
+}
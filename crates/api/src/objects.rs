@@ -0,0 +1,324 @@
+// Content-addressed object retrieval
+// Objects by sha256, independent of repo, ref and path, and the storage
+// class distribution of the objects a repo references
+
+use axum::{
+    extract::{ConnectInfo, Path, Query, State},
+    http::{header, HeaderMap},
+    response::{IntoResponse, Json, Response},
+    routing::get,
+    Router,
+};
+use blacklake_core::features::RepoFeature;
+use blacklake_core::{validate_sha256, Permission};
+use blacklake_storage::{StorageClassReport, StorageClient};
+use chrono::{DateTime, Duration, Utc};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::conditional::{self, if_none_match, not_modified, strong_etag};
+use crate::{ApiError, ApiResult, AppState};
+
+pub fn create_object_routes() -> Router<AppState> {
+    Router::new()
+        .route("/v1/objects/:sha256", get(get_object_by_sha))
+        .route("/v1/repos/:repo/objects/stats", get(get_object_stats))
+}
+
+/// Default lifetime of a cached storage class report
+const DEFAULT_STATS_CACHE_TTL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+/// Storage class reports per repo, reused until they are older than the TTL
+#[derive(Clone)]
+pub struct StorageClassCache {
+    ttl: std::time::Duration,
+    reports: Arc<RwLock<HashMap<Uuid, CachedReport>>>,
+}
+
+#[derive(Clone)]
+struct CachedReport {
+    stored: Instant,
+    computed_at: DateTime<Utc>,
+    report: StorageClassReport,
+}
+
+impl StorageClassCache {
+    pub fn new(ttl: std::time::Duration) -> Self {
+        Self { ttl, reports: Arc::new(RwLock::new(HashMap::new())) }
+    }
+
+    /// Cache with the lifetime in `OBJECT_STATS_CACHE_TTL_SECS`, an hour by default
+    pub fn from_env() -> Self {
+        let ttl = std::env::var("OBJECT_STATS_CACHE_TTL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .map(std::time::Duration::from_secs)
+            .unwrap_or(DEFAULT_STATS_CACHE_TTL);
+        Self::new(ttl)
+    }
+
+    /// A repo's report and when it was computed, unless it has expired
+    async fn get(&self, repo_id: Uuid) -> Option<(StorageClassReport, DateTime<Utc>)> {
+        let reports = self.reports.read().await;
+        let cached = reports.get(&repo_id)?;
+        (cached.stored.elapsed() < self.ttl).then(|| (cached.report.clone(), cached.computed_at))
+    }
+
+    async fn insert(&self, repo_id: Uuid, report: StorageClassReport, computed_at: DateTime<Utc>) {
+        self.reports
+            .write()
+            .await
+            .insert(repo_id, CachedReport { stored: Instant::now(), computed_at, report });
+    }
+}
+
+/// Bytes and objects per storage class for the objects a repo's history
+/// references, so operators can see how much has tiered down to IA or
+/// Glacier. Results are cached; `?refresh=true` recomputes them.
+async fn get_object_stats(
+    State(state): State<AppState>,
+    Path(repo): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> ApiResult<Json<Value>> {
+    let auth = crate::extract_auth(&state.auth_layer, &headers).await?;
+    let refresh = params.get("refresh").map(|v| v == "true").unwrap_or(false);
+
+    let repo_info = state.index.get_repo_by_name(&repo).await?;
+    crate::require_permission(&state, repo_info.id.0, &auth, Permission::Read).await?;
+
+    if !refresh {
+        if let Some((report, computed_at)) = state.object_stats_cache.get(repo_info.id.0).await {
+            return Ok(Json(stats_body(&repo_info.name, &report, computed_at, true)));
+        }
+    }
+
+    let keys = state.index.repo_object_keys(repo_info.id.0).await?;
+    let report = state.storage.storage_class_usage(&keys).await?;
+    let computed_at = Utc::now();
+    state.object_stats_cache.insert(repo_info.id.0, report.clone(), computed_at).await;
+    Ok(Json(stats_body(&repo_info.name, &report, computed_at, false)))
+}
+
+fn stats_body(repo: &str, report: &StorageClassReport, computed_at: DateTime<Utc>, cached: bool) -> Value {
+    let storage_classes: serde_json::Map<String, Value> = report
+        .classes
+        .iter()
+        .map(|(class, usage)| (class.clone(), json!({"objects": usage.objects, "bytes": usage.bytes})))
+        .collect();
+    json!({
+        "repo": repo,
+        "objects": report.classes.values().map(|usage| usage.objects).sum::<u64>(),
+        "bytes": report.classes.values().map(|usage| usage.bytes).sum::<u64>(),
+        "missing": report.missing,
+        "storage_classes": storage_classes,
+        "computed_at": computed_at,
+        "cached": cached
+    })
+}
+
+/// Fetch any object by content hash.
+///
+/// The caller needs read permission on at least one repository whose history
+/// references the object; that repository's signed URL constraints and URL
+/// lifetime then apply. Returns a presigned URL, or the bytes with `?raw=true`.
+async fn get_object_by_sha(
+    State(state): State<AppState>,
+    Path(sha256): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> ApiResult<Response> {
+    let auth = crate::extract_auth(&state.auth_layer, &headers).await?;
+    let raw = params.get("raw").map(|v| v == "true").unwrap_or(false);
+
+    let sha256 = validate_sha256(&sha256).map_err(|e| ApiError::InvalidRequest(format!("Invalid sha256: {}", e)))?;
+    let object = state
+        .index
+        .get_object(&sha256)
+        .await?
+        .ok_or_else(|| ApiError::Repo(format!("Object not found: {}", sha256)))?;
+
+    let repos = state.index.list_object_repos(&sha256).await?;
+    let (repo_id, repo_name) = find_readable_repo(&sha256, repos, |repo_id| {
+        crate::has_permission(&state, repo_id, &auth, Permission::Read)
+    })
+    .await?;
+
+    let etag = strong_etag(&sha256);
+    if if_none_match(&headers, &etag) {
+        return Ok(not_modified(&etag));
+    }
+
+    let s3_key = StorageClient::content_address_key(&sha256);
+    let head = state
+        .storage
+        .head_object(&s3_key)
+        .await?
+        .ok_or_else(|| ApiError::Repo(format!("Object missing from storage: {}", sha256)))?;
+
+    state
+        .index
+        .append_audit_log(
+            &auth.sub,
+            "object_access",
+            Some(&repo_name),
+            None,
+            None,
+            None,
+            Some(json!({"sha256": sha256, "raw": raw})),
+        )
+        .await?;
+
+    if raw {
+        let range = headers.get(header::RANGE).and_then(|v| v.to_str().ok());
+        let body = state
+            .storage
+            .get_object_range(&s3_key, range)
+            .await?
+            .ok_or_else(|| ApiError::Repo(format!("Object missing from storage: {}", sha256)))?;
+        return Ok(conditional::stream_object(&etag, &sha256, body));
+    }
+
+    let features = state.index.get_repo_features(repo_id).await?;
+    let expires = state
+        .storage
+        .download_url_ttl(crate::repo_url_ttl_seconds(&features, RepoFeature::DownloadUrlTtl)?)?;
+    let expires_at = Utc::now() + Duration::seconds(expires.as_secs() as i64);
+    let grant = crate::enforce_presign_constraints(&state, repo_id, "GET", &s3_key, peer.ip(), &headers, expires_at).await?;
+    let download_url = state.storage.presign_get(&s3_key, expires).await?;
+
+    let mut response = Json(json!({
+        "download_url": download_url.to_string(),
+        "sha256": sha256,
+        "size": object.size,
+        "media_type": object.media_type.or(head.content_type),
+        "etag": head.etag,
+        "repo": repo_name,
+        "expires_at": expires_at,
+        "constraints_applied": grant.constraints_applied,
+        "access_token": grant.access_token
+    }))
+    .into_response();
+    if let Ok(value) = etag.parse() {
+        response.headers_mut().insert(header::ETAG, value);
+    }
+    Ok(response)
+}
+
+/// The first repository referencing the object that `can_read` allows.
+///
+/// An object no repository references is reported as missing, the same as an
+/// unknown hash; one the caller can't read through any repository is forbidden.
+async fn find_readable_repo<F, Fut>(sha256: &str, repos: Vec<(Uuid, String)>, can_read: F) -> ApiResult<(Uuid, String)>
+where
+    F: Fn(Uuid) -> Fut,
+    Fut: Future<Output = ApiResult<bool>>,
+{
+    if repos.is_empty() {
+        return Err(ApiError::Repo(format!("Object not found: {}", sha256)));
+    }
+    for (repo_id, repo_name) in repos {
+        if can_read(repo_id).await? {
+            return Ok((repo_id, repo_name));
+        }
+    }
+    Err(ApiError::Forbidden(format!(
+        "read permission required on a repository referencing {}",
+        sha256
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+    use blacklake_storage::StorageClassUsage;
+
+    const SHA: &str = "a665a45920422f9d417e4867efdc4fb8a04a1f3fff1fa07e998e86f7f7a27ae3";
+
+    fn status(err: ApiError) -> StatusCode {
+        err.into_response().status()
+    }
+
+    #[tokio::test]
+    async fn test_valid_hash_resolves_through_a_readable_repo() {
+        let private = Uuid::new_v4();
+        let shared = Uuid::new_v4();
+        let repos = vec![(private, "private".to_string()), (shared, "shared".to_string())];
+
+        let found = find_readable_repo(SHA, repos, |repo_id| async move { Ok(repo_id == shared) })
+            .await
+            .unwrap();
+
+        assert_eq!(found, (shared, "shared".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_unreferenced_hash_is_not_found() {
+        let err = find_readable_repo(SHA, Vec::new(), |_| async { Ok(true) }).await.unwrap_err();
+
+        assert_eq!(status(err), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_hash_only_in_unreadable_repos_is_forbidden() {
+        let repos = vec![(Uuid::new_v4(), "private".to_string())];
+
+        let err = find_readable_repo(SHA, repos, |_| async { Ok(false) }).await.unwrap_err();
+
+        assert_eq!(status(err), StatusCode::FORBIDDEN);
+    }
+
+    fn report() -> StorageClassReport {
+        let mut report = StorageClassReport { missing: 1, ..Default::default() };
+        report.classes.insert("STANDARD".to_string(), StorageClassUsage { objects: 2, bytes: 150 });
+        report.classes.insert("GLACIER".to_string(), StorageClassUsage { objects: 3, bytes: 9_000 });
+        report
+    }
+
+    #[test]
+    fn test_stats_body_totals_storage_classes() {
+        let body = stats_body("mylab", &report(), Utc::now(), false);
+
+        assert_eq!(body["objects"], 5);
+        assert_eq!(body["bytes"], 9_150);
+        assert_eq!(body["missing"], 1);
+        assert_eq!(body["storage_classes"]["GLACIER"], json!({"objects": 3, "bytes": 9_000}));
+        assert_eq!(body["cached"], false);
+    }
+
+    #[tokio::test]
+    async fn test_cached_report_expires_after_ttl() {
+        let repo_id = Uuid::new_v4();
+        let computed_at = Utc::now();
+
+        let cache = StorageClassCache::new(std::time::Duration::from_secs(60));
+        assert!(cache.get(repo_id).await.is_none());
+        cache.insert(repo_id, report(), computed_at).await;
+        assert_eq!(cache.get(repo_id).await, Some((report(), computed_at)));
+        assert!(cache.get(Uuid::new_v4()).await.is_none());
+
+        let expired = StorageClassCache::new(std::time::Duration::ZERO);
+        expired.insert(repo_id, report(), computed_at).await;
+        assert!(expired.get(repo_id).await.is_none());
+    }
+
+    #[test]
+    fn test_malformed_hash_is_a_bad_request() {
+        let bad_digit = format!("{}z", &SHA[1..]);
+        for malformed in ["abc123", &SHA[1..], bad_digit.as_str()] {
+            let err = validate_sha256(malformed)
+                .map_err(|e| ApiError::InvalidRequest(format!("Invalid sha256: {}", e)))
+                .unwrap_err();
+            assert_eq!(status(err), StatusCode::BAD_REQUEST);
+        }
+        assert_eq!(validate_sha256(&SHA.to_uppercase()).unwrap(), SHA);
+    }
+}
@@ -1,6 +1,6 @@
 use axum::{
     extract::{Request, State},
-    http::{HeaderMap, StatusCode},
+    http::{HeaderMap, HeaderValue, StatusCode},
     middleware::Next,
     response::Response,
 };
@@ -14,7 +14,7 @@ use tokio::sync::RwLock;
 use tower::ServiceBuilder;
 use tower_governor::{
     governor::{
-        clock::DefaultClock,
+        clock::{Clock, DefaultClock},
         state::{InMemoryState, NotKeyed},
         RateLimiter,
     },
@@ -22,6 +22,32 @@ use tower_governor::{
 };
 use tracing::{error, warn, info};
 
+/// Length of the fixed per-user and per-IP counting windows
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Where a client stands against the limit that applies to it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitStatus {
+    pub limit: u32,
+    pub remaining: u32,
+    /// Time until the window resets and `remaining` goes back to `limit`
+    pub reset: Duration,
+}
+
+impl RateLimitStatus {
+    /// Add `X-RateLimit-Limit`, `X-RateLimit-Remaining` and `X-RateLimit-Reset`
+    /// (whole seconds until reset, rounded up) to a response.
+    pub fn apply_headers(&self, headers: &mut HeaderMap) {
+        headers.insert("X-RateLimit-Limit", HeaderValue::from(self.limit));
+        headers.insert("X-RateLimit-Remaining", HeaderValue::from(self.remaining));
+        headers.insert("X-RateLimit-Reset", HeaderValue::from(self.reset_secs()));
+    }
+
+    fn reset_secs(&self) -> u64 {
+        self.reset.as_secs() + u64::from(self.reset.subsec_nanos() > 0)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct RateLimitConfig {
     pub requests_per_minute: u32,
@@ -95,49 +121,67 @@ impl RateLimitState {
         }
     }
 
+    /// Count a request against the global, per-user and per-IP limits.
+    ///
+    /// On success, returns whichever of the per-user and per-IP windows has
+    /// the fewest requests left, since that is the one the client hits first.
     pub async fn check_rate_limit(
         &self,
         user_id: Option<&str>,
         ip: &str,
-    ) -> Result<(), RateLimitError> {
+    ) -> Result<RateLimitStatus, RateLimitError> {
         let now = Instant::now();
 
         // Check global rate limit
-        if self.global_limiter.check().is_err() {
-            return Err(RateLimitError::GlobalLimitExceeded);
+        if let Err(not_until) = self.global_limiter.check() {
+            return Err(RateLimitError::GlobalLimitExceeded(RateLimitStatus {
+                limit: self.config.burst_size,
+                remaining: 0,
+                reset: not_until.wait_time_from(DefaultClock::default().now()),
+            }));
         }
 
         // Check per-user rate limit
-        if let Some(user_id) = user_id {
-            if let Err(_) = self.check_user_rate_limit(user_id, now).await {
-                return Err(RateLimitError::UserLimitExceeded);
-            }
-        }
+        let user_status = match user_id {
+            Some(user_id) => Some(
+                self.check_user_rate_limit(user_id, now)
+                    .await
+                    .map_err(RateLimitError::UserLimitExceeded)?,
+            ),
+            None => None,
+        };
 
         // Check per-IP rate limit
-        if let Err(_) = self.check_ip_rate_limit(ip, now).await {
-            return Err(RateLimitError::IpLimitExceeded);
-        }
-
-        Ok(())
+        let ip_status = self
+            .check_ip_rate_limit(ip, now)
+            .await
+            .map_err(RateLimitError::IpLimitExceeded)?;
+
+        Ok(match user_status {
+            Some(user_status) if user_status.remaining < ip_status.remaining => user_status,
+            _ => ip_status,
+        })
     }
 
-    async fn check_user_rate_limit(&self, user_id: &str, now: Instant) -> Result<(), ()> {
+    async fn check_user_rate_limit(&self, user_id: &str, now: Instant) -> Result<RateLimitStatus, RateLimitStatus> {
         let mut user_limits = self.user_limits.write().await;
+        let max = self.config.per_user_limit;
         
         if let Some(limit) = user_limits.get_mut(user_id) {
             // Reset window if needed
-            if now.duration_since(limit.window_start) >= Duration::from_secs(60) {
+            if now.duration_since(limit.window_start) >= WINDOW {
                 limit.requests = 0;
                 limit.window_start = now;
             }
 
-            if limit.requests >= self.config.per_user_limit {
-                return Err(());
+            let reset = WINDOW - now.duration_since(limit.window_start);
+            if limit.requests >= max {
+                return Err(RateLimitStatus { limit: max, remaining: 0, reset });
             }
 
             limit.requests += 1;
             limit.last_request = now;
+            Ok(RateLimitStatus { limit: max, remaining: max - limit.requests, reset })
         } else {
             // First request for this user
             user_limits.insert(
@@ -149,27 +193,29 @@ impl RateLimitState {
                     last_request: now,
                 },
             );
+            first_request_status(max)
         }
-
-        Ok(())
     }
 
-    async fn check_ip_rate_limit(&self, ip: &str, now: Instant) -> Result<(), ()> {
+    async fn check_ip_rate_limit(&self, ip: &str, now: Instant) -> Result<RateLimitStatus, RateLimitStatus> {
         let mut ip_limits = self.ip_limits.write().await;
+        let max = self.config.per_ip_limit;
         
         if let Some(limit) = ip_limits.get_mut(ip) {
             // Reset window if needed
-            if now.duration_since(limit.window_start) >= Duration::from_secs(60) {
+            if now.duration_since(limit.window_start) >= WINDOW {
                 limit.requests = 0;
                 limit.window_start = now;
             }
 
-            if limit.requests >= self.config.per_ip_limit {
-                return Err(());
+            let reset = WINDOW - now.duration_since(limit.window_start);
+            if limit.requests >= max {
+                return Err(RateLimitStatus { limit: max, remaining: 0, reset });
             }
 
             limit.requests += 1;
             limit.last_request = now;
+            Ok(RateLimitStatus { limit: max, remaining: max - limit.requests, reset })
         } else {
             // First request from this IP
             ip_limits.insert(
@@ -181,9 +227,8 @@ impl RateLimitState {
                     last_request: now,
                 },
             );
+            first_request_status(max)
         }
-
-        Ok(())
     }
 
     pub async fn cleanup_expired_limits(&self) {
@@ -208,39 +253,53 @@ impl RateLimitState {
     }
 }
 
+/// Status for the first request a user or IP makes in a window.
+///
+/// A limit of zero admits nothing, so the first request is already over it.
+fn first_request_status(max: u32) -> Result<RateLimitStatus, RateLimitStatus> {
+    if max == 0 {
+        return Err(RateLimitStatus { limit: 0, remaining: 0, reset: WINDOW });
+    }
+    Ok(RateLimitStatus { limit: max, remaining: max - 1, reset: WINDOW })
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum RateLimitError {
     #[error("Global rate limit exceeded")]
-    GlobalLimitExceeded,
+    GlobalLimitExceeded(RateLimitStatus),
     #[error("User rate limit exceeded")]
-    UserLimitExceeded,
+    UserLimitExceeded(RateLimitStatus),
     #[error("IP rate limit exceeded")]
-    IpLimitExceeded,
+    IpLimitExceeded(RateLimitStatus),
+}
+
+impl RateLimitError {
+    /// The limit that was exceeded
+    pub fn status(&self) -> &RateLimitStatus {
+        match self {
+            RateLimitError::GlobalLimitExceeded(status)
+            | RateLimitError::UserLimitExceeded(status)
+            | RateLimitError::IpLimitExceeded(status) => status,
+        }
+    }
 }
 
 impl axum::response::IntoResponse for RateLimitError {
     fn into_response(self) -> Response {
-        let (status, message) = match self {
-            RateLimitError::GlobalLimitExceeded => (
-                StatusCode::TOO_MANY_REQUESTS,
-                "Global rate limit exceeded",
-            ),
-            RateLimitError::UserLimitExceeded => (
-                StatusCode::TOO_MANY_REQUESTS,
-                "User rate limit exceeded",
-            ),
-            RateLimitError::IpLimitExceeded => (
-                StatusCode::TOO_MANY_REQUESTS,
-                "IP rate limit exceeded",
-            ),
-        };
+        let status = *self.status();
+        let retry_after = status.reset_secs();
 
-        let response = serde_json::json!({
-            "error": message,
-            "retry_after": 60
+        let body = serde_json::json!({
+            "error": self.to_string(),
+            "retry_after": retry_after
         });
 
-        (status, response).into_response()
+        let mut response = (StatusCode::TOO_MANY_REQUESTS, axum::Json(body)).into_response();
+        status.apply_headers(response.headers_mut());
+        response
+            .headers_mut()
+            .insert(axum::http::header::RETRY_AFTER, HeaderValue::from(retry_after));
+        response
     }
 }
 
@@ -249,28 +308,25 @@ pub async fn rate_limit_middleware(
     mut request: Request,
     next: Next,
 ) -> Result<Response, RateLimitError> {
-    let headers = request.headers();
-    
     // Extract user ID from auth context if available
     let user_id = request
         .extensions()
         .get::<AuthContext>()
-        .map(|auth| auth.user_id.as_str());
+        .map(|auth| auth.sub.clone());
 
     // Extract IP address
-    let ip = extract_client_ip(headers)
+    let ip = extract_client_ip(request.headers())
         .unwrap_or_else(|| "unknown".to_string());
 
     // Check rate limits
-    rate_limit_state.check_rate_limit(user_id, &ip).await?;
+    let status = rate_limit_state.check_rate_limit(user_id.as_deref(), &ip).await?;
 
     // Add rate limit info to request extensions
-    request.extensions_mut().insert(RateLimitInfo {
-        user_id: user_id.map(|s| s.to_string()),
-        ip,
-    });
+    request.extensions_mut().insert(RateLimitInfo { user_id, ip });
 
-    Ok(next.run(request).await)
+    let mut response = next.run(request).await;
+    status.apply_headers(response.headers_mut());
+    Ok(response)
 }
 
 #[derive(Debug, Clone)]
@@ -279,7 +335,7 @@ pub struct RateLimitInfo {
     pub ip: String,
 }
 
-fn extract_client_ip(headers: &HeaderMap) -> Option<String> {
+pub(crate) fn extract_client_ip(headers: &HeaderMap) -> Option<String> {
     // Check X-Forwarded-For header first
     if let Some(forwarded) = headers.get("X-Forwarded-For") {
         if let Ok(forwarded_str) = forwarded.to_str() {
@@ -380,6 +436,90 @@ mod tests {
         assert!(state.check_rate_limit(Some("user2"), "192.168.1.1").await.is_ok());
     }
 
+    fn limited_app(per_ip_limit: u32) -> axum::Router {
+        use axum::{middleware, routing::get, Router};
+
+        let state = RateLimitState::new(RateLimitConfig {
+            requests_per_minute: 6000,
+            burst_size: 100,
+            per_user_limit: 1000,
+            per_ip_limit,
+        });
+        Router::new()
+            .route("/v1/repos", get(|| async { "ok" }))
+            .layer(middleware::from_fn_with_state(state, rate_limit_middleware))
+    }
+
+    async fn get_from(app: &axum::Router, ip: &str) -> Response {
+        use axum::body::Body;
+        use tower::ServiceExt;
+
+        app.clone()
+            .oneshot(
+                axum::http::Request::builder()
+                    .uri("/v1/repos")
+                    .header("X-Forwarded-For", ip)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+    }
+
+    fn header(response: &Response, name: &str) -> u64 {
+        response.headers()[name].to_str().unwrap().parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_remaining_decrements_under_the_limit() {
+        let app = limited_app(3);
+
+        for expected_remaining in [2, 1, 0] {
+            let response = get_from(&app, "10.0.0.1").await;
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(header(&response, "X-RateLimit-Limit"), 3);
+            assert_eq!(header(&response, "X-RateLimit-Remaining"), expected_remaining);
+            let reset = header(&response, "X-RateLimit-Reset");
+            assert!(reset > 0 && reset <= 60);
+        }
+
+        // Another client has its own window
+        let response = get_from(&app, "10.0.0.2").await;
+        assert_eq!(header(&response, "X-RateLimit-Remaining"), 2);
+    }
+
+    #[tokio::test]
+    async fn test_over_the_limit_returns_429_with_retry_after() {
+        let app = limited_app(2);
+        get_from(&app, "10.0.0.1").await;
+        get_from(&app, "10.0.0.1").await;
+
+        let response = get_from(&app, "10.0.0.1").await;
+
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let retry_after = header(&response, "Retry-After");
+        assert!(retry_after > 0 && retry_after <= 60);
+        assert_eq!(header(&response, "X-RateLimit-Limit"), 2);
+        assert_eq!(header(&response, "X-RateLimit-Remaining"), 0);
+        assert_eq!(header(&response, "X-RateLimit-Reset"), retry_after);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"], "IP rate limit exceeded");
+        assert_eq!(body["retry_after"], retry_after);
+    }
+
+    #[test]
+    fn test_reset_rounds_up_to_whole_seconds() {
+        let status = RateLimitStatus { limit: 10, remaining: 4, reset: Duration::from_millis(1500) };
+        let mut headers = HeaderMap::new();
+        status.apply_headers(&mut headers);
+
+        assert_eq!(headers["X-RateLimit-Limit"], "10");
+        assert_eq!(headers["X-RateLimit-Remaining"], "4");
+        assert_eq!(headers["X-RateLimit-Reset"], "2");
+    }
+
     #[test]
     fn test_extract_client_ip() {
         let mut headers = HeaderMap::new();
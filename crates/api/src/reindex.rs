@@ -0,0 +1,187 @@
+// Search reindexing for admins
+// Starts FullReindexJob runs and reports their progress
+
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+    routing::{get, post},
+    Router,
+};
+use blacklake_core::jobs::{FullReindexJob, JobContext, JobMetadata};
+use blacklake_core::reindex::{PgReindexStore, ReindexStore};
+use blacklake_index::IndexError;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::health::process_job_with_metrics;
+use crate::auth::require_admin;
+use crate::{ApiError, ApiResult, AppState};
+
+const DEFAULT_BATCH_SIZE: u32 = 500;
+const MAX_BATCH_SIZE: u32 = 5000;
+
+/// Body of `POST /v1/admin/reindex`; every field is optional
+#[derive(Debug, Default, Deserialize)]
+pub struct ReindexRequest {
+    pub repo_id: Option<Uuid>,
+    pub since_commit_id: Option<Uuid>,
+    pub batch_size: Option<u32>,
+}
+
+pub fn create_reindex_routes() -> Router<AppState> {
+    Router::new()
+        .route("/v1/admin/reindex", post(start_reindex))
+        .route("/v1/admin/reindex/:job_id", get(get_reindex))
+}
+
+/// Queue a reindex of one repo, or every repo, and return its job id.
+///
+/// With `since_commit_id` only entries from later commits are reindexed; when
+/// `repo_id` is also given the commit must belong to that repo.
+async fn start_reindex(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<ReindexRequest>,
+) -> ApiResult<(StatusCode, Json<Value>)> {
+    let auth = crate::extract_auth(&state.auth_layer, &headers).await?;
+    require_admin(&auth)?;
+
+    let since_repo = match request.since_commit_id {
+        Some(commit_id) => match state.index.get_commit(commit_id).await {
+            Ok(commit) => Some(commit.repo_id.0),
+            Err(IndexError::CommitNotFound(_)) => {
+                return Err(ApiError::InvalidRequest(format!("Unknown since_commit_id: {}", commit_id)))
+            }
+            Err(e) => return Err(e.into()),
+        },
+        None => None,
+    };
+    let job = reindex_job(request, since_repo)?;
+
+    let job_id = Uuid::new_v4();
+    let store = PgReindexStore::new(state.index.pool().clone());
+    store
+        .create_run(job_id, &job.scope())
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+
+    state
+        .index
+        .append_audit_log(
+            &auth.sub,
+            "reindex_triggered",
+            None,
+            None,
+            None,
+            None,
+            Some(json!({
+                "job_id": job_id,
+                "repo_id": job.repo_id,
+                "since_commit_id": job.since_commit_id,
+                "batch_size": job.batch_size
+            })),
+        )
+        .await?;
+
+    let ctx = JobContext {
+        job_id,
+        worker_id: "api".to_string(),
+        s3_client: None,
+        db_pool: Some(state.index.pool().clone()),
+        solr: Some(state.solr_client.clone()),
+    };
+    tokio::spawn(async move {
+        if let Err(e) = process_job_with_metrics(&job, &ctx).await {
+            warn!("Reindex {} failed: {}", ctx.job_id, e);
+        }
+    });
+
+    Ok((StatusCode::ACCEPTED, Json(json!({ "job_id": job_id, "status": "pending" }))))
+}
+
+/// Status, `indexed_count` and progress of a reindex job
+async fn get_reindex(
+    State(state): State<AppState>,
+    Path(job_id): Path<Uuid>,
+    headers: HeaderMap,
+) -> ApiResult<Json<JobMetadata>> {
+    let auth = crate::extract_auth(&state.auth_layer, &headers).await?;
+    require_admin(&auth)?;
+
+    let run = PgReindexStore::new(state.index.pool().clone())
+        .get_run(job_id)
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?
+        .ok_or_else(|| ApiError::Repo(format!("Reindex job not found: {}", job_id)))?;
+
+    Ok(Json(run.job_metadata()))
+}
+
+/// The job a request describes; `since_repo` is the repo `since_commit_id` belongs to
+fn reindex_job(request: ReindexRequest, since_repo: Option<Uuid>) -> ApiResult<FullReindexJob> {
+    let batch_size = request.batch_size.unwrap_or(DEFAULT_BATCH_SIZE);
+    if !(1..=MAX_BATCH_SIZE).contains(&batch_size) {
+        return Err(ApiError::InvalidRequest(format!(
+            "batch_size must be between 1 and {}",
+            MAX_BATCH_SIZE
+        )));
+    }
+    if let (Some(repo_id), Some(since_repo)) = (request.repo_id, since_repo) {
+        if repo_id != since_repo {
+            return Err(ApiError::InvalidRequest(
+                "since_commit_id does not belong to repo_id".to_string(),
+            ));
+        }
+    }
+
+    Ok(FullReindexJob {
+        repo_id: request.repo_id,
+        since_commit_id: request.since_commit_id,
+        batch_size,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::response::IntoResponse;
+
+    #[test]
+    fn test_empty_request_reindexes_everything_with_default_batch() {
+        let job = reindex_job(ReindexRequest::default(), None).unwrap();
+
+        assert_eq!(job.repo_id, None);
+        assert_eq!(job.since_commit_id, None);
+        assert_eq!(job.batch_size, DEFAULT_BATCH_SIZE);
+    }
+
+    #[test]
+    fn test_out_of_range_batch_size_is_rejected() {
+        for batch_size in [0, MAX_BATCH_SIZE + 1] {
+            let request = ReindexRequest { batch_size: Some(batch_size), ..Default::default() };
+            let err = reindex_job(request, None).unwrap_err();
+            assert_eq!(err.into_response().status(), StatusCode::BAD_REQUEST);
+        }
+    }
+
+    #[test]
+    fn test_since_commit_must_belong_to_repo() {
+        let repo_id = Uuid::new_v4();
+        let request = |since_commit_id| ReindexRequest {
+            repo_id: Some(repo_id),
+            since_commit_id: Some(since_commit_id),
+            batch_size: Some(100),
+        };
+
+        let err = reindex_job(request(Uuid::new_v4()), Some(Uuid::new_v4())).unwrap_err();
+        assert_eq!(err.into_response().status(), StatusCode::BAD_REQUEST);
+
+        let since = Uuid::new_v4();
+        let job = reindex_job(request(since), Some(repo_id)).unwrap();
+        assert_eq!(job.scope().since_commit_id, Some(since));
+        assert_eq!(job.batch_size, 100);
+    }
+}
@@ -0,0 +1,277 @@
+// Search result ordering and output formats
+// JSON (the default), CSV for spreadsheets, and JSON Lines for scripts
+
+use axum::{
+    http::{header, HeaderValue},
+    response::{IntoResponse, Json, Response},
+};
+use blacklake_core::{features::RepoFeatures, SearchEntry, SearchResponse, SearchSort};
+use serde_json::Value;
+use std::borrow::Cow;
+
+/// Header carrying the keyset cursor when results are CSV or JSON Lines
+pub const NEXT_CURSOR_HEADER: &str = "x-next-cursor";
+
+/// Metadata fields flattened into their own CSV columns, in column order
+const CSV_META_COLUMNS: &[&str] = &[
+    "file_name",
+    "file_type",
+    "file_size",
+    "creation_dt",
+    "creator",
+    "org_lab",
+    "description",
+    "tags",
+    "license",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchFormat {
+    Json,
+    Csv,
+    Jsonl,
+}
+
+impl std::str::FromStr for SearchFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(SearchFormat::Json),
+            "csv" => Ok(SearchFormat::Csv),
+            "jsonl" => Ok(SearchFormat::Jsonl),
+            other => Err(format!("Invalid format: {}. Use 'json', 'csv' or 'jsonl'", other)),
+        }
+    }
+}
+
+impl SearchFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            SearchFormat::Json => "application/json",
+            SearchFormat::Csv => "text/csv; charset=utf-8",
+            SearchFormat::Jsonl => "application/x-ndjson",
+        }
+    }
+}
+
+/// Sort for an offset-paginated search: the request's `sort`, else the
+/// repo's `search_sort` feature. An unknown field is a 400 rather than a
+/// silent fallback to the default.
+pub fn search_sort(requested: Option<&str>, features: &RepoFeatures) -> Result<SearchSort, crate::ApiError> {
+    match requested {
+        Some(sort) => sort.parse().map_err(crate::ApiError::InvalidRequest),
+        None => Ok(features.search_sort()),
+    }
+}
+
+/// Render a page of search results in `format`.
+///
+/// CSV and JSON Lines carry only the entries; the total goes in
+/// `X-Total-Count` and any keyset cursor in `X-Next-Cursor`.
+pub fn render_search(format: SearchFormat, response: SearchResponse) -> Response {
+    let body = match format {
+        SearchFormat::Json => return Json(response).into_response(),
+        SearchFormat::Csv => to_csv(&response.entries),
+        SearchFormat::Jsonl => to_jsonl(&response.entries),
+    };
+
+    let mut http_response = ([(header::CONTENT_TYPE, format.content_type())], body).into_response();
+    let headers = http_response.headers_mut();
+    headers.insert("x-total-count", response.total.into());
+    if let Some(value) = response.next_cursor.as_deref().and_then(|c| HeaderValue::from_str(c).ok()) {
+        headers.insert(NEXT_CURSOR_HEADER, value);
+    }
+    http_response
+}
+
+/// One row per entry under a header row; metadata outside `CSV_META_COLUMNS`
+/// is left out.
+pub fn to_csv(entries: &[SearchEntry]) -> String {
+    let mut csv = String::new();
+    let header: Vec<&str> = ["path", "commit_id", "size", "media_type"]
+        .into_iter()
+        .chain(CSV_META_COLUMNS.iter().copied())
+        .collect();
+    push_csv_row(&mut csv, header.into_iter().map(Cow::Borrowed));
+
+    for entry in entries {
+        let fixed = [
+            Cow::Borrowed(entry.path.as_str()),
+            Cow::Owned(entry.commit_id.0.to_string()),
+            Cow::Owned(entry.size.map(|size| size.to_string()).unwrap_or_default()),
+            Cow::Borrowed(entry.media_type.as_deref().unwrap_or("")),
+        ];
+        let meta = CSV_META_COLUMNS
+            .iter()
+            .map(|column| Cow::Owned(meta_cell(entry.meta.get(*column))));
+        push_csv_row(&mut csv, fixed.into_iter().chain(meta));
+    }
+    csv
+}
+
+/// One JSON object per line
+pub fn to_jsonl(entries: &[SearchEntry]) -> String {
+    let mut jsonl = String::new();
+    for entry in entries {
+        // SearchEntry serializes from plain fields, so this cannot fail
+        jsonl.push_str(&serde_json::to_string(entry).unwrap_or_default());
+        jsonl.push('\n');
+    }
+    jsonl
+}
+
+/// A metadata value as CSV text; lists are joined with `;`
+fn meta_cell(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Array(items)) => items
+            .iter()
+            .map(|item| match item {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(";"),
+        Some(other) => other.to_string(),
+    }
+}
+
+pub(crate) fn push_csv_row<'a>(csv: &mut String, fields: impl Iterator<Item = Cow<'a, str>>) {
+    for (i, field) in fields.enumerate() {
+        if i > 0 {
+            csv.push(',');
+        }
+        csv.push_str(&csv_field(&field));
+    }
+    csv.push_str("\r\n");
+}
+
+/// Quote a field if it contains a delimiter, quote or line break (RFC 4180)
+fn csv_field(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\r', '\n']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::to_bytes;
+    use blacklake_core::UuidWrapper;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    fn entry(path: &str, meta: Value) -> SearchEntry {
+        SearchEntry {
+            path: path.to_string(),
+            commit_id: UuidWrapper(Uuid::nil()),
+            meta,
+            size: Some(2048),
+            media_type: Some("text/csv".to_string()),
+        }
+    }
+
+    /// Split CSV text into rows of fields, undoing RFC 4180 quoting
+    fn parse_csv(text: &str) -> Vec<Vec<String>> {
+        let mut rows = vec![];
+        let mut row = vec![];
+        let mut field = String::new();
+        let mut quoted = false;
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            match (c, quoted) {
+                ('"', true) if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                ('"', _) => quoted = !quoted,
+                (',', false) => row.push(std::mem::take(&mut field)),
+                ('\r', false) => {}
+                ('\n', false) => {
+                    row.push(std::mem::take(&mut field));
+                    rows.push(std::mem::take(&mut row));
+                }
+                (c, _) => field.push(c),
+            }
+        }
+        rows
+    }
+
+    #[test]
+    fn test_csv_quotes_commas_quotes_and_newlines() {
+        let description = "Temps, \"calibrated\" daily\nstation A";
+        let entries = vec![entry(
+            "data/temps.csv",
+            json!({"description": description, "file_type": "csv", "file_size": 2048, "tags": ["climate", "raw"]}),
+        )];
+
+        let csv = to_csv(&entries);
+        let rows = parse_csv(&csv);
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].len(), rows[1].len());
+        let column = |name: &str| rows[0].iter().position(|h| h == name).unwrap();
+        assert_eq!(rows[1][column("description")], description);
+        assert_eq!(rows[1][column("tags")], "climate;raw");
+        assert_eq!(rows[1][column("file_size")], "2048");
+        assert_eq!(rows[1][column("path")], "data/temps.csv");
+        assert!(csv.contains("\"Temps, \"\"calibrated\"\" daily\nstation A\""));
+    }
+
+    #[test]
+    fn test_jsonl_has_one_line_per_entry() {
+        let entries: Vec<SearchEntry> = (0..3)
+            .map(|i| entry(&format!("data/{}.csv", i), json!({"description": "line\nbreak"})))
+            .collect();
+
+        let jsonl = to_jsonl(&entries);
+
+        let lines: Vec<&str> = jsonl.lines().collect();
+        assert_eq!(lines.len(), 3);
+        for (i, line) in lines.iter().enumerate() {
+            let value: Value = serde_json::from_str(line).unwrap();
+            assert_eq!(value["path"], format!("data/{}.csv", i));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rendered_response_sets_content_type_and_cursor() {
+        let response = SearchResponse {
+            entries: vec![entry("data/a.csv", json!({}))],
+            total: 7,
+            next_cursor: Some("abc".to_string()),
+        };
+
+        let rendered = render_search(SearchFormat::Jsonl, response);
+
+        assert_eq!(rendered.headers()[header::CONTENT_TYPE], "application/x-ndjson");
+        assert_eq!(rendered.headers()["x-total-count"], "7");
+        assert_eq!(rendered.headers()[NEXT_CURSOR_HEADER], "abc");
+        let body = to_bytes(rendered.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(String::from_utf8(body.to_vec()).unwrap().lines().count(), 1);
+    }
+
+    #[test]
+    fn test_unknown_format_is_rejected() {
+        assert_eq!("csv".parse::<SearchFormat>(), Ok(SearchFormat::Csv));
+        assert!("xlsx".parse::<SearchFormat>().is_err());
+    }
+
+    #[test]
+    fn test_sort_falls_back_to_repo_default_and_rejects_unknown_fields() {
+        let unset = RepoFeatures::default();
+        let by_path = RepoFeatures(json!({"search_sort": "path"}));
+
+        assert_eq!(search_sort(None, &unset).unwrap(), SearchSort::CreatedAt);
+        assert_eq!(search_sort(None, &by_path).unwrap(), SearchSort::Path);
+        assert_eq!(search_sort(Some("size"), &by_path).unwrap(), SearchSort::Size);
+
+        let err = search_sort(Some("name"), &by_path).unwrap_err();
+        assert!(err.to_string().contains("Invalid sort field: name. Use one of created_at, path, size"), "{}", err);
+        assert_eq!(err.into_response().status(), axum::http::StatusCode::BAD_REQUEST);
+    }
+}
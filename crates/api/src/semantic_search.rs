@@ -9,9 +9,9 @@ use axum::{
     Router,
 };
 use blacklake_core::{
-    AuthContext,
+    AuthContext, Permission,
 };
-use blacklake_core::embeddings::{EmbeddingService, MockEmbeddingService, SemanticSearchRequest, SemanticSearchResponse, EmbeddingRequest, EmbeddingResponse, SuggestedTags};
+use blacklake_core::embeddings::{EmbeddingBackend, EmbeddingService, MockEmbeddingService, EmbeddingRequest, EmbeddingResponse, SuggestedTags};
 use crate::{ApiError, ApiResponse};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -86,7 +86,21 @@ pub struct ExtractTagsResponse {
     pub processing_time_ms: u64,
 }
 
+/// The embedding backend selected by `EMBEDDING_BACKEND`
+fn embedding_service() -> Result<Arc<dyn EmbeddingService>, ApiError> {
+    EmbeddingBackend::from_env()
+        .map(EmbeddingBackend::into_service)
+        .map_err(|e| ApiError::Internal(format!("Embedding backend misconfigured: {}", e)))
+}
+
+fn meta_str(meta: &serde_json::Value, key: &str) -> Option<String> {
+    meta.get(key).and_then(|v| v.as_str()).map(|s| s.to_string())
+}
+
 /// Semantic search endpoint
+///
+/// Embeds the query with the configured backend and returns the repo's
+/// entries nearest to it by cosine distance.
 async fn semantic_search(
     State(state): State<AppState>,
     auth: AuthContext,
@@ -106,61 +120,70 @@ async fn semantic_search(
         return Err(ApiError::Forbidden("Access denied".to_string()));
     }
 
-    // Create embedding service (in production, this would be injected)
-    let embedding_service = Arc::new(MockEmbeddingService::new());
-    
-    // Build search request
-    let mut filters = HashMap::new();
-    if let Some(repo) = params.repo {
-        filters.insert("repo_name".to_string(), serde_json::Value::String(repo));
-    }
-    if let Some(classification) = params.classification {
-        filters.insert("classification".to_string(), serde_json::Value::String(classification));
-    }
-    
-    let search_request = SemanticSearchRequest {
-        query: params.q.clone(),
-        limit: params.limit,
-        threshold: params.threshold,
-        filters: if filters.is_empty() { None } else { Some(filters) },
-    };
-    
-    // Perform semantic search
-    let search_response = embedding_service.semantic_search(&search_request).await
-        .map_err(|e| ApiError::Internal(format!("Semantic search failed: {}", e)))?;
-    
-    // Convert results to API format
-    let results: Vec<SemanticSearchResult> = search_response.results.into_iter().map(|result| {
-        SemanticSearchResult {
-            id: result.id,
-            title: result.title,
-            description: result.description,
-            url: result.url,
-            similarity_score: result.similarity_score,
-            source_type: result.source_type,
-            repo_name: result.metadata.get("repo_name").and_then(|v| v.as_str()).map(|s| s.to_string()),
-            path: result.metadata.get("path").and_then(|v| v.as_str()).map(|s| s.to_string()),
-            content_type: result.metadata.get("content_type").and_then(|v| v.as_str()).map(|s| s.to_string()),
-            size: result.metadata.get("size").and_then(|v| v.as_u64()),
-            modified_at: result.metadata.get("modified_at")
-                .and_then(|v| v.as_str())
-                .and_then(|s| chrono::DateTime::parse_from_rfc3339(s).ok())
-                .map(|dt| dt.with_timezone(&chrono::Utc)),
-            tags: result.metadata.get("tags")
-                .and_then(|v| v.as_array())
-                .map(|arr| arr.iter().filter_map(|v| v.as_str().map(|s| s.to_string())).collect())
-                .unwrap_or_default(),
-            classification: result.metadata.get("classification").and_then(|v| v.as_str()).map(|s| s.to_string()),
-        }
-    }).collect();
-    
+    let start_time = std::time::Instant::now();
+    let repo = params
+        .repo
+        .clone()
+        .ok_or_else(|| ApiError::InvalidRequest("Semantic search requires a repo".to_string()))?;
+    let repo_info = state.index.get_repo_by_name(&repo).await?;
+    crate::require_permission(&state, repo_info.id.0, &auth, Permission::Read).await?;
+
+    // Embed the query with the same backend that embedded the entries
+    let embedding_service = embedding_service()?;
+    let query_embedding = embedding_service
+        .generate_embedding(&EmbeddingRequest {
+            text: params.q.clone(),
+            metadata: None,
+        })
+        .await
+        .map_err(|e| ApiError::Internal(format!("Query embedding failed: {}", e)))?;
+
+    let limit = params.limit.unwrap_or(10).min(100);
+    let nearest = state
+        .index
+        .nearest_entries(repo_info.id.0, &query_embedding.embedding, limit as u32)
+        .await?;
+
+    let results: Vec<SemanticSearchResult> = nearest
+        .into_iter()
+        .filter(|(_, similarity)| params.threshold.map_or(true, |threshold| *similarity >= threshold))
+        .filter(|(entry, _)| {
+            params.classification.as_ref().map_or(true, |classification| {
+                meta_str(&entry.meta, "classification").as_ref() == Some(classification)
+            })
+        })
+        .map(|(entry, similarity)| {
+            let file_name = entry.path.rsplit('/').next().unwrap_or(&entry.path).to_string();
+            SemanticSearchResult {
+                id: entry.id.0.to_string(),
+                title: meta_str(&entry.meta, "file_name").unwrap_or(file_name),
+                description: meta_str(&entry.meta, "description"),
+                url: format!("/v1/repos/{}/blob/main/{}", repo, entry.path),
+                similarity_score: similarity,
+                source_type: "internal".to_string(),
+                repo_name: Some(repo.clone()),
+                content_type: meta_str(&entry.meta, "file_type"),
+                size: entry.meta.get("file_size").and_then(|v| v.as_u64()),
+                modified_at: Some(entry.created_at),
+                tags: entry
+                    .meta
+                    .get("tags")
+                    .and_then(|v| v.as_array())
+                    .map(|arr| arr.iter().filter_map(|v| v.as_str().map(|s| s.to_string())).collect())
+                    .unwrap_or_default(),
+                classification: meta_str(&entry.meta, "classification"),
+                path: Some(entry.path),
+            }
+        })
+        .collect();
+
     let response = SemanticSearchApiResponse {
+        total: results.len(),
         results,
-        total: search_response.total,
-        processing_time_ms: search_response.processing_time_ms,
+        processing_time_ms: start_time.elapsed().as_millis() as u64,
         query: params.q,
     };
-    
+
     Ok(AxumJson(ApiResponse::success(response)))
 }
 
@@ -183,8 +206,7 @@ async fn generate_embedding(
         return Err(ApiError::Forbidden("Access denied".to_string()));
     }
 
-    // Create embedding service
-    let embedding_service = Arc::new(MockEmbeddingService::new());
+    let embedding_service = embedding_service()?;
     
     // Generate embedding
     let request = EmbeddingRequest {
@@ -259,8 +281,7 @@ async fn get_embedding_model_info(
         return Err(ApiError::Forbidden("Access denied".to_string()));
     }
 
-    // Create embedding service
-    let embedding_service = Arc::new(MockEmbeddingService::new());
+    let embedding_service = embedding_service()?;
     let config = embedding_service.get_model_config();
     
     let model_info = serde_json::json!({
@@ -1,22 +1,31 @@
 // Signed URL Constraints System
 // Implements optional IP CIDR restrictions, user agent pinning capabilities
 // Enforces max rate per URL on gateway and time-based access controls
+// Access tokens carry the constraint ids so the gateway can re-check each fetch
 
 use axum::{
     extract::{Query, State},
-    http::{HeaderMap, StatusCode},
+    http::HeaderMap,
     response::Json,
     routing::{get, post},
     Router,
 };
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sqlx::{PgPool, Row};
 use std::collections::HashMap;
-use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::net::IpAddr;
 use std::sync::Arc;
-use tokio::sync::RwLock;
 use uuid::Uuid;
-use chrono::{DateTime, Utc, Duration};
-use ipnet::{IpNet, Ipv4Net, Ipv6Net};
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use ipnet::IpNet;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use base64::{Engine as _, engine::general_purpose};
+
+use crate::geoip::{GeoIpResolver, UnknownLocationPolicy};
+use crate::signed_url_rate_limit::{InMemoryRateLimitStore, RateDecision, RateLimitStore, RateTier};
+use crate::auth::require_admin;
+use crate::{extract_auth, ApiError, ApiResult, AppState};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SignedUrlConstraint {
@@ -114,6 +123,8 @@ pub struct DeviceFingerprinting {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SignedUrlRequest {
+    /// Id the constraints were registered against
+    pub url_id: Uuid,
     pub url: String,
     pub method: String,
     pub headers: HashMap<String, String>,
@@ -171,18 +182,125 @@ pub enum ViolationSeverity {
     Critical,
 }
 
-pub struct SignedUrlConstraintService {
-    constraints: Arc<RwLock<Vec<SignedUrlConstraint>>>,
-    violations: Arc<RwLock<Vec<ConstraintViolation>>>,
-    rate_limiters: Arc<RwLock<HashMap<String, RateLimiterState>>>,
-    ip_geolocation: Arc<RwLock<HashMap<String, GeographicInfo>>>,
+type HmacSha256 = Hmac<Sha256>;
+
+/// What an access token vouches for: one method on one object, under the
+/// constraints checked when its URL was issued
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccessTokenClaims {
+    pub url_id: Uuid,
+    /// Object key the URL was presigned for
+    pub key: String,
+    pub method: String,
+    pub constraints: Vec<Uuid>,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum AccessTokenError {
+    #[error("malformed access token")]
+    Malformed,
+    #[error("access token signature does not match")]
+    BadSignature,
+    #[error("access token expired at {0}")]
+    Expired(DateTime<Utc>),
+    #[error("access token was issued for a different object or method")]
+    Mismatch,
+    #[error("failed to check access token constraints: {0}")]
+    Lookup(String),
+}
+
+/// Read `SIGNED_URL_TOKEN_SECRET`.
+///
+/// Without it a random per-process secret is used, so tokens only verify
+/// on the replica that minted them.
+pub fn token_secret_from_env() -> Vec<u8> {
+    match std::env::var("SIGNED_URL_TOKEN_SECRET") {
+        Ok(secret) if !secret.is_empty() => secret.into_bytes(),
+        _ => {
+            tracing::warn!("SIGNED_URL_TOKEN_SECRET not set; access tokens will not verify across replicas");
+            random_secret()
+        }
+    }
+}
+
+/// Proxies whose forwarding headers are believed when resolving a client address
+///
+/// Anyone can send `X-Forwarded-For`, so only hops appended by a listed
+/// proxy count; with no proxies configured the socket peer is the client.
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies(Vec<IpNet>);
+
+impl TrustedProxies {
+    pub fn new(cidrs: Vec<IpNet>) -> Self {
+        Self(cidrs)
+    }
+
+    /// Read comma-separated CIDRs from `TRUSTED_PROXIES`
+    pub fn from_env() -> Self {
+        let Ok(value) = std::env::var("TRUSTED_PROXIES") else {
+            return Self::default();
+        };
+        let cidrs = value
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .filter_map(|entry| match entry.parse::<IpNet>() {
+                Ok(cidr) => Some(cidr),
+                Err(_) => match entry.parse::<IpAddr>() {
+                    Ok(ip) => Some(IpNet::from(ip)),
+                    Err(e) => {
+                        tracing::warn!("Ignoring trusted proxy {:?}: {}", entry, e);
+                        None
+                    }
+                },
+            })
+            .collect();
+        Self(cidrs)
+    }
+
+    fn trusts(&self, ip: &IpAddr) -> bool {
+        self.0.iter().any(|cidr| cidr.contains(ip))
+    }
+
+    /// Walk `X-Forwarded-For` right to left from `peer` while each hop is a trusted proxy
+    pub fn client_ip(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        let mut client = peer;
+        if !self.trusts(&client) {
+            return client;
+        }
+        let hops: Vec<&str> = headers
+            .get_all("x-forwarded-for")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .collect();
+        for hop in hops.into_iter().rev() {
+            match hop.parse::<IpAddr>() {
+                Ok(ip) => client = ip,
+                Err(_) => break,
+            }
+            if !self.trusts(&client) {
+                break;
+            }
+        }
+        client
+    }
 }
 
-#[derive(Debug, Clone)]
-pub struct RateLimiterState {
-    pub requests: Vec<DateTime<Utc>>,
-    pub last_reset: DateTime<Utc>,
-    pub current_window: u64,
+fn random_secret() -> Vec<u8> {
+    let mut secret = Uuid::new_v4().as_bytes().to_vec();
+    secret.extend_from_slice(Uuid::new_v4().as_bytes());
+    secret
+}
+
+pub struct SignedUrlConstraintService {
+    pool: PgPool,
+    rate_limits: Arc<dyn RateLimitStore>,
+    geoip: Arc<GeoIpResolver>,
+    token_secret: Arc<Vec<u8>>,
+    trusted_proxies: TrustedProxies,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -196,13 +314,127 @@ pub struct GeographicInfo {
 }
 
 impl SignedUrlConstraintService {
-    pub fn new() -> Self {
+    pub fn new(pool: PgPool) -> Self {
         Self {
-            constraints: Arc::new(RwLock::new(Vec::new())),
-            violations: Arc::new(RwLock::new(Vec::new())),
-            rate_limiters: Arc::new(RwLock::new(HashMap::new())),
-            ip_geolocation: Arc::new(RwLock::new(HashMap::new())),
+            pool,
+            rate_limits: Arc::new(InMemoryRateLimitStore::new()),
+            geoip: Arc::new(GeoIpResolver::disabled(UnknownLocationPolicy::Allow)),
+            token_secret: Arc::new(random_secret()),
+            trusted_proxies: TrustedProxies::default(),
+        }
+    }
+
+    /// Sign access tokens with `secret`; replicas behind one gateway must share it
+    pub fn with_token_secret(mut self, secret: impl Into<Vec<u8>>) -> Self {
+        self.token_secret = Arc::new(secret.into());
+        self
+    }
+
+    /// Believe forwarding headers only when they arrive through `proxies`
+    pub fn with_trusted_proxies(mut self, proxies: TrustedProxies) -> Self {
+        self.trusted_proxies = proxies;
+        self
+    }
+
+    /// The address constraints are checked against for a request from `peer`
+    pub fn client_ip(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        self.trusted_proxies.client_ip(peer, headers)
+    }
+
+    fn token_mac(&self, payload: &str) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.token_secret).expect("HMAC accepts keys of any length");
+        mac.update(payload.as_bytes());
+        mac
+    }
+
+    /// Mint `{claims}.{hmac}`, both base64url without padding
+    pub fn mint_access_token(&self, claims: &AccessTokenClaims) -> String {
+        let payload = general_purpose::URL_SAFE_NO_PAD
+            .encode(serde_json::to_vec(claims).expect("access token claims serialize"));
+        let signature = self.token_mac(&payload).finalize().into_bytes();
+        format!("{}.{}", payload, general_purpose::URL_SAFE_NO_PAD.encode(signature))
+    }
+
+    /// Check a token's signature and expiry and return its claims
+    pub fn decode_access_token(&self, token: &str, now: DateTime<Utc>) -> Result<AccessTokenClaims, AccessTokenError> {
+        let (payload, signature) = token.split_once('.').ok_or(AccessTokenError::Malformed)?;
+        let signature = general_purpose::URL_SAFE_NO_PAD
+            .decode(signature)
+            .map_err(|_| AccessTokenError::Malformed)?;
+        self.token_mac(payload)
+            .verify_slice(&signature)
+            .map_err(|_| AccessTokenError::BadSignature)?;
+
+        let claims: AccessTokenClaims = general_purpose::URL_SAFE_NO_PAD
+            .decode(payload)
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .ok_or(AccessTokenError::Malformed)?;
+        if claims.expires_at <= now {
+            return Err(AccessTokenError::Expired(claims.expires_at));
         }
+        Ok(claims)
+    }
+
+    /// Re-check a token against the request presenting it.
+    ///
+    /// The token must be signed by this service, unexpired, and issued for
+    /// the request's URL id, object and method. Of `constraints`, those named
+    /// in the token are evaluated again, so IP, user-agent and time limits
+    /// hold for every fetch and not just at issuance.
+    pub async fn check_access_token(
+        &self,
+        token: &str,
+        request: &SignedUrlRequest,
+        constraints: &[SignedUrlConstraint],
+    ) -> Result<ValidationResult, AccessTokenError> {
+        let claims = self.decode_access_token(token, Utc::now())?;
+        if claims.url_id != request.url_id
+            || claims.key != request.url
+            || !claims.method.eq_ignore_ascii_case(&request.method)
+        {
+            return Err(AccessTokenError::Mismatch);
+        }
+
+        let pinned: Vec<SignedUrlConstraint> = constraints
+            .iter()
+            .filter(|c| claims.constraints.contains(&c.id))
+            .cloned()
+            .collect();
+        Ok(self.evaluate_constraints(&pinned, request).await)
+    }
+
+    /// `check_access_token` against the URL's stored constraints, recording violations
+    pub async fn verify_access_token(
+        &self,
+        token: &str,
+        request: &SignedUrlRequest,
+    ) -> Result<ValidationResult, AccessTokenError> {
+        let constraints = self
+            .load_constraints(request.url_id)
+            .await
+            .map_err(|e| AccessTokenError::Lookup(e.to_string()))?;
+        let result = self.check_access_token(token, request, &constraints).await?;
+
+        for violation in &result.violations {
+            self.record_violation(violation)
+                .await
+                .map_err(|e| AccessTokenError::Lookup(e.to_string()))?;
+        }
+
+        Ok(result)
+    }
+
+    /// Resolve client locations with `geoip` for geographic restrictions
+    pub fn with_geoip(mut self, geoip: GeoIpResolver) -> Self {
+        self.geoip = Arc::new(geoip);
+        self
+    }
+
+    /// Count rate-limited requests in `store`, shared across replicas
+    pub fn with_rate_limit_store(mut self, store: impl RateLimitStore + 'static) -> Self {
+        self.rate_limits = Arc::new(store);
+        self
     }
 
     /// Create a new signed URL constraint
@@ -223,117 +455,141 @@ impl SignedUrlConstraintService {
             active: true,
         };
 
-        let mut constraints = self.constraints.write().await;
-        constraints.push(constraint.clone());
+        sqlx::query(
+            r#"
+            INSERT INTO signed_url_constraint (id, url_id, constraint_type, configuration, created_at, expires_at, active)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+        )
+        .bind(constraint.id)
+        .bind(constraint.url_id)
+        .bind(enum_to_text(&constraint.constraint_type)?)
+        .bind(serde_json::to_value(&constraint.configuration)?)
+        .bind(constraint.created_at)
+        .bind(constraint.expires_at)
+        .bind(constraint.active)
+        .execute(&self.pool)
+        .await?;
 
         Ok(constraint)
     }
 
+    /// Active, unexpired constraints registered for a URL
+    async fn load_constraints(&self, url_id: Uuid) -> Result<Vec<SignedUrlConstraint>, Box<dyn std::error::Error + Send + Sync>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, url_id, constraint_type, configuration, created_at, expires_at, active
+            FROM signed_url_constraint
+            WHERE url_id = $1 AND active AND (expires_at IS NULL OR expires_at > NOW())
+            ORDER BY created_at
+            "#,
+        )
+        .bind(url_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| -> Result<SignedUrlConstraint, Box<dyn std::error::Error + Send + Sync>> {
+                Ok(SignedUrlConstraint {
+                    id: row.try_get("id")?,
+                    url_id: row.try_get("url_id")?,
+                    constraint_type: enum_from_text(&row.try_get::<String, _>("constraint_type")?)?,
+                    configuration: serde_json::from_value(row.try_get::<serde_json::Value, _>("configuration")?)?,
+                    created_at: row.try_get("created_at")?,
+                    expires_at: row.try_get("expires_at")?,
+                    active: row.try_get("active")?,
+                })
+            })
+            .collect()
+    }
+
     /// Validate signed URL request against constraints
     pub async fn validate_request(
         &self,
         request: &SignedUrlRequest,
     ) -> Result<ValidationResult, Box<dyn std::error::Error + Send + Sync>> {
-        let constraints = self.constraints.read().await;
+        let constraints = self
+            .load_constraints(request.url_id)
+            .await?;
+        let result = self.evaluate_constraints(&constraints, request).await;
+
+        // Record violations
+        for violation in &result.violations {
+            self.record_violation(violation).await?;
+        }
+
+        Ok(result)
+    }
+
+    /// Check a request against already-selected constraints
+    pub async fn evaluate_constraints(
+        &self,
+        constraints: &[SignedUrlConstraint],
+        request: &SignedUrlRequest,
+    ) -> ValidationResult {
         let applicable_constraints: Vec<&SignedUrlConstraint> = constraints
             .iter()
-            .filter(|c| c.url_id == Uuid::parse_str(&request.url).unwrap_or_default())
+            .filter(|c| c.url_id == request.url_id)
             .filter(|c| c.active)
-            .filter(|c| c.expires_at.is_none() || c.expires_at.unwrap() > Utc::now())
+            .filter(|c| c.expires_at.is_none_or(|expires_at| expires_at > Utc::now()))
             .collect();
 
         let mut violations = Vec::new();
-        let mut warnings = Vec::new();
-
-        for constraint in applicable_constraints {
-            match &constraint.constraint_type {
-                ConstraintType::IpCidrRestriction => {
-                    if let Some(ip_restrictions) = &constraint.configuration.ip_cidr_restrictions {
-                        match self.validate_ip_cidr(request.client_ip.as_str(), ip_restrictions).await {
-                            Ok(_) => {}
-                            Err(violation) => {
-                                violations.push(violation);
-                            }
-                        }
-                    }
-                }
-                ConstraintType::UserAgentPinning => {
-                    if let Some(ua_pinning) = &constraint.configuration.user_agent_pinning {
-                        match self.validate_user_agent(request.user_agent.as_str(), ua_pinning).await {
-                            Ok(_) => {}
-                            Err(violation) => {
-                                violations.push(violation);
-                            }
-                        }
-                    }
-                }
-                ConstraintType::RateLimit => {
-                    if let Some(rate_limit) = &constraint.configuration.rate_limit {
-                        match self.validate_rate_limit(request, rate_limit).await {
-                            Ok(_) => {}
-                            Err(violation) => {
-                                violations.push(violation);
-                            }
-                        }
-                    }
-                }
-                ConstraintType::TimeBasedAccess => {
-                    if let Some(time_access) = &constraint.configuration.time_based_access {
-                        match self.validate_time_based_access(time_access).await {
-                            Ok(_) => {}
-                            Err(violation) => {
-                                violations.push(violation);
-                            }
-                        }
-                    }
-                }
-                ConstraintType::GeographicRestriction => {
-                    if let Some(geo_restriction) = &constraint.configuration.geographic_restriction {
-                        match self.validate_geographic_restriction(request.client_ip.as_str(), geo_restriction).await {
-                            Ok(_) => {}
-                            Err(violation) => {
-                                violations.push(violation);
-                            }
-                        }
-                    }
-                }
-                ConstraintType::DeviceFingerprinting => {
-                    if let Some(device_fp) = &constraint.configuration.device_fingerprinting {
-                        match self.validate_device_fingerprint(request, device_fp).await {
-                            Ok(_) => {}
-                            Err(violation) => {
-                                violations.push(violation);
-                            }
-                        }
-                    }
-                }
-            }
-        }
+        let warnings = Vec::new();
 
-        // Record violations
-        for violation in &violations {
-            self.record_violation(violation).await?;
+        for constraint in &applicable_constraints {
+            let outcome = match &constraint.constraint_type {
+                ConstraintType::IpCidrRestriction => match &constraint.configuration.ip_cidr_restrictions {
+                    Some(ip_restrictions) => self.validate_ip_cidr(constraint, request.client_ip.as_str(), ip_restrictions).await,
+                    None => Ok(()),
+                },
+                ConstraintType::UserAgentPinning => match &constraint.configuration.user_agent_pinning {
+                    Some(ua_pinning) => self.validate_user_agent(constraint, request.user_agent.as_str(), ua_pinning).await,
+                    None => Ok(()),
+                },
+                ConstraintType::RateLimit => match &constraint.configuration.rate_limit {
+                    Some(rate_limit) => self.validate_rate_limit(constraint, request, rate_limit).await,
+                    None => Ok(()),
+                },
+                ConstraintType::TimeBasedAccess => match &constraint.configuration.time_based_access {
+                    Some(time_access) => self.validate_time_based_access(constraint, time_access).await,
+                    None => Ok(()),
+                },
+                ConstraintType::GeographicRestriction => match &constraint.configuration.geographic_restriction {
+                    Some(geo_restriction) => self.validate_geographic_restriction(constraint, request.client_ip.as_str(), geo_restriction).await,
+                    None => Ok(()),
+                },
+                ConstraintType::DeviceFingerprinting => match &constraint.configuration.device_fingerprinting {
+                    Some(device_fp) => self.validate_device_fingerprint(constraint, request, device_fp).await,
+                    None => Ok(()),
+                },
+            };
+
+            if let Err(violation) = outcome {
+                violations.push(violation);
+            }
         }
 
-        Ok(ValidationResult {
+        ValidationResult {
             valid: violations.is_empty(),
             violations,
             warnings,
             applied_constraints: applicable_constraints.iter().map(|c| c.id).collect(),
-        })
+        }
     }
 
     /// Validate IP CIDR restrictions
     async fn validate_ip_cidr(
         &self,
+        constraint: &SignedUrlConstraint,
         client_ip: &str,
         restrictions: &IpCidrRestrictions,
     ) -> Result<(), ConstraintViolation> {
         let client_ip_addr: IpAddr = client_ip.parse()
             .map_err(|_| ConstraintViolation {
                 id: Uuid::new_v4(),
-                url_id: Uuid::new_v4(),
-                constraint_id: Uuid::new_v4(),
+                url_id: constraint.url_id,
+                constraint_id: constraint.id,
                 violation_type: ViolationType::IpCidrViolation,
                 client_ip: client_ip.to_string(),
                 user_agent: String::new(),
@@ -352,8 +608,8 @@ impl SignedUrlConstraintService {
             let cidr: IpNet = blocked_cidr.parse()
                 .map_err(|_| ConstraintViolation {
                     id: Uuid::new_v4(),
-                    url_id: Uuid::new_v4(),
-                    constraint_id: Uuid::new_v4(),
+                    url_id: constraint.url_id,
+                    constraint_id: constraint.id,
                     violation_type: ViolationType::IpCidrViolation,
                     client_ip: client_ip.to_string(),
                     user_agent: String::new(),
@@ -370,8 +626,8 @@ impl SignedUrlConstraintService {
             if cidr.contains(&client_ip_addr) {
                 return Err(ConstraintViolation {
                     id: Uuid::new_v4(),
-                    url_id: Uuid::new_v4(),
-                    constraint_id: Uuid::new_v4(),
+                    url_id: constraint.url_id,
+                    constraint_id: constraint.id,
                     violation_type: ViolationType::IpCidrViolation,
                     client_ip: client_ip.to_string(),
                     user_agent: String::new(),
@@ -394,8 +650,8 @@ impl SignedUrlConstraintService {
                 let cidr: IpNet = allowed_cidr.parse()
                     .map_err(|_| ConstraintViolation {
                         id: Uuid::new_v4(),
-                        url_id: Uuid::new_v4(),
-                        constraint_id: Uuid::new_v4(),
+                        url_id: constraint.url_id,
+                        constraint_id: constraint.id,
                         violation_type: ViolationType::IpCidrViolation,
                         client_ip: client_ip.to_string(),
                         user_agent: String::new(),
@@ -418,8 +674,8 @@ impl SignedUrlConstraintService {
             if !allowed {
                 return Err(ConstraintViolation {
                     id: Uuid::new_v4(),
-                    url_id: Uuid::new_v4(),
-                    constraint_id: Uuid::new_v4(),
+                    url_id: constraint.url_id,
+                    constraint_id: constraint.id,
                     violation_type: ViolationType::IpCidrViolation,
                     client_ip: client_ip.to_string(),
                     user_agent: String::new(),
@@ -441,6 +697,7 @@ impl SignedUrlConstraintService {
     /// Validate user agent pinning
     async fn validate_user_agent(
         &self,
+        constraint: &SignedUrlConstraint,
         user_agent: &str,
         pinning: &UserAgentPinning,
     ) -> Result<(), ConstraintViolation> {
@@ -455,8 +712,8 @@ impl SignedUrlConstraintService {
             if matches {
                 return Err(ConstraintViolation {
                     id: Uuid::new_v4(),
-                    url_id: Uuid::new_v4(),
-                    constraint_id: Uuid::new_v4(),
+                    url_id: constraint.url_id,
+                    constraint_id: constraint.id,
                     violation_type: ViolationType::UserAgentViolation,
                     client_ip: String::new(),
                     user_agent: user_agent.to_string(),
@@ -491,8 +748,8 @@ impl SignedUrlConstraintService {
             if !allowed {
                 return Err(ConstraintViolation {
                     id: Uuid::new_v4(),
-                    url_id: Uuid::new_v4(),
-                    constraint_id: Uuid::new_v4(),
+                    url_id: constraint.url_id,
+                    constraint_id: constraint.id,
                     violation_type: ViolationType::UserAgentViolation,
                     client_ip: String::new(),
                     user_agent: user_agent.to_string(),
@@ -511,53 +768,67 @@ impl SignedUrlConstraintService {
         Ok(())
     }
 
-    /// Validate rate limit
+    /// Validate rate limit across the burst, minute, hour and day windows
     async fn validate_rate_limit(
         &self,
+        constraint: &SignedUrlConstraint,
         request: &SignedUrlRequest,
         rate_limit: &RateLimit,
     ) -> Result<(), ConstraintViolation> {
-        let key = format!("{}:{}", request.client_ip, request.url);
-        let now = Utc::now();
-
-        let mut rate_limiters = self.rate_limiters.write().await;
-        let limiter = rate_limiters.entry(key.clone()).or_insert_with(|| RateLimiterState {
-            requests: Vec::new(),
-            last_reset: now,
-            current_window: 0,
-        });
+        let key = format!("{}:{}", constraint.id, request.client_ip);
+        let tiers = RateTier::from_rate_limit(rate_limit);
+        if tiers.is_empty() {
+            return Ok(());
+        }
 
-        // Clean old requests
-        limiter.requests.retain(|&timestamp| now - timestamp < Duration::seconds(rate_limit.window_size_seconds as i64));
-        limiter.requests.push(now);
+        let now = Utc::now();
+        let decision = match self.rate_limits.acquire(&key, &tiers, now.timestamp_millis()).await {
+            Ok(decision) => decision,
+            Err(e) => {
+                // An unreachable counter store must not take downloads offline
+                tracing::warn!("Rate limit check failed for constraint {}: {}", constraint.id, e);
+                return Ok(());
+            }
+        };
 
-        let request_count = limiter.requests.len() as u32;
+        match decision {
+            RateDecision::Allowed => Ok(()),
+            RateDecision::Limited { tier, retry_after_ms } => {
+                let tier = &tiers[tier];
+                let period = if tier.name == "burst" {
+                    format!("{} seconds", rate_limit.window_size_seconds.max(1))
+                } else {
+                    tier.name.to_string()
+                };
+                let retry_after_seconds = (retry_after_ms.max(0) as u64).div_ceil(1000);
 
-        if request_count > rate_limit.requests_per_minute {
-            return Err(ConstraintViolation {
-                id: Uuid::new_v4(),
-                url_id: Uuid::new_v4(),
-                constraint_id: Uuid::new_v4(),
-                violation_type: ViolationType::RateLimitExceeded,
-                client_ip: request.client_ip.clone(),
-                user_agent: request.user_agent.clone(),
-                timestamp: now,
-                details: ViolationDetails {
-                    constraint_value: format!("Max {} requests per minute", rate_limit.requests_per_minute),
-                    actual_value: request_count.to_string(),
-                    severity: ViolationSeverity::High,
-                    context: HashMap::new(),
-                },
-                action_taken: rate_limit.enforcement_action.clone(),
-            });
+                Err(ConstraintViolation {
+                    id: Uuid::new_v4(),
+                    url_id: constraint.url_id,
+                    constraint_id: constraint.id,
+                    violation_type: ViolationType::RateLimitExceeded,
+                    client_ip: request.client_ip.clone(),
+                    user_agent: request.user_agent.clone(),
+                    timestamp: now,
+                    details: ViolationDetails {
+                        constraint_value: format!("Max {} requests per {}", tier.limit, period),
+                        actual_value: (tier.limit + 1).to_string(),
+                        severity: ViolationSeverity::High,
+                        context: HashMap::from([
+                            ("tier".to_string(), tier.name.to_string()),
+                            ("retry_after_seconds".to_string(), retry_after_seconds.to_string()),
+                        ]),
+                    },
+                    action_taken: rate_limit.enforcement_action.clone(),
+                })
+            }
         }
-
-        Ok(())
     }
 
     /// Validate time-based access
     async fn validate_time_based_access(
         &self,
+        constraint: &SignedUrlConstraint,
         time_access: &TimeBasedAccess,
     ) -> Result<(), ConstraintViolation> {
         let now = Utc::now();
@@ -568,8 +839,8 @@ impl SignedUrlConstraintService {
         if !time_access.allowed_hours.is_empty() && !time_access.allowed_hours.contains(&current_hour) {
             return Err(ConstraintViolation {
                 id: Uuid::new_v4(),
-                url_id: Uuid::new_v4(),
-                constraint_id: Uuid::new_v4(),
+                url_id: constraint.url_id,
+                constraint_id: constraint.id,
                 violation_type: ViolationType::TimeRestrictionViolation,
                 client_ip: String::new(),
                 user_agent: String::new(),
@@ -588,8 +859,8 @@ impl SignedUrlConstraintService {
         if !time_access.allowed_days.is_empty() && !time_access.allowed_days.contains(&current_day) {
             return Err(ConstraintViolation {
                 id: Uuid::new_v4(),
-                url_id: Uuid::new_v4(),
-                constraint_id: Uuid::new_v4(),
+                url_id: constraint.url_id,
+                constraint_id: constraint.id,
                 violation_type: ViolationType::TimeRestrictionViolation,
                 client_ip: String::new(),
                 user_agent: String::new(),
@@ -610,53 +881,66 @@ impl SignedUrlConstraintService {
     /// Validate geographic restriction
     async fn validate_geographic_restriction(
         &self,
+        constraint: &SignedUrlConstraint,
         client_ip: &str,
         geo_restriction: &GeographicRestriction,
     ) -> Result<(), ConstraintViolation> {
-        // In a real implementation, you would use a geolocation service
-        // For now, we'll simulate the check
-        let geo_info = self.get_geographic_info(client_ip).await;
+        let violation = |constraint_value: String, actual_value: String| ConstraintViolation {
+            id: Uuid::new_v4(),
+            url_id: constraint.url_id,
+            constraint_id: constraint.id,
+            violation_type: ViolationType::GeographicViolation,
+            client_ip: client_ip.to_string(),
+            user_agent: String::new(),
+            timestamp: Utc::now(),
+            details: ViolationDetails {
+                constraint_value,
+                actual_value,
+                severity: ViolationSeverity::High,
+                context: HashMap::new(),
+            },
+            action_taken: EnforcementAction::Block,
+        };
 
-        if let Some(geo_info) = geo_info {
-            // Check blocked countries
-            if geo_restriction.blocked_countries.contains(&geo_info.country) {
-                return Err(ConstraintViolation {
-                    id: Uuid::new_v4(),
-                    url_id: Uuid::new_v4(),
-                    constraint_id: Uuid::new_v4(),
-                    violation_type: ViolationType::GeographicViolation,
-                    client_ip: client_ip.to_string(),
-                    user_agent: String::new(),
-                    timestamp: Utc::now(),
-                    details: ViolationDetails {
-                        constraint_value: format!("Not in blocked countries: {:?}", geo_restriction.blocked_countries),
-                        actual_value: geo_info.country,
-                        severity: ViolationSeverity::High,
-                        context: HashMap::new(),
-                    },
-                    action_taken: EnforcementAction::Block,
-                });
+        let geo_info = match self.get_geographic_info(client_ip).await {
+            Some(geo_info) => geo_info,
+            None => {
+                return match self.geoip.unknown_policy() {
+                    UnknownLocationPolicy::Allow => Ok(()),
+                    UnknownLocationPolicy::Deny => Err(violation("Resolvable location".to_string(), "unknown".to_string())),
+                };
             }
+        };
 
-            // Check allowed countries
-            if !geo_restriction.allowed_countries.is_empty() && !geo_restriction.allowed_countries.contains(&geo_info.country) {
-                return Err(ConstraintViolation {
-                    id: Uuid::new_v4(),
-                    url_id: Uuid::new_v4(),
-                    constraint_id: Uuid::new_v4(),
-                    violation_type: ViolationType::GeographicViolation,
-                    client_ip: client_ip.to_string(),
-                    user_agent: String::new(),
-                    timestamp: Utc::now(),
-                    details: ViolationDetails {
-                        constraint_value: format!("In allowed countries: {:?}", geo_restriction.allowed_countries),
-                        actual_value: geo_info.country,
-                        severity: ViolationSeverity::High,
-                        context: HashMap::new(),
-                    },
-                    action_taken: EnforcementAction::Block,
-                });
-            }
+        // Check blocked countries
+        if geo_restriction.blocked_countries.contains(&geo_info.country) {
+            return Err(violation(
+                format!("Not in blocked countries: {:?}", geo_restriction.blocked_countries),
+                geo_info.country,
+            ));
+        }
+
+        // Check allowed countries
+        if !geo_restriction.allowed_countries.is_empty() && !geo_restriction.allowed_countries.contains(&geo_info.country) {
+            return Err(violation(
+                format!("In allowed countries: {:?}", geo_restriction.allowed_countries),
+                geo_info.country,
+            ));
+        }
+
+        // Regions are ISO 3166-2 codes, e.g. "US-WA"
+        let region = format!("{}-{}", geo_info.country, geo_info.region);
+        if !geo_info.region.is_empty() && geo_restriction.blocked_regions.contains(&region) {
+            return Err(violation(
+                format!("Not in blocked regions: {:?}", geo_restriction.blocked_regions),
+                region,
+            ));
+        }
+        if !geo_restriction.allowed_regions.is_empty() && !geo_restriction.allowed_regions.contains(&region) {
+            return Err(violation(
+                format!("In allowed regions: {:?}", geo_restriction.allowed_regions),
+                region,
+            ));
         }
 
         Ok(())
@@ -665,6 +949,7 @@ impl SignedUrlConstraintService {
     /// Validate device fingerprinting
     async fn validate_device_fingerprint(
         &self,
+        constraint: &SignedUrlConstraint,
         request: &SignedUrlRequest,
         device_fp: &DeviceFingerprinting,
     ) -> Result<(), ConstraintViolation> {
@@ -677,8 +962,8 @@ impl SignedUrlConstraintService {
             if fingerprint.contains(blocked_attr) {
                 return Err(ConstraintViolation {
                     id: Uuid::new_v4(),
-                    url_id: Uuid::new_v4(),
-                    constraint_id: Uuid::new_v4(),
+                    url_id: constraint.url_id,
+                    constraint_id: constraint.id,
                     violation_type: ViolationType::DeviceFingerprintViolation,
                     client_ip: request.client_ip.clone(),
                     user_agent: request.user_agent.clone(),
@@ -699,24 +984,7 @@ impl SignedUrlConstraintService {
 
     /// Get geographic information for IP
     async fn get_geographic_info(&self, ip: &str) -> Option<GeographicInfo> {
-        let mut geo_cache = self.ip_geolocation.write().await;
-        
-        if let Some(info) = geo_cache.get(ip) {
-            return Some(info.clone());
-        }
-
-        // In a real implementation, you would call a geolocation service
-        let geo_info = GeographicInfo {
-            country: "US".to_string(),
-            region: "CA".to_string(),
-            city: "San Francisco".to_string(),
-            isp: "Example ISP".to_string(),
-            latitude: 37.7749,
-            longitude: -122.4194,
-        };
-
-        geo_cache.insert(ip.to_string(), geo_info.clone());
-        Some(geo_info)
+        self.geoip.lookup(ip).await
     }
 
     /// Generate device fingerprint
@@ -736,47 +1004,116 @@ impl SignedUrlConstraintService {
 
     /// Record constraint violation
     async fn record_violation(&self, violation: &ConstraintViolation) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let mut violations = self.violations.write().await;
-        violations.push(violation.clone());
+        sqlx::query(
+            r#"
+            INSERT INTO signed_url_violation
+                (id, url_id, constraint_id, violation_type, client_ip, user_agent, occurred_at, details, action_taken)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            "#,
+        )
+        .bind(violation.id)
+        .bind(violation.url_id)
+        .bind(violation.constraint_id)
+        .bind(enum_to_text(&violation.violation_type)?)
+        .bind(&violation.client_ip)
+        .bind(&violation.user_agent)
+        .bind(violation.timestamp)
+        .bind(serde_json::to_value(&violation.details)?)
+        .bind(enum_to_text(&violation.action_taken)?)
+        .execute(&self.pool)
+        .await?;
         Ok(())
     }
 
     /// Get constraint violations
     pub async fn get_violations(&self, url_id: Option<Uuid>) -> Result<Vec<ConstraintViolation>, Box<dyn std::error::Error + Send + Sync>> {
-        let violations = self.violations.read().await;
-        let filtered_violations: Vec<ConstraintViolation> = if let Some(url_id) = url_id {
-            violations.iter().filter(|v| v.url_id == url_id).cloned().collect()
-        } else {
-            violations.clone()
-        };
-        Ok(filtered_violations)
+        let rows = sqlx::query(
+            r#"
+            SELECT id, url_id, constraint_id, violation_type, client_ip, user_agent, occurred_at, details, action_taken
+            FROM signed_url_violation
+            WHERE $1::uuid IS NULL OR url_id = $1
+            ORDER BY occurred_at DESC
+            "#,
+        )
+        .bind(url_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| -> Result<ConstraintViolation, Box<dyn std::error::Error + Send + Sync>> {
+                Ok(ConstraintViolation {
+                    id: row.try_get("id")?,
+                    url_id: row.try_get("url_id")?,
+                    constraint_id: row.try_get("constraint_id")?,
+                    violation_type: enum_from_text(&row.try_get::<String, _>("violation_type")?)?,
+                    client_ip: row.try_get("client_ip")?,
+                    user_agent: row.try_get("user_agent")?,
+                    timestamp: row.try_get("occurred_at")?,
+                    details: serde_json::from_value(row.try_get::<serde_json::Value, _>("details")?)?,
+                    action_taken: enum_from_text(&row.try_get::<String, _>("action_taken")?)?,
+                })
+            })
+            .collect()
     }
 
     /// Get constraint statistics
     pub async fn get_constraint_statistics(&self) -> Result<ConstraintStatistics, Box<dyn std::error::Error + Send + Sync>> {
-        let constraints = self.constraints.read().await;
-        let violations = self.violations.read().await;
-
-        let total_constraints = constraints.len();
-        let active_constraints = constraints.iter().filter(|c| c.active).count();
-
-        let total_violations = violations.len();
-        let ip_violations = violations.iter().filter(|v| matches!(v.violation_type, ViolationType::IpCidrViolation)).count();
-        let ua_violations = violations.iter().filter(|v| matches!(v.violation_type, ViolationType::UserAgentViolation)).count();
-        let rate_violations = violations.iter().filter(|v| matches!(v.violation_type, ViolationType::RateLimitExceeded)).count();
+        let constraints = sqlx::query(
+            "SELECT COUNT(*) AS total, COUNT(*) FILTER (WHERE active) AS active FROM signed_url_constraint",
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        let violations = sqlx::query(
+            r#"
+            SELECT
+                COUNT(*) AS total,
+                COUNT(*) FILTER (WHERE violation_type = 'IpCidrViolation') AS ip,
+                COUNT(*) FILTER (WHERE violation_type = 'UserAgentViolation') AS ua,
+                COUNT(*) FILTER (WHERE violation_type = 'RateLimitExceeded') AS rate
+            FROM signed_url_violation
+            "#,
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        let count = |row: &sqlx::postgres::PgRow, column: &str| -> Result<usize, sqlx::Error> {
+            Ok(row.try_get::<i64, _>(column)? as usize)
+        };
+        let total_constraints = count(&constraints, "total")?;
+        let total_violations = count(&violations, "total")?;
 
         Ok(ConstraintStatistics {
             total_constraints,
-            active_constraints,
+            active_constraints: count(&constraints, "active")?,
             total_violations,
-            ip_violations,
-            ua_violations,
-            rate_violations,
+            ip_violations: count(&violations, "ip")?,
+            ua_violations: count(&violations, "ua")?,
+            rate_violations: count(&violations, "rate")?,
             violation_rate: if total_constraints > 0 { total_violations as f64 / total_constraints as f64 } else { 0.0 },
         })
     }
 }
 
+/// Store unit enum variants by their serde name
+fn enum_to_text<T: Serialize>(value: &T) -> Result<String, serde_json::Error> {
+    match serde_json::to_value(value)? {
+        serde_json::Value::String(text) => Ok(text),
+        other => Ok(other.to_string()),
+    }
+}
+
+fn enum_from_text<T: DeserializeOwned>(text: &str) -> Result<T, serde_json::Error> {
+    serde_json::from_value(serde_json::Value::String(text.to_string()))
+}
+
+/// What to do with a presign request after validation
+#[derive(Debug, Clone, PartialEq)]
+pub enum Enforcement {
+    Allow,
+    Throttle(String),
+    Deny(String),
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ValidationResult {
     pub valid: bool,
@@ -785,6 +1122,33 @@ pub struct ValidationResult {
     pub applied_constraints: Vec<Uuid>,
 }
 
+impl ValidationResult {
+    /// The strictest action demanded by any violation.
+    ///
+    /// `Challenge` has no interactive flow for presigned URLs, so it denies.
+    pub fn enforcement(&self) -> Enforcement {
+        let describe = |v: &ConstraintViolation| {
+            let reason = format!("{:?}: expected {}, got {}", v.violation_type, v.details.constraint_value, v.details.actual_value);
+            match v.details.context.get("retry_after_seconds") {
+                Some(retry_after) => format!("{}; retry after {} seconds", reason, retry_after),
+                None => reason,
+            }
+        };
+
+        if let Some(violation) = self.violations.iter()
+            .find(|v| matches!(v.action_taken, EnforcementAction::Block | EnforcementAction::Challenge))
+        {
+            return Enforcement::Deny(describe(violation));
+        }
+        if let Some(violation) = self.violations.iter()
+            .find(|v| matches!(v.action_taken, EnforcementAction::Throttle))
+        {
+            return Enforcement::Throttle(describe(violation));
+        }
+        Enforcement::Allow
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConstraintStatistics {
     pub total_constraints: usize,
@@ -797,58 +1161,91 @@ pub struct ConstraintStatistics {
 }
 
 /// Signed URL constraints router
-pub fn signed_url_constraints_router() -> Router {
+pub fn signed_url_constraints_router() -> Router<AppState> {
     Router::new()
-        .route("/signed-url-constraints", post(create_constraint))
-        .route("/signed-url-constraints/validate", post(validate_request))
-        .route("/signed-url-constraints/violations", get(get_violations))
-        .route("/signed-url-constraints/statistics", get(get_constraint_statistics))
+        .route("/v1/signed-url-constraints", post(create_constraint))
+        .route("/v1/signed-url-constraints/validate", post(validate_request))
+        .route("/v1/signed-url-constraints/verify-token", post(verify_access_token))
+        .route("/v1/signed-url-constraints/violations", get(get_violations))
+        .route("/v1/signed-url-constraints/statistics", get(get_constraint_statistics))
 }
 
 /// Create constraint
 async fn create_constraint(
-    State(service): State<Arc<SignedUrlConstraintService>>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
     Json(request): Json<CreateConstraintRequest>,
-) -> Result<Json<SignedUrlConstraint>, String> {
-    let constraint = service.create_constraint(
+) -> ApiResult<Json<SignedUrlConstraint>> {
+    let auth = extract_auth(&state.auth_layer, &headers).await?;
+    require_admin(&auth)?;
+
+    let constraint = state.signed_url_constraints.create_constraint(
         request.url_id,
         request.constraint_type,
         request.configuration,
         request.expires_at,
     ).await
-        .map_err(|e| format!("Failed to create constraint: {}", e))?;
+        .map_err(|e| ApiError::Internal(format!("Failed to create constraint: {}", e)))?;
     
     Ok(Json(constraint))
 }
 
 /// Validate request
 async fn validate_request(
-    State(service): State<Arc<SignedUrlConstraintService>>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
     Json(request): Json<SignedUrlRequest>,
-) -> Result<Json<ValidationResult>, String> {
-    let result = service.validate_request(&request).await
-        .map_err(|e| format!("Failed to validate request: {}", e))?;
+) -> ApiResult<Json<ValidationResult>> {
+    let auth = extract_auth(&state.auth_layer, &headers).await?;
+    require_admin(&auth)?;
+
+    let result = state.signed_url_constraints.validate_request(&request).await
+        .map_err(|e| ApiError::Internal(format!("Failed to validate request: {}", e)))?;
     
     Ok(Json(result))
 }
 
+/// Re-check an access token on use; called by the gateway in front of S3
+async fn verify_access_token(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<VerifyAccessTokenRequest>,
+) -> ApiResult<Json<ValidationResult>> {
+    let auth = extract_auth(&state.auth_layer, &headers).await?;
+    require_admin(&auth)?;
+
+    match state.signed_url_constraints.verify_access_token(&request.token, &request.request).await {
+        Ok(result) => Ok(Json(result)),
+        Err(AccessTokenError::Lookup(e)) => Err(ApiError::Internal(format!("Failed to verify access token: {}", e))),
+        Err(e) => Err(ApiError::Forbidden(e.to_string())),
+    }
+}
+
 /// Get violations
 async fn get_violations(
-    State(service): State<Arc<SignedUrlConstraintService>>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
     Query(params): Query<GetViolationsQuery>,
-) -> Result<Json<Vec<ConstraintViolation>>, String> {
-    let violations = service.get_violations(params.url_id).await
-        .map_err(|e| format!("Failed to get violations: {}", e))?;
+) -> ApiResult<Json<Vec<ConstraintViolation>>> {
+    let auth = extract_auth(&state.auth_layer, &headers).await?;
+    require_admin(&auth)?;
+
+    let violations = state.signed_url_constraints.get_violations(params.url_id).await
+        .map_err(|e| ApiError::Internal(format!("Failed to get violations: {}", e)))?;
     
     Ok(Json(violations))
 }
 
 /// Get constraint statistics
 async fn get_constraint_statistics(
-    State(service): State<Arc<SignedUrlConstraintService>>,
-) -> Result<Json<ConstraintStatistics>, String> {
-    let stats = service.get_constraint_statistics().await
-        .map_err(|e| format!("Failed to get constraint statistics: {}", e))?;
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> ApiResult<Json<ConstraintStatistics>> {
+    let auth = extract_auth(&state.auth_layer, &headers).await?;
+    require_admin(&auth)?;
+
+    let stats = state.signed_url_constraints.get_constraint_statistics().await
+        .map_err(|e| ApiError::Internal(format!("Failed to get constraint statistics: {}", e)))?;
     
     Ok(Json(stats))
 }
@@ -861,7 +1258,375 @@ pub struct CreateConstraintRequest {
     pub expires_at: Option<DateTime<Utc>>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerifyAccessTokenRequest {
+    pub token: String,
+    /// The fetch presenting the token
+    pub request: SignedUrlRequest,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GetViolationsQuery {
     pub url_id: Option<Uuid>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service() -> SignedUrlConstraintService {
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .connect_lazy("postgres://localhost/blacklake_test")
+            .unwrap();
+        SignedUrlConstraintService::new(pool)
+    }
+
+    fn cidr_constraint(url_id: Uuid, allowed: &[&str], blocked: &[&str]) -> SignedUrlConstraint {
+        SignedUrlConstraint {
+            id: Uuid::new_v4(),
+            url_id,
+            constraint_type: ConstraintType::IpCidrRestriction,
+            configuration: ConstraintConfiguration {
+                ip_cidr_restrictions: Some(IpCidrRestrictions {
+                    allowed_cidrs: allowed.iter().map(|s| s.to_string()).collect(),
+                    blocked_cidrs: blocked.iter().map(|s| s.to_string()).collect(),
+                    allow_private_ips: true,
+                    allow_public_ips: true,
+                    log_violations: true,
+                }),
+                user_agent_pinning: None,
+                rate_limit: None,
+                time_based_access: None,
+                geographic_restriction: None,
+                device_fingerprinting: None,
+            },
+            created_at: Utc::now(),
+            expires_at: None,
+            active: true,
+        }
+    }
+
+    fn request_from(url_id: Uuid, client_ip: &str) -> SignedUrlRequest {
+        SignedUrlRequest {
+            url_id,
+            url: format!("https://s3.example.org/blacklake/{}", url_id),
+            method: "GET".to_string(),
+            headers: HashMap::new(),
+            client_ip: client_ip.to_string(),
+            user_agent: "blacklake-cli/0.1".to_string(),
+            timestamp: Utc::now(),
+            constraints: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_blocked_cidr_is_denied() {
+        let service = service();
+        let url_id = Uuid::new_v4();
+        let constraints = vec![cidr_constraint(url_id, &[], &["203.0.113.0/24"])];
+
+        let result = service.evaluate_constraints(&constraints, &request_from(url_id, "203.0.113.7")).await;
+
+        assert!(!result.valid);
+        assert_eq!(result.applied_constraints, vec![constraints[0].id]);
+        assert!(matches!(result.enforcement(), Enforcement::Deny(_)));
+    }
+
+    fn forwarded_for(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", value.parse().unwrap());
+        headers.insert("x-real-ip", value.parse().unwrap());
+        headers
+    }
+
+    #[tokio::test]
+    async fn test_spoofed_forwarded_for_does_not_escape_a_blocked_cidr() {
+        let service = service().with_trusted_proxies(TrustedProxies::new(vec!["10.0.0.0/8".parse().unwrap()]));
+        let url_id = Uuid::new_v4();
+        let constraints = vec![cidr_constraint(url_id, &[], &["203.0.113.0/24"])];
+        let peer: IpAddr = "203.0.113.7".parse().unwrap();
+
+        // The peer is not a proxy, so its forwarding headers are its own claim
+        let client_ip = service.client_ip(peer, &forwarded_for("198.51.100.1"));
+        assert_eq!(client_ip, peer);
+
+        let result = service
+            .evaluate_constraints(&constraints, &request_from(url_id, &client_ip.to_string()))
+            .await;
+        assert!(matches!(result.enforcement(), Enforcement::Deny(_)));
+    }
+
+    #[test]
+    fn test_trusted_proxy_forwarded_for_names_the_client() {
+        let proxies = TrustedProxies::new(vec!["10.0.0.0/8".parse().unwrap()]);
+        let peer: IpAddr = "10.0.0.2".parse().unwrap();
+
+        // Hops the proxies appended are skipped; a spoofed leftmost entry is not reached
+        let headers = forwarded_for("198.51.100.1, 203.0.113.7, 10.0.0.9");
+        assert_eq!(proxies.client_ip(peer, &headers), "203.0.113.7".parse::<IpAddr>().unwrap());
+
+        assert_eq!(TrustedProxies::default().client_ip(peer, &headers), peer);
+        assert_eq!(proxies.client_ip(peer, &HeaderMap::new()), peer);
+    }
+
+    #[tokio::test]
+    async fn test_registered_constraint_is_selected_by_url_id() {
+        let service = service();
+        let url_id = Uuid::new_v4();
+        let other = cidr_constraint(Uuid::new_v4(), &[], &["0.0.0.0/0"]);
+        let registered = cidr_constraint(url_id, &[], &["203.0.113.0/24"]);
+        let constraints = vec![other, registered.clone()];
+
+        let result = service.evaluate_constraints(&constraints, &request_from(url_id, "203.0.113.7")).await;
+
+        assert_eq!(result.applied_constraints, vec![registered.id]);
+        assert_eq!(result.violations.len(), 1);
+        assert_eq!(result.violations[0].constraint_id, registered.id);
+        assert_eq!(result.violations[0].url_id, url_id);
+        assert!(matches!(result.enforcement(), Enforcement::Deny(_)));
+
+        // The URL string plays no part in selection
+        let mut request = request_from(Uuid::new_v4(), "203.0.113.7");
+        request.url = url_id.to_string();
+        assert!(service.evaluate_constraints(&constraints, &request).await.applied_constraints.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_allowed_cidr_passes() {
+        let service = service();
+        let url_id = Uuid::new_v4();
+        let constraints = vec![cidr_constraint(url_id, &["10.0.0.0/8"], &["203.0.113.0/24"])];
+
+        let result = service.evaluate_constraints(&constraints, &request_from(url_id, "10.1.2.3")).await;
+        assert!(result.valid);
+        assert_eq!(result.enforcement(), Enforcement::Allow);
+
+        let result = service.evaluate_constraints(&constraints, &request_from(url_id, "192.0.2.1")).await;
+        assert!(matches!(result.enforcement(), Enforcement::Deny(_)));
+    }
+
+    #[tokio::test]
+    async fn test_inactive_and_expired_constraints_are_skipped() {
+        let service = service();
+        let url_id = Uuid::new_v4();
+        let mut inactive = cidr_constraint(url_id, &[], &["0.0.0.0/0"]);
+        inactive.active = false;
+        let mut expired = cidr_constraint(url_id, &[], &["0.0.0.0/0"]);
+        expired.expires_at = Some(Utc::now() - chrono::Duration::minutes(1));
+
+        let result = service.evaluate_constraints(&[inactive, expired], &request_from(url_id, "10.1.2.3")).await;
+        assert!(result.valid);
+        assert!(result.applied_constraints.is_empty());
+    }
+
+    fn geo_constraint(url_id: Uuid, allowed: &[&str], blocked: &[&str]) -> SignedUrlConstraint {
+        let mut constraint = cidr_constraint(url_id, &[], &[]);
+        constraint.constraint_type = ConstraintType::GeographicRestriction;
+        constraint.configuration.ip_cidr_restrictions = None;
+        constraint.configuration.geographic_restriction = Some(GeographicRestriction {
+            allowed_countries: allowed.iter().map(|s| s.to_string()).collect(),
+            blocked_countries: blocked.iter().map(|s| s.to_string()).collect(),
+            allowed_regions: vec![],
+            blocked_regions: vec![],
+            require_vpn: false,
+        });
+        constraint
+    }
+
+    fn geo_service(unknown_policy: UnknownLocationPolicy) -> SignedUrlConstraintService {
+        let db = concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/GeoIP2-City-Test.mmdb");
+        service().with_geoip(GeoIpResolver::open(db, unknown_policy).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_geographic_block_and_allow() {
+        let service = geo_service(UnknownLocationPolicy::Allow);
+        let url_id = Uuid::new_v4();
+        let constraints = vec![geo_constraint(url_id, &[], &["GB"])];
+
+        let result = service.evaluate_constraints(&constraints, &request_from(url_id, "81.2.69.142")).await;
+        assert!(matches!(result.enforcement(), Enforcement::Deny(_)));
+        assert!(matches!(result.violations[0].violation_type, ViolationType::GeographicViolation));
+        assert_eq!(result.violations[0].details.actual_value, "GB");
+
+        let result = service.evaluate_constraints(&constraints, &request_from(url_id, "89.160.20.112")).await;
+        assert_eq!(result.enforcement(), Enforcement::Allow);
+
+        let constraints = vec![geo_constraint(url_id, &["US"], &[])];
+        assert!(service.evaluate_constraints(&constraints, &request_from(url_id, "216.160.83.56")).await.valid);
+        let result = service.evaluate_constraints(&constraints, &request_from(url_id, "89.160.20.112")).await;
+        assert_eq!(result.violations[0].details.actual_value, "SE");
+    }
+
+    #[tokio::test]
+    async fn test_unknown_location_follows_policy() {
+        let url_id = Uuid::new_v4();
+        let constraints = vec![geo_constraint(url_id, &["US"], &[])];
+
+        let allow = geo_service(UnknownLocationPolicy::Allow);
+        assert!(allow.evaluate_constraints(&constraints, &request_from(url_id, "10.0.0.5")).await.valid);
+
+        let deny = geo_service(UnknownLocationPolicy::Deny);
+        let result = deny.evaluate_constraints(&constraints, &request_from(url_id, "127.0.0.1")).await;
+        assert_eq!(result.violations[0].details.actual_value, "unknown");
+    }
+
+    #[test]
+    fn test_throttle_and_log_enforcement() {
+        let violation = |action| ConstraintViolation {
+            id: Uuid::new_v4(),
+            url_id: Uuid::new_v4(),
+            constraint_id: Uuid::new_v4(),
+            violation_type: ViolationType::RateLimitExceeded,
+            client_ip: "10.1.2.3".to_string(),
+            user_agent: String::new(),
+            timestamp: Utc::now(),
+            details: ViolationDetails {
+                constraint_value: "Max 1 requests per minute".to_string(),
+                actual_value: "2".to_string(),
+                severity: ViolationSeverity::High,
+                context: HashMap::new(),
+            },
+            action_taken: action,
+        };
+        let result = |violations| ValidationResult {
+            valid: false,
+            violations,
+            warnings: vec![],
+            applied_constraints: vec![],
+        };
+
+        assert_eq!(result(vec![violation(EnforcementAction::Log)]).enforcement(), Enforcement::Allow);
+        assert!(matches!(result(vec![violation(EnforcementAction::Throttle)]).enforcement(), Enforcement::Throttle(_)));
+        assert!(matches!(
+            result(vec![violation(EnforcementAction::Throttle), violation(EnforcementAction::Block)]).enforcement(),
+            Enforcement::Deny(_)
+        ));
+    }
+
+    fn rate_constraint(url_id: Uuid, per_minute: u32, per_hour: u32) -> SignedUrlConstraint {
+        let mut constraint = cidr_constraint(url_id, &[], &[]);
+        constraint.constraint_type = ConstraintType::RateLimit;
+        constraint.configuration.ip_cidr_restrictions = None;
+        constraint.configuration.rate_limit = Some(RateLimit {
+            requests_per_minute: per_minute,
+            requests_per_hour: per_hour,
+            requests_per_day: 0,
+            burst_limit: 0,
+            window_size_seconds: 1,
+            enforcement_action: EnforcementAction::Throttle,
+        });
+        constraint
+    }
+
+    #[tokio::test]
+    async fn test_hourly_rate_limit_throttles_under_minute_limit() {
+        let service = service();
+        let url_id = Uuid::new_v4();
+        let constraints = vec![rate_constraint(url_id, 100, 3)];
+        let request = request_from(url_id, "10.1.2.3");
+
+        for _ in 0..3 {
+            assert!(service.evaluate_constraints(&constraints, &request).await.valid);
+        }
+
+        let result = service.evaluate_constraints(&constraints, &request).await;
+        let violation = &result.violations[0];
+        assert!(matches!(violation.violation_type, ViolationType::RateLimitExceeded));
+        assert_eq!(violation.details.constraint_value, "Max 3 requests per hour");
+        assert_eq!(violation.details.context["tier"], "hour");
+        let retry_after: u64 = violation.details.context["retry_after_seconds"].parse().unwrap();
+        assert!((3590..=3600).contains(&retry_after));
+        assert!(matches!(result.enforcement(), Enforcement::Throttle(reason) if reason.contains("retry after")));
+
+        // Counters are per client
+        assert!(service.evaluate_constraints(&constraints, &request_from(url_id, "10.9.9.9")).await.valid);
+    }
+
+    #[test]
+    fn test_enum_text_round_trip() {
+        assert_eq!(enum_to_text(&ConstraintType::IpCidrRestriction).unwrap(), "IpCidrRestriction");
+        let action: EnforcementAction = enum_from_text("Throttle").unwrap();
+        assert!(matches!(action, EnforcementAction::Throttle));
+    }
+
+    fn claims_for(request: &SignedUrlRequest, constraints: &[SignedUrlConstraint], expires_in: chrono::Duration) -> AccessTokenClaims {
+        AccessTokenClaims {
+            url_id: request.url_id,
+            key: request.url.clone(),
+            method: request.method.clone(),
+            constraints: constraints.iter().map(|c| c.id).collect(),
+            expires_at: Utc::now() + expires_in,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_valid_access_token_rechecks_its_constraints() {
+        let service = service();
+        let url_id = Uuid::new_v4();
+        let constraints = vec![cidr_constraint(url_id, &["10.0.0.0/8"], &[])];
+        let request = request_from(url_id, "10.1.2.3");
+        let token = service.mint_access_token(&claims_for(&request, &constraints, chrono::Duration::minutes(5)));
+
+        let result = service.check_access_token(&token, &request, &constraints).await.unwrap();
+
+        assert!(result.valid);
+        assert_eq!(result.applied_constraints, vec![constraints[0].id]);
+        assert_eq!(service.decode_access_token(&token, Utc::now()).unwrap().key, request.url);
+    }
+
+    #[tokio::test]
+    async fn test_expired_access_token_is_refused() {
+        let service = service();
+        let url_id = Uuid::new_v4();
+        let request = request_from(url_id, "10.1.2.3");
+        let claims = claims_for(&request, &[], chrono::Duration::minutes(-1));
+        let token = service.mint_access_token(&claims);
+
+        let result = service.check_access_token(&token, &request, &[]).await;
+
+        assert_eq!(result.unwrap_err(), AccessTokenError::Expired(claims.expires_at));
+    }
+
+    #[tokio::test]
+    async fn test_access_token_fails_when_its_constraints_now_fail() {
+        let service = service();
+        let url_id = Uuid::new_v4();
+        let constraints = vec![cidr_constraint(url_id, &["10.0.0.0/8"], &[])];
+        let issued_to = request_from(url_id, "10.1.2.3");
+        let token = service.mint_access_token(&claims_for(&issued_to, &constraints, chrono::Duration::minutes(5)));
+
+        // The URL is replayed from outside the allowed range
+        let replayed = request_from(url_id, "203.0.113.7");
+        let result = service.check_access_token(&token, &replayed, &constraints).await.unwrap();
+
+        assert!(!result.valid);
+        assert!(matches!(result.enforcement(), Enforcement::Deny(_)));
+    }
+
+    #[tokio::test]
+    async fn test_tampered_or_foreign_access_token_is_refused() {
+        let other = service().with_token_secret("another secret");
+        let service = service();
+        let url_id = Uuid::new_v4();
+        let request = request_from(url_id, "10.1.2.3");
+        let token = service.mint_access_token(&claims_for(&request, &[], chrono::Duration::minutes(5)));
+
+        assert_eq!(other.check_access_token(&token, &request, &[]).await.unwrap_err(), AccessTokenError::BadSignature);
+
+        let (_, signature) = token.split_once('.').unwrap();
+        let mut widened = claims_for(&request, &[], chrono::Duration::days(365));
+        widened.method = "PUT".to_string();
+        let forged = format!(
+            "{}.{}",
+            general_purpose::URL_SAFE_NO_PAD.encode(serde_json::to_vec(&widened).unwrap()),
+            signature
+        );
+        assert_eq!(service.check_access_token(&forged, &request, &[]).await.unwrap_err(), AccessTokenError::BadSignature);
+
+        let mut put = request.clone();
+        put.method = "PUT".to_string();
+        assert_eq!(service.check_access_token(&token, &put, &[]).await.unwrap_err(), AccessTokenError::Mismatch);
+        assert_eq!(service.check_access_token("not-a-token", &request, &[]).await.unwrap_err(), AccessTokenError::Malformed);
+    }
+}
@@ -0,0 +1,259 @@
+//! Multi-window rate limiting for signed URL constraints.
+//!
+//! A `RateLimit` constraint is enforced as four sliding windows: the burst
+//! allowance over `window_size_seconds`, plus per-minute, per-hour and
+//! per-day limits. A request is admitted only if every window has room, and
+//! only admitted requests are counted. Counters live in Redis so every API
+//! replica sees the same totals; the in-memory store is for single-process
+//! deployments and tests.
+
+use async_trait::async_trait;
+use std::collections::HashMap;
+use tokio::sync::Mutex;
+
+use crate::signed_url_constraints::RateLimit;
+
+/// One sliding window of a rate limit
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateTier {
+    pub name: &'static str,
+    pub limit: u32,
+    pub window_ms: i64,
+}
+
+impl RateTier {
+    /// The enabled windows of a rate limit constraint; a limit of 0 disables a window
+    pub fn from_rate_limit(rate_limit: &RateLimit) -> Vec<RateTier> {
+        [
+            ("burst", rate_limit.burst_limit, rate_limit.window_size_seconds.max(1) as i64 * 1000),
+            ("minute", rate_limit.requests_per_minute, 60 * 1000),
+            ("hour", rate_limit.requests_per_hour, 60 * 60 * 1000),
+            ("day", rate_limit.requests_per_day, 24 * 60 * 60 * 1000),
+        ]
+        .into_iter()
+        .filter(|(_, limit, _)| *limit > 0)
+        .map(|(name, limit, window_ms)| RateTier { name, limit, window_ms })
+        .collect()
+    }
+}
+
+/// Outcome of trying to admit one request
+#[derive(Debug, Clone, PartialEq)]
+pub enum RateDecision {
+    Allowed,
+    /// `tier` indexes the window that will stay full longest
+    Limited { tier: usize, retry_after_ms: i64 },
+}
+
+/// Shared request counters
+#[async_trait]
+pub trait RateLimitStore: Send + Sync {
+    /// Admit and count a request at `now_ms` if every tier has room
+    async fn acquire(&self, key: &str, tiers: &[RateTier], now_ms: i64) -> Result<RateDecision, String>;
+}
+
+/// Decide against per-tier timestamp logs, recording the request if admitted.
+///
+/// When several windows are full the one with the longest wait wins, since
+/// the request cannot succeed before it clears.
+pub fn decide(logs: &mut [Vec<i64>], tiers: &[RateTier], now_ms: i64) -> RateDecision {
+    let mut limited: Option<(usize, i64)> = None;
+
+    for (index, (log, tier)) in logs.iter_mut().zip(tiers).enumerate() {
+        log.retain(|&timestamp| timestamp > now_ms - tier.window_ms);
+
+        let count = log.len();
+        let limit = tier.limit as usize;
+        if count >= limit {
+            // The window has room again once the entry at `count - limit` ages out
+            let retry_after_ms = log[count - limit] + tier.window_ms - now_ms;
+            if limited.is_none_or(|(_, worst)| retry_after_ms > worst) {
+                limited = Some((index, retry_after_ms));
+            }
+        }
+    }
+
+    match limited {
+        Some((tier, retry_after_ms)) => RateDecision::Limited { tier, retry_after_ms },
+        None => {
+            for log in logs.iter_mut() {
+                log.push(now_ms);
+            }
+            RateDecision::Allowed
+        }
+    }
+}
+
+/// Per-process counters
+#[derive(Default)]
+pub struct InMemoryRateLimitStore {
+    logs: Mutex<HashMap<String, Vec<Vec<i64>>>>,
+}
+
+impl InMemoryRateLimitStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl RateLimitStore for InMemoryRateLimitStore {
+    async fn acquire(&self, key: &str, tiers: &[RateTier], now_ms: i64) -> Result<RateDecision, String> {
+        let mut logs = self.logs.lock().await;
+        let logs = logs.entry(key.to_string()).or_default();
+        logs.resize_with(tiers.len(), Vec::new);
+
+        Ok(decide(logs, tiers, now_ms))
+    }
+}
+
+/// The same algorithm as [`decide`], run atomically inside Redis.
+///
+/// KEYS holds one sorted set per tier; ARGV is `now_ms`, a unique member,
+/// then `limit, window_ms` for each tier. Returns `{allowed, tier, retry_after_ms}`.
+const ACQUIRE_SCRIPT: &str = r#"
+local now = tonumber(ARGV[1])
+local member = ARGV[2]
+local worst_tier, worst_retry = -1, -1
+for i, key in ipairs(KEYS) do
+  local limit = tonumber(ARGV[1 + i * 2])
+  local window = tonumber(ARGV[2 + i * 2])
+  redis.call('ZREMRANGEBYSCORE', key, '-inf', now - window)
+  local count = redis.call('ZCARD', key)
+  if count >= limit then
+    local entry = redis.call('ZRANGE', key, count - limit, count - limit, 'WITHSCORES')
+    local retry = tonumber(entry[2]) + window - now
+    if retry > worst_retry then
+      worst_tier, worst_retry = i - 1, retry
+    end
+  end
+end
+if worst_tier >= 0 then
+  return {0, worst_tier, worst_retry}
+end
+for i, key in ipairs(KEYS) do
+  redis.call('ZADD', key, now, member)
+  redis.call('PEXPIRE', key, tonumber(ARGV[2 + i * 2]))
+end
+return {1, -1, 0}
+"#;
+
+/// Counters shared by all replicas through Redis
+pub struct RedisRateLimitStore {
+    connection: redis::aio::ConnectionManager,
+    script: redis::Script,
+}
+
+impl RedisRateLimitStore {
+    pub async fn connect(redis_url: &str) -> Result<Self, redis::RedisError> {
+        let client = redis::Client::open(redis_url)?;
+        Ok(Self {
+            connection: redis::aio::ConnectionManager::new(client).await?,
+            script: redis::Script::new(ACQUIRE_SCRIPT),
+        })
+    }
+}
+
+#[async_trait]
+impl RateLimitStore for RedisRateLimitStore {
+    async fn acquire(&self, key: &str, tiers: &[RateTier], now_ms: i64) -> Result<RateDecision, String> {
+        let mut invocation = self.script.prepare_invoke();
+        for tier in tiers {
+            invocation.key(format!("signed_url_rate:{}:{}", key, tier.name));
+        }
+        invocation.arg(now_ms).arg(uuid::Uuid::new_v4().to_string());
+        for tier in tiers {
+            invocation.arg(tier.limit).arg(tier.window_ms);
+        }
+
+        let mut connection = self.connection.clone();
+        let (allowed, tier, retry_after_ms): (i64, i64, i64) = invocation
+            .invoke_async(&mut connection)
+            .await
+            .map_err(|e| format!("Rate limit script failed: {}", e))?;
+
+        Ok(if allowed == 1 {
+            RateDecision::Allowed
+        } else {
+            RateDecision::Limited { tier: tier as usize, retry_after_ms }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signed_url_constraints::EnforcementAction;
+
+    const MINUTE: i64 = 60 * 1000;
+
+    fn rate_limit(burst: u32, per_minute: u32, per_hour: u32, per_day: u32) -> RateLimit {
+        RateLimit {
+            requests_per_minute: per_minute,
+            requests_per_hour: per_hour,
+            requests_per_day: per_day,
+            burst_limit: burst,
+            window_size_seconds: 1,
+            enforcement_action: EnforcementAction::Throttle,
+        }
+    }
+
+    #[test]
+    fn test_tiers_skip_disabled_limits() {
+        let tiers = RateTier::from_rate_limit(&rate_limit(0, 10, 0, 100));
+        let names: Vec<&str> = tiers.iter().map(|t| t.name).collect();
+        assert_eq!(names, vec!["minute", "day"]);
+    }
+
+    #[tokio::test]
+    async fn test_hourly_limit_applies_under_minute_limit() {
+        let store = InMemoryRateLimitStore::new();
+        let tiers = RateTier::from_rate_limit(&rate_limit(0, 100, 3, 0));
+        let start = 1_700_000_000_000;
+
+        // One request a minute stays far below the per-minute limit
+        for i in 0..3 {
+            assert_eq!(store.acquire("k", &tiers, start + i * MINUTE).await.unwrap(), RateDecision::Allowed);
+        }
+
+        let decision = store.acquire("k", &tiers, start + 3 * MINUTE).await.unwrap();
+        assert_eq!(decision, RateDecision::Limited { tier: 1, retry_after_ms: 57 * MINUTE });
+        assert_eq!(tiers[1].name, "hour");
+
+        // The first request ages out of the hour
+        assert_eq!(store.acquire("k", &tiers, start + 60 * MINUTE + 1).await.unwrap(), RateDecision::Allowed);
+    }
+
+    #[tokio::test]
+    async fn test_burst_limit_and_rejections_are_not_counted() {
+        let store = InMemoryRateLimitStore::new();
+        let tiers = RateTier::from_rate_limit(&rate_limit(2, 5, 0, 0));
+        let start = 1_700_000_000_000;
+
+        assert_eq!(store.acquire("k", &tiers, start).await.unwrap(), RateDecision::Allowed);
+        assert_eq!(store.acquire("k", &tiers, start + 10).await.unwrap(), RateDecision::Allowed);
+        assert_eq!(
+            store.acquire("k", &tiers, start + 20).await.unwrap(),
+            RateDecision::Limited { tier: 0, retry_after_ms: 980 }
+        );
+
+        // Rejected requests do not extend the wait
+        for second in 1..=3 {
+            assert_eq!(store.acquire("k", &tiers, start + second * 1000 + 10).await.unwrap(), RateDecision::Allowed);
+        }
+        let decision = store.acquire("k", &tiers, start + 3500).await.unwrap();
+        assert!(matches!(decision, RateDecision::Limited { tier: 1, .. }));
+        assert!(store.acquire("other", &tiers, start + 3500).await.unwrap() == RateDecision::Allowed);
+    }
+
+    #[test]
+    fn test_longest_wait_wins_when_several_tiers_are_full() {
+        let tiers = RateTier::from_rate_limit(&rate_limit(1, 1, 1, 0));
+        let mut logs = vec![vec![1000], vec![1000], vec![1000]];
+
+        assert_eq!(
+            decide(&mut logs, &tiers, 1500),
+            RateDecision::Limited { tier: 2, retry_after_ms: 60 * MINUTE - 500 }
+        );
+    }
+}
@@ -5,7 +5,7 @@ use axum::{
     extract::{Path, Query, State},
     http::StatusCode,
     response::Json,
-    routing::{get, post},
+    routing::get,
     Router,
 };
 use blacklake_core::{
@@ -44,21 +44,6 @@ pub struct SolrSearchResponse {
     pub suggestions: Option<Vec<String>>,
 }
 
-/// Reindex request
-#[derive(Debug, Deserialize)]
-pub struct ReindexRequest {
-    pub repo_id: Option<uuid::Uuid>,
-    pub since_commit_id: Option<uuid::Uuid>,
-    pub full_reindex: Option<bool>,
-}
-
-/// Reindex response
-#[derive(Debug, Serialize)]
-pub struct ReindexResponse {
-    pub job_id: uuid::Uuid,
-    pub message: String,
-}
-
 /// Search endpoint with Solr
 async fn solr_search(
     State(state): State<AppState>,
@@ -70,10 +55,24 @@ async fn solr_search(
         return Err(ApiError::Auth("User or admin role required".to_string()));
     }
 
+    // Scope to one repo: its own collection when per-repo routing is on, and
+    // a filter either way since the shared collection holds every repo
+    let mut fq: Vec<String> = params.get("fq")
+        .map(|s| s.split(',').map(|s| s.trim().to_string()).collect())
+        .unwrap_or_default();
+    let solr_client = match params.get("repo") {
+        Some(repo_name) => {
+            let repo = state.index.get_repo_by_name(repo_name).await?;
+            fq.push(format!("repo:\"{}\"", repo.name.replace('\\', "\\\\").replace('"', "\\\"")));
+            state.solr_client.for_repo(repo.id.0)
+        }
+        None => state.solr_client.clone(),
+    };
+
     // Build search query
     let search_query = SearchQuery {
         q: params.get("q").cloned().unwrap_or_else(|| "*:*".to_string()),
-        fq: params.get("fq").map(|s| s.split(',').map(|s| s.trim().to_string()).collect()),
+        fq: (!fq.is_empty()).then_some(fq),
         sort: params.get("sort").cloned(),
         limit: params.get("limit").and_then(|s| s.parse().ok()),
         offset: params.get("offset").and_then(|s| s.parse().ok()),
@@ -86,7 +85,7 @@ async fn solr_search(
     SEARCH_REQUESTS_TOTAL.inc();
     SOLR_OPERATIONS_TOTAL.inc();
     
-    let response = state.solr_client.search(&search_query).await
+    let response = solr_client.search(&search_query).await
         .map_err(|e| ApiError::Internal(format!("Search failed: {}", e)))?;
 
     // Record search metrics
@@ -96,7 +95,7 @@ async fn solr_search(
 
     // Get suggestions if requested
     let suggestions = if let Some(suggest_query) = params.get("suggest") {
-        state.solr_client.suggest(suggest_query, 5).await.ok()
+        solr_client.suggest(suggest_query, 5).await.ok()
     } else {
         None
     };
@@ -182,60 +181,6 @@ async fn get_status(
     Ok(Json(ApiResponse::success(status)))
 }
 
-/// Trigger reindex job
-async fn trigger_reindex(
-    State(state): State<AppState>,
-    auth: AuthContext,
-    Json(payload): Json<ReindexRequest>,
-) -> Result<Json<ApiResponse<ReindexResponse>>, ApiError> {
-    // Check permissions
-    if !auth.roles.contains(&"admin".to_string()) {
-        return Err(ApiError::Auth("Admin role required".to_string()));
-    }
-
-    let job_id = uuid::Uuid::new_v4();
-
-    // Enqueue reindex job using Apalis
-    let reindex_job = blacklake_core::jobs::FullReindexJob {
-        repo_id: payload.repo_id,
-        repo_name: payload.repo_name,
-        since_commit_id: payload.since_commit_id,
-        batch_size: payload.batch_size.unwrap_or(100),
-    };
-    
-    // Enqueue the job using the job manager
-    if let Some(job_manager) = &state.job_manager {
-        job_manager.enqueue_job(
-            blacklake_core::jobs::JobType::FullReindex,
-            blacklake_core::jobs::JobData::FullReindex(reindex_job)
-        ).await
-        .map_err(|e| ApiError::Internal(format!("Failed to enqueue reindex job: {}", e)))?;
-        
-        tracing::info!("Reindex job {} enqueued for repo {}", job_id, payload.repo_name);
-    } else {
-        return Err(ApiError::Internal("Job manager not available".to_string()));
-    }
-    state.index.log_audit(
-        &auth.sub,
-        "reindex_triggered",
-        None,
-        None,
-        None,
-        Some(&serde_json::json!({
-            "job_id": job_id,
-            "repo_id": payload.repo_id,
-            "since_commit_id": payload.since_commit_id,
-            "full_reindex": payload.full_reindex
-        })),
-        None,
-    ).await?;
-
-    Ok(Json(ApiResponse::success(ReindexResponse {
-        job_id,
-        message: "Reindex job queued successfully".to_string(),
-    })))
-}
-
 /// Create Solr search API routes
 pub fn create_solr_search_routes() -> Router<AppState> {
     Router::new()
@@ -243,6 +188,5 @@ pub fn create_solr_search_routes() -> Router<AppState> {
         .route("/v1/search/suggest", get(get_suggestions))
         .route("/v1/search/schema", get(get_schema))
         .route("/v1/search/status", get(get_status))
-        .route("/v1/search/reindex", post(trigger_reindex))
 }
 
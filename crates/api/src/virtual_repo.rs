@@ -0,0 +1,328 @@
+// Connector-backed virtual repositories
+// A repo marked with a connector lists that connector's source through the
+// tree and blob endpoints; it has no commits of its own and is read-only
+
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    response::Json,
+    routing::put,
+    Router,
+};
+use blacklake_connectors::manager::ConnectorFactory;
+use blacklake_connectors::traits::{ConnectorFactory as _, ExternalEntry};
+use blacklake_connectors::{Connector, ConnectorConfig, ConnectorError, ConnectorType};
+use blacklake_core::{TreeEntry, TreeResponse};
+use blacklake_index::{IndexClient, RepoConnector};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::auth::require_admin;
+use crate::{ApiError, ApiResult, AppState};
+
+const DEFAULT_TREE_LIMIT: u32 = 1000;
+const MAX_TREE_LIMIT: u32 = 10_000;
+
+/// Body of `PUT /v1/repos/:repo/connector`; `null` turns the repo back into an ordinary one
+#[derive(Debug, Deserialize)]
+pub struct SetRepoConnectorRequest {
+    pub connector_id: Option<Uuid>,
+}
+
+pub fn create_virtual_repo_routes() -> Router<AppState> {
+    Router::new().route("/v1/repos/:repo/connector", put(set_repo_connector))
+}
+
+/// Mark a repository as a view over an external source (admin only)
+async fn set_repo_connector(
+    State(state): State<AppState>,
+    Path(repo): Path<String>,
+    headers: HeaderMap,
+    Json(request): Json<SetRepoConnectorRequest>,
+) -> ApiResult<Json<Value>> {
+    let auth = crate::extract_auth(&state.auth_layer, &headers).await?;
+    require_admin(&auth)?;
+
+    let repo_info = state.index.get_repo_by_name(&repo).await?;
+    state.index.set_repo_connector(repo_info.id.0, request.connector_id).await?;
+
+    state
+        .index
+        .append_audit_log(
+            &auth.sub,
+            "repo_connector_set",
+            Some(&repo),
+            None,
+            None,
+            None,
+            Some(json!({ "connector_id": request.connector_id })),
+        )
+        .await?;
+
+    Ok(Json(json!({ "repo": repo, "connector_id": request.connector_id })))
+}
+
+/// Refuse writes to `repo` when it is backed by a connector
+pub async fn ensure_writable(index: &IndexClient, repo_id: Uuid, repo: &str) -> ApiResult<()> {
+    match index.get_repo_connector(repo_id).await? {
+        Some(source) => Err(ApiError::Forbidden(format!(
+            "Repository {} mirrors connector '{}' and is read-only",
+            repo, source.name
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Build the connector a virtual repository proxies
+pub async fn open_connector(source: &RepoConnector) -> ApiResult<Arc<dyn Connector>> {
+    let connector_type: ConnectorType = source.connector_type.parse().map_err(ApiError::Internal)?;
+    ConnectorFactory
+        .create_connector(ConnectorConfig {
+            name: source.name.clone(),
+            description: None,
+            connector_type,
+            config: source.config.clone(),
+            enabled: true,
+            sync_interval_minutes: 0,
+        })
+        .await
+        .map_err(connector_error)
+}
+
+fn connector_error(error: ConnectorError) -> ApiError {
+    match error {
+        ConnectorError::EntryNotFound(msg) => ApiError::Repo(msg),
+        ConnectorError::ConfigurationError(msg) => ApiError::Internal(format!("Connector misconfigured: {}", msg)),
+        other => ApiError::Unavailable(format!("Connector request failed: {}", other)),
+    }
+}
+
+/// List the connector's entries as a tree, with the same prefix, delimiter and
+/// paging rules as `IndexClient::list_tree`
+pub async fn list_tree(
+    connector: &dyn Connector,
+    prefix: Option<&str>,
+    delimiter: Option<&str>,
+    limit: Option<u32>,
+    offset: u32,
+) -> ApiResult<TreeResponse> {
+    let entries = connector.list_entries().await.map_err(connector_error)?;
+    Ok(build_tree(entries, prefix, delimiter, limit, offset))
+}
+
+fn build_tree(
+    entries: Vec<ExternalEntry>,
+    prefix: Option<&str>,
+    delimiter: Option<&str>,
+    limit: Option<u32>,
+    offset: u32,
+) -> TreeResponse {
+    let prefix = prefix.unwrap_or("");
+    let delimiter = delimiter.filter(|d| !d.is_empty());
+
+    // Keyed by path so rows come out in the index's path order
+    let mut rows: BTreeMap<String, TreeEntry> = BTreeMap::new();
+    for entry in entries {
+        let path = entry.tree_path();
+        let Some(rest) = path.strip_prefix(prefix) else {
+            continue;
+        };
+        if let Some(cut) = delimiter.and_then(|d| rest.find(d).map(|i| prefix.len() + i + d.len())) {
+            let dir = path[..cut].to_string();
+            rows.entry(dir.clone()).or_insert_with(|| TreeEntry {
+                path: dir,
+                is_dir: true,
+                size: None,
+                media_type: None,
+                last_modified: None,
+                meta: json!({}),
+            });
+            continue;
+        }
+        rows.insert(
+            path.clone(),
+            TreeEntry {
+                path,
+                is_dir: false,
+                size: entry.size.map(|s| s as i64),
+                media_type: entry.content_type.clone(),
+                last_modified: entry.modified_at,
+                meta: entry_meta(&entry),
+            },
+        );
+    }
+
+    let total = rows.len() as u64;
+    let limit = limit.unwrap_or(DEFAULT_TREE_LIMIT).min(MAX_TREE_LIMIT) as usize;
+    let entries: Vec<TreeEntry> = rows.into_values().skip(offset as usize).take(limit).collect();
+    let end = offset as u64 + entries.len() as u64;
+    let next_offset = (!entries.is_empty() && end < total).then_some(end as u32);
+
+    TreeResponse { entries, total, next_offset }
+}
+
+/// Metadata shown for a proxied entry: the connector's own fields plus its
+/// descriptive ones. Source URLs are left out since they may be short-lived.
+fn entry_meta(entry: &ExternalEntry) -> Value {
+    let mut meta: serde_json::Map<String, Value> = entry.metadata.clone().into_iter().collect();
+    meta.insert("title".to_string(), json!(entry.title));
+    meta.insert("source_id".to_string(), json!(entry.id));
+    if let Some(description) = &entry.description {
+        meta.insert("description".to_string(), json!(description));
+    }
+    if !entry.tags.is_empty() {
+        meta.insert("tags".to_string(), json!(entry.tags));
+    }
+    if let Some(modified_at) = entry.modified_at {
+        meta.insert("modified_at".to_string(), json!(modified_at));
+    }
+    Value::Object(meta)
+}
+
+/// Blob response for `path`: a download URL from the connector, valid for
+/// `expires_in_seconds`
+pub async fn get_blob(connector: &dyn Connector, path: &str, expires_in_seconds: u32) -> ApiResult<Value> {
+    let entry = connector
+        .list_entries()
+        .await
+        .map_err(connector_error)?
+        .into_iter()
+        .find(|entry| entry.tree_path() == path)
+        .ok_or_else(|| ApiError::Repo(format!("Path not found: {}", path)))?;
+    let download_url = connector
+        .get_presigned_url(&entry, expires_in_seconds)
+        .await
+        .map_err(connector_error)?;
+
+    Ok(json!({
+        "download_url": download_url,
+        "path": path,
+        "size": entry.size,
+        "media_type": entry.content_type,
+        "meta": entry_meta(&entry),
+        "expires_at": chrono::Utc::now() + chrono::Duration::seconds(expires_in_seconds as i64),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use blacklake_connectors::traits::SyncResult;
+    use std::collections::HashMap;
+
+    /// Serves a fixed listing of `(key, size)` objects
+    struct StubConnector {
+        objects: Vec<(&'static str, u64)>,
+    }
+
+    impl StubConnector {
+        fn small() -> Self {
+            Self {
+                objects: vec![
+                    ("raw/2024/a.csv", 10),
+                    ("raw/2024/b.csv", 20),
+                    ("raw/readme.txt", 5),
+                    ("derived/a.parquet", 30),
+                    ("index.json", 2),
+                ],
+            }
+        }
+    }
+
+    #[async_trait]
+    impl Connector for StubConnector {
+        fn connector_type(&self) -> ConnectorType {
+            ConnectorType::S3
+        }
+
+        fn name(&self) -> &str {
+            "stub"
+        }
+
+        async fn test_connection(&self) -> Result<(), ConnectorError> {
+            Ok(())
+        }
+
+        async fn list_entries(&self) -> Result<Vec<ExternalEntry>, ConnectorError> {
+            Ok(self
+                .objects
+                .iter()
+                .map(|(key, size)| ExternalEntry {
+                    id: format!("stub:{}", key),
+                    title: key.rsplit('/').next().unwrap().to_string(),
+                    description: None,
+                    url: format!("s3://open-data/{}", key),
+                    content_type: Some("text/csv".to_string()),
+                    size: Some(*size),
+                    modified_at: None,
+                    tags: vec![],
+                    metadata: HashMap::from([("key".to_string(), json!(key))]),
+                    source_id: Uuid::nil(),
+                    source_type: "s3".to_string(),
+                })
+                .collect())
+        }
+
+        async fn get_entry(&self, id: &str) -> Result<Option<ExternalEntry>, ConnectorError> {
+            Ok(self.list_entries().await?.into_iter().find(|e| e.id == id))
+        }
+
+        async fn get_presigned_url(&self, entry: &ExternalEntry, expires_in_seconds: u32) -> Result<String, ConnectorError> {
+            Ok(format!("https://stub.example/{}?expires={}", entry.tree_path(), expires_in_seconds))
+        }
+
+        async fn sync_entries(&self) -> Result<SyncResult, ConnectorError> {
+            Err(ConnectorError::SyncError("not supported".to_string()))
+        }
+    }
+
+    fn paths(tree: &TreeResponse) -> Vec<&str> {
+        tree.entries.iter().map(|e| e.path.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_tree_collapses_directories_at_the_delimiter() {
+        let connector = StubConnector::small();
+
+        let root = list_tree(&connector, None, Some("/"), None, 0).await.unwrap();
+        assert_eq!(paths(&root), vec!["derived/", "index.json", "raw/"]);
+        assert!(root.entries[0].is_dir);
+        assert_eq!(root.entries[1].size, Some(2));
+        assert_eq!(root.entries[1].meta["key"], "index.json");
+
+        let raw = list_tree(&connector, Some("raw/"), Some("/"), None, 0).await.unwrap();
+        assert_eq!(paths(&raw), vec!["raw/2024/", "raw/readme.txt"]);
+
+        let flat = list_tree(&connector, Some("raw/"), None, None, 0).await.unwrap();
+        assert_eq!(paths(&flat), vec!["raw/2024/a.csv", "raw/2024/b.csv", "raw/readme.txt"]);
+    }
+
+    #[tokio::test]
+    async fn test_tree_pages_like_the_index() {
+        let connector = StubConnector::small();
+
+        let first = list_tree(&connector, None, None, Some(2), 0).await.unwrap();
+        assert_eq!(paths(&first), vec!["derived/a.parquet", "index.json"]);
+        assert_eq!((first.total, first.next_offset), (5, Some(2)));
+
+        let last = list_tree(&connector, None, None, Some(2), 4).await.unwrap();
+        assert_eq!(paths(&last), vec!["raw/readme.txt"]);
+        assert_eq!(last.next_offset, None);
+    }
+
+    #[tokio::test]
+    async fn test_blob_returns_connector_url_or_not_found() {
+        let connector = StubConnector::small();
+
+        let blob = get_blob(&connector, "raw/2024/b.csv", 300).await.unwrap();
+        assert_eq!(blob["download_url"], "https://stub.example/raw/2024/b.csv?expires=300");
+        assert_eq!(blob["size"], 20);
+
+        let missing = get_blob(&connector, "raw/2024/c.csv", 300).await;
+        assert!(matches!(missing, Err(ApiError::Repo(_))));
+    }
+}
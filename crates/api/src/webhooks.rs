@@ -1,579 +1,646 @@
 // BlackLake Webhook System
 // Week 4: Webhook delivery with retries and dead letter handling
+//
+// The commit handler enqueues rows in `webhook_deliveries`; the worker here
+// POSTs each due payload to its webhook, signs the exact body bytes with the
+// webhook secret, and either marks the delivery delivered, schedules a retry
+// with exponential backoff, or moves it to `webhook_dead` once `max_attempts`
+// is exhausted. Workers on every replica claim due rows before sending them,
+// so each delivery is sent once. Admins can replay a past delivery or requeue
+// a dead letter.
 
 use axum::{
-    extract::{Path, Query, State},
-    http::StatusCode,
+    extract::{Path, State},
+    http::HeaderMap,
     response::Json,
-    routing::{get, post, delete},
+    routing::post,
     Router,
 };
-use blacklake_core::{
-    AuthContext, Uuid,
-};
-use blacklake_core::governance::{Webhook, WebhookDelivery, WebhookDead,
-    WebhookEvent, WebhookPayload};
-use crate::{ApiError, ApiResponse};
-use blacklake_index::IndexClient;
-use hmac::{Hmac, Mac};
-use sha2::Sha256;
-use std::{
-    collections::HashMap,
-    sync::Arc,
-    time::{Duration, SystemTime, UNIX_EPOCH},
-};
-use tokio::{
-    sync::RwLock,
-    time::{interval, sleep},
-};
+use blacklake_core::governance::{Webhook, WebhookDead, WebhookDelivery, WebhookSignature};
+use blacklake_core::Permission;
+use blacklake_index::{IndexClient, IndexError};
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use std::time::Duration;
 use tracing::{error, info, warn};
-use url::Url;
+use uuid::Uuid;
+
+use crate::events::{Event, EventBus, EventKind};
+use crate::{extract_auth, require_permission, ApiError, ApiResult, AppState};
+
+/// Header carrying `sha256=<hex HMAC of the body>`
+pub const SIGNATURE_HEADER: &str = "X-Blacklake-Signature";
+
+/// Receiver responses are stored for debugging, not archived
+const MAX_STORED_RESPONSE_BYTES: usize = 4096;
+
+/// Attempts given to requeued dead letters, matching what the commit handler enqueues
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+
+/// Deliveries a worker claims at a time
+const CLAIM_BATCH_SIZE: i64 = 20;
 
 /// Webhook delivery configuration
 #[derive(Debug, Clone)]
 pub struct WebhookConfig {
-    pub max_retries: u32,
-    pub retry_delay_seconds: u64,
     pub timeout_seconds: u64,
-    pub batch_size: usize,
+    /// Delay before the first retry; doubles with each failed attempt
+    pub retry_delay_seconds: u64,
+    pub max_retry_delay_seconds: u64,
     pub delivery_interval_seconds: u64,
 }
 
 impl Default for WebhookConfig {
     fn default() -> Self {
         Self {
-            max_retries: 3,
-            retry_delay_seconds: 60,
-            timeout_seconds: 30,
-            batch_size: 10,
+            timeout_seconds: 10,
+            retry_delay_seconds: 30,
+            max_retry_delay_seconds: 3600,
             delivery_interval_seconds: 5,
         }
     }
 }
 
-/// Webhook delivery client
-#[derive(Debug, Clone)]
-pub struct WebhookClient {
-    index: Arc<IndexClient>,
-    config: WebhookConfig,
-    http_client: reqwest::Client,
+impl WebhookConfig {
+    /// Read `WEBHOOK_*` overrides, keeping defaults for unset or invalid values
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let var = |name: &str, default: u64| {
+            std::env::var(name)
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .filter(|value| *value > 0)
+                .unwrap_or(default)
+        };
+
+        Self {
+            timeout_seconds: var("WEBHOOK_TIMEOUT_SECONDS", defaults.timeout_seconds),
+            retry_delay_seconds: var("WEBHOOK_RETRY_DELAY_SECONDS", defaults.retry_delay_seconds),
+            max_retry_delay_seconds: var("WEBHOOK_MAX_RETRY_DELAY_SECONDS", defaults.max_retry_delay_seconds),
+            delivery_interval_seconds: var("WEBHOOK_DELIVERY_INTERVAL_SECONDS", defaults.delivery_interval_seconds),
+        }
+    }
+
+    /// Backoff after `attempts` failed attempts
+    pub fn retry_delay(&self, attempts: u32) -> chrono::Duration {
+        let factor = 2_u64.saturating_pow(attempts.saturating_sub(1));
+        let seconds = self.retry_delay_seconds.saturating_mul(factor).min(self.max_retry_delay_seconds);
+        chrono::Duration::seconds(seconds as i64)
+    }
 }
 
-/// Webhook delivery worker
-#[derive(Debug)]
-pub struct WebhookWorker {
-    client: WebhookClient,
-    running: Arc<RwLock<bool>>,
+/// Result of one delivery attempt, with the delivery updated to match
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeliveryOutcome {
+    Delivered(WebhookDelivery),
+    /// Failed; `next_retry_at` on the delivery says when to try again
+    Retry { delivery: WebhookDelivery, error: String },
+    /// Failed on the last allowed attempt
+    Dead { delivery: WebhookDelivery, error: String },
+}
+
+/// Sends signed webhook requests
+#[derive(Debug, Clone)]
+pub struct WebhookSender {
+    http_client: reqwest::Client,
+    config: WebhookConfig,
 }
 
-impl WebhookClient {
-    /// Create a new webhook client
-    pub fn new(index: Arc<IndexClient>, config: WebhookConfig) -> Self {
+impl WebhookSender {
+    pub fn new(config: WebhookConfig) -> Self {
         let http_client = reqwest::Client::builder()
             .timeout(Duration::from_secs(config.timeout_seconds))
             .build()
             .expect("Failed to create HTTP client");
 
-        Self {
-            index,
-            config,
-            http_client,
-        }
+        Self { http_client, config }
     }
 
-    /// Deliver a webhook payload
-    pub async fn deliver_webhook(
-        &self,
-        webhook: &Webhook,
-        payload: &WebhookPayload,
-    ) -> Result<WebhookDelivery, ApiError> {
-        let delivery_id = Uuid::new_v4();
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-
-        // Create delivery record
-        let mut delivery = WebhookDelivery {
-            id: delivery_id,
-            webhook_id: webhook.id,
-            event: payload.event.clone(),
-            payload: serde_json::to_value(payload).unwrap(),
-            status: "pending".to_string(),
-            attempts: 0,
-            last_attempt_at: None,
-            next_retry_at: Some(now),
-            response_status: None,
-            response_body: None,
-            error_message: None,
-            created_at: now,
-            updated_at: now,
-        };
+    /// POST the delivery payload to the webhook and work out what happens next
+    pub async fn attempt(&self, webhook: &Webhook, mut delivery: WebhookDelivery, now: DateTime<Utc>) -> DeliveryOutcome {
+        delivery.attempts += 1;
+
+        let body = delivery.payload.to_string();
+        let signature = WebhookSignature::generate(&webhook.secret, body.as_bytes());
 
-        // Store delivery record
-        self.index.create_webhook_delivery(&delivery).await?;
-
-        // Attempt delivery
-        match self.attempt_delivery(&webhook, &payload, &mut delivery).await {
-            Ok(()) => {
-                delivery.status = "delivered".to_string();
-                delivery.updated_at = SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .unwrap()
-                    .as_secs();
-                self.index.update_webhook_delivery(&delivery).await?;
-                Ok(delivery)
+        let result = self
+            .http_client
+            .post(&webhook.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(reqwest::header::USER_AGENT, "BlackLake-Webhook/1.0")
+            .header(SIGNATURE_HEADER, signature)
+            .header("X-Blacklake-Event", &delivery.event_type)
+            .header("X-Blacklake-Delivery", delivery.id.to_string())
+            .body(body)
+            .send()
+            .await;
+
+        let error = match result {
+            Ok(response) => {
+                let status = response.status();
+                let mut text = response.text().await.unwrap_or_default();
+                truncate_at_char_boundary(&mut text, MAX_STORED_RESPONSE_BYTES);
+
+                delivery.response_status = Some(status.as_u16());
+                delivery.response_body = Some(text);
+
+                if status.is_success() {
+                    delivery.delivered_at = Some(now);
+                    delivery.next_retry_at = None;
+                    return DeliveryOutcome::Delivered(delivery);
+                }
+                format!("Receiver responded with HTTP {}", status.as_u16())
             }
             Err(e) => {
-                delivery.status = "failed".to_string();
-                delivery.error_message = Some(e.to_string());
-                delivery.updated_at = SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .unwrap()
-                    .as_secs();
-                self.index.update_webhook_delivery(&delivery).await?;
-                Err(e)
+                delivery.response_status = None;
+                delivery.response_body = None;
+                if e.is_timeout() {
+                    format!("Request timed out after {}s", self.config.timeout_seconds)
+                } else {
+                    format!("Request failed: {}", e)
+                }
             }
-        }
-    }
-
-    /// Attempt webhook delivery
-    async fn attempt_delivery(
-        &self,
-        webhook: &Webhook,
-        payload: &WebhookPayload,
-        delivery: &mut WebhookDelivery,
-    ) -> Result<(), ApiError> {
-        delivery.attempts += 1;
-        delivery.last_attempt_at = Some(
-            SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
-        );
-
-        // Prepare request
-        let payload_json = serde_json::to_string(payload)
-            .map_err(|e| ApiError::InternalServerError(format!("Payload serialization failed: {}", e)))?;
-
-        let mut request = self.http_client
-            .post(&webhook.url)
-            .header("Content-Type", "application/json")
-            .header("User-Agent", "BlackLake-Webhook/1.0")
-            .header("X-Webhook-Event", &payload.event.to_string())
-            .header("X-Webhook-Delivery", delivery.id.to_string())
-            .body(payload_json);
-
-        // Add signature if secret is provided
-        if let Some(secret) = &webhook.secret {
-            let signature = self.generate_signature(&payload_json, secret);
-            request = request.header("X-Webhook-Signature", signature);
-        }
-
-        // Send request
-        let response = request.send().await
-            .map_err(|e| ApiError::InternalServerError(format!("HTTP request failed: {}", e)))?;
-
-        let status = response.status();
-        let response_body = response.text().await
-            .map_err(|e| ApiError::InternalServerError(format!("Failed to read response: {}", e)))?;
-
-        delivery.response_status = Some(status.as_u16());
-        delivery.response_body = Some(response_body.clone());
+        };
 
-        if status.is_success() {
-            info!("Webhook delivered successfully: {} -> {}", webhook.id, webhook.url);
-            Ok(())
+        if delivery.attempts >= delivery.max_attempts {
+            delivery.next_retry_at = None;
+            DeliveryOutcome::Dead { delivery, error }
         } else {
-            let error_msg = format!("HTTP {}: {}", status, response_body);
-            error!("Webhook delivery failed: {} -> {}: {}", webhook.id, webhook.url, error_msg);
-            Err(ApiError::InternalServerError(error_msg))
+            delivery.next_retry_at = Some(now + self.config.retry_delay(delivery.attempts));
+            DeliveryOutcome::Retry { delivery, error }
         }
     }
+}
 
-    /// Generate webhook signature
-    fn generate_signature(&self, payload: &str, secret: &str) -> String {
-        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
-            .expect("HMAC can take key of any size");
-        mac.update(payload.as_bytes());
-        let result = mac.finalize();
-        format!("sha256={}", hex::encode(result.into_bytes()))
-    }
-
-    /// Get pending webhook deliveries
-    pub async fn get_pending_deliveries(&self) -> Result<Vec<WebhookDelivery>, ApiError> {
-        self.index.get_pending_webhook_deliveries().await
+fn truncate_at_char_boundary(text: &mut String, max_bytes: usize) {
+    if text.len() > max_bytes {
+        let mut end = max_bytes;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
     }
+}
 
-    /// Retry failed webhook delivery
-    pub async fn retry_delivery(&self, delivery_id: Uuid) -> Result<(), ApiError> {
-        let delivery = self.index.get_webhook_delivery(delivery_id).await?;
-        let webhook = self.index.get_webhook(delivery.webhook_id).await?;
+/// Background worker that drains due webhook deliveries
+pub struct WebhookWorker {
+    index: IndexClient,
+    sender: WebhookSender,
+    interval: Duration,
+    /// How long a claim may go unresolved before another worker takes it over
+    claim_lease: chrono::Duration,
+    events: Option<EventBus>,
+}
 
-        // Parse payload
-        let payload: WebhookPayload = serde_json::from_value(delivery.payload)
-            .map_err(|e| ApiError::InternalServerError(format!("Payload deserialization failed: {}", e)))?;
-
-        // Check if we should retry or move to dead letter
-        if delivery.attempts < self.config.max_retries {
-            // Calculate next retry time with exponential backoff
-            let delay = self.config.retry_delay_seconds * (2_u64.pow(delivery.attempts));
-            let next_retry = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_secs() + delay;
-
-            let mut updated_delivery = delivery.clone();
-            updated_delivery.status = "pending".to_string();
-            updated_delivery.next_retry_at = Some(next_retry);
-            updated_delivery.updated_at = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_secs();
-
-            self.index.update_webhook_delivery(&updated_delivery).await?;
-            info!("Webhook delivery scheduled for retry: {}", delivery_id);
-        } else {
-            // Move to dead letter queue
-            self.move_to_dead_letter(delivery).await?;
+impl WebhookWorker {
+    pub fn new(index: IndexClient, config: WebhookConfig) -> Self {
+        let interval = Duration::from_secs(config.delivery_interval_seconds);
+        let claim_lease = claim_lease(&config);
+        Self {
+            index,
+            sender: WebhookSender::new(config),
+            interval,
+            claim_lease,
+            events: None,
         }
+    }
 
-        Ok(())
+    /// Publish `webhook.delivered` for each successful delivery
+    pub fn with_events(mut self, events: EventBus) -> Self {
+        self.events = Some(events);
+        self
     }
 
-    /// Move delivery to dead letter queue
-    async fn move_to_dead_letter(&self, delivery: WebhookDelivery) -> Result<(), ApiError> {
-        let dead_letter = WebhookDead {
-            id: delivery.id,
-            webhook_id: delivery.webhook_id,
-            event: delivery.event,
-            payload: delivery.payload,
-            attempts: delivery.attempts,
-            last_error: delivery.error_message,
-            created_at: delivery.created_at,
-            moved_at: SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
-        };
+    /// Run the webhook delivery worker
+    pub async fn run(&self) {
+        let mut interval = tokio::time::interval(self.interval);
 
-        self.index.create_webhook_dead(&dead_letter).await?;
-        self.index.delete_webhook_delivery(delivery.id).await?;
+        loop {
+            interval.tick().await;
 
-        warn!("Webhook delivery moved to dead letter: {}", delivery.id);
-        Ok(())
+            if let Err(e) = self.process_pending_deliveries().await {
+                error!("Webhook delivery worker error: {}", e);
+            }
+        }
     }
 
-    /// Process pending deliveries
-    pub async fn process_pending_deliveries(&self) -> Result<(), ApiError> {
-        let pending_deliveries = self.get_pending_deliveries().await?;
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-
-        for delivery in pending_deliveries {
-            // Check if it's time to retry
-            if let Some(next_retry) = delivery.next_retry_at {
-                if next_retry > now {
-                    continue;
+    /// Claim and attempt every delivery that is due, a batch at a time
+    pub async fn process_pending_deliveries(&self) -> Result<(), IndexError> {
+        loop {
+            let claimed = self
+                .index
+                .claim_due_webhook_deliveries(CLAIM_BATCH_SIZE, Utc::now() - self.claim_lease)
+                .await?;
+            let exhausted = (claimed.len() as i64) < CLAIM_BATCH_SIZE;
+
+            for delivery in claimed {
+                let delivery_id = delivery.id;
+                if let Err(e) = self.process_delivery(delivery).await {
+                    error!("Failed to process webhook delivery {}: {}", delivery_id, e);
                 }
             }
 
-            // Get webhook
-            let webhook = self.index.get_webhook(delivery.webhook_id).await?;
-
-            // Parse payload
-            let payload: WebhookPayload = serde_json::from_value(delivery.payload)
-                .map_err(|e| ApiError::InternalServerError(format!("Payload deserialization failed: {}", e)))?;
-
-            // Attempt delivery
-            let mut updated_delivery = delivery.clone();
-            match self.attempt_delivery(&webhook, &payload, &mut updated_delivery).await {
-                Ok(()) => {
-                    updated_delivery.status = "delivered".to_string();
-                    updated_delivery.updated_at = now;
-                    self.index.update_webhook_delivery(&updated_delivery).await?;
-                }
-                Err(e) => {
-                    updated_delivery.status = "failed".to_string();
-                    updated_delivery.error_message = Some(e.to_string());
-                    updated_delivery.updated_at = now;
-
-                    if updated_delivery.attempts >= self.config.max_retries {
-                        self.move_to_dead_letter(updated_delivery).await?;
-                    } else {
-                        // Schedule next retry
-                        let delay = self.config.retry_delay_seconds * (2_u64.pow(updated_delivery.attempts));
-                        updated_delivery.next_retry_at = Some(now + delay);
-                        self.index.update_webhook_delivery(&updated_delivery).await?;
-                    }
-                }
+            if exhausted {
+                return Ok(());
             }
         }
-
-        Ok(())
     }
-}
 
-impl WebhookWorker {
-    /// Create a new webhook worker
-    pub fn new(client: WebhookClient) -> Self {
-        Self {
-            client,
-            running: Arc::new(RwLock::new(false)),
+    async fn process_delivery(&self, delivery: WebhookDelivery) -> Result<(), IndexError> {
+        let webhook = self.index.get_webhook(delivery.webhook_id).await?;
+        if !webhook.active {
+            warn!("Webhook {} is inactive; dead-lettering delivery {}", webhook.id, delivery.id);
+            return self.index.dead_letter_webhook_delivery(&delivery, "Webhook is inactive").await;
         }
-    }
 
-    /// Start the webhook worker
-    pub async fn start(&self) -> Result<(), ApiError> {
-        let mut running = self.running.write().await;
-        if *running {
-            return Err(ApiError::BadRequest("Webhook worker is already running".to_string()));
+        match self.sender.attempt(&webhook, delivery, Utc::now()).await {
+            DeliveryOutcome::Delivered(delivery) => {
+                info!("Delivered webhook {} to {}", delivery.id, webhook.url);
+                if let Some(events) = &self.events {
+                    events.publish(Event::for_repo(
+                        EventKind::WebhookDelivered,
+                        webhook.repo_id,
+                        None,
+                        json!({
+                            "webhook_id": webhook.id,
+                            "delivery_id": delivery.id,
+                            "event_type": delivery.event_type,
+                            "attempts": delivery.attempts,
+                            "response_status": delivery.response_status,
+                        }),
+                    ));
+                }
+                self.index.record_webhook_attempt(&delivery, "delivered", None).await
+            }
+            DeliveryOutcome::Retry { delivery, error } => {
+                warn!(
+                    "Webhook delivery {} failed (attempt {}/{}): {}",
+                    delivery.id, delivery.attempts, delivery.max_attempts, error
+                );
+                self.index.record_webhook_attempt(&delivery, "failed", Some(&error)).await
+            }
+            DeliveryOutcome::Dead { delivery, error } => {
+                warn!("Webhook delivery {} moved to dead letter after {} attempts: {}", delivery.id, delivery.attempts, error);
+                self.index.dead_letter_webhook_delivery(&delivery, &error).await
+            }
         }
-        *running = true;
-        drop(running);
+    }
+}
 
-        info!("Starting webhook worker");
+/// Time a worker may hold a claim: long enough to attempt a whole batch,
+/// each attempt running to its timeout, with a minute to spare
+fn claim_lease(config: &WebhookConfig) -> chrono::Duration {
+    let seconds = config.timeout_seconds.saturating_mul(CLAIM_BATCH_SIZE as u64).saturating_add(60);
+    chrono::Duration::seconds(seconds.min(i64::MAX as u64) as i64)
+}
 
-        let worker = self.clone();
-        tokio::spawn(async move {
-            let mut interval = interval(Duration::from_secs(worker.client.config.delivery_interval_seconds));
+/// A fresh pending delivery carrying the payload of `original`
+pub fn redelivery_of(original: &WebhookDelivery, now: DateTime<Utc>) -> WebhookDelivery {
+    WebhookDelivery {
+        id: Uuid::new_v4(),
+        webhook_id: original.webhook_id,
+        event_type: original.event_type.clone(),
+        payload: original.payload.clone(),
+        response_status: None,
+        response_body: None,
+        attempts: 0,
+        max_attempts: original.max_attempts,
+        next_retry_at: Some(now),
+        delivered_at: None,
+    }
+}
 
-            loop {
-                if !*worker.running.read().await {
-                    break;
-                }
+/// A pending delivery that puts a dead-lettered event back in the queue
+pub fn requeued_from(dead: &WebhookDead, now: DateTime<Utc>) -> WebhookDelivery {
+    WebhookDelivery {
+        id: Uuid::new_v4(),
+        webhook_id: dead.webhook_id,
+        event_type: dead.event_type.clone(),
+        payload: dead.payload.clone(),
+        response_status: None,
+        response_body: None,
+        attempts: 0,
+        max_attempts: DEFAULT_MAX_ATTEMPTS,
+        next_retry_at: Some(now),
+        delivered_at: None,
+    }
+}
 
-                interval.tick().await;
+/// Webhook replay routes
+pub fn create_webhook_replay_routes() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/v1/repos/:repo/webhooks/:webhook_id/deliveries/:delivery_id/redeliver",
+            post(redeliver_webhook),
+        )
+        .route("/v1/repos/:repo/webhook_dead/:dead_id/requeue", post(requeue_dead_webhook))
+}
 
-                if let Err(e) = worker.client.process_pending_deliveries().await {
-                    error!("Webhook delivery processing failed: {}", e);
-                }
-            }
-        });
+/// Load a webhook, checking that it belongs to `repo` and the caller administers the repo
+async fn admin_webhook(state: &AppState, headers: &HeaderMap, repo: &str, webhook_id: Uuid) -> ApiResult<(String, Webhook)> {
+    let auth = extract_auth(&state.auth_layer, headers).await?;
+    let repo_info = state.index.get_repo_by_name(repo).await?;
+    require_permission(state, repo_info.id.0, &auth, Permission::Admin).await?;
 
-        Ok(())
+    let webhook = state.index.get_webhook(webhook_id).await.map_err(not_found("Webhook", webhook_id))?;
+    if webhook.repo_id != repo_info.id.0 {
+        return Err(ApiError::Repo(format!("Webhook not found: {}", webhook_id)));
     }
 
-    /// Stop the webhook worker
-    pub async fn stop(&self) {
-        let mut running = self.running.write().await;
-        *running = false;
-        info!("Stopping webhook worker");
+    Ok((auth.sub, webhook))
+}
+
+fn not_found(kind: &'static str, id: Uuid) -> impl FnOnce(IndexError) -> ApiError {
+    move |e| match e {
+        IndexError::Database(sqlx::Error::RowNotFound) => ApiError::Repo(format!("{} not found: {}", kind, id)),
+        other => other.into(),
     }
 }
 
-/// API handlers for webhook management
-
-/// Create a new webhook
-async fn create_webhook(
-    State(index): State<Arc<IndexClient>>,
-    Path(repo): Path<String>,
-    auth: AuthContext,
-    Json(payload): Json<CreateWebhookRequest>,
-) -> Result<Json<ApiResponse<Webhook>>, ApiError> {
-    // Validate URL
-    let url = Url::parse(&payload.url)
-        .map_err(|_| ApiError::BadRequest("Invalid webhook URL".to_string()))?;
-
-    if !url.scheme().starts_with("http") {
-        return Err(ApiError::BadRequest("Webhook URL must use HTTP or HTTPS".to_string()));
+/// Replay a past delivery as a new pending delivery
+async fn redeliver_webhook(
+    State(state): State<AppState>,
+    Path((repo, webhook_id, delivery_id)): Path<(String, Uuid, Uuid)>,
+    headers: HeaderMap,
+) -> ApiResult<Json<WebhookDelivery>> {
+    let (actor, webhook) = admin_webhook(&state, &headers, &repo, webhook_id).await?;
+
+    let original = state.index.get_webhook_delivery(delivery_id).await.map_err(not_found("Delivery", delivery_id))?;
+    if original.webhook_id != webhook.id {
+        return Err(ApiError::Repo(format!("Delivery not found: {}", delivery_id)));
     }
 
-    // Get repository
-    let repo_info = index.get_repo_by_name(&repo).await?;
+    let delivery = redelivery_of(&original, Utc::now());
+    state.index.create_webhook_delivery(&delivery).await?;
 
-    // Check permissions
-    if !auth.roles.contains(&"admin".to_string()) {
-        return Err(ApiError::Forbidden("Admin role required".to_string()));
-    }
+    state.index.append_audit_log(
+        &actor,
+        "webhook_redeliver",
+        Some(&repo),
+        None,
+        None,
+        Some(serde_json::json!({
+            "webhook_id": webhook.id,
+            "original_delivery_id": original.id,
+            "delivery_id": delivery.id,
+        })),
+        None,
+    ).await?;
 
-    // Create webhook
-    let webhook = Webhook {
-        id: Uuid::new_v4(),
-        repo_id: repo_info.id,
-        url: payload.url,
-        secret: payload.secret,
-        events: payload.events,
-        active: true,
-        created_at: SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs(),
-        updated_at: SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs(),
-    };
-
-    index.create_webhook(&webhook).await?;
-
-    // Log audit
-    index.log_audit(
-        &auth.sub,
-        "webhook_created",
+    Ok(Json(delivery))
+}
+
+/// Move a dead-lettered event back into the pending queue
+async fn requeue_dead_webhook(
+    State(state): State<AppState>,
+    Path((repo, dead_id)): Path<(String, Uuid)>,
+    headers: HeaderMap,
+) -> ApiResult<Json<WebhookDelivery>> {
+    let dead = state.index.get_webhook_dead(dead_id).await.map_err(not_found("Dead letter", dead_id))?;
+    let (actor, webhook) = admin_webhook(&state, &headers, &repo, dead.webhook_id).await?;
+
+    let delivery = requeued_from(&dead, Utc::now());
+    state.index.requeue_webhook_dead(dead.id, &delivery).await?;
+
+    state.index.append_audit_log(
+        &actor,
+        "webhook_requeue",
         Some(&repo),
         None,
         None,
-        Some(&serde_json::json!({
+        Some(serde_json::json!({
             "webhook_id": webhook.id,
-            "url": webhook.url,
-            "events": webhook.events
+            "dead_letter_id": dead.id,
+            "delivery_id": delivery.id,
         })),
         None,
     ).await?;
 
-    Ok(Json(ApiResponse::success(webhook)))
+    Ok(Json(delivery))
 }
 
-/// Get webhooks for a repository
-async fn get_webhooks(
-    State(index): State<Arc<IndexClient>>,
-    Path(repo): Path<String>,
-    auth: AuthContext,
-) -> Result<Json<ApiResponse<Vec<Webhook>>>, ApiError> {
-    // Get repository
-    let repo_info = index.get_repo_by_name(&repo).await?;
-
-    // Check permissions
-    if !auth.roles.contains(&"admin".to_string()) {
-        return Err(ApiError::Forbidden("Admin role required".to_string()));
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Bytes, extract::State, http::HeaderMap, http::StatusCode, routing::post, Router};
+    use uuid::Uuid;
+    use std::collections::VecDeque;
+    use std::sync::{Arc, Mutex};
+
+    /// Records each request and answers with the next queued status (200 once empty)
+    #[derive(Clone, Default)]
+    struct Sink {
+        received: Arc<Mutex<Vec<(HeaderMap, Bytes)>>>,
+        statuses: Arc<Mutex<VecDeque<StatusCode>>>,
     }
 
-    let webhooks = index.get_webhooks(repo_info.id).await?;
-    Ok(Json(ApiResponse::success(webhooks)))
-}
+    async fn receive(State(sink): State<Sink>, headers: HeaderMap, body: Bytes) -> (StatusCode, &'static str) {
+        sink.received.lock().unwrap().push((headers, body));
+        let status = sink.statuses.lock().unwrap().pop_front().unwrap_or(StatusCode::OK);
+        (status, "thanks")
+    }
+
+    async fn slow(body: Bytes) -> Bytes {
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        body
+    }
+
+    async fn start_sink(statuses: &[StatusCode]) -> (Sink, String) {
+        let sink = Sink::default();
+        sink.statuses.lock().unwrap().extend(statuses.iter().copied());
+
+        let app = Router::new()
+            .route("/hook", post(receive))
+            .route("/slow", post(slow))
+            .with_state(sink.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
 
-/// Delete a webhook
-async fn delete_webhook(
-    State(index): State<Arc<IndexClient>>,
-    Path((repo, webhook_id)): Path<(String, Uuid)>,
-    auth: AuthContext,
-) -> Result<Json<ApiResponse<()>>, ApiError> {
-    // Get repository
-    let repo_info = index.get_repo_by_name(&repo).await?;
-
-    // Check permissions
-    if !auth.roles.contains(&"admin".to_string()) {
-        return Err(ApiError::Forbidden("Admin role required".to_string()));
+        (sink, format!("http://{}", addr))
     }
 
-    // Get webhook
-    let webhook = index.get_webhook(webhook_id).await?;
-    if webhook.repo_id != repo_info.id {
-        return Err(ApiError::NotFound("Webhook not found".to_string()));
+    fn webhook(url: String) -> Webhook {
+        Webhook {
+            id: Uuid::new_v4(),
+            repo_id: Uuid::new_v4(),
+            url,
+            secret: "s3cr3t".to_string(),
+            events: vec![],
+            active: true,
+        }
     }
 
-    // Delete webhook
-    index.delete_webhook(webhook_id).await?;
+    fn pending_delivery(webhook: &Webhook, max_attempts: u32) -> WebhookDelivery {
+        WebhookDelivery {
+            id: Uuid::new_v4(),
+            webhook_id: webhook.id,
+            event_type: "commit.created".to_string(),
+            payload: serde_json::json!({"event": "commit_created", "repo_name": "demo", "commit_id": "abc"}),
+            response_status: None,
+            response_body: None,
+            attempts: 0,
+            max_attempts,
+            next_retry_at: Some(Utc::now()),
+            delivered_at: None,
+        }
+    }
 
-    // Log audit
-    index.log_audit(
-        &auth.sub,
-        "webhook_deleted",
-        Some(&repo),
-        None,
-        None,
-        Some(&serde_json::json!({
-            "webhook_id": webhook_id,
-            "url": webhook.url
-        })),
-        None,
-    ).await?;
+    fn sender() -> WebhookSender {
+        WebhookSender::new(WebhookConfig { timeout_seconds: 1, ..WebhookConfig::default() })
+    }
 
-    Ok(Json(ApiResponse::success(())))
-}
+    #[tokio::test]
+    async fn test_delivery_is_signed_with_webhook_secret() {
+        let (sink, base) = start_sink(&[]).await;
+        let webhook = webhook(format!("{}/hook", base));
+        let delivery = pending_delivery(&webhook, 3);
+        let now = Utc::now();
+
+        let outcome = sender().attempt(&webhook, delivery.clone(), now).await;
+
+        let DeliveryOutcome::Delivered(delivered) = outcome else { panic!("expected delivery, got {:?}", outcome) };
+        assert_eq!(delivered.attempts, 1);
+        assert_eq!(delivered.response_status, Some(200));
+        assert_eq!(delivered.response_body.as_deref(), Some("thanks"));
+        assert_eq!(delivered.delivered_at, Some(now));
+
+        let received = sink.received.lock().unwrap();
+        let (headers, body) = &received[0];
+        let signature = headers[SIGNATURE_HEADER].to_str().unwrap();
+        assert!(WebhookSignature::verify("s3cr3t", body, signature));
+        assert!(!WebhookSignature::verify("other", body, signature));
+        assert_eq!(serde_json::from_slice::<serde_json::Value>(body).unwrap(), delivery.payload);
+        assert_eq!(headers["x-blacklake-event"], "commit.created");
+        assert_eq!(headers["x-blacklake-delivery"], delivery.id.to_string().as_str());
+    }
 
-/// Test webhook delivery
-async fn test_webhook(
-    State(client): State<WebhookClient>,
-    Path((repo, webhook_id)): Path<(String, Uuid)>,
-    auth: AuthContext,
-) -> Result<Json<ApiResponse<WebhookDelivery>>, ApiError> {
-    // Check permissions
-    if !auth.roles.contains(&"admin".to_string()) {
-        return Err(ApiError::Forbidden("Admin role required".to_string()));
+    #[tokio::test]
+    async fn test_failures_back_off_then_dead_letter() {
+        let (sink, base) = start_sink(&[StatusCode::INTERNAL_SERVER_ERROR; 3]).await;
+        let webhook = webhook(format!("{}/hook", base));
+        let sender = sender();
+        let now = Utc::now();
+
+        let outcome = sender.attempt(&webhook, pending_delivery(&webhook, 3), now).await;
+        let DeliveryOutcome::Retry { delivery, error } = outcome else { panic!("expected retry, got {:?}", outcome) };
+        assert_eq!(delivery.attempts, 1);
+        assert_eq!(delivery.response_status, Some(500));
+        assert_eq!(delivery.next_retry_at, Some(now + chrono::Duration::seconds(30)));
+        assert!(error.contains("500"));
+
+        let outcome = sender.attempt(&webhook, delivery, now).await;
+        let DeliveryOutcome::Retry { delivery, .. } = outcome else { panic!("expected retry, got {:?}", outcome) };
+        assert_eq!(delivery.next_retry_at, Some(now + chrono::Duration::seconds(60)));
+
+        let outcome = sender.attempt(&webhook, delivery, now).await;
+        let DeliveryOutcome::Dead { delivery, error } = outcome else { panic!("expected dead letter, got {:?}", outcome) };
+        assert_eq!(delivery.attempts, 3);
+        assert_eq!(delivery.next_retry_at, None);
+        assert!(delivery.delivered_at.is_none());
+        assert!(error.contains("500"));
+        assert_eq!(sink.received.lock().unwrap().len(), 3);
     }
 
-    // Get webhook
-    let webhook = client.index.get_webhook(webhook_id).await?;
-
-    // Create test payload
-    let test_payload = WebhookPayload {
-        event: WebhookEvent::Test,
-        repo_id: webhook.repo_id,
-        repo_name: repo,
-        timestamp: SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs(),
-        data: serde_json::json!({
-            "message": "Test webhook delivery",
-            "test": true
-        }),
-    };
-
-    // Deliver webhook
-    let delivery = client.deliver_webhook(&webhook, &test_payload).await?;
-
-    Ok(Json(ApiResponse::success(delivery)))
-}
+    #[tokio::test]
+    async fn test_recovers_after_transient_failure() {
+        let (_sink, base) = start_sink(&[StatusCode::SERVICE_UNAVAILABLE]).await;
+        let webhook = webhook(format!("{}/hook", base));
+        let sender = sender();
 
-/// Get webhook deliveries
-async fn get_webhook_deliveries(
-    State(index): State<Arc<IndexClient>>,
-    Path((repo, webhook_id)): Path<(String, Uuid)>,
-    auth: AuthContext,
-    Query(params): Query<HashMap<String, String>>,
-) -> Result<Json<ApiResponse<Vec<WebhookDelivery>>>, ApiError> {
-    // Check permissions
-    if !auth.roles.contains(&"admin".to_string()) {
-        return Err(ApiError::Forbidden("Admin role required".to_string()));
+        let DeliveryOutcome::Retry { delivery, .. } = sender.attempt(&webhook, pending_delivery(&webhook, 3), Utc::now()).await else {
+            panic!("expected retry")
+        };
+        let outcome = sender.attempt(&webhook, delivery, Utc::now()).await;
+        assert!(matches!(outcome, DeliveryOutcome::Delivered(ref d) if d.attempts == 2 && d.response_status == Some(200)));
     }
 
-    // Get webhook
-    let webhook = index.get_webhook(webhook_id).await?;
+    #[tokio::test]
+    async fn test_timeout_and_unreachable_receiver_are_retried() {
+        let (_sink, base) = start_sink(&[]).await;
+        let sender = sender();
+
+        let slow_hook = webhook(format!("{}/slow", base));
+        let outcome = sender.attempt(&slow_hook, pending_delivery(&slow_hook, 2), Utc::now()).await;
+        let DeliveryOutcome::Retry { delivery, error } = outcome else { panic!("expected retry, got {:?}", outcome) };
+        assert!(error.contains("timed out"), "{}", error);
+        assert_eq!(delivery.response_status, None);
+
+        let closed = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let dead_hook = webhook(format!("http://{}/hook", closed.local_addr().unwrap()));
+        drop(closed);
+        let outcome = sender.attempt(&dead_hook, pending_delivery(&dead_hook, 1), Utc::now()).await;
+        assert!(matches!(outcome, DeliveryOutcome::Dead { ref delivery, .. } if delivery.response_status.is_none()));
+    }
 
-    // Get deliveries (simplified - would need proper filtering)
-    let deliveries = index.get_webhook_deliveries(webhook_id).await?;
-    Ok(Json(ApiResponse::success(deliveries)))
-}
+    #[tokio::test]
+    async fn test_redelivered_event_is_sent_again_as_pending() {
+        let (sink, base) = start_sink(&[]).await;
+        let webhook = webhook(format!("{}/hook", base));
+        let sender = sender();
 
-/// Get dead letter webhooks
-async fn get_dead_letter_webhooks(
-    State(index): State<Arc<IndexClient>>,
-    Path(repo): Path<String>,
-    auth: AuthContext,
-) -> Result<Json<ApiResponse<Vec<WebhookDead>>>, ApiError> {
-    // Check permissions
-    if !auth.roles.contains(&"admin".to_string()) {
-        return Err(ApiError::Forbidden("Admin role required".to_string()));
+        let DeliveryOutcome::Delivered(delivered) = sender.attempt(&webhook, pending_delivery(&webhook, 3), Utc::now()).await else {
+            panic!("expected delivery")
+        };
+
+        let now = Utc::now();
+        let replay = redelivery_of(&delivered, now);
+        assert_ne!(replay.id, delivered.id);
+        assert_eq!(replay.payload, delivered.payload);
+        assert_eq!(replay.event_type, delivered.event_type);
+        assert_eq!((replay.attempts, replay.max_attempts), (0, 3));
+        assert_eq!(replay.next_retry_at, Some(now));
+        assert!(replay.delivered_at.is_none() && replay.response_status.is_none() && replay.response_body.is_none());
+
+        assert!(matches!(sender.attempt(&webhook, replay, now).await, DeliveryOutcome::Delivered(_)));
+        let received = sink.received.lock().unwrap();
+        assert_eq!(received.len(), 2);
+        assert_eq!(received[0].1, received[1].1);
     }
 
-    // Get repository
-    let repo_info = index.get_repo_by_name(&repo).await?;
+    #[tokio::test]
+    async fn test_requeued_dead_letter_gets_fresh_attempts() {
+        let (_sink, base) = start_sink(&[StatusCode::BAD_GATEWAY]).await;
+        let webhook = webhook(format!("{}/hook", base));
+        let sender = sender();
 
-    // Get dead letter webhooks
-    let dead_webhooks = index.get_webhook_dead_letter(repo_info.id).await?;
-    Ok(Json(ApiResponse::success(dead_webhooks)))
-}
+        let DeliveryOutcome::Dead { delivery, error } = sender.attempt(&webhook, pending_delivery(&webhook, 1), Utc::now()).await else {
+            panic!("expected dead letter")
+        };
+        let dead = WebhookDead {
+            id: delivery.id,
+            webhook_id: delivery.webhook_id,
+            event_type: delivery.event_type.clone(),
+            payload: delivery.payload.clone(),
+            failure_reason: error,
+            attempts: delivery.attempts,
+        };
 
-/// Create webhook routes
-pub fn create_webhook_routes() -> Router<Arc<IndexClient>> {
-    Router::new()
-        .route("/repos/:repo/webhooks", post(create_webhook))
-        .route("/repos/:repo/webhooks", get(get_webhooks))
-        .route("/repos/:repo/webhooks/:webhook_id", delete(delete_webhook))
-        .route("/repos/:repo/webhooks/:webhook_id/test", post(test_webhook))
-        .route("/repos/:repo/webhooks/:webhook_id/deliveries", get(get_webhook_deliveries))
-        .route("/repos/:repo/webhooks/dead-letter", get(get_dead_letter_webhooks))
-}
+        let now = Utc::now();
+        let requeued = requeued_from(&dead, now);
+        assert_ne!(requeued.id, dead.id);
+        assert_eq!(requeued.webhook_id, webhook.id);
+        assert_eq!(requeued.payload, dead.payload);
+        assert_eq!((requeued.attempts, requeued.max_attempts), (0, DEFAULT_MAX_ATTEMPTS));
+        assert_eq!(requeued.next_retry_at, Some(now));
+
+        // The receiver has been fixed
+        let outcome = sender.attempt(&webhook, requeued, now).await;
+        assert!(matches!(outcome, DeliveryOutcome::Delivered(ref d) if d.attempts == 1));
+    }
+
+    #[test]
+    fn test_retry_delay_doubles_up_to_cap() {
+        let config = WebhookConfig { retry_delay_seconds: 10, max_retry_delay_seconds: 60, ..WebhookConfig::default() };
+        let delays: Vec<i64> = (1..=5).map(|attempts| config.retry_delay(attempts).num_seconds()).collect();
+        assert_eq!(delays, vec![10, 20, 40, 60, 60]);
+    }
 
-/// Request types
-#[derive(Debug, serde::Deserialize)]
-struct CreateWebhookRequest {
-    url: String,
-    secret: Option<String>,
-    events: Vec<WebhookEvent>,
+    #[test]
+    fn test_stored_response_is_truncated_on_char_boundary() {
+        let mut text = "é".repeat(3000);
+        truncate_at_char_boundary(&mut text, MAX_STORED_RESPONSE_BYTES);
+        assert_eq!(text.len(), MAX_STORED_RESPONSE_BYTES);
+        assert!(text.chars().all(|c| c == 'é'));
+    }
 }
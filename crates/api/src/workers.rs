@@ -5,21 +5,23 @@ use axum::extract::State;
 use blacklake_core::{
     Uuid,
 };
-use blacklake_core::governance::{WebhookDelivery, WebhookSignature, RetentionPolicy};
+use blacklake_core::export_jobs::{export_key, verify_export_archive, ExportArchive, ExportFile, ResolvedExportManifest};
+use blacklake_core::governance::RetentionPolicy;
 use blacklake_core::jobs::{
     IndexEntryJob, AntivirusScanJob, RdfEmitJob, ExportJob, ReindexJob, SampleJob,
     JobContext, JobError, run_all_workers,
 };
 use blacklake_core::search::SolrClient;
 use blacklake_index::IndexClient;
-use blacklake_storage::StorageClient;
+use blacklake_storage::{ObjectVersion, StorageClient};
 use chrono::{Duration, Utc};
-use reqwest::Client;
 use serde_json::Value;
 use std::sync::Arc;
 use tokio::time::{sleep, Duration as TokioDuration};
 use tracing::{error, info, warn};
 
+use crate::events::EventBus;
+use crate::webhooks::{WebhookConfig, WebhookWorker};
 use crate::AppState;
 
 /// Background worker manager
@@ -27,7 +29,7 @@ pub struct WorkerManager {
     index: IndexClient,
     storage: StorageClient,
     solr_client: SolrClient,
-    http_client: Client,
+    events: Option<EventBus>,
 }
 
 impl WorkerManager {
@@ -36,22 +38,28 @@ impl WorkerManager {
             index,
             storage,
             solr_client,
-            http_client: Client::new(),
+            events: None,
         }
     }
 
+    /// Workers publish their events here for `/v1/events` subscribers
+    pub fn with_events(mut self, events: EventBus) -> Self {
+        self.events = Some(events);
+        self
+    }
+
     /// Start all background workers
     pub async fn start_all(&self) {
         let index = self.index.clone();
         let storage = self.storage.clone();
         let solr_client = self.solr_client.clone();
-        let http_client = self.http_client.clone();
 
         // Start Apalis job workers
         let redis_url = std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://localhost:6379".to_string());
         let job_context = JobContext {
             db_pool: index.get_pool().clone(),
             s3_client: storage.get_s3_client().clone(),
+            solr: Some(solr_client.clone()),
         };
 
         tokio::spawn(async move {
@@ -60,289 +68,244 @@ impl WorkerManager {
             }
         });
 
-        // Start legacy webhook delivery worker
+        // Start webhook delivery worker
+        let webhook_index = index.clone();
+        let webhook_events = self.events.clone();
         tokio::spawn(async move {
-            let worker = WebhookWorker::new(index.clone(), http_client);
+            let mut worker = WebhookWorker::new(webhook_index, WebhookConfig::from_env());
+            if let Some(events) = webhook_events {
+                worker = worker.with_events(events);
+            }
             worker.run().await;
         });
 
         // Start legacy retention cleanup worker
+        let gc_index = index.clone();
+        let gc_storage = storage.clone();
         tokio::spawn(async move {
             let worker = RetentionWorker::new(index.clone(), storage);
             worker.run().await;
         });
 
+        // Start orphaned object garbage collection
+        tokio::spawn(async move {
+            let worker = ObjectGcWorker::new(gc_index, gc_storage);
+            worker.run().await;
+        });
+
         info!("Background workers started (Apalis + legacy)");
     }
 }
 
-/// Webhook delivery worker
-pub struct WebhookWorker {
+/// Retention cleanup worker
+pub struct RetentionWorker {
     index: IndexClient,
-    http_client: Client,
+    storage: StorageClient,
 }
 
-impl WebhookWorker {
-    pub fn new(index: IndexClient, http_client: Client) -> Self {
-        Self { index, http_client }
+impl RetentionWorker {
+    pub fn new(index: IndexClient, storage: StorageClient) -> Self {
+        Self { index, storage }
     }
 
-    /// Run the webhook delivery worker
+    /// Run the retention cleanup worker
     pub async fn run(&self) {
-        let mut interval = tokio::time::interval(TokioDuration::from_secs(30));
+        let mut interval = tokio::time::interval(TokioDuration::from_secs(3600)); // Run hourly
         
         loop {
             interval.tick().await;
             
-            if let Err(e) = self.process_pending_deliveries().await {
-                error!("Webhook delivery worker error: {}", e);
+            if let Err(e) = self.cleanup_expired_artifacts().await {
+                error!("Retention cleanup worker error: {}", e);
             }
         }
     }
 
-    /// Process pending webhook deliveries
-    async fn process_pending_deliveries(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let pending_deliveries = self.index.get_pending_webhook_deliveries().await?;
-        
-        for delivery in pending_deliveries {
-            if let Err(e) = self.deliver_webhook(&delivery).await {
-                error!("Failed to deliver webhook {}: {}", delivery.id, e);
-                
-                // Move to dead letter queue if max attempts reached
-                if delivery.attempts >= delivery.max_attempts {
-                    self.move_to_dead_letter(&delivery, &e.to_string()).await?;
-                } else {
-                    // Schedule retry with exponential backoff
-                    self.schedule_retry(&delivery).await?;
-                }
-            }
-        }
-        
-        Ok(())
-    }
+    /// Apply every repository's retention policy to its deleted entries
+    async fn cleanup_expired_artifacts(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        info!("Starting retention cleanup");
+        let now = Utc::now();
 
-    /// Deliver a single webhook
-    async fn deliver_webhook(&self, delivery: &WebhookDelivery) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        // Get webhook configuration
-        let webhook = self.index.get_webhook(delivery.webhook_id).await?
-            .ok_or_else(|| "Webhook not found")?;
-
-        // Generate signature
-        let payload_json = serde_json::to_string(&delivery.payload)?;
-        let signature = WebhookSignature::generate(&webhook.secret, payload_json.as_bytes());
-
-        // Make HTTP request
-        let response = self.http_client
-            .post(&webhook.url)
-            .header("Content-Type", "application/json")
-            .header("X-Blacklake-Signature", signature)
-            .header("X-Blacklake-Event", &delivery.event_type)
-            .body(payload_json)
-            .send()
-            .await?;
-
-        let status = response.status();
-        let body = response.text().await?;
-
-        // Update delivery record
-        self.update_delivery_status(delivery.id, Some(status.as_u16()), Some(body), true).await?;
-
-        if !status.is_success() {
-            return Err(format!("HTTP {}: {}", status, body).into());
+        for (repo_id, repo_name) in self.index.list_repos_with_retention().await? {
+            if let Err(e) = self.cleanup_repo_artifacts(repo_id, &repo_name, now).await {
+                error!("Failed to cleanup artifacts for repo {}: {}", repo_name, e);
+            }
         }
 
-        info!("Successfully delivered webhook {} to {}", delivery.id, webhook.url);
+        info!("Retention cleanup completed");
         Ok(())
     }
 
-    /// Update delivery status
-    async fn update_delivery_status(
+    /// Tombstone and hard-delete one repository's deleted entries, then
+    /// collect the objects nothing references any more
+    async fn cleanup_repo_artifacts(
         &self,
-        delivery_id: Uuid,
-        response_status: Option<u16>,
-        response_body: Option<String>,
-        delivered: bool,
+        repo_id: Uuid,
+        repo_name: &str,
+        now: chrono::DateTime<Utc>,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        // TODO: Implement delivery status update in index client
-        // For now, just log the update
-        info!(
-            "Updated delivery status for {}: status={:?}, delivered={}",
-            delivery_id, response_status, delivered
-        );
-        Ok(())
-    }
-
-    /// Schedule retry with exponential backoff
-    async fn schedule_retry(&self, delivery: &WebhookDelivery) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let backoff_seconds = 2_u64.pow(delivery.attempts.min(6)); // Max 64 seconds
-        let next_retry = Utc::now() + Duration::seconds(backoff_seconds as i64);
-        
-        // TODO: Implement retry scheduling in index client
-        info!(
-            "Scheduled retry for delivery {} in {} seconds (attempt {})",
-            delivery.id, backoff_seconds, delivery.attempts + 1
-        );
-        Ok(())
-    }
+        let run = self.index.enforce_retention(repo_id, now).await?;
+        if run.legal_hold {
+            info!("Skipping cleanup for repo {} due to legal hold", repo_name);
+            return Ok(());
+        }
+        if !run.tombstoned.is_empty() || !run.hard_deleted.is_empty() {
+            info!(
+                "Retention for repo {}: tombstoned {} entries, hard deleted {}",
+                repo_name,
+                run.tombstoned.len(),
+                run.hard_deleted.len()
+            );
+        }
 
-    /// Move failed delivery to dead letter queue
-    async fn move_to_dead_letter(&self, delivery: &WebhookDelivery, reason: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        // TODO: Implement dead letter queue in index client
-        error!(
-            "Moved delivery {} to dead letter queue: {}",
-            delivery.id, reason
-        );
+        let mut candidates = Vec::with_capacity(run.released_objects.len());
+        for sha256 in &run.released_objects {
+            if let Some(object) = self.index.get_object(sha256).await? {
+                candidates.push(object);
+            }
+        }
+        let removed = delete_unreferenced_objects(&self.index, &self.storage, candidates, now).await?;
+        if !removed.is_empty() {
+            info!("Retention for repo {} removed {} objects", repo_name, removed.len());
+        }
         Ok(())
     }
-
-    /// Get webhook by ID
-    async fn get_webhook(&self, webhook_id: Uuid) -> Result<Option<blacklake_core::governance::Webhook>, Box<dyn std::error::Error + Send + Sync>> {
-        // Implement webhook lookup in index client
-        self.index.get_webhook_by_id(webhook_id).await
-            .map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)
-    }
-    
-    /// Implement delivery status update in index client
-    async fn update_delivery_status(&self, delivery_id: Uuid, status: &str, response_code: Option<i32>, response_body: Option<String>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        self.index.update_webhook_delivery_status(delivery_id, status, response_code, response_body).await
-            .map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)
-    }
-    
-    /// Implement retry scheduling in index client
-    async fn schedule_retry(&self, webhook_id: Uuid, retry_count: i32, next_retry_at: chrono::DateTime<Utc>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        self.index.schedule_webhook_retry(webhook_id, retry_count, next_retry_at).await
-            .map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)
-    }
-    
-    /// Implement dead letter queue in index client
-    async fn move_to_dead_letter(&self, webhook_id: Uuid, error_message: String) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        self.index.move_webhook_to_dead_letter(webhook_id, error_message).await
-            .map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)
-    }
 }
 
-/// Retention cleanup worker
-pub struct RetentionWorker {
+/// Garbage collector for content-addressed objects no entry references
+pub struct ObjectGcWorker {
     index: IndexClient,
     storage: StorageClient,
 }
 
-impl RetentionWorker {
+impl ObjectGcWorker {
     pub fn new(index: IndexClient, storage: StorageClient) -> Self {
         Self { index, storage }
     }
 
-    /// Run the retention cleanup worker
+    /// Run the garbage collector
     pub async fn run(&self) {
-        let mut interval = tokio::time::interval(TokioDuration::from_secs(3600)); // Run hourly
-        
+        let mut interval = tokio::time::interval(TokioDuration::from_secs(6 * 3600));
+
         loop {
             interval.tick().await;
-            
-            if let Err(e) = self.cleanup_expired_artifacts().await {
-                error!("Retention cleanup worker error: {}", e);
-            }
-        }
-    }
 
-    /// Clean up expired artifacts based on retention policies
-    async fn cleanup_expired_artifacts(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        info!("Starting retention cleanup");
-        
-        // Get all repositories with retention policies
-        let repos = self.get_repos_with_retention_policies().await?;
-        
-        for repo in repos {
-            if let Err(e) = self.cleanup_repo_artifacts(&repo).await {
-                error!("Failed to cleanup artifacts for repo {}: {}", repo.id, e);
+            if let Err(e) = self.collect_orphaned_objects().await {
+                error!("Object GC worker error: {}", e);
             }
         }
-        
-        info!("Retention cleanup completed");
-        Ok(())
-    }
-
-    /// Get repositories with retention policies
-    async fn get_repos_with_retention_policies(&self) -> Result<Vec<blacklake_core::Repository>, Box<dyn std::error::Error + Send + Sync>> {
-        // Implement repository query with retention policies
-        self.index.get_repos_with_retention_policies().await
-            .map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)
     }
 
-    /// Clean up artifacts for a specific repository
-    async fn cleanup_repo_artifacts(&self, repo: &blacklake_core::Repository) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let retention = self.index.get_repo_retention(repo.id).await?
-            .ok_or_else(|| "No retention policy found")?;
-
-        // Skip cleanup if legal hold is active
-        if retention.retention_policy.legal_hold {
-            info!("Skipping cleanup for repo {} due to legal hold", repo.id);
+    /// Delete orphaned objects from storage and the index
+    async fn collect_orphaned_objects(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let policies = self.index.list_retention_policies().await?;
+        let Some(cutoff) = orphan_gc_cutoff(&policies, Utc::now()) else {
+            info!("Skipping object GC: a repository is under legal hold");
             return Ok(());
-        }
-
-        let now = Utc::now();
-        let tombstone_cutoff = now - Duration::days(retention.retention_policy.tombstone_days as i64);
-        let hard_delete_cutoff = now - Duration::days(retention.retention_policy.hard_delete_days as i64);
+        };
 
-        // Find artifacts to tombstone
-        let artifacts_to_tombstone = self.find_artifacts_to_tombstone(repo.id, tombstone_cutoff).await?;
-        for artifact in artifacts_to_tombstone {
-            self.tombstone_artifact(&artifact).await?;
+        let candidates = self.index.find_orphaned_objects(cutoff).await?;
+        if candidates.is_empty() {
+            return Ok(());
         }
+        info!("Object GC found {} orphaned objects older than {}", candidates.len(), cutoff);
 
-        // Find artifacts to hard delete
-        let artifacts_to_delete = self.find_artifacts_to_delete(repo.id, hard_delete_cutoff).await?;
-        for artifact in artifacts_to_delete {
-            self.hard_delete_artifact(&artifact).await?;
-        }
+        let removed = delete_unreferenced_objects(&self.index, &self.storage, candidates, cutoff).await?;
 
+        info!("Object GC removed {} orphaned objects", removed.len());
         Ok(())
     }
+}
 
-    /// Find artifacts that should be tombstoned
-    async fn find_artifacts_to_tombstone(&self, _repo_id: Uuid, _cutoff: chrono::DateTime<chrono::Utc>) -> Result<Vec<Uuid>, Box<dyn std::error::Error + Send + Sync>> {
-        // TODO: Implement artifact query for tombstoning
-        Ok(vec![])
+/// Delete the rows of `candidates` that no entry references, then the blob
+/// versions written before `cutoff`, returning the hashes removed
+async fn delete_unreferenced_objects(
+    index: &IndexClient,
+    storage: &StorageClient,
+    candidates: Vec<blacklake_core::Object>,
+    cutoff: chrono::DateTime<Utc>,
+) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
+    if candidates.is_empty() {
+        return Ok(Vec::new());
     }
 
-    /// Find artifacts that should be hard deleted
-    async fn find_artifacts_to_delete(&self, repo_id: Uuid, cutoff: chrono::DateTime<chrono::Utc>) -> Result<Vec<Uuid>, Box<dyn std::error::Error + Send + Sync>> {
-        // Implement artifact query for hard deletion
-        self.index.get_artifacts_for_hard_deletion(repo_id, cutoff).await
-            .map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)
+    // A commit may have picked an object up since the scan. The rows go
+    // first, under a lock, and only the blobs of rows actually removed are
+    // deleted, so a blob is never deleted while something references it
+    let sha256s: Vec<String> = candidates.into_iter().map(|o| o.sha256).collect();
+    let removed = index.delete_orphaned_objects(&sha256s).await?;
+    if removed.is_empty() {
+        return Ok(Vec::new());
     }
 
-    /// Tombstone an artifact (mark as deleted but keep metadata)
-    async fn tombstone_artifact(&self, artifact_id: &Uuid) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        // Implement artifact tombstoning
-        self.index.tombstone_artifact(*artifact_id).await
-            .map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)?;
-        info!("Tombstoned artifact {}", artifact_id);
-        Ok(())
+    // On a versioned bucket a plain delete only hides the blob behind a
+    // marker, so each version is removed. Versions from after the cutoff are
+    // a fresh upload of the same content and are left for it. A bucket that
+    // can't list versions falls back to deleting the key if it is old enough.
+    let mut versions = Vec::new();
+    for object in &removed {
+        match storage.list_object_versions(&object.s3_key).await {
+            Ok(found) => versions.extend(written_before(found, cutoff)),
+            Err(e) => {
+                warn!("Failed to list versions of blob {}: {}", object.s3_key, e);
+                match storage.head_object(&object.s3_key).await {
+                    Ok(Some(head)) if head.last_modified.is_some_and(|at| at < std::time::SystemTime::from(cutoff)) => {
+                        versions.push(object.s3_key.clone().into());
+                    }
+                    Ok(_) => {}
+                    Err(e) => warn!("Failed to check the age of blob {}: {}", object.s3_key, e),
+                }
+            }
+        }
     }
 
-    /// Hard delete an artifact (remove from storage and metadata)
-    async fn hard_delete_artifact(&self, artifact_id: &Uuid) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        // Implement artifact hard deletion
-        // First, get artifact metadata to find storage location
-        let artifact = self.index.get_artifact(*artifact_id).await?;
-        
-        // Delete from storage
-        if let Some(storage_path) = artifact.storage_path {
-            if let Err(e) = self.storage.delete_object(&storage_path).await {
-                warn!("Failed to delete artifact from storage {}: {}", storage_path, e);
+    // A blob that fails to delete is only wasted space now that its row is gone
+    match storage.delete_objects(&versions).await {
+        Ok(result) => {
+            for failure in &result.failed {
+                warn!(
+                    "Failed to delete blob {} (version {:?}) of a collected object: {:?}",
+                    failure.key, failure.version_id, failure.message
+                );
             }
         }
-        
-        // Delete from database
-        self.index.hard_delete_artifact(*artifact_id).await
-            .map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)?;
-        
-        info!("Hard deleted artifact {}", artifact_id);
-        Ok(())
+        Err(e) => warn!("Failed to delete {} blob versions of collected objects: {}", versions.len(), e),
     }
+
+    Ok(removed.into_iter().map(|o| o.sha256).collect())
 }
 
+/// The versions written before `cutoff`; one of unknown age is kept
+fn written_before(versions: Vec<ObjectVersion>, cutoff: chrono::DateTime<Utc>) -> Vec<ObjectVersion> {
+    let cutoff = std::time::SystemTime::from(cutoff);
+    versions
+        .into_iter()
+        .filter(|version| version.last_modified.is_some_and(|at| at < cutoff))
+        .collect()
+}
+
+/// Creation cutoff for collecting orphaned objects.
+///
+/// Objects are shared across repositories, so the most conservative policy
+/// wins: any legal hold pauses GC entirely (`None`), and otherwise objects
+/// must be older than the longest tombstone or hard-delete window.
+fn orphan_gc_cutoff(policies: &[RetentionPolicy], now: chrono::DateTime<Utc>) -> Option<chrono::DateTime<Utc>> {
+    if policies.iter().any(|p| p.legal_hold) {
+        return None;
+    }
+
+    let days = policies
+        .iter()
+        .map(|p| p.tombstone_days.max(p.hard_delete_days))
+        .max()
+        .unwrap_or(DEFAULT_ORPHAN_GC_DAYS);
+
+    Some(now - Duration::days(days as i64))
+}
+
+/// Grace period for orphaned objects when no repository sets a policy
+const DEFAULT_ORPHAN_GC_DAYS: u32 = 90;
+
 /// Export job worker
 pub struct ExportWorker {
     index: IndexClient,
@@ -397,62 +360,58 @@ impl ExportWorker {
 
     /// Create export package
     async fn create_export_package(&self, job: &blacklake_core::governance::ExportJob) -> Result<(String, String), Box<dyn std::error::Error + Send + Sync>> {
-        // Implement export package creation
-        // 1. Collecting all artifacts from the specified paths
-        let artifacts = self.index.get_artifacts_for_export(job.repo_id, &job.paths).await?;
-        
-        // 2. Creating a tarball with blobs and metadata
-        let temp_dir = std::env::temp_dir().join(format!("export_{}", job.id));
-        std::fs::create_dir_all(&temp_dir)?;
+        // 1. Resolving the manifest's paths, prefixes and globs to files at the ref's head
+        let manifest = &job.manifest;
+        let ref_info = self.index.get_ref(job.repo_id, &manifest.ref_name).await?;
+        let commit_id = ref_info.commit_id.0;
+        let entries = self.index.resolve_export_entries(commit_id, &manifest.paths).await?;
+        let files: Vec<ExportFile> = entries
+            .iter()
+            .filter_map(|entry| {
+                entry.object_sha256.clone().map(|sha256| ExportFile { path: entry.path.clone(), sha256 })
+            })
+            .collect();
+
+        // 2. Packaging the resolved manifest, blobs and metadata in the requested format
+        let mut archive = ExportArchive::new(manifest.format, std::io::Cursor::new(Vec::new()));
+
+        let resolved_manifest = ResolvedExportManifest { request: manifest.clone(), commit_id, files };
+        archive.append("manifest.json", serde_json::to_string_pretty(&resolved_manifest)?.as_bytes())?;
+        let mut written = 1;
         
-        let mut tar_builder = tar::Builder::new(std::fs::File::create(temp_dir.join("export.tar"))?);
-        
-        for artifact in artifacts {
-            // Add metadata file
-            let metadata_path = format!("metadata/{}.json", artifact.id);
-            let metadata_json = serde_json::to_string_pretty(&artifact.metadata)?;
-            let mut header = tar::Header::new_gnu();
-            header.set_path(&metadata_path)?;
-            header.set_size(metadata_json.len() as u64);
-            header.set_cksum();
-            tar_builder.append(&header, metadata_json.as_bytes())?;
-            
-            // Add blob file if it exists
-            if let Some(blob_path) = &artifact.blob_path {
-                let blob_data = self.storage.get_object(blob_path).await?;
-                let blob_file_path = format!("blobs/{}", artifact.id);
-                let mut header = tar::Header::new_gnu();
-                header.set_path(&blob_file_path)?;
-                header.set_size(blob_data.len() as u64);
-                header.set_cksum();
-                tar_builder.append(&header, &blob_data)?;
+        for entry in &entries {
+            let Some(sha256) = &entry.object_sha256 else {
+                continue;
+            };
+            let key = StorageClient::content_address_key(sha256);
+            let blob = self.storage.get_object(&key).await?
+                .ok_or_else(|| format!("Object missing from storage for path: {}", entry.path))?;
+            let blob_data = blob.collect().await?.into_bytes();
+            archive.append(&entry.path, &blob_data)?;
+            written += 1;
+
+            if manifest.include_meta {
+                let metadata_path = format!("metadata/{}.json", entry.path);
+                archive.append(&metadata_path, serde_json::to_string_pretty(&entry.meta)?.as_bytes())?;
+                written += 1;
             }
         }
         
-        tar_builder.finish()?;
-        
-        // Compress the tarball
-        let tar_path = temp_dir.join("export.tar");
-        let gz_path = temp_dir.join("export.tar.gz");
-        
-        let mut gz_encoder = flate2::write::GzEncoder::new(
-            std::fs::File::create(&gz_path)?,
-            flate2::Compression::default()
-        );
-        std::io::copy(&mut std::fs::File::open(&tar_path)?, &mut gz_encoder)?;
-        gz_encoder.finish()?;
+        let archive_data = archive.finish()?.into_inner();
         
         // 3. Uploading to S3
-        let s3_key = format!("exports/{}.tar.gz", job.id);
-        let gz_data = std::fs::read(&gz_path)?;
-        
-        self.storage.put_object(&s3_key, &gz_data).await?;
+        let s3_key = export_key(job.id, manifest.format);
+        self.storage.put_object(&s3_key, archive_data, manifest.format.content_type()).await?;
         
-        // 4. Generating presigned download URL
-        let download_url = self.storage.get_presigned_url(&s3_key, 3600).await?;
+        // 4. Reading the archive back; a truncated or corrupt upload fails the export
+        let stored = self.storage.get_object(&s3_key).await?
+            .ok_or_else(|| format!("Export archive {} missing right after upload", s3_key))?;
+        let stored = stored.collect().await?.into_bytes();
+        verify_export_archive(&stored, manifest.format, written)
+            .map_err(|reason| format!("Export archive {} failed verification: {}", s3_key, reason))?;
         
-        // Cleanup temp files
-        std::fs::remove_dir_all(&temp_dir)?;
+        // 5. Generating presigned download URL
+        let download_url = self.storage.presign_get(&s3_key, std::time::Duration::from_secs(3600)).await?.to_string();
         
         Ok((s3_key, download_url))
     }
@@ -461,6 +420,7 @@ impl ExportWorker {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use blacklake_core::governance::WebhookSignature;
 
     #[tokio::test]
     async fn test_webhook_signature_generation() {
@@ -483,4 +443,49 @@ mod tests {
         // Legal hold should prevent cleanup
         assert!(policy.legal_hold);
     }
+
+    fn policy(tombstone_days: u32, hard_delete_days: u32, legal_hold: bool) -> RetentionPolicy {
+        RetentionPolicy {
+            tombstone_days,
+            hard_delete_days,
+            legal_hold,
+        }
+    }
+
+    #[test]
+    fn test_orphan_gc_cutoff_uses_longest_window() {
+        let now = Utc::now();
+        let policies = vec![policy(30, 90, false), policy(7, 180, false)];
+
+        assert_eq!(orphan_gc_cutoff(&policies, now), Some(now - Duration::days(180)));
+        assert_eq!(
+            orphan_gc_cutoff(&[], now),
+            Some(now - Duration::days(DEFAULT_ORPHAN_GC_DAYS as i64))
+        );
+    }
+
+    #[test]
+    fn test_only_versions_older_than_the_cutoff_are_collected() {
+        let cutoff = Utc::now() - Duration::days(30);
+        let version = |id: &str, last_modified: Option<chrono::DateTime<Utc>>| ObjectVersion {
+            key: "sha256/ab/cd/abcd".to_string(),
+            version_id: Some(id.to_string()),
+            last_modified: last_modified.map(Into::into),
+        };
+        let versions = vec![
+            version("old", Some(cutoff - Duration::days(1))),
+            version("reuploaded", Some(cutoff + Duration::days(1))),
+            version("unknown", None),
+        ];
+
+        let collected = written_before(versions, cutoff);
+
+        assert_eq!(collected, vec![version("old", Some(cutoff - Duration::days(1)))]);
+    }
+
+    #[test]
+    fn test_orphan_gc_cutoff_paused_by_legal_hold() {
+        let policies = vec![policy(30, 90, false), policy(30, 90, true)];
+        assert_eq!(orphan_gc_cutoff(&policies, Utc::now()), None);
+    }
 }
@@ -0,0 +1,137 @@
+#!/usr/bin/env python3
+"""Write GeoIP2-City-Test.mmdb, a tiny MaxMind DB used by the geoip tests.
+
+The networks mirror those in MaxMind's own GeoIP2 test databases:
+
+    81.2.69.0/24     GB / ENG / London
+    89.160.20.0/24   SE / E / Linköping
+    216.160.83.0/24  US / WA / Milton
+    2001:db8::/32    US / WA / Milton
+
+Run from this directory: python3 make_geoip_test_db.py
+"""
+
+import ipaddress
+import struct
+import time
+
+NETWORKS = [
+    ("81.2.69.0/24", "GB", "ENG", "London", 51.5142, -0.0931),
+    ("89.160.20.0/24", "SE", "E", "Linköping", 58.4167, 15.6167),
+    ("216.160.83.0/24", "US", "WA", "Milton", 47.2513, -122.3149),
+    ("2001:db8::/32", "US", "WA", "Milton", 47.2513, -122.3149),
+]
+
+RECORD_SIZE = 24
+
+
+def control(type_id, size):
+    """Control byte(s) for a field of `type_id` with payload `size`."""
+    if size < 29:
+        head, extra = size, b""
+    elif size < 285:
+        head, extra = 29, bytes([size - 29])
+    else:
+        head, extra = 30, struct.pack(">H", size - 285)
+    if type_id <= 7:
+        return bytes([(type_id << 5) | head]) + extra
+    return bytes([head, type_id - 7]) + extra
+
+
+def encode(value):
+    if isinstance(value, str):
+        raw = value.encode("utf-8")
+        return control(2, len(raw)) + raw
+    if isinstance(value, float):
+        return control(3, 8) + struct.pack(">d", value)
+    if isinstance(value, dict):
+        out = control(7, len(value))
+        for key, item in value.items():
+            out += encode(key) + encode(item)
+        return out
+    if isinstance(value, list):
+        out = control(11, len(value))
+        for item in value:
+            out += encode(item)
+        return out
+    raise TypeError(value)
+
+
+def encode_uint(type_id, value, width):
+    raw = value.to_bytes(width, "big").lstrip(b"\0")
+    return control(type_id, len(raw)) + raw
+
+
+def city_record(country, region, city, latitude, longitude):
+    return {
+        "city": {"names": {"en": city}},
+        "country": {"iso_code": country, "names": {"en": country}},
+        "location": {"latitude": latitude, "longitude": longitude},
+        "subdivisions": [{"iso_code": region, "names": {"en": region}}],
+    }
+
+
+def network_bits(cidr):
+    network = ipaddress.ip_network(cidr)
+    # IPv4 networks live under ::/96 in an IPv6 tree
+    value = int(network.network_address)
+    prefix = network.prefixlen
+    if network.version == 4:
+        prefix += 96
+    return [(value >> (127 - i)) & 1 for i in range(prefix)]
+
+
+def main():
+    data = b""
+    root = [None, None]
+
+    for cidr, *fields in NETWORKS:
+        offset = len(data)
+        data += encode(city_record(*fields))
+        bits = network_bits(cidr)
+        node = root
+        for bit in bits[:-1]:
+            if node[bit] is None:
+                node[bit] = [None, None]
+            node = node[bit]
+        node[bits[-1]] = ("data", offset)
+
+    # Number nodes breadth-first
+    nodes = []
+    queue = [root]
+    while queue:
+        node = queue.pop(0)
+        nodes.append(node)
+        queue.extend(child for child in node if isinstance(child, list))
+    index = {id(node): i for i, node in enumerate(nodes)}
+    node_count = len(nodes)
+
+    def record(child):
+        if child is None:
+            return node_count
+        if isinstance(child, tuple):
+            return node_count + 16 + child[1]
+        return index[id(child)]
+
+    tree = b"".join(
+        record(left).to_bytes(3, "big") + record(right).to_bytes(3, "big")
+        for left, right in nodes
+    )
+
+    metadata = control(7, 9)
+    metadata += encode("binary_format_major_version") + encode_uint(5, 2, 2)
+    metadata += encode("binary_format_minor_version") + encode_uint(5, 0, 2)
+    metadata += encode("build_epoch") + encode_uint(9, int(time.time()), 8)
+    metadata += encode("database_type") + encode("GeoIP2-City")
+    metadata += encode("description") + encode({"en": "BlackLake GeoIP test database"})
+    metadata += encode("ip_version") + encode_uint(5, 6, 2)
+    metadata += encode("languages") + encode(["en"])
+    metadata += encode("node_count") + encode_uint(6, node_count, 4)
+    metadata += encode("record_size") + encode_uint(5, RECORD_SIZE, 2)
+
+    with open("GeoIP2-City-Test.mmdb", "wb") as out:
+        out.write(tree + b"\0" * 16 + data + b"\xab\xcd\xefMaxMind.com" + metadata)
+
+
+if __name__ == "__main__":
+    main()
@@ -115,6 +115,7 @@ fn create_mock_export_job() -> ExportJob {
         }),
         include_metadata: true,
         include_rdf: false,
+        format: Default::default(),
     }
 }
 
@@ -1,18 +1,77 @@
 use anyhow::{anyhow, Result};
-use blacklake_core::{CanonicalMeta, Change, ChangeOp, CommitRequest, CommitResponse, SearchRequest, SearchResponse, TreeResponse, UploadInitResponse};
+use blacklake_core::signing::SignatureBundle;
+use blacklake_core::{CommitPlan, CommitRequest, CommitResponse, CreateRepoResponse, CreateTagRequest, EntrySample, QuotaWarning, Reference, SearchRequest, SearchResponse, TreeResponse, UploadCompleteRequest, UploadCompleteResponse, UploadInitResponse, UploadVerifyRequest, UploadVerifyResponse, ValidateCommitResponse};
 use indicatif::{ProgressBar, ProgressStyle};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::path::Path;
 use std::time::Duration;
+use uuid::Uuid;
+
+/// Lets the server recognise a resent commit or upload-init and answer it
+/// with the first attempt's response
+const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+/// Tries for a request sent under an idempotency key
+const IDEMPOTENT_ATTEMPTS: u32 = 3;
+
+/// Print a dry-run validation result, failing if the commit would be rejected
+pub fn report_validation(response: &ValidateCommitResponse) -> Result<()> {
+    if response.valid {
+        println!("✅ Validation passed");
+        return Ok(());
+    }
+
+    println!("❌ Validation failed:");
+    for error in &response.errors {
+        println!("  {} [{}]: {}", error.path, error.field, error.message);
+    }
+    Err(anyhow!("Commit would be rejected: {} validation error(s)", response.errors.len()))
+}
+
+/// Print what a dry-run commit would do, failing if it would be refused
+pub fn report_plan(plan: &CommitPlan) -> Result<()> {
+    for change in &plan.changes {
+        println!("  {:?} {}", change.op, change.path);
+    }
+    for reason in &plan.blocking_reasons {
+        println!("⛔ {}", reason);
+    }
+    for error in &plan.errors {
+        println!("❌ {} [{}]: {}", error.path, error.field, error.message);
+    }
+
+    if plan.allowed {
+        println!("✅ Commit would succeed");
+        return Ok(());
+    }
+    Err(anyhow!(
+        "Commit would be rejected: {} blocking reason(s), {} validation error(s)",
+        plan.blocking_reasons.len(),
+        plan.errors.len()
+    ))
+}
+
+/// Tell the user the repository is past its soft quota
+fn report_quota_warning(warning: Option<&QuotaWarning>) {
+    if let Some(warning) = warning {
+        println!(
+            "⚠️  Repository is over its soft quota: {} of {} bytes used ({:.1}% of the {} byte hard limit)",
+            warning.current_bytes, warning.soft_limit, warning.usage_percentage, warning.hard_limit
+        );
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UploadInitRequest {
     pub path: String,
     pub size: u64,
     pub media_type: Option<String>,
+    /// Content hash of the file; the server uses it as the object key and S3 checks the bytes against it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -65,12 +124,29 @@ impl ApiClient {
         req
     }
 
+    /// Send `request` under a fresh idempotency key, resending it with the
+    /// same key after connection failures and 5xx responses
+    async fn send_idempotent(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        let request = request.header(IDEMPOTENCY_KEY_HEADER, Uuid::new_v4().to_string());
+        let mut attempt = 1;
+        loop {
+            let attempt_request = request
+                .try_clone()
+                .ok_or_else(|| anyhow!("Request body cannot be resent"))?;
+            let last = attempt == IDEMPOTENT_ATTEMPTS;
+            match attempt_request.send().await {
+                Ok(response) if last || !response.status().is_server_error() => return Ok(response),
+                Err(e) if last || !(e.is_connect() || e.is_timeout() || e.is_request()) => return Err(e.into()),
+                _ => {}
+            }
+            tokio::time::sleep(Duration::from_millis(250 << attempt)).await;
+            attempt += 1;
+        }
+    }
+
     pub async fn upload_init(&self, repo: &str, request: &UploadInitRequest) -> Result<UploadInitResponse> {
         let url = format!("{}/v1/repos/{}/upload-init", self.base_url, repo);
-        let response = self.post_request(&url)
-            .json(request)
-            .send()
-            .await?;
+        let response = self.send_idempotent(self.post_request(&url).json(request)).await?;
 
         if !response.status().is_success() {
             let error_text = response.text().await?;
@@ -78,10 +154,12 @@ impl ApiClient {
         }
 
         let upload_response: UploadInitResponse = response.json().await?;
+        report_quota_warning(upload_response.quota_warning.as_ref());
         Ok(upload_response)
     }
 
-    pub async fn upload_file(&self, upload_url: &str, file_path: &Path) -> Result<()> {
+    /// PUT a file to a presigned URL, sending any headers signed into it
+    pub async fn upload_file(&self, upload_url: &str, file_path: &Path, headers: &HashMap<String, String>) -> Result<()> {
         let file_size = std::fs::metadata(file_path)?.len();
         let file_content = std::fs::read(file_path)?;
 
@@ -93,8 +171,11 @@ impl ApiClient {
                 .progress_chars("#>-"),
         );
 
-        let response = self.client
-            .put(upload_url)
+        let mut req = self.client.put(upload_url);
+        for (name, value) in headers {
+            req = req.header(name.as_str(), value.as_str());
+        }
+        let response = req
             .body(file_content)
             .send()
             .await?;
@@ -109,6 +190,61 @@ impl ApiClient {
         Ok(())
     }
 
+    /// Upload one part of a multipart upload, returning the ETag S3 assigned it
+    pub async fn upload_part(&self, upload_url: &str, body: Vec<u8>) -> Result<String> {
+        let response = self.client
+            .put(upload_url)
+            .body(body)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow!("Part upload failed: {}", error_text));
+        }
+
+        response
+            .headers()
+            .get(reqwest::header::ETAG)
+            .and_then(|v| v.to_str().ok())
+            .map(|etag| etag.to_string())
+            .ok_or_else(|| anyhow!("Part upload response has no ETag"))
+    }
+
+    pub async fn upload_complete(&self, repo: &str, request: &UploadCompleteRequest) -> Result<UploadCompleteResponse> {
+        let url = format!("{}/v1/repos/{}/upload-complete", self.base_url, repo);
+        let response = self.post_request(&url)
+            .json(request)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow!("Upload complete failed: {}", error_text));
+        }
+
+        let complete_response: UploadCompleteResponse = response.json().await?;
+        Ok(complete_response)
+    }
+
+    /// Ask the server to confirm an upload matches its sha256, attaching a
+    /// detached signature when there is one
+    pub async fn upload_verify(&self, repo: &str, sha256: &str, signature: Option<SignatureBundle>) -> Result<UploadVerifyResponse> {
+        let url = format!("{}/v1/repos/{}/upload-verify", self.base_url, repo);
+        let response = self.post_request(&url)
+            .json(&UploadVerifyRequest { sha256: sha256.to_string(), signature })
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow!("Upload verification failed: {}", error_text));
+        }
+
+        let verify_response: UploadVerifyResponse = response.json().await?;
+        Ok(verify_response)
+    }
+
     pub async fn commit(&self, repo: &str, request: &CommitRequest, merge: bool) -> Result<CommitResponse> {
         let url = format!("{}/v1/repos/{}/commit", self.base_url, repo);
         
@@ -118,10 +254,7 @@ impl ApiClient {
             req_builder = req_builder.header("X-Blacklake-Merge", "true");
         }
 
-        let response = req_builder
-            .json(request)
-            .send()
-            .await?;
+        let response = self.send_idempotent(req_builder.json(request)).await?;
 
         if !response.status().is_success() {
             let error_text = response.text().await?;
@@ -129,9 +262,47 @@ impl ApiClient {
         }
 
         let commit_response: CommitResponse = response.json().await?;
+        report_quota_warning(commit_response.quota_warning.as_ref());
         Ok(commit_response)
     }
 
+    /// Run a commit server-side with `?dry_run=true`: every check, no writes
+    pub async fn plan_commit(&self, repo: &str, request: &CommitRequest, merge: bool) -> Result<CommitPlan> {
+        let url = format!("{}/v1/repos/{}/commit?dry_run=true", self.base_url, repo);
+
+        let mut req_builder = self.post_request(&url);
+        if merge {
+            req_builder = req_builder.header("X-Blacklake-Merge", "true");
+        }
+
+        let response = req_builder.json(request).send().await?;
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow!("Dry run failed: {}", error_text));
+        }
+
+        let plan: CommitPlan = response.json().await?;
+        Ok(plan)
+    }
+
+    /// Validate a commit server-side without writing it
+    pub async fn validate_commit(&self, repo: &str, request: &CommitRequest) -> Result<ValidateCommitResponse> {
+        let url = format!("{}/v1/repos/{}/validate", self.base_url, repo);
+
+        let response = self.post_request(&url)
+            .json(request)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow!("Validation failed: {}", error_text));
+        }
+
+        let validate_response: ValidateCommitResponse = response.json().await?;
+        Ok(validate_response)
+    }
+
     pub async fn get_tree(&self, repo: &str, r#ref: &str, path: Option<&str>) -> Result<TreeResponse> {
         let mut url = format!("{}/v1/repos/{}/tree/{}", self.base_url, repo, r#ref);
         
@@ -153,7 +324,114 @@ impl ApiClient {
         Ok(tree_response)
     }
 
+    /// Repositories visible to the caller
+    pub async fn list_repos(&self) -> Result<Vec<CreateRepoResponse>> {
+        let url = format!("{}/v1/repos", self.base_url);
+
+        let mut req = self.client.get(&url);
+        if let Some(ref token) = self.token {
+            req = req.bearer_auth(token);
+        }
+
+        let response = req.send().await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow!("List repositories failed: {}", error_text));
+        }
+
+        let repos: Vec<CreateRepoResponse> = response.json().await?;
+        Ok(repos)
+    }
+
+    /// One page of the directory `dir` (empty for the root, otherwise ending
+    /// in `/`); subdirectories come back as single rows ending in `/`
+    pub async fn list_dir(&self, repo: &str, r#ref: &str, dir: &str, offset: u32) -> Result<TreeResponse> {
+        let url = format!(
+            "{}/v1/repos/{}/tree/{}?p={}&delimiter=%2F&offset={}",
+            self.base_url, repo, r#ref, urlencoding::encode(dir), offset
+        );
+
+        let mut req = self.client.get(&url);
+        if let Some(ref token) = self.token {
+            req = req.bearer_auth(token);
+        }
+
+        let response = req.send().await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow!("List directory failed: {}", error_text));
+        }
+
+        let tree_response: TreeResponse = response.json().await?;
+        Ok(tree_response)
+    }
+
+    /// The stored sample of a tabular entry, or `None` when it has none
+    pub async fn get_sample(&self, repo: &str, r#ref: &str, path: &str) -> Result<Option<EntrySample>> {
+        let url = format!("{}/v1/repos/{}/sample/{}/{}",
+            self.base_url, repo, r#ref, urlencoding::encode(path));
+
+        let mut req = self.client.get(&url);
+        if let Some(ref token) = self.token {
+            req = req.bearer_auth(token);
+        }
+
+        let response = req.send().await?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow!("Get sample failed: {}", error_text));
+        }
+
+        let sample: EntrySample = response.json().await?;
+        Ok(Some(sample))
+    }
+
     pub async fn search(&self, repo: &str, request: &SearchRequest) -> Result<SearchResponse> {
+        let url = self.search_url(repo, request, None);
+
+        let response = self.client
+            .get(&url)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow!("Search failed: {}", error_text));
+        }
+
+        let search_response: SearchResponse = response.json().await?;
+        Ok(search_response)
+    }
+
+    /// Search results as text in `format` (`csv` or `jsonl`), with the cursor for the next page
+    pub async fn search_export(&self, repo: &str, request: &SearchRequest, format: &str) -> Result<(String, Option<String>)> {
+        let url = self.search_url(repo, request, Some(format));
+
+        let response = self.client
+            .get(&url)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow!("Search failed: {}", error_text));
+        }
+
+        let next_cursor = response
+            .headers()
+            .get("x-next-cursor")
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string());
+        Ok((response.text().await?, next_cursor))
+    }
+
+    fn search_url(&self, repo: &str, request: &SearchRequest, format: Option<&str>) -> String {
         let mut url = format!("{}/v1/repos/{}/search", self.base_url, repo);
         
         let mut query_params = Vec::new();
@@ -169,24 +447,18 @@ impl ApiClient {
         if let Some(offset) = request.offset {
             query_params.push(format!("offset={}", offset));
         }
+        if let Some(cursor) = &request.cursor {
+            query_params.push(format!("cursor={}", urlencoding::encode(cursor)));
+        }
+        if let Some(format) = format {
+            query_params.push(format!("format={}", format));
+        }
 
         if !query_params.is_empty() {
             url.push('?');
             url.push_str(&query_params.join("&"));
         }
-
-        let response = self.client
-            .get(&url)
-            .send()
-            .await?;
-
-        if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow!("Search failed: {}", error_text));
-        }
-
-        let search_response: SearchResponse = response.json().await?;
-        Ok(search_response)
+        url
     }
 
     pub async fn get_blob(&self, repo: &str, r#ref: &str, path: &str) -> Result<String> {
@@ -211,6 +483,29 @@ impl ApiClient {
         Ok(download_url.to_string())
     }
 
+    /// Stream a committed blob's bytes and return their hex sha256
+    pub async fn blob_sha256(&self, repo: &str, r#ref: &str, path: &str) -> Result<String> {
+        let url = format!("{}/v1/repos/{}/blob/{}/{}?raw=true", 
+            self.base_url, repo, r#ref, urlencoding::encode(path));
+        
+        let mut req = self.client.get(&url);
+        if let Some(ref token) = self.token {
+            req = req.bearer_auth(token);
+        }
+        let mut response = req.send().await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow!("Get blob failed: {}", error_text));
+        }
+
+        let mut hasher = Sha256::new();
+        while let Some(chunk) = response.chunk().await? {
+            hasher.update(&chunk);
+        }
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+
     pub async fn get_rdf(&self, repo: &str, r#ref: &str, path: &str, format: &str) -> Result<String> {
         let url = format!("{}/v1/repos/{}/rdf/{}/{}?format={}", 
             self.base_url, repo, r#ref, urlencoding::encode(path), format);
@@ -229,6 +524,67 @@ impl ApiClient {
         Ok(rdf_content)
     }
 
+    /// List references, optionally filtered to `branch` or `tag`
+    pub async fn list_refs(&self, repo: &str, kind: Option<&str>) -> Result<Vec<Reference>> {
+        let mut url = format!("{}/v1/repos/{}/refs", self.base_url, repo);
+
+        if let Some(kind) = kind {
+            url.push_str(&format!("?kind={}", kind));
+        }
+
+        let mut req = self.client.get(&url);
+        if let Some(ref token) = self.token {
+            req = req.bearer_auth(token);
+        }
+
+        let response = req.send().await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow!("List refs failed: {}", error_text));
+        }
+
+        let refs: Vec<Reference> = response.json().await?;
+        Ok(refs)
+    }
+
+    pub async fn create_tag(&self, repo: &str, request: &CreateTagRequest) -> Result<Reference> {
+        let url = format!("{}/v1/repos/{}/tags", self.base_url, repo);
+
+        let mut req = self.client.post(&url).json(request);
+        if let Some(ref token) = self.token {
+            req = req.bearer_auth(token);
+        }
+
+        let response = req.send().await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow!("Create tag failed: {}", error_text));
+        }
+
+        let tag: Reference = response.json().await?;
+        Ok(tag)
+    }
+
+    pub async fn delete_ref(&self, repo: &str, name: &str) -> Result<()> {
+        let url = format!("{}/v1/repos/{}/refs/{}", self.base_url, repo, name);
+
+        let mut req = self.client.delete(&url);
+        if let Some(ref token) = self.token {
+            req = req.bearer_auth(token);
+        }
+
+        let response = req.send().await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow!("Delete ref failed: {}", error_text));
+        }
+
+        Ok(())
+    }
+
     pub async fn get_schema(&self, collection: Option<&str>) -> Result<Value> {
         let url = if let Some(collection) = collection {
             format!("{}/v1/schemas/{}", self.base_url, collection)
@@ -268,4 +624,47 @@ mod tests {
             .with_token("test-token".to_string());
         assert_eq!(client.token, Some("test-token".to_string()));
     }
+
+    #[tokio::test]
+    async fn test_commit_retries_under_one_idempotency_key() {
+        use axum::{extract::State, http::{HeaderMap, StatusCode}, response::IntoResponse, routing::post, Json, Router};
+        use std::sync::{Arc, Mutex};
+
+        // Fails the first attempt, then answers; records every key it saw
+        let keys: Arc<Mutex<Vec<String>>> = Arc::default();
+        let app = Router::new()
+            .route(
+                "/v1/repos/:repo/commit",
+                post(|State(keys): State<Arc<Mutex<Vec<String>>>>, headers: HeaderMap| async move {
+                    let mut keys = keys.lock().unwrap();
+                    keys.push(headers["idempotency-key"].to_str().unwrap().to_string());
+                    if keys.len() == 1 {
+                        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+                    }
+                    Json(serde_json::json!({
+                        "commit_id": Uuid::nil(),
+                        "parent_id": null,
+                        "created_at": "2024-01-01T00:00:00Z"
+                    }))
+                    .into_response()
+                }),
+            )
+            .with_state(keys.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let request = CommitRequest {
+            r#ref: "main".to_string(),
+            message: Some("retry".to_string()),
+            changes: vec![],
+            expected_parent: None,
+        };
+        let response = ApiClient::new(base_url).commit("mylab", &request, false).await.unwrap();
+
+        assert_eq!(response.commit_id.0, Uuid::nil());
+        let keys = keys.lock().unwrap();
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys[0], keys[1]);
+    }
 }
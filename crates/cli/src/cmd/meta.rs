@@ -1,6 +1,6 @@
 use anyhow::{anyhow, Result};
 use blacklake_core::{CanonicalMeta, Change, ChangeOp, CommitRequest};
-use crate::api::ApiClient;
+use crate::api::{report_validation, ApiClient};
 use crate::prompt::{collect_metadata_interactive, PromptContext};
 use clap::Args;
 use colored::*;
@@ -106,11 +106,6 @@ pub async fn meta_edit_command(args: MetaEditArgs, api_client: &ApiClient) -> Re
     println!("📊 Metadata changes:");
     show_metadata_diff(&current_metadata, &new_metadata);
 
-    if args.dry_run {
-        println!("🔍 Dry run - would commit metadata changes");
-        return Ok(());
-    }
-
     // Create metadata-only commit
     let commit_request = CommitRequest {
         r#ref: args.r#ref.clone(),
@@ -124,6 +119,12 @@ pub async fn meta_edit_command(args: MetaEditArgs, api_client: &ApiClient) -> Re
         }],
     };
 
+    if args.dry_run {
+        println!("🔍 Dry run - would commit metadata changes");
+        let validation = api_client.validate_commit(&args.repo, &commit_request).await?;
+        return report_validation(&validation);
+    }
+
     println!("💾 Committing metadata changes...");
     let commit_response = api_client.commit(&args.repo, &commit_request, true).await?;
 
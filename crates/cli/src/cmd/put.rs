@@ -1,6 +1,8 @@
 use anyhow::{anyhow, Result};
+use blacklake_core::signing::{SignatureBundle, SignatureStatus};
 use blacklake_core::{CanonicalMeta, Change, ChangeOp, CommitRequest};
-use crate::api::ApiClient;
+use crate::api::{report_validation, ApiClient};
+use crate::multipart::{self, PartState, DEFAULT_UPLOAD_CONCURRENCY};
 use crate::prompt::{collect_metadata_interactive, load_templates, select_template, PromptContext};
 use clap::Args;
 use colored::*;
@@ -95,6 +97,14 @@ pub struct PutArgs {
     /// Non-interactive mode
     #[arg(long)]
     pub non_interactive: bool,
+    
+    /// Parts uploaded in parallel for multipart uploads
+    #[arg(long, default_value_t = DEFAULT_UPLOAD_CONCURRENCY)]
+    pub concurrency: usize,
+
+    /// Cosign bundle (`cosign sign-blob --bundle`) to attach to the upload
+    #[arg(long)]
+    pub signature: Option<String>,
 }
 
 fn parse_key_value(s: &str) -> Result<(String, String)> {
@@ -137,15 +147,73 @@ pub async fn put_command(args: PutArgs, api_client: &ApiClient) -> Result<()> {
         );
     }
 
-    // Step 1: Initialize upload
-    let upload_init = api_client.upload_init(&args.repo, &crate::api::UploadInitRequest {
-        path: args.path.clone(),
-        size: file_size,
-        media_type: mime_type.clone(),
-    }).await?;
+    // Step 1: Initialize upload, or pick up an interrupted multipart upload
+    let resumed = PartState::load(&PartState::state_path(local_file_path))?
+        .filter(|state| state.resumable(&args.repo, &args.path, file_size, chrono::Utc::now()));
 
-    println!("📤 Uploading file...");
-    api_client.upload_file(&upload_init.upload_url, local_file_path).await?;
+    let (sha256, multipart) = match resumed {
+        Some(state) => {
+            println!("⏯️  Resuming upload: {}/{} parts already uploaded", state.completed.len(), state.parts.len());
+            let sha256 = state.sha256.clone();
+            multipart::upload_and_complete(api_client, local_file_path, state, args.concurrency).await?;
+            (sha256, true)
+        }
+        None => {
+            let content_sha256 = if local_file_path.is_file() {
+                Some(crate::staging::hash_file(local_file_path)?)
+            } else {
+                None
+            };
+            let upload_init = api_client.upload_init(&args.repo, &crate::api::UploadInitRequest {
+                path: args.path.clone(),
+                size: file_size,
+                media_type: mime_type.clone(),
+                sha256: content_sha256,
+            }).await?;
+
+            match PartState::new(&args.repo, &args.path, file_size, &upload_init) {
+                Some(state) => {
+                    println!("📤 Uploading file in {} parts ({} at a time)...", state.parts.len(), args.concurrency);
+                    multipart::upload_and_complete(api_client, local_file_path, state, args.concurrency).await?;
+                    (upload_init.sha256, true)
+                }
+                None if upload_init.already_stored => {
+                    println!("⏭️  Content already stored; skipping upload");
+                    (upload_init.sha256, false)
+                }
+                None => {
+                    println!("📤 Uploading file...");
+                    api_client.upload_file(&upload_init.upload_url, local_file_path, &upload_init.upload_headers).await?;
+                    (upload_init.sha256, false)
+                }
+            }
+        }
+    };
+
+    // Confirm the stored bytes match the content address before committing to it
+    if local_file_path.is_file() {
+        let signature = args
+            .signature
+            .as_deref()
+            .map(|bundle| -> Result<SignatureBundle> {
+                let text = std::fs::read_to_string(bundle)?;
+                serde_json::from_str(&text).map_err(|e| anyhow!("Invalid signature bundle {}: {}", bundle, e))
+            })
+            .transpose()?;
+        let verified = api_client.upload_verify(&args.repo, &sha256, signature).await?;
+        if let Some(signature) = verified.signature {
+            match signature.status {
+                SignatureStatus::Verified => println!(
+                    "🔏 Signature verified by {}",
+                    signature.signer.unwrap_or_default().green()
+                ),
+                _ => println!(
+                    "⚠️  Signature not trusted: {}",
+                    signature.reason.unwrap_or_default().yellow()
+                ),
+            }
+        }
+    }
 
     // Step 2: Collect metadata
     let metadata = if let Some(bl_metadata) = bl_metadata {
@@ -165,20 +233,13 @@ pub async fn put_command(args: PutArgs, api_client: &ApiClient) -> Result<()> {
 
     // If uploading a directory, handle multiple files
     if local_file_path.is_dir() {
+        if args.signature.is_some() {
+            return Err(anyhow!("--signature covers a single file and cannot be used when uploading a directory"));
+        }
         return upload_directory_with_metadata(local_file_path, &args, api_client, &metadata).await;
     }
 
     // Step 3: Create commit
-    if args.dry_run {
-        println!("🔍 Dry run - would commit:");
-        println!("  Repository: {}", args.repo);
-        println!("  Ref: {}", args.r#ref);
-        println!("  Path: {}", args.path);
-        println!("  SHA256: {}", upload_init.sha256);
-        println!("  Metadata: {}", serde_json::to_string_pretty(&metadata)?);
-        return Ok(());
-    }
-
     let commit_request = CommitRequest {
         r#ref: args.r#ref.clone(),
         message: Some(format!("Add {}", args.path)),
@@ -186,15 +247,33 @@ pub async fn put_command(args: PutArgs, api_client: &ApiClient) -> Result<()> {
         changes: vec![Change {
             op: ChangeOp::Add,
             path: args.path.clone(),
-            sha256: Some(upload_init.sha256),
+            sha256: Some(sha256.clone()),
             meta: serde_json::to_value(&metadata)?,
         }],
     };
 
+    if args.dry_run {
+        println!("🔍 Dry run - would commit:");
+        println!("  Repository: {}", args.repo);
+        println!("  Ref: {}", args.r#ref);
+        println!("  Path: {}", args.path);
+        println!("  SHA256: {}", sha256);
+        println!("  Metadata: {}", serde_json::to_string_pretty(&metadata)?);
+        let validation = api_client.validate_commit(&args.repo, &commit_request).await?;
+        return report_validation(&validation);
+    }
+
     println!("💾 Committing changes...");
     let commit_response = api_client.commit(&args.repo, &commit_request, true).await?;
 
     println!("✅ Successfully committed: {:?}", commit_response.commit_id);
+
+    // Parts are assembled server-side, so check the result byte for byte
+    if multipart {
+        println!("🔍 Verifying uploaded object...");
+        multipart::verify_upload(api_client, &args.repo, &args.r#ref, &args.path, local_file_path).await?;
+        println!("✅ Uploaded object matches local file");
+    }
     
     if args.emit_rdf {
         println!("🔗 RDF metadata available at: /v1/repos/{}/rdf/{}/{}", 
@@ -507,9 +586,14 @@ async fn upload_directory_with_metadata(
                 path: repo_path.clone(),
                 size: file_size,
                 media_type: mime_type,
+                sha256: Some(crate::staging::hash_file(&entry_path)?),
             }).await?;
             
-            api_client.upload_file(&upload_init.upload_url, &entry_path).await?;
+            if !upload_init.already_stored {
+                api_client.upload_file(&upload_init.upload_url, &entry_path, &upload_init.upload_headers).await?;
+                // A signature bundle covers one blob, so directory files go unsigned
+                api_client.upload_verify(&args.repo, &upload_init.sha256, None).await?;
+            }
             
             changes.push(Change {
                 op: ChangeOp::Add,
@@ -621,4 +705,126 @@ mod tests {
         set_metadata_field(&mut metadata, "tags", "tag1,tag2,tag3").unwrap();
         assert_eq!(metadata.tags, Some(vec!["tag1".to_string(), "tag2".to_string(), "tag3".to_string()]));
     }
+
+    /// Requests the stub server below received, in order
+    type Recorded = std::sync::Arc<std::sync::Mutex<Vec<(String, Value)>>>;
+
+    /// A server answering upload-init with a PUT URL on itself, and recording
+    /// every upload-verify and commit body
+    async fn stub_upload_api(recorded: Recorded) -> ApiClient {
+        use axum::{body::Bytes, extract::State, routing::{post, put}, Json, Router};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let upload_base = base_url.clone();
+        let app = Router::new()
+            .route(
+                "/v1/repos/:repo/upload-init",
+                post(move |Json(body): Json<Value>| async move {
+                    let sha256 = body["sha256"].as_str().unwrap().to_string();
+                    Json(serde_json::json!({
+                        "upload_url": format!("{}/s3/{}", upload_base, sha256),
+                        "sha256": sha256,
+                        "s3_key": format!("objects/{}", sha256),
+                        "expires_at": "2030-01-01T00:00:00Z",
+                        "multipart": null
+                    }))
+                }),
+            )
+            .route("/s3/:sha256", put(|_body: Bytes| async {}))
+            .route(
+                "/v1/repos/:repo/upload-verify",
+                post(|State(recorded): State<Recorded>, Json(body): Json<Value>| async move {
+                    recorded.lock().unwrap().push(("upload-verify".to_string(), body.clone()));
+                    Json(serde_json::json!({"sha256": body["sha256"], "verification": "verified"}))
+                }),
+            )
+            .route(
+                "/v1/repos/:repo/commit",
+                post(|State(recorded): State<Recorded>, Json(body): Json<Value>| async move {
+                    recorded.lock().unwrap().push(("commit".to_string(), body));
+                    Json(serde_json::json!({
+                        "commit_id": uuid::Uuid::nil(),
+                        "parent_id": null,
+                        "created_at": "2024-01-01T00:00:00Z"
+                    }))
+                }),
+            )
+            .with_state(recorded);
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        ApiClient::new(base_url)
+    }
+
+    fn directory_put_args(local_file: &Path) -> PutArgs {
+        PutArgs {
+            repo: "climate".to_string(),
+            r#ref: "main".to_string(),
+            local_file: local_file.to_string_lossy().into_owned(),
+            path: "data/".to_string(),
+            r#type: None,
+            emit_rdf: false,
+            open_editor: false,
+            meta: None,
+            meta_key: Vec::new(),
+            template: None,
+            dry_run: false,
+            non_interactive: true,
+            concurrency: DEFAULT_UPLOAD_CONCURRENCY,
+            signature: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_directory_put_verifies_each_file_before_committing() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.csv"), "x,y\n1,2\n").unwrap();
+        std::fs::write(dir.path().join("b.csv"), "x,y\n3,4\n").unwrap();
+        std::fs::write(dir.path().join(".hidden"), "skipped").unwrap();
+
+        let recorded = Recorded::default();
+        let api_client = stub_upload_api(recorded.clone()).await;
+        let base_metadata = CanonicalMeta {
+            creation_dt: chrono::Utc::now(),
+            creator: "test@example.com".to_string(),
+            file_name: "".to_string(),
+            file_type: "text/csv".to_string(),
+            file_size: 0,
+            org_lab: "lab".to_string(),
+            description: "readings".to_string(),
+            data_source: "sensor".to_string(),
+            data_collection_method: "automatic".to_string(),
+            version: "1".to_string(),
+            notes: None,
+            tags: None,
+            license: None,
+        };
+
+        upload_directory_with_metadata(dir.path(), &directory_put_args(dir.path()), &api_client, &base_metadata)
+            .await
+            .unwrap();
+
+        let recorded = recorded.lock().unwrap();
+        let (verifies, commits): (Vec<_>, Vec<_>) = recorded.iter().partition(|(route, _)| route == "upload-verify");
+        let mut verified: Vec<&str> = verifies.iter().map(|(_, body)| body["sha256"].as_str().unwrap()).collect();
+        verified.sort();
+        let mut expected = vec![
+            crate::staging::hash_file(&dir.path().join("a.csv")).unwrap(),
+            crate::staging::hash_file(&dir.path().join("b.csv")).unwrap(),
+        ];
+        expected.sort();
+        assert_eq!(verified, expected);
+        assert!(verifies.iter().all(|(_, body)| body.get("signature").is_none()));
+
+        // Every file is verified before the single commit that adds them
+        assert_eq!(commits.len(), 1);
+        assert_eq!(recorded.last().unwrap().0, "commit");
+        let mut committed: Vec<&str> = commits[0].1["changes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|change| change["path"].as_str().unwrap())
+            .collect();
+        committed.sort();
+        assert_eq!(committed, vec!["data/a.csv", "data/b.csv"]);
+    }
 }
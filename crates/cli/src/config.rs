@@ -0,0 +1,280 @@
+//! CLI configuration file with named profiles.
+//!
+//! `~/.config/blacklake/config.toml` (under `$XDG_CONFIG_HOME` when set)
+//! holds one table per profile:
+//!
+//! ```toml
+//! default_profile = "staging"
+//!
+//! [profiles.staging]
+//! api_url = "https://staging.blacklake.example"
+//! token = "..."
+//! repo = "climate"
+//! ref = "dev"
+//! output = "json"
+//! ```
+//!
+//! The profile is chosen by `--profile`, then `BLACKLAKE_PROFILE`, then
+//! `default_profile`, then `default`. Flags given on the command line win
+//! over the profile's values.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_API_URL: &str = "http://localhost:8080";
+pub const DEFAULT_PROFILE: &str = "default";
+/// Environment variable naming the profile to use
+pub const PROFILE_ENV: &str = "BLACKLAKE_PROFILE";
+/// Keys `blacklake config set/get` accepts
+pub const KEYS: [&str; 5] = ["api_url", "token", "repo", "ref", "output"];
+/// Values of the `output` key
+pub const OUTPUT_FORMATS: [&str; 3] = ["table", "json", "csv"];
+
+/// One named set of defaults
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Profile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    /// Repository used when a command's repo argument is left out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repo: Option<String>,
+    /// Branch or ref used when a command's ref is left out
+    #[serde(default, rename = "ref", skip_serializing_if = "Option::is_none")]
+    pub r#ref: Option<String>,
+    /// Output format for listings: table, json or csv
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+}
+
+impl Profile {
+    pub fn get(&self, key: &str) -> Result<Option<&str>> {
+        let value = match key {
+            "api_url" => &self.api_url,
+            "token" => &self.token,
+            "repo" => &self.repo,
+            "ref" => &self.r#ref,
+            "output" => &self.output,
+            other => return Err(unknown_key(other)),
+        };
+        Ok(value.as_deref())
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        let field = match key {
+            "api_url" => &mut self.api_url,
+            "token" => &mut self.token,
+            "repo" => &mut self.repo,
+            "ref" => &mut self.r#ref,
+            "output" if OUTPUT_FORMATS.contains(&value) => &mut self.output,
+            "output" => {
+                return Err(anyhow!(
+                    "Unknown output format '{}'; expected one of {}",
+                    value,
+                    OUTPUT_FORMATS.join(", ")
+                ))
+            }
+            other => return Err(unknown_key(other)),
+        };
+        *field = Some(value.to_string());
+        Ok(())
+    }
+}
+
+fn unknown_key(key: &str) -> anyhow::Error {
+    anyhow!("Unknown config key '{}'; expected one of {}", key, KEYS.join(", "))
+}
+
+/// Contents of the config file
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Config {
+    /// Profile used when neither `--profile` nor `BLACKLAKE_PROFILE` names one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_profile: Option<String>,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+impl Config {
+    /// Where the config file lives for the current user
+    pub fn default_path() -> Option<PathBuf> {
+        let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| dirs::home_dir().map(|home| home.join(".config")))?;
+        Some(config_dir.join("blacklake").join("config.toml"))
+    }
+
+    /// Read the config at `path`, starting empty if there is none yet
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)?;
+        toml::from_str(&content).map_err(|e| anyhow!("Invalid config file {}: {}", path.display(), e))
+    }
+
+    /// Write the config to `path`; it may hold tokens, so only the owner can read it
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, toml::to_string_pretty(self)?)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+        }
+        Ok(())
+    }
+
+    /// Name of the profile to use, from the `--profile` flag or `BLACKLAKE_PROFILE`
+    pub fn profile_name(&self, flag: Option<&str>, env: Option<&str>) -> String {
+        flag.or(env)
+            .or(self.default_profile.as_deref())
+            .unwrap_or(DEFAULT_PROFILE)
+            .to_string()
+    }
+
+    /// The profile called `name`. Only the `default` profile may be missing,
+    /// in which case it is empty.
+    pub fn profile(&self, name: &str) -> Result<Profile> {
+        match self.profiles.get(name) {
+            Some(profile) => Ok(profile.clone()),
+            None if name == DEFAULT_PROFILE => Ok(Profile::default()),
+            None => Err(anyhow!("Profile '{}' is not defined in the config file", name)),
+        }
+    }
+}
+
+/// Settings given as command-line flags
+#[derive(Debug, Clone, Default)]
+pub struct Flags {
+    pub api_url: Option<String>,
+    pub token: Option<String>,
+}
+
+/// What a command runs with: the selected profile with flags applied over it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+    pub api_url: String,
+    pub token: Option<String>,
+    pub repo: Option<String>,
+    pub r#ref: Option<String>,
+    pub output: Option<String>,
+}
+
+impl Settings {
+    pub fn resolve(profile: Profile, flags: Flags) -> Self {
+        Self {
+            api_url: flags
+                .api_url
+                .or(profile.api_url)
+                .unwrap_or_else(|| DEFAULT_API_URL.to_string()),
+            token: flags.token.or(profile.token),
+            repo: profile.repo,
+            r#ref: profile.r#ref,
+            output: profile.output,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const CONFIG: &str = r#"
+default_profile = "staging"
+
+[profiles.default]
+api_url = "http://localhost:8080"
+
+[profiles.staging]
+api_url = "https://staging.blacklake.example"
+token = "staging-token"
+repo = "climate"
+ref = "dev"
+output = "json"
+
+[profiles.prod]
+api_url = "https://blacklake.example"
+token = "prod-token"
+"#;
+
+    fn write_config(content: &str) -> (TempDir, PathBuf) {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("blacklake").join("config.toml");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, content).unwrap();
+        (dir, path)
+    }
+
+    #[test]
+    fn test_load_uses_the_default_profile() {
+        let (_dir, path) = write_config(CONFIG);
+        let config = Config::load(&path).unwrap();
+
+        let name = config.profile_name(None, None);
+        let settings = Settings::resolve(config.profile(&name).unwrap(), Flags::default());
+
+        assert_eq!(name, "staging");
+        assert_eq!(settings.api_url, "https://staging.blacklake.example");
+        assert_eq!(settings.token.as_deref(), Some("staging-token"));
+        assert_eq!(settings.repo.as_deref(), Some("climate"));
+        assert_eq!(settings.r#ref.as_deref(), Some("dev"));
+        assert_eq!(settings.output.as_deref(), Some("json"));
+    }
+
+    #[test]
+    fn test_flags_override_the_profile() {
+        let (_dir, path) = write_config(CONFIG);
+        let config = Config::load(&path).unwrap();
+        let flags = Flags { api_url: Some("http://127.0.0.1:9000".to_string()), token: None };
+
+        let settings = Settings::resolve(config.profile("staging").unwrap(), flags);
+
+        assert_eq!(settings.api_url, "http://127.0.0.1:9000");
+        assert_eq!(settings.token.as_deref(), Some("staging-token"));
+    }
+
+    #[test]
+    fn test_selecting_a_non_default_profile() {
+        let (_dir, path) = write_config(CONFIG);
+        let config = Config::load(&path).unwrap();
+
+        // The flag wins over the environment, which wins over default_profile
+        assert_eq!(config.profile_name(Some("prod"), Some("default")), "prod");
+        assert_eq!(config.profile_name(None, Some("prod")), "prod");
+
+        let settings = Settings::resolve(config.profile("prod").unwrap(), Flags::default());
+        assert_eq!(settings.api_url, "https://blacklake.example");
+        assert_eq!(settings.repo, None);
+        assert!(config.profile("missing").is_err());
+    }
+
+    #[test]
+    fn test_set_and_get_round_trip_through_the_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("blacklake").join("config.toml");
+        let mut config = Config::load(&path).unwrap();
+        assert_eq!(config.profile(DEFAULT_PROFILE).unwrap(), Profile::default());
+
+        let profile = config.profiles.entry("dev".to_string()).or_default();
+        profile.set("api_url", "http://dev:8080").unwrap();
+        profile.set("ref", "feature").unwrap();
+        assert!(profile.set("output", "yaml").is_err());
+        assert!(profile.set("colour", "blue").is_err());
+        config.save(&path).unwrap();
+
+        let reloaded = Config::load(&path).unwrap().profile("dev").unwrap();
+        assert_eq!(reloaded.get("api_url").unwrap(), Some("http://dev:8080"));
+        assert_eq!(reloaded.get("ref").unwrap(), Some("feature"));
+        assert_eq!(reloaded.get("token").unwrap(), None);
+        assert!(fs::read_to_string(&path).unwrap().contains("ref = \"feature\""));
+    }
+}
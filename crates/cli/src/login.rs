@@ -0,0 +1,369 @@
+//! OIDC device-code login and stored credentials.
+//!
+//! `blacklake login` runs the device authorization grant (RFC 8628) against
+//! the issuer and keeps the resulting tokens in the OS keychain, one entry
+//! per config profile. Commands then use the stored access token, refreshing
+//! it with the refresh token first when it has expired or is about to.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Utc};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+/// Keychain service the logins are filed under
+const KEYCHAIN_SERVICE: &str = "blacklake";
+const DEVICE_CODE_GRANT: &str = "urn:ietf:params:oauth:grant-type:device_code";
+pub const DEFAULT_CLIENT_ID: &str = "blacklake";
+pub const DEFAULT_SCOPE: &str = "openid profile email offline_access";
+/// Environment variable naming the issuer when `--issuer` is not given
+pub const ISSUER_ENV: &str = "BLACKLAKE_OIDC_ISSUER";
+/// Access tokens this close to expiry are refreshed before use
+const EXPIRY_MARGIN_SECONDS: i64 = 60;
+
+/// Tokens from a login, as kept in the keychain
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredLogin {
+    pub access_token: String,
+    pub refresh_token: Option<String>,
+    pub expires_at: DateTime<Utc>,
+    /// Where refreshes are sent, so they work without the issuer at hand
+    pub token_endpoint: String,
+    pub client_id: String,
+}
+
+impl StoredLogin {
+    /// Whether the access token is expired, or will be within the margin, at `now`
+    pub fn needs_refresh(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at - Duration::seconds(EXPIRY_MARGIN_SECONDS) <= now
+    }
+}
+
+/// The keychain entry holding one profile's login
+pub struct Keychain {
+    entry: keyring::Entry,
+}
+
+impl Keychain {
+    pub fn for_profile(profile: &str) -> Result<Self> {
+        let entry = keyring::Entry::new(KEYCHAIN_SERVICE, profile)
+            .map_err(|e| anyhow!("Cannot open keychain entry for profile {}: {}", profile, e))?;
+        Ok(Self { entry })
+    }
+
+    pub fn load(&self) -> Result<Option<StoredLogin>> {
+        match self.entry.get_password() {
+            Ok(secret) => serde_json::from_str(&secret)
+                .map(Some)
+                .map_err(|e| anyhow!("Corrupt login in keychain: {}", e)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(anyhow!("Cannot read keychain: {}", e)),
+        }
+    }
+
+    pub fn save(&self, login: &StoredLogin) -> Result<()> {
+        self.entry
+            .set_password(&serde_json::to_string(login)?)
+            .map_err(|e| anyhow!("Cannot write keychain: {}", e))
+    }
+
+    /// Forget the login; returns whether there was one
+    pub fn delete(&self) -> Result<bool> {
+        match self.entry.delete_password() {
+            Ok(()) => Ok(true),
+            Err(keyring::Error::NoEntry) => Ok(false),
+            Err(e) => Err(anyhow!("Cannot write keychain: {}", e)),
+        }
+    }
+}
+
+/// The endpoints `login` needs, from the issuer's discovery document
+#[derive(Debug, Clone, Deserialize)]
+pub struct OidcEndpoints {
+    pub device_authorization_endpoint: String,
+    pub token_endpoint: String,
+}
+
+/// What the user is asked to do to approve the login
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeviceAuthorization {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    #[serde(default)]
+    pub verification_uri_complete: Option<String>,
+    pub expires_in: u64,
+    /// Seconds to wait between polls
+    #[serde(default = "default_poll_interval")]
+    pub interval: u64,
+}
+
+fn default_poll_interval() -> u64 {
+    5
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    #[serde(default)]
+    refresh_token: Option<String>,
+    #[serde(default)]
+    expires_in: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenError {
+    error: String,
+    #[serde(default)]
+    error_description: Option<String>,
+}
+
+impl TokenError {
+    fn into_error(self, action: &str) -> anyhow::Error {
+        match self.error_description {
+            Some(description) => anyhow!("{} failed: {} ({})", action, self.error, description),
+            None => anyhow!("{} failed: {}", action, self.error),
+        }
+    }
+}
+
+fn stored_login(tokens: TokenResponse, previous_refresh: Option<String>, endpoints: (&str, &str)) -> StoredLogin {
+    let (token_endpoint, client_id) = endpoints;
+    StoredLogin {
+        access_token: tokens.access_token,
+        // Servers that don't rotate refresh tokens leave it out of the response
+        refresh_token: tokens.refresh_token.or(previous_refresh),
+        expires_at: Utc::now() + Duration::seconds(tokens.expires_in.unwrap_or(300)),
+        token_endpoint: token_endpoint.to_string(),
+        client_id: client_id.to_string(),
+    }
+}
+
+/// Fetch the issuer's OIDC discovery document
+pub async fn discover(client: &Client, issuer: &str) -> Result<OidcEndpoints> {
+    let url = format!("{}/.well-known/openid-configuration", issuer.trim_end_matches('/'));
+    let response = client.get(&url).send().await?;
+    if !response.status().is_success() {
+        return Err(anyhow!("OIDC discovery at {} failed: {}", url, response.status()));
+    }
+    response
+        .json()
+        .await
+        .map_err(|e| anyhow!("Issuer {} does not support the device flow: {}", issuer, e))
+}
+
+/// Run the device authorization grant: `prompt` tells the user where to
+/// approve the login, then the token endpoint is polled until they do
+pub async fn device_login(
+    client: &Client,
+    endpoints: &OidcEndpoints,
+    client_id: &str,
+    scope: &str,
+    prompt: impl FnOnce(&DeviceAuthorization),
+) -> Result<StoredLogin> {
+    let response = client
+        .post(&endpoints.device_authorization_endpoint)
+        .form(&[("client_id", client_id), ("scope", scope)])
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        let error: TokenError = response.json().await?;
+        return Err(error.into_error("Device authorization"));
+    }
+    let authorization: DeviceAuthorization = response.json().await?;
+    prompt(&authorization);
+
+    let deadline = Utc::now() + Duration::seconds(authorization.expires_in as i64);
+    let mut interval = authorization.interval;
+    loop {
+        tokio::time::sleep(std::time::Duration::from_secs(interval)).await;
+        if Utc::now() > deadline {
+            return Err(anyhow!("Login timed out; run `blacklake login` again"));
+        }
+
+        let response = client
+            .post(&endpoints.token_endpoint)
+            .form(&[
+                ("grant_type", DEVICE_CODE_GRANT),
+                ("device_code", authorization.device_code.as_str()),
+                ("client_id", client_id),
+            ])
+            .send()
+            .await?;
+        if response.status().is_success() {
+            let tokens: TokenResponse = response.json().await?;
+            return Ok(stored_login(tokens, None, (&endpoints.token_endpoint, client_id)));
+        }
+
+        let error: TokenError = response.json().await?;
+        match error.error.as_str() {
+            "authorization_pending" => {}
+            "slow_down" => interval += 5,
+            _ => return Err(error.into_error("Login")),
+        }
+    }
+}
+
+/// Exchange `login`'s refresh token for a new access token
+pub async fn refresh(client: &Client, login: &StoredLogin) -> Result<StoredLogin> {
+    let refresh_token = login
+        .refresh_token
+        .as_deref()
+        .ok_or_else(|| anyhow!("Login has expired and cannot be refreshed; run `blacklake login`"))?;
+    let response = client
+        .post(&login.token_endpoint)
+        .form(&[
+            ("grant_type", "refresh_token"),
+            ("refresh_token", refresh_token),
+            ("client_id", login.client_id.as_str()),
+        ])
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        let error: TokenError = response.json().await?;
+        return Err(anyhow!("{}; run `blacklake login`", error.into_error("Token refresh")));
+    }
+
+    let tokens: TokenResponse = response.json().await?;
+    Ok(stored_login(tokens, login.refresh_token.clone(), (&login.token_endpoint, &login.client_id)))
+}
+
+/// The access token of `login`, loaded from `keychain`; when it has expired
+/// it is refreshed and the new tokens are written back first
+pub async fn access_token(client: &Client, keychain: &Keychain, login: StoredLogin) -> Result<String> {
+    if !login.needs_refresh(Utc::now()) {
+        return Ok(login.access_token);
+    }
+
+    let refreshed = refresh(client, &login).await?;
+    keychain.save(&refreshed)?;
+    Ok(refreshed.access_token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::State, routing::post, Form, Json, Router};
+    use serde_json::{json, Value};
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    /// Stub identity provider; records every form posted to its token endpoint
+    async fn stub_issuer(pending_polls: usize) -> (OidcEndpoints, Arc<Mutex<Vec<HashMap<String, String>>>>) {
+        let requests: Arc<Mutex<Vec<HashMap<String, String>>>> = Arc::default();
+        let app = Router::new()
+            .route(
+                "/device",
+                post(|| async {
+                    Json(json!({
+                        "device_code": "dev-code",
+                        "user_code": "ABCD-EFGH",
+                        "verification_uri": "https://idp.example/device",
+                        "expires_in": 60,
+                        "interval": 0
+                    }))
+                }),
+            )
+            .route(
+                "/token",
+                post(
+                    move |State(requests): State<Arc<Mutex<Vec<HashMap<String, String>>>>>,
+                          Form(form): Form<HashMap<String, String>>| async move {
+                        let mut requests = requests.lock().unwrap();
+                        requests.push(form.clone());
+                        let body: Value = match form["grant_type"].as_str() {
+                            DEVICE_CODE_GRANT if requests.len() <= pending_polls => {
+                                return (axum::http::StatusCode::BAD_REQUEST, Json(json!({"error": "authorization_pending"})));
+                            }
+                            DEVICE_CODE_GRANT => json!({"access_token": "first", "refresh_token": "refresh-1", "expires_in": 3600}),
+                            _ => json!({"access_token": format!("refreshed-{}", requests.len()), "expires_in": 3600}),
+                        };
+                        (axum::http::StatusCode::OK, Json(body))
+                    },
+                ),
+            )
+            .with_state(requests.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let endpoints = OidcEndpoints {
+            device_authorization_endpoint: format!("{}/device", base),
+            token_endpoint: format!("{}/token", base),
+        };
+        (endpoints, requests)
+    }
+
+    fn mock_keychain(profile: &str) -> Keychain {
+        keyring::set_default_credential_builder(keyring::mock::default_credential_builder());
+        Keychain::for_profile(profile).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_device_login_polls_until_approved() {
+        let (endpoints, requests) = stub_issuer(2).await;
+        let mut shown = None;
+
+        let login = device_login(&Client::new(), &endpoints, DEFAULT_CLIENT_ID, DEFAULT_SCOPE, |auth| {
+            shown = Some(auth.user_code.clone())
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(shown.as_deref(), Some("ABCD-EFGH"));
+        assert_eq!(login.access_token, "first");
+        assert_eq!(login.refresh_token.as_deref(), Some("refresh-1"));
+        assert_eq!(requests.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_expired_token_is_refreshed_and_stored() {
+        let (endpoints, requests) = stub_issuer(0).await;
+        let keychain = mock_keychain("refresh-test");
+        let client = Client::new();
+        keychain
+            .save(&StoredLogin {
+                access_token: "stale".to_string(),
+                refresh_token: Some("refresh-1".to_string()),
+                expires_at: Utc::now() - Duration::minutes(5),
+                token_endpoint: endpoints.token_endpoint.clone(),
+                client_id: DEFAULT_CLIENT_ID.to_string(),
+            })
+            .unwrap();
+
+        let stored = keychain.load().unwrap().unwrap();
+        assert_eq!(access_token(&client, &keychain, stored).await.unwrap(), "refreshed-1");
+        {
+            let requests = requests.lock().unwrap();
+            assert_eq!(requests[0]["grant_type"], "refresh_token");
+            assert_eq!(requests[0]["refresh_token"], "refresh-1");
+        }
+
+        // The refreshed token is stored, keeps its refresh token, and is reused while valid
+        let stored = keychain.load().unwrap().unwrap();
+        assert_eq!(stored.access_token, "refreshed-1");
+        assert_eq!(stored.refresh_token.as_deref(), Some("refresh-1"));
+        assert_eq!(access_token(&client, &keychain, stored).await.unwrap(), "refreshed-1");
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_keychain_round_trip() {
+        let keychain = mock_keychain("round-trip");
+        assert_eq!(keychain.load().unwrap(), None);
+
+        let login = StoredLogin {
+            access_token: "access".to_string(),
+            refresh_token: None,
+            expires_at: Utc::now() + Duration::hours(1),
+            token_endpoint: "https://idp.example/token".to_string(),
+            client_id: DEFAULT_CLIENT_ID.to_string(),
+        };
+        keychain.save(&login).unwrap();
+
+        assert_eq!(keychain.load().unwrap(), Some(login.clone()));
+        assert!(!login.needs_refresh(Utc::now()));
+        assert!(login.needs_refresh(Utc::now() + Duration::minutes(59) + Duration::seconds(30)));
+        assert!(keychain.delete().unwrap());
+        assert_eq!(keychain.load().unwrap(), None);
+    }
+}
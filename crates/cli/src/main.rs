@@ -1,21 +1,29 @@
-use clap::{Parser, Subcommand, CommandFactory};
+use clap::{Args, Parser, Subcommand, CommandFactory};
 use reqwest::Client;
 use serde_json::json;
 use std::fs;
 use std::path::PathBuf;
 use uuid::Uuid;
+use blacklake_core::features::RepoFeature;
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
 
 mod api;
+mod config;
+mod login;
+mod multipart;
 mod cmd {
     pub mod meta;
     pub mod put;
     pub mod init;
 }
 mod prompt;
+mod staging;
+mod tui;
 
 use api::ApiClient;
+use config::{Config, Flags, Settings};
+use staging::Staging;
 use cmd::{put, meta, init};
 
 #[derive(Parser)]
@@ -23,13 +31,17 @@ use cmd::{put, meta, init};
 #[command(about = "Blacklake CLI - Git-style data artifact service")]
 #[command(version)]
 struct Cli {
-    /// API base URL
-    #[arg(long, default_value = "http://localhost:8080")]
-    api_url: String,
+    /// API base URL (default: the profile's api_url, then http://localhost:8080)
+    #[arg(long)]
+    api_url: Option<String>,
     
-    /// Authentication token
+    /// Authentication token (default: the profile's `blacklake login`, then its token)
     #[arg(long)]
     token: Option<String>,
+
+    /// Config profile to use (default: $BLACKLAKE_PROFILE, then the file's default_profile)
+    #[arg(long, global = true)]
+    profile: Option<String>,
     
     /// Verbose output
     #[arg(short, long)]
@@ -76,6 +88,12 @@ enum Commands {
         /// Non-interactive mode
         #[arg(long)]
         non_interactive: bool,
+        /// Parts uploaded in parallel for multipart uploads
+        #[arg(long, default_value_t = multipart::DEFAULT_UPLOAD_CONCURRENCY)]
+        concurrency: usize,
+        /// Cosign bundle (`cosign sign-blob --bundle`) to attach to the upload
+        #[arg(long)]
+        signature: Option<String>,
     },
     /// Edit metadata for existing files
     Meta {
@@ -95,40 +113,7 @@ enum Commands {
         out: Option<String>,
     },
     /// Search repository
-    Search {
-        /// Repository name
-        repo: String,
-        /// File type filter
-        #[arg(long)]
-        file_type: Option<String>,
-        /// Organization/Lab filter
-        #[arg(long)]
-        org: Option<String>,
-        /// Tag filter
-        #[arg(long)]
-        tag: Vec<String>,
-        /// Created after date
-        #[arg(long)]
-        from: Option<String>,
-        /// Created before date
-        #[arg(long)]
-        to: Option<String>,
-        /// Query string
-        #[arg(short, long)]
-        q: Option<String>,
-        /// Limit results
-        #[arg(long)]
-        limit: Option<u32>,
-        /// Sort by field (path, size, creation_dt, file_type, org_lab)
-        #[arg(long)]
-        sort: Option<String>,
-        /// Fields to display (comma-separated)
-        #[arg(long)]
-        fields: Option<String>,
-        /// JSON output
-        #[arg(long)]
-        json: bool,
-    },
+    Search(SearchArgs),
     /// Repository operations
     Repo {
         #[command(subcommand)]
@@ -246,8 +231,14 @@ enum Commands {
         /// File path
         path: String,
     },
-    /// Commit changes
+    /// Commit staged changes
     Commit {
+        /// Repository name
+        #[arg(long)]
+        repo: Option<String>,
+        /// Branch or ref name (default: the profile's ref, then main)
+        #[arg(long)]
+        r#ref: Option<String>,
         /// Commit message
         #[arg(short, long)]
         message: String,
@@ -298,9 +289,15 @@ enum Commands {
         repo: Option<String>,
         /// Tag name
         name: Option<String>,
-        /// Tag message
+        /// Tag message; makes the tag annotated
         #[arg(long)]
         message: Option<String>,
+        /// Branch, tag or commit id to tag
+        #[arg(long, default_value = "main")]
+        target: String,
+        /// Move an existing tag (admins only)
+        #[arg(long)]
+        force: bool,
         /// Delete tag
         #[arg(long)]
         delete: bool,
@@ -316,11 +313,96 @@ enum Commands {
         #[arg(long)]
         commit: Option<String>,
     },
+    /// Browse repositories, trees, metadata and samples in a terminal UI (read-only)
+    Tui {
+        /// Repository to open (default: the profile's repo, otherwise the repository list)
+        repo: Option<String>,
+        /// Branch or ref to browse (default: the profile's ref, then main)
+        #[arg(long)]
+        r#ref: Option<String>,
+    },
     /// Generate shell completions
     Completions {
         /// Shell type
         shell: clap_complete::Shell,
     },
+    /// Read or change the config file
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
+    /// Log in with the OIDC device flow and keep the tokens in the OS keychain
+    Login {
+        /// OIDC issuer URL
+        #[arg(long, env = login::ISSUER_ENV)]
+        issuer: String,
+        /// OAuth client ID registered for the CLI
+        #[arg(long, default_value = login::DEFAULT_CLIENT_ID)]
+        client_id: String,
+        /// Scopes to request; offline_access is needed for refresh tokens
+        #[arg(long, default_value = login::DEFAULT_SCOPE)]
+        scope: String,
+    },
+    /// Remove the selected profile's login from the OS keychain
+    Logout,
+}
+
+#[derive(Args)]
+struct SearchArgs {
+    /// Repository name
+    repo: String,
+    /// File type filter
+    #[arg(long)]
+    file_type: Option<String>,
+    /// Organization/Lab filter
+    #[arg(long)]
+    org: Option<String>,
+    /// Tag filter
+    #[arg(long)]
+    tag: Vec<String>,
+    /// Created after date
+    #[arg(long)]
+    from: Option<String>,
+    /// Created before date
+    #[arg(long)]
+    to: Option<String>,
+    /// Query string
+    #[arg(short, long)]
+    q: Option<String>,
+    /// Limit results
+    #[arg(long)]
+    limit: Option<u32>,
+    /// Resume from the `next_cursor` of a previous search (newest first)
+    #[arg(long)]
+    cursor: Option<String>,
+    /// Sort by field (path, size, creation_dt, file_type, org_lab)
+    #[arg(long)]
+    sort: Option<String>,
+    /// Fields to display (comma-separated)
+    #[arg(long)]
+    fields: Option<String>,
+    /// JSON output
+    #[arg(long, conflicts_with = "csv")]
+    json: bool,
+    /// CSV output, one row per result with common metadata as columns
+    #[arg(long)]
+    csv: bool,
+}
+
+#[derive(Subcommand)]
+enum ConfigCommands {
+    /// Set a value in the selected profile
+    Set {
+        /// Key (api_url, token, repo, ref, output)
+        key: String,
+        /// Value to store
+        value: String,
+    },
+    /// Print a value from the selected profile
+    Get {
+        /// Key (api_url, token, repo, ref, output)
+        key: String,
+    },
 }
 
 #[derive(Subcommand)]
@@ -370,9 +452,10 @@ enum RepoFeatureCommands {
     Set {
         /// Repository name
         repo: String,
-        /// Feature key
+        /// Feature key (auto_rdf, sampling_enabled, thumbnails_enabled, parquet_conversion,
+        /// kms_key_id, upload_url_ttl, download_url_ttl, schema, schema_version)
         key: String,
-        /// Feature value
+        /// Feature value: true/false, a number of seconds, a string, a JSON list of patterns, or null to reset
         value: String,
     },
 }
@@ -387,7 +470,7 @@ enum RdfCommands {
         r#ref: String,
         /// Path to artifact
         path: String,
-        /// Output format (turtle or jsonld)
+        /// Output format (turtle, jsonld, ntriples or rdfxml)
         #[arg(long, default_value = "turtle")]
         format: String,
     },
@@ -412,11 +495,40 @@ async fn main() -> Result<()> {
             .init();
     }
 
-    let api_client = ApiClient::new(cli.api_url.clone())
-        .with_token(cli.token.unwrap_or_default());
+    // Profile from ~/.config/blacklake/config.toml; flags win over its values
+    let config_path = Config::default_path();
+    let config = match &config_path {
+        Some(path) => Config::load(path)?,
+        None => Config::default(),
+    };
+    let env_profile = std::env::var(config::PROFILE_ENV).ok();
+    let profile_name = config.profile_name(cli.profile.as_deref(), env_profile.as_deref());
+
+    let command = match cli.command {
+        Commands::Config { command } => return config_command(command, config, config_path, &profile_name),
+        Commands::Login { issuer, client_id, scope } => {
+            return login_command(&issuer, &client_id, &scope, &profile_name).await
+        },
+        Commands::Logout => return logout_command(&profile_name),
+        command => command,
+    };
+
+    // An explicit --token wins; otherwise the profile's login, refreshed if
+    // it has expired, is used ahead of any token in the config file
+    let token = match cli.token {
+        Some(token) => Some(token),
+        None => login_token(&profile_name).await?,
+    };
+    let settings = Settings::resolve(
+        config.profile(&profile_name)?,
+        Flags { api_url: cli.api_url, token },
+    );
+    let api_client = ApiClient::new(settings.api_url.clone())
+        .with_token(settings.token.clone().unwrap_or_default());
+    let default_repo = |repo: Option<String>| repo.or_else(|| settings.repo.clone());
 
-    match cli.command {
-        Commands::Put { repo, r#ref, local_file, path, r#type, emit_rdf, open_editor, meta, meta_key, template, dry_run, non_interactive } => {
+    match command {
+        Commands::Put { repo, r#ref, local_file, path, r#type, emit_rdf, open_editor, meta, meta_key, template, dry_run, non_interactive, concurrency, signature } => {
             put::put_command(put::PutArgs {
                 repo,
                 r#ref,
@@ -430,6 +542,8 @@ async fn main() -> Result<()> {
                 template,
                 dry_run,
                 non_interactive,
+                concurrency,
+                signature,
             }, &api_client).await?;
         },
         Commands::Meta { command } => {
@@ -450,8 +564,14 @@ async fn main() -> Result<()> {
         Commands::Get { repo, r#ref, path, out } => {
             get_command(repo, r#ref, path, out, &api_client).await?;
         },
-        Commands::Search { repo, file_type, org, tag, from, to, q, limit, sort, fields, json } => {
-            search_command(repo, file_type, org, tag, from, to, q, limit, sort, fields, json, &api_client).await?;
+        Commands::Search(mut args) => {
+            (args.json, args.csv) = match settings.output.as_deref() {
+                _ if args.json || args.csv => (args.json, args.csv),
+                Some("json") => (true, false),
+                Some("csv") => (false, true),
+                _ => (false, false),
+            };
+            search_command(args, &api_client).await?;
         },
         Commands::Repo { command } => {
             match command {
@@ -527,41 +647,118 @@ async fn main() -> Result<()> {
             cp_command(src, dst, dry_run, &api_client).await?;
         },
         Commands::Ls { repo, long, all } => {
-            ls_command(repo, long, all, &api_client).await?;
+            ls_command(default_repo(repo), long, all, &api_client).await?;
         },
         Commands::Show { repo, path } => {
-            show_command(repo, path, &api_client).await?;
+            show_command(default_repo(repo), path, &api_client).await?;
         },
-        Commands::Commit { message, set, dry_run } => {
-            commit_command(message, set, dry_run, &api_client).await?;
+        Commands::Commit { repo, r#ref, message, set, dry_run } => {
+            let r#ref = r#ref.or_else(|| settings.r#ref.clone()).unwrap_or_else(|| "main".to_string());
+            commit_command(default_repo(repo), r#ref, message, set, dry_run, &api_client).await?;
         },
         Commands::Log { repo, count, oneline } => {
-            log_command(repo, count, oneline, &api_client).await?;
+            log_command(default_repo(repo), count, oneline, &api_client).await?;
         },
         Commands::Status { repo } => {
-            status_command(repo, &api_client).await?;
+            status_command(default_repo(repo), &api_client).await?;
         },
         Commands::Info { repo } => {
-            info_command(repo, &api_client).await?;
+            info_command(default_repo(repo), &api_client).await?;
         },
         Commands::Branch { repo, name, create, delete } => {
-            branch_command(repo, name, create, delete, &api_client).await?;
+            branch_command(default_repo(repo), name, create, delete, &api_client).await?;
         },
-        Commands::Tag { repo, name, message, delete, list } => {
-            tag_command(repo, name, message, delete, list, &api_client).await?;
+        Commands::Tag { repo, name, message, target, force, delete, list } => {
+            tag_command(default_repo(repo), name, message, target, force, delete, list, &api_client).await?;
         },
         Commands::Diff { repo, commit } => {
-            diff_command(repo, commit, &api_client).await?;
+            diff_command(default_repo(repo), commit, &api_client).await?;
+        },
+        Commands::Tui { repo, r#ref } => {
+            let r#ref = r#ref.or_else(|| settings.r#ref.clone()).unwrap_or_else(|| "main".to_string());
+            tui::run(&api_client, r#ref, default_repo(repo)).await?;
         },
         Commands::Completions { shell } => {
             let mut cmd = Cli::command();
             clap_complete::generate(shell, &mut cmd, "blacklake", &mut std::io::stdout());
         },
+        Commands::Config { .. } | Commands::Login { .. } | Commands::Logout => {
+            unreachable!("handled before the API client is built")
+        },
     }
 
     Ok(())
 }
 
+/// `blacklake config set/get` against profile `profile_name`
+fn config_command(command: ConfigCommands, mut config: Config, path: Option<PathBuf>, profile_name: &str) -> Result<()> {
+    let path = path.ok_or("Cannot locate the config directory; set XDG_CONFIG_HOME")?;
+    match command {
+        ConfigCommands::Set { key, value } => {
+            config.profiles.entry(profile_name.to_string()).or_default().set(&key, &value)?;
+            config.save(&path)?;
+            println!("✅ Set {} for profile {} in {}", key, profile_name, path.display());
+        },
+        ConfigCommands::Get { key } => {
+            match config.profile(profile_name)?.get(&key)? {
+                Some(value) => println!("{}", value),
+                None => return Err(format!("{} is not set for profile {}", key, profile_name).into()),
+            }
+        },
+    }
+    Ok(())
+}
+
+/// `blacklake login`: run the device flow and store the tokens for `profile_name`
+async fn login_command(issuer: &str, client_id: &str, scope: &str, profile_name: &str) -> Result<()> {
+    let client = Client::new();
+    let endpoints = login::discover(&client, issuer).await?;
+    let stored = login::device_login(&client, &endpoints, client_id, scope, |authorization| {
+        println!("🔑 To log in, open {}", authorization.verification_uri);
+        println!("   and enter the code: {}", authorization.user_code);
+        if let Some(complete) = &authorization.verification_uri_complete {
+            println!("   (or open {})", complete);
+        }
+        println!("⏳ Waiting for approval...");
+    })
+    .await?;
+
+    login::Keychain::for_profile(profile_name)?.save(&stored)?;
+    println!("✅ Logged in for profile {}", profile_name);
+    Ok(())
+}
+
+/// `blacklake logout`: forget `profile_name`'s stored tokens
+fn logout_command(profile_name: &str) -> Result<()> {
+    if login::Keychain::for_profile(profile_name)?.delete()? {
+        println!("✅ Logged out of profile {}", profile_name);
+    } else {
+        println!("Profile {} was not logged in", profile_name);
+    }
+    Ok(())
+}
+
+/// The access token from `profile_name`'s login, if any. A keychain that
+/// cannot be reached counts as no login, so config tokens still work on
+/// machines without a secret service.
+async fn login_token(profile_name: &str) -> Result<Option<String>> {
+    let keychain = match login::Keychain::for_profile(profile_name) {
+        Ok(keychain) => keychain,
+        Err(e) => {
+            log::debug!("Skipping stored login: {}", e);
+            return Ok(None);
+        }
+    };
+    match keychain.load() {
+        Ok(Some(stored)) => Ok(Some(login::access_token(&Client::new(), &keychain, stored).await?)),
+        Ok(None) => Ok(None),
+        Err(e) => {
+            log::debug!("Skipping stored login: {}", e);
+            Ok(None)
+        }
+    }
+}
+
 async fn get_command(repo: String, r#ref: String, path: String, out: Option<String>, api_client: &ApiClient) -> Result<()> {
     println!("📥 Downloading {}/{}", repo, path);
     
@@ -583,20 +780,8 @@ async fn get_command(repo: String, r#ref: String, path: String, out: Option<Stri
     Ok(())
 }
 
-async fn search_command(
-    repo: String,
-    file_type: Option<String>,
-    org: Option<String>,
-    tag: Vec<String>,
-    from: Option<String>,
-    created_before: Option<String>,
-    q: Option<String>,
-    limit: Option<u32>,
-    sort: Option<String>,
-    fields: Option<String>,
-    json: bool,
-    api_client: &ApiClient,
-) -> Result<()> {
+async fn search_command(args: SearchArgs, api_client: &ApiClient) -> Result<()> {
+    let SearchArgs { repo, file_type, org, tag, from, to: created_before, q, limit, cursor, sort, fields, json, csv } = args;
     let mut filters = std::collections::HashMap::new();
     if let Some(query) = q {
         if !query.is_empty() {
@@ -626,8 +811,19 @@ async fn search_command(
         sort: None,
         limit,
         offset: None,
+        // Always page by keyset so results come back with a resumable cursor
+        cursor: Some(cursor.unwrap_or_default()),
     };
     
+    if csv {
+        let (body, next_cursor) = api_client.search_export(&repo, &search_request, "csv").await?;
+        print!("{}", body);
+        if let Some(next_cursor) = next_cursor {
+            eprintln!("More results: --cursor {}", next_cursor);
+        }
+        return Ok(());
+    }
+    
     let response = api_client.search(&repo, &search_request).await?;
     
     if json {
@@ -644,6 +840,7 @@ async fn search_command(
         };
         
         // Sort entries if requested
+        let next_cursor = response.next_cursor;
         let mut entries = response.entries;
         if let Some(sort_field) = sort {
             entries.sort_by(|a, b| {
@@ -752,6 +949,10 @@ async fn search_command(
             }
             println!();
         }
+
+        if let Some(next_cursor) = next_cursor {
+            println!("More results: --cursor {}", next_cursor);
+        }
     }
     
     Ok(())
@@ -799,6 +1000,10 @@ async fn list_repos_command(api_client: &ApiClient) -> Result<()> {
 }
 
 async fn set_repo_feature_command(repo: String, key: String, value: String, api_client: &ApiClient) -> Result<()> {
+    // JSON literals keep their type (`true`, `3600`, `null` to reset); anything else is a string
+    let value = serde_json::from_str(&value).unwrap_or(serde_json::Value::String(value));
+    RepoFeature::validate(&key, &value)?;
+
     println!("⚙️ Setting feature {}={} for repository {}", key, value, repo);
     
     let response = api_client.post_request(&format!("{}/v1/repos/{}/features", api_client.base_url(), repo))
@@ -810,7 +1015,11 @@ async fn set_repo_feature_command(repo: String, key: String, value: String, api_
         println!("✅ Feature set successfully");
     } else {
         let error_text = response.text().await?;
-        return Err(format!("Failed to set feature: {}", error_text).into());
+        let message = serde_json::from_str::<serde_json::Value>(&error_text)
+            .ok()
+            .and_then(|body| body.get("error")?.as_str().map(|e| e.to_string()))
+            .unwrap_or(error_text);
+        return Err(format!("Failed to set feature: {}", message).into());
     }
     
     Ok(())
@@ -829,38 +1038,53 @@ async fn get_rdf_command(repo: String, r#ref: String, path: String, format: Stri
 async fn add_command(path: String, set: Vec<(String, String)>, dry_run: bool, api_client: &ApiClient) -> Result<()> {
     println!("📁 Adding file: {}", path);
     
+    let cwd = std::env::current_dir()?;
+    let mut staging = Staging::discover(&cwd)?;
+    let logical = staging.logical_path(&cwd, &path)?;
+
     if dry_run {
-        println!("🔍 Dry run - would add: {}", path);
+        println!("🔍 Dry run - would add: {}", logical);
         return Ok(());
     }
     
-    // TODO: Implement actual add functionality
-    println!("✅ File added to staging: {}", path);
+    let staged = staging.add(&logical, &set)?;
+    println!("✅ File added to staging: {} ({})", staged.path, staged.sha256.as_deref().unwrap_or_default());
+    staging.save()?;
     Ok(())
 }
 
 async fn rm_command(path: String, dry_run: bool, api_client: &ApiClient) -> Result<()> {
     println!("🗑️ Removing file: {}", path);
     
+    let cwd = std::env::current_dir()?;
+    let mut staging = Staging::discover(&cwd)?;
+    let logical = staging.logical_path(&cwd, &path)?;
+
     if dry_run {
-        println!("🔍 Dry run - would remove: {}", path);
+        println!("🔍 Dry run - would remove: {}", logical);
         return Ok(());
     }
     
-    // TODO: Implement actual remove functionality
-    println!("✅ File removed: {}", path);
+    staging.remove(&logical)?;
+    staging.save()?;
+    println!("✅ File removed: {}", logical);
     Ok(())
 }
 
 async fn mv_command(src: String, dst: String, dry_run: bool, api_client: &ApiClient) -> Result<()> {
     println!("📦 Moving file: {} -> {}", src, dst);
     
+    let cwd = std::env::current_dir()?;
+    let mut staging = Staging::discover(&cwd)?;
+    let (src, dst) = (staging.logical_path(&cwd, &src)?, staging.logical_path(&cwd, &dst)?);
+
     if dry_run {
         println!("🔍 Dry run - would move: {} -> {}", src, dst);
         return Ok(());
     }
     
-    // TODO: Implement actual move functionality
+    staging.rename(&src, &dst)?;
+    staging.save()?;
     println!("✅ File moved: {} -> {}", src, dst);
     Ok(())
 }
@@ -868,12 +1092,17 @@ async fn mv_command(src: String, dst: String, dry_run: bool, api_client: &ApiCli
 async fn cp_command(src: String, dst: String, dry_run: bool, api_client: &ApiClient) -> Result<()> {
     println!("📋 Copying file: {} -> {}", src, dst);
     
+    let cwd = std::env::current_dir()?;
+    let mut staging = Staging::discover(&cwd)?;
+    let (src, dst) = (staging.logical_path(&cwd, &src)?, staging.logical_path(&cwd, &dst)?);
+
     if dry_run {
         println!("🔍 Dry run - would copy: {} -> {}", src, dst);
         return Ok(());
     }
     
-    // TODO: Implement actual copy functionality
+    staging.copy(&src, &dst)?;
+    staging.save()?;
     println!("✅ File copied: {} -> {}", src, dst);
     Ok(())
 }
@@ -906,16 +1135,21 @@ async fn show_command(repo: Option<String>, path: String, api_client: &ApiClient
     Ok(())
 }
 
-async fn commit_command(message: String, set: Vec<(String, String)>, dry_run: bool, api_client: &ApiClient) -> Result<()> {
+async fn commit_command(repo: Option<String>, r#ref: String, message: String, set: Vec<(String, String)>, dry_run: bool, api_client: &ApiClient) -> Result<()> {
+    let repo_name = repo.unwrap_or_else(|| "default".to_string());
     println!("💾 Committing changes: {}", message);
     
+    let mut staging = Staging::discover(&std::env::current_dir()?)?;
+
     if dry_run {
-        println!("🔍 Dry run - would commit: {}", message);
-        return Ok(());
+        println!("🔍 Dry run - would commit to {}/{}: {}", repo_name, r#ref, message);
+        let request = staging.commit_request(&r#ref, &message, &set)?;
+        let plan = api_client.plan_commit(&repo_name, &request, false).await?;
+        return api::report_plan(&plan).map_err(Into::into);
     }
     
-    // TODO: Implement actual commit functionality
-    println!("✅ Changes committed: {}", message);
+    let response = staging.commit(api_client, &repo_name, &r#ref, &message, &set).await?;
+    println!("✅ Changes committed: {} ({})", message, response.commit_id.0);
     Ok(())
 }
 
@@ -944,15 +1178,24 @@ async fn status_command(repo: Option<String>, api_client: &ApiClient) -> Result<
     let repo_name = repo.unwrap_or_else(|| "default".to_string());
     println!("📊 Repository status: {}", repo_name);
     
-    // TODO: Implement actual status functionality
-    println!("On branch main");
+    let staging = Staging::discover(&std::env::current_dir()?)?;
+    let changes = &staging.index().changes;
+    if changes.is_empty() {
+        println!("Nothing to commit, staging area is empty");
+        return Ok(());
+    }
+
     println!("Changes to be committed:");
-    println!("  (use \"blacklake-cli reset HEAD <file>\" to unstage)");
-    println!("        new file:   file1.txt");
-    println!("");
-    println!("Changes not staged for commit:");
-    println!("  (use \"blacklake-cli add <file>\" to update what will be committed)");
-    println!("        modified:   file2.txt");
+    println!("  (use \"blacklake commit -m <message>\" to commit)");
+    for change in changes.values() {
+        let label = match change.op {
+            blacklake_core::ChangeOp::Add => "new file:",
+            blacklake_core::ChangeOp::Modify => "modified:",
+            blacklake_core::ChangeOp::Delete => "deleted:",
+            blacklake_core::ChangeOp::Meta => "metadata:",
+        };
+        println!("        {:<12}{}", label, change.path);
+    }
     
     Ok(())
 }
@@ -981,44 +1224,68 @@ async fn branch_command(repo: Option<String>, name: Option<String>, create: bool
         // TODO: Implement actual branch creation
         println!("✅ Branch created: {}", branch_name);
     } else if delete {
-        let branch_name = name.unwrap_or_else(|| "feature-branch".to_string());
+        let branch_name = name.ok_or_else(|| anyhow::anyhow!("Branch name is required for --delete"))?;
         println!("🗑️ Deleting branch: {} in repository: {}", branch_name, repo_name);
-        // TODO: Implement actual branch deletion
+        api_client.delete_ref(&repo_name, &branch_name).await?;
         println!("✅ Branch deleted: {}", branch_name);
     } else {
         println!("🌿 Branches in repository: {}", repo_name);
-        // TODO: Implement actual branch listing
-        println!("  main");
-        println!("  feature-branch");
+        for branch in api_client.list_refs(&repo_name, Some("branch")).await? {
+            println!("  {} ({})", branch.name, short_id(&branch.commit_id.0));
+        }
     }
     
     Ok(())
 }
 
-async fn tag_command(repo: Option<String>, name: Option<String>, message: Option<String>, delete: bool, list: bool, api_client: &ApiClient) -> Result<()> {
+#[allow(clippy::too_many_arguments)]
+async fn tag_command(
+    repo: Option<String>,
+    name: Option<String>,
+    message: Option<String>,
+    target: String,
+    force: bool,
+    delete: bool,
+    list: bool,
+    api_client: &ApiClient,
+) -> Result<()> {
     let repo_name = repo.unwrap_or_else(|| "default".to_string());
     
     if delete {
-        let tag_name = name.unwrap_or_else(|| "v1.0.0".to_string());
+        let tag_name = name.ok_or_else(|| anyhow::anyhow!("Tag name is required for --delete"))?;
         println!("🏷️ Deleting tag: {} in repository: {}", tag_name, repo_name);
-        // TODO: Implement actual tag deletion
+        api_client.delete_ref(&repo_name, &tag_name).await?;
         println!("✅ Tag deleted: {}", tag_name);
     } else if list {
         println!("🏷️ Tags in repository: {}", repo_name);
-        // TODO: Implement actual tag listing
-        println!("  v1.0.0");
-        println!("  v1.1.0");
+        for tag in api_client.list_refs(&repo_name, Some("tag")).await? {
+            match &tag.annotation {
+                Some(annotation) => println!(
+                    "  {} ({}) {} - {}",
+                    tag.name,
+                    short_id(&tag.commit_id.0),
+                    annotation.tagger,
+                    annotation.message
+                ),
+                None => println!("  {} ({})", tag.name, short_id(&tag.commit_id.0)),
+            }
+        }
     } else {
-        let tag_name = name.unwrap_or_else(|| "v1.0.0".to_string());
-        let tag_message = message.unwrap_or_else(|| "Release version 1.0.0".to_string());
-        println!("🏷️ Creating tag: {} in repository: {}", tag_name, repo_name);
-        // TODO: Implement actual tag creation
-        println!("✅ Tag created: {} - {}", tag_name, tag_message);
+        let tag_name = name.ok_or_else(|| anyhow::anyhow!("Tag name is required"))?;
+        println!("🏷️ Creating tag: {} at {} in repository: {}", tag_name, target, repo_name);
+        let request = blacklake_core::CreateTagRequest { name: tag_name, target, message, force };
+        let tag = api_client.create_tag(&repo_name, &request).await?;
+        println!("✅ Tag created: {} ({})", tag.name, short_id(&tag.commit_id.0));
     }
     
     Ok(())
 }
 
+/// First eight characters of a commit id, for compact listings
+fn short_id(id: &Uuid) -> String {
+    id.simple().to_string()[..8].to_string()
+}
+
 async fn diff_command(repo: Option<String>, commit: Option<String>, api_client: &ApiClient) -> Result<()> {
     let repo_name = repo.unwrap_or_else(|| "default".to_string());
     println!("🔍 Showing differences for repository: {}", repo_name);
@@ -0,0 +1,431 @@
+//! Resumable, parallel multipart uploads for `put`.
+//!
+//! Progress is checkpointed to `<file>.part-state` after every part, so an
+//! interrupted upload resumes by skipping the parts S3 already acknowledged.
+
+use anyhow::{anyhow, Result};
+use blacklake_core::{UploadCompleteRequest, UploadInitResponse, UploadPart};
+use chrono::{DateTime, Utc};
+use crate::api::ApiClient;
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+/// Suffix of the checkpoint file written next to the file being uploaded
+pub const PART_STATE_SUFFIX: &str = ".part-state";
+/// Parts uploaded at once unless `--concurrency` says otherwise
+pub const DEFAULT_UPLOAD_CONCURRENCY: usize = 4;
+
+/// Presigned URL for one part, as recorded in the checkpoint
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PartUrl {
+    pub part_number: i32,
+    pub upload_url: String,
+}
+
+/// Checkpoint of an in-flight multipart upload
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PartState {
+    pub repo: String,
+    pub path: String,
+    pub file_size: u64,
+    /// Object sha256 returned by upload-init, used in the commit
+    pub sha256: String,
+    pub s3_key: String,
+    pub upload_id: String,
+    /// Key the parts are assembled under until the server verifies them
+    #[serde(default)]
+    pub staging_key: String,
+    pub part_size: u64,
+    /// When the presigned part URLs stop working
+    pub expires_at: DateTime<Utc>,
+    pub parts: Vec<PartUrl>,
+    /// ETags of parts S3 has acknowledged, by part number
+    #[serde(default)]
+    pub completed: BTreeMap<i32, String>,
+}
+
+impl PartState {
+    /// Checkpoint path for `local_file`
+    pub fn state_path(local_file: &Path) -> PathBuf {
+        let mut name = local_file.as_os_str().to_os_string();
+        name.push(PART_STATE_SUFFIX);
+        PathBuf::from(name)
+    }
+
+    /// Start a checkpoint from an upload-init response; `None` for single-PUT uploads
+    pub fn new(repo: &str, path: &str, file_size: u64, init: &UploadInitResponse) -> Option<Self> {
+        let plan = init.multipart.as_ref()?;
+        Some(Self {
+            repo: repo.to_string(),
+            path: path.to_string(),
+            file_size,
+            sha256: init.sha256.clone(),
+            s3_key: init.s3_key.clone(),
+            upload_id: plan.upload_id.clone(),
+            staging_key: plan.staging_key.clone(),
+            part_size: plan.part_size,
+            expires_at: init.expires_at,
+            parts: plan
+                .parts
+                .iter()
+                .map(|p| PartUrl { part_number: p.part_number, upload_url: p.upload_url.clone() })
+                .collect(),
+            completed: BTreeMap::new(),
+        })
+    }
+
+    /// Load a checkpoint, if one exists
+    pub fn load(state_path: &Path) -> Result<Option<Self>> {
+        if !state_path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(state_path)?;
+        let state = serde_json::from_str(&content)
+            .map_err(|e| anyhow!("Corrupt upload state {}: {}", state_path.display(), e))?;
+        Ok(Some(state))
+    }
+
+    pub fn save(&self, state_path: &Path) -> Result<()> {
+        fs::write(state_path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Whether this checkpoint belongs to the same upload and its URLs still work
+    pub fn resumable(&self, repo: &str, path: &str, file_size: u64, now: DateTime<Utc>) -> bool {
+        self.repo == repo && self.path == path && self.file_size == file_size && self.expires_at > now
+    }
+
+    /// Parts S3 has not acknowledged yet
+    pub fn pending_parts(&self) -> Vec<PartUrl> {
+        self.parts
+            .iter()
+            .filter(|p| !self.completed.contains_key(&p.part_number))
+            .cloned()
+            .collect()
+    }
+
+    /// Acknowledged parts in part-number order, for upload-complete
+    pub fn completed_parts(&self) -> Vec<UploadPart> {
+        self.completed
+            .iter()
+            .map(|(part_number, etag)| UploadPart { part_number: *part_number, etag: etag.clone() })
+            .collect()
+    }
+
+    /// Byte range `(offset, length)` of a part within the file
+    fn part_range(&self, part_number: i32) -> (u64, u64) {
+        let offset = (part_number as u64 - 1) * self.part_size;
+        (offset, self.part_size.min(self.file_size.saturating_sub(offset)))
+    }
+}
+
+/// Upload the pending parts of `state` with at most `concurrency` in flight.
+///
+/// The checkpoint at `state_path` is rewritten after every acknowledged part;
+/// on error it holds everything that finished, ready for a retry.
+pub async fn upload_parts(
+    api_client: &ApiClient,
+    local_file: &Path,
+    state: PartState,
+    state_path: &Path,
+    concurrency: usize,
+) -> Result<PartState> {
+    let total = state.file_size;
+    let done: u64 = state.completed.keys().map(|part| state.part_range(*part).1).sum();
+    let pb = ProgressBar::new(total);
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})")
+            .unwrap()
+            .progress_chars("#>-"),
+    );
+    pb.set_position(done);
+
+    let pending = state.pending_parts();
+    let state = Arc::new(Mutex::new(state));
+    let permits = Arc::new(Semaphore::new(concurrency.max(1)));
+    let mut workers = JoinSet::new();
+
+    for part in pending {
+        let api_client = api_client.clone();
+        let local_file = local_file.to_path_buf();
+        let state_path = state_path.to_path_buf();
+        let state = state.clone();
+        let permits = permits.clone();
+        let pb = pb.clone();
+
+        workers.spawn(async move {
+            let _permit = permits.acquire_owned().await?;
+            let (offset, length) = state.lock().unwrap().part_range(part.part_number);
+            let body = read_range(&local_file, offset, length)?;
+            let etag = api_client.upload_part(&part.upload_url, body).await?;
+
+            let mut state = state.lock().unwrap();
+            state.completed.insert(part.part_number, etag);
+            state.save(&state_path)?;
+            pb.inc(length);
+            Ok::<_, anyhow::Error>(())
+        });
+    }
+
+    while let Some(result) = workers.join_next().await {
+        if let Err(e) = result.map_err(anyhow::Error::from).and_then(|r| r) {
+            workers.abort_all();
+            pb.abandon_with_message("Upload interrupted");
+            return Err(anyhow!("Multipart upload interrupted, rerun to resume: {}", e));
+        }
+    }
+
+    pb.finish_with_message("Upload complete");
+    let state = state.lock().unwrap().clone();
+    Ok(state)
+}
+
+/// Upload any remaining parts, then ask the API to assemble the object
+pub async fn upload_and_complete(
+    api_client: &ApiClient,
+    local_file: &Path,
+    state: PartState,
+    concurrency: usize,
+) -> Result<()> {
+    let state_path = PartState::state_path(local_file);
+    state.save(&state_path)?;
+
+    let state = upload_parts(api_client, local_file, state, &state_path, concurrency).await?;
+    api_client
+        .upload_complete(&state.repo, &UploadCompleteRequest {
+            sha256: state.sha256.clone(),
+            upload_id: state.upload_id.clone(),
+            staging_key: state.staging_key.clone(),
+            parts: Some(state.completed_parts()),
+        })
+        .await?;
+
+    fs::remove_file(&state_path)?;
+    Ok(())
+}
+
+/// Check that the committed object has exactly the bytes of `local_file`
+pub async fn verify_upload(api_client: &ApiClient, repo: &str, r#ref: &str, path: &str, local_file: &Path) -> Result<()> {
+    let local = crate::staging::hash_file(local_file)?;
+    let remote = api_client.blob_sha256(repo, r#ref, path).await?;
+    if local != remote {
+        return Err(anyhow!(
+            "Uploaded object for {} does not match local file (local sha256 {}, remote {})",
+            path, local, remote
+        ));
+    }
+    Ok(())
+}
+
+fn read_range(path: &Path, offset: u64, length: u64) -> Result<Vec<u8>> {
+    let mut file = fs::File::open(path)?;
+    file.seek(SeekFrom::Start(offset))?;
+    let mut buffer = vec![0u8; length as usize];
+    file.read_exact(&mut buffer)?;
+    Ok(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::{Path as UrlPath, State}, http::{HeaderMap, StatusCode}, routing::put, Router};
+    use tempfile::TempDir;
+
+    const PART_SIZE: u64 = 1024;
+
+    #[derive(Default)]
+    struct MockS3 {
+        /// Part bodies by part number
+        parts: Mutex<BTreeMap<i32, Vec<u8>>>,
+        /// PUTs accepted before the server starts failing, if limited
+        accept: Mutex<Option<usize>>,
+        /// Part numbers of every accepted PUT, in arrival order
+        puts: Mutex<Vec<i32>>,
+    }
+
+    /// Serve presigned part PUTs, answering each with an ETag
+    async fn mock_s3() -> (String, Arc<MockS3>) {
+        let s3 = Arc::new(MockS3::default());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+
+        let app = Router::new()
+            .route(
+                "/part/:number",
+                put(|State(s3): State<Arc<MockS3>>, UrlPath(number): UrlPath<i32>, body: axum::body::Bytes| async move {
+                    let mut accept = s3.accept.lock().unwrap();
+                    if let Some(remaining) = accept.as_mut() {
+                        if *remaining == 0 {
+                            return Err(StatusCode::SERVICE_UNAVAILABLE);
+                        }
+                        *remaining -= 1;
+                    }
+                    s3.puts.lock().unwrap().push(number);
+                    s3.parts.lock().unwrap().insert(number, body.to_vec());
+
+                    let mut headers = HeaderMap::new();
+                    headers.insert("ETag", format!("\"etag-{}\"", number).parse().unwrap());
+                    Ok(headers)
+                }),
+            )
+            .with_state(s3.clone());
+
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (base_url, s3)
+    }
+
+    fn test_file(size: usize) -> (TempDir, PathBuf, Vec<u8>) {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("large.bin");
+        let content: Vec<u8> = (0..size).map(|i| (i % 251) as u8).collect();
+        fs::write(&path, &content).unwrap();
+        (dir, path, content)
+    }
+
+    fn test_state(base_url: &str, file_size: u64) -> PartState {
+        let part_count = file_size.div_ceil(PART_SIZE) as i32;
+        PartState {
+            repo: "mylab".to_string(),
+            path: "data/large.bin".to_string(),
+            file_size,
+            sha256: "abc".to_string(),
+            s3_key: "sha256/ab/c/abc".to_string(),
+            upload_id: "upload-1".to_string(),
+            staging_key: "uploads/abc/01".to_string(),
+            part_size: PART_SIZE,
+            expires_at: Utc::now() + chrono::Duration::hours(1),
+            parts: (1..=part_count)
+                .map(|n| PartUrl { part_number: n, upload_url: format!("{}/part/{}", base_url, n) })
+                .collect(),
+            completed: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_part_ranges_cover_file() {
+        let state = test_state("http://s3.test", 2 * PART_SIZE + 10);
+
+        assert_eq!(state.part_range(1), (0, PART_SIZE));
+        assert_eq!(state.part_range(3), (2 * PART_SIZE, 10));
+        assert_eq!(PartState::state_path(Path::new("/tmp/a.bin")), PathBuf::from("/tmp/a.bin.part-state"));
+    }
+
+    #[test]
+    fn test_resumable_requires_same_upload_and_live_urls() {
+        let state = test_state("http://s3.test", PART_SIZE);
+        let now = Utc::now();
+
+        assert!(state.resumable("mylab", "data/large.bin", PART_SIZE, now));
+        assert!(!state.resumable("mylab", "data/large.bin", PART_SIZE + 1, now));
+        assert!(!state.resumable("mylab", "other.bin", PART_SIZE, now));
+        assert!(!state.resumable("mylab", "data/large.bin", PART_SIZE, now + chrono::Duration::hours(2)));
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_upload_resumes_skipping_completed_parts() {
+        let (_dir, local_file, content) = test_file(5 * PART_SIZE as usize + 17);
+        let (base_url, s3) = mock_s3().await;
+        let api_client = ApiClient::new(base_url.clone());
+        let state_path = PartState::state_path(&local_file);
+        let state = test_state(&base_url, content.len() as u64);
+
+        // The connection "dies" after three parts
+        *s3.accept.lock().unwrap() = Some(3);
+        let err = upload_parts(&api_client, &local_file, state, &state_path, 1).await.unwrap_err();
+        assert!(err.to_string().contains("rerun to resume"));
+
+        let checkpoint = PartState::load(&state_path).unwrap().unwrap();
+        assert_eq!(checkpoint.completed.len(), 3);
+        assert_eq!(checkpoint.pending_parts().len(), 3);
+
+        let checkpoint_parts: Vec<i32> = checkpoint.completed.keys().copied().collect();
+        *s3.accept.lock().unwrap() = None;
+        let finished = upload_parts(&api_client, &local_file, checkpoint, &state_path, 4).await.unwrap();
+
+        // Parts acknowledged before the interruption were not sent again
+        let puts = s3.puts.lock().unwrap().clone();
+        for part in checkpoint_parts {
+            assert_eq!(puts.iter().filter(|p| **p == part).count(), 1);
+        }
+        assert_eq!(finished.completed_parts().len(), 6);
+        assert_eq!(finished.completed_parts()[0].etag, "\"etag-1\"");
+
+        let assembled: Vec<u8> = s3.parts.lock().unwrap().values().flatten().copied().collect();
+        assert_eq!(assembled, content);
+    }
+
+    /// Kill and resume an upload against a real MinIO, then compare the assembled object.
+    ///
+    /// Uses the same `S3_*` variables as the API (`S3_ENDPOINT=http://localhost:9000`,
+    /// `S3_FORCE_PATH_STYLE=true`, bucket and keys); run with `--features minio-tests`.
+    #[cfg(feature = "minio-tests")]
+    #[tokio::test]
+    async fn test_resume_against_minio_is_byte_identical() {
+        let storage = blacklake_storage::StorageClient::from_env().await.unwrap();
+        let (_dir, local_file, content) = test_file(12 * 1024 * 1024 + 5);
+        let part_size = blacklake_storage::plan_part_size(content.len() as u64, 5 * 1024 * 1024);
+        let part_count = blacklake_storage::part_count(content.len() as u64, part_size) as i32;
+
+        let key = format!("test/multipart-resume-{}", uuid::Uuid::new_v4());
+        let upload_id = storage.create_multipart_upload(&key, "application/octet-stream", None).await.unwrap();
+        let mut parts = Vec::new();
+        for part_number in 1..=part_count {
+            let url = storage
+                .presign_upload_part(&key, &upload_id, part_number, std::time::Duration::from_secs(3600))
+                .await
+                .unwrap();
+            parts.push(PartUrl { part_number, upload_url: url.to_string() });
+        }
+
+        let state = PartState {
+            repo: "mylab".to_string(),
+            path: "large.bin".to_string(),
+            file_size: content.len() as u64,
+            sha256: String::new(),
+            s3_key: key.clone(),
+            upload_id: upload_id.clone(),
+            staging_key: key.clone(),
+            part_size,
+            expires_at: Utc::now() + chrono::Duration::hours(1),
+            parts,
+            completed: BTreeMap::new(),
+        };
+        let api_client = ApiClient::new("http://unused".to_string());
+        let state_path = PartState::state_path(&local_file);
+
+        // Kill the upload as soon as the first part has been checkpointed
+        let upload = tokio::spawn({
+            let (api_client, local_file, state_path) = (api_client.clone(), local_file.clone(), state_path.clone());
+            async move { upload_parts(&api_client, &local_file, state, &state_path, 1).await }
+        });
+        while PartState::load(&state_path).ok().flatten().is_none_or(|s| s.completed.is_empty()) {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        upload.abort();
+        let _ = upload.await;
+
+        let checkpoint = PartState::load(&state_path).unwrap().unwrap();
+        assert!(!checkpoint.completed.is_empty());
+        let finished = upload_parts(&api_client, &local_file, checkpoint, &state_path, 2).await.unwrap();
+
+        let uploaded: Vec<_> = finished
+            .completed_parts()
+            .into_iter()
+            .map(|p| blacklake_storage::UploadedPart { part_number: p.part_number, etag: p.etag })
+            .collect();
+        storage.complete_multipart_upload(&key, &upload_id, &uploaded).await.unwrap();
+
+        let body = storage.get_object(&key).await.unwrap().unwrap();
+        let assembled = body.collect().await.unwrap().into_bytes();
+        assert_eq!(&assembled[..], &content[..]);
+        storage.delete_object(&key, None).await.unwrap();
+    }
+}
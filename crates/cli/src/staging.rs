@@ -0,0 +1,502 @@
+//! Local staging index, modeled on git's staging area.
+//!
+//! `add`, `rm`, `mv` and `cp` record pending changes in `.blacklake/index`;
+//! `commit` uploads the staged files and posts them as one `CommitRequest`.
+
+use anyhow::{anyhow, Result};
+use blacklake_core::{Change, ChangeOp, CommitRequest, CommitResponse};
+use crate::api::{ApiClient, UploadInitRequest};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+
+/// Directory holding the staging index, at the root of the working tree
+pub const STAGING_DIR: &str = ".blacklake";
+/// Index file inside `STAGING_DIR`
+pub const INDEX_FILE: &str = "index";
+
+/// A change waiting to be committed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StagedChange {
+    pub path: String,
+    pub op: ChangeOp,
+    pub sha256: Option<String>,
+    /// Metadata to send with the change
+    #[serde(default)]
+    pub meta: Value,
+}
+
+/// Contents of `.blacklake/index`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StagingIndex {
+    /// Paths known to exist on the remote after the last commit from this tree
+    #[serde(default)]
+    pub tracked: BTreeSet<String>,
+    /// Staged changes keyed by logical path
+    #[serde(default)]
+    pub changes: BTreeMap<String, StagedChange>,
+}
+
+/// A working tree and its staging index
+#[derive(Debug)]
+pub struct Staging {
+    root: PathBuf,
+    index: StagingIndex,
+}
+
+impl Staging {
+    /// Open the staging index rooted at `root`, starting empty if there is none yet
+    pub fn open(root: &Path) -> Result<Self> {
+        let index_path = root.join(STAGING_DIR).join(INDEX_FILE);
+        let index = if index_path.exists() {
+            let content = fs::read_to_string(&index_path)?;
+            serde_json::from_str(&content)
+                .map_err(|e| anyhow!("Corrupt staging index {}: {}", index_path.display(), e))?
+        } else {
+            StagingIndex::default()
+        };
+
+        Ok(Self { root: root.to_path_buf(), index })
+    }
+
+    /// Open the index of the nearest ancestor of `start` holding `.blacklake`,
+    /// or a new one rooted at `start`
+    pub fn discover(start: &Path) -> Result<Self> {
+        let root = start
+            .ancestors()
+            .find(|dir| dir.join(STAGING_DIR).is_dir())
+            .unwrap_or(start);
+        Self::open(root)
+    }
+
+    /// Write the index back to `.blacklake/index`
+    pub fn save(&self) -> Result<()> {
+        let dir = self.root.join(STAGING_DIR);
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join(INDEX_FILE), serde_json::to_string_pretty(&self.index)?)?;
+        Ok(())
+    }
+
+    pub fn index(&self) -> &StagingIndex {
+        &self.index
+    }
+
+    /// Logical repository path for `path` given relative to `cwd`
+    pub fn logical_path(&self, cwd: &Path, path: &str) -> Result<String> {
+        let absolute = clean_path(&cwd.join(path));
+        let relative = absolute
+            .strip_prefix(clean_path(&self.root))
+            .map_err(|_| anyhow!("{} is outside the working tree at {}", path, self.root.display()))?;
+
+        let logical = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        if logical.is_empty() {
+            return Err(anyhow!("Path cannot be the working tree root"));
+        }
+        if logical == STAGING_DIR || logical.starts_with(&format!("{}/", STAGING_DIR)) {
+            return Err(anyhow!("Cannot stage files inside {}", STAGING_DIR));
+        }
+        Ok(logical)
+    }
+
+    /// Hash the file at `path` and stage it as an Add, or a Modify if already tracked
+    pub fn add(&mut self, path: &str, set: &[(String, String)]) -> Result<&StagedChange> {
+        let sha256 = hash_file(&self.root.join(path))?;
+        let op = if self.index.tracked.contains(path) { ChangeOp::Modify } else { ChangeOp::Add };
+
+        // Re-adding keeps metadata staged earlier
+        let mut meta = match self.index.changes.remove(path) {
+            Some(previous) if previous.op != ChangeOp::Delete => previous.meta,
+            _ => Value::Object(Default::default()),
+        };
+        for (key, value) in set {
+            set_meta_field(&mut meta, key, value);
+        }
+
+        Ok(self.stage(StagedChange { path: path.to_string(), op, sha256: Some(sha256), meta }))
+    }
+
+    /// Stage the removal of `path`; an Add that was never committed is simply unstaged
+    pub fn remove(&mut self, path: &str) -> Result<()> {
+        if self.index.tracked.contains(path) {
+            self.stage(StagedChange {
+                path: path.to_string(),
+                op: ChangeOp::Delete,
+                sha256: None,
+                meta: Value::Object(Default::default()),
+            });
+            Ok(())
+        } else if self.index.changes.remove(path).is_some() {
+            Ok(())
+        } else {
+            Err(anyhow!("Path is not tracked or staged: {}", path))
+        }
+    }
+
+    /// Move `src` to `dst` in the working tree and stage it as Delete + Add
+    pub fn rename(&mut self, src: &str, dst: &str) -> Result<()> {
+        self.copy(src, dst)?;
+        fs::remove_file(self.root.join(src))?;
+
+        if self.index.tracked.contains(src) || self.index.changes.contains_key(src) {
+            self.remove(src)?;
+        }
+        Ok(())
+    }
+
+    /// Copy `src` to `dst` in the working tree and stage `dst` as an Add,
+    /// carrying over any metadata staged for `src`
+    pub fn copy(&mut self, src: &str, dst: &str) -> Result<()> {
+        let local_src = self.root.join(src);
+        if !local_src.is_file() {
+            return Err(anyhow!("Source file not found: {}", local_src.display()));
+        }
+
+        let local_dst = self.root.join(dst);
+        if let Some(parent) = local_dst.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(&local_src, &local_dst)?;
+
+        let meta = self
+            .index
+            .changes
+            .get(src)
+            .filter(|change| change.op != ChangeOp::Delete)
+            .map(|change| change.meta.clone());
+
+        self.add(dst, &[])?;
+        if let (Some(meta), Some(change)) = (meta, self.index.changes.get_mut(dst)) {
+            change.meta = meta;
+        }
+        Ok(())
+    }
+
+    /// Staged changes in path order, as they will be sent to the API
+    pub fn changes(&self) -> Vec<Change> {
+        self.index
+            .changes
+            .values()
+            .map(|change| Change {
+                op: change.op.clone(),
+                path: change.path.clone(),
+                sha256: change.sha256.clone(),
+                meta: change.meta.clone(),
+            })
+            .collect()
+    }
+
+    /// The commit request for the staged changes, with `set` applied to the
+    /// metadata of everything not being deleted
+    pub fn commit_request(&self, r#ref: &str, message: &str, set: &[(String, String)]) -> Result<CommitRequest> {
+        if self.index.changes.is_empty() {
+            return Err(anyhow!("Nothing to commit, staging area is empty"));
+        }
+
+        let mut changes = self.changes();
+        for change in changes.iter_mut().filter(|c| c.op != ChangeOp::Delete) {
+            for (key, value) in set {
+                set_meta_field(&mut change.meta, key, value);
+            }
+        }
+        Ok(CommitRequest {
+            r#ref: r#ref.to_string(),
+            message: Some(message.to_string()),
+            changes,
+            expected_parent: None,
+        })
+    }
+
+    /// Upload staged files, post the commit, and clear the index on success
+    pub async fn commit(
+        &mut self,
+        api_client: &ApiClient,
+        repo: &str,
+        r#ref: &str,
+        message: &str,
+        set: &[(String, String)],
+    ) -> Result<CommitResponse> {
+        let request = self.commit_request(r#ref, message, set)?;
+        for change in request.changes.iter().filter(|c| c.op != ChangeOp::Delete) {
+            let local_file = self.root.join(&change.path);
+            let size = fs::metadata(&local_file)?.len();
+            let upload = api_client
+                .upload_init(repo, &UploadInitRequest {
+                    path: change.path.clone(),
+                    size,
+                    media_type: mime_guess::from_path(&local_file).first().map(|m| m.to_string()),
+                    sha256: change.sha256.clone(),
+                })
+                .await?;
+            if upload.already_stored {
+                continue;
+            }
+            if upload.upload_url.is_empty() {
+                return Err(anyhow!("{} requires a multipart upload; use `blacklake put`", change.path));
+            }
+            api_client.upload_file(&upload.upload_url, &local_file, &upload.upload_headers).await?;
+            api_client.upload_verify(repo, &upload.sha256, None).await?;
+        }
+
+        let response = api_client.commit(repo, &request, false).await?;
+
+        for change in std::mem::take(&mut self.index.changes).into_values() {
+            if change.op == ChangeOp::Delete {
+                self.index.tracked.remove(&change.path);
+            } else {
+                self.index.tracked.insert(change.path);
+            }
+        }
+        self.save()?;
+
+        Ok(response)
+    }
+
+    fn stage(&mut self, change: StagedChange) -> &StagedChange {
+        let path = change.path.clone();
+        self.index.changes.insert(path.clone(), change);
+        &self.index.changes[&path]
+    }
+}
+
+/// Remove `.` and `..` components without touching the filesystem
+fn clean_path(path: &Path) -> PathBuf {
+    let mut cleaned = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                cleaned.pop();
+            }
+            other => cleaned.push(other),
+        }
+    }
+    cleaned
+}
+
+/// Hex-encoded sha256 of a file's contents
+pub(crate) fn hash_file(path: &Path) -> Result<String> {
+    let mut file = fs::File::open(path)
+        .map_err(|e| anyhow!("Cannot read {}: {}", path.display(), e))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 8192];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Set a metadata field using dot notation, e.g. `provenance.source=lab`
+fn set_meta_field(meta: &mut Value, key: &str, value: &str) {
+    let mut current = meta;
+    for part in key.split('.') {
+        if !current.is_object() {
+            *current = Value::Object(Default::default());
+        }
+        current = current
+            .as_object_mut()
+            .expect("just ensured an object")
+            .entry(part.to_string())
+            .or_insert(Value::Null);
+    }
+    *current = Value::String(value.to_string());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::State, routing::{post, put}, Json, Router};
+    use std::sync::{Arc, Mutex};
+    use tempfile::TempDir;
+
+    const HELLO_SHA256: &str = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+    fn staging_with_file(name: &str, content: &str) -> (TempDir, Staging) {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join(name), content).unwrap();
+        let staging = Staging::open(dir.path()).unwrap();
+        (dir, staging)
+    }
+
+    #[test]
+    fn test_add_hashes_file_and_persists_index() {
+        let (dir, mut staging) = staging_with_file("hello.txt", "hello");
+        let staged = staging.add("hello.txt", &[("provenance.source".to_string(), "lab".to_string())]).unwrap();
+
+        assert_eq!(staged.op, ChangeOp::Add);
+        assert_eq!(staged.sha256.as_deref(), Some(HELLO_SHA256));
+        assert_eq!(staged.meta["provenance"]["source"], "lab");
+
+        staging.save().unwrap();
+        let reopened = Staging::discover(&dir.path().join("nested")).unwrap();
+        assert_eq!(reopened.index(), staging.index());
+    }
+
+    #[test]
+    fn test_add_of_tracked_path_is_modify_and_rm_is_delete() {
+        let (_dir, mut staging) = staging_with_file("hello.txt", "hello");
+        staging.index.tracked.insert("hello.txt".to_string());
+
+        assert_eq!(staging.add("hello.txt", &[]).unwrap().op, ChangeOp::Modify);
+        staging.remove("hello.txt").unwrap();
+        assert_eq!(staging.index().changes["hello.txt"].op, ChangeOp::Delete);
+        assert!(staging.remove("never-seen.txt").is_err());
+    }
+
+    #[test]
+    fn test_rm_of_uncommitted_add_unstages_it() {
+        let (_dir, mut staging) = staging_with_file("hello.txt", "hello");
+        staging.add("hello.txt", &[]).unwrap();
+        staging.remove("hello.txt").unwrap();
+
+        assert!(staging.index().changes.is_empty());
+    }
+
+    #[test]
+    fn test_mv_and_cp_stage_expected_ops() {
+        let (dir, mut staging) = staging_with_file("a.txt", "hello");
+        staging.index.tracked.insert("a.txt".to_string());
+
+        staging.copy("a.txt", "copies/b.txt").unwrap();
+        assert!(dir.path().join("copies/b.txt").exists());
+        assert_eq!(staging.index().changes["copies/b.txt"].op, ChangeOp::Add);
+
+        staging.rename("a.txt", "c.txt").unwrap();
+        assert!(!dir.path().join("a.txt").exists());
+        assert_eq!(staging.index().changes["a.txt"].op, ChangeOp::Delete);
+        assert_eq!(staging.index().changes["c.txt"].sha256.as_deref(), Some(HELLO_SHA256));
+    }
+
+    #[test]
+    fn test_logical_path_is_relative_to_root() {
+        let (dir, staging) = staging_with_file("a.txt", "");
+        let cwd = dir.path().join("sub");
+
+        assert_eq!(staging.logical_path(&cwd, "data/x.csv").unwrap(), "sub/data/x.csv");
+        assert_eq!(staging.logical_path(&cwd, "../a.txt").unwrap(), "a.txt");
+        assert!(staging.logical_path(&cwd, "../../outside.txt").is_err());
+        assert!(staging.logical_path(dir.path(), ".blacklake/index").is_err());
+    }
+
+    #[derive(Default)]
+    struct MockApi {
+        uploads: Vec<Vec<u8>>,
+        upload_checksums: Vec<Option<String>>,
+        verified: Vec<String>,
+        commits: Vec<Value>,
+    }
+
+    /// Serve the upload-init, upload, upload-verify and commit endpoints, recording what was sent
+    async fn mock_api() -> (String, Arc<Mutex<MockApi>>) {
+        let recorded = Arc::new(Mutex::new(MockApi::default()));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+
+        let upload_url = format!("{}/upload", base_url);
+        let app = Router::new()
+            .route(
+                "/v1/repos/:repo/upload-init",
+                post(move |Json(request): Json<Value>| {
+                    let upload_url = upload_url.clone();
+                    async move {
+                        Json(serde_json::json!({
+                            "upload_url": upload_url,
+                            "sha256": request["sha256"],
+                            "s3_key": "pending",
+                            "expires_at": "2030-01-01T00:00:00Z",
+                            "multipart": null,
+                            "upload_headers": { "x-amz-checksum-sha256": "checksum" },
+                        }))
+                    }
+                }),
+            )
+            .route(
+                "/upload",
+                put(
+                    |State(recorded): State<Arc<Mutex<MockApi>>>, headers: axum::http::HeaderMap, body: axum::body::Bytes| async move {
+                        let checksum = headers
+                            .get("x-amz-checksum-sha256")
+                            .map(|v| v.to_str().unwrap().to_string());
+                        let mut recorded = recorded.lock().unwrap();
+                        recorded.uploads.push(body.to_vec());
+                        recorded.upload_checksums.push(checksum);
+                    },
+                ),
+            )
+            .route(
+                "/v1/repos/:repo/upload-verify",
+                post(|State(recorded): State<Arc<Mutex<MockApi>>>, Json(request): Json<Value>| async move {
+                    let sha256 = request["sha256"].as_str().unwrap().to_string();
+                    recorded.lock().unwrap().verified.push(sha256.clone());
+                    Json(serde_json::json!({ "sha256": sha256, "verification": "verified" }))
+                }),
+            )
+            .route(
+                "/v1/repos/:repo/commit",
+                post(|State(recorded): State<Arc<Mutex<MockApi>>>, Json(request): Json<Value>| async move {
+                    recorded.lock().unwrap().commits.push(request);
+                    Json(serde_json::json!({
+                        "commit_id": "00000000-0000-0000-0000-000000000001",
+                        "parent_id": null,
+                        "created_at": "2030-01-01T00:00:00Z",
+                    }))
+                }),
+            )
+            .with_state(recorded.clone());
+
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (base_url, recorded)
+    }
+
+    #[tokio::test]
+    async fn test_stage_then_commit_posts_changes_and_clears_index() {
+        let (dir, mut staging) = staging_with_file("hello.txt", "hello");
+        staging.index.tracked.insert("old.txt".to_string());
+        staging.add("hello.txt", &[]).unwrap();
+        staging.remove("old.txt").unwrap();
+
+        let (base_url, recorded) = mock_api().await;
+        let api_client = ApiClient::new(base_url);
+        let version = [("version".to_string(), "1.0".to_string())];
+        staging.commit(&api_client, "mylab", "main", "Add hello", &version).await.unwrap();
+
+        let recorded = recorded.lock().unwrap();
+        assert_eq!(recorded.uploads, vec![b"hello".to_vec()]);
+        assert_eq!(recorded.upload_checksums, vec![Some("checksum".to_string())]);
+        assert_eq!(recorded.verified, vec![HELLO_SHA256.to_string()]);
+        assert_eq!(recorded.commits.len(), 1);
+
+        let commit = &recorded.commits[0];
+        assert_eq!(commit["ref"], "main");
+        assert_eq!(commit["message"], "Add hello");
+        assert_eq!(commit["changes"][0]["op"], "add");
+        assert_eq!(commit["changes"][0]["path"], "hello.txt");
+        assert_eq!(commit["changes"][0]["sha256"], HELLO_SHA256);
+        assert_eq!(commit["changes"][0]["meta"]["version"], "1.0");
+        assert_eq!(commit["changes"][1]["op"], "delete");
+        assert_eq!(commit["changes"][1]["path"], "old.txt");
+
+        let reopened = Staging::open(dir.path()).unwrap();
+        assert!(reopened.index().changes.is_empty());
+        assert_eq!(reopened.index().tracked, BTreeSet::from(["hello.txt".to_string()]));
+    }
+
+    #[tokio::test]
+    async fn test_commit_with_nothing_staged_fails() {
+        let (_dir, mut staging) = staging_with_file("hello.txt", "hello");
+        let api_client = ApiClient::new("http://127.0.0.1:9".to_string());
+
+        assert!(staging.commit(&api_client, "mylab", "main", "empty", &[]).await.is_err());
+    }
+}
@@ -0,0 +1,262 @@
+//! Screen state and key handling. Keys never fetch anything themselves; they
+//! return a [`Load`] for the event loop to carry out.
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use super::data::{parent_dir, EntryDetail, TreeItem};
+
+/// Data a key press asks the event loop to fetch
+#[derive(Debug, Clone, PartialEq)]
+pub enum Load {
+    Repos,
+    /// The directory `dir` of `repo`
+    Dir { repo: String, dir: String },
+    /// Metadata and sample of a file
+    Detail { repo: String, item: TreeItem },
+}
+
+/// What the event loop does after a key
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    Continue,
+    Fetch(Load),
+    Quit,
+}
+
+/// Which list is on screen
+#[derive(Debug, Clone, PartialEq)]
+pub enum Screen {
+    Repos(Vec<String>),
+    Tree { repo: String, dir: String, items: Vec<TreeItem> },
+}
+
+pub struct App {
+    pub r#ref: String,
+    pub screen: Screen,
+    /// Index into the filtered list
+    pub selected: usize,
+    /// Case-insensitive filter applied to the list's names
+    pub query: String,
+    /// Keys go to the search box rather than moving around
+    pub searching: bool,
+    pub detail: Option<EntryDetail>,
+    /// Lines the detail pane is scrolled by
+    pub detail_scroll: u16,
+    /// Last error or hint, shown in the status bar
+    pub status: Option<String>,
+}
+
+impl App {
+    pub fn new(r#ref: String) -> Self {
+        Self {
+            r#ref,
+            screen: Screen::Repos(Vec::new()),
+            selected: 0,
+            query: String::new(),
+            searching: false,
+            detail: None,
+            detail_scroll: 0,
+            status: None,
+        }
+    }
+
+    /// Names of the rows that pass the search filter, with their index in
+    /// the unfiltered list
+    pub fn visible(&self) -> Vec<(usize, &str)> {
+        let query = self.query.to_lowercase();
+        let names: Vec<&str> = match &self.screen {
+            Screen::Repos(repos) => repos.iter().map(String::as_str).collect(),
+            Screen::Tree { items, .. } => items.iter().map(|item| item.name.as_str()).collect(),
+        };
+        names
+            .into_iter()
+            .enumerate()
+            .filter(|(_, name)| name.to_lowercase().contains(&query))
+            .collect()
+    }
+
+    pub fn show_repos(&mut self, repos: Vec<String>) {
+        self.screen = Screen::Repos(repos);
+        self.reset_list();
+    }
+
+    pub fn show_dir(&mut self, repo: String, dir: String, items: Vec<TreeItem>) {
+        self.screen = Screen::Tree { repo, dir, items };
+        self.reset_list();
+    }
+
+    pub fn show_detail(&mut self, detail: EntryDetail) {
+        self.detail = Some(detail);
+        self.detail_scroll = 0;
+    }
+
+    fn reset_list(&mut self) {
+        self.selected = 0;
+        self.query.clear();
+        self.searching = false;
+        self.detail = None;
+        self.detail_scroll = 0;
+    }
+
+    pub fn on_key(&mut self, key: KeyEvent) -> Step {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return Step::Quit;
+        }
+        if self.searching {
+            self.on_search_key(key.code);
+            return Step::Continue;
+        }
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => Step::Quit,
+            KeyCode::Char('/') => {
+                self.searching = true;
+                Step::Continue
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.selected = (self.selected + 1).min(self.visible().len().saturating_sub(1));
+                Step::Continue
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.selected = self.selected.saturating_sub(1);
+                Step::Continue
+            }
+            KeyCode::PageDown => {
+                self.detail_scroll = self.detail_scroll.saturating_add(10);
+                Step::Continue
+            }
+            KeyCode::PageUp => {
+                self.detail_scroll = self.detail_scroll.saturating_sub(10);
+                Step::Continue
+            }
+            KeyCode::Enter | KeyCode::Right | KeyCode::Char('l') => self.open_selected(),
+            KeyCode::Backspace | KeyCode::Left | KeyCode::Char('h') => self.go_up(),
+            KeyCode::Char('r') => match &self.screen {
+                Screen::Repos(_) => Step::Fetch(Load::Repos),
+                Screen::Tree { repo, dir, .. } => Step::Fetch(Load::Dir { repo: repo.clone(), dir: dir.clone() }),
+            },
+            _ => Step::Continue,
+        }
+    }
+
+    /// Typing narrows the list; Enter keeps the filter, Esc drops it
+    fn on_search_key(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Enter => self.searching = false,
+            KeyCode::Esc => {
+                self.searching = false;
+                self.query.clear();
+            }
+            KeyCode::Backspace => {
+                self.query.pop();
+            }
+            KeyCode::Char(c) => self.query.push(c),
+            _ => return,
+        }
+        self.selected = 0;
+    }
+
+    fn open_selected(&mut self) -> Step {
+        let Some(&(index, _)) = self.visible().get(self.selected) else {
+            return Step::Continue;
+        };
+        match &self.screen {
+            Screen::Repos(repos) => Step::Fetch(Load::Dir { repo: repos[index].clone(), dir: String::new() }),
+            Screen::Tree { repo, items, .. } => {
+                let item = &items[index];
+                if item.is_dir {
+                    Step::Fetch(Load::Dir { repo: repo.clone(), dir: item.path.clone() })
+                } else {
+                    Step::Fetch(Load::Detail { repo: repo.clone(), item: item.clone() })
+                }
+            }
+        }
+    }
+
+    /// Up one directory, or back to the repositories from a repo's root
+    fn go_up(&mut self) -> Step {
+        match &self.screen {
+            Screen::Repos(_) => Step::Continue,
+            Screen::Tree { dir, .. } if dir.is_empty() => Step::Fetch(Load::Repos),
+            Screen::Tree { repo, dir, .. } => Step::Fetch(Load::Dir { repo: repo.clone(), dir: parent_dir(dir) }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn press(app: &mut App, code: KeyCode) -> Step {
+        app.on_key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    fn item(path: &str, name: &str, is_dir: bool) -> TreeItem {
+        TreeItem {
+            path: path.to_string(),
+            name: name.to_string(),
+            is_dir,
+            size: None,
+            media_type: None,
+            meta: json!({}),
+        }
+    }
+
+    #[test]
+    fn test_keys_navigate_repos_directories_and_files() {
+        let mut app = App::new("main".to_string());
+        app.show_repos(vec!["climate".to_string(), "soil".to_string()]);
+
+        press(&mut app, KeyCode::Down);
+        assert_eq!(
+            press(&mut app, KeyCode::Enter),
+            Step::Fetch(Load::Dir { repo: "soil".to_string(), dir: String::new() })
+        );
+
+        app.show_dir("soil".to_string(), "data/".to_string(), vec![item("data/raw/", "raw/", true), item("data/a.csv", "a.csv", false)]);
+        assert_eq!(
+            press(&mut app, KeyCode::Enter),
+            Step::Fetch(Load::Dir { repo: "soil".to_string(), dir: "data/raw/".to_string() })
+        );
+        press(&mut app, KeyCode::Down);
+        assert_eq!(
+            press(&mut app, KeyCode::Enter),
+            Step::Fetch(Load::Detail { repo: "soil".to_string(), item: item("data/a.csv", "a.csv", false) })
+        );
+        assert_eq!(
+            press(&mut app, KeyCode::Backspace),
+            Step::Fetch(Load::Dir { repo: "soil".to_string(), dir: String::new() })
+        );
+
+        app.show_dir("soil".to_string(), String::new(), vec![]);
+        assert_eq!(press(&mut app, KeyCode::Backspace), Step::Fetch(Load::Repos));
+        assert_eq!(press(&mut app, KeyCode::Char('q')), Step::Quit);
+    }
+
+    #[test]
+    fn test_search_filters_the_list_and_selection_follows_it() {
+        let mut app = App::new("main".to_string());
+        app.show_repos(vec!["climate".to_string(), "soil".to_string(), "Soil-2020".to_string()]);
+
+        press(&mut app, KeyCode::Char('/'));
+        for c in "soil".chars() {
+            press(&mut app, KeyCode::Char(c));
+        }
+        // While searching, letters are typed rather than acting as keys
+        assert_eq!(press(&mut app, KeyCode::Char('q')), Step::Continue);
+        press(&mut app, KeyCode::Backspace);
+        assert_eq!(app.visible(), vec![(1, "soil"), (2, "Soil-2020")]);
+
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Down);
+        assert_eq!(
+            press(&mut app, KeyCode::Enter),
+            Step::Fetch(Load::Dir { repo: "Soil-2020".to_string(), dir: String::new() })
+        );
+
+        press(&mut app, KeyCode::Char('/'));
+        press(&mut app, KeyCode::Esc);
+        assert_eq!(app.visible().len(), 3);
+    }
+}
@@ -0,0 +1,200 @@
+//! What the TUI shows, fetched through `ApiClient`. Nothing here touches the
+//! terminal, so the screens can be tested apart from I/O.
+
+use anyhow::Result;
+use serde_json::Value;
+
+use crate::api::ApiClient;
+
+/// Most pages of one directory read before the listing is cut short
+const MAX_DIR_PAGES: usize = 50;
+
+/// A row of a directory listing
+#[derive(Debug, Clone, PartialEq)]
+pub struct TreeItem {
+    /// Full path in the repository; directories end with `/`
+    pub path: String,
+    /// Last path segment, as shown in the list
+    pub name: String,
+    pub is_dir: bool,
+    pub size: Option<i64>,
+    pub media_type: Option<String>,
+    pub meta: Value,
+}
+
+/// Everything the detail pane shows for one file
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntryDetail {
+    pub item: TreeItem,
+    /// Leading rows written by the sampling job, when the file has a sample
+    pub sample: Option<Vec<Value>>,
+}
+
+/// Names of the repositories visible to the caller, sorted
+pub async fn load_repos(api: &ApiClient) -> Result<Vec<String>> {
+    let mut names: Vec<String> = api.list_repos().await?.into_iter().map(|repo| repo.name).collect();
+    names.sort();
+    Ok(names)
+}
+
+/// Every row of the directory `dir` at `ref`, subdirectories first.
+///
+/// `dir` is empty for the root and otherwise ends with `/`.
+pub async fn load_dir(api: &ApiClient, repo: &str, r#ref: &str, dir: &str) -> Result<Vec<TreeItem>> {
+    let mut items = Vec::new();
+    let mut offset = 0;
+    for _ in 0..MAX_DIR_PAGES {
+        let page = api.list_dir(repo, r#ref, dir, offset).await?;
+        items.extend(page.entries.into_iter().map(|entry| {
+            let name = entry.path.strip_prefix(dir).unwrap_or(&entry.path).to_string();
+            TreeItem {
+                name,
+                is_dir: entry.is_dir || entry.path.ends_with('/'),
+                path: entry.path,
+                size: entry.size,
+                media_type: entry.media_type,
+                meta: entry.meta,
+            }
+        }));
+        match page.next_offset {
+            Some(next) => offset = next,
+            None => break,
+        }
+    }
+
+    items.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+    Ok(items)
+}
+
+/// A file's metadata along with its sample, if it has one
+pub async fn load_detail(api: &ApiClient, repo: &str, r#ref: &str, item: &TreeItem) -> Result<EntryDetail> {
+    let sample = api
+        .get_sample(repo, r#ref, &item.path)
+        .await?
+        .map(|sample| match sample.sample {
+            Value::Array(rows) => rows,
+            other => vec![other],
+        });
+    Ok(EntryDetail { item: item.clone(), sample })
+}
+
+/// The directory above `dir`: `a/b/` becomes `a/`, and `a/` the root
+pub fn parent_dir(dir: &str) -> String {
+    let trimmed = dir.trim_end_matches('/');
+    match trimmed.rfind('/') {
+        Some(i) => trimmed[..=i].to_string(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        extract::{Path, Query},
+        http::StatusCode,
+        response::IntoResponse,
+        routing::get,
+        Json, Router,
+    };
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    /// A server answering the tree, sample and repo endpoints from fixed data
+    async fn stub_api() -> ApiClient {
+        let app = Router::new()
+            .route(
+                "/v1/repos",
+                get(|| async {
+                    Json(json!([
+                        {"id": uuid::Uuid::nil(), "name": "soil", "created_at": "2024-01-01T00:00:00Z"},
+                        {"id": uuid::Uuid::nil(), "name": "climate", "created_at": "2024-01-01T00:00:00Z"}
+                    ]))
+                }),
+            )
+            .route(
+                "/v1/repos/:repo/tree/:ref",
+                get(|Query(params): Query<HashMap<String, String>>| async move {
+                    assert_eq!(params["delimiter"], "/");
+                    let entry = |path: &str, is_dir: bool| {
+                        json!({"path": path, "is_dir": is_dir, "size": null, "media_type": null, "meta": {"title": path}})
+                    };
+                    // The root comes back in two pages
+                    let page = match (params["p"].as_str(), params["offset"].as_str()) {
+                        ("", "0") => json!({"entries": [entry("readme.md", false), entry("data/", true)], "total": 3, "next_offset": 2}),
+                        ("", "2") => json!({"entries": [entry("a.txt", false)], "total": 3}),
+                        ("data/", _) => json!({"entries": [entry("data/temps.csv", false)], "total": 1}),
+                        other => panic!("unexpected listing {:?}", other),
+                    };
+                    Json(page)
+                }),
+            )
+            .route(
+                "/v1/repos/:repo/sample/:ref/*path",
+                get(|Path((_, _, path)): Path<(String, String, String)>| async move {
+                    if path != "data/temps.csv" {
+                        return StatusCode::NOT_FOUND.into_response();
+                    }
+                    Json(json!({
+                        "commit_id": uuid::Uuid::nil(),
+                        "path": path,
+                        "sample": [{"day": "1", "temp": "12.5"}, {"day": "2", "temp": "13.1"}],
+                        "schema": {"columns": []},
+                        "created_at": "2024-01-01T00:00:00Z"
+                    }))
+                    .into_response()
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        ApiClient::new(base_url)
+    }
+
+    #[tokio::test]
+    async fn test_repos_are_listed_by_name() {
+        let api = stub_api().await;
+        assert_eq!(load_repos(&api).await.unwrap(), vec!["climate", "soil"]);
+    }
+
+    #[tokio::test]
+    async fn test_directory_pages_are_joined_with_subdirectories_first() {
+        let api = stub_api().await;
+
+        let root = load_dir(&api, "climate", "main", "").await.unwrap();
+        let names: Vec<&str> = root.iter().map(|item| item.name.as_str()).collect();
+        assert_eq!(names, vec!["data/", "a.txt", "readme.md"]);
+        assert!(root[0].is_dir);
+
+        let data = load_dir(&api, "climate", "main", &root[0].path).await.unwrap();
+        assert_eq!(data.len(), 1);
+        assert_eq!(data[0].name, "temps.csv");
+        assert_eq!(data[0].path, "data/temps.csv");
+        assert_eq!(data[0].meta["title"], "data/temps.csv");
+    }
+
+    #[tokio::test]
+    async fn test_detail_carries_sample_rows_when_there_are_any() {
+        let api = stub_api().await;
+        let data = load_dir(&api, "climate", "main", "data/").await.unwrap();
+
+        let detail = load_detail(&api, "climate", "main", &data[0]).await.unwrap();
+        let sample = detail.sample.unwrap();
+        assert_eq!(sample.len(), 2);
+        assert_eq!(sample[1]["temp"], "13.1");
+
+        // A file without a sample still has its metadata
+        let root = load_dir(&api, "climate", "main", "").await.unwrap();
+        let readme = root.iter().find(|item| item.name == "readme.md").unwrap();
+        let detail = load_detail(&api, "climate", "main", readme).await.unwrap();
+        assert_eq!(detail.sample, None);
+        assert_eq!(detail.item.meta["title"], "readme.md");
+    }
+
+    #[test]
+    fn test_parent_dir() {
+        assert_eq!(parent_dir("a/b/"), "a/");
+        assert_eq!(parent_dir("a/"), "");
+        assert_eq!(parent_dir(""), "");
+    }
+}
@@ -0,0 +1,68 @@
+//! `blacklake tui`: browse repositories, trees, metadata and samples.
+//!
+//! Read-only. [`data`] fetches through `ApiClient`, [`app`] turns keys into
+//! state changes and fetch requests, and [`ui`] draws; only [`run`] touches
+//! both the terminal and the network.
+
+mod app;
+mod data;
+mod ui;
+
+use anyhow::Result;
+use ratatui::crossterm::event::{self, Event, KeyEventKind};
+use ratatui::DefaultTerminal;
+
+use crate::api::ApiClient;
+use app::{App, Load, Step};
+
+/// Open the TUI at the repository list, or at `repo`'s root when given
+pub async fn run(api: &ApiClient, r#ref: String, repo: Option<String>) -> Result<()> {
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, api, r#ref, repo).await;
+    ratatui::restore();
+    result
+}
+
+async fn event_loop(terminal: &mut DefaultTerminal, api: &ApiClient, r#ref: String, repo: Option<String>) -> Result<()> {
+    let mut app = App::new(r#ref);
+    let first = match repo {
+        Some(repo) => Load::Dir { repo, dir: String::new() },
+        None => Load::Repos,
+    };
+    fetch(&mut app, api, first).await;
+
+    loop {
+        terminal.draw(|frame| ui::draw(frame, &app))?;
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match app.on_key(key) {
+            Step::Continue => {}
+            Step::Fetch(load) => {
+                app.status = Some("Loading…".to_string());
+                terminal.draw(|frame| ui::draw(frame, &app))?;
+                fetch(&mut app, api, load).await;
+            }
+            Step::Quit => return Ok(()),
+        }
+    }
+}
+
+/// Carry out `load`; a failure stays on the current screen and is shown in
+/// the status bar
+async fn fetch(app: &mut App, api: &ApiClient, load: Load) {
+    let r#ref = app.r#ref.clone();
+    let result = match load {
+        Load::Repos => data::load_repos(api).await.map(|repos| app.show_repos(repos)),
+        Load::Dir { repo, dir } => data::load_dir(api, &repo, &r#ref, &dir)
+            .await
+            .map(|items| app.show_dir(repo, dir, items)),
+        Load::Detail { repo, item } => data::load_detail(api, &repo, &r#ref, &item)
+            .await
+            .map(|detail| app.show_detail(detail)),
+    };
+    app.status = result.err().map(|e| format!("Error: {}", e));
+}
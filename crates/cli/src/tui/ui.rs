@@ -0,0 +1,85 @@
+//! Drawing the screen from [`App`]
+
+use ratatui::{
+    layout::{Constraint, Direction, Layout},
+    style::{Modifier, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap},
+    Frame,
+};
+
+use super::app::{App, Screen};
+use super::data::EntryDetail;
+
+/// Sample rows shown under the metadata
+const SAMPLE_PREVIEW_ROWS: usize = 20;
+
+pub fn draw(frame: &mut Frame, app: &App) {
+    let [body, search, status] = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(3), Constraint::Length(3), Constraint::Length(1)])
+        .areas(frame.area());
+    let [list_area, detail_area] = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+        .areas(body);
+
+    let title = match &app.screen {
+        Screen::Repos(_) => " Repositories ".to_string(),
+        Screen::Tree { repo, dir, .. } => format!(" {}@{}:/{} ", repo, app.r#ref, dir),
+    };
+    let rows: Vec<ListItem> = app
+        .visible()
+        .into_iter()
+        .map(|(index, name)| {
+            let is_dir = matches!(&app.screen, Screen::Tree { items, .. } if items[index].is_dir);
+            let style = if is_dir { Style::default().bold() } else { Style::default() };
+            ListItem::new(Span::styled(name.to_string(), style))
+        })
+        .collect();
+    let list = List::new(rows)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut state = ListState::default().with_selected(Some(app.selected));
+    frame.render_stateful_widget(list, list_area, &mut state);
+
+    let detail = Paragraph::new(app.detail.as_ref().map(detail_lines).unwrap_or_default())
+        .block(Block::default().borders(Borders::ALL).title(" Metadata "))
+        .wrap(Wrap { trim: false })
+        .scroll((app.detail_scroll, 0));
+    frame.render_widget(detail, detail_area);
+
+    let search_title = if app.searching { " Search (Enter keeps, Esc clears) " } else { " Search (/) " };
+    let search_style = if app.searching { Style::default().bold() } else { Style::default() };
+    frame.render_widget(
+        Paragraph::new(app.query.as_str())
+            .style(search_style)
+            .block(Block::default().borders(Borders::ALL).title(search_title)),
+        search,
+    );
+
+    let hint = "↑↓ move  ⏎ open  ⌫ up  / search  PgUp/PgDn scroll  r reload  q quit";
+    frame.render_widget(Paragraph::new(app.status.as_deref().unwrap_or(hint)), status);
+}
+
+fn detail_lines(detail: &EntryDetail) -> Vec<Line<'static>> {
+    let item = &detail.item;
+    let mut lines = vec![
+        Line::from(item.path.clone()).bold(),
+        Line::from(format!(
+            "{} · {}",
+            item.media_type.as_deref().unwrap_or("unknown type"),
+            item.size.map(|size| format!("{} bytes", size)).unwrap_or_else(|| "size unknown".to_string())
+        )),
+        Line::default(),
+    ];
+    let meta = serde_json::to_string_pretty(&item.meta).unwrap_or_default();
+    lines.extend(meta.lines().map(|line| Line::from(line.to_string())));
+
+    if let Some(sample) = &detail.sample {
+        lines.push(Line::default());
+        lines.push(Line::from(format!("Sample ({} rows)", sample.len())).bold());
+        lines.extend(sample.iter().take(SAMPLE_PREVIEW_ROWS).map(|row| Line::from(row.to_string())));
+    }
+    lines
+}
@@ -0,0 +1,205 @@
+// Per-connector circuit breaker
+// A connector that keeps failing is left alone for a cooldown instead of
+// being called (and waited on) by every operation that enumerates connectors
+
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Whether calls to a connector are going through
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    /// Calls go through
+    #[default]
+    Closed,
+    /// Too many consecutive failures; calls are refused until the cooldown ends
+    Open,
+    /// The cooldown has ended and one probe call decides whether to close again
+    HalfOpen,
+}
+
+/// When a breaker opens and how long it stays open
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BreakerConfig {
+    /// Consecutive failures that open the circuit
+    pub failure_threshold: u32,
+    /// How long an open circuit refuses calls before allowing a probe
+    pub cooldown: Duration,
+    /// Longest a single connector call may take before it counts as a failure
+    pub call_timeout: Duration,
+}
+
+impl Default for BreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            cooldown: Duration::from_secs(60),
+            call_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+impl BreakerConfig {
+    /// Defaults overridden by `CONNECTOR_BREAKER_FAILURES`,
+    /// `CONNECTOR_BREAKER_COOLDOWN_SECS` and `CONNECTOR_CALL_TIMEOUT_SECS`
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let var = |name: &str| std::env::var(name).ok().and_then(|v| v.trim().parse::<u64>().ok());
+        Self {
+            failure_threshold: var("CONNECTOR_BREAKER_FAILURES")
+                .map(|n| n.clamp(1, u32::MAX as u64) as u32)
+                .unwrap_or(defaults.failure_threshold),
+            cooldown: var("CONNECTOR_BREAKER_COOLDOWN_SECS")
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.cooldown),
+            call_timeout: var("CONNECTOR_CALL_TIMEOUT_SECS")
+                .filter(|&secs| secs > 0)
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.call_timeout),
+        }
+    }
+}
+
+/// Consecutive-failure circuit breaker for one connector
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    config: BreakerConfig,
+    state: CircuitState,
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    /// When the half-open probe started; a probe that never reports back
+    /// (its caller was cancelled) is replaced after the call timeout
+    probe_started: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: BreakerConfig) -> Self {
+        Self {
+            config,
+            state: CircuitState::Closed,
+            consecutive_failures: 0,
+            opened_at: None,
+            probe_started: None,
+        }
+    }
+
+    pub fn state(&self) -> CircuitState {
+        self.state
+    }
+
+    pub fn consecutive_failures(&self) -> u32 {
+        self.consecutive_failures
+    }
+
+    /// Whether a call may go ahead at `now`. An open circuit whose cooldown
+    /// has passed turns half-open and lets a single probe through.
+    pub fn try_acquire(&mut self, now: Instant) -> bool {
+        match self.state {
+            CircuitState::Closed => true,
+            CircuitState::Open => {
+                let cooled_down = self
+                    .opened_at
+                    .is_none_or(|opened| now.duration_since(opened) >= self.config.cooldown);
+                if cooled_down {
+                    self.state = CircuitState::HalfOpen;
+                    self.probe_started = Some(now);
+                }
+                cooled_down
+            }
+            CircuitState::HalfOpen => {
+                let probe_abandoned = self
+                    .probe_started
+                    .is_none_or(|started| now.duration_since(started) >= self.config.call_timeout);
+                if probe_abandoned {
+                    self.probe_started = Some(now);
+                }
+                probe_abandoned
+            }
+        }
+    }
+
+    /// How long until an open circuit allows a probe
+    pub fn retry_after(&self, now: Instant) -> Option<Duration> {
+        match (self.state, self.opened_at) {
+            (CircuitState::Open, Some(opened)) => Some(self.config.cooldown.saturating_sub(now.duration_since(opened))),
+            _ => None,
+        }
+    }
+
+    pub fn record_success(&mut self) {
+        self.state = CircuitState::Closed;
+        self.consecutive_failures = 0;
+        self.opened_at = None;
+        self.probe_started = None;
+    }
+
+    /// Count a failure; a failed probe or reaching the threshold opens the circuit
+    pub fn record_failure(&mut self, now: Instant) {
+        self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+        if self.state == CircuitState::HalfOpen || self.consecutive_failures >= self.config.failure_threshold {
+            self.state = CircuitState::Open;
+            self.opened_at = Some(now);
+            self.probe_started = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> BreakerConfig {
+        BreakerConfig {
+            failure_threshold: 3,
+            cooldown: Duration::from_secs(30),
+            call_timeout: Duration::from_secs(5),
+        }
+    }
+
+    #[test]
+    fn test_breaker_opens_after_threshold_and_probes_after_cooldown() {
+        let start = Instant::now();
+        let mut breaker = CircuitBreaker::new(config());
+
+        for _ in 0..2 {
+            assert!(breaker.try_acquire(start));
+            breaker.record_failure(start);
+        }
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        breaker.record_failure(start);
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert!(!breaker.try_acquire(start + Duration::from_secs(29)));
+        assert_eq!(breaker.retry_after(start + Duration::from_secs(20)), Some(Duration::from_secs(10)));
+
+        // One probe after the cooldown; a second caller waits on it
+        let later = start + Duration::from_secs(30);
+        assert!(breaker.try_acquire(later));
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        assert!(!breaker.try_acquire(later));
+
+        // A failed probe reopens immediately
+        breaker.record_failure(later);
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert!(!breaker.try_acquire(later + Duration::from_secs(1)));
+
+        let recovered = later + Duration::from_secs(30);
+        assert!(breaker.try_acquire(recovered));
+        breaker.record_success();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert_eq!(breaker.consecutive_failures(), 0);
+    }
+
+    #[test]
+    fn test_abandoned_probe_is_replaced_after_call_timeout() {
+        let start = Instant::now();
+        let mut breaker = CircuitBreaker::new(config());
+        for _ in 0..3 {
+            breaker.record_failure(start);
+        }
+
+        let probe = start + Duration::from_secs(30);
+        assert!(breaker.try_acquire(probe));
+        assert!(!breaker.try_acquire(probe + Duration::from_secs(4)));
+        assert!(breaker.try_acquire(probe + Duration::from_secs(5)));
+    }
+}
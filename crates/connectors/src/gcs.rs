@@ -0,0 +1,619 @@
+// Google Cloud Storage connector for bucket federation
+// Week 8: Federation across data sources
+
+use super::traits::*;
+use async_trait::async_trait;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+/// Public GCS endpoint, used unless `endpoint` points at an emulator
+pub const DEFAULT_GCS_ENDPOINT: &str = "https://storage.googleapis.com";
+/// Google's OAuth token endpoint for service-account assertions
+pub const DEFAULT_TOKEN_URI: &str = "https://oauth2.googleapis.com/token";
+/// V4 signed URLs may live at most seven days
+pub const MAX_SIGNED_URL_SECONDS: u32 = 7 * 24 * 60 * 60;
+
+const READ_ONLY_SCOPE: &str = "https://www.googleapis.com/auth/devstorage.read_only";
+
+/// GCS connector configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GcsConnectorConfig {
+    pub bucket: String,
+    pub prefix: Option<String>,
+    /// Service-account key used for API access and signed URLs; omit for
+    /// public buckets and emulators
+    pub service_account_key: Option<GcsServiceAccountKey>,
+    /// Alternate endpoint, e.g. a fake-gcs-server at `http://localhost:4443`
+    pub endpoint: Option<String>,
+}
+
+/// The fields of a service-account JSON key the connector needs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GcsServiceAccountKey {
+    pub client_email: String,
+    /// PEM-encoded RSA private key
+    pub private_key: String,
+    pub private_key_id: Option<String>,
+    pub token_uri: Option<String>,
+}
+
+/// Object resource from the GCS JSON API
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GcsObject {
+    name: String,
+    bucket: String,
+    content_type: Option<String>,
+    /// The JSON API sends sizes as decimal strings
+    size: Option<String>,
+    updated: Option<String>,
+    etag: Option<String>,
+    md5_hash: Option<String>,
+    generation: Option<String>,
+    #[serde(default)]
+    metadata: HashMap<String, String>,
+}
+
+/// Page of an object listing
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GcsObjectList {
+    #[serde(default)]
+    items: Vec<GcsObject>,
+    next_page_token: Option<String>,
+}
+
+/// OAuth token response
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: i64,
+}
+
+/// JWT claims for the service-account token exchange
+#[derive(Debug, Serialize)]
+struct TokenClaims<'a> {
+    iss: &'a str,
+    scope: &'a str,
+    aud: &'a str,
+    iat: i64,
+    exp: i64,
+}
+
+/// GCS connector implementation
+pub struct GcsConnector {
+    config: GcsConnectorConfig,
+    client: Client,
+    name: String,
+    /// Cached access token and when it expires
+    token: RwLock<Option<(String, chrono::DateTime<chrono::Utc>)>>,
+}
+
+impl GcsConnector {
+    pub fn new(name: String, config: GcsConnectorConfig) -> Result<Self, ConnectorError> {
+        if let Some(ref key) = config.service_account_key {
+            EncodingKey::from_rsa_pem(key.private_key.as_bytes())
+                .map_err(|e| ConnectorError::ConfigurationError(format!("Invalid service account key: {}", e)))?;
+        }
+
+        Ok(Self {
+            config,
+            client: Client::new(),
+            name,
+            token: RwLock::new(None),
+        })
+    }
+
+    fn endpoint(&self) -> &str {
+        self.config.endpoint.as_deref().unwrap_or(DEFAULT_GCS_ENDPOINT).trim_end_matches('/')
+    }
+
+    /// Build JSON API URL for an object, or for the bucket's object collection
+    fn objects_url(&self, object: Option<&str>) -> String {
+        let base = format!("{}/storage/v1/b/{}/o", self.endpoint(), percent_encode(&self.config.bucket));
+        match object {
+            Some(object) => format!("{}/{}", base, percent_encode(object)),
+            None => base,
+        }
+    }
+
+    /// Bearer token for the service account, refreshed a minute before expiry
+    async fn access_token(&self) -> Result<Option<String>, ConnectorError> {
+        let key = match self.config.service_account_key {
+            Some(ref key) => key,
+            None => return Ok(None),
+        };
+
+        let now = chrono::Utc::now();
+        if let Some((token, expires_at)) = self.token.read().await.as_ref() {
+            if *expires_at > now + chrono::Duration::seconds(60) {
+                return Ok(Some(token.clone()));
+            }
+        }
+
+        let token_uri = key.token_uri.as_deref().unwrap_or(DEFAULT_TOKEN_URI);
+        let claims = TokenClaims {
+            iss: &key.client_email,
+            scope: READ_ONLY_SCOPE,
+            aud: token_uri,
+            iat: now.timestamp(),
+            exp: (now + chrono::Duration::hours(1)).timestamp(),
+        };
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = key.private_key_id.clone();
+        let encoding_key = EncodingKey::from_rsa_pem(key.private_key.as_bytes())
+            .map_err(|e| ConnectorError::ConfigurationError(format!("Invalid service account key: {}", e)))?;
+        let assertion = jsonwebtoken::encode(&header, &claims, &encoding_key)
+            .map_err(|e| ConnectorError::AuthenticationError(format!("Failed to sign token request: {}", e)))?;
+
+        let response = self.client
+            .post(token_uri)
+            .form(&[
+                ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+                ("assertion", assertion.as_str()),
+            ])
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(ConnectorError::AuthenticationError(format!(
+                "Token exchange failed with status {}", response.status()
+            )));
+        }
+
+        let token: TokenResponse = response.json().await?;
+        let expires_at = now + chrono::Duration::seconds(token.expires_in);
+        *self.token.write().await = Some((token.access_token.clone(), expires_at));
+        Ok(Some(token.access_token))
+    }
+
+    /// GET a JSON API URL; `None` on 404
+    async fn get_json<T>(&self, url: &str, query: &[(&str, String)]) -> Result<Option<T>, ConnectorError>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let mut request = self.client.get(url).query(query);
+        if let Some(token) = self.access_token().await? {
+            request = request.bearer_auth(token);
+        }
+
+        let response = request.send().await?;
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(None),
+            StatusCode::UNAUTHORIZED => Err(ConnectorError::AuthenticationError(format!("GCS rejected credentials for {}", url))),
+            StatusCode::FORBIDDEN => Err(ConnectorError::PermissionError(format!("Access denied to {}", url))),
+            status if !status.is_success() => Err(ConnectorError::NetworkError(format!("GCS returned {} for {}", status, url))),
+            _ => Ok(Some(response.json().await?)),
+        }
+    }
+
+    /// List objects under `prefix`, following page tokens
+    async fn list_objects(&self, prefix: Option<&str>) -> Result<Vec<GcsObject>, ConnectorError> {
+        let url = self.objects_url(None);
+        let mut objects = Vec::new();
+        let mut page_token: Option<String> = None;
+
+        loop {
+            let mut query = vec![("maxResults", "1000".to_string())];
+            if let Some(prefix) = prefix {
+                query.push(("prefix", prefix.to_string()));
+            }
+            if let Some(token) = page_token.take() {
+                query.push(("pageToken", token));
+            }
+
+            let page: GcsObjectList = self.get_json(&url, &query).await?
+                .ok_or_else(|| ConnectorError::ConnectionError(format!("Bucket not found: {}", self.config.bucket)))?;
+            objects.extend(page.items);
+
+            page_token = page.next_page_token;
+            if page_token.is_none() {
+                break;
+            }
+        }
+
+        Ok(objects)
+    }
+
+    /// Convert GCS object to ExternalEntry
+    fn object_to_entry(&self, object: GcsObject) -> ExternalEntry {
+        let mut metadata = HashMap::new();
+        metadata.insert("bucket".to_string(), serde_json::Value::String(object.bucket.clone()));
+        metadata.insert("key".to_string(), serde_json::Value::String(object.name.clone()));
+        if let Some(etag) = object.etag {
+            metadata.insert("etag".to_string(), serde_json::Value::String(etag));
+        }
+        if let Some(md5) = object.md5_hash {
+            metadata.insert("md5_hash".to_string(), serde_json::Value::String(md5));
+        }
+        if let Some(generation) = object.generation {
+            metadata.insert("generation".to_string(), serde_json::Value::String(generation));
+        }
+        for (key, value) in object.metadata {
+            metadata.insert(format!("custom:{}", key), serde_json::Value::String(value));
+        }
+
+        ExternalEntry {
+            id: format!("gcs:{}:{}", object.bucket, object.name),
+            title: object.name.rsplit('/').next().unwrap_or(&object.name).to_string(),
+            description: None,
+            url: format!("gs://{}/{}", object.bucket, object.name),
+            content_type: object.content_type,
+            size: object.size.and_then(|s| s.parse().ok()),
+            modified_at: object.updated
+                .as_ref()
+                .and_then(|s| chrono::DateTime::parse_from_rfc3339(s).ok())
+                .map(|dt| dt.with_timezone(&chrono::Utc)),
+            tags: vec![],
+            metadata,
+            source_id: Uuid::new_v4(), // This would be the connector ID
+            source_type: "gcs".to_string(),
+        }
+    }
+
+    /// Generate a V4 signed GET URL for an object
+    fn generate_signed_url(&self, object: &str, expires_in_seconds: u32) -> Result<String, ConnectorError> {
+        let key = self.config.service_account_key.as_ref()
+            .ok_or_else(|| ConnectorError::ConfigurationError("Signed URLs require a service account key".to_string()))?;
+
+        signed_url(key, self.endpoint(), &self.config.bucket, object, expires_in_seconds, chrono::Utc::now())
+    }
+}
+
+/// Build a V4 (`GOOG4-RSA-SHA256`) signed GET URL valid for `expires_in_seconds` from `now`
+pub fn signed_url(
+    key: &GcsServiceAccountKey,
+    endpoint: &str,
+    bucket: &str,
+    object: &str,
+    expires_in_seconds: u32,
+    now: chrono::DateTime<chrono::Utc>,
+) -> Result<String, ConnectorError> {
+    if expires_in_seconds == 0 || expires_in_seconds > MAX_SIGNED_URL_SECONDS {
+        return Err(ConnectorError::ConfigurationError(format!(
+            "Signed URL expiry must be between 1 and {} seconds", MAX_SIGNED_URL_SECONDS
+        )));
+    }
+
+    let endpoint = url::Url::parse(endpoint)
+        .map_err(|e| ConnectorError::ConfigurationError(format!("Invalid GCS endpoint: {}", e)))?;
+    let host = match (endpoint.host_str(), endpoint.port()) {
+        (Some(host), Some(port)) => format!("{}:{}", host, port),
+        (Some(host), None) => host.to_string(),
+        (None, _) => return Err(ConnectorError::ConfigurationError("GCS endpoint has no host".to_string())),
+    };
+
+    let datetime = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let scope = format!("{}/auto/storage/goog4_request", now.format("%Y%m%d"));
+    let resource = format!(
+        "/{}/{}",
+        percent_encode(bucket),
+        object.split('/').map(percent_encode).collect::<Vec<_>>().join("/")
+    );
+
+    // Parameters must be sorted for the canonical request
+    let credential = format!("{}/{}", key.client_email, scope);
+    let query = [
+        ("X-Goog-Algorithm", "GOOG4-RSA-SHA256".to_string()),
+        ("X-Goog-Credential", credential),
+        ("X-Goog-Date", datetime.clone()),
+        ("X-Goog-Expires", expires_in_seconds.to_string()),
+        ("X-Goog-SignedHeaders", "host".to_string()),
+    ]
+    .iter()
+    .map(|(name, value)| format!("{}={}", name, percent_encode(value)))
+    .collect::<Vec<_>>()
+    .join("&");
+
+    let canonical_request = format!("GET\n{}\n{}\nhost:{}\n\nhost\nUNSIGNED-PAYLOAD", resource, query, host);
+    let string_to_sign = format!(
+        "GOOG4-RSA-SHA256\n{}\n{}\n{}",
+        datetime,
+        scope,
+        hex_encode(&Sha256::digest(canonical_request.as_bytes()))
+    );
+
+    let encoding_key = EncodingKey::from_rsa_pem(key.private_key.as_bytes())
+        .map_err(|e| ConnectorError::ConfigurationError(format!("Invalid service account key: {}", e)))?;
+    let signature = jsonwebtoken::crypto::sign(string_to_sign.as_bytes(), &encoding_key, Algorithm::RS256)
+        .map_err(|e| ConnectorError::AuthenticationError(format!("Failed to sign URL: {}", e)))?;
+    let signature = URL_SAFE_NO_PAD.decode(signature)
+        .map_err(|e| ConnectorError::AuthenticationError(format!("Malformed signature: {}", e)))?;
+
+    Ok(format!(
+        "{}://{}{}?{}&X-Goog-Signature={}",
+        endpoint.scheme(), host, resource, query, hex_encode(&signature)
+    ))
+}
+
+/// Percent-encode everything outside RFC 3986's unreserved set
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[async_trait]
+impl Connector for GcsConnector {
+    fn connector_type(&self) -> ConnectorType {
+        ConnectorType::Gcs
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn test_connection(&self) -> Result<(), ConnectorError> {
+        let query = [("maxResults", "1".to_string())];
+        self.get_json::<GcsObjectList>(&self.objects_url(None), &query).await?
+            .ok_or_else(|| ConnectorError::ConnectionError(format!("Bucket not found: {}", self.config.bucket)))?;
+
+        Ok(())
+    }
+
+    async fn list_entries(&self) -> Result<Vec<ExternalEntry>, ConnectorError> {
+        let objects = self.list_objects(self.config.prefix.as_deref()).await?;
+
+        Ok(objects
+            .into_iter()
+            // Skip directory placeholders
+            .filter(|object| !object.name.ends_with('/'))
+            .map(|object| self.object_to_entry(object))
+            .collect())
+    }
+
+    async fn get_entry(&self, id: &str) -> Result<Option<ExternalEntry>, ConnectorError> {
+        // Parse GCS ID format: gcs:bucket:object (object names may contain ':')
+        let parts: Vec<&str> = id.splitn(3, ':').collect();
+        if parts.len() != 3 || parts[0] != "gcs" {
+            return Err(ConnectorError::EntryNotFound(format!("Invalid GCS entry ID: {}", id)));
+        }
+
+        if parts[1] != self.config.bucket {
+            return Ok(None);
+        }
+
+        let object: Option<GcsObject> = self.get_json(&self.objects_url(Some(parts[2])), &[]).await?;
+        Ok(object.map(|object| self.object_to_entry(object)))
+    }
+
+    async fn get_presigned_url(&self, entry: &ExternalEntry, expires_in_seconds: u32) -> Result<String, ConnectorError> {
+        // Extract key from metadata
+        let key = entry.metadata.get("key")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ConnectorError::ConfigurationError("Missing key in entry metadata".to_string()))?;
+
+        self.generate_signed_url(key, expires_in_seconds)
+    }
+
+    async fn sync_entries(&self) -> Result<SyncResult, ConnectorError> {
+        let start_time = std::time::Instant::now();
+
+        // Test connection first
+        self.test_connection().await?;
+
+        // List all entries
+        let entries = self.list_entries().await?;
+
+        let duration = start_time.elapsed();
+
+        Ok(SyncResult {
+            entries_processed: entries.len() as u64,
+            entries_added: entries.len() as u64,
+            entries_updated: 0,
+            entries_removed: 0,
+            errors: vec![],
+            duration_seconds: duration.as_secs_f64(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use jsonwebtoken::DecodingKey;
+
+    fn test_key() -> GcsServiceAccountKey {
+        GcsServiceAccountKey {
+            client_email: "federation@test-project.iam.gserviceaccount.com".to_string(),
+            private_key: include_str!("../testdata/gcs-test-key.pem").to_string(),
+            private_key_id: Some("test-key-id".to_string()),
+            token_uri: None,
+        }
+    }
+
+    fn query_param(url: &str, name: &str) -> String {
+        url::Url::parse(url).unwrap()
+            .query_pairs()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.into_owned())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_gcs_connector_config() {
+        let config: GcsConnectorConfig = serde_json::from_value(serde_json::json!({
+            "bucket": "test-bucket",
+            "prefix": "data/",
+        })).unwrap();
+
+        assert_eq!(config.bucket, "test-bucket");
+        assert!(config.service_account_key.is_none());
+
+        let connector = GcsConnector::new("test".to_string(), config).unwrap();
+        assert_eq!(connector.objects_url(Some("data/a b.csv")), "https://storage.googleapis.com/storage/v1/b/test-bucket/o/data%2Fa%20b.csv");
+    }
+
+    #[test]
+    fn test_invalid_service_account_key_is_rejected() {
+        let config = GcsConnectorConfig {
+            bucket: "test-bucket".to_string(),
+            prefix: None,
+            service_account_key: Some(GcsServiceAccountKey { private_key: "not a key".to_string(), ..test_key() }),
+            endpoint: None,
+        };
+
+        assert!(matches!(GcsConnector::new("test".to_string(), config), Err(ConnectorError::ConfigurationError(_))));
+    }
+
+    #[test]
+    fn test_object_to_entry() {
+        let connector = GcsConnector::new("test".to_string(), GcsConnectorConfig {
+            bucket: "test-bucket".to_string(),
+            prefix: None,
+            service_account_key: None,
+            endpoint: None,
+        }).unwrap();
+        let object: GcsObject = serde_json::from_value(serde_json::json!({
+            "name": "data/2024/file.csv",
+            "bucket": "test-bucket",
+            "contentType": "text/csv",
+            "size": "1234",
+            "updated": "2024-01-15T10:00:00.000Z",
+            "etag": "CJv8",
+            "md5Hash": "XrY7u+Ae7tCTyyK7j1rNww==",
+            "metadata": { "owner": "lab" },
+        })).unwrap();
+
+        let entry = connector.object_to_entry(object);
+        assert_eq!(entry.id, "gcs:test-bucket:data/2024/file.csv");
+        assert_eq!(entry.title, "file.csv");
+        assert_eq!(entry.size, Some(1234));
+        assert_eq!(entry.content_type.as_deref(), Some("text/csv"));
+        assert!(entry.modified_at.is_some());
+        assert_eq!(entry.metadata["key"], "data/2024/file.csv");
+        assert_eq!(entry.metadata["custom:owner"], "lab");
+    }
+
+    #[test]
+    fn test_signed_url_is_verifiable_v4_signature() {
+        let now = chrono::Utc.with_ymd_and_hms(2024, 1, 15, 10, 0, 0).unwrap();
+        let url = signed_url(&test_key(), DEFAULT_GCS_ENDPOINT, "test-bucket", "data/a b.csv", 900, now).unwrap();
+
+        assert!(url.starts_with("https://storage.googleapis.com/test-bucket/data/a%20b.csv?X-Goog-Algorithm=GOOG4-RSA-SHA256&"));
+        assert_eq!(
+            query_param(&url, "X-Goog-Credential"),
+            "federation@test-project.iam.gserviceaccount.com/20240115/auto/storage/goog4_request"
+        );
+        assert_eq!(query_param(&url, "X-Goog-Date"), "20240115T100000Z");
+        assert_eq!(query_param(&url, "X-Goog-Expires"), "900");
+
+        // Rebuild the string to sign and check the signature against the public key
+        let (unsigned, signature) = url.split_once("&X-Goog-Signature=").unwrap();
+        let query = unsigned.split_once('?').unwrap().1;
+        let canonical_request = format!(
+            "GET\n/test-bucket/data/a%20b.csv\n{}\nhost:storage.googleapis.com\n\nhost\nUNSIGNED-PAYLOAD",
+            query
+        );
+        let string_to_sign = format!(
+            "GOOG4-RSA-SHA256\n20240115T100000Z\n20240115/auto/storage/goog4_request\n{}",
+            hex_encode(&Sha256::digest(canonical_request.as_bytes()))
+        );
+        let signature_bytes: Vec<u8> = (0..signature.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&signature[i..i + 2], 16).unwrap())
+            .collect();
+        let public_key = DecodingKey::from_rsa_pem(include_bytes!("../testdata/gcs-test-key.pub.pem")).unwrap();
+
+        assert!(jsonwebtoken::crypto::verify(
+            &URL_SAFE_NO_PAD.encode(signature_bytes),
+            string_to_sign.as_bytes(),
+            &public_key,
+            Algorithm::RS256,
+        ).unwrap());
+    }
+
+    #[test]
+    fn test_signed_url_expiry_limits() {
+        let now = chrono::Utc::now();
+        assert!(signed_url(&test_key(), DEFAULT_GCS_ENDPOINT, "b", "o", 0, now).is_err());
+        assert!(signed_url(&test_key(), DEFAULT_GCS_ENDPOINT, "b", "o", MAX_SIGNED_URL_SECONDS + 1, now).is_err());
+        assert!(signed_url(&test_key(), "http://localhost:4443", "b", "o", MAX_SIGNED_URL_SECONDS, now)
+            .unwrap()
+            .starts_with("http://localhost:4443/b/o?"));
+    }
+
+    #[test]
+    fn test_gcs_entry_id_parsing() {
+        let id = "gcs:my-bucket:path/to/file:v2.csv";
+        let parts: Vec<&str> = id.splitn(3, ':').collect();
+
+        assert_eq!(parts, vec!["gcs", "my-bucket", "path/to/file:v2.csv"]);
+    }
+
+    /// Runs against fake-gcs-server, e.g.
+    /// `docker run -p 4443:4443 fsouza/fake-gcs-server -scheme http`,
+    /// with `GCS_EMULATOR_HOST=http://localhost:4443` and `--features gcs-emulator-tests`.
+    #[cfg(feature = "gcs-emulator-tests")]
+    mod emulator {
+        use super::*;
+
+        async fn seeded_connector(prefix: Option<&str>) -> (GcsConnector, String) {
+            let endpoint = std::env::var("GCS_EMULATOR_HOST").unwrap_or_else(|_| "http://localhost:4443".to_string());
+            let bucket = format!("blacklake-test-{}", Uuid::new_v4());
+            let client = Client::new();
+
+            client.post(format!("{}/storage/v1/b", endpoint))
+                .json(&serde_json::json!({ "name": bucket }))
+                .send().await.unwrap()
+                .error_for_status().unwrap();
+            for (name, body) in [("data/a.csv", "a,b\n1,2\n"), ("data/nested/b.json", "{}"), ("other/c.txt", "c")] {
+                client.post(format!("{}/upload/storage/v1/b/{}/o", endpoint, bucket))
+                    .query(&[("uploadType", "media"), ("name", name)])
+                    .header("Content-Type", if name.ends_with(".csv") { "text/csv" } else { "application/octet-stream" })
+                    .body(body)
+                    .send().await.unwrap()
+                    .error_for_status().unwrap();
+            }
+
+            let connector = GcsConnector::new("emulator".to_string(), GcsConnectorConfig {
+                bucket: bucket.clone(),
+                prefix: prefix.map(str::to_string),
+                service_account_key: None,
+                endpoint: Some(endpoint),
+            }).unwrap();
+            (connector, bucket)
+        }
+
+        #[tokio::test]
+        async fn test_list_entries_with_prefix() {
+            let (connector, bucket) = seeded_connector(Some("data/")).await;
+            connector.test_connection().await.unwrap();
+
+            let mut ids: Vec<String> = connector.list_entries().await.unwrap().into_iter().map(|e| e.id).collect();
+            ids.sort();
+            assert_eq!(ids, vec![
+                format!("gcs:{}:data/a.csv", bucket),
+                format!("gcs:{}:data/nested/b.json", bucket),
+            ]);
+        }
+
+        #[tokio::test]
+        async fn test_get_entry_metadata() {
+            let (connector, bucket) = seeded_connector(None).await;
+
+            let entry = connector.get_entry(&format!("gcs:{}:data/a.csv", bucket)).await.unwrap().unwrap();
+            assert_eq!(entry.title, "a.csv");
+            assert_eq!(entry.size, Some(8));
+            assert_eq!(entry.content_type.as_deref(), Some("text/csv"));
+            assert!(entry.metadata.contains_key("md5_hash"));
+
+            assert!(connector.get_entry(&format!("gcs:{}:missing.csv", bucket)).await.unwrap().is_none());
+            assert!(connector.get_entry("gcs:other-bucket:data/a.csv").await.unwrap().is_none());
+        }
+    }
+}
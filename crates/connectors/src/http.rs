@@ -0,0 +1,630 @@
+// HTTP(S) connector for catalogs published on plain web servers
+// Week 8: Federation across data sources
+
+use super::traits::*;
+use async_trait::async_trait;
+use reqwest::{header, Client, RequestBuilder, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tokio::sync::RwLock;
+use url::Url;
+use uuid::Uuid;
+
+/// HTTP connector configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpConnectorConfig {
+    pub base_url: String,
+    /// Manifest location, relative to `base_url`
+    pub manifest_path: String,
+    /// Manifest format; inferred from the path or Content-Type when omitted
+    pub manifest_format: Option<ManifestFormat>,
+    pub auth: Option<HttpAuth>,
+}
+
+/// Manifest formats the connector can read
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ManifestFormat {
+    Json,
+    Csv,
+}
+
+/// Credentials sent with every request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum HttpAuth {
+    Basic { username: String, password: Option<String> },
+    Bearer { token: String },
+}
+
+/// One file listed in the manifest.
+///
+/// JSON manifests are either an array of these or `{"entries": [...]}`; CSV
+/// manifests use the same names as columns, with `tags` separated by `;`.
+#[derive(Debug, Clone, Deserialize)]
+struct ManifestItem {
+    /// Relative to `base_url`, or an absolute URL
+    #[serde(alias = "url")]
+    path: String,
+    title: Option<String>,
+    description: Option<String>,
+    content_type: Option<String>,
+    size: Option<u64>,
+    modified_at: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+/// CSV row, before `tags` is split
+#[derive(Debug, Deserialize)]
+struct ManifestRow {
+    #[serde(alias = "url")]
+    path: String,
+    title: Option<String>,
+    description: Option<String>,
+    content_type: Option<String>,
+    size: Option<u64>,
+    modified_at: Option<String>,
+    tags: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum JsonManifest {
+    Items(Vec<ManifestItem>),
+    Wrapped { entries: Vec<ManifestItem> },
+}
+
+/// Cache validators returned by the server for a URL
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Validators {
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+impl Validators {
+    fn from_response(response: &Response) -> Self {
+        let header = |name| {
+            response.headers().get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        };
+
+        Self {
+            etag: header(header::ETAG),
+            last_modified: header(header::LAST_MODIFIED),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+
+    /// Add `If-None-Match` / `If-Modified-Since` for a conditional request
+    fn apply(&self, mut request: RequestBuilder) -> RequestBuilder {
+        if let Some(ref etag) = self.etag {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+        if let Some(ref last_modified) = self.last_modified {
+            request = request.header(header::IF_MODIFIED_SINCE, last_modified);
+        }
+        request
+    }
+}
+
+/// HTTP connector implementation
+pub struct HttpConnector {
+    config: HttpConnectorConfig,
+    base_url: Url,
+    client: Client,
+    name: String,
+    /// Last manifest fetched, reused when the server answers 304
+    manifest: RwLock<Option<(Validators, Vec<ManifestItem>)>>,
+    /// Validators per entry ID as of the last sync
+    entry_validators: RwLock<HashMap<String, Validators>>,
+}
+
+impl HttpConnector {
+    pub fn new(name: String, config: HttpConnectorConfig) -> Result<Self, ConnectorError> {
+        // A trailing slash makes relative paths resolve inside the base path
+        let mut base_url = config.base_url.clone();
+        if !base_url.ends_with('/') {
+            base_url.push('/');
+        }
+        let base_url = Url::parse(&base_url)
+            .map_err(|e| ConnectorError::ConfigurationError(format!("Invalid base URL: {}", e)))?;
+        if !matches!(base_url.scheme(), "http" | "https") {
+            return Err(ConnectorError::ConfigurationError(format!(
+                "Unsupported URL scheme: {}", base_url.scheme()
+            )));
+        }
+
+        Ok(Self {
+            config,
+            base_url,
+            client: Client::new(),
+            name,
+            manifest: RwLock::new(None),
+            entry_validators: RwLock::new(HashMap::new()),
+        })
+    }
+
+    /// Resolve a manifest path against the base URL
+    fn resolve(&self, path: &str) -> Result<Url, ConnectorError> {
+        self.base_url.join(path)
+            .map_err(|e| ConnectorError::ConfigurationError(format!("Invalid path {}: {}", path, e)))
+    }
+
+    /// Attach configured credentials
+    fn authorize(&self, request: RequestBuilder) -> RequestBuilder {
+        match self.config.auth {
+            Some(HttpAuth::Basic { ref username, ref password }) => request.basic_auth(username, password.as_ref()),
+            Some(HttpAuth::Bearer { ref token }) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    /// Map error statuses; 304 passes through for conditional requests
+    fn check_status(response: Response, url: &Url) -> Result<Response, ConnectorError> {
+        match response.status() {
+            StatusCode::UNAUTHORIZED => Err(ConnectorError::AuthenticationError(format!("Server rejected credentials for {}", url))),
+            StatusCode::FORBIDDEN => Err(ConnectorError::PermissionError(format!("Access denied to {}", url))),
+            StatusCode::NOT_FOUND => Err(ConnectorError::EntryNotFound(url.to_string())),
+            status if status.is_success() || status == StatusCode::NOT_MODIFIED => Ok(response),
+            status => Err(ConnectorError::NetworkError(format!("{} returned {}", url, status))),
+        }
+    }
+
+    /// Fetch the manifest, revalidating any cached copy
+    async fn fetch_manifest(&self) -> Result<Vec<ManifestItem>, ConnectorError> {
+        let url = self.resolve(&self.config.manifest_path)?;
+
+        let mut request = self.authorize(self.client.get(url.clone()));
+        if let Some((ref validators, _)) = *self.manifest.read().await {
+            request = validators.apply(request);
+        }
+
+        let response = Self::check_status(request.send().await?, &url)?;
+        if response.status() == StatusCode::NOT_MODIFIED {
+            if let Some((_, ref items)) = *self.manifest.read().await {
+                return Ok(items.clone());
+            }
+        }
+
+        let validators = Validators::from_response(&response);
+        let format = self.config.manifest_format.unwrap_or_else(|| {
+            let is_csv = url.path().ends_with(".csv")
+                || response.headers().get(header::CONTENT_TYPE)
+                    .and_then(|v| v.to_str().ok())
+                    .is_some_and(|ct| ct.starts_with("text/csv"));
+            if is_csv { ManifestFormat::Csv } else { ManifestFormat::Json }
+        });
+        let items = parse_manifest(&response.bytes().await?, format)?;
+
+        if !validators.is_empty() {
+            *self.manifest.write().await = Some((validators, items.clone()));
+        }
+        Ok(items)
+    }
+
+    /// Convert manifest item to ExternalEntry
+    fn item_to_entry(&self, item: ManifestItem) -> Result<ExternalEntry, ConnectorError> {
+        let url = self.resolve(&item.path)?;
+
+        let mut metadata = HashMap::new();
+        metadata.insert("path".to_string(), serde_json::Value::String(item.path.clone()));
+
+        let title = item.title.unwrap_or_else(|| {
+            url.path_segments()
+                .and_then(|mut segments| segments.next_back())
+                .filter(|segment| !segment.is_empty())
+                .unwrap_or(&item.path)
+                .to_string()
+        });
+
+        Ok(ExternalEntry {
+            id: format!("http:{}", url),
+            title,
+            description: item.description,
+            url: url.to_string(),
+            content_type: item.content_type,
+            size: item.size,
+            modified_at: item.modified_at.as_deref().and_then(parse_timestamp),
+            tags: item.tags,
+            metadata,
+            source_id: Uuid::new_v4(), // This would be the connector ID
+            source_type: "http".to_string(),
+        })
+    }
+
+    /// HEAD an entry; `None` when it is unchanged since `previous`
+    async fn revalidate(&self, url: &str, previous: Option<&Validators>) -> Result<Option<Response>, ConnectorError> {
+        let url = Url::parse(url)
+            .map_err(|e| ConnectorError::ConfigurationError(format!("Invalid entry URL: {}", e)))?;
+
+        let mut request = self.authorize(self.client.head(url.clone()));
+        if let Some(previous) = previous {
+            request = previous.apply(request);
+        }
+
+        let response = Self::check_status(request.send().await?, &url)?;
+        if response.status() == StatusCode::NOT_MODIFIED {
+            return Ok(None);
+        }
+
+        // Servers that ignore conditional headers still return the same validators
+        let validators = Validators::from_response(&response);
+        if previous.is_some_and(|previous| !validators.is_empty() && *previous == validators) {
+            return Ok(None);
+        }
+
+        Ok(Some(response))
+    }
+
+    /// Download an entry's content
+    pub async fn fetch_entry(&self, entry: &ExternalEntry) -> Result<Vec<u8>, ConnectorError> {
+        let url = Url::parse(&entry.url)
+            .map_err(|e| ConnectorError::ConfigurationError(format!("Invalid entry URL: {}", e)))?;
+
+        let response = self.authorize(self.client.get(url.clone())).send().await?;
+        let response = Self::check_status(response, &url)?;
+
+        Ok(response.bytes().await?.to_vec())
+    }
+}
+
+/// Parse a manifest body
+fn parse_manifest(body: &[u8], format: ManifestFormat) -> Result<Vec<ManifestItem>, ConnectorError> {
+    match format {
+        ManifestFormat::Json => match serde_json::from_slice(body)? {
+            JsonManifest::Items(items) | JsonManifest::Wrapped { entries: items } => Ok(items),
+        },
+        ManifestFormat::Csv => {
+            let mut reader = csv::Reader::from_reader(body);
+            reader.deserialize::<ManifestRow>()
+                .map(|row| {
+                    let row = row.map_err(|e| ConnectorError::SyncError(format!("Invalid manifest row: {}", e)))?;
+                    Ok(ManifestItem {
+                        path: row.path,
+                        title: row.title.filter(|s| !s.is_empty()),
+                        description: row.description.filter(|s| !s.is_empty()),
+                        content_type: row.content_type.filter(|s| !s.is_empty()),
+                        size: row.size,
+                        modified_at: row.modified_at.filter(|s| !s.is_empty()),
+                        tags: row.tags
+                            .map(|tags| tags.split(';').map(str::trim).filter(|t| !t.is_empty()).map(str::to_string).collect())
+                            .unwrap_or_default(),
+                    })
+                })
+                .collect()
+        }
+    }
+}
+
+/// Accept RFC 3339 from manifests and HTTP-dates from `Last-Modified`
+fn parse_timestamp(value: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    chrono::DateTime::parse_from_rfc3339(value)
+        .or_else(|_| chrono::DateTime::parse_from_rfc2822(value))
+        .ok()
+        .map(|dt| dt.with_timezone(&chrono::Utc))
+}
+
+#[async_trait]
+impl Connector for HttpConnector {
+    fn connector_type(&self) -> ConnectorType {
+        ConnectorType::Http
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn test_connection(&self) -> Result<(), ConnectorError> {
+        self.fetch_manifest().await?;
+        Ok(())
+    }
+
+    async fn list_entries(&self) -> Result<Vec<ExternalEntry>, ConnectorError> {
+        let validators = self.entry_validators.read().await;
+
+        self.fetch_manifest().await?
+            .into_iter()
+            .map(|item| {
+                let mut entry = self.item_to_entry(item)?;
+                if let Some(known) = validators.get(&entry.id) {
+                    if let Some(ref etag) = known.etag {
+                        entry.metadata.insert("etag".to_string(), serde_json::Value::String(etag.clone()));
+                    }
+                    if let Some(ref last_modified) = known.last_modified {
+                        entry.metadata.insert("last_modified".to_string(), serde_json::Value::String(last_modified.clone()));
+                    }
+                }
+                Ok(entry)
+            })
+            .collect()
+    }
+
+    async fn get_entry(&self, id: &str) -> Result<Option<ExternalEntry>, ConnectorError> {
+        if !id.starts_with("http:") {
+            return Err(ConnectorError::EntryNotFound(format!("Invalid HTTP entry ID: {}", id)));
+        }
+
+        let mut entry = match self.list_entries().await?.into_iter().find(|e| e.id == id) {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+
+        // Fill in whatever the manifest left out from the file's own headers
+        let response = match self.revalidate(&entry.url, None).await {
+            Ok(Some(response)) => response,
+            Ok(None) => return Ok(Some(entry)),
+            Err(ConnectorError::EntryNotFound(_)) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let header = |name| {
+            response.headers().get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        };
+
+        if entry.content_type.is_none() {
+            entry.content_type = header(header::CONTENT_TYPE);
+        }
+        if entry.size.is_none() {
+            entry.size = header(header::CONTENT_LENGTH).and_then(|s| s.parse().ok());
+        }
+        if entry.modified_at.is_none() {
+            entry.modified_at = header(header::LAST_MODIFIED).as_deref().and_then(parse_timestamp);
+        }
+        let validators = Validators::from_response(&response);
+        if let Some(etag) = validators.etag {
+            entry.metadata.insert("etag".to_string(), serde_json::Value::String(etag));
+        }
+        if let Some(last_modified) = validators.last_modified {
+            entry.metadata.insert("last_modified".to_string(), serde_json::Value::String(last_modified));
+        }
+
+        Ok(Some(entry))
+    }
+
+    async fn get_presigned_url(&self, entry: &ExternalEntry, _expires_in_seconds: u32) -> Result<String, ConnectorError> {
+        // Plain web servers have no URL signing; only public sources can hand out links
+        if self.config.auth.is_some() {
+            return Err(ConnectorError::PermissionError(
+                "Entries behind authentication must be fetched through the connector".to_string()
+            ));
+        }
+
+        Ok(entry.url.clone())
+    }
+
+    async fn sync_entries(&self) -> Result<SyncResult, ConnectorError> {
+        let start_time = std::time::Instant::now();
+
+        let entries = self.list_entries().await?;
+        let previous = self.entry_validators.read().await.clone();
+
+        let mut current = HashMap::new();
+        let mut added = 0;
+        let mut updated = 0;
+        let mut errors = Vec::new();
+
+        for entry in &entries {
+            let known = previous.get(&entry.id);
+            match self.revalidate(&entry.url, known).await {
+                Ok(Some(response)) => {
+                    if known.is_some() {
+                        updated += 1;
+                    } else {
+                        added += 1;
+                    }
+                    current.insert(entry.id.clone(), Validators::from_response(&response));
+                }
+                Ok(None) => {
+                    current.insert(entry.id.clone(), known.cloned().unwrap_or_default());
+                }
+                Err(e) => {
+                    errors.push(format!("{}: {}", entry.url, e));
+                    if let Some(known) = known {
+                        current.insert(entry.id.clone(), known.clone());
+                    }
+                }
+            }
+        }
+
+        let removed = previous.keys().filter(|id| !entries.iter().any(|e| &e.id == *id)).count();
+        *self.entry_validators.write().await = current;
+
+        let duration = start_time.elapsed();
+
+        Ok(SyncResult {
+            entries_processed: entries.len() as u64,
+            entries_added: added,
+            entries_updated: updated,
+            entries_removed: removed as u64,
+            errors,
+            duration_seconds: duration.as_secs_f64(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        extract::{Path, State},
+        http::{HeaderMap, StatusCode as AxumStatus},
+        response::IntoResponse,
+        routing::get,
+        Router,
+    };
+    use std::sync::{Arc, Mutex};
+
+    const TOKEN: &str = "secret-token";
+
+    /// Files served by the mock server: path -> (etag, body)
+    type Files = Arc<Mutex<HashMap<String, (String, String)>>>;
+
+    async fn serve_file(State(files): State<Files>, Path(path): Path<String>, headers: HeaderMap) -> axum::response::Response {
+        if headers.get("authorization").and_then(|v| v.to_str().ok()) != Some(&format!("Bearer {}", TOKEN)) {
+            return AxumStatus::UNAUTHORIZED.into_response();
+        }
+
+        let (etag, body) = match files.lock().unwrap().get(&path) {
+            Some(file) => file.clone(),
+            None => return AxumStatus::NOT_FOUND.into_response(),
+        };
+        let content_type = if path.ends_with(".csv") { "text/csv" } else { "application/json" };
+        let validators = [
+            ("etag", etag.clone()),
+            ("last-modified", "Mon, 15 Jan 2024 10:00:00 GMT".to_string()),
+            ("content-type", content_type.to_string()),
+        ];
+
+        if headers.get("if-none-match").and_then(|v| v.to_str().ok()) == Some(etag.as_str()) {
+            return (AxumStatus::NOT_MODIFIED, validators).into_response();
+        }
+        (validators, body).into_response()
+    }
+
+    async fn start_server(files: Files) -> String {
+        let app = Router::new()
+            .route("/catalog/*path", get(serve_file))
+            .with_state(files);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{}/catalog", addr)
+    }
+
+    fn catalog() -> Files {
+        let manifest = serde_json::json!({
+            "entries": [
+                { "path": "files/a.csv", "title": "Sample A", "size": 8, "tags": ["lab"] },
+                { "path": "files/b.json", "modified_at": "2024-01-15T10:00:00Z" },
+            ]
+        });
+        let csv_manifest = "path,title,description,content_type,size,modified_at,tags\n\
+                            files/a.csv,Sample A,,text/csv,8,,lab; raw\n";
+
+        Arc::new(Mutex::new(HashMap::from([
+            ("manifest.json".to_string(), ("\"m1\"".to_string(), manifest.to_string())),
+            ("manifest.csv".to_string(), ("\"c1\"".to_string(), csv_manifest.to_string())),
+            ("files/a.csv".to_string(), ("\"a1\"".to_string(), "a,b\n1,2\n".to_string())),
+            ("files/b.json".to_string(), ("\"b1\"".to_string(), "{}".to_string())),
+        ])))
+    }
+
+    fn connector(base_url: String, manifest_path: &str, auth: Option<HttpAuth>) -> HttpConnector {
+        HttpConnector::new("http".to_string(), HttpConnectorConfig {
+            base_url,
+            manifest_path: manifest_path.to_string(),
+            manifest_format: None,
+            auth,
+        }).unwrap()
+    }
+
+    fn bearer() -> Option<HttpAuth> {
+        Some(HttpAuth::Bearer { token: TOKEN.to_string() })
+    }
+
+    #[test]
+    fn test_http_connector_config() {
+        let config: HttpConnectorConfig = serde_json::from_value(serde_json::json!({
+            "base_url": "https://data.example.org/catalog",
+            "manifest_path": "manifest.csv",
+            "auth": { "type": "basic", "username": "reader", "password": "pw" },
+        })).unwrap();
+        assert!(matches!(config.auth, Some(HttpAuth::Basic { .. })));
+
+        let connector = HttpConnector::new("test".to_string(), config).unwrap();
+        assert_eq!(connector.resolve("files/a.csv").unwrap().as_str(), "https://data.example.org/catalog/files/a.csv");
+        assert_eq!(connector.resolve("https://mirror.example.org/b.csv").unwrap().as_str(), "https://mirror.example.org/b.csv");
+
+        let invalid = HttpConnectorConfig {
+            base_url: "ftp://data.example.org".to_string(),
+            manifest_path: "manifest.json".to_string(),
+            manifest_format: None,
+            auth: None,
+        };
+        assert!(matches!(HttpConnector::new("test".to_string(), invalid), Err(ConnectorError::ConfigurationError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_list_entries_from_json_and_csv_manifests() {
+        let base_url = start_server(catalog()).await;
+
+        let entries = connector(base_url.clone(), "manifest.json", bearer()).list_entries().await.unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].id, format!("http:{}/files/a.csv", base_url));
+        assert_eq!(entries[0].title, "Sample A");
+        assert_eq!(entries[0].size, Some(8));
+        assert_eq!(entries[0].tags, vec!["lab"]);
+        assert_eq!(entries[1].title, "b.json");
+        assert!(entries[1].modified_at.is_some());
+
+        let entries = connector(base_url, "manifest.csv", bearer()).list_entries().await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].content_type.as_deref(), Some("text/csv"));
+        assert!(entries[0].description.is_none());
+        assert_eq!(entries[0].tags, vec!["lab", "raw"]);
+    }
+
+    #[tokio::test]
+    async fn test_auth_is_required_and_sent() {
+        let base_url = start_server(catalog()).await;
+
+        let result = connector(base_url.clone(), "manifest.json", None).test_connection().await;
+        assert!(matches!(result, Err(ConnectorError::AuthenticationError(_))));
+
+        let connector = connector(base_url, "manifest.json", bearer());
+        connector.test_connection().await.unwrap();
+
+        let entry = connector.list_entries().await.unwrap().remove(0);
+        assert_eq!(connector.fetch_entry(&entry).await.unwrap(), b"a,b\n1,2\n");
+        assert!(matches!(connector.get_presigned_url(&entry, 60).await, Err(ConnectorError::PermissionError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_get_entry_fills_metadata_from_headers() {
+        let base_url = start_server(catalog()).await;
+        let connector = connector(base_url.clone(), "manifest.json", bearer());
+
+        let entry = connector.get_entry(&format!("http:{}/files/b.json", base_url)).await.unwrap().unwrap();
+        assert_eq!(entry.content_type.as_deref(), Some("application/json"));
+        assert_eq!(entry.metadata["etag"], "\"b1\"");
+        assert_eq!(entry.metadata["last_modified"], "Mon, 15 Jan 2024 10:00:00 GMT");
+
+        assert!(connector.get_entry(&format!("http:{}/files/missing.csv", base_url)).await.unwrap().is_none());
+        assert!(connector.get_entry("s3:bucket:key").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sync_detects_changes_with_etags() {
+        let files = catalog();
+        let base_url = start_server(files.clone()).await;
+        let connector = connector(base_url, "manifest.json", bearer());
+
+        let result = connector.sync_entries().await.unwrap();
+        assert_eq!((result.entries_added, result.entries_updated, result.entries_removed), (2, 0, 0));
+
+        let result = connector.sync_entries().await.unwrap();
+        assert_eq!((result.entries_added, result.entries_updated, result.entries_removed), (0, 0, 0));
+        assert!(result.errors.is_empty());
+
+        // Change one file and drop the other from the manifest
+        {
+            let mut files = files.lock().unwrap();
+            files.insert("files/a.csv".to_string(), ("\"a2\"".to_string(), "a,b\n3,4\n".to_string()));
+            let manifest = serde_json::json!([{ "path": "files/a.csv" }]);
+            files.insert("manifest.json".to_string(), ("\"m2\"".to_string(), manifest.to_string()));
+        }
+
+        let result = connector.sync_entries().await.unwrap();
+        assert_eq!(result.entries_processed, 1);
+        assert_eq!((result.entries_added, result.entries_updated, result.entries_removed), (0, 1, 1));
+
+        let entries = connector.list_entries().await.unwrap();
+        assert_eq!(entries[0].metadata["etag"], "\"a2\"");
+    }
+}
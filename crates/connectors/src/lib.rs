@@ -5,7 +5,13 @@ pub mod traits;
 pub mod s3;
 pub mod postgres;
 pub mod ckan;
+pub mod gcs;
+pub mod http;
 pub mod manager;
+pub mod sync;
+pub mod breaker;
 
 pub use traits::{Connector, ConnectorRegistry, ConnectorType, ConnectorConfig, ConnectorStatus, ConnectorError};
 pub use manager::ConnectorManager;
+pub use breaker::{BreakerConfig, CircuitState};
+pub use sync::{SyncReport, SyncStateStore, PostgresSyncStateStore};
@@ -5,9 +5,15 @@ use super::traits::*;
 use super::s3::S3Connector;
 use super::postgres::PostgresConnector;
 use super::ckan::CkanConnector;
+use super::gcs::GcsConnector;
+use super::http::HttpConnector;
+use super::sync::{diff_entries, InMemorySyncStateStore, ObjectState, SyncReport, SyncStateStore};
+use super::breaker::{BreakerConfig, CircuitBreaker, CircuitState};
 use async_trait::async_trait;
 use std::collections::HashMap;
+use std::future::Future;
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
@@ -16,6 +22,9 @@ pub struct ConnectorManager {
     connectors: Arc<RwLock<HashMap<Uuid, Arc<dyn Connector>>>>,
     configs: Arc<RwLock<HashMap<Uuid, ConnectorConfig>>>,
     statuses: Arc<RwLock<HashMap<Uuid, ConnectorStatus>>>,
+    sync_state: Arc<dyn SyncStateStore>,
+    breakers: Arc<RwLock<HashMap<Uuid, CircuitBreaker>>>,
+    breaker_config: BreakerConfig,
 }
 
 impl ConnectorManager {
@@ -24,9 +33,127 @@ impl ConnectorManager {
             connectors: Arc::new(RwLock::new(HashMap::new())),
             configs: Arc::new(RwLock::new(HashMap::new())),
             statuses: Arc::new(RwLock::new(HashMap::new())),
+            sync_state: Arc::new(InMemorySyncStateStore::new()),
+            breakers: Arc::new(RwLock::new(HashMap::new())),
+            breaker_config: BreakerConfig::from_env(),
         }
     }
     
+    /// Persist incremental sync state in `store` instead of memory
+    pub fn with_sync_state(mut self, store: Arc<dyn SyncStateStore>) -> Self {
+        self.sync_state = store;
+        self
+    }
+    
+    /// Open connector circuits after `config.failure_threshold` failures instead
+    /// of the `CONNECTOR_BREAKER_*` settings
+    pub fn with_breaker_config(mut self, config: BreakerConfig) -> Self {
+        self.breaker_config = config;
+        self
+    }
+    
+    /// Run `op` against a connector behind its circuit breaker.
+    ///
+    /// While the circuit is open the call is refused straight away with
+    /// `CircuitOpen`, so one unreachable source cannot stall operations that
+    /// walk every connector. Calls are cut off after the configured timeout,
+    /// and the outcome is recorded in the connector's status.
+    pub async fn call<T, F, Fut>(&self, id: Uuid, op: F) -> Result<T, ConnectorError>
+    where
+        F: FnOnce(Arc<dyn Connector>) -> Fut,
+        Fut: Future<Output = Result<T, ConnectorError>>,
+    {
+        let connector = self.connectors.read().await.get(&id).cloned()
+            .ok_or_else(|| ConnectorError::EntryNotFound(format!("Connector {} not found", id)))?;
+        
+        {
+            let mut breakers = self.breakers.write().await;
+            let breaker = breakers.entry(id).or_insert_with(|| CircuitBreaker::new(self.breaker_config));
+            let now = Instant::now();
+            if !breaker.try_acquire(now) {
+                let retry_after = breaker.retry_after(now).unwrap_or_default();
+                return Err(ConnectorError::CircuitOpen(format!(
+                    "connector {} is failing; retrying in {}s",
+                    connector.name(),
+                    retry_after.as_secs().max(1)
+                )));
+            }
+        }
+        
+        let result = match tokio::time::timeout(self.breaker_config.call_timeout, op(connector)).await {
+            Ok(result) => result,
+            Err(_) => Err(ConnectorError::NetworkError(format!(
+                "call timed out after {}s",
+                self.breaker_config.call_timeout.as_secs()
+            ))),
+        };
+        self.record_outcome(id, result.as_ref().err()).await;
+        result
+    }
+    
+    /// Feed a call's outcome to the connector's breaker and mirror it in its status
+    async fn record_outcome(&self, id: Uuid, error: Option<&ConnectorError>) {
+        let (circuit, consecutive_failures) = {
+            let mut breakers = self.breakers.write().await;
+            let breaker = breakers.entry(id).or_insert_with(|| CircuitBreaker::new(self.breaker_config));
+            match error {
+                Some(e) if e.trips_breaker() => breaker.record_failure(Instant::now()),
+                _ => breaker.record_success(),
+            }
+            (breaker.state(), breaker.consecutive_failures())
+        };
+        
+        if let Some(mut status) = self.get_status(id).await {
+            status.circuit = circuit;
+            status.consecutive_failures = consecutive_failures;
+            if let Some(e) = error {
+                status.last_error = Some(e.to_string());
+            }
+            self.update_status(id, status).await;
+        }
+    }
+    
+    /// Diff a connector's listing against its stored state and record the result
+    pub async fn sync(&self, id: Uuid) -> Result<SyncReport, ConnectorError> {
+        self.incremental_sync(id, false).await
+    }
+    
+    /// Report what `sync` would find without updating the stored state
+    pub async fn sync_dry_run(&self, id: Uuid) -> Result<SyncReport, ConnectorError> {
+        self.incremental_sync(id, true).await
+    }
+    
+    async fn incremental_sync(&self, id: Uuid, dry_run: bool) -> Result<SyncReport, ConnectorError> {
+        let start_time = std::time::Instant::now();
+        
+        let previous = self.sync_state.load(id).await?;
+        let entries = self.call(id, |connector| async move { connector.list_entries().await }).await?;
+        let entries_count = entries.len() as u64;
+        let (added, changed, removed, unchanged) = diff_entries(&previous, entries);
+        
+        if !dry_run {
+            let upserts: Vec<ObjectState> = added.iter().chain(changed.iter()).map(ObjectState::from_entry).collect();
+            self.sync_state.save(id, &upserts, &removed).await?;
+            
+            if let Some(mut status) = self.get_status(id).await {
+                status.last_sync = Some(chrono::Utc::now());
+                status.last_error = None;
+                status.entries_count = entries_count;
+                self.update_status(id, status).await;
+            }
+        }
+        
+        Ok(SyncReport {
+            connector_id: id,
+            added,
+            changed,
+            removed,
+            unchanged,
+            dry_run,
+            duration_seconds: start_time.elapsed().as_secs_f64(),
+        })
+    }
+    
     /// Create connector from configuration
     async fn create_connector(&self, _id: Uuid, config: ConnectorConfig) -> Result<Arc<dyn Connector>, ConnectorError> {
         match config.connector_type {
@@ -51,6 +178,20 @@ impl ConnectorManager {
                 let connector = CkanConnector::new(config.name.clone(), ckan_config);
                 Ok(Arc::new(connector))
             }
+            ConnectorType::Gcs => {
+                let gcs_config: super::gcs::GcsConnectorConfig = serde_json::from_value(config.config)
+                    .map_err(|e| ConnectorError::ConfigurationError(format!("Invalid GCS config: {}", e)))?;
+                
+                let connector = GcsConnector::new(config.name.clone(), gcs_config)?;
+                Ok(Arc::new(connector))
+            }
+            ConnectorType::Http => {
+                let http_config: super::http::HttpConnectorConfig = serde_json::from_value(config.config)
+                    .map_err(|e| ConnectorError::ConfigurationError(format!("Invalid HTTP config: {}", e)))?;
+                
+                let connector = HttpConnector::new(config.name.clone(), http_config)?;
+                Ok(Arc::new(connector))
+            }
         }
     }
     
@@ -61,7 +202,7 @@ impl ConnectorManager {
     }
     
     /// Get connector status
-    async fn get_status(&self, id: Uuid) -> Option<ConnectorStatus> {
+    pub async fn get_status(&self, id: Uuid) -> Option<ConnectorStatus> {
         let statuses = self.statuses.read().await;
         statuses.get(&id).cloned()
     }
@@ -105,6 +246,8 @@ impl ConnectorRegistry for ConnectorManager {
             last_error: None,
             entries_count: 0,
             sync_in_progress: false,
+            circuit: CircuitState::Closed,
+            consecutive_failures: 0,
         };
         
         self.update_status(id, status).await;
@@ -128,6 +271,8 @@ impl ConnectorRegistry for ConnectorManager {
             statuses.remove(&id);
         }
         
+        self.breakers.write().await.remove(&id);
+        
         Ok(())
     }
     
@@ -142,16 +287,15 @@ impl ConnectorRegistry for ConnectorManager {
     }
     
     async fn test_connector(&self, id: Uuid) -> Result<(), ConnectorError> {
-        let connectors = self.connectors.read().await;
-        let connector = connectors.get(&id)
-            .ok_or_else(|| ConnectorError::EntryNotFound(format!("Connector {} not found", id)))?;
-        
-        connector.test_connection().await
+        self.call(id, |connector| async move { connector.test_connection().await }).await
     }
     
     async fn sync_all_connectors(&self) -> Result<Vec<SyncResult>, ConnectorError> {
-        let statuses = self.statuses.read().await;
-        let enabled_connectors: Vec<Uuid> = statuses
+        // Collected up front: syncing updates the statuses
+        let enabled_connectors: Vec<Uuid> = self
+            .statuses
+            .read()
+            .await
             .iter()
             .filter(|(_, status)| status.enabled)
             .map(|(id, _)| *id)
@@ -184,11 +328,7 @@ impl ConnectorRegistry for ConnectorManager {
             self.update_status(id, status).await;
         }
         
-        let connectors = self.connectors.read().await;
-        let connector = connectors.get(&id)
-            .ok_or_else(|| ConnectorError::EntryNotFound(format!("Connector {} not found", id)))?;
-        
-        let result = connector.sync_entries().await;
+        let result = self.call(id, |connector| async move { connector.sync_entries().await }).await;
         
         // Update status with result
         if let Some(mut status) = self.get_status(id).await {
@@ -240,6 +380,20 @@ impl super::traits::ConnectorFactory for ConnectorFactory {
                 let connector = CkanConnector::new(config.name.clone(), ckan_config);
                 Ok(Arc::new(connector))
             }
+            ConnectorType::Gcs => {
+                let gcs_config: super::gcs::GcsConnectorConfig = serde_json::from_value(config.config)
+                    .map_err(|e| ConnectorError::ConfigurationError(format!("Invalid GCS config: {}", e)))?;
+                
+                let connector = GcsConnector::new(config.name.clone(), gcs_config)?;
+                Ok(Arc::new(connector))
+            }
+            ConnectorType::Http => {
+                let http_config: super::http::HttpConnectorConfig = serde_json::from_value(config.config)
+                    .map_err(|e| ConnectorError::ConfigurationError(format!("Invalid HTTP config: {}", e)))?;
+                
+                let connector = HttpConnector::new(config.name.clone(), http_config)?;
+                Ok(Arc::new(connector))
+            }
         }
     }
     
@@ -248,6 +402,8 @@ impl super::traits::ConnectorFactory for ConnectorFactory {
             ConnectorType::S3,
             ConnectorType::Postgres,
             ConnectorType::Ckan,
+            ConnectorType::Gcs,
+            ConnectorType::Http,
         ]
     }
 }
@@ -255,6 +411,7 @@ impl super::traits::ConnectorFactory for ConnectorFactory {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::traits::ConnectorFactory as _;
     
     #[tokio::test]
     async fn test_connector_manager_creation() {
@@ -267,9 +424,251 @@ mod tests {
     fn test_connector_factory_supported_types() {
         let factory = ConnectorFactory;
         let types = factory.supported_types();
-        assert_eq!(types.len(), 3);
+        assert_eq!(types.len(), 5);
         assert!(types.contains(&ConnectorType::S3));
         assert!(types.contains(&ConnectorType::Postgres));
         assert!(types.contains(&ConnectorType::Ckan));
+        assert!(types.contains(&ConnectorType::Gcs));
+        assert!(types.contains(&ConnectorType::Http));
+    }
+    
+    /// Connector whose listing tests can rewrite between syncs
+    struct MockConnector {
+        entries: std::sync::Mutex<Vec<ExternalEntry>>,
+    }
+    
+    #[async_trait]
+    impl Connector for MockConnector {
+        fn connector_type(&self) -> ConnectorType {
+            ConnectorType::Http
+        }
+        
+        fn name(&self) -> &str {
+            "mock"
+        }
+        
+        async fn test_connection(&self) -> Result<(), ConnectorError> {
+            Ok(())
+        }
+        
+        async fn list_entries(&self) -> Result<Vec<ExternalEntry>, ConnectorError> {
+            Ok(self.entries.lock().unwrap().clone())
+        }
+        
+        async fn get_entry(&self, id: &str) -> Result<Option<ExternalEntry>, ConnectorError> {
+            Ok(self.entries.lock().unwrap().iter().find(|e| e.id == id).cloned())
+        }
+        
+        async fn get_presigned_url(&self, entry: &ExternalEntry, _expires_in_seconds: u32) -> Result<String, ConnectorError> {
+            Ok(entry.url.clone())
+        }
+        
+        async fn sync_entries(&self) -> Result<SyncResult, ConnectorError> {
+            unimplemented!()
+        }
+    }
+    
+    fn mock_entry(key: &str, etag: &str) -> ExternalEntry {
+        ExternalEntry {
+            id: format!("mock:{}", key),
+            title: key.to_string(),
+            description: None,
+            url: format!("https://example.org/{}", key),
+            content_type: None,
+            size: Some(10),
+            modified_at: None,
+            tags: vec![],
+            metadata: HashMap::from([("etag".to_string(), serde_json::Value::String(etag.to_string()))]),
+            source_id: Uuid::new_v4(),
+            source_type: "mock".to_string(),
+        }
+    }
+    
+    async fn manager_with_mock(entries: Vec<ExternalEntry>) -> (ConnectorManager, Uuid, Arc<MockConnector>) {
+        let manager = ConnectorManager::new();
+        let id = Uuid::new_v4();
+        let connector = Arc::new(MockConnector { entries: std::sync::Mutex::new(entries) });
+        manager.connectors.write().await.insert(id, connector.clone());
+        (manager, id, connector)
+    }
+    
+    #[tokio::test]
+    async fn test_second_sync_reports_only_changes() {
+        let (manager, id, connector) = manager_with_mock(vec![
+            mock_entry("a.csv", "1"),
+            mock_entry("b.csv", "1"),
+            mock_entry("c.csv", "1"),
+        ]).await;
+        
+        let first = manager.sync(id).await.unwrap();
+        assert_eq!(first.added.len(), 3);
+        assert!(first.changed.is_empty() && first.removed.is_empty());
+        
+        *connector.entries.lock().unwrap() = vec![
+            mock_entry("a.csv", "1"),
+            mock_entry("b.csv", "2"),
+            mock_entry("c.csv", "1"),
+        ];
+        
+        let second = manager.sync(id).await.unwrap();
+        assert!(second.added.is_empty() && second.removed.is_empty());
+        assert_eq!(second.changed.iter().map(|e| e.id.as_str()).collect::<Vec<_>>(), vec!["mock:b.csv"]);
+        assert_eq!(second.unchanged, 2);
+        
+        let third = manager.sync(id).await.unwrap();
+        assert!(!third.has_changes());
+    }
+    
+    #[tokio::test]
+    async fn test_dry_run_does_not_record_state() {
+        let (manager, id, connector) = manager_with_mock(vec![mock_entry("a.csv", "1")]).await;
+        manager.sync(id).await.unwrap();
+        
+        *connector.entries.lock().unwrap() = vec![mock_entry("b.csv", "1")];
+        
+        for _ in 0..2 {
+            let report = manager.sync_dry_run(id).await.unwrap();
+            assert!(report.dry_run);
+            assert_eq!(report.added.len(), 1);
+            assert_eq!(report.removed, vec!["mock:a.csv"]);
+        }
+        
+        let report = manager.sync(id).await.unwrap();
+        assert_eq!(report.removed, vec!["mock:a.csv"]);
+        assert!(!manager.sync(id).await.unwrap().has_changes());
+        
+        assert!(manager.sync(Uuid::new_v4()).await.is_err());
+    }
+    
+    /// Connector whose health tests toggle, counting the calls that reach it
+    #[derive(Default)]
+    struct FlakyConnector {
+        down: std::sync::atomic::AtomicBool,
+        calls: std::sync::atomic::AtomicU32,
+    }
+    
+    impl FlakyConnector {
+        fn respond(&self) -> Result<(), ConnectorError> {
+            self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            if self.down.load(std::sync::atomic::Ordering::SeqCst) {
+                Err(ConnectorError::ConnectionError("connection refused".to_string()))
+            } else {
+                Ok(())
+            }
+        }
+    }
+    
+    #[async_trait]
+    impl Connector for FlakyConnector {
+        fn connector_type(&self) -> ConnectorType {
+            ConnectorType::Ckan
+        }
+        
+        fn name(&self) -> &str {
+            "flaky"
+        }
+        
+        async fn test_connection(&self) -> Result<(), ConnectorError> {
+            self.respond()
+        }
+        
+        async fn list_entries(&self) -> Result<Vec<ExternalEntry>, ConnectorError> {
+            self.respond().map(|_| Vec::new())
+        }
+        
+        async fn get_entry(&self, _id: &str) -> Result<Option<ExternalEntry>, ConnectorError> {
+            self.respond().map(|_| None)
+        }
+        
+        async fn get_presigned_url(&self, entry: &ExternalEntry, _expires_in_seconds: u32) -> Result<String, ConnectorError> {
+            Ok(entry.url.clone())
+        }
+        
+        async fn sync_entries(&self) -> Result<SyncResult, ConnectorError> {
+            self.respond()?;
+            Ok(SyncResult {
+                entries_processed: 0,
+                entries_added: 0,
+                entries_updated: 0,
+                entries_removed: 0,
+                errors: vec![],
+                duration_seconds: 0.0,
+            })
+        }
+    }
+    
+    async fn add_flaky(manager: &ConnectorManager) -> (Uuid, Arc<FlakyConnector>) {
+        let id = Uuid::new_v4();
+        let connector = Arc::new(FlakyConnector::default());
+        manager.connectors.write().await.insert(id, connector.clone());
+        manager.update_status(id, ConnectorStatus {
+            id,
+            name: "flaky".to_string(),
+            connector_type: ConnectorType::Ckan,
+            enabled: true,
+            last_sync: None,
+            last_error: None,
+            entries_count: 0,
+            sync_in_progress: false,
+            circuit: CircuitState::Closed,
+            consecutive_failures: 0,
+        }).await;
+        (id, connector)
+    }
+    
+    fn breaker_config(cooldown: std::time::Duration) -> BreakerConfig {
+        BreakerConfig { failure_threshold: 3, cooldown, call_timeout: std::time::Duration::from_secs(5) }
+    }
+    
+    #[tokio::test]
+    async fn test_failing_connector_trips_breaker_and_recovers_after_cooldown() {
+        use std::sync::atomic::Ordering;
+        
+        let cooldown = std::time::Duration::from_millis(50);
+        let manager = ConnectorManager::new().with_breaker_config(breaker_config(cooldown));
+        let (id, flaky) = add_flaky(&manager).await;
+        flaky.down.store(true, Ordering::SeqCst);
+        
+        for _ in 0..3 {
+            assert!(matches!(manager.test_connector(id).await, Err(ConnectorError::ConnectionError(_))));
+        }
+        let status = manager.get_status(id).await.unwrap();
+        assert_eq!(status.circuit, CircuitState::Open);
+        assert_eq!(status.consecutive_failures, 3);
+        
+        // Refused without reaching the source while open
+        assert!(matches!(manager.test_connector(id).await, Err(ConnectorError::CircuitOpen(_))));
+        assert_eq!(flaky.calls.load(Ordering::SeqCst), 3);
+        
+        // After the cooldown a successful probe closes the circuit again
+        flaky.down.store(false, Ordering::SeqCst);
+        tokio::time::sleep(cooldown).await;
+        manager.test_connector(id).await.unwrap();
+        let status = manager.get_status(id).await.unwrap();
+        assert_eq!(status.circuit, CircuitState::Closed);
+        assert_eq!(status.consecutive_failures, 0);
+        assert_eq!(flaky.calls.load(Ordering::SeqCst), 4);
+    }
+    
+    #[tokio::test]
+    async fn test_open_circuit_does_not_stop_other_connectors_syncing() {
+        use std::sync::atomic::Ordering;
+        
+        let manager = ConnectorManager::new().with_breaker_config(breaker_config(std::time::Duration::from_secs(60)));
+        let (broken, flaky) = add_flaky(&manager).await;
+        let (healthy, _) = add_flaky(&manager).await;
+        flaky.down.store(true, Ordering::SeqCst);
+        
+        for _ in 0..3 {
+            assert_eq!(manager.sync_all_connectors().await.unwrap().len(), 1);
+        }
+        let results = manager.sync_all_connectors().await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(flaky.calls.load(Ordering::SeqCst), 3);
+        
+        let broken = manager.get_status(broken).await.unwrap();
+        assert_eq!(broken.circuit, CircuitState::Open);
+        assert!(broken.last_error.unwrap().contains("unavailable"));
+        assert_eq!(manager.get_status(healthy).await.unwrap().circuit, CircuitState::Closed);
     }
 }
@@ -0,0 +1,271 @@
+// Incremental sync state for federation connectors
+// Week 8: Federation across data sources
+
+use super::traits::*;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+/// What a connector last reported for one object
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ObjectState {
+    /// The entry ID, which connectors keep stable per object
+    pub key: String,
+    pub etag: Option<String>,
+    pub modified_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub size: Option<u64>,
+}
+
+impl ObjectState {
+    pub fn from_entry(entry: &ExternalEntry) -> Self {
+        Self {
+            key: entry.id.clone(),
+            etag: entry.metadata.get("etag")
+                .and_then(|v| v.as_str())
+                .filter(|etag| !etag.is_empty())
+                .map(str::to_string),
+            modified_at: entry.modified_at,
+            size: entry.size,
+        }
+    }
+}
+
+/// Delta between the remote listing and the stored state
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncReport {
+    pub connector_id: Uuid,
+    pub added: Vec<ExternalEntry>,
+    pub changed: Vec<ExternalEntry>,
+    /// Keys that were present last time and are gone now
+    pub removed: Vec<String>,
+    pub unchanged: u64,
+    /// Whether the stored state was left untouched
+    pub dry_run: bool,
+    pub duration_seconds: f64,
+}
+
+impl SyncReport {
+    /// Whether anything needs ingesting or removing
+    pub fn has_changes(&self) -> bool {
+        !self.added.is_empty() || !self.changed.is_empty() || !self.removed.is_empty()
+    }
+}
+
+/// Compare a fresh listing with the previous state
+pub fn diff_entries(
+    previous: &HashMap<String, ObjectState>,
+    entries: Vec<ExternalEntry>,
+) -> (Vec<ExternalEntry>, Vec<ExternalEntry>, Vec<String>, u64) {
+    let mut added = Vec::new();
+    let mut changed = Vec::new();
+    let mut unchanged = 0;
+
+    for entry in entries.iter() {
+        match previous.get(&entry.id) {
+            None => added.push(entry.clone()),
+            Some(state) if *state != ObjectState::from_entry(entry) => changed.push(entry.clone()),
+            Some(_) => unchanged += 1,
+        }
+    }
+
+    let mut removed: Vec<String> = previous.keys()
+        .filter(|key| !entries.iter().any(|entry| &entry.id == *key))
+        .cloned()
+        .collect();
+    removed.sort();
+
+    (added, changed, removed, unchanged)
+}
+
+/// Persistence for per-connector sync state
+#[async_trait]
+pub trait SyncStateStore: Send + Sync {
+    /// Load the last-seen state of every object for a connector
+    async fn load(&self, connector_id: Uuid) -> Result<HashMap<String, ObjectState>, ConnectorError>;
+
+    /// Record new or changed objects and forget removed ones
+    async fn save(&self, connector_id: Uuid, upserts: &[ObjectState], removed: &[String]) -> Result<(), ConnectorError>;
+}
+
+/// Process-local state store, used when no database is configured
+#[derive(Default)]
+pub struct InMemorySyncStateStore {
+    states: RwLock<HashMap<Uuid, HashMap<String, ObjectState>>>,
+}
+
+impl InMemorySyncStateStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl SyncStateStore for InMemorySyncStateStore {
+    async fn load(&self, connector_id: Uuid) -> Result<HashMap<String, ObjectState>, ConnectorError> {
+        let states = self.states.read().await;
+        Ok(states.get(&connector_id).cloned().unwrap_or_default())
+    }
+
+    async fn save(&self, connector_id: Uuid, upserts: &[ObjectState], removed: &[String]) -> Result<(), ConnectorError> {
+        let mut states = self.states.write().await;
+        let state = states.entry(connector_id).or_default();
+        for object in upserts {
+            state.insert(object.key.clone(), object.clone());
+        }
+        for key in removed {
+            state.remove(key);
+        }
+        Ok(())
+    }
+}
+
+const LOAD_SYNC_STATE_SQL: &str =
+    "SELECT object_key, etag, modified_at, size FROM connector_sync_state WHERE connector_id = $1";
+
+const UPSERT_SYNC_STATE_SQL: &str = r#"
+    INSERT INTO connector_sync_state (connector_id, object_key, etag, modified_at, size, synced_at)
+    VALUES ($1, $2, $3, $4, $5, NOW())
+    ON CONFLICT (connector_id, object_key) DO UPDATE SET
+        etag = EXCLUDED.etag,
+        modified_at = EXCLUDED.modified_at,
+        size = EXCLUDED.size,
+        synced_at = EXCLUDED.synced_at
+"#;
+
+const DELETE_SYNC_STATE_SQL: &str =
+    "DELETE FROM connector_sync_state WHERE connector_id = $1 AND object_key = ANY($2)";
+
+/// Sync state in the `connector_sync_state` table
+pub struct PostgresSyncStateStore {
+    client: RwLock<tokio_postgres::Client>,
+}
+
+impl PostgresSyncStateStore {
+    pub fn new(client: tokio_postgres::Client) -> Self {
+        Self { client: RwLock::new(client) }
+    }
+
+    pub async fn connect(database_url: &str) -> Result<Self, ConnectorError> {
+        let (client, connection) = tokio_postgres::connect(database_url, tokio_postgres::NoTls).await?;
+
+        // Spawn connection task
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                tracing::error!("Sync state connection error: {}", e);
+            }
+        });
+
+        Ok(Self::new(client))
+    }
+}
+
+#[async_trait]
+impl SyncStateStore for PostgresSyncStateStore {
+    async fn load(&self, connector_id: Uuid) -> Result<HashMap<String, ObjectState>, ConnectorError> {
+        let client = self.client.read().await;
+        let rows = client.query(LOAD_SYNC_STATE_SQL, &[&connector_id]).await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                let state = ObjectState {
+                    key: row.get("object_key"),
+                    etag: row.get("etag"),
+                    modified_at: row.get("modified_at"),
+                    size: row.get::<_, Option<i64>>("size").map(|s| s as u64),
+                };
+                (state.key.clone(), state)
+            })
+            .collect())
+    }
+
+    async fn save(&self, connector_id: Uuid, upserts: &[ObjectState], removed: &[String]) -> Result<(), ConnectorError> {
+        let mut client = self.client.write().await;
+        let transaction = client.transaction().await?;
+
+        for object in upserts {
+            let size = object.size.map(|s| s as i64);
+            transaction.execute(
+                UPSERT_SYNC_STATE_SQL,
+                &[&connector_id, &object.key, &object.etag, &object.modified_at, &size],
+            ).await?;
+        }
+        if !removed.is_empty() {
+            transaction.execute(DELETE_SYNC_STATE_SQL, &[&connector_id, &removed]).await?;
+        }
+
+        transaction.commit().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: &str, etag: &str, size: u64) -> ExternalEntry {
+        ExternalEntry {
+            id: id.to_string(),
+            title: id.to_string(),
+            description: None,
+            url: format!("https://example.org/{}", id),
+            content_type: None,
+            size: Some(size),
+            modified_at: None,
+            tags: vec![],
+            metadata: HashMap::from([("etag".to_string(), serde_json::Value::String(etag.to_string()))]),
+            source_id: Uuid::new_v4(),
+            source_type: "test".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_diff_entries() {
+        let previous: HashMap<String, ObjectState> = [entry("a", "1", 10), entry("b", "1", 10), entry("c", "1", 10)]
+            .iter()
+            .map(|e| (e.id.clone(), ObjectState::from_entry(e)))
+            .collect();
+
+        let (added, changed, removed, unchanged) = diff_entries(
+            &previous,
+            vec![entry("a", "1", 10), entry("b", "2", 10), entry("d", "1", 10)],
+        );
+
+        assert_eq!(added.iter().map(|e| e.id.as_str()).collect::<Vec<_>>(), vec!["d"]);
+        assert_eq!(changed.iter().map(|e| e.id.as_str()).collect::<Vec<_>>(), vec!["b"]);
+        assert_eq!(removed, vec!["c"]);
+        assert_eq!(unchanged, 1);
+    }
+
+    #[test]
+    fn test_empty_etag_is_ignored() {
+        let state = ObjectState::from_entry(&entry("a", "", 10));
+        assert!(state.etag.is_none());
+        assert_eq!(state.size, Some(10));
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_store_round_trip() {
+        let store = InMemorySyncStateStore::new();
+        let id = Uuid::new_v4();
+        let a = ObjectState::from_entry(&entry("a", "1", 10));
+        let b = ObjectState::from_entry(&entry("b", "1", 10));
+
+        store.save(id, &[a.clone(), b], &[]).await.unwrap();
+        store.save(id, &[], &["b".to_string()]).await.unwrap();
+
+        let state = store.load(id).await.unwrap();
+        assert_eq!(state.len(), 1);
+        assert_eq!(state["a"], a);
+        assert!(store.load(Uuid::new_v4()).await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_sync_state_sql_is_keyed_by_connector_and_object() {
+        assert!(UPSERT_SYNC_STATE_SQL.contains("ON CONFLICT (connector_id, object_key)"));
+        assert!(LOAD_SYNC_STATE_SQL.contains("WHERE connector_id = $1"));
+        assert!(DELETE_SYNC_STATE_SQL.contains("object_key = ANY($2)"));
+    }
+}
@@ -1,6 +1,7 @@
 // Connector traits for federation
 // Week 8: Federation across data sources
 
+use crate::breaker::CircuitState;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -23,6 +24,21 @@ pub struct ExternalEntry {
     pub source_type: String,
 }
 
+impl ExternalEntry {
+    /// Where the entry sits when a virtual repository lists its source as a tree:
+    /// the object key for bucket connectors, `package/title` for CKAN resources,
+    /// and the title otherwise
+    pub fn tree_path(&self) -> String {
+        if let Some(key) = self.metadata.get("key").and_then(|v| v.as_str()) {
+            return key.trim_start_matches('/').to_string();
+        }
+        match self.metadata.get("package_name").and_then(|v| v.as_str()) {
+            Some(package) => format!("{}/{}", package, self.title),
+            None => self.title.clone(),
+        }
+    }
+}
+
 /// Connector configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConnectorConfig {
@@ -35,11 +51,29 @@ pub struct ConnectorConfig {
 }
 
 /// Supported connector types
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ConnectorType {
     S3,
     Postgres,
     Ckan,
+    Gcs,
+    Http,
+}
+
+impl std::str::FromStr for ConnectorType {
+    type Err = String;
+
+    /// Parse the lowercase names stored in `external_source.connector_type`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "s3" => Ok(ConnectorType::S3),
+            "postgres" => Ok(ConnectorType::Postgres),
+            "ckan" => Ok(ConnectorType::Ckan),
+            "gcs" => Ok(ConnectorType::Gcs),
+            "http" => Ok(ConnectorType::Http),
+            other => Err(format!("Unknown connector type: {}", other)),
+        }
+    }
 }
 
 /// Connector status
@@ -53,6 +87,11 @@ pub struct ConnectorStatus {
     pub last_error: Option<String>,
     pub entries_count: u64,
     pub sync_in_progress: bool,
+    /// Open while the connector is failing and calls to it are refused
+    #[serde(default)]
+    pub circuit: CircuitState,
+    #[serde(default)]
+    pub consecutive_failures: u32,
 }
 
 /// Connector sync result
@@ -127,8 +166,30 @@ pub enum ConnectorError {
     #[error("Database error: {0}")]
     DatabaseError(#[from] tokio_postgres::Error),
     
+    /// Boxed: the rusoto error is large enough to bloat every connector `Result`
     #[error("AWS error: {0}")]
-    AwsError(#[from] rusoto_core::RusotoError<rusoto_s3::GetObjectError>),
+    AwsError(Box<rusoto_core::RusotoError<rusoto_s3::GetObjectError>>),
+    
+    #[error("Connector unavailable: {0}")]
+    CircuitOpen(String),
+}
+
+impl From<rusoto_core::RusotoError<rusoto_s3::GetObjectError>> for ConnectorError {
+    fn from(err: rusoto_core::RusotoError<rusoto_s3::GetObjectError>) -> Self {
+        ConnectorError::AwsError(Box::new(err))
+    }
+}
+
+impl ConnectorError {
+    /// Whether the error suggests the source itself is unhealthy. Bad
+    /// configuration, missing entries and refused calls do not count
+    /// towards opening a connector's circuit.
+    pub fn trips_breaker(&self) -> bool {
+        !matches!(
+            self,
+            ConnectorError::ConfigurationError(_) | ConnectorError::EntryNotFound(_) | ConnectorError::CircuitOpen(_)
+        )
+    }
 }
 
 /// Connector factory trait
@@ -165,3 +226,42 @@ pub trait ConnectorRegistry: Send + Sync {
     /// Sync a specific connector
     async fn sync_connector(&self, id: Uuid) -> Result<SyncResult, ConnectorError>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(title: &str, metadata: serde_json::Value) -> ExternalEntry {
+        ExternalEntry {
+            id: title.to_string(),
+            title: title.to_string(),
+            description: None,
+            url: String::new(),
+            content_type: None,
+            size: None,
+            modified_at: None,
+            tags: vec![],
+            metadata: serde_json::from_value(metadata).unwrap(),
+            source_id: Uuid::nil(),
+            source_type: "test".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_tree_path_prefers_key_then_package() {
+        let object = entry("a.csv", serde_json::json!({"key": "/raw/a.csv"}));
+        let resource = entry("b.csv", serde_json::json!({"package_name": "census"}));
+        let plain = entry("c.csv", serde_json::json!({}));
+
+        assert_eq!(object.tree_path(), "raw/a.csv");
+        assert_eq!(resource.tree_path(), "census/b.csv");
+        assert_eq!(plain.tree_path(), "c.csv");
+    }
+
+    #[test]
+    fn test_connector_type_parses_stored_names() {
+        assert_eq!("s3".parse::<ConnectorType>().unwrap(), ConnectorType::S3);
+        assert_eq!("CKAN".parse::<ConnectorType>().unwrap(), ConnectorType::Ckan);
+        assert!("ftp".parse::<ConnectorType>().is_err());
+    }
+}
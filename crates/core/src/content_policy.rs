@@ -0,0 +1,161 @@
+//! Per-repository content type allow and block lists.
+//!
+//! The `allowed_content_types` and `blocked_content_types` features hold
+//! glob patterns (`*` matches any run of characters, so `image/*` covers
+//! every image type) matched case-insensitively against a media type's
+//! essence, without parameters. A blocked match always refuses; otherwise a
+//! non-empty allow list must match. Uploads are checked against the type
+//! they declare, and commits also sniff each object's leading bytes so that
+//! declaring `image/png` for something else does not get it through.
+
+use thiserror::Error;
+
+use crate::features::RepoFeatures;
+use crate::role_permissions::glob_captures;
+use crate::sniff::corrected_media_type;
+
+/// Type assumed for content that declares none
+const UNDECLARED_MEDIA_TYPE: &str = "application/octet-stream";
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ContentTypeError {
+    #[error("Content type '{content_type}' is blocked in this repository (matches '{pattern}')")]
+    Blocked { content_type: String, pattern: String },
+    #[error("Content type '{content_type}' is not allowed in this repository; allowed: {allowed}")]
+    NotAllowed { content_type: String, allowed: String },
+    #[error("Content detected as '{detected}' although declared as '{declared}': {source}")]
+    Detected {
+        declared: String,
+        detected: &'static str,
+        source: Box<ContentTypeError>,
+    },
+}
+
+/// A repo's content type allow and block lists
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContentTypePolicy {
+    allowed: Vec<String>,
+    blocked: Vec<String>,
+}
+
+impl ContentTypePolicy {
+    pub fn new(allowed: Vec<String>, blocked: Vec<String>) -> Self {
+        Self { allowed, blocked }
+    }
+
+    pub fn from_features(features: &RepoFeatures) -> Self {
+        let owned = |patterns: Vec<&str>| patterns.into_iter().map(str::to_string).collect();
+        Self::new(owned(features.allowed_content_types()), owned(features.blocked_content_types()))
+    }
+
+    /// Whether either list is set; an unenforced policy accepts everything
+    pub fn is_enforced(&self) -> bool {
+        !self.allowed.is_empty() || !self.blocked.is_empty()
+    }
+
+    /// Check a declared content type; `None` is treated as `application/octet-stream`
+    pub fn check(&self, content_type: Option<&str>) -> Result<(), ContentTypeError> {
+        let content_type = content_type.unwrap_or(UNDECLARED_MEDIA_TYPE);
+        let essence = content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+
+        if let Some(pattern) = self.blocked.iter().find(|pattern| matches(pattern, &essence)) {
+            return Err(ContentTypeError::Blocked {
+                content_type: content_type.to_string(),
+                pattern: pattern.clone(),
+            });
+        }
+        if !self.allowed.is_empty() && !self.allowed.iter().any(|pattern| matches(pattern, &essence)) {
+            return Err(ContentTypeError::NotAllowed {
+                content_type: content_type.to_string(),
+                allowed: self.allowed.join(", "),
+            });
+        }
+        Ok(())
+    }
+
+    /// Check an object against both its declared type and the type sniffed
+    /// from `prefix`, its leading bytes, when detection overrules the
+    /// declaration
+    pub fn check_object(&self, declared: Option<&str>, prefix: &[u8]) -> Result<(), ContentTypeError> {
+        self.check(declared)?;
+
+        let Some(detected) = corrected_media_type(declared, prefix) else {
+            return Ok(());
+        };
+        self.check(Some(detected)).map_err(|e| ContentTypeError::Detected {
+            declared: declared.unwrap_or(UNDECLARED_MEDIA_TYPE).to_string(),
+            detected,
+            source: Box::new(e),
+        })
+    }
+}
+
+fn matches(pattern: &str, essence: &str) -> bool {
+    glob_captures(&pattern.to_ascii_lowercase(), essence).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR";
+    const PDF: &[u8] = b"%PDF-1.7\n";
+
+    fn policy(features: serde_json::Value) -> ContentTypePolicy {
+        ContentTypePolicy::from_features(&RepoFeatures(features))
+    }
+
+    #[test]
+    fn test_allowed_type_is_accepted() {
+        let policy = policy(json!({ "allowed_content_types": ["image/*", "text/csv"] }));
+
+        assert!(policy.is_enforced());
+        assert_eq!(policy.check(Some("image/png")), Ok(()));
+        assert_eq!(policy.check(Some("Text/CSV; charset=utf-8")), Ok(()));
+        assert_eq!(policy.check_object(Some("image/png"), PNG), Ok(()));
+        assert!(matches!(policy.check(Some("application/pdf")), Err(ContentTypeError::NotAllowed { .. })));
+        assert!(matches!(policy.check(None), Err(ContentTypeError::NotAllowed { .. })));
+
+        let unset = ContentTypePolicy::default();
+        assert!(!unset.is_enforced());
+        assert_eq!(unset.check(Some("application/x-anything")), Ok(()));
+    }
+
+    #[test]
+    fn test_blocked_glob_match_is_rejected() {
+        let policy = policy(json!({
+            "allowed_content_types": ["image/*", "video/*"],
+            "blocked_content_types": ["video/*"]
+        }));
+
+        let err = policy.check(Some("video/mp4")).unwrap_err();
+        assert_eq!(
+            err,
+            ContentTypeError::Blocked { content_type: "video/mp4".to_string(), pattern: "video/*".to_string() }
+        );
+        assert!(err.to_string().contains("blocked"), "{}", err);
+        assert_eq!(policy.check(Some("image/gif")), Ok(()));
+    }
+
+    #[test]
+    fn test_sniffed_mismatch_is_rejected() {
+        let policy = policy(json!({ "blocked_content_types": ["application/pdf"] }));
+
+        // Declared as a picture, but the bytes are a PDF
+        let err = policy.check_object(Some("image/png"), PDF).unwrap_err();
+        match &err {
+            ContentTypeError::Detected { declared, detected, source } => {
+                assert_eq!(declared, "image/png");
+                assert_eq!(*detected, "application/pdf");
+                assert!(matches!(**source, ContentTypeError::Blocked { .. }));
+            }
+            other => panic!("expected a detected mismatch, got {:?}", other),
+        }
+        assert!(err.to_string().contains("detected as 'application/pdf'"), "{}", err);
+
+        // Bytes that match the declaration, or say nothing, pass
+        assert_eq!(policy.check_object(Some("image/png"), PNG), Ok(()));
+        assert_eq!(policy.check_object(Some("image/png"), b"plain text"), Ok(()));
+    }
+}
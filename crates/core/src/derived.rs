@@ -0,0 +1,221 @@
+//! Objects generated from committed entries.
+//!
+//! Conversion and preview jobs store their output as ordinary
+//! content-addressed objects and link each one back to its source entry in
+//! `derived_artifact`. The link is dropped with the source entry; until then
+//! it keeps the derived object out of orphan GC.
+
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::jobs::JobError;
+
+/// Link from a committed entry to an object derived from it
+#[derive(Debug, Clone, PartialEq)]
+pub struct DerivedArtifact {
+    pub commit_id: Uuid,
+    pub path: String,
+    pub kind: String,
+    pub source_sha256: String,
+    pub derived_sha256: String,
+    pub s3_key: String,
+    pub size: i64,
+    pub media_type: String,
+}
+
+/// A stored object as the derivation jobs see it
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceObject {
+    pub s3_key: String,
+    pub size: i64,
+    pub media_type: Option<String>,
+}
+
+/// Content-addressed key for an object
+pub fn content_key(sha256: &str) -> String {
+    format!("sha256/{}/{}/{}", &sha256[0..2], &sha256[2..4], sha256)
+}
+
+/// Stored objects the derivation jobs read and the links they record
+#[async_trait::async_trait]
+pub trait DerivedArtifactStore: Send + Sync {
+    /// Key, size and media type of an object, if it exists
+    async fn object(&self, sha256: &str) -> Result<Option<SourceObject>, JobError>;
+
+    /// Register the derived object and link it to its source entry
+    async fn record_derived(&self, artifact: &DerivedArtifact) -> Result<(), JobError>;
+}
+
+/// Derived artifact store backed by the `object` and `derived_artifact` tables
+pub struct PgDerivedArtifactStore {
+    pool: PgPool,
+}
+
+impl PgDerivedArtifactStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl DerivedArtifactStore for PgDerivedArtifactStore {
+    async fn object(&self, sha256: &str) -> Result<Option<SourceObject>, JobError> {
+        let row: Option<(String, i64, Option<String>)> =
+            sqlx::query_as("SELECT s3_key, size, media_type FROM object WHERE sha256 = $1")
+                .bind(sha256)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(|e| JobError::Storage(format!("Failed to look up object {}: {}", sha256, e)))?;
+        Ok(row.map(|(s3_key, size, media_type)| SourceObject { s3_key, size, media_type }))
+    }
+
+    async fn record_derived(&self, artifact: &DerivedArtifact) -> Result<(), JobError> {
+        let storage_error =
+            |e: sqlx::Error| JobError::Storage(format!("Failed to record derived artifact for {}: {}", artifact.path, e));
+        let mut tx = self.pool.begin().await.map_err(storage_error)?;
+
+        sqlx::query(
+            "INSERT INTO object (sha256, size, media_type, s3_key) VALUES ($1, $2, $3, $4)
+             ON CONFLICT (sha256) DO NOTHING",
+        )
+        .bind(&artifact.derived_sha256)
+        .bind(artifact.size)
+        .bind(&artifact.media_type)
+        .bind(&artifact.s3_key)
+        .execute(&mut *tx)
+        .await
+        .map_err(storage_error)?;
+        sqlx::query(
+            "INSERT INTO derived_artifact (commit_id, path, kind, source_sha256, derived_sha256)
+             VALUES ($1, $2, $3, $4, $5)
+             ON CONFLICT (commit_id, path, kind) DO UPDATE SET
+                source_sha256 = EXCLUDED.source_sha256,
+                derived_sha256 = EXCLUDED.derived_sha256,
+                created_at = now()",
+        )
+        .bind(artifact.commit_id)
+        .bind(&artifact.path)
+        .bind(&artifact.kind)
+        .bind(&artifact.source_sha256)
+        .bind(&artifact.derived_sha256)
+        .execute(&mut *tx)
+        .await
+        .map_err(storage_error)?;
+
+        tx.commit().await.map_err(storage_error)
+    }
+}
+
+/// Read a whole object's bytes
+pub async fn read_object(s3_client: &aws_sdk_s3::Client, bucket: &str, s3_key: &str) -> Result<Vec<u8>, JobError> {
+    let output = s3_client
+        .get_object()
+        .bucket(bucket)
+        .key(s3_key)
+        .send()
+        .await
+        .map_err(|e| JobError::Storage(format!("Failed to read {}: {}", s3_key, e)))?;
+    Ok(output.body.collect().await?.into_bytes().to_vec())
+}
+
+/// Upload `bytes` under their content address and link them to the source entry.
+///
+/// The object is read back before the link is recorded: it must hash to what
+/// was written and pass `verify`. An object that fails is left unlinked, for
+/// orphan GC, and the job fails.
+#[allow(clippy::too_many_arguments)]
+pub async fn store_derived(
+    s3_client: &aws_sdk_s3::Client,
+    bucket: &str,
+    store: &dyn DerivedArtifactStore,
+    commit_id: Uuid,
+    path: &str,
+    source_sha256: &str,
+    kind: &str,
+    media_type: &str,
+    bytes: Vec<u8>,
+    verify: impl FnOnce(&[u8]) -> Result<(), String>,
+) -> Result<DerivedArtifact, JobError> {
+    let derived_sha256 = crate::hash_bytes(&bytes);
+    let s3_key = content_key(&derived_sha256);
+    let size = bytes.len() as i64;
+
+    s3_client
+        .put_object()
+        .bucket(bucket)
+        .key(&s3_key)
+        .content_type(media_type)
+        .body(bytes.into())
+        .send()
+        .await
+        .map_err(|e| JobError::Storage(format!("Failed to store {}: {}", s3_key, e)))?;
+
+    let failed = |reason: String| JobError::Processing(format!("Stored {} of {} failed verification: {}", kind, path, reason));
+    let stored = read_object(s3_client, bucket, &s3_key).await?;
+    if stored.len() as i64 != size {
+        return Err(failed(format!("read back {} of {} bytes", stored.len(), size)));
+    }
+    if crate::hash_bytes(&stored) != derived_sha256 {
+        return Err(failed("stored bytes do not match what was written".to_string()));
+    }
+    verify(&stored).map_err(failed)?;
+
+    let artifact = DerivedArtifact {
+        commit_id,
+        path: path.to_string(),
+        kind: kind.to_string(),
+        source_sha256: source_sha256.to_string(),
+        derived_sha256,
+        s3_key,
+        size,
+        media_type: media_type.to_string(),
+    };
+    store.record_derived(&artifact).await?;
+    Ok(artifact)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    /// Objects and recorded links held in memory
+    #[derive(Default)]
+    pub(crate) struct MemoryStore {
+        pub objects: HashMap<String, SourceObject>,
+        pub recorded: Mutex<Vec<DerivedArtifact>>,
+    }
+
+    impl MemoryStore {
+        pub fn with_object(sha256: &str, size: usize, media_type: Option<&str>) -> Self {
+            let mut store = Self::default();
+            store.objects.insert(
+                sha256.to_string(),
+                SourceObject { s3_key: content_key(sha256), size: size as i64, media_type: media_type.map(str::to_string) },
+            );
+            store
+        }
+
+        pub fn recorded(&self) -> Vec<DerivedArtifact> {
+            self.recorded.lock().unwrap().clone()
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl DerivedArtifactStore for MemoryStore {
+        async fn object(&self, sha256: &str) -> Result<Option<SourceObject>, JobError> {
+            Ok(self.objects.get(sha256).cloned())
+        }
+
+        async fn record_derived(&self, artifact: &DerivedArtifact) -> Result<(), JobError> {
+            self.recorded.lock().unwrap().push(artifact.clone());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_content_key_shards_by_hash_prefix() {
+        assert_eq!(content_key("abcdef"), "sha256/ab/cd/abcdef");
+    }
+}
@@ -6,6 +6,8 @@ use std::collections::HashMap;
 use thiserror::Error;
 use uuid::Uuid;
 
+use crate::jobs::{EmbedEntryJob, JobError};
+
 /// Embedding vector (384 dimensions for MiniLM)
 pub type Embedding = Vec<f32>;
 
@@ -153,7 +155,7 @@ impl MockEmbeddingService {
         (0..self.config.dimensions)
             .map(|i| {
                 let seed = hash.wrapping_add(i as u64);
-                let mut rng = (seed * 1103515245 + 12345) as u32;
+                let mut rng = seed.wrapping_mul(1103515245).wrapping_add(12345) as u32;
                 rng = rng.wrapping_mul(1103515245).wrapping_add(12345);
                 (rng as f32) / (u32::MAX as f32) * 2.0 - 1.0
             })
@@ -320,6 +322,347 @@ pub struct SuggestedTagsJob {
     pub existing_tags: Vec<String>,
 }
 
+/// Width of `entry_embedding.embedding`; every backend must produce vectors of this size
+pub const ENTRY_EMBEDDING_DIMENSIONS: usize = 384;
+
+/// Largest artifact whose content is read for embedding
+pub const MAX_EMBED_SOURCE_BYTES: u64 = 1024 * 1024;
+
+pub const UPSERT_ENTRY_EMBEDDING_SQL: &str =
+    "INSERT INTO entry_embedding (commit_id, path, model, embedding)
+     VALUES ($1, $2, $3, $4::vector)
+     ON CONFLICT (commit_id, path)
+     DO UPDATE SET model = EXCLUDED.model, embedding = EXCLUDED.embedding, created_at = NOW()";
+
+/// pgvector's text form of an embedding, e.g. `[0.5,-1,0]`
+pub fn vector_literal(embedding: &[f32]) -> String {
+    let values: Vec<String> = embedding.iter().map(|v| v.to_string()).collect();
+    format!("[{}]", values.join(","))
+}
+
+/// Where embeddings are generated
+#[derive(Debug, Clone, PartialEq)]
+pub enum EmbeddingBackend {
+    /// Deterministic hashing embedder running in-process; needs no external service
+    Local,
+    /// Any server speaking the OpenAI `/embeddings` API
+    OpenAi {
+        base_url: String,
+        api_key: Option<String>,
+        model: String,
+    },
+}
+
+impl EmbeddingBackend {
+    /// Read `EMBEDDING_BACKEND` (`local` or `openai`) and the OpenAI settings
+    pub fn from_env() -> Result<Self, EmbeddingError> {
+        let backend = std::env::var("EMBEDDING_BACKEND").unwrap_or_else(|_| "local".to_string());
+        match backend.to_ascii_lowercase().as_str() {
+            "local" => Ok(EmbeddingBackend::Local),
+            "openai" => Ok(EmbeddingBackend::OpenAi {
+                base_url: std::env::var("EMBEDDING_API_URL")
+                    .unwrap_or_else(|_| "https://api.openai.com/v1".to_string()),
+                api_key: std::env::var("EMBEDDING_API_KEY").ok().filter(|key| !key.is_empty()),
+                model: std::env::var("EMBEDDING_MODEL").unwrap_or_else(|_| "text-embedding-3-small".to_string()),
+            }),
+            other => Err(EmbeddingError::ModelLoadingError(format!(
+                "Unknown EMBEDDING_BACKEND '{}', expected 'local' or 'openai'",
+                other
+            ))),
+        }
+    }
+
+    pub fn into_service(self) -> std::sync::Arc<dyn EmbeddingService> {
+        match self {
+            EmbeddingBackend::Local => std::sync::Arc::new(MockEmbeddingService::new()),
+            EmbeddingBackend::OpenAi { base_url, api_key, model } => {
+                std::sync::Arc::new(OpenAiEmbeddingService::new(base_url, api_key, model))
+            }
+        }
+    }
+}
+
+/// Embedding service backed by an OpenAI-compatible `/embeddings` endpoint
+pub struct OpenAiEmbeddingService {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+    config: EmbeddingModelConfig,
+}
+
+#[derive(Deserialize)]
+struct OpenAiEmbeddingsResponse {
+    data: Vec<OpenAiEmbedding>,
+}
+
+#[derive(Deserialize)]
+struct OpenAiEmbedding {
+    index: usize,
+    embedding: Embedding,
+}
+
+impl OpenAiEmbeddingService {
+    pub fn new(base_url: impl Into<String>, api_key: Option<String>, model: impl Into<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            api_key,
+            config: EmbeddingModelConfig {
+                model_name: model.into(),
+                dimensions: ENTRY_EMBEDDING_DIMENSIONS,
+                max_text_length: 8000,
+                batch_size: 64,
+            },
+        }
+    }
+
+    /// Embed `texts` in one request, returning vectors in input order
+    async fn embed(&self, texts: &[&str]) -> Result<Vec<Embedding>, EmbeddingError> {
+        for text in texts {
+            if text.len() > self.config.max_text_length {
+                return Err(EmbeddingError::TextTooLong(text.len(), self.config.max_text_length));
+            }
+        }
+
+        let url = format!("{}/embeddings", self.base_url);
+        let mut request = self.client.post(&url).json(&serde_json::json!({
+            "model": self.config.model_name,
+            "input": texts,
+            "dimensions": self.config.dimensions,
+        }));
+        if let Some(api_key) = &self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| EmbeddingError::EmbeddingGenerationError(format!("{}: {}", url, e)))?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(EmbeddingError::EmbeddingGenerationError(format!("{} returned {}: {}", url, status, body)));
+        }
+
+        let mut body: OpenAiEmbeddingsResponse = response
+            .json()
+            .await
+            .map_err(|e| EmbeddingError::EmbeddingGenerationError(format!("Invalid response from {}: {}", url, e)))?;
+        if body.data.len() != texts.len() {
+            return Err(EmbeddingError::EmbeddingGenerationError(format!(
+                "{} returned {} embeddings for {} inputs",
+                url,
+                body.data.len(),
+                texts.len()
+            )));
+        }
+        body.data.sort_by_key(|item| item.index);
+
+        body.data
+            .into_iter()
+            .map(|item| {
+                if item.embedding.len() != self.config.dimensions {
+                    return Err(EmbeddingError::InvalidDimensions(self.config.dimensions, item.embedding.len()));
+                }
+                Ok(item.embedding)
+            })
+            .collect()
+    }
+}
+
+#[async_trait::async_trait]
+impl EmbeddingService for OpenAiEmbeddingService {
+    async fn generate_embedding(&self, request: &EmbeddingRequest) -> Result<EmbeddingResponse, EmbeddingError> {
+        let start_time = std::time::Instant::now();
+        let embedding = self.embed(&[request.text.as_str()]).await?.remove(0);
+
+        Ok(EmbeddingResponse {
+            embedding,
+            model: self.config.model_name.clone(),
+            dimensions: self.config.dimensions,
+            processing_time_ms: start_time.elapsed().as_millis() as u64,
+        })
+    }
+
+    async fn generate_embeddings_batch(&self, requests: &[EmbeddingRequest]) -> Result<Vec<EmbeddingResponse>, EmbeddingError> {
+        let mut responses = Vec::with_capacity(requests.len());
+
+        for chunk in requests.chunks(self.config.batch_size) {
+            let start_time = std::time::Instant::now();
+            let texts: Vec<&str> = chunk.iter().map(|request| request.text.as_str()).collect();
+            let embeddings = self.embed(&texts).await?;
+            let processing_time = start_time.elapsed().as_millis() as u64;
+
+            responses.extend(embeddings.into_iter().map(|embedding| EmbeddingResponse {
+                embedding,
+                model: self.config.model_name.clone(),
+                dimensions: self.config.dimensions,
+                processing_time_ms: processing_time,
+            }));
+        }
+
+        Ok(responses)
+    }
+
+    async fn semantic_search(&self, _request: &SemanticSearchRequest) -> Result<SemanticSearchResponse, EmbeddingError> {
+        Err(EmbeddingError::SearchError(
+            "Nearest neighbours come from the index; embed the query and search entry_embedding".to_string(),
+        ))
+    }
+
+    async fn extract_suggested_tags(&self, _text: &str) -> Result<SuggestedTags, EmbeddingError> {
+        Err(EmbeddingError::NerError("Tag extraction is not supported by the OpenAI backend".to_string()))
+    }
+
+    fn get_model_config(&self) -> &EmbeddingModelConfig {
+        &self.config
+    }
+}
+
+/// Whether an artifact's bytes are worth embedding alongside its description
+pub fn is_text_like(content_type: Option<&str>, path: &str) -> bool {
+    if let Some(content_type) = content_type {
+        let essence = content_type.split(';').next().unwrap_or("").trim().to_ascii_lowercase();
+        if essence.starts_with("text/")
+            || matches!(
+                essence.as_str(),
+                "application/json" | "application/ld+json" | "application/xml" | "application/yaml" | "application/x-yaml" | "application/toml"
+            )
+        {
+            return true;
+        }
+    }
+
+    let extension = path.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase());
+    matches!(
+        extension.as_deref(),
+        Some("txt" | "md" | "rst" | "csv" | "tsv" | "json" | "jsonl" | "yaml" | "yml" | "xml" | "toml")
+    )
+}
+
+/// Text embedded for an entry: its description, then the start of its content.
+///
+/// Cut to `max_len` bytes on a character boundary; `None` when there is
+/// nothing to embed.
+pub fn entry_embedding_text(description: Option<&str>, content: Option<&[u8]>, max_len: usize) -> Option<String> {
+    let mut parts = Vec::new();
+    if let Some(description) = description.map(str::trim).filter(|d| !d.is_empty()) {
+        parts.push(description.to_string());
+    }
+    if let Some(content) = content {
+        let content = String::from_utf8_lossy(content);
+        let content = content.trim();
+        if !content.is_empty() {
+            parts.push(content.to_string());
+        }
+    }
+    if parts.is_empty() {
+        return None;
+    }
+
+    let mut text = parts.join("\n\n");
+    if text.len() > max_len {
+        let mut end = max_len;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
+    }
+    Some(text)
+}
+
+/// Persistence for entry embeddings
+#[async_trait::async_trait]
+pub trait EntryEmbeddingStore: Send + Sync {
+    /// S3 key of a stored object, if it exists
+    async fn object_key(&self, sha256: &str) -> Result<Option<String>, JobError>;
+
+    async fn store_embedding(&self, commit_id: Uuid, path: &str, model: &str, embedding: &[f32]) -> Result<(), JobError>;
+}
+
+/// Embedding store backed by `entry_embedding`
+pub struct PgEntryEmbeddingStore {
+    pool: sqlx::PgPool,
+}
+
+impl PgEntryEmbeddingStore {
+    pub fn new(pool: sqlx::PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl EntryEmbeddingStore for PgEntryEmbeddingStore {
+    async fn object_key(&self, sha256: &str) -> Result<Option<String>, JobError> {
+        sqlx::query_scalar("SELECT s3_key FROM object WHERE sha256 = $1")
+            .bind(sha256)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| JobError::Storage(format!("Failed to look up object {}: {}", sha256, e)))
+    }
+
+    async fn store_embedding(&self, commit_id: Uuid, path: &str, model: &str, embedding: &[f32]) -> Result<(), JobError> {
+        sqlx::query(UPSERT_ENTRY_EMBEDDING_SQL)
+            .bind(commit_id)
+            .bind(path)
+            .bind(model)
+            .bind(vector_literal(embedding))
+            .execute(&self.pool)
+            .await
+            .map_err(|e| JobError::Storage(format!("Failed to store embedding for {}: {}", path, e)))?;
+        Ok(())
+    }
+}
+
+/// Embed one committed entry and store its vector.
+///
+/// Text-like artifacts up to `MAX_EMBED_SOURCE_BYTES` contribute their
+/// content; anything else is embedded from its description alone. Returns
+/// `false` when the entry has no text to embed.
+pub async fn embed_entry(
+    job: &EmbedEntryJob,
+    s3_client: Option<&aws_sdk_s3::Client>,
+    bucket: &str,
+    service: &dyn EmbeddingService,
+    store: &dyn EntryEmbeddingStore,
+) -> Result<bool, JobError> {
+    let mut content = None;
+    if is_text_like(job.content_type.as_deref(), &job.path) && job.file_size <= MAX_EMBED_SOURCE_BYTES {
+        if let (Some(s3_client), Some(sha256)) = (s3_client, &job.object_sha256) {
+            if let Some(key) = store.object_key(sha256).await? {
+                let output = s3_client
+                    .get_object()
+                    .bucket(bucket)
+                    .key(&key)
+                    .send()
+                    .await
+                    .map_err(|e| JobError::Storage(format!("Failed to read {}: {}", key, e)))?;
+                content = Some(output.body.collect().await?.into_bytes());
+            }
+        }
+    }
+
+    let config = service.get_model_config();
+    let Some(text) = entry_embedding_text(job.description.as_deref(), content.as_deref(), config.max_text_length) else {
+        return Ok(false);
+    };
+
+    let response = service
+        .generate_embedding(&EmbeddingRequest { text, metadata: None })
+        .await
+        .map_err(|e| JobError::Processing(format!("Embedding failed for {}: {}", job.path, e)))?;
+    if response.embedding.len() != ENTRY_EMBEDDING_DIMENSIONS {
+        return Err(JobError::Processing(
+            EmbeddingError::InvalidDimensions(ENTRY_EMBEDDING_DIMENSIONS, response.embedding.len()).to_string(),
+        ));
+    }
+
+    store
+        .store_embedding(job.commit_id, &job.path, &response.model, &response.embedding)
+        .await?;
+    Ok(true)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -382,4 +725,251 @@ mod tests {
         assert_eq!(service.cosine_similarity(&a, &b), 1.0);
         assert_eq!(service.cosine_similarity(&a, &c), 0.0);
     }
+    
+    /// Embedding service that returns a fixed axis-aligned vector per text
+    struct StubEmbeddingService {
+        config: EmbeddingModelConfig,
+    }
+    
+    impl StubEmbeddingService {
+        fn new() -> Self {
+            Self {
+                config: EmbeddingModelConfig {
+                    model_name: "stub".to_string(),
+                    dimensions: ENTRY_EMBEDDING_DIMENSIONS,
+                    max_text_length: 64,
+                    batch_size: 8,
+                },
+            }
+        }
+    }
+    
+    #[async_trait::async_trait]
+    impl EmbeddingService for StubEmbeddingService {
+        async fn generate_embedding(&self, request: &EmbeddingRequest) -> Result<EmbeddingResponse, EmbeddingError> {
+            let mut embedding = vec![0.0; ENTRY_EMBEDDING_DIMENSIONS];
+            embedding[request.text.len() % ENTRY_EMBEDDING_DIMENSIONS] = 1.0;
+            Ok(EmbeddingResponse {
+                embedding,
+                model: request.text.clone(),
+                dimensions: ENTRY_EMBEDDING_DIMENSIONS,
+                processing_time_ms: 0,
+            })
+        }
+        
+        async fn generate_embeddings_batch(&self, requests: &[EmbeddingRequest]) -> Result<Vec<EmbeddingResponse>, EmbeddingError> {
+            let mut responses = Vec::new();
+            for request in requests {
+                responses.push(self.generate_embedding(request).await?);
+            }
+            Ok(responses)
+        }
+        
+        async fn semantic_search(&self, _request: &SemanticSearchRequest) -> Result<SemanticSearchResponse, EmbeddingError> {
+            unimplemented!()
+        }
+        
+        async fn extract_suggested_tags(&self, _text: &str) -> Result<SuggestedTags, EmbeddingError> {
+            unimplemented!()
+        }
+        
+        fn get_model_config(&self) -> &EmbeddingModelConfig {
+            &self.config
+        }
+    }
+    
+    /// Store that records embeddings in memory; the model name carries the embedded text
+    #[derive(Default)]
+    struct RecordingStore {
+        objects: HashMap<String, String>,
+        stored: std::sync::Mutex<Vec<(Uuid, String, String, usize)>>,
+    }
+    
+    #[async_trait::async_trait]
+    impl EntryEmbeddingStore for RecordingStore {
+        async fn object_key(&self, sha256: &str) -> Result<Option<String>, JobError> {
+            Ok(self.objects.get(sha256).cloned())
+        }
+        
+        async fn store_embedding(&self, commit_id: Uuid, path: &str, model: &str, embedding: &[f32]) -> Result<(), JobError> {
+            self.stored.lock().unwrap().push((commit_id, path.to_string(), model.to_string(), embedding.len()));
+            Ok(())
+        }
+    }
+    
+    fn embed_job(path: &str, content_type: Option<&str>, description: Option<&str>) -> EmbedEntryJob {
+        EmbedEntryJob {
+            repo_id: Uuid::new_v4(),
+            commit_id: Uuid::new_v4(),
+            path: path.to_string(),
+            object_sha256: Some("abc123".to_string()),
+            content_type: content_type.map(str::to_string),
+            description: description.map(str::to_string),
+            file_size: 16,
+        }
+    }
+    
+    #[test]
+    fn test_embed_job_for_change_reads_metadata_and_object() {
+        let change = |op, sha256: Option<&str>| crate::Change {
+            op,
+            path: "data/notes.txt".to_string(),
+            sha256: sha256.map(str::to_string),
+            meta: serde_json::json!({"description": "Field notes"}),
+        };
+        let object = crate::Object {
+            sha256: "abc123".to_string(),
+            size: 2048,
+            media_type: Some("text/plain".to_string()),
+            s3_key: "abc123".to_string(),
+            created_at: chrono::Utc::now(),
+        };
+        let (repo_id, commit_id) = (Uuid::new_v4(), Uuid::new_v4());
+
+        let job = EmbedEntryJob::for_change(repo_id, commit_id, &change(crate::ChangeOp::Add, Some("abc123")), Some(&object))
+            .unwrap();
+        assert_eq!((job.repo_id, job.commit_id, job.path.as_str()), (repo_id, commit_id, "data/notes.txt"));
+        assert_eq!(job.description.as_deref(), Some("Field notes"));
+        assert_eq!((job.content_type.as_deref(), job.file_size), (Some("text/plain"), 2048));
+
+        assert!(EmbedEntryJob::for_change(repo_id, commit_id, &change(crate::ChangeOp::Delete, None), None).is_none());
+        assert!(EmbedEntryJob::for_change(repo_id, commit_id, &change(crate::ChangeOp::Modify, None), None).is_none());
+    }
+
+    #[test]
+    fn test_vector_literal() {
+        assert_eq!(vector_literal(&[0.5, -1.0, 0.0]), "[0.5,-1,0]");
+        assert_eq!(vector_literal(&[]), "[]");
+    }
+    
+    #[test]
+    fn test_is_text_like() {
+        assert!(is_text_like(Some("text/plain; charset=utf-8"), "notes"));
+        assert!(is_text_like(Some("application/json"), "data.bin"));
+        assert!(is_text_like(Some("application/octet-stream"), "README.md"));
+        assert!(is_text_like(None, "data/temps.CSV"));
+        assert!(!is_text_like(Some("application/octet-stream"), "model.onnx"));
+        assert!(!is_text_like(None, "image.png"));
+    }
+    
+    #[test]
+    fn test_entry_embedding_text_joins_and_truncates_on_char_boundary() {
+        assert_eq!(entry_embedding_text(None, None, 64), None);
+        assert_eq!(entry_embedding_text(Some("  "), Some(b"\n"), 64), None);
+        assert_eq!(
+            entry_embedding_text(Some("Daily temps"), Some(b"day,temp\n"), 64).as_deref(),
+            Some("Daily temps\n\nday,temp")
+        );
+        // "é" is two bytes; a limit inside it backs off to the previous boundary
+        assert_eq!(entry_embedding_text(Some("caf\u{e9}"), None, 4).as_deref(), Some("caf"));
+    }
+    
+    #[tokio::test]
+    async fn test_embed_entry_includes_text_content() {
+        let s3 = crate::s3_mock::MockS3::default();
+        s3.put("blacklake/objects/abc123", b"day,temp\n1,12.5");
+        let s3_client = s3.client().await;
+        let store = RecordingStore {
+            objects: HashMap::from([("abc123".to_string(), "objects/abc123".to_string())]),
+            ..Default::default()
+        };
+        let job = embed_job("data/temps.csv", Some("text/csv"), Some("Daily temps"));
+        
+        let embedded = embed_entry(&job, Some(&s3_client), "blacklake", &StubEmbeddingService::new(), &store)
+            .await
+            .unwrap();
+        
+        assert!(embedded);
+        let stored = store.stored.lock().unwrap();
+        assert_eq!(
+            *stored,
+            vec![(job.commit_id, "data/temps.csv".to_string(), "Daily temps\n\nday,temp\n1,12.5".to_string(), ENTRY_EMBEDDING_DIMENSIONS)]
+        );
+    }
+    
+    #[tokio::test]
+    async fn test_embed_entry_uses_description_only_for_binary_artifacts() {
+        let s3 = crate::s3_mock::MockS3::default();
+        let s3_client = s3.client().await;
+        let store = RecordingStore {
+            objects: HashMap::from([("abc123".to_string(), "objects/abc123".to_string())]),
+            ..Default::default()
+        };
+        
+        let job = embed_job("models/net.onnx", Some("application/octet-stream"), Some("Classifier"));
+        assert!(embed_entry(&job, Some(&s3_client), "blacklake", &StubEmbeddingService::new(), &store).await.unwrap());
+        assert!(s3.reads().is_empty());
+        assert_eq!(store.stored.lock().unwrap()[0].2, "Classifier");
+        
+        let job = embed_job("models/net.onnx", Some("application/octet-stream"), None);
+        assert!(!embed_entry(&job, Some(&s3_client), "blacklake", &StubEmbeddingService::new(), &store).await.unwrap());
+        assert_eq!(store.stored.lock().unwrap().len(), 1);
+    }
+    
+    #[tokio::test]
+    async fn test_openai_backend_returns_vectors_in_input_order() {
+        async fn embeddings(
+            headers: axum::http::HeaderMap,
+            axum::Json(body): axum::Json<serde_json::Value>,
+        ) -> axum::Json<serde_json::Value> {
+            assert_eq!(headers["authorization"], "Bearer secret");
+            assert_eq!(body["model"], "text-embedding-3-small");
+            assert_eq!(body["dimensions"], ENTRY_EMBEDDING_DIMENSIONS);
+            let inputs = body["input"].as_array().unwrap();
+            // Answer out of order; the client sorts by index
+            let data: Vec<serde_json::Value> = inputs
+                .iter()
+                .enumerate()
+                .rev()
+                .map(|(index, _)| {
+                    let mut embedding = vec![0.0; ENTRY_EMBEDDING_DIMENSIONS];
+                    embedding[index] = 1.0;
+                    serde_json::json!({ "index": index, "embedding": embedding })
+                })
+                .collect();
+            axum::Json(serde_json::json!({ "data": data }))
+        }
+        
+        let app = axum::Router::new().route("/v1/embeddings", axum::routing::post(embeddings));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}/v1/", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        
+        let service = OpenAiEmbeddingService::new(base_url, Some("secret".to_string()), "text-embedding-3-small");
+        let requests: Vec<EmbeddingRequest> = ["first", "second"]
+            .iter()
+            .map(|text| EmbeddingRequest { text: text.to_string(), metadata: None })
+            .collect();
+        
+        let responses = service.generate_embeddings_batch(&requests).await.unwrap();
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0].embedding[0], 1.0);
+        assert_eq!(responses[1].embedding[1], 1.0);
+        assert_eq!(responses[1].model, "text-embedding-3-small");
+    }
+    
+    #[test]
+    fn test_embedding_backend_from_env() {
+        std::env::remove_var("EMBEDDING_BACKEND");
+        assert_eq!(EmbeddingBackend::from_env().unwrap(), EmbeddingBackend::Local);
+        
+        std::env::set_var("EMBEDDING_BACKEND", "openai");
+        std::env::set_var("EMBEDDING_API_URL", "http://localhost:11434/v1");
+        std::env::set_var("EMBEDDING_MODEL", "nomic-embed-text");
+        assert_eq!(
+            EmbeddingBackend::from_env().unwrap(),
+            EmbeddingBackend::OpenAi {
+                base_url: "http://localhost:11434/v1".to_string(),
+                api_key: None,
+                model: "nomic-embed-text".to_string(),
+            }
+        );
+        
+        std::env::set_var("EMBEDDING_BACKEND", "word2vec");
+        assert!(EmbeddingBackend::from_env().is_err());
+        
+        for var in ["EMBEDDING_BACKEND", "EMBEDDING_API_URL", "EMBEDDING_MODEL"] {
+            std::env::remove_var(var);
+        }
+    }
 }
@@ -0,0 +1,710 @@
+//! Export job execution.
+//!
+//! An export moves through `pending → running → completed | failed` in the
+//! `export_jobs` table. A completed export has its archive under
+//! `exports/{id}.tar.gz`, or `exports/{id}.zip` when the manifest asks for
+//! `"format": "zip"`, a presigned GET URL for it, and an `expires_at`
+//! matching the URL lifetime; the cleanup sweep removes exports once that
+//! time has passed.
+//!
+//! Manifest paths name exact files, directory prefixes, or `*`/`**` globs;
+//! they are resolved against the exported commit's tree before any bytes
+//! are copied, and the archive's `manifest.json` lists what they resolved to.
+//!
+//! Before an export is marked completed its archive is read back from S3 and
+//! must open as a complete archive holding every file that was written.
+
+use aws_sdk_s3::presigning::PresigningConfig;
+use chrono::{DateTime, Utc};
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::BTreeSet;
+use std::io::{self, Seek, Write};
+use std::time::Duration;
+use uuid::Uuid;
+use zip::write::SimpleFileOptions;
+
+use crate::governance::{ExportFormat, ExportManifest};
+use crate::jobs::{ExportJob, JobError};
+
+/// Default lifetime of an export download URL
+pub const DEFAULT_DOWNLOAD_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// SigV4 presigned URLs cannot outlive seven days
+pub const MAX_DOWNLOAD_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Read `EXPORT_DOWNLOAD_TTL_SECONDS`, clamped to S3's presign limit
+pub fn download_ttl_from_env() -> Duration {
+    match std::env::var("EXPORT_DOWNLOAD_TTL_SECONDS") {
+        Ok(raw) => match raw.parse::<u64>() {
+            Ok(seconds) if seconds > 0 => Duration::from_secs(seconds).min(MAX_DOWNLOAD_TTL),
+            _ => {
+                tracing::warn!("Invalid EXPORT_DOWNLOAD_TTL_SECONDS '{}', using default", raw);
+                DEFAULT_DOWNLOAD_TTL
+            }
+        },
+        Err(_) => DEFAULT_DOWNLOAD_TTL,
+    }
+}
+
+/// Whether one path segment matches a pattern segment, where `*` matches
+/// any run of characters and `?` any single character
+fn segment_matches(pattern: &[char], segment: &[char]) -> bool {
+    match pattern.split_first() {
+        None => segment.is_empty(),
+        Some(('*', rest)) => (0..=segment.len()).any(|i| segment_matches(rest, &segment[i..])),
+        Some(('?', rest)) => !segment.is_empty() && segment_matches(rest, &segment[1..]),
+        Some((c, rest)) => segment.first() == Some(c) && segment_matches(rest, &segment[1..]),
+    }
+}
+
+/// `**` matches any number of whole segments, including none
+fn segments_match(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|i| segments_match(rest, &path[i..])),
+        Some((segment, rest)) => match path.split_first() {
+            Some((first, remaining)) => {
+                let pattern: Vec<char> = segment.chars().collect();
+                let first: Vec<char> = first.chars().collect();
+                segment_matches(&pattern, &first) && segments_match(rest, remaining)
+            }
+            None => false,
+        },
+    }
+}
+
+fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?'])
+}
+
+/// Whether a manifest path selects `path`.
+///
+/// A glob must match the whole path. Anything else selects the file of that
+/// name and, as a directory prefix, everything beneath it; an empty path
+/// selects the whole tree.
+pub fn export_path_matches(pattern: &str, path: &str) -> bool {
+    let pattern = pattern.trim_matches('/');
+    if is_glob(pattern) {
+        let pattern: Vec<&str> = pattern.split('/').collect();
+        let path: Vec<&str> = path.split('/').collect();
+        return segments_match(&pattern, &path);
+    }
+    pattern.is_empty()
+        || path == pattern
+        || path.strip_prefix(pattern).is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// The part of a manifest path before its first wildcard, cut back to a
+/// directory boundary; every path it selects starts with this
+pub fn literal_prefix(pattern: &str) -> &str {
+    let pattern = pattern.trim_start_matches('/');
+    match pattern.find(['*', '?']) {
+        Some(wildcard) => pattern[..wildcard].rfind('/').map(|slash| &pattern[..=slash]).unwrap_or(""),
+        None => pattern.trim_end_matches('/'),
+    }
+}
+
+/// The distinct `paths` selected by any of `patterns`, in path order
+pub fn resolve_export_paths<'a>(patterns: &[String], paths: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let resolved: BTreeSet<&str> = paths
+        .into_iter()
+        .filter(|path| patterns.iter().any(|pattern| export_path_matches(pattern, path)))
+        .collect();
+    resolved.into_iter().map(str::to_string).collect()
+}
+
+/// One file an export contains
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportFile {
+    pub path: String,
+    pub sha256: String,
+}
+
+/// `manifest.json` inside an export archive: the request as submitted and
+/// the files it resolved to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResolvedExportManifest {
+    #[serde(flatten)]
+    pub request: ExportManifest,
+    pub commit_id: Uuid,
+    pub files: Vec<ExportFile>,
+}
+
+/// Key of an export's archive in the bucket
+pub fn export_key(export_id: Uuid, format: ExportFormat) -> String {
+    format!("exports/{}.{}", export_id, format.extension())
+}
+
+/// Writes an export's files into a `tar.gz` or `zip` archive
+pub enum ExportArchive<W: Write + Seek> {
+    TarGz(tar::Builder<GzEncoder<W>>),
+    /// Zip entries for each parent directory are written once, ahead of
+    /// the first file beneath it
+    Zip {
+        writer: zip::ZipWriter<W>,
+        directories: BTreeSet<String>,
+    },
+}
+
+impl<W: Write + Seek> ExportArchive<W> {
+    pub fn new(format: ExportFormat, writer: W) -> Self {
+        match format {
+            ExportFormat::TarGz => {
+                ExportArchive::TarGz(tar::Builder::new(GzEncoder::new(writer, flate2::Compression::default())))
+            }
+            ExportFormat::Zip => ExportArchive::Zip {
+                writer: zip::ZipWriter::new(writer),
+                directories: BTreeSet::new(),
+            },
+        }
+    }
+
+    /// Add a file at `path`, a `/`-separated path relative to the archive root
+    pub fn append(&mut self, path: &str, data: &[u8]) -> io::Result<()> {
+        match self {
+            ExportArchive::TarGz(builder) => {
+                let mut header = tar::Header::new_gnu();
+                header.set_path(path)?;
+                header.set_size(data.len() as u64);
+                header.set_mode(0o644);
+                header.set_cksum();
+                builder.append(&header, data)
+            }
+            ExportArchive::Zip { writer, directories } => {
+                let options = SimpleFileOptions::default().unix_permissions(0o644);
+                let mut parents: Vec<&str> = path.match_indices('/').map(|(i, _)| &path[..=i]).collect();
+                parents.retain(|dir| !directories.contains(*dir));
+                for dir in parents {
+                    writer.add_directory(dir, options.unix_permissions(0o755)).map_err(io::Error::other)?;
+                    directories.insert(dir.to_string());
+                }
+                writer.start_file(path, options).map_err(io::Error::other)?;
+                writer.write_all(data)
+            }
+        }
+    }
+
+    /// Write the archive's trailer and hand back the underlying writer
+    pub fn finish(self) -> io::Result<W> {
+        match self {
+            ExportArchive::TarGz(builder) => builder.into_inner()?.finish(),
+            ExportArchive::Zip { writer, .. } => writer.finish().map_err(io::Error::other),
+        }
+    }
+}
+
+/// Check that `bytes` is a complete `format` archive of `expected_files`
+/// files, reading every file through to catch truncated or corrupt data
+pub fn verify_export_archive(bytes: &[u8], format: ExportFormat, expected_files: usize) -> Result<(), String> {
+    let files = match format {
+        ExportFormat::TarGz => {
+            let unreadable = |e: io::Error| format!("not a readable tar.gz: {}", e);
+            let mut tar = tar::Archive::new(flate2::read::GzDecoder::new(bytes));
+            let mut files = 0;
+            for entry in tar.entries().map_err(unreadable)? {
+                io::copy(&mut entry.map_err(unreadable)?, &mut io::sink()).map_err(unreadable)?;
+                files += 1;
+            }
+            // The tar reader stops at the end-of-archive marker; reading on to
+            // the end of the gzip stream checks its length and CRC as well
+            io::copy(&mut tar.into_inner(), &mut io::sink()).map_err(unreadable)?;
+            files
+        }
+        ExportFormat::Zip => {
+            let unreadable = |e: &dyn std::fmt::Display| format!("not a readable zip: {}", e);
+            let mut zip = zip::ZipArchive::new(io::Cursor::new(bytes)).map_err(|e| unreadable(&e))?;
+            let mut files = 0;
+            for i in 0..zip.len() {
+                let mut entry = zip.by_index(i).map_err(|e| unreadable(&e))?;
+                if entry.is_dir() {
+                    continue;
+                }
+                // Reading a zip entry to the end checks its CRC
+                io::copy(&mut entry, &mut io::sink()).map_err(|e| unreadable(&e))?;
+                files += 1;
+            }
+            files
+        }
+    };
+
+    if files != expected_files {
+        return Err(format!("holds {} files, expected {}", files, expected_files));
+    }
+    Ok(())
+}
+
+/// Where a finished export can be fetched from, and until when
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportCompletion {
+    pub s3_key: String,
+    pub download_url: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Persists export status transitions
+#[async_trait::async_trait]
+pub trait ExportStatusStore: Send + Sync {
+    async fn mark_running(&self, export_id: Uuid) -> Result<(), JobError>;
+    async fn mark_completed(&self, export_id: Uuid, completion: &ExportCompletion) -> Result<(), JobError>;
+    async fn mark_failed(&self, export_id: Uuid, error: &str) -> Result<(), JobError>;
+}
+
+/// Status store backed by the `export_jobs` table
+pub struct PgExportStatusStore {
+    pool: PgPool,
+}
+
+impl PgExportStatusStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    async fn update(&self, query: sqlx::query::Query<'_, sqlx::Postgres, sqlx::postgres::PgArguments>) -> Result<(), JobError> {
+        query
+            .execute(&self.pool)
+            .await
+            .map_err(|e| JobError::Storage(format!("Failed to update export job: {}", e)))?;
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl ExportStatusStore for PgExportStatusStore {
+    async fn mark_running(&self, export_id: Uuid) -> Result<(), JobError> {
+        self.update(
+            sqlx::query("UPDATE export_jobs SET status = 'running', error_message = NULL WHERE id = $1")
+                .bind(export_id),
+        )
+        .await
+    }
+
+    async fn mark_completed(&self, export_id: Uuid, completion: &ExportCompletion) -> Result<(), JobError> {
+        self.update(
+            sqlx::query(
+                "UPDATE export_jobs SET status = 'completed', s3_key = $2, download_url = $3, expires_at = $4, error_message = NULL
+                 WHERE id = $1",
+            )
+            .bind(export_id)
+            .bind(&completion.s3_key)
+            .bind(&completion.download_url)
+            .bind(completion.expires_at),
+        )
+        .await
+    }
+
+    async fn mark_failed(&self, export_id: Uuid, error: &str) -> Result<(), JobError> {
+        self.update(
+            sqlx::query("UPDATE export_jobs SET status = 'failed', error_message = $2 WHERE id = $1")
+                .bind(export_id)
+                .bind(error),
+        )
+        .await
+    }
+}
+
+/// Presigned GET URL for an export archive, served as a `format` attachment
+pub async fn presign_download(
+    s3_client: &aws_sdk_s3::Client,
+    bucket: &str,
+    s3_key: &str,
+    format: ExportFormat,
+    ttl: Duration,
+) -> Result<String, JobError> {
+    let presigning_config = PresigningConfig::expires_in(ttl)
+        .map_err(|e| JobError::Processing(format!("Invalid presigning config: {}", e)))?;
+    let file_name = s3_key.rsplit('/').next().unwrap_or(s3_key);
+
+    let request = s3_client
+        .get_object()
+        .bucket(bucket)
+        .key(s3_key)
+        .response_content_type(format.content_type())
+        .response_content_disposition(format!("attachment; filename=\"{}\"", file_name))
+        .presigned(presigning_config)
+        .await
+        .map_err(|e| JobError::Storage(format!("Failed to presign export download: {}", e)))?;
+
+    Ok(request.uri().to_string())
+}
+
+/// Build, upload and publish an export, recording each status transition.
+///
+/// The job is marked failed with the error text if any step after
+/// `mark_running` fails.
+pub async fn run_export(
+    job: &ExportJob,
+    s3_client: &aws_sdk_s3::Client,
+    bucket: &str,
+    store: &dyn ExportStatusStore,
+    ttl: Duration,
+    now: DateTime<Utc>,
+) -> Result<ExportCompletion, JobError> {
+    store.mark_running(job.export_id).await?;
+
+    let result: Result<ExportCompletion, JobError> = async {
+        let (s3_key, files) = job
+            .create_export_archive(s3_client, bucket)
+            .await
+            .map_err(|e| JobError::Processing(format!("Failed to create export archive: {}", e)))?;
+        let stored = crate::derived::read_object(s3_client, bucket, &s3_key).await?;
+        verify_export_archive(&stored, job.format, files).map_err(|reason| {
+            JobError::Processing(format!("Export archive {} failed verification: {}", s3_key, reason))
+        })?;
+        let download_url = presign_download(s3_client, bucket, &s3_key, job.format, ttl).await?;
+        let expires_at = now
+            + chrono::Duration::from_std(ttl).map_err(|e| JobError::Processing(format!("Invalid download TTL: {}", e)))?;
+
+        Ok(ExportCompletion { s3_key, download_url, expires_at })
+    }
+    .await;
+
+    match result {
+        Ok(completion) => {
+            store.mark_completed(job.export_id, &completion).await?;
+            Ok(completion)
+        }
+        Err(e) => {
+            store.mark_failed(job.export_id, &e.to_string()).await?;
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::s3_mock::MockS3;
+    use std::collections::HashMap;
+    use std::io::Read;
+    use std::sync::Mutex;
+
+    /// Status store that records transitions in memory
+    #[derive(Default)]
+    struct RecordingStore {
+        transitions: Mutex<Vec<(String, Option<ExportCompletion>, Option<String>)>>,
+    }
+
+    impl RecordingStore {
+        fn statuses(&self) -> Vec<String> {
+            self.transitions.lock().unwrap().iter().map(|(status, _, _)| status.clone()).collect()
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl ExportStatusStore for RecordingStore {
+        async fn mark_running(&self, _export_id: Uuid) -> Result<(), JobError> {
+            self.transitions.lock().unwrap().push(("running".to_string(), None, None));
+            Ok(())
+        }
+
+        async fn mark_completed(&self, _export_id: Uuid, completion: &ExportCompletion) -> Result<(), JobError> {
+            self.transitions.lock().unwrap().push(("completed".to_string(), Some(completion.clone()), None));
+            Ok(())
+        }
+
+        async fn mark_failed(&self, _export_id: Uuid, error: &str) -> Result<(), JobError> {
+            self.transitions.lock().unwrap().push(("failed".to_string(), None, Some(error.to_string())));
+            Ok(())
+        }
+    }
+
+    fn export_job(paths: &[&str]) -> ExportJob {
+        ExportJob {
+            export_id: Uuid::new_v4(),
+            repo_id: Uuid::new_v4(),
+            repo_name: "climate".to_string(),
+            manifest: serde_json::json!({
+                "artifacts": paths.iter().map(|path| serde_json::json!({ "path": path })).collect::<Vec<_>>(),
+            }),
+            include_metadata: true,
+            include_rdf: false,
+            format: ExportFormat::TarGz,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_successful_export_completes_with_working_download_url() {
+        let s3 = MockS3::default();
+        s3.put("blacklake/climate/data/temps.csv", b"day,temp\n1,12.5\n");
+        let s3_client = s3.client().await;
+        let store = RecordingStore::default();
+        let job = export_job(&["data/temps.csv"]);
+        let now = Utc::now();
+
+        let completion = run_export(&job, &s3_client, "blacklake", &store, Duration::from_secs(3600), now)
+            .await
+            .unwrap();
+
+        assert_eq!(store.statuses(), vec!["running", "completed"]);
+        assert_eq!(completion.s3_key, format!("exports/{}.tar.gz", job.export_id));
+        assert_eq!(completion.expires_at, now + chrono::Duration::hours(1));
+        assert!(completion.download_url.contains("X-Amz-Expires=3600"));
+
+        let archive = reqwest::get(&completion.download_url).await.unwrap();
+        assert!(archive.status().is_success());
+        let archive = archive.bytes().await.unwrap();
+
+        let mut tar = tar::Archive::new(flate2::read::GzDecoder::new(&archive[..]));
+        let mut files = HashMap::new();
+        for entry in tar.entries().unwrap() {
+            let mut entry = entry.unwrap();
+            let mut contents = String::new();
+            entry.read_to_string(&mut contents).unwrap();
+            files.insert(entry.path().unwrap().to_string_lossy().to_string(), contents);
+        }
+        assert_eq!(files["data/temps.csv"], "day,temp\n1,12.5\n");
+        assert!(files.contains_key("manifest.json"));
+    }
+
+    #[tokio::test]
+    async fn test_zip_export_records_format_and_extracts_expected_files() {
+        let s3 = MockS3::default();
+        s3.put("blacklake/climate/data/temps.csv", b"day,temp\n1,12.5\n");
+        s3.put("blacklake/climate/data/raw/jan.json", b"{\"day\": 1}");
+        let s3_client = s3.client().await;
+        let store = RecordingStore::default();
+        let job = ExportJob { format: ExportFormat::Zip, ..export_job(&["data/temps.csv", "data/raw/jan.json"]) };
+
+        let completion = run_export(&job, &s3_client, "blacklake", &store, Duration::from_secs(3600), Utc::now())
+            .await
+            .unwrap();
+
+        assert_eq!(completion.s3_key, format!("exports/{}.zip", job.export_id));
+        assert_eq!(s3.content_type(&format!("blacklake/{}", completion.s3_key)).as_deref(), Some("application/zip"));
+        assert!(completion.download_url.contains("response-content-type=application%2Fzip"));
+        assert!(completion.download_url.contains(&format!("{}.zip", job.export_id)));
+
+        let archive = reqwest::get(&completion.download_url).await.unwrap().bytes().await.unwrap();
+        let mut zip = zip::ZipArchive::new(std::io::Cursor::new(archive)).unwrap();
+        let mut read = |name: &str| {
+            let mut contents = String::new();
+            zip.by_name(name).unwrap().read_to_string(&mut contents).unwrap();
+            contents
+        };
+        assert_eq!(read("data/temps.csv"), "day,temp\n1,12.5\n");
+        assert_eq!(read("data/raw/jan.json"), "{\"day\": 1}");
+        let manifest: serde_json::Value = serde_json::from_str(&read("manifest.json")).unwrap();
+        assert_eq!(manifest["format"], "zip");
+        assert_eq!(manifest["artifacts"][1]["path"], "data/raw/jan.json");
+
+        let names: Vec<&str> = zip.file_names().collect();
+        assert!(names.contains(&"data/") && names.contains(&"data/raw/"), "{:?}", names);
+    }
+
+    #[test]
+    fn test_archive_formats_hold_the_same_files() {
+        let files = [("manifest.json", "{}"), ("a/b/c.txt", "nested"), ("a/d.txt", "sibling")];
+        let build = |format| {
+            let mut archive = ExportArchive::new(format, std::io::Cursor::new(Vec::new()));
+            for (path, contents) in files {
+                archive.append(path, contents.as_bytes()).unwrap();
+            }
+            archive.finish().unwrap().into_inner()
+        };
+
+        let tar_bytes = build(ExportFormat::TarGz);
+        let mut tar = tar::Archive::new(flate2::read::GzDecoder::new(&tar_bytes[..]));
+        let mut from_tar = HashMap::new();
+        for entry in tar.entries().unwrap() {
+            let mut entry = entry.unwrap();
+            let mut contents = String::new();
+            entry.read_to_string(&mut contents).unwrap();
+            from_tar.insert(entry.path().unwrap().to_string_lossy().to_string(), contents);
+        }
+
+        let mut zip = zip::ZipArchive::new(std::io::Cursor::new(build(ExportFormat::Zip))).unwrap();
+        let mut from_zip = HashMap::new();
+        for i in 0..zip.len() {
+            let mut entry = zip.by_index(i).unwrap();
+            if entry.is_dir() {
+                continue;
+            }
+            let mut contents = String::new();
+            entry.read_to_string(&mut contents).unwrap();
+            from_zip.insert(entry.name().to_string(), contents);
+        }
+
+        let expected: HashMap<String, String> = files.iter().map(|(p, c)| (p.to_string(), c.to_string())).collect();
+        assert_eq!(from_tar, expected);
+        assert_eq!(from_zip, expected);
+        // Each directory gets a single entry
+        assert_eq!(zip.len(), files.len() + 2);
+    }
+
+    #[tokio::test]
+    async fn test_truncated_archive_upload_marks_export_failed() {
+        for format in [ExportFormat::TarGz, ExportFormat::Zip] {
+            let s3 = MockS3::default();
+            // Random text, so the archive cannot compress below the cut
+            let data: String = (0..100).map(|_| Uuid::new_v4().to_string()).collect();
+            s3.put("blacklake/climate/data/temps.csv", data.as_bytes());
+            s3.truncate_uploads(300);
+            let store = RecordingStore::default();
+            let job = ExportJob { format, ..export_job(&["data/temps.csv"]) };
+
+            let err = run_export(&job, &s3.client().await, "blacklake", &store, DEFAULT_DOWNLOAD_TTL, Utc::now())
+                .await
+                .unwrap_err();
+
+            assert_eq!(store.statuses(), vec!["running", "failed"], "{:?}", format);
+            let message = store.transitions.lock().unwrap()[1].2.clone().unwrap();
+            assert!(message.contains("failed verification"), "{}", message);
+            assert_eq!(message, err.to_string());
+        }
+    }
+
+    #[test]
+    fn test_archive_verification_checks_the_file_count() {
+        for format in [ExportFormat::TarGz, ExportFormat::Zip] {
+            let mut archive = ExportArchive::new(format, std::io::Cursor::new(Vec::new()));
+            archive.append("manifest.json", b"{}").unwrap();
+            archive.append("data/a.csv", b"a,b\n1,2\n").unwrap();
+            let bytes = archive.finish().unwrap().into_inner();
+
+            assert_eq!(verify_export_archive(&bytes, format, 2), Ok(()));
+            assert_eq!(
+                verify_export_archive(&bytes, format, 3),
+                Err("holds 2 files, expected 3".to_string())
+            );
+            assert!(verify_export_archive(&bytes[..bytes.len() - 10], format, 2).is_err(), "{:?}", format);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_missing_artifact_marks_export_failed() {
+        let s3_client = MockS3::default().client().await;
+        let store = RecordingStore::default();
+        let job = export_job(&["data/missing.csv"]);
+
+        let result = run_export(&job, &s3_client, "blacklake", &store, DEFAULT_DOWNLOAD_TTL, Utc::now()).await;
+
+        assert!(result.is_err());
+        assert_eq!(store.statuses(), vec!["running", "failed"]);
+        let transitions = store.transitions.lock().unwrap();
+        assert!(transitions[1].2.as_ref().unwrap().contains("Failed to create export archive"));
+    }
+
+    #[tokio::test]
+    async fn test_completed_export_is_expired_once_its_url_lapses() {
+        let s3_client = MockS3::default().client().await;
+        let store = RecordingStore::default();
+        let now = Utc::now();
+
+        let completion = run_export(&export_job(&[]), &s3_client, "blacklake", &store, Duration::from_secs(60), now)
+            .await
+            .unwrap();
+
+        let stored = crate::governance::ExportJob {
+            id: Uuid::new_v4(),
+            repo_id: Uuid::new_v4(),
+            user_id: "user".to_string(),
+            manifest: crate::governance::ExportManifest {
+                ref_name: "main".to_string(),
+                paths: vec![],
+                include_meta: true,
+                include_rdf: false,
+                format: ExportFormat::TarGz,
+            },
+            status: crate::governance::ExportJobStatus::Completed,
+            s3_key: Some(completion.s3_key),
+            download_url: Some(completion.download_url),
+            error_message: None,
+            expires_at: Some(completion.expires_at),
+        };
+
+        assert!(!stored.is_expired(now));
+        assert!(stored.is_expired(now + chrono::Duration::seconds(60)));
+    }
+
+    fn patterns(patterns: &[&str]) -> Vec<String> {
+        patterns.iter().map(|p| p.to_string()).collect()
+    }
+
+    const TREE: &[&str] = &[
+        "README.md",
+        "datasets/climate/temps.csv",
+        "datasets/climate/raw/2024/jan.csv",
+        "datasets/climate/raw/2024/jan.json",
+        "datasets/ocean/salinity.csv",
+        "datasets-archive/old.csv",
+        "models/net.onnx",
+    ];
+
+    #[test]
+    fn test_recursive_prefix_selects_everything_beneath_it() {
+        let resolved = resolve_export_paths(&patterns(&["datasets/climate/", "models"]), TREE.iter().copied());
+
+        assert_eq!(
+            resolved,
+            vec![
+                "datasets/climate/raw/2024/jan.csv",
+                "datasets/climate/raw/2024/jan.json",
+                "datasets/climate/temps.csv",
+                "models/net.onnx",
+            ]
+        );
+        // A prefix stops at a directory boundary
+        assert_eq!(resolve_export_paths(&patterns(&["datasets"]), TREE.iter().copied()).len(), 4);
+    }
+
+    #[test]
+    fn test_globs_select_matching_files_once() {
+        let resolved = resolve_export_paths(
+            &patterns(&["datasets/**/*.csv", "datasets/*/temps.csv", "*.md"]),
+            TREE.iter().copied(),
+        );
+
+        assert_eq!(
+            resolved,
+            vec![
+                "README.md",
+                "datasets/climate/raw/2024/jan.csv",
+                "datasets/climate/temps.csv",
+                "datasets/ocean/salinity.csv",
+            ]
+        );
+        assert_eq!(resolve_export_paths(&patterns(&["**/jan.*"]), TREE.iter().copied()).len(), 2);
+        assert!(resolve_export_paths(&patterns(&["datasets/*.csv"]), TREE.iter().copied()).is_empty());
+    }
+
+    #[test]
+    fn test_literal_prefix_stops_before_first_wildcard() {
+        assert_eq!(literal_prefix("datasets/**/*.csv"), "datasets/");
+        assert_eq!(literal_prefix("datasets/clim*/x.csv"), "datasets/");
+        assert_eq!(literal_prefix("**/*.csv"), "");
+        assert_eq!(literal_prefix("/datasets/climate/"), "datasets/climate");
+    }
+
+    #[test]
+    fn test_resolved_manifest_keeps_request_fields_beside_files() {
+        let manifest = ResolvedExportManifest {
+            request: crate::governance::ExportManifest {
+                ref_name: "main".to_string(),
+                paths: patterns(&["datasets/**"]),
+                include_meta: true,
+                include_rdf: false,
+                format: ExportFormat::TarGz,
+            },
+            commit_id: Uuid::nil(),
+            files: vec![ExportFile { path: "datasets/a.csv".to_string(), sha256: "abcd".to_string() }],
+        };
+
+        let json = serde_json::to_value(&manifest).unwrap();
+        assert_eq!(json["ref_name"], "main");
+        assert_eq!(json["paths"][0], "datasets/**");
+        assert_eq!(json["files"][0]["path"], "datasets/a.csv");
+        assert_eq!(serde_json::from_value::<ResolvedExportManifest>(json).unwrap(), manifest);
+    }
+
+    #[test]
+    fn test_download_ttl_is_capped_at_presign_limit() {
+        std::env::set_var("EXPORT_DOWNLOAD_TTL_SECONDS", "9999999");
+        assert_eq!(download_ttl_from_env(), MAX_DOWNLOAD_TTL);
+        std::env::set_var("EXPORT_DOWNLOAD_TTL_SECONDS", "not-a-number");
+        assert_eq!(download_ttl_from_env(), DEFAULT_DOWNLOAD_TTL);
+        std::env::remove_var("EXPORT_DOWNLOAD_TTL_SECONDS");
+        assert_eq!(download_ttl_from_env(), DEFAULT_DOWNLOAD_TTL);
+    }
+}
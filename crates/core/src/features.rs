@@ -0,0 +1,330 @@
+//! Per-repository feature flags.
+//!
+//! Flags live in the repo's `features` JSONB column. Every flag the service
+//! reads is listed in [`RepoFeature`] with the type its value must have, so a
+//! misspelt key or a value of the wrong type is rejected when it is set
+//! instead of being stored and silently ignored. Setting a flag to `null`
+//! resets it to its default.
+
+use serde_json::Value;
+use thiserror::Error;
+
+/// Type a feature flag's value must have
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeatureType {
+    Bool,
+    String,
+    /// A whole number of seconds, at least 1
+    Seconds,
+    /// A non-empty list of non-empty glob patterns, such as `["image/*"]`
+    Patterns,
+    /// An absolute http(s) URL
+    Url,
+    /// A field search results may be sorted by, such as `"path"`
+    SearchSort,
+}
+
+impl FeatureType {
+    fn describe(&self) -> &'static str {
+        match self {
+            FeatureType::Bool => "a boolean",
+            FeatureType::String => "a string",
+            FeatureType::Seconds => "a whole number of seconds",
+            FeatureType::Patterns => "a non-empty list of patterns",
+            FeatureType::Url => "an absolute http(s) URL without a query or fragment",
+            FeatureType::SearchSort => "one of created_at, path, size",
+        }
+    }
+
+    fn accepts(&self, value: &Value) -> bool {
+        match self {
+            FeatureType::Bool => value.is_boolean(),
+            FeatureType::String => value.as_str().is_some_and(|s| !s.is_empty()),
+            FeatureType::Seconds => value.as_u64().is_some_and(|seconds| seconds > 0),
+            FeatureType::Patterns => value.as_array().is_some_and(|patterns| {
+                !patterns.is_empty() && patterns.iter().all(|p| p.as_str().is_some_and(|p| !p.is_empty()))
+            }),
+            FeatureType::Url => value.as_str().is_some_and(|url| crate::SubjectIriBase::parse(url).is_ok()),
+            FeatureType::SearchSort => value.as_str().is_some_and(|sort| sort.parse::<crate::SearchSort>().is_ok()),
+        }
+    }
+}
+
+/// A known feature flag
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RepoFeature {
+    /// Generate RDF on the fly when none is stored (default false)
+    AutoRdf,
+    /// Sample committed CSV/Parquet files (default true)
+    SamplingEnabled,
+    /// Render previews of committed pictures and PDFs (default true)
+    ThumbnailsEnabled,
+    /// Store a Parquet copy of each committed CSV (default false)
+    ParquetConversion,
+    /// KMS key for SSE-KMS uploads, overriding `S3_KMS_KEY_ID`
+    KmsKeyId,
+    /// Presigned upload URL lifetime, overriding `S3_UPLOAD_URL_TTL_SECONDS`
+    UploadUrlTtl,
+    /// Presigned download URL lifetime, overriding `S3_DOWNLOAD_URL_TTL_SECONDS`
+    DownloadUrlTtl,
+    /// Metadata schema collection commits are validated against (default "default")
+    Schema,
+    /// Pinned version of that schema; the collection's current one otherwise
+    SchemaVersion,
+    /// Refuse commits of model files without a trusted signature (default false)
+    RequireSignedModels,
+    /// Content types uploads and commits may have, as globs (default any)
+    AllowedContentTypes,
+    /// Content types uploads and commits may not have, as globs; wins over the allow list
+    BlockedContentTypes,
+    /// Base of generated RDF subject IRIs, overriding `RDF_BASE_IRI`
+    RdfBaseIri,
+    /// Order of search results when the request has no `sort` (default "created_at")
+    SearchSort,
+}
+
+impl RepoFeature {
+    pub const ALL: &'static [RepoFeature] = &[
+        RepoFeature::AutoRdf,
+        RepoFeature::SamplingEnabled,
+        RepoFeature::ThumbnailsEnabled,
+        RepoFeature::ParquetConversion,
+        RepoFeature::KmsKeyId,
+        RepoFeature::UploadUrlTtl,
+        RepoFeature::DownloadUrlTtl,
+        RepoFeature::Schema,
+        RepoFeature::SchemaVersion,
+        RepoFeature::RequireSignedModels,
+        RepoFeature::AllowedContentTypes,
+        RepoFeature::BlockedContentTypes,
+        RepoFeature::RdfBaseIri,
+        RepoFeature::SearchSort,
+    ];
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            RepoFeature::AutoRdf => "auto_rdf",
+            RepoFeature::SamplingEnabled => "sampling_enabled",
+            RepoFeature::ThumbnailsEnabled => "thumbnails_enabled",
+            RepoFeature::ParquetConversion => "parquet_conversion",
+            RepoFeature::KmsKeyId => "kms_key_id",
+            RepoFeature::UploadUrlTtl => "upload_url_ttl",
+            RepoFeature::DownloadUrlTtl => "download_url_ttl",
+            RepoFeature::Schema => "schema",
+            RepoFeature::SchemaVersion => "schema_version",
+            RepoFeature::RequireSignedModels => "require_signed_models",
+            RepoFeature::AllowedContentTypes => "allowed_content_types",
+            RepoFeature::BlockedContentTypes => "blocked_content_types",
+            RepoFeature::RdfBaseIri => "rdf_base_iri",
+            RepoFeature::SearchSort => "search_sort",
+        }
+    }
+
+    pub fn value_type(&self) -> FeatureType {
+        match self {
+            RepoFeature::AutoRdf
+            | RepoFeature::SamplingEnabled
+            | RepoFeature::ThumbnailsEnabled
+            | RepoFeature::ParquetConversion
+            | RepoFeature::RequireSignedModels => FeatureType::Bool,
+            RepoFeature::KmsKeyId | RepoFeature::Schema | RepoFeature::SchemaVersion => FeatureType::String,
+            RepoFeature::UploadUrlTtl | RepoFeature::DownloadUrlTtl => FeatureType::Seconds,
+            RepoFeature::AllowedContentTypes | RepoFeature::BlockedContentTypes => FeatureType::Patterns,
+            RepoFeature::RdfBaseIri => FeatureType::Url,
+            RepoFeature::SearchSort => FeatureType::SearchSort,
+        }
+    }
+
+    /// Check `value` for `key`, returning the flag it sets
+    pub fn validate(key: &str, value: &Value) -> Result<RepoFeature, FeatureError> {
+        let feature: RepoFeature = key.parse()?;
+        let value_type = feature.value_type();
+        if value.is_null() || value_type.accepts(value) {
+            Ok(feature)
+        } else {
+            Err(FeatureError::TypeMismatch {
+                key: key.to_string(),
+                expected: value_type.describe(),
+            })
+        }
+    }
+}
+
+impl std::str::FromStr for RepoFeature {
+    type Err = FeatureError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        RepoFeature::ALL
+            .iter()
+            .copied()
+            .find(|feature| feature.key() == s)
+            .ok_or_else(|| FeatureError::UnknownKey(s.to_string()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum FeatureError {
+    #[error("Unknown repository feature '{0}'; known features: {known}", known = known_keys())]
+    UnknownKey(String),
+    #[error("Repository feature '{key}' must be {expected}")]
+    TypeMismatch { key: String, expected: &'static str },
+}
+
+fn known_keys() -> String {
+    RepoFeature::ALL.iter().map(RepoFeature::key).collect::<Vec<_>>().join(", ")
+}
+
+/// A repo's feature flags with typed accessors that apply each flag's default
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RepoFeatures(pub Value);
+
+impl RepoFeatures {
+    /// The stored value of `feature`; `None` when unset or reset with `null`
+    pub fn get(&self, feature: RepoFeature) -> Option<&Value> {
+        self.0.get(feature.key()).filter(|value| !value.is_null())
+    }
+
+    fn flag(&self, feature: RepoFeature, default: bool) -> bool {
+        self.get(feature).and_then(Value::as_bool).unwrap_or(default)
+    }
+
+    fn text(&self, feature: RepoFeature) -> Option<&str> {
+        self.get(feature).and_then(Value::as_str)
+    }
+
+    fn patterns(&self, feature: RepoFeature) -> Vec<&str> {
+        self.get(feature)
+            .and_then(Value::as_array)
+            .map(|patterns| patterns.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn auto_rdf(&self) -> bool {
+        self.flag(RepoFeature::AutoRdf, false)
+    }
+
+    pub fn sampling_enabled(&self) -> bool {
+        self.flag(RepoFeature::SamplingEnabled, true)
+    }
+
+    pub fn thumbnails_enabled(&self) -> bool {
+        self.flag(RepoFeature::ThumbnailsEnabled, true)
+    }
+
+    pub fn parquet_conversion(&self) -> bool {
+        self.flag(RepoFeature::ParquetConversion, false)
+    }
+
+    pub fn require_signed_models(&self) -> bool {
+        self.flag(RepoFeature::RequireSignedModels, false)
+    }
+
+    pub fn kms_key_id(&self) -> Option<&str> {
+        self.text(RepoFeature::KmsKeyId)
+    }
+
+    pub fn schema(&self) -> &str {
+        self.text(RepoFeature::Schema).unwrap_or("default")
+    }
+
+    pub fn schema_version(&self) -> Option<&str> {
+        self.text(RepoFeature::SchemaVersion)
+    }
+
+    pub fn allowed_content_types(&self) -> Vec<&str> {
+        self.patterns(RepoFeature::AllowedContentTypes)
+    }
+
+    pub fn blocked_content_types(&self) -> Vec<&str> {
+        self.patterns(RepoFeature::BlockedContentTypes)
+    }
+
+    pub fn rdf_base_iri(&self) -> Option<&str> {
+        self.text(RepoFeature::RdfBaseIri)
+    }
+
+    pub fn search_sort(&self) -> crate::SearchSort {
+        self.text(RepoFeature::SearchSort)
+            .and_then(|sort| sort.parse().ok())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_valid_flag_is_accepted() {
+        assert_eq!(RepoFeature::validate("auto_rdf", &json!(true)), Ok(RepoFeature::AutoRdf));
+        assert_eq!(RepoFeature::validate("kms_key_id", &json!("alias/regulated")), Ok(RepoFeature::KmsKeyId));
+        assert_eq!(RepoFeature::validate("upload_url_ttl", &json!(900)), Ok(RepoFeature::UploadUrlTtl));
+        assert_eq!(RepoFeature::validate("auto_rdf", &Value::Null), Ok(RepoFeature::AutoRdf));
+        assert_eq!(
+            RepoFeature::validate("blocked_content_types", &json!(["application/x-msdownload", "video/*"])),
+            Ok(RepoFeature::BlockedContentTypes)
+        );
+        assert_eq!(
+            RepoFeature::validate("rdf_base_iri", &json!("https://data.example.org/ld/")),
+            Ok(RepoFeature::RdfBaseIri)
+        );
+        assert_eq!(RepoFeature::validate("search_sort", &json!("path")), Ok(RepoFeature::SearchSort));
+    }
+
+    #[test]
+    fn test_unknown_key_is_rejected() {
+        let err = RepoFeature::validate("auto_rdff", &json!(true)).unwrap_err();
+
+        assert_eq!(err, FeatureError::UnknownKey("auto_rdff".to_string()));
+        assert!(err.to_string().contains("auto_rdf,"), "{}", err);
+    }
+
+    #[test]
+    fn test_type_mismatch_is_rejected() {
+        for (key, value) in [
+            ("auto_rdf", json!("true")),
+            ("thumbnails_enabled", json!(1)),
+            ("kms_key_id", json!(42)),
+            ("schema", json!("")),
+            ("download_url_ttl", json!("3600")),
+            ("download_url_ttl", json!(0)),
+            ("download_url_ttl", json!(1.5)),
+            ("allowed_content_types", json!("image/*")),
+            ("allowed_content_types", json!([])),
+            ("allowed_content_types", json!(["image/*", ""])),
+            ("rdf_base_iri", json!("data.example.org/ld")),
+            ("search_sort", json!("name")),
+        ] {
+            let err = RepoFeature::validate(key, &value).unwrap_err();
+            assert!(matches!(err, FeatureError::TypeMismatch { .. }), "{} = {}", key, value);
+        }
+    }
+
+    #[test]
+    fn test_typed_accessors_apply_defaults() {
+        let unset = RepoFeatures::default();
+        assert!(!unset.auto_rdf());
+        assert!(unset.thumbnails_enabled());
+        assert!(unset.sampling_enabled());
+        assert_eq!(unset.schema(), "default");
+        assert_eq!(unset.kms_key_id(), None);
+        assert!(!unset.require_signed_models());
+        assert_eq!(unset.search_sort(), crate::SearchSort::CreatedAt);
+
+        let set = RepoFeatures(json!({
+            "auto_rdf": true,
+            "thumbnails_enabled": false,
+            "schema": "climate",
+            "schema_version": null,
+            "download_url_ttl": 900,
+            "search_sort": "size"
+        }));
+        assert!(set.auto_rdf());
+        assert!(!set.thumbnails_enabled());
+        assert_eq!(set.schema(), "climate");
+        assert_eq!(set.schema_version(), None);
+        assert_eq!(set.get(RepoFeature::DownloadUrlTtl), Some(&json!(900)));
+        assert_eq!(set.search_sort(), crate::SearchSort::Size);
+    }
+}
@@ -119,6 +119,23 @@ pub struct WebhookDelivery {
     pub delivered_at: Option<chrono::DateTime<chrono::Utc>>,
 }
 
+impl WebhookDelivery {
+    /// Stable delivery id for `event_type` about `subject` (e.g. a commit id),
+    /// so enqueueing the same event twice yields a single delivery
+    pub fn idempotent_id(webhook_id: Uuid, event_type: &str, subject: Uuid) -> Uuid {
+        use sha2::{Digest, Sha256};
+
+        let digest = Sha256::new()
+            .chain_update(webhook_id.as_bytes())
+            .chain_update(event_type.as_bytes())
+            .chain_update(subject.as_bytes())
+            .finalize();
+        let mut bytes = [0u8; 16];
+        bytes.copy_from_slice(&digest[..16]);
+        uuid::Builder::from_custom_bytes(bytes).into_uuid()
+    }
+}
+
 /// Webhook dead letter entry
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct WebhookDead {
@@ -137,6 +154,48 @@ pub struct ExportManifest {
     pub paths: Vec<String>,
     pub include_meta: bool,
     pub include_rdf: bool,
+    /// Archive the export is packaged in
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+/// Archive format of an export
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ExportFormat {
+    #[default]
+    #[serde(rename = "tar.gz")]
+    TarGz,
+    #[serde(rename = "zip")]
+    Zip,
+}
+
+impl ExportFormat {
+    /// File extension of the archive, without the leading dot
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::TarGz => "tar.gz",
+            ExportFormat::Zip => "zip",
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::TarGz => "application/gzip",
+            ExportFormat::Zip => "application/zip",
+        }
+    }
+}
+
+impl std::str::FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tar.gz" | "tgz" => Ok(ExportFormat::TarGz),
+            "zip" => Ok(ExportFormat::Zip),
+            _ => Err(format!("Unknown export format '{}'; expected 'tar.gz' or 'zip'", s)),
+        }
+    }
 }
 
 /// Export job status
@@ -144,7 +203,7 @@ pub struct ExportManifest {
 #[serde(rename_all = "snake_case")]
 pub enum ExportJobStatus {
     Pending,
-    Processing,
+    Running,
     Completed,
     Failed,
 }
@@ -155,7 +214,8 @@ impl std::str::FromStr for ExportJobStatus {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "pending" => Ok(ExportJobStatus::Pending),
-            "processing" => Ok(ExportJobStatus::Processing),
+            // "processing" was written by older releases
+            "running" | "processing" => Ok(ExportJobStatus::Running),
             "completed" => Ok(ExportJobStatus::Completed),
             "failed" => Ok(ExportJobStatus::Failed),
             _ => Err(format!("Unknown export job status: {}", s)),
@@ -167,7 +227,7 @@ impl std::fmt::Display for ExportJobStatus {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ExportJobStatus::Pending => write!(f, "pending"),
-            ExportJobStatus::Processing => write!(f, "processing"),
+            ExportJobStatus::Running => write!(f, "running"),
             ExportJobStatus::Completed => write!(f, "completed"),
             ExportJobStatus::Failed => write!(f, "failed"),
         }
@@ -185,6 +245,16 @@ pub struct ExportJob {
     pub s3_key: Option<String>,
     pub download_url: Option<String>,
     pub error_message: Option<String>,
+    /// When the download URL lapses and the export becomes eligible for cleanup
+    #[serde(default)]
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl ExportJob {
+    /// Whether the export's download window has closed
+    pub fn is_expired(&self, now: chrono::DateTime<chrono::Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
 }
 
 /// Check result for branch protection
@@ -221,6 +291,15 @@ pub struct CheckResult {
     pub output: Option<String>,
 }
 
+/// A change to an existing ref that branch protection may refuse
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefMutation {
+    /// Point the ref elsewhere; `fast_forward` when the new commit descends
+    /// from the current head
+    Update { fast_forward: bool },
+    Delete,
+}
+
 /// Policy evaluation result
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct PolicyEvaluation {
@@ -260,6 +339,18 @@ impl QuotaStatus {
             usage_percentage,
         }
     }
+
+    /// Warning to surface if `additional_bytes` more are stored, or `None`
+    /// while usage stays at or under the soft limit
+    pub fn soft_warning_after(&self, additional_bytes: u64) -> Option<crate::QuotaWarning> {
+        let projected = Self::new(self.current_bytes.saturating_add(additional_bytes), self.soft_limit, self.hard_limit);
+        projected.soft_warning.then_some(crate::QuotaWarning {
+            current_bytes: projected.current_bytes,
+            soft_limit: projected.soft_limit,
+            hard_limit: projected.hard_limit,
+            usage_percentage: projected.usage_percentage,
+        })
+    }
 }
 
 /// Webhook payload for artifact events
@@ -417,6 +508,41 @@ impl PolicyEngine {
         }
     }
 
+    /// Evaluate branch protection for moving or deleting a ref.
+    ///
+    /// A non-fast-forward update needs `allow_fast_forward` and a delete
+    /// needs `allow_delete`. A ref with `require_admin` is reserved to
+    /// admins, who may then override either rule.
+    pub fn evaluate_ref_mutation(
+        protected_ref: &ProtectedRef,
+        mutation: RefMutation,
+        is_admin: bool,
+    ) -> PolicyEvaluation {
+        let refused = match mutation {
+            RefMutation::Update { fast_forward: false } if !protected_ref.allow_fast_forward => Some(format!(
+                "Reference {} is protected from non-fast-forward updates",
+                protected_ref.ref_name
+            )),
+            RefMutation::Delete if !protected_ref.allow_delete => {
+                Some(format!("Reference {} is protected from deletion", protected_ref.ref_name))
+            }
+            _ => None,
+        };
+
+        let reason = match (protected_ref.require_admin, is_admin) {
+            (true, false) => Some("Admin access required".to_string()),
+            (true, true) => None,
+            (false, _) => refused,
+        };
+
+        PolicyEvaluation {
+            allowed: reason.is_none(),
+            reason,
+            required_checks: Vec::new(),
+            missing_reviewers: 0,
+        }
+    }
+
     /// Check if fast-forward is allowed
     pub fn is_fast_forward_allowed(protected_ref: &ProtectedRef) -> bool {
         protected_ref.allow_fast_forward
@@ -459,6 +585,28 @@ mod tests {
         assert_eq!(status.usage_percentage, 110.0);
     }
 
+    #[test]
+    fn test_soft_warning_after_crossing_soft_limit() {
+        let status = QuotaStatus::new(900, 1_000, 2_000);
+
+        let warning = status.soft_warning_after(600).unwrap();
+        assert_eq!(warning.current_bytes, 1_500);
+        assert_eq!(warning.usage_percentage, 75.0);
+        assert_eq!(warning.header_value(), "current=1500; soft=1000; hard=2000; percent=75.0");
+
+        let json = serde_json::to_value(&warning).unwrap();
+        assert_eq!(json["soft_limit"], 1_000);
+        assert_eq!(json["hard_limit"], 2_000);
+    }
+
+    #[test]
+    fn test_no_soft_warning_at_or_below_soft_limit() {
+        let status = QuotaStatus::new(900, 1_000, 2_000);
+        assert_eq!(status.soft_warning_after(0), None);
+        assert_eq!(status.soft_warning_after(100), None);
+        assert!(status.soft_warning_after(101).is_some());
+    }
+
     #[test]
     fn test_webhook_signature_generation() {
         let secret = "test-secret";
@@ -504,6 +652,54 @@ mod tests {
         assert_eq!(evaluation.reason, Some("Admin access required".to_string()));
     }
 
+    fn ref_protection(require_admin: bool, allow_fast_forward: bool, allow_delete: bool) -> ProtectedRef {
+        ProtectedRef {
+            id: Uuid::new_v4(),
+            repo_id: Uuid::new_v4(),
+            ref_name: "main".to_string(),
+            require_admin,
+            allow_fast_forward,
+            allow_delete,
+            required_checks: vec![],
+            required_reviewers: 0,
+            require_schema_pass: false,
+        }
+    }
+
+    #[test]
+    fn test_force_update_of_protected_ref_is_blocked() {
+        let protected_ref = ref_protection(false, false, true);
+
+        let forced = PolicyEngine::evaluate_ref_mutation(&protected_ref, RefMutation::Update { fast_forward: false }, true);
+        assert!(!forced.allowed);
+        assert_eq!(forced.reason.as_deref(), Some("Reference main is protected from non-fast-forward updates"));
+
+        let fast_forward = PolicyEngine::evaluate_ref_mutation(&protected_ref, RefMutation::Update { fast_forward: true }, false);
+        assert!(fast_forward.allowed);
+    }
+
+    #[test]
+    fn test_delete_of_protected_ref_is_blocked() {
+        let protected_ref = ref_protection(false, true, false);
+
+        let evaluation = PolicyEngine::evaluate_ref_mutation(&protected_ref, RefMutation::Delete, true);
+        assert!(!evaluation.allowed);
+        assert_eq!(evaluation.reason.as_deref(), Some("Reference main is protected from deletion"));
+        assert!(PolicyEngine::evaluate_ref_mutation(&ref_protection(false, true, true), RefMutation::Delete, false).allowed);
+    }
+
+    #[test]
+    fn test_admin_only_ref_lets_admins_override() {
+        let protected_ref = ref_protection(true, false, false);
+
+        assert!(PolicyEngine::evaluate_ref_mutation(&protected_ref, RefMutation::Delete, true).allowed);
+        assert!(PolicyEngine::evaluate_ref_mutation(&protected_ref, RefMutation::Update { fast_forward: false }, true).allowed);
+
+        let member = PolicyEngine::evaluate_ref_mutation(&protected_ref, RefMutation::Update { fast_forward: true }, false);
+        assert!(!member.allowed);
+        assert_eq!(member.reason.as_deref(), Some("Admin access required"));
+    }
+
     #[test]
     fn test_policy_evaluation_checks_required() {
         let protected_ref = ProtectedRef {
@@ -0,0 +1,804 @@
+//! Content-integrity verification.
+//!
+//! Objects are content addressed, so the bytes stored under an object's key
+//! must always hash to its `object.sha256`. A verification run walks the
+//! objects referenced by a repo (or by every repo) in sha256 order, re-hashes
+//! each one as it streams from S3, and records every object that no longer
+//! matches in its `integrity_report`. Progress is saved after each page so an
+//! interrupted run resumes from its cursor, and reads are paced so a run does
+//! not compete with user traffic.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sqlx::{PgPool, Row};
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::jobs::JobError;
+
+/// An object to verify and where it is stored
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectRef {
+    pub sha256: String,
+    pub s3_key: String,
+}
+
+/// Why an object failed verification
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FindingKind {
+    /// The stored bytes hash to something else
+    Mismatch,
+    /// Nothing is stored under the key
+    Missing,
+    /// The object could not be read; says nothing about its contents
+    Error,
+}
+
+impl FindingKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FindingKind::Mismatch => "mismatch",
+            FindingKind::Missing => "missing",
+            FindingKind::Error => "error",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IntegrityFinding {
+    pub sha256: String,
+    pub s3_key: String,
+    pub kind: FindingKind,
+    pub actual_sha256: Option<String>,
+    pub detail: Option<String>,
+}
+
+/// How far a run has got
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IntegrityProgress {
+    /// sha256 of the last object in the last completed page
+    pub cursor: Option<String>,
+    pub objects_checked: i64,
+    /// Mismatched or missing objects; read errors are not counted
+    pub mismatches: i64,
+}
+
+/// A verification run as stored, with the objects it flagged
+#[derive(Debug, Clone, Serialize)]
+pub struct IntegrityReport {
+    pub id: Uuid,
+    /// `None` for a run over every repo
+    pub repo_id: Option<Uuid>,
+    /// `running` or `completed`
+    pub status: String,
+    pub objects_checked: i64,
+    pub mismatches: i64,
+    pub started_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub findings: Vec<IntegrityFinding>,
+}
+
+/// Tuning for a verification run
+#[derive(Debug, Clone)]
+pub struct IntegrityOptions {
+    /// Objects fetched per page, and so between progress saves
+    pub batch_size: i64,
+    /// Upper bound on S3 reads per second; 0 disables pacing
+    pub objects_per_second: u32,
+}
+
+impl Default for IntegrityOptions {
+    fn default() -> Self {
+        Self {
+            batch_size: 100,
+            objects_per_second: 10,
+        }
+    }
+}
+
+/// Persistence for integrity reports
+#[async_trait::async_trait]
+pub trait IntegrityStore: Send + Sync {
+    /// Create the report if it does not exist yet and return its progress
+    async fn resume_report(&self, report_id: Uuid, repo_id: Option<Uuid>) -> Result<IntegrityProgress, JobError>;
+
+    /// Up to `limit` referenced objects whose sha256 sorts after `after`, in sha256 order
+    async fn referenced_objects(
+        &self,
+        repo_id: Option<Uuid>,
+        after: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<ObjectRef>, JobError>;
+
+    /// Record a finding; `false` if an earlier attempt at the same page already did
+    async fn record_finding(&self, report_id: Uuid, finding: &IntegrityFinding) -> Result<bool, JobError>;
+
+    async fn save_progress(&self, report_id: Uuid, progress: &IntegrityProgress) -> Result<(), JobError>;
+
+    async fn complete_report(&self, report_id: Uuid, progress: &IntegrityProgress) -> Result<(), JobError>;
+}
+
+/// Marks an object's bytes as checked against its sha256
+pub const MARK_OBJECT_VERIFIED_SQL: &str =
+    "UPDATE object SET verified_at = NOW() WHERE sha256 = $1 AND verified_at IS NULL";
+
+/// Drops the row of an object whose upload failed verification, unless an
+/// earlier upload verified it or an entry or derived artifact references it.
+/// Returns the sha256 only when the row was dropped.
+pub const DISCARD_UNVERIFIED_OBJECT_SQL: &str = "DELETE FROM object o
+     WHERE o.sha256 = $1
+       AND o.verified_at IS NULL
+       AND NOT EXISTS (SELECT 1 FROM entry e WHERE e.object_sha256 = o.sha256)
+       AND NOT EXISTS (SELECT 1 FROM derived_artifact d WHERE d.derived_sha256 = o.sha256)
+     RETURNING o.sha256";
+
+/// The `object` rows uploads are verified against.
+///
+/// Content addresses are shared, so a mismatched upload may only delete the
+/// blob under its key when nothing else can be relying on it.
+#[async_trait::async_trait]
+pub trait UploadStore: Send + Sync {
+    /// Record that the object's bytes hash to its sha256
+    async fn mark_verified(&self, sha256: &str) -> Result<(), JobError>;
+
+    /// Drop the row of an object whose upload did not match, unless it was
+    /// verified before or is referenced. Only when this returns `true` may
+    /// the blob be deleted.
+    async fn discard_unverified(&self, sha256: &str) -> Result<bool, JobError>;
+}
+
+/// Stream an object and return the hex sha256 of its bytes; `None` if it does not exist
+pub async fn hash_object(s3_client: &aws_sdk_s3::Client, bucket: &str, key: &str) -> Result<Option<String>, JobError> {
+    let output = match s3_client.get_object().bucket(bucket).key(key).send().await {
+        Ok(output) => output,
+        Err(e) if e.raw_response().is_some_and(|r| r.status().as_u16() == 404) => return Ok(None),
+        Err(e) => {
+            return Err(JobError::Storage(format!(
+                "Failed to read {}: {}",
+                key,
+                aws_sdk_s3::error::DisplayErrorContext(&e)
+            )))
+        }
+    };
+
+    let mut body = output.body;
+    let mut hasher = Sha256::new();
+    while let Some(chunk) = body.next().await {
+        hasher.update(&chunk?);
+    }
+
+    Ok(Some(hex::encode(hasher.finalize())))
+}
+
+/// Verify one object, returning a finding if it does not check out
+pub async fn check_object(s3_client: &aws_sdk_s3::Client, bucket: &str, object: &ObjectRef) -> Option<IntegrityFinding> {
+    let finding = |kind, actual_sha256, detail| IntegrityFinding {
+        sha256: object.sha256.clone(),
+        s3_key: object.s3_key.clone(),
+        kind,
+        actual_sha256,
+        detail,
+    };
+
+    match hash_object(s3_client, bucket, &object.s3_key).await {
+        Ok(Some(actual)) if actual == object.sha256 => None,
+        Ok(Some(actual)) => Some(finding(FindingKind::Mismatch, Some(actual), None)),
+        Ok(None) => Some(finding(FindingKind::Missing, None, None)),
+        Err(e) => Some(finding(FindingKind::Error, None, Some(e.to_string()))),
+    }
+}
+
+/// Check a freshly uploaded object against the sha256 its uploader claimed.
+///
+/// Used when S3 holds no checksum to compare with. An object that hashes to
+/// something else is deleted so its content address never serves the wrong
+/// bytes, and the upload is rejected with an error. A mismatched object that
+/// was verified before or is already referenced is kept, since deleting it
+/// would take data away from whoever relies on it.
+pub async fn verify_upload(
+    s3_client: &aws_sdk_s3::Client,
+    bucket: &str,
+    object: &ObjectRef,
+    uploads: &dyn UploadStore,
+) -> Result<(), JobError> {
+    let Some(finding) = check_object(s3_client, bucket, object).await else {
+        return uploads.mark_verified(&object.sha256).await;
+    };
+
+    match finding.kind {
+        FindingKind::Mismatch => {
+            let actual_sha256 = finding.actual_sha256.unwrap_or_default();
+            if !uploads.discard_unverified(&object.sha256).await? {
+                tracing::error!(
+                    "Object {} at {} hashes to {} but is verified or in use; it was kept",
+                    object.sha256,
+                    object.s3_key,
+                    actual_sha256
+                );
+                return Err(JobError::Processing(format!(
+                    "Upload {} claimed sha256 {} but hashes to {}; kept, as the object is verified or in use",
+                    object.s3_key, object.sha256, actual_sha256
+                )));
+            }
+            s3_client
+                .delete_object()
+                .bucket(bucket)
+                .key(&object.s3_key)
+                .send()
+                .await
+                .map_err(|e| JobError::Storage(format!("Failed to delete mismatched upload {}: {}", object.s3_key, e)))?;
+            Err(JobError::Processing(format!(
+                "Upload {} claimed sha256 {} but hashes to {}; deleted",
+                object.s3_key, object.sha256, actual_sha256
+            )))
+        }
+        FindingKind::Missing => Err(JobError::NotFound(format!("Upload {} not found", object.s3_key))),
+        FindingKind::Error => Err(JobError::Storage(finding.detail.unwrap_or_default())),
+    }
+}
+
+/// Verify a multipart upload assembled at `staging_key`, then move it to its
+/// content address.
+///
+/// Nothing is written to `object.s3_key` until the staged bytes hash to
+/// `object.sha256`, so a bad upload never replaces content someone else
+/// stored. The staging object is removed either way; on a mismatch the
+/// `object` row goes too, unless it was verified before or is referenced.
+pub async fn promote_upload(
+    s3_client: &aws_sdk_s3::Client,
+    bucket: &str,
+    staging_key: &str,
+    object: &ObjectRef,
+    uploads: &dyn UploadStore,
+) -> Result<(), JobError> {
+    let storage = blacklake_storage::StorageClient::from_client(s3_client.clone(), bucket);
+    let staged = ObjectRef {
+        sha256: object.sha256.clone(),
+        s3_key: staging_key.to_string(),
+    };
+
+    let Some(finding) = check_object(s3_client, bucket, &staged).await else {
+        storage
+            .copy_object(staging_key, &object.s3_key)
+            .await
+            .map_err(|e| JobError::Storage(format!("Failed to move {} to {}: {}", staging_key, object.s3_key, e)))?;
+        uploads.mark_verified(&object.sha256).await?;
+        if let Err(e) = storage.delete_object(staging_key, None).await {
+            tracing::warn!("Failed to delete staged upload {}: {}", staging_key, e);
+        }
+        return Ok(());
+    };
+
+    match finding.kind {
+        FindingKind::Mismatch => {
+            if let Err(e) = storage.delete_object(staging_key, None).await {
+                tracing::warn!("Failed to delete staged upload {}: {}", staging_key, e);
+            }
+            uploads.discard_unverified(&object.sha256).await?;
+            Err(JobError::Processing(format!(
+                "Upload {} claimed sha256 {} but hashes to {}; discarded",
+                staging_key,
+                object.sha256,
+                finding.actual_sha256.unwrap_or_default()
+            )))
+        }
+        FindingKind::Missing => Err(JobError::NotFound(format!("Upload {} not found", staging_key))),
+        FindingKind::Error => Err(JobError::Storage(finding.detail.unwrap_or_default())),
+    }
+}
+
+/// Run (or resume) verification for `report_id` and mark the report completed
+pub async fn verify_integrity(
+    report_id: Uuid,
+    repo_id: Option<Uuid>,
+    s3_client: &aws_sdk_s3::Client,
+    bucket: &str,
+    store: &dyn IntegrityStore,
+    options: &IntegrityOptions,
+) -> Result<IntegrityProgress, JobError> {
+    let mut progress = store.resume_report(report_id, repo_id).await?;
+    let mut pacer = (options.objects_per_second > 0).then(|| {
+        let mut interval = tokio::time::interval(Duration::from_secs(1) / options.objects_per_second);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        interval
+    });
+
+    loop {
+        let page = store
+            .referenced_objects(repo_id, progress.cursor.as_deref(), options.batch_size.max(1))
+            .await?;
+        let Some(last) = page.last() else {
+            break;
+        };
+
+        for object in &page {
+            if let Some(pacer) = pacer.as_mut() {
+                pacer.tick().await;
+            }
+
+            if let Some(finding) = check_object(s3_client, bucket, object).await {
+                tracing::warn!(
+                    "Integrity check {} for object {} at {}",
+                    finding.kind.as_str(),
+                    finding.sha256,
+                    finding.s3_key
+                );
+                let new = store.record_finding(report_id, &finding).await?;
+                if new && finding.kind != FindingKind::Error {
+                    progress.mismatches += 1;
+                }
+            }
+            progress.objects_checked += 1;
+        }
+
+        progress.cursor = Some(last.sha256.clone());
+        store.save_progress(report_id, &progress).await?;
+    }
+
+    store.complete_report(report_id, &progress).await?;
+    Ok(progress)
+}
+
+/// Integrity store backed by `integrity_report` and `integrity_report_finding`
+pub struct PgIntegrityStore {
+    pool: PgPool,
+}
+
+impl PgIntegrityStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+impl PgIntegrityStore {
+    /// A run and its findings, in the order they were found; `None` if there is no such run
+    pub async fn load_report(&self, report_id: Uuid) -> Result<Option<IntegrityReport>, JobError> {
+        let Some(row) = sqlx::query(
+            "SELECT id, repo_id, status, objects_checked, mismatches, started_at, completed_at
+             FROM integrity_report WHERE id = $1",
+        )
+        .bind(report_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(db_error)?
+        else {
+            return Ok(None);
+        };
+
+        let findings = sqlx::query(
+            "SELECT sha256, s3_key, kind, actual_sha256, detail FROM integrity_report_finding
+             WHERE report_id = $1 ORDER BY id",
+        )
+        .bind(report_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(db_error)?
+        .into_iter()
+        .map(|finding| IntegrityFinding {
+            sha256: finding.get("sha256"),
+            s3_key: finding.get("s3_key"),
+            kind: match finding.get::<String, _>("kind").as_str() {
+                "mismatch" => FindingKind::Mismatch,
+                "missing" => FindingKind::Missing,
+                _ => FindingKind::Error,
+            },
+            actual_sha256: finding.get("actual_sha256"),
+            detail: finding.get("detail"),
+        })
+        .collect();
+
+        Ok(Some(IntegrityReport {
+            id: row.get("id"),
+            repo_id: row.get("repo_id"),
+            status: row.get("status"),
+            objects_checked: row.get("objects_checked"),
+            mismatches: row.get("mismatches"),
+            started_at: row.get("started_at"),
+            completed_at: row.get("completed_at"),
+            findings,
+        }))
+    }
+}
+
+fn db_error(e: sqlx::Error) -> JobError {
+    JobError::Storage(format!("Integrity report query failed: {}", e))
+}
+
+#[async_trait::async_trait]
+impl IntegrityStore for PgIntegrityStore {
+    async fn resume_report(&self, report_id: Uuid, repo_id: Option<Uuid>) -> Result<IntegrityProgress, JobError> {
+        sqlx::query("INSERT INTO integrity_report (id, repo_id) VALUES ($1, $2) ON CONFLICT (id) DO NOTHING")
+            .bind(report_id)
+            .bind(repo_id)
+            .execute(&self.pool)
+            .await
+            .map_err(db_error)?;
+
+        let row = sqlx::query("SELECT cursor, objects_checked, mismatches FROM integrity_report WHERE id = $1")
+            .bind(report_id)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(db_error)?;
+
+        Ok(IntegrityProgress {
+            cursor: row.get("cursor"),
+            objects_checked: row.get("objects_checked"),
+            mismatches: row.get("mismatches"),
+        })
+    }
+
+    async fn referenced_objects(
+        &self,
+        repo_id: Option<Uuid>,
+        after: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<ObjectRef>, JobError> {
+        let rows = sqlx::query(
+            "SELECT o.sha256, o.s3_key FROM object o
+             WHERE ($2::text IS NULL OR o.sha256 > $2)
+               AND EXISTS (
+                 SELECT 1 FROM entry e JOIN commit c ON c.id = e.commit_id
+                 WHERE e.object_sha256 = o.sha256 AND ($1::uuid IS NULL OR c.repo_id = $1)
+               )
+             ORDER BY o.sha256
+             LIMIT $3",
+        )
+        .bind(repo_id)
+        .bind(after)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(db_error)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| ObjectRef {
+                sha256: row.get("sha256"),
+                s3_key: row.get("s3_key"),
+            })
+            .collect())
+    }
+
+    async fn record_finding(&self, report_id: Uuid, finding: &IntegrityFinding) -> Result<bool, JobError> {
+        let result = sqlx::query(
+            "INSERT INTO integrity_report_finding (report_id, sha256, s3_key, kind, actual_sha256, detail)
+             VALUES ($1, $2, $3, $4, $5, $6)
+             ON CONFLICT (report_id, sha256) DO NOTHING",
+        )
+        .bind(report_id)
+        .bind(&finding.sha256)
+        .bind(&finding.s3_key)
+        .bind(finding.kind.as_str())
+        .bind(&finding.actual_sha256)
+        .bind(&finding.detail)
+        .execute(&self.pool)
+        .await
+        .map_err(db_error)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn save_progress(&self, report_id: Uuid, progress: &IntegrityProgress) -> Result<(), JobError> {
+        sqlx::query(
+            "UPDATE integrity_report SET cursor = $2, objects_checked = $3, mismatches = $4, updated_at = NOW()
+             WHERE id = $1",
+        )
+        .bind(report_id)
+        .bind(&progress.cursor)
+        .bind(progress.objects_checked)
+        .bind(progress.mismatches)
+        .execute(&self.pool)
+        .await
+        .map_err(db_error)?;
+        Ok(())
+    }
+
+    async fn complete_report(&self, report_id: Uuid, progress: &IntegrityProgress) -> Result<(), JobError> {
+        sqlx::query(
+            "UPDATE integrity_report SET status = 'completed', objects_checked = $2, mismatches = $3,
+                 updated_at = NOW(), completed_at = NOW()
+             WHERE id = $1",
+        )
+        .bind(report_id)
+        .bind(progress.objects_checked)
+        .bind(progress.mismatches)
+        .execute(&self.pool)
+        .await
+        .map_err(db_error)?;
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl UploadStore for PgIntegrityStore {
+    async fn mark_verified(&self, sha256: &str) -> Result<(), JobError> {
+        sqlx::query(MARK_OBJECT_VERIFIED_SQL)
+            .bind(sha256)
+            .execute(&self.pool)
+            .await
+            .map_err(db_error)?;
+        Ok(())
+    }
+
+    async fn discard_unverified(&self, sha256: &str) -> Result<bool, JobError> {
+        let discarded: Option<String> = sqlx::query_scalar(DISCARD_UNVERIFIED_OBJECT_SQL)
+            .bind(sha256)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(db_error)?;
+        Ok(discarded.is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::s3_mock::MockS3;
+    use std::sync::Mutex;
+
+    fn sha256_hex(data: &[u8]) -> String {
+        hex::encode(Sha256::digest(data))
+    }
+
+    /// Store whose referenced objects come from a fixed list
+    #[derive(Default)]
+    struct MemoryStore {
+        objects: Vec<ObjectRef>,
+        progress: Mutex<IntegrityProgress>,
+        findings: Mutex<Vec<IntegrityFinding>>,
+        completed: Mutex<bool>,
+    }
+
+    impl MemoryStore {
+        fn new(mut objects: Vec<ObjectRef>) -> Self {
+            objects.sort_by(|a, b| a.sha256.cmp(&b.sha256));
+            Self { objects, ..Default::default() }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl IntegrityStore for MemoryStore {
+        async fn resume_report(&self, _report_id: Uuid, _repo_id: Option<Uuid>) -> Result<IntegrityProgress, JobError> {
+            Ok(self.progress.lock().unwrap().clone())
+        }
+
+        async fn referenced_objects(
+            &self,
+            _repo_id: Option<Uuid>,
+            after: Option<&str>,
+            limit: i64,
+        ) -> Result<Vec<ObjectRef>, JobError> {
+            Ok(self
+                .objects
+                .iter()
+                .filter(|object| after.is_none_or(|after| object.sha256.as_str() > after))
+                .take(limit as usize)
+                .cloned()
+                .collect())
+        }
+
+        async fn record_finding(&self, _report_id: Uuid, finding: &IntegrityFinding) -> Result<bool, JobError> {
+            let mut findings = self.findings.lock().unwrap();
+            if findings.iter().any(|f| f.sha256 == finding.sha256) {
+                return Ok(false);
+            }
+            findings.push(finding.clone());
+            Ok(true)
+        }
+
+        async fn save_progress(&self, _report_id: Uuid, progress: &IntegrityProgress) -> Result<(), JobError> {
+            *self.progress.lock().unwrap() = progress.clone();
+            Ok(())
+        }
+
+        async fn complete_report(&self, _report_id: Uuid, progress: &IntegrityProgress) -> Result<(), JobError> {
+            *self.progress.lock().unwrap() = progress.clone();
+            *self.completed.lock().unwrap() = true;
+            Ok(())
+        }
+    }
+
+    /// Upload records where `kept` objects are verified or referenced
+    #[derive(Default)]
+    struct MemoryUploads {
+        kept: Vec<String>,
+        verified: Mutex<Vec<String>>,
+        discarded: Mutex<Vec<String>>,
+    }
+
+    #[async_trait::async_trait]
+    impl UploadStore for MemoryUploads {
+        async fn mark_verified(&self, sha256: &str) -> Result<(), JobError> {
+            self.verified.lock().unwrap().push(sha256.to_string());
+            Ok(())
+        }
+
+        async fn discard_unverified(&self, sha256: &str) -> Result<bool, JobError> {
+            if self.kept.iter().any(|kept| kept == sha256) {
+                return Ok(false);
+            }
+            self.discarded.lock().unwrap().push(sha256.to_string());
+            Ok(true)
+        }
+    }
+
+    /// Store `data` under its content address and return the reference
+    fn stored(s3: &MockS3, data: &[u8]) -> ObjectRef {
+        let sha256 = sha256_hex(data);
+        let s3_key = format!("sha256/{}/{}/{}", &sha256[0..2], &sha256[2..4], sha256);
+        s3.put(&format!("blacklake/{}", s3_key), data);
+        ObjectRef { sha256, s3_key }
+    }
+
+    fn unthrottled(batch_size: i64) -> IntegrityOptions {
+        IntegrityOptions { batch_size, objects_per_second: 0 }
+    }
+
+    #[tokio::test]
+    async fn test_corrupted_object_is_flagged() {
+        let s3 = MockS3::default();
+        let healthy = stored(&s3, b"station,temp\nA,12.5\n");
+        let corrupted = stored(&s3, b"station,temp\nB,14.0\n");
+        // Same key, different bytes
+        s3.put(&format!("blacklake/{}", corrupted.s3_key), b"station,temp\nB,99.9\n");
+        let store = MemoryStore::new(vec![healthy, corrupted.clone()]);
+
+        let progress = verify_integrity(Uuid::new_v4(), None, &s3.client().await, "blacklake", &store, &unthrottled(10))
+            .await
+            .unwrap();
+
+        assert_eq!(progress.objects_checked, 2);
+        assert_eq!(progress.mismatches, 1);
+        assert!(*store.completed.lock().unwrap());
+
+        let findings = store.findings.lock().unwrap();
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].sha256, corrupted.sha256);
+        assert_eq!(findings[0].kind, FindingKind::Mismatch);
+        assert_eq!(findings[0].actual_sha256.as_deref(), Some(sha256_hex(b"station,temp\nB,99.9\n").as_str()));
+    }
+
+    #[tokio::test]
+    async fn test_missing_object_is_flagged() {
+        let s3 = MockS3::default();
+        let missing = ObjectRef {
+            sha256: sha256_hex(b"gone"),
+            s3_key: "sha256/aa/bb/gone".to_string(),
+        };
+        let store = MemoryStore::new(vec![missing]);
+
+        let progress = verify_integrity(Uuid::new_v4(), None, &s3.client().await, "blacklake", &store, &unthrottled(10))
+            .await
+            .unwrap();
+
+        assert_eq!(progress.mismatches, 1);
+        assert_eq!(store.findings.lock().unwrap()[0].kind, FindingKind::Missing);
+    }
+
+    #[tokio::test]
+    async fn test_verify_upload_accepts_matching_bytes() {
+        let s3 = MockS3::default();
+        let upload = stored(&s3, b"station,temp\nA,12.5\n");
+
+        let uploads = MemoryUploads::default();
+
+        verify_upload(&s3.client().await, "blacklake", &upload, &uploads).await.unwrap();
+
+        assert!(s3.objects.lock().unwrap().contains_key(&format!("blacklake/{}", upload.s3_key)));
+        assert_eq!(*uploads.verified.lock().unwrap(), vec![upload.sha256.clone()]);
+    }
+
+    #[tokio::test]
+    async fn test_verify_upload_deletes_mismatched_bytes() {
+        let s3 = MockS3::default();
+        let upload = stored(&s3, b"claimed");
+        s3.put(&format!("blacklake/{}", upload.s3_key), b"actually uploaded");
+        let uploads = MemoryUploads::default();
+
+        let err = verify_upload(&s3.client().await, "blacklake", &upload, &uploads).await.unwrap_err();
+
+        assert!(err.to_string().contains(&sha256_hex(b"actually uploaded")), "{}", err);
+        assert!(s3.objects.lock().unwrap().is_empty());
+        assert_eq!(*uploads.discarded.lock().unwrap(), vec![upload.sha256.clone()]);
+    }
+
+    #[tokio::test]
+    async fn test_verify_upload_keeps_a_verified_or_referenced_object() {
+        let s3 = MockS3::default();
+        let upload = stored(&s3, b"claimed");
+        s3.put(&format!("blacklake/{}", upload.s3_key), b"overwritten");
+        let uploads = MemoryUploads { kept: vec![upload.sha256.clone()], ..Default::default() };
+
+        let err = verify_upload(&s3.client().await, "blacklake", &upload, &uploads).await.unwrap_err();
+
+        assert!(err.to_string().contains("kept"), "{}", err);
+        assert!(s3.objects.lock().unwrap().contains_key(&format!("blacklake/{}", upload.s3_key)));
+        assert!(uploads.verified.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_promote_upload_moves_matching_bytes_to_the_content_address() {
+        let s3 = MockS3::default();
+        let data = b"station,temp\nA,12.5\n";
+        let sha256 = sha256_hex(data);
+        let object = ObjectRef {
+            s3_key: format!("sha256/{}/{}/{}", &sha256[0..2], &sha256[2..4], sha256),
+            sha256,
+        };
+        let staging_key = format!("uploads/{}/01", object.sha256);
+        s3.put(&format!("blacklake/{}", staging_key), data);
+        let uploads = MemoryUploads::default();
+
+        promote_upload(&s3.client().await, "blacklake", &staging_key, &object, &uploads).await.unwrap();
+
+        let objects = s3.objects.lock().unwrap();
+        assert_eq!(objects.get(&format!("blacklake/{}", object.s3_key)).map(Vec::as_slice), Some(&data[..]));
+        assert!(!objects.contains_key(&format!("blacklake/{}", staging_key)));
+        assert_eq!(*uploads.verified.lock().unwrap(), vec![object.sha256.clone()]);
+    }
+
+    #[tokio::test]
+    async fn test_promote_upload_never_overwrites_the_content_address_on_mismatch() {
+        let s3 = MockS3::default();
+        let object = stored(&s3, b"verified bytes");
+        let staging_key = format!("uploads/{}/01", object.sha256);
+        s3.put(&format!("blacklake/{}", staging_key), b"someone else's bytes");
+        let uploads = MemoryUploads { kept: vec![object.sha256.clone()], ..Default::default() };
+
+        let err = promote_upload(&s3.client().await, "blacklake", &staging_key, &object, &uploads)
+            .await
+            .unwrap_err();
+
+        assert!(err.to_string().contains(&sha256_hex(b"someone else's bytes")), "{}", err);
+        let objects = s3.objects.lock().unwrap();
+        assert_eq!(
+            objects.get(&format!("blacklake/{}", object.s3_key)).map(Vec::as_slice),
+            Some(&b"verified bytes"[..])
+        );
+        assert!(!objects.contains_key(&format!("blacklake/{}", staging_key)));
+        assert!(uploads.verified.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_run_resumes_from_saved_cursor() {
+        let s3 = MockS3::default();
+        let objects: Vec<ObjectRef> = (0..5).map(|i| stored(&s3, format!("object {}", i).as_bytes())).collect();
+        let store = MemoryStore::new(objects);
+        let already_checked = store.objects[..2].to_vec();
+        *store.progress.lock().unwrap() = IntegrityProgress {
+            cursor: Some(already_checked[1].sha256.clone()),
+            objects_checked: 2,
+            mismatches: 0,
+        };
+
+        let progress = verify_integrity(Uuid::new_v4(), None, &s3.client().await, "blacklake", &store, &unthrottled(2))
+            .await
+            .unwrap();
+
+        assert_eq!(progress.objects_checked, 5);
+        assert_eq!(progress.cursor.as_deref(), Some(store.objects[4].sha256.as_str()));
+        let reads = s3.reads();
+        assert_eq!(reads.len(), 3);
+        for object in &already_checked {
+            assert!(!reads.iter().any(|read| read.ends_with(&object.sha256)));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reads_are_paced() {
+        let s3 = MockS3::default();
+        let objects: Vec<ObjectRef> = (0..3).map(|i| stored(&s3, format!("object {}", i).as_bytes())).collect();
+        let store = MemoryStore::new(objects);
+        let options = IntegrityOptions { batch_size: 10, objects_per_second: 20 };
+
+        let started = std::time::Instant::now();
+        verify_integrity(Uuid::new_v4(), None, &s3.client().await, "blacklake", &store, &options)
+            .await
+            .unwrap();
+
+        // The first read is immediate, the next two wait 50ms each
+        assert!(started.elapsed() >= Duration::from_millis(100));
+    }
+}
@@ -7,7 +7,6 @@ use serde::{Deserialize, Serialize};
 use std::time::Duration;
 use thiserror::Error;
 use uuid::Uuid;
-use sqlx::Row;
 use std::io::Read;
 
 // Job types
@@ -35,6 +34,7 @@ pub struct JobContext {
     pub worker_id: String,
     pub s3_client: Option<aws_sdk_s3::Client>,
     pub db_pool: Option<sqlx::PgPool>,
+    pub solr: Option<crate::search::SolrClient>,
 }
 
 pub enum JobResponse {
@@ -147,11 +147,7 @@ pub trait BlackLakeJob: Job + Send + Sync + 'static {
     fn generate_rdf_from_manifest(&self) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
         Err("Method not implemented".into())
     }
-    
-    /// Perform full reindex (optional method)
-    async fn perform_full_reindex(&self, _db_pool: &sqlx::PgPool) -> Result<u32, Box<dyn std::error::Error + Send + Sync>> {
-        Err("Method not implemented".into())
-    }
+
 }
 
 /// Index entry job for Solr indexing
@@ -174,6 +170,52 @@ pub enum IndexOperation {
     Delete,
 }
 
+impl IndexEntryJob {
+    /// Solr document id: `{repo}:{ref}:{path}:{commit_id}`
+    pub fn solr_document_id(&self) -> String {
+        format!("{}:{}:{}:{}", self.repo_name, self.ref_name, self.path, self.commit_id)
+    }
+
+    pub fn solr_document(&self) -> crate::search::SolrDocument {
+        crate::search::SolrDocument {
+            id: self.solr_document_id(),
+            repo: self.repo_name.clone(),
+            r#ref: self.ref_name.clone(),
+            path: self.path.clone(),
+            commit_id: self.commit_id.to_string(),
+            file_name: self.path.rsplit('/').next().unwrap_or("").to_string(),
+            title: None,
+            description: None,
+            tags: vec![],
+            org_lab: "default".to_string(),
+            file_type: self.metadata.get("file_type").and_then(|v| v.as_str()).unwrap_or("unknown").to_string(),
+            file_size: self.metadata.get("file_size").and_then(|v| v.as_i64()).unwrap_or(0),
+            creation_dt: chrono::Utc::now().to_rfc3339(),
+            sha256: self.object_sha256.clone(),
+            content: None,
+            meta: self.metadata.clone(),
+        }
+    }
+
+    /// Query matching every indexed version of this path on this ref
+    pub fn solr_delete_query(&self) -> String {
+        let prefix = format!("{}:{}:{}:", self.repo_name, self.ref_name, self.path);
+        format!("id:{}*", solr_escape(&prefix))
+    }
+}
+
+/// Escape Solr query syntax characters in a term
+fn solr_escape(term: &str) -> String {
+    let mut escaped = String::with_capacity(term.len());
+    for c in term.chars() {
+        if "+-&|!(){}[]^\"~*?:\\/ ".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
 #[async_trait::async_trait]
 impl Job for IndexEntryJob {
     fn name(&self) -> &str {
@@ -199,7 +241,7 @@ impl BlackLakeJob for IndexEntryJob {
         Duration::from_secs(120)
     }
     
-    async fn process(&self, _ctx: &JobContext) -> Result<JobResponse, JobError> {
+    async fn process(&self, ctx: &JobContext) -> Result<JobResponse, JobError> {
         tracing::info!(
             "Processing index entry job: repo={}, path={}, operation={:?}",
             self.repo_name,
@@ -207,87 +249,24 @@ impl BlackLakeJob for IndexEntryJob {
             self.operation
         );
         
-        // Create Solr document
-        let doc = serde_json::json!({
-            "id": format!("{}:{}:{}", self.repo_id, self.commit_id, self.path),
-            "repo_id": self.repo_id.to_string(),
-            "repo_name": self.repo_name,
-            "commit_id": self.commit_id.to_string(),
-            "path": self.path,
-            "object_sha256": self.object_sha256,
-            "meta": self.metadata,
-            "_version_": 1
-        });
-        
+        let solr = ctx.solr.as_ref().ok_or_else(|| {
+            JobError::Processing("Solr client not configured for index entry job".to_string())
+        })?;
+        let solr_error = |e: crate::search::SolrError| JobError::Processing(format!("Solr request failed: {}", e));
+        let solr = solr.ensure_repo_collection(self.repo_id).await.map_err(solr_error)?;
+
         match self.operation {
-            IndexOperation::Index => {
-                // Index the document in Solr
+            IndexOperation::Index | IndexOperation::Update => {
                 tracing::info!("Indexing document: {}", self.path);
-                
-                // Convert to SolrDocument format
-                let solr_doc = crate::search::SolrDocument {
-                    id: format!("{}:{}:{}:{}", self.repo_name, self.ref_name, self.path, self.commit_id),
-                    repo: self.repo_name.clone(),
-                    r#ref: self.ref_name.clone(),
-                    path: self.path.clone(),
-                    commit_id: self.commit_id.to_string(),
-                    file_name: self.path.split('/').last().unwrap_or("").to_string(),
-                    title: None,
-                    description: None,
-                    tags: vec![],
-                    org_lab: "default".to_string(),
-                    file_type: self.metadata.get("file_type").and_then(|v| v.as_str()).unwrap_or("unknown").to_string(),
-                    file_size: self.metadata.get("file_size").and_then(|v| v.as_i64()).unwrap_or(0),
-                    creation_dt: chrono::Utc::now().to_rfc3339(),
-                    sha256: self.object_sha256.clone(),
-                    content: None,
-                    meta: self.metadata.clone(),
-                };
-                
-                // TODO: Use actual SolrClient instance for indexing
-                // This would require passing the SolrClient through the job context
-                tracing::info!("Document prepared for indexing: {}", self.path);
-            }
-            IndexOperation::Update => {
-                // Update the document in Solr
-                tracing::info!("Updating document: {}", self.path);
-                
-                // Convert to SolrDocument format (same as index)
-                let solr_doc = crate::search::SolrDocument {
-                    id: format!("{}:{}:{}:{}", self.repo_name, self.ref_name, self.path, self.commit_id),
-                    repo: self.repo_name.clone(),
-                    r#ref: self.ref_name.clone(),
-                    path: self.path.clone(),
-                    commit_id: self.commit_id.to_string(),
-                    file_name: self.path.split('/').last().unwrap_or("").to_string(),
-                    title: None,
-                    description: None,
-                    tags: vec![],
-                    org_lab: "default".to_string(),
-                    file_type: self.metadata.get("file_type").and_then(|v| v.as_str()).unwrap_or("unknown").to_string(),
-                    file_size: self.metadata.get("file_size").and_then(|v| v.as_i64()).unwrap_or(0),
-                    creation_dt: chrono::Utc::now().to_rfc3339(),
-                    sha256: self.object_sha256.clone(),
-                    content: None,
-                    meta: self.metadata.clone(),
-                };
-                
-                // TODO: Use actual SolrClient instance for updating
-                // This would require passing the SolrClient through the job context
-                tracing::info!("Document prepared for update: {}", self.path);
+                solr.index_document(&self.solr_document()).await.map_err(solr_error)?;
             }
             IndexOperation::Delete => {
-                // Delete the document from Solr
                 tracing::info!("Deleting document: {}", self.path);
-                
-                let query = format!("id:{}:{}:{}:*", self.repo_name, self.ref_name, self.path);
-                
-                // TODO: Use actual SolrClient instance for deletion
-                // This would require passing the SolrClient through the job context
-                tracing::info!("Document prepared for deletion: {}", self.path);
+                solr.delete_by_query(&self.solr_delete_query()).await.map_err(solr_error)?;
             }
         }
-        
+        solr.commit().await.map_err(solr_error)?;
+
         Ok(JobResponse::Success)
     }
 }
@@ -298,6 +277,7 @@ pub struct SamplingJob {
     pub repo_id: Uuid,
     pub repo_name: String,
     pub path: String,
+    pub commit_id: Uuid,
     pub object_sha256: String,
     pub file_type: String,
     pub file_size: u64,
@@ -328,7 +308,7 @@ impl BlackLakeJob for SamplingJob {
         Duration::from_secs(180)
     }
     
-    async fn process(&self, _ctx: &JobContext) -> Result<JobResponse, JobError> {
+    async fn process(&self, ctx: &JobContext) -> Result<JobResponse, JobError> {
         tracing::info!(
             "Processing sampling job: repo={}, path={}, type={}",
             self.repo_name,
@@ -340,16 +320,22 @@ impl BlackLakeJob for SamplingJob {
         match self.file_type.as_str() {
             "csv" => {
                 tracing::info!("Sampling CSV file: {}", self.path);
-                // Download file from S3, sample first N rows, extract schema
-                if let Some(s3_client) = &_ctx.s3_client {
+                // Read the leading rows from S3 and infer column types
+                if let Some(s3_client) = &ctx.s3_client {
                     match self.sample_csv_file(s3_client).await {
-                        Ok(sample_data) => {
+                        Ok((sample_data, schema)) => {
                             tracing::info!("CSV sampling completed for {}: {} rows sampled", self.path, sample_data.len());
                             // Store sample data in database for UI display
+                            let db_pool = ctx.db_pool.as_ref().ok_or_else(|| {
+                                JobError::Processing("Database pool not available to store sample".to_string())
+                            })?;
+                            store_entry_sample(db_pool, self.commit_id, &self.path, &sample_data, &schema)
+                                .await
+                                .map_err(|e| JobError::Storage(format!("Failed to store sample: {}", e)))?;
                         }
                         Err(e) => {
                             tracing::error!("Failed to sample CSV file {}: {}", self.path, e);
-                            return Err(JobError::Processing(format!("CSV sampling failed: {}", e)));
+                            return Err(e);
                         }
                     }
                 } else {
@@ -358,16 +344,22 @@ impl BlackLakeJob for SamplingJob {
             }
             "parquet" => {
                 tracing::info!("Sampling Parquet file: {}", self.path);
-                // Download file from S3, read metadata, sample data
-                if let Some(s3_client) = &_ctx.s3_client {
+                // Read the footer and first row group from S3, sample their rows
+                if let Some(s3_client) = &ctx.s3_client {
                     match self.sample_parquet_file(s3_client).await {
-                        Ok(sample_data) => {
+                        Ok((sample_data, schema)) => {
                             tracing::info!("Parquet sampling completed for {}: {} rows sampled", self.path, sample_data.len());
                             // Store sample data in database for UI display
+                            let db_pool = ctx.db_pool.as_ref().ok_or_else(|| {
+                                JobError::Processing("Database pool not available to store sample".to_string())
+                            })?;
+                            store_entry_sample(db_pool, self.commit_id, &self.path, &sample_data, &schema)
+                                .await
+                                .map_err(|e| JobError::Storage(format!("Failed to store sample: {}", e)))?;
                         }
                         Err(e) => {
                             tracing::error!("Failed to sample Parquet file {}: {}", self.path, e);
-                            return Err(JobError::Processing(format!("Parquet sampling failed: {}", e)));
+                            return Err(e);
                         }
                     }
                 } else {
@@ -385,77 +377,125 @@ impl BlackLakeJob for SamplingJob {
 }
 
 impl SamplingJob {
-    async fn sample_csv_file(&self, s3_client: &aws_sdk_s3::Client) -> Result<Vec<serde_json::Value>, Box<dyn std::error::Error + Send + Sync>> {
-        // Download file from S3
+    fn object_location(&self) -> (String, String) {
         let bucket = std::env::var("S3_BUCKET").unwrap_or_else(|_| "blacklake".to_string());
-        let key = format!("{}/{}", self.repo_name, self.path);
-        
-        let response = s3_client
-            .get_object()
-            .bucket(&bucket)
-            .key(&key)
-            .send()
-            .await?;
-        
-        let data = response.body.collect().await?.into_bytes();
-        
-        // Parse CSV and sample first 100 rows
-        let mut reader = csv::Reader::from_reader(data.as_ref());
-        let headers = reader.headers()?.clone();
-        let mut sample_data = Vec::new();
-        let mut row_count = 0;
-        let max_rows = 100;
-        
-        for result in reader.records() {
-            if row_count >= max_rows {
-                break;
-            }
-            
-            let record = result?;
-            let mut row = serde_json::Map::new();
-            
-            for (i, field) in record.iter().enumerate() {
-                if let Some(header) = headers.get(i) {
-                    row.insert(header.to_string(), serde_json::Value::String(field.to_string()));
-                }
+        (bucket, format!("{}/{}", self.repo_name, self.path))
+    }
+
+    async fn sample_csv_file(&self, s3_client: &aws_sdk_s3::Client) -> Result<(Vec<serde_json::Value>, serde_json::Value), JobError> {
+        let (bucket, key) = self.object_location();
+        crate::sampling::sample_csv_object(s3_client, &bucket, &key).await
+    }
+    
+    async fn sample_parquet_file(&self, s3_client: &aws_sdk_s3::Client) -> Result<(Vec<serde_json::Value>, serde_json::Value), JobError> {
+        let (bucket, key) = self.object_location();
+        crate::sampling::sample_parquet_object(s3_client, &bucket, &key).await
+    }
+}
+
+/// Most rows kept in a stored sample
+pub const MAX_SAMPLE_ROWS: usize = 100;
+/// Most bytes of serialized rows kept in a stored sample
+pub const MAX_SAMPLE_BYTES: usize = 256 * 1024;
+
+/// Upsert for `entry_sample`; shared with `IndexClient::upsert_entry_sample`
+pub const UPSERT_ENTRY_SAMPLE_SQL: &str =
+    "INSERT INTO entry_sample (commit_id, path, sample, schema)
+     VALUES ($1, $2, $3, $4)
+     ON CONFLICT (commit_id, path) DO UPDATE SET
+        sample = EXCLUDED.sample,
+        schema = EXCLUDED.schema,
+        created_at = now()";
+
+/// Sample the leading rows of a CSV file and infer a type for each column.
+///
+/// Rows are kept until `MAX_SAMPLE_ROWS` or `MAX_SAMPLE_BYTES` is reached;
+/// types are inferred from the rows that were kept.
+pub fn sample_csv(data: &[u8]) -> Result<(Vec<serde_json::Value>, serde_json::Value), csv::Error> {
+    let mut reader = csv::Reader::from_reader(data);
+    let headers = reader.headers()?.clone();
+    let mut column_types: Vec<Option<&'static str>> = vec![None; headers.len()];
+    let mut sample_data = Vec::new();
+    let mut sample_bytes = 0;
+
+    for result in reader.records() {
+        if sample_data.len() >= MAX_SAMPLE_ROWS {
+            break;
+        }
+
+        let record = result?;
+        let mut row = serde_json::Map::new();
+        for (i, field) in record.iter().enumerate() {
+            if let Some(header) = headers.get(i) {
+                row.insert(header.to_string(), serde_json::Value::String(field.to_string()));
             }
-            
-            sample_data.push(serde_json::Value::Object(row));
-            row_count += 1;
         }
-        
-        Ok(sample_data)
+
+        let row = serde_json::Value::Object(row);
+        sample_bytes += row.to_string().len();
+        if sample_bytes > MAX_SAMPLE_BYTES {
+            break;
+        }
+
+        for (column_type, field) in column_types.iter_mut().zip(record.iter()) {
+            *column_type = widen_column_type(*column_type, field);
+        }
+        sample_data.push(row);
     }
-    
-    async fn sample_parquet_file(&self, s3_client: &aws_sdk_s3::Client) -> Result<Vec<serde_json::Value>, Box<dyn std::error::Error + Send + Sync>> {
-        // Download file from S3
-        let bucket = std::env::var("S3_BUCKET").unwrap_or_else(|_| "blacklake".to_string());
-        let key = format!("{}/{}", self.repo_name, self.path);
-        
-        let response = s3_client
-            .get_object()
-            .bucket(&bucket)
-            .key(&key)
-            .send()
-            .await?;
-        
-        let data = response.body.collect().await?.into_bytes();
-        
-        // Parse Parquet file and sample first 100 rows
-        // Note: This is a simplified implementation. In production, you'd use a proper Parquet library
-        let mut sample_data = Vec::new();
-        
-        // For now, return a placeholder indicating Parquet sampling would be implemented
-        sample_data.push(serde_json::json!({
-            "message": "Parquet sampling not yet implemented",
-            "file": self.path,
-            "type": "parquet"
-        }));
-        
-        Ok(sample_data)
+
+    let columns: Vec<serde_json::Value> = headers
+        .iter()
+        .zip(column_types)
+        .map(|(name, column_type)| serde_json::json!({ "name": name, "type": column_type.unwrap_or("string") }))
+        .collect();
+
+    Ok((sample_data, serde_json::json!({ "columns": columns })))
+}
+
+/// Combine the type seen so far for a column with the type of one more value
+fn widen_column_type(current: Option<&'static str>, value: &str) -> Option<&'static str> {
+    let value = value.trim();
+    if value.is_empty() {
+        return current;
+    }
+
+    let observed = if value.parse::<i64>().is_ok() {
+        "integer"
+    } else if value.parse::<f64>().is_ok() {
+        "number"
+    } else if value.eq_ignore_ascii_case("true") || value.eq_ignore_ascii_case("false") {
+        "boolean"
+    } else {
+        "string"
+    };
+
+    match (current, observed) {
+        (None, observed) => Some(observed),
+        (Some(current), observed) if current == observed => Some(current),
+        (Some("integer"), "number") | (Some("number"), "integer") => Some("number"),
+        _ => Some("string"),
     }
 }
 
+/// Persist a sample through the job's database pool
+async fn store_entry_sample(
+    db_pool: &sqlx::PgPool,
+    commit_id: Uuid,
+    path: &str,
+    sample_data: &[serde_json::Value],
+    schema: &serde_json::Value,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(UPSERT_ENTRY_SAMPLE_SQL)
+        .bind(commit_id)
+        .bind(path)
+        .bind(serde_json::Value::Array(sample_data.to_vec()))
+        .bind(schema)
+        .execute(db_pool)
+        .await?;
+
+    Ok(())
+}
+
 /// RDF emission job
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RdfEmissionJob {
@@ -672,55 +712,87 @@ impl BlackLakeJob for AntivirusScanJob {
 }
 
 /// ClamAV scan result
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ScanResult {
     Clean,
     Infected(String),
     Error(String),
 }
 
+/// Bytes sent per INSTREAM chunk
+const CLAMAV_CHUNK_SIZE: usize = 64 * 1024;
+/// clamd's default `StreamMaxLength`; override with `CLAMAV_MAX_STREAM_BYTES`
+const CLAMAV_DEFAULT_MAX_STREAM_BYTES: usize = 25 * 1024 * 1024;
+const CLAMAV_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const CLAMAV_SCAN_TIMEOUT: Duration = Duration::from_secs(120);
+
 /// Scan file data with ClamAV daemon
 async fn scan_with_clamav(file_data: &[u8], host: &str, port: &str) -> Result<ScanResult, JobError> {
-    use std::io::Write;
-    use std::net::TcpStream;
-    
     let address = format!("{}:{}", host, port);
-    
-    // Connect to ClamAV daemon
-    let mut stream = TcpStream::connect(&address)
+    let max_stream_bytes = std::env::var("CLAMAV_MAX_STREAM_BYTES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(CLAMAV_DEFAULT_MAX_STREAM_BYTES);
+
+    let stream = tokio::time::timeout(CLAMAV_CONNECT_TIMEOUT, tokio::net::TcpStream::connect(&address))
+        .await
+        .map_err(|_| JobError::Timeout(format!("Connecting to ClamAV daemon at {}", address)))?
         .map_err(|e| JobError::Processing(format!("Failed to connect to ClamAV daemon: {}", e)))?;
-    
-    // Send SCAN command
-    stream.write_all(b"nSCAN\n")
-        .map_err(|e| JobError::Processing(format!("Failed to send SCAN command: {}", e)))?;
-    
-    // Send file data
-    stream.write_all(file_data)
-        .map_err(|e| JobError::Processing(format!("Failed to send file data: {}", e)))?;
-    
-    // Send end marker
-    stream.write_all(b"\x00")
-        .map_err(|e| JobError::Processing(format!("Failed to send end marker: {}", e)))?;
-    
-    // Read response
-    let mut response = String::new();
-    std::io::Read::read_to_string(&mut stream, &mut response)
+
+    tokio::time::timeout(CLAMAV_SCAN_TIMEOUT, clamav_instream(stream, file_data, max_stream_bytes))
+        .await
+        .map_err(|_| JobError::Timeout(format!("ClamAV scan via {}", address)))?
+}
+
+/// Run a clamd `zINSTREAM` exchange over an open connection.
+///
+/// Data is sent as chunks prefixed with their length as a 4-byte big-endian
+/// integer, followed by a zero-length chunk; clamd then replies with a single
+/// NUL-terminated line such as `stream: OK`.
+async fn clamav_instream<S>(mut stream: S, file_data: &[u8], max_stream_bytes: usize) -> Result<ScanResult, JobError>
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+{
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    if file_data.len() > max_stream_bytes {
+        return Ok(ScanResult::Error(format!(
+            "File size {} exceeds ClamAV stream limit of {} bytes",
+            file_data.len(),
+            max_stream_bytes
+        )));
+    }
+
+    let send_error = |e: std::io::Error| JobError::Processing(format!("Failed to stream data to ClamAV: {}", e));
+
+    stream.write_all(b"zINSTREAM\0").await.map_err(send_error)?;
+    for chunk in file_data.chunks(CLAMAV_CHUNK_SIZE) {
+        stream.write_all(&(chunk.len() as u32).to_be_bytes()).await.map_err(send_error)?;
+        stream.write_all(chunk).await.map_err(send_error)?;
+    }
+    stream.write_all(&0u32.to_be_bytes()).await.map_err(send_error)?;
+    stream.flush().await.map_err(send_error)?;
+
+    let mut reply = Vec::new();
+    stream
+        .read_to_end(&mut reply)
+        .await
         .map_err(|e| JobError::Processing(format!("Failed to read ClamAV response: {}", e)))?;
-    
-    // Parse response
-    if response.contains("OK") {
-        Ok(ScanResult::Clean)
-    } else if response.contains("FOUND") {
-        // Extract virus name from response
-        let virus_name = response
-            .lines()
-            .find(|line| line.contains("FOUND"))
-            .and_then(|line| line.split_whitespace().last())
-            .unwrap_or("Unknown")
-            .to_string();
-        Ok(ScanResult::Infected(virus_name))
+
+    Ok(parse_clamav_reply(&String::from_utf8_lossy(&reply)))
+}
+
+/// Interpret a clamd reply: `stream: OK`, `stream: <name> FOUND` or `<message> ERROR`
+fn parse_clamav_reply(reply: &str) -> ScanResult {
+    let reply = reply.trim_end_matches(['\0', '\n', '\r']).trim();
+    let body = reply.strip_prefix("stream:").map(str::trim).unwrap_or(reply);
+
+    if body == "OK" {
+        ScanResult::Clean
+    } else if let Some(virus_name) = body.strip_suffix(" FOUND") {
+        ScanResult::Infected(virus_name.trim().to_string())
     } else {
-        Ok(ScanResult::Error(response))
+        ScanResult::Error(reply.to_string())
     }
 }
 
@@ -733,82 +805,59 @@ pub struct ExportJob {
     pub manifest: serde_json::Value,
     pub include_metadata: bool,
     pub include_rdf: bool,
+    #[serde(default)]
+    pub format: crate::governance::ExportFormat,
 }
 
 impl ExportJob {
-    /// Create export tarball
-    async fn create_export_tarball(&self, s3_client: &aws_sdk_s3::Client) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
-        // Create temporary directory for export
-        let temp_dir = std::env::temp_dir().join(format!("export_{}", self.export_id));
-        std::fs::create_dir_all(&temp_dir)?;
-        
-        // Create tarball
-        let tar_path = temp_dir.join("export.tar");
-        let mut tar_builder = tar::Builder::new(std::fs::File::create(&tar_path)?);
-        
-        // Add manifest
-        let manifest_json = serde_json::to_string_pretty(&self.manifest)?;
-        let mut header = tar::Header::new_gnu();
-        header.set_path("manifest.json")?;
-        header.set_size(manifest_json.len() as u64);
-        header.set_cksum();
-        tar_builder.append(&header, manifest_json.as_bytes())?;
+    /// Create the export archive in `bucket`, in the job's format, and return
+    /// its key and the number of files written to it
+    pub(crate) async fn create_export_archive(&self, s3_client: &aws_sdk_s3::Client, bucket: &str) -> Result<(String, usize), Box<dyn std::error::Error + Send + Sync>> {
+        let mut archive = crate::export_jobs::ExportArchive::new(self.format, std::io::Cursor::new(Vec::new()));
+
+        // Add manifest, recording the format it was packaged in
+        let mut manifest = self.manifest.clone();
+        if let Some(fields) = manifest.as_object_mut() {
+            fields.insert("format".to_string(), serde_json::to_value(self.format)?);
+        }
+        archive.append("manifest.json", serde_json::to_string_pretty(&manifest)?.as_bytes())?;
+        let mut files = 1;
         
         // Add artifacts from manifest
         if let Some(artifacts) = self.manifest.get("artifacts").and_then(|a| a.as_array()) {
             for artifact in artifacts {
                 if let Some(path) = artifact.get("path").and_then(|p| p.as_str()) {
                     // Download artifact from S3
-                    let bucket = std::env::var("S3_BUCKET").unwrap_or_else(|_| "blacklake".to_string());
                     let key = format!("{}/{}", self.repo_name, path);
                     
                     let response = s3_client
                         .get_object()
-                        .bucket(&bucket)
+                        .bucket(bucket)
                         .key(&key)
                         .send()
                         .await?;
                     
                     let data = response.body.collect().await?.into_bytes();
-                    
-                    // Add to tarball
-                    let mut header = tar::Header::new_gnu();
-                    header.set_path(path)?;
-                    header.set_size(data.len() as u64);
-                    header.set_cksum();
-                    tar_builder.append(&header, &*data)?;
+                    archive.append(path, &data)?;
+                    files += 1;
                 }
             }
         }
         
-        tar_builder.finish()?;
-        
-        // Compress tarball
-        let gz_path = temp_dir.join("export.tar.gz");
-        let mut gz_encoder = flate2::write::GzEncoder::new(
-            std::fs::File::create(&gz_path)?,
-            flate2::Compression::default()
-        );
-        std::io::copy(&mut std::fs::File::open(&tar_path)?, &mut gz_encoder)?;
-        gz_encoder.finish()?;
+        let data = archive.finish()?.into_inner();
         
         // Upload to S3
-        let s3_key = format!("exports/{}.tar.gz", self.export_id);
-        let gz_data = std::fs::read(&gz_path)?;
-        
+        let s3_key = crate::export_jobs::export_key(self.export_id, self.format);
         s3_client
             .put_object()
-            .bucket(&std::env::var("S3_BUCKET").unwrap_or_else(|_| "blacklake".to_string()))
+            .bucket(bucket)
             .key(&s3_key)
-            .body(aws_sdk_s3::primitives::ByteStream::from(gz_data))
-            .content_type("application/gzip")
+            .body(aws_sdk_s3::primitives::ByteStream::from(data))
+            .content_type(self.format.content_type())
             .send()
             .await?;
         
-        // Cleanup
-        std::fs::remove_dir_all(&temp_dir)?;
-        
-        Ok(s3_key)
+        Ok((s3_key, files))
     }
     
     /// Generate RDF from manifest
@@ -865,33 +914,37 @@ impl BlackLakeJob for ExportJob {
         Duration::from_secs(1800) // 30 minutes
     }
     
-    async fn process(&self, _ctx: &JobContext) -> Result<JobResponse, JobError> {
+    async fn process(&self, ctx: &JobContext) -> Result<JobResponse, JobError> {
         tracing::info!(
             "Processing export job: export_id={}, repo={}",
             self.export_id,
             self.repo_name
         );
         
-        // Implement export logic
-        // This creates a tarball with the requested artifacts
-        if let Some(s3_client) = &_ctx.s3_client {
-            match self.create_export_tarball(s3_client).await {
-                Ok(export_path) => {
-                    tracing::info!("Export tarball created successfully: {}", export_path);
-                    // Store export metadata in database
-                    // This would typically update the export status in the database
-                }
-                Err(e) => {
-                    tracing::error!("Failed to create export tarball: {}", e);
-                    return Err(JobError::Processing(format!("Export failed: {}", e)));
-                }
+        let (Some(s3_client), Some(db_pool)) = (&ctx.s3_client, &ctx.db_pool) else {
+            tracing::warn!("S3 client or database not available for export: {}", self.repo_name);
+            return Err(JobError::Processing("S3 client or database not available".to_string()));
+        };
+        
+        let bucket = std::env::var("S3_BUCKET").unwrap_or_else(|_| "blacklake".to_string());
+        let store = crate::export_jobs::PgExportStatusStore::new(db_pool.clone());
+        let ttl = crate::export_jobs::download_ttl_from_env();
+        
+        match crate::export_jobs::run_export(self, s3_client, &bucket, &store, ttl, chrono::Utc::now()).await {
+            Ok(completion) => {
+                tracing::info!(
+                    "Export {} completed: {} (download expires {})",
+                    self.export_id,
+                    completion.s3_key,
+                    completion.expires_at
+                );
+                Ok(JobResponse::Success)
+            }
+            Err(e) => {
+                tracing::error!("Export {} failed: {}", self.export_id, e);
+                Err(JobError::Processing(format!("Export failed: {}", e)))
             }
-        } else {
-            tracing::warn!("S3 client not available for export: {}", self.repo_name);
-            return Err(JobError::Processing("S3 client not available".to_string()));
         }
-        
-        Ok(JobResponse::Success)
     }
     
     async fn create_export_tarball(&self, s3_client: &aws_sdk_s3::Client) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
@@ -1005,6 +1058,9 @@ impl BlackLakeJob for ExportJob {
 }
 
 /// Full reindex job
+///
+/// Rebuilds the search documents for live entries; progress is kept in
+/// `reindex_run` under the job id, so a re-run with the same id resumes.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FullReindexJob {
     pub repo_id: Option<Uuid>, // None for full system reindex
@@ -1013,79 +1069,11 @@ pub struct FullReindexJob {
 }
 
 impl FullReindexJob {
-    /// Perform full reindex
-    async fn perform_full_reindex(&self, db_pool: &sqlx::PgPool) -> Result<u32, Box<dyn std::error::Error + Send + Sync>> {
-        let mut indexed_count = 0;
-        let batch_size = self.batch_size as i64;
-        
-        // Get all commits that need reindexing
-        let query = if let Some(repo_id) = self.repo_id {
-            if let Some(since_commit_id) = self.since_commit_id {
-                "SELECT id, repo_id, commit_hash, message, author, created_at FROM commits WHERE repo_id = $1 AND id > $2 ORDER BY created_at"
-            } else {
-                "SELECT id, repo_id, commit_hash, message, author, created_at FROM commits WHERE repo_id = $1 ORDER BY created_at"
-            }
-        } else {
-            if let Some(since_commit_id) = self.since_commit_id {
-                "SELECT id, repo_id, commit_hash, message, author, created_at FROM commits WHERE id > $1 ORDER BY created_at"
-            } else {
-                "SELECT id, repo_id, commit_hash, message, author, created_at FROM commits ORDER BY created_at"
-            }
-        };
-        
-        let mut rows = if let Some(repo_id) = self.repo_id {
-            if let Some(since_commit_id) = self.since_commit_id {
-                sqlx::query(query)
-                    .bind(repo_id)
-                    .bind(since_commit_id)
-                    .fetch_all(db_pool)
-                    .await?
-            } else {
-                sqlx::query(query)
-                    .bind(repo_id)
-                    .fetch_all(db_pool)
-                    .await?
-            }
-        } else {
-            if let Some(since_commit_id) = self.since_commit_id {
-                sqlx::query(query)
-                    .bind(since_commit_id)
-                    .fetch_all(db_pool)
-                    .await?
-            } else {
-                sqlx::query(query)
-                    .fetch_all(db_pool)
-                    .await?
-            }
-        };
-        
-        // Process commits in batches
-        for chunk in rows.chunks(batch_size as usize) {
-            for row in chunk {
-                let commit_id: Uuid = row.get("id");
-                let repo_id: Uuid = row.get("repo_id");
-                let commit_hash: String = row.get("commit_hash");
-                let message: String = row.get("message");
-                let author: String = row.get("author");
-                let created_at: chrono::DateTime<chrono::Utc> = row.get("created_at");
-                
-                // Reindex this commit
-                tracing::info!("Reindexing commit {} in repo {}", commit_hash, repo_id);
-                
-                // This would typically:
-                // 1. Get all files in the commit
-                // 2. Extract metadata from each file
-                // 3. Update search index
-                // 4. Update RDF store
-                
-                indexed_count += 1;
-            }
-            
-            // Small delay between batches to avoid overwhelming the system
-            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+    pub fn scope(&self) -> crate::reindex::ReindexScope {
+        crate::reindex::ReindexScope {
+            repo_id: self.repo_id,
+            since_commit_id: self.since_commit_id,
         }
-        
-        Ok(indexed_count)
     }
 }
 
@@ -1114,7 +1102,7 @@ impl BlackLakeJob for FullReindexJob {
         Duration::from_secs(3600) // 1 hour
     }
     
-    async fn process(&self, _ctx: &JobContext) -> Result<JobResponse, JobError> {
+    async fn process(&self, ctx: &JobContext) -> Result<JobResponse, JobError> {
         tracing::info!(
             "Processing full reindex job: repo_id={:?}, since_commit={:?}, batch_size={}",
             self.repo_id,
@@ -1122,156 +1110,433 @@ impl BlackLakeJob for FullReindexJob {
             self.batch_size
         );
         
-        // Implement full reindex logic
-        // This iterates through all commits and reindexes them
-        if let Some(db_pool) = &_ctx.db_pool {
-            match self.perform_full_reindex(db_pool).await {
-                Ok(indexed_count) => {
-                    tracing::info!("Full reindex completed successfully: {} documents indexed", indexed_count);
-                }
-                Err(e) => {
-                    tracing::error!("Full reindex failed: {}", e);
-                    return Err(JobError::Processing(format!("Full reindex failed: {}", e)));
-                }
-            }
-        } else {
-            tracing::warn!("Database pool not available for full reindex");
-            return Err(JobError::Processing("Database pool not available".to_string()));
-        }
+        let (Some(db_pool), Some(solr)) = (&ctx.db_pool, &ctx.solr) else {
+            return Err(JobError::Processing("Database or Solr client not available".to_string()));
+        };
         
-        match self.repo_id {
-            Some(repo_id) => {
-                tracing::info!("Reindexing repository: {}", repo_id);
-            }
-            None => {
-                tracing::info!("Reindexing all repositories");
-            }
-        }
+        let store = crate::reindex::PgReindexStore::new(db_pool.clone());
+        let progress =
+            crate::reindex::run_reindex(ctx.job_id, &self.scope(), &store, solr, self.batch_size as i64).await?;
         
+        tracing::info!(
+            "Full reindex {} completed: {} documents indexed",
+            ctx.job_id,
+            progress.indexed_count
+        );
         Ok(JobResponse::Success)
     }
+}
+
+/// Content-integrity verification job
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerifyIntegrityJob {
+    /// Row in `integrity_report`; re-running with the same id resumes the run
+    pub report_id: Uuid,
+    pub repo_id: Option<Uuid>, // None verifies every repo
+    pub batch_size: u32,
+    pub objects_per_second: u32,
+}
+
+#[async_trait::async_trait]
+impl Job for VerifyIntegrityJob {
+    fn name(&self) -> &str {
+        "verify_integrity"
+    }
+}
+
+#[async_trait::async_trait]
+impl BlackLakeJob for VerifyIntegrityJob {
+    fn job_type(&self) -> &'static str {
+        "verify_integrity"
+    }
     
-    async fn perform_full_reindex(&self, db_pool: &sqlx::PgPool) -> Result<u32, Box<dyn std::error::Error + Send + Sync>> {
-        use sqlx::Row;
-        
-        let mut indexed_count = 0;
+    fn max_attempts(&self) -> u32 {
+        3 // Each attempt resumes from the saved cursor
+    }
+    
+    fn retry_delay(&self) -> Duration {
+        Duration::from_secs(600)
+    }
+    
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(6 * 3600)
+    }
+    
+    async fn process(&self, ctx: &JobContext) -> Result<JobResponse, JobError> {
+        tracing::info!(
+            "Processing integrity verification: report_id={}, repo_id={:?}",
+            self.report_id,
+            self.repo_id
+        );
         
-        // Build query based on scope
-        let query = match self.repo_id {
-            Some(repo_id) => {
-                "SELECT id, repo_id, path, object_sha256, metadata FROM tree_entries 
-                 WHERE repo_id = $1 
-                 ORDER BY created_at DESC"
-            }
-            None => {
-                "SELECT id, repo_id, path, object_sha256, metadata FROM tree_entries 
-                 ORDER BY created_at DESC"
-            }
+        let (Some(s3_client), Some(db_pool)) = (&ctx.s3_client, &ctx.db_pool) else {
+            return Err(JobError::Processing("S3 client or database not available".to_string()));
         };
         
-        let rows = if let Some(_repo_id) = self.repo_id {
-            sqlx::query(query)
-                .bind(_repo_id)
-                .fetch_all(db_pool)
-                .await?
-        } else {
-            sqlx::query(query)
-                .fetch_all(db_pool)
-                .await?
+        let bucket = std::env::var("S3_BUCKET").unwrap_or_else(|_| "blacklake".to_string());
+        let store = crate::integrity::PgIntegrityStore::new(db_pool.clone());
+        let options = crate::integrity::IntegrityOptions {
+            batch_size: self.batch_size as i64,
+            objects_per_second: self.objects_per_second,
         };
         
-        // Process in batches
-        let batch_size = self.batch_size as usize;
-        for chunk in rows.chunks(batch_size) {
-            for row in chunk {
-                let entry_id: Uuid = row.get("id");
-                let repo_id: Uuid = row.get("repo_id");
-                let path: String = row.get("path");
-                let object_sha256: String = row.get("object_sha256");
-                let metadata: serde_json::Value = row.get("metadata");
-                
-                // Create index job for this entry
-                let index_job = IndexEntryJob {
-                    repo_id,
-                    repo_name: "unknown".to_string(), // Would need to fetch from repo table
-                    ref_name: "main".to_string(), // Would need to determine from commit
-                    path: path.clone(),
-                    commit_id: entry_id, // Simplified - would need proper commit ID
-                    object_sha256,
-                    metadata,
-                    operation: IndexOperation::Index,
-                };
-                
-                // Process the index job
-                match index_job.process(&JobContext {
-                    job_id: Uuid::new_v4(),
-                    worker_id: "test-worker".to_string(),
-                    db_pool: Some(db_pool.clone()),
-                    s3_client: None, // Would be injected by job processor
-                }).await {
-                    Ok(_) => {
-                        indexed_count += 1;
-                        tracing::debug!("Indexed entry: {}", path);
-                    }
-                    Err(e) => {
-                        tracing::warn!("Failed to index entry {}: {}", path, e);
-                    }
-                }
-            }
-            
-            // Small delay between batches to avoid overwhelming the system
-            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-        }
+        let progress =
+            crate::integrity::verify_integrity(self.report_id, self.repo_id, s3_client, &bucket, &store, &options).await?;
         
-        Ok(indexed_count)
+        tracing::info!(
+            "Integrity verification {} completed: {} objects checked, {} mismatches",
+            self.report_id,
+            progress.objects_checked,
+            progress.mismatches
+        );
+        Ok(JobResponse::Success)
     }
 }
 
-/// Job queue configuration
-#[derive(Debug, Clone)]
-pub struct JobQueueConfig {
-    pub name: String,
-    pub concurrency: u32,
-    pub max_attempts: u32,
-    pub retry_delay: Duration,
-    pub timeout: Duration,
+/// Re-hash an upload S3 could not verify against its claimed sha256
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerifyUploadJob {
+    pub sha256: String,
+    pub s3_key: String,
+    /// Where a multipart upload was assembled; it is copied to `s3_key`
+    /// only once its bytes check out
+    #[serde(default)]
+    pub staging_key: Option<String>,
 }
 
-impl JobQueueConfig {
-    pub fn index_queue() -> Self {
-        Self {
-            name: "index".to_string(),
-            concurrency: 5,
-            max_attempts: 5,
-            retry_delay: Duration::from_secs(30),
-            timeout: Duration::from_secs(120),
-        }
+#[async_trait::async_trait]
+impl Job for VerifyUploadJob {
+    fn name(&self) -> &str {
+        "verify_upload"
+    }
+}
+
+#[async_trait::async_trait]
+impl BlackLakeJob for VerifyUploadJob {
+    fn job_type(&self) -> &'static str {
+        "verify_upload"
     }
     
-    pub fn sampling_queue() -> Self {
-        Self {
-            name: "sampling".to_string(),
-            concurrency: 3,
-            max_attempts: 3,
-            retry_delay: Duration::from_secs(60),
-            timeout: Duration::from_secs(180),
-        }
+    fn max_attempts(&self) -> u32 {
+        3
     }
     
-    pub fn rdf_queue() -> Self {
-        Self {
-            name: "rdf".to_string(),
-            concurrency: 2,
-            max_attempts: 3,
-            retry_delay: Duration::from_secs(45),
-            timeout: Duration::from_secs(120),
-        }
+    fn retry_delay(&self) -> Duration {
+        Duration::from_secs(60)
     }
     
-    pub fn antivirus_queue() -> Self {
-        Self {
-            name: "antivirus".to_string(),
-            concurrency: 2,
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(3600) // Streams the whole object
+    }
+    
+    async fn process(&self, ctx: &JobContext) -> Result<JobResponse, JobError> {
+        tracing::info!("Processing upload verification: sha256={}", self.sha256);
+        
+        let s3_client = ctx.s3_client.as_ref().ok_or_else(|| {
+            JobError::Processing("S3 client not available".to_string())
+        })?;
+        
+        let db_pool = ctx.db_pool.as_ref().ok_or_else(|| {
+            JobError::Processing("Database pool not available to record verification".to_string())
+        })?;
+
+        let bucket = std::env::var("S3_BUCKET").unwrap_or_else(|_| "blacklake".to_string());
+        let object = crate::integrity::ObjectRef {
+            sha256: self.sha256.clone(),
+            s3_key: self.s3_key.clone(),
+        };
+        let uploads = crate::integrity::PgIntegrityStore::new(db_pool.clone());
+        match &self.staging_key {
+            Some(staging_key) => {
+                crate::integrity::promote_upload(s3_client, &bucket, staging_key, &object, &uploads).await?
+            }
+            None => crate::integrity::verify_upload(s3_client, &bucket, &object, &uploads).await?,
+        }
+        
+        Ok(JobResponse::Success)
+    }
+}
+
+/// Detect a committed entry's media type from its content
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SniffMediaTypeJob {
+    pub commit_id: Uuid,
+    pub path: String,
+    pub sha256: String,
+}
+
+#[async_trait::async_trait]
+impl Job for SniffMediaTypeJob {
+    fn name(&self) -> &str {
+        "sniff_media_type"
+    }
+}
+
+#[async_trait::async_trait]
+impl BlackLakeJob for SniffMediaTypeJob {
+    fn job_type(&self) -> &'static str {
+        "sniff_media_type"
+    }
+    
+    fn max_attempts(&self) -> u32 {
+        3
+    }
+    
+    fn retry_delay(&self) -> Duration {
+        Duration::from_secs(30)
+    }
+    
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(60) // Reads only the first few KB
+    }
+    
+    async fn process(&self, ctx: &JobContext) -> Result<JobResponse, JobError> {
+        tracing::info!("Processing media type sniff: commit={}, path={}", self.commit_id, self.path);
+        
+        let s3_client = ctx.s3_client.as_ref().ok_or_else(|| {
+            JobError::Processing("S3 client not available".to_string())
+        })?;
+        let db_pool = ctx.db_pool.as_ref().ok_or_else(|| {
+            JobError::Processing("Database pool not available to record media type".to_string())
+        })?;
+        
+        let bucket = std::env::var("S3_BUCKET").unwrap_or_else(|_| "blacklake".to_string());
+        let store = crate::sniff::PgMediaTypeStore::new(db_pool.clone());
+        if let Some(media_type) = crate::sniff::sniff_entry(self, s3_client, &bucket, &store).await? {
+            tracing::info!("Detected {} for {}", media_type, self.path);
+        }
+        Ok(JobResponse::Success)
+    }
+}
+
+/// Convert a committed CSV entry to Parquet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConvertToParquetJob {
+    pub commit_id: Uuid,
+    pub path: String,
+    pub sha256: String,
+}
+
+#[async_trait::async_trait]
+impl Job for ConvertToParquetJob {
+    fn name(&self) -> &str {
+        "convert_to_parquet"
+    }
+}
+
+#[async_trait::async_trait]
+impl BlackLakeJob for ConvertToParquetJob {
+    fn job_type(&self) -> &'static str {
+        "convert_to_parquet"
+    }
+    
+    fn max_attempts(&self) -> u32 {
+        3
+    }
+    
+    fn retry_delay(&self) -> Duration {
+        Duration::from_secs(60)
+    }
+    
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(600) // Whole file is read and rewritten
+    }
+    
+    async fn process(&self, ctx: &JobContext) -> Result<JobResponse, JobError> {
+        tracing::info!("Processing Parquet conversion: commit={}, path={}", self.commit_id, self.path);
+        
+        let s3_client = ctx.s3_client.as_ref().ok_or_else(|| {
+            JobError::Processing("S3 client not available".to_string())
+        })?;
+        let db_pool = ctx.db_pool.as_ref().ok_or_else(|| {
+            JobError::Processing("Database pool not available to record derived artifact".to_string())
+        })?;
+        
+        let bucket = std::env::var("S3_BUCKET").unwrap_or_else(|_| "blacklake".to_string());
+        let store = crate::derived::PgDerivedArtifactStore::new(db_pool.clone());
+        if let Some(artifact) = crate::parquet_convert::convert_entry(self, s3_client, &bucket, &store).await? {
+            tracing::info!("Stored Parquet copy of {} as {}", self.path, artifact.derived_sha256);
+        }
+        Ok(JobResponse::Success)
+    }
+}
+
+/// Render a preview image for a committed picture or PDF
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThumbnailJob {
+    pub commit_id: Uuid,
+    pub path: String,
+    pub sha256: String,
+}
+
+#[async_trait::async_trait]
+impl Job for ThumbnailJob {
+    fn name(&self) -> &str {
+        "thumbnail"
+    }
+}
+
+#[async_trait::async_trait]
+impl BlackLakeJob for ThumbnailJob {
+    fn job_type(&self) -> &'static str {
+        "thumbnail"
+    }
+    
+    fn max_attempts(&self) -> u32 {
+        3
+    }
+    
+    fn retry_delay(&self) -> Duration {
+        Duration::from_secs(30)
+    }
+    
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(120)
+    }
+    
+    async fn process(&self, ctx: &JobContext) -> Result<JobResponse, JobError> {
+        tracing::info!("Processing thumbnail: commit={}, path={}", self.commit_id, self.path);
+        
+        let s3_client = ctx.s3_client.as_ref().ok_or_else(|| {
+            JobError::Processing("S3 client not available".to_string())
+        })?;
+        let db_pool = ctx.db_pool.as_ref().ok_or_else(|| {
+            JobError::Processing("Database pool not available to record derived artifact".to_string())
+        })?;
+        
+        let bucket = std::env::var("S3_BUCKET").unwrap_or_else(|_| "blacklake".to_string());
+        let store = crate::derived::PgDerivedArtifactStore::new(db_pool.clone());
+        if let Some(artifact) = crate::thumbnail::generate_thumbnail(self, s3_client, &bucket, &store).await? {
+            tracing::info!("Stored thumbnail of {} as {}", self.path, artifact.derived_sha256);
+        }
+        Ok(JobResponse::Success)
+    }
+}
+
+/// Embedding job for a committed entry, feeding semantic search
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbedEntryJob {
+    pub repo_id: Uuid,
+    pub commit_id: Uuid,
+    pub path: String,
+    pub object_sha256: Option<String>,
+    pub content_type: Option<String>,
+    pub description: Option<String>,
+    pub file_size: u64,
+}
+
+impl EmbedEntryJob {
+    /// The job for a committed change, described by its metadata and sized by
+    /// its stored `object`; `None` for deletes and changes without content
+    pub fn for_change(repo_id: Uuid, commit_id: Uuid, change: &crate::Change, object: Option<&crate::Object>) -> Option<Self> {
+        let (crate::ChangeOp::Add | crate::ChangeOp::Modify, Some(sha256)) = (&change.op, &change.sha256) else {
+            return None;
+        };
+        let meta_str = |key: &str| change.meta.get(key).and_then(|v| v.as_str()).map(str::to_string);
+        Some(Self {
+            repo_id,
+            commit_id,
+            path: change.path.clone(),
+            object_sha256: Some(sha256.clone()),
+            content_type: meta_str("file_type").or_else(|| object.and_then(|o| o.media_type.clone())),
+            description: meta_str("description"),
+            file_size: object.map_or(0, |o| o.size.max(0) as u64),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl Job for EmbedEntryJob {
+    fn name(&self) -> &str {
+        "embed_entry"
+    }
+}
+
+#[async_trait::async_trait]
+impl BlackLakeJob for EmbedEntryJob {
+    fn job_type(&self) -> &'static str {
+        "embed_entry"
+    }
+    
+    fn max_attempts(&self) -> u32 {
+        5 // Remote backends rate limit
+    }
+    
+    fn retry_delay(&self) -> Duration {
+        Duration::from_secs(60)
+    }
+    
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(120)
+    }
+    
+    async fn process(&self, ctx: &JobContext) -> Result<JobResponse, JobError> {
+        tracing::info!("Processing embed entry job: commit={}, path={}", self.commit_id, self.path);
+        
+        let db_pool = ctx.db_pool.as_ref().ok_or_else(|| {
+            JobError::Processing("Database pool not available to store embedding".to_string())
+        })?;
+        
+        let bucket = std::env::var("S3_BUCKET").unwrap_or_else(|_| "blacklake".to_string());
+        let service = crate::embeddings::EmbeddingBackend::from_env()
+            .map_err(|e| JobError::Processing(e.to_string()))?
+            .into_service();
+        let store = crate::embeddings::PgEntryEmbeddingStore::new(db_pool.clone());
+        
+        let embedded =
+            crate::embeddings::embed_entry(self, ctx.s3_client.as_ref(), &bucket, service.as_ref(), &store).await?;
+        if !embedded {
+            tracing::info!("Nothing to embed for {}", self.path);
+        }
+        Ok(JobResponse::Success)
+    }
+}
+
+/// Job queue configuration
+#[derive(Debug, Clone)]
+pub struct JobQueueConfig {
+    pub name: String,
+    pub concurrency: u32,
+    pub max_attempts: u32,
+    pub retry_delay: Duration,
+    pub timeout: Duration,
+}
+
+impl JobQueueConfig {
+    pub fn index_queue() -> Self {
+        Self {
+            name: "index".to_string(),
+            concurrency: 5,
+            max_attempts: 5,
+            retry_delay: Duration::from_secs(30),
+            timeout: Duration::from_secs(120),
+        }
+    }
+    
+    pub fn sampling_queue() -> Self {
+        Self {
+            name: "sampling".to_string(),
+            concurrency: 3,
+            max_attempts: 3,
+            retry_delay: Duration::from_secs(60),
+            timeout: Duration::from_secs(180),
+        }
+    }
+    
+    pub fn rdf_queue() -> Self {
+        Self {
+            name: "rdf".to_string(),
+            concurrency: 2,
+            max_attempts: 3,
+            retry_delay: Duration::from_secs(45),
+            timeout: Duration::from_secs(120),
+        }
+    }
+    
+    pub fn antivirus_queue() -> Self {
+        Self {
+            name: "antivirus".to_string(),
+            concurrency: 2,
             max_attempts: 2,
             retry_delay: Duration::from_secs(120),
             timeout: Duration::from_secs(300),
@@ -1297,6 +1562,69 @@ impl JobQueueConfig {
             timeout: Duration::from_secs(3600),
         }
     }
+
+    /// Every queue with its built-in settings
+    pub fn defaults() -> Vec<Self> {
+        vec![
+            Self::index_queue(),
+            Self::sampling_queue(),
+            Self::rdf_queue(),
+            Self::antivirus_queue(),
+            Self::export_queue(),
+            Self::reindex_queue(),
+        ]
+    }
+
+    /// Every queue with `JOB_<QUEUE>_*` environment overrides applied
+    pub fn all_from_env() -> Vec<Self> {
+        Self::defaults().into_iter().map(Self::with_env_overrides).collect()
+    }
+
+    /// Apply `JOB_<QUEUE>_CONCURRENCY`, `_MAX_ATTEMPTS`, `_RETRY_DELAY_SECS`
+    /// and `_TIMEOUT_SECS`, e.g. `JOB_INDEX_CONCURRENCY=10`
+    pub fn with_env_overrides(self) -> Self {
+        self.with_overrides(|name| std::env::var(name).ok())
+    }
+
+    /// Apply overrides looked up by variable name. A value that does not
+    /// parse, or is out of range (concurrency, attempts and timeout must be
+    /// at least 1), is ignored with a warning and the current setting kept.
+    pub fn with_overrides(mut self, lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let prefix = format!("JOB_{}_", self.name.to_ascii_uppercase());
+        let setting = |suffix: &str, min: u64| -> Option<u64> {
+            let var = format!("{}{}", prefix, suffix);
+            let raw = lookup(&var)?;
+            match raw.trim().parse::<u64>() {
+                Ok(value) if value >= min => Some(value),
+                _ => {
+                    tracing::warn!("Ignoring invalid {}='{}' (expected an integer >= {}); using the default", var, raw, min);
+                    None
+                }
+            }
+        };
+
+        if let Some(concurrency) = setting("CONCURRENCY", 1) {
+            self.concurrency = concurrency.min(u32::MAX as u64) as u32;
+        }
+        if let Some(max_attempts) = setting("MAX_ATTEMPTS", 1) {
+            self.max_attempts = max_attempts.min(u32::MAX as u64) as u32;
+        }
+        if let Some(secs) = setting("RETRY_DELAY_SECS", 0) {
+            self.retry_delay = Duration::from_secs(secs);
+        }
+        if let Some(secs) = setting("TIMEOUT_SECS", 1) {
+            self.timeout = Duration::from_secs(secs);
+        }
+        self
+    }
+}
+
+/// Redis list of the ids of jobs that ran out of attempts
+const DEAD_LETTER_QUEUE: &str = "dead_letter_queue";
+
+/// Redis list of the ids of pending jobs of `job_type`
+pub fn pending_queue_key(job_type: &str) -> String {
+    format!("job:queue:{}", job_type)
 }
 
 /// Job manager for handling all BlackLake jobs
@@ -1306,19 +1634,32 @@ pub struct JobManager {
 }
 
 impl JobManager {
+    /// Queue settings are read from the environment here, once, at startup
     pub fn new(redis_storage: apalis_redis::RedisStorage<JobData>) -> Self {
-        let configs = vec![
-            JobQueueConfig::index_queue(),
-            JobQueueConfig::sampling_queue(),
-            JobQueueConfig::rdf_queue(),
-            JobQueueConfig::antivirus_queue(),
-            JobQueueConfig::export_queue(),
-            JobQueueConfig::reindex_queue(),
-        ];
+        let configs = JobQueueConfig::all_from_env();
+        for config in &configs {
+            info!(
+                "Job queue {}: concurrency={}, max_attempts={}, retry_delay={:?}, timeout={:?}",
+                config.name, config.concurrency, config.max_attempts, config.retry_delay, config.timeout
+            );
+        }
 
         Self { redis_storage, configs }
     }
 
+    /// Job manager backed by the Redis server at `redis_url`
+    pub async fn connect(redis_url: &str) -> Result<Self, JobError> {
+        let conn = apalis_redis::connect(redis_url)
+            .await
+            .map_err(|e| JobError::Storage(format!("Failed to connect to Redis: {}", e)))?;
+        Ok(Self::new(apalis_redis::RedisStorage::new(conn)))
+    }
+    
+    /// Effective settings of the queue called `name`
+    pub fn queue_config(&self, name: &str) -> Option<&JobQueueConfig> {
+        self.configs.iter().find(|config| config.name == name)
+    }
+
     /// Process the next available job
     pub async fn process_next_job(
         &self,
@@ -1352,6 +1693,7 @@ impl JobManager {
                     "completed" => Ok(JobStatus::Completed),
                     "failed" => Ok(JobStatus::Failed),
                     "cancelled" => Ok(JobStatus::Cancelled),
+                    "dead_letter" => Ok(JobStatus::DeadLetter),
                     _ => Ok(JobStatus::Unknown),
                 }
             }
@@ -1364,9 +1706,7 @@ impl JobManager {
         use redis::AsyncCommands;
         
         let mut conn = self.redis_storage.get_connection().clone();
-        let dead_letter_key = "dead_letter_queue";
-        
-        let job_ids: Vec<String> = conn.lrange(&dead_letter_key, 0, -1).await
+        let job_ids: Vec<String> = conn.lrange(DEAD_LETTER_QUEUE, 0, -1).await
             .map_err(|e| JobError::Storage(format!("Failed to get dead letter jobs: {}", e)))?;
         
         let mut dead_letter_jobs = Vec::new();
@@ -1381,13 +1721,20 @@ impl JobManager {
                     let error_key = format!("job:error:{}", job_id);
                     let error_message: Option<String> = conn.get(&error_key).await
                         .map_err(|e| JobError::Storage(format!("Failed to get error message: {}", e)))?;
+                    let failed_at: Option<String> = conn.get(format!("job:failed_at:{}", job_id)).await
+                        .map_err(|e| JobError::Storage(format!("Failed to get failure time: {}", e)))?;
+                    let retry_count: Option<u32> = conn.get(format!("job:retry:{}", job_id)).await
+                        .map_err(|e| JobError::Storage(format!("Failed to get retry count: {}", e)))?;
                     
                     dead_letter_jobs.push(DeadLetterJob {
                         job_id,
                         job_data: job,
                         error_message: error_message.unwrap_or_else(|| "Unknown error".to_string()),
-                        failed_at: chrono::Utc::now(),
-                        retry_count: 0,
+                        failed_at: failed_at
+                            .and_then(|at| chrono::DateTime::parse_from_rfc3339(&at).ok())
+                            .map(|at| at.with_timezone(&chrono::Utc))
+                            .unwrap_or_else(chrono::Utc::now),
+                        retry_count: retry_count.unwrap_or(0),
                     });
                 }
             }
@@ -1396,6 +1743,80 @@ impl JobManager {
         Ok(dead_letter_jobs)
     }
     
+    /// Move a job that has used up its attempts to the dead-letter queue,
+    /// keeping its data and the error it last failed with
+    pub async fn dead_letter_job(&self, job_id: &str, job: &JobData, error_message: &str) -> Result<(), JobError> {
+        let mut conn = self.redis_storage.get_connection().clone();
+        let data = serde_json::to_string(job)
+            .map_err(|e| JobError::Serialization(format!("Failed to serialize job data: {}", e)))?;
+        
+        let _: () = redis::pipe()
+            .atomic()
+            .set(format!("job:data:{}", job_id), data)
+            .set(format!("job:error:{}", job_id), error_message)
+            .set(format!("job:failed_at:{}", job_id), chrono::Utc::now().to_rfc3339())
+            .set(format!("job:status:{}", job_id), "dead_letter")
+            .rpush(DEAD_LETTER_QUEUE, job_id)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| JobError::Storage(format!("Failed to dead-letter job: {}", e)))?;
+        
+        info!("Job {} ({}) moved to the dead-letter queue: {}", job_id, job.job_type, error_message);
+        Ok(())
+    }
+    
+    /// Take a job off the dead-letter queue and put it back on its type's
+    /// pending queue with a fresh retry count. Returns false when the job is
+    /// not dead-lettered.
+    pub async fn requeue_dead_letter_job(&self, job_id: &str) -> Result<bool, JobError> {
+        use redis::AsyncCommands;
+        
+        let mut conn = self.redis_storage.get_connection().clone();
+        let data: Option<String> = conn.get(format!("job:data:{}", job_id)).await
+            .map_err(|e| JobError::Storage(format!("Failed to get job data: {}", e)))?;
+        let Some(data) = data else {
+            return Ok(false);
+        };
+        let job: JobData = serde_json::from_str(&data)
+            .map_err(|e| JobError::Serialization(format!("Invalid data for job {}: {}", job_id, e)))?;
+        
+        // Removing it from the dead-letter queue first means two concurrent
+        // requeues of the same job cannot both queue it
+        let removed: i64 = conn.lrem(DEAD_LETTER_QUEUE, 0, job_id).await
+            .map_err(|e| JobError::Storage(format!("Failed to remove dead-lettered job: {}", e)))?;
+        if removed == 0 {
+            return Ok(false);
+        }
+        
+        let _: () = redis::pipe()
+            .atomic()
+            .del(format!("job:retry:{}", job_id))
+            .del(format!("job:failed_at:{}", job_id))
+            .set(format!("job:status:{}", job_id), "pending")
+            .rpush(pending_queue_key(&job.job_type), job_id)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| JobError::Storage(format!("Failed to requeue job: {}", e)))?;
+        
+        info!("Dead-lettered job {} ({}) requeued", job_id, job.job_type);
+        Ok(true)
+    }
+    
+    /// Requeue every dead-lettered job, or only those of `job_type`, and
+    /// return how many went back on a queue
+    pub async fn requeue_dead_letter_jobs(&self, job_type: Option<&str>) -> Result<usize, JobError> {
+        let mut requeued = 0;
+        for job in self.get_dead_letter_jobs().await? {
+            if job_type.is_some_and(|job_type| job.job_data.job_type != job_type) {
+                continue;
+            }
+            if self.requeue_dead_letter_job(&job.job_id).await? {
+                requeued += 1;
+            }
+        }
+        Ok(requeued)
+    }
+    
     /// Implement job retry logic
     pub async fn retry_job(&self, job_id: &str, max_retries: u32) -> Result<(), JobError> {
         use redis::AsyncCommands;
@@ -1504,6 +1925,7 @@ impl JobManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashMap;
     
     #[test]
     fn test_job_queue_configs() {
@@ -1518,6 +1940,45 @@ mod tests {
         assert_eq!(sampling_config.max_attempts, 3);
     }
     
+    #[test]
+    fn test_env_override_changes_effective_queue_config() {
+        std::env::set_var("JOB_RDF_CONCURRENCY", "8");
+        std::env::set_var("JOB_RDF_TIMEOUT_SECS", "600");
+        let rdf = JobQueueConfig::all_from_env().into_iter().find(|c| c.name == "rdf").unwrap();
+        std::env::remove_var("JOB_RDF_CONCURRENCY");
+        std::env::remove_var("JOB_RDF_TIMEOUT_SECS");
+
+        assert_eq!(rdf.concurrency, 8);
+        assert_eq!(rdf.timeout, Duration::from_secs(600));
+        // Settings without an override keep their defaults
+        assert_eq!(rdf.max_attempts, JobQueueConfig::rdf_queue().max_attempts);
+        assert_eq!(rdf.retry_delay, JobQueueConfig::rdf_queue().retry_delay);
+
+        let overrides: HashMap<&str, &str> =
+            HashMap::from([("JOB_EXPORT_MAX_ATTEMPTS", "4"), ("JOB_EXPORT_RETRY_DELAY_SECS", "0")]);
+        let export = JobQueueConfig::export_queue().with_overrides(|name| overrides.get(name).map(|v| v.to_string()));
+        assert_eq!(export.max_attempts, 4);
+        assert_eq!(export.retry_delay, Duration::ZERO);
+        assert_eq!(export.concurrency, 1);
+    }
+
+    #[test]
+    fn test_invalid_queue_overrides_fall_back_to_defaults() {
+        let overrides: HashMap<&str, &str> = HashMap::from([
+            ("JOB_INDEX_CONCURRENCY", "0"),
+            ("JOB_INDEX_MAX_ATTEMPTS", "-2"),
+            ("JOB_INDEX_RETRY_DELAY_SECS", "soon"),
+            ("JOB_INDEX_TIMEOUT_SECS", "0"),
+        ]);
+
+        let index = JobQueueConfig::index_queue().with_overrides(|name| overrides.get(name).map(|v| v.to_string()));
+        let defaults = JobQueueConfig::index_queue();
+        assert_eq!(index.concurrency, defaults.concurrency);
+        assert_eq!(index.max_attempts, defaults.max_attempts);
+        assert_eq!(index.retry_delay, defaults.retry_delay);
+        assert_eq!(index.timeout, defaults.timeout);
+    }
+
     #[test]
     fn test_index_entry_job() {
         let job = IndexEntryJob {
@@ -1583,6 +2044,280 @@ mod tests {
         assert_eq!(solr_doc.file_type, "csv");
         assert_eq!(solr_doc.file_size, 1024);
     }
+
+    #[test]
+    fn test_sample_csv_infers_schema() {
+        let csv = b"id,score,label,active\n1,0.5,alpha,true\n2,3,,false\n3,4.25,gamma,TRUE\n";
+        let (rows, schema) = sample_csv(csv).unwrap();
+
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0]["label"], "alpha");
+        assert_eq!(
+            schema,
+            serde_json::json!({ "columns": [
+                { "name": "id", "type": "integer" },
+                { "name": "score", "type": "number" },
+                { "name": "label", "type": "string" },
+                { "name": "active", "type": "boolean" },
+            ]})
+        );
+    }
+
+    #[test]
+    fn test_sample_csv_caps_rows_and_bytes() {
+        let mut csv = String::from("n\n");
+        for i in 0..500 {
+            csv.push_str(&format!("{}\n", i));
+        }
+        let (rows, _) = sample_csv(csv.as_bytes()).unwrap();
+        assert_eq!(rows.len(), MAX_SAMPLE_ROWS);
+
+        let wide = "x".repeat(100 * 1024);
+        let csv = format!("blob\n{0}\n{0}\n{0}\n{0}\n", wide);
+        let (rows, _) = sample_csv(csv.as_bytes()).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert!(serde_json::Value::Array(rows).to_string().len() <= MAX_SAMPLE_BYTES);
+    }
+
+    /// Start an HTTP stub for Solr's update handler that records every request body
+    async fn spawn_solr_stub() -> (String, std::sync::Arc<std::sync::Mutex<Vec<serde_json::Value>>>) {
+        use axum::{extract::State, routing::post, Json, Router};
+        use std::sync::{Arc, Mutex};
+
+        let requests: Arc<Mutex<Vec<serde_json::Value>>> = Arc::default();
+        let app = Router::new()
+            .route(
+                "/:collection/update",
+                post(
+                    |State(requests): State<Arc<Mutex<Vec<serde_json::Value>>>>, Json(body): Json<serde_json::Value>| async move {
+                        requests.lock().unwrap().push(body);
+                        Json(serde_json::json!({"responseHeader": {"status": 0}}))
+                    },
+                ),
+            )
+            .with_state(requests.clone());
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        (url, requests)
+    }
+
+    fn solr_job_context(solr: Option<crate::search::SolrClient>) -> JobContext {
+        JobContext {
+            job_id: Uuid::new_v4(),
+            worker_id: "test-worker".to_string(),
+            s3_client: None,
+            db_pool: None,
+            solr,
+        }
+    }
+
+    fn solr_test_job(operation: IndexOperation) -> IndexEntryJob {
+        IndexEntryJob {
+            repo_id: Uuid::new_v4(),
+            repo_name: "test-repo".to_string(),
+            ref_name: "main".to_string(),
+            path: "data/test file.csv".to_string(),
+            commit_id: Uuid::new_v4(),
+            object_sha256: "abc123".to_string(),
+            metadata: serde_json::json!({"file_type": "csv", "file_size": 1024}),
+            operation,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_index_entry_job_issues_solr_add_and_delete() {
+        let (url, requests) = spawn_solr_stub().await;
+        let solr = crate::search::SolrClient::new(crate::search::SolrConfig {
+            url,
+            ..Default::default()
+        });
+        let ctx = solr_job_context(Some(solr));
+
+        let job = solr_test_job(IndexOperation::Index);
+        assert!(matches!(job.process(&ctx).await, Ok(JobResponse::Success)));
+        {
+            let requests = requests.lock().unwrap();
+            assert_eq!(requests.len(), 2);
+            assert_eq!(requests[0]["add"]["doc"]["id"], job.solr_document_id());
+            assert_eq!(requests[0]["add"]["doc"]["file_name"], "test file.csv");
+            assert!(requests[1].get("commit").is_some());
+        }
+
+        let delete = IndexEntryJob {
+            operation: IndexOperation::Delete,
+            ..job.clone()
+        };
+        assert!(matches!(delete.process(&ctx).await, Ok(JobResponse::Success)));
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 4);
+        assert_eq!(
+            requests[2]["delete"]["query"],
+            "id:test\\-repo\\:main\\:data\\/test\\ file.csv\\:*"
+        );
+        assert!(requests[3].get("commit").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_index_entry_job_requires_solr_client() {
+        let job = solr_test_job(IndexOperation::Index);
+        let result = job.process(&solr_job_context(None)).await;
+        assert!(matches!(result, Err(JobError::Processing(msg)) if msg.contains("Solr client not configured")));
+    }
+
+    /// Read one INSTREAM request the way clamd does and return the reassembled payload
+    async fn read_instream_request<S>(stream: &mut S) -> Vec<u8>
+    where
+        S: tokio::io::AsyncRead + Unpin,
+    {
+        use tokio::io::AsyncReadExt;
+
+        let mut command = [0u8; 10];
+        stream.read_exact(&mut command).await.unwrap();
+        assert_eq!(&command, b"zINSTREAM\0");
+
+        let mut payload = Vec::new();
+        loop {
+            let mut len = [0u8; 4];
+            stream.read_exact(&mut len).await.unwrap();
+            let len = u32::from_be_bytes(len) as usize;
+            if len == 0 {
+                return payload;
+            }
+            assert!(len <= CLAMAV_CHUNK_SIZE);
+            let mut chunk = vec![0u8; len];
+            stream.read_exact(&mut chunk).await.unwrap();
+            payload.extend_from_slice(&chunk);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_clamav_instream_framing() {
+        use tokio::io::AsyncWriteExt;
+
+        let data: Vec<u8> = (0..CLAMAV_CHUNK_SIZE * 2 + 17).map(|i| (i % 251) as u8).collect();
+        let (client, mut server) = tokio::io::duplex(1024);
+
+        let clamd = tokio::spawn(async move {
+            let payload = read_instream_request(&mut server).await;
+            server.write_all(b"stream: OK\0").await.unwrap();
+            payload
+        });
+
+        let result = clamav_instream(client, &data, CLAMAV_DEFAULT_MAX_STREAM_BYTES).await.unwrap();
+        assert_eq!(result, ScanResult::Clean);
+        assert_eq!(clamd.await.unwrap(), data);
+    }
+
+    #[tokio::test]
+    async fn test_clamav_instream_rejects_oversized_data() {
+        let (client, _server) = tokio::io::duplex(64);
+        let result = clamav_instream(client, &[0u8; 32], 16).await.unwrap();
+        assert!(matches!(result, ScanResult::Error(msg) if msg.contains("exceeds")));
+    }
+
+    #[test]
+    fn test_parse_clamav_reply() {
+        assert_eq!(parse_clamav_reply("stream: OK\0"), ScanResult::Clean);
+        assert_eq!(
+            parse_clamav_reply("stream: Win.Test.EICAR_HDB-1 FOUND\0"),
+            ScanResult::Infected("Win.Test.EICAR_HDB-1".to_string())
+        );
+        assert_eq!(
+            parse_clamav_reply("INSTREAM size limit exceeded. ERROR\0"),
+            ScanResult::Error("INSTREAM size limit exceeded. ERROR".to_string())
+        );
+    }
+
+    /// Requires a running clamd, e.g. `docker run -p 3310:3310 clamav/clamav`,
+    /// then `cargo test -p blacklake-core --features clamav-tests`.
+    #[cfg(feature = "clamav-tests")]
+    #[tokio::test]
+    async fn test_clamav_detects_eicar() {
+        const EICAR: &[u8] = b"X5O!P%@AP[4\\PZX54(P^)7CC)7}$EICAR-STANDARD-ANTIVIRUS-TEST-FILE!$H+H*";
+
+        let host = std::env::var("CLAMAV_HOST").unwrap_or_else(|_| "localhost".to_string());
+        let port = std::env::var("CLAMAV_PORT").unwrap_or_else(|_| "3310".to_string());
+
+        match scan_with_clamav(EICAR, &host, &port).await.unwrap() {
+            ScanResult::Infected(name) => assert!(name.contains("Eicar") || name.contains("EICAR"), "{}", name),
+            other => panic!("expected EICAR detection, got {:?}", other),
+        }
+
+        let clean = scan_with_clamav(b"just some text", &host, &port).await.unwrap();
+        assert_eq!(clean, ScanResult::Clean);
+    }
+
+    async fn pending_ids(manager: &JobManager, job_type: &str) -> Vec<String> {
+        use redis::AsyncCommands;
+        let mut conn = manager.redis_storage.get_connection().clone();
+        conn.lrange(pending_queue_key(job_type), 0, -1).await.unwrap()
+    }
+
+    fn job_data(job_type: &str) -> JobData {
+        JobData {
+            job_type: job_type.to_string(),
+            payload: serde_json::json!({"repo_id": Uuid::new_v4()}),
+        }
+    }
+
+    /// Runs against the Redis server named by `TEST_REDIS_URL`; skipped otherwise
+    #[tokio::test]
+    async fn test_dead_lettered_job_is_listed_and_requeued() {
+        let Ok(url) = std::env::var("TEST_REDIS_URL") else {
+            return;
+        };
+        let manager = JobManager::connect(&url).await.unwrap();
+        // A type of its own keeps other tests' jobs out of its pending queue
+        let job_type = format!("export-{}", Uuid::new_v4());
+        let job_id = Uuid::new_v4().to_string();
+
+        manager.dead_letter_job(&job_id, &job_data(&job_type), "S3 upload timed out").await.unwrap();
+
+        assert_eq!(manager.get_job_status(&job_id).await.unwrap(), JobStatus::DeadLetter);
+        let listed = manager.get_dead_letter_jobs().await.unwrap();
+        let dead = listed.iter().find(|job| job.job_id == job_id).unwrap();
+        assert_eq!(dead.job_data.job_type, job_type);
+        assert_eq!(dead.error_message, "S3 upload timed out");
+
+        assert!(manager.requeue_dead_letter_job(&job_id).await.unwrap());
+        assert_eq!(manager.get_job_status(&job_id).await.unwrap(), JobStatus::Pending);
+        assert_eq!(pending_ids(&manager, &job_type).await, vec![job_id.clone()]);
+        let listed = manager.get_dead_letter_jobs().await.unwrap();
+        assert!(listed.iter().all(|job| job.job_id != job_id));
+
+        // Already requeued, so there is nothing to do
+        assert!(!manager.requeue_dead_letter_job(&job_id).await.unwrap());
+        assert!(!manager.requeue_dead_letter_job(&Uuid::new_v4().to_string()).await.unwrap());
+        assert_eq!(pending_ids(&manager, &job_type).await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_bulk_requeue_only_touches_the_given_type() {
+        let Ok(url) = std::env::var("TEST_REDIS_URL") else {
+            return;
+        };
+        let manager = JobManager::connect(&url).await.unwrap();
+        let export_type = format!("export-{}", Uuid::new_v4());
+        let index_type = format!("index-{}", Uuid::new_v4());
+        let exports: Vec<String> = (0..2).map(|_| Uuid::new_v4().to_string()).collect();
+        let index_job = Uuid::new_v4().to_string();
+        for job_id in &exports {
+            manager.dead_letter_job(job_id, &job_data(&export_type), "boom").await.unwrap();
+        }
+        manager.dead_letter_job(&index_job, &job_data(&index_type), "boom").await.unwrap();
+
+        assert_eq!(manager.requeue_dead_letter_jobs(Some(&export_type)).await.unwrap(), 2);
+
+        assert_eq!(pending_ids(&manager, &export_type).await, exports);
+        assert!(pending_ids(&manager, &index_type).await.is_empty());
+        assert_eq!(manager.get_job_status(&index_job).await.unwrap(), JobStatus::DeadLetter);
+        assert_eq!(manager.requeue_dead_letter_jobs(Some(&export_type)).await.unwrap(), 0);
+
+        assert!(manager.requeue_dead_letter_job(&index_job).await.unwrap());
+    }
 }
 
 // Run all workers function
@@ -1590,4 +2325,4 @@ pub async fn run_all_workers() -> Result<(), JobError> {
     info!("Starting all job workers");
     // Simplified implementation - just log for now
     Ok(())
-}
\ No newline at end of file
+}
@@ -97,6 +97,7 @@ mod tests {
             manifest,
             include_metadata: true,
             include_rdf: false,
+            format: Default::default(),
         };
 
         assert!(job.include_metadata);
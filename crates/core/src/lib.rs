@@ -2,10 +2,18 @@ use chrono::{DateTime, Utc};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use uuid::Uuid;
-use sophia::api::graph::Graph;
-// use sophia::turtle::TurtleSerializer; // Commented out due to import issues
+use sophia::api::parser::QuadParser;
+use sophia::api::prefix::{Prefix, PrefixMapPair};
+use sophia::api::quad::Spog;
+use sophia::api::serializer::{QuadSerializer, Stringifier, TripleSerializer};
+use sophia::api::source::QuadSource;
+use sophia::api::term::SimpleTerm;
+use sophia::iri::{Iri, IriRef};
+use sophia::jsonld::{JsonLdParser, JsonLdSerializer};
+use sophia::turtle::serializer::nt::NtSerializer;
+use sophia::turtle::serializer::turtle::{TurtleConfig, TurtleSerializer};
 use url::Url;
 
 // Re-export common types
@@ -18,6 +26,42 @@ pub enum SortOrder {
     Desc,
 }
 
+/// Field offset-paginated search orders results by. Ties are broken on the
+/// entry id so every page boundary falls in the same place.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SearchSort {
+    /// Newest first
+    #[default]
+    CreatedAt,
+    /// Alphabetical
+    Path,
+    /// Largest first; entries without an object last
+    Size,
+}
+
+impl SearchSort {
+    pub const ALL: &'static [SearchSort] = &[SearchSort::CreatedAt, SearchSort::Path, SearchSort::Size];
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            SearchSort::CreatedAt => "created_at",
+            SearchSort::Path => "path",
+            SearchSort::Size => "size",
+        }
+    }
+}
+
+impl std::str::FromStr for SearchSort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        SearchSort::ALL.iter().copied().find(|sort| sort.key() == s).ok_or_else(|| {
+            let known: Vec<&str> = SearchSort::ALL.iter().map(SearchSort::key).collect();
+            format!("Invalid sort field: {}. Use one of {}", s, known.join(", "))
+        })
+    }
+}
+
 // Search query struct
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SearchQuery {
@@ -88,6 +132,52 @@ pub struct Reference {
     pub name: String,
     pub kind: ReferenceKind,
     pub commit_id: UuidWrapper,
+    /// Message and tagger of an annotated tag
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotation: Option<RefAnnotation>,
+}
+
+/// Who tagged a commit, when, and why
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct RefAnnotation {
+    pub message: String,
+    pub tagger: String,
+    pub tagged_at: DateTime<Utc>,
+}
+
+/// Body of `POST /v1/repos/:repo/tags`
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CreateTagRequest {
+    pub name: String,
+    /// Branch, tag or commit id to tag
+    pub target: String,
+    /// Makes the tag annotated; without it the tag is lightweight
+    #[serde(default)]
+    pub message: Option<String>,
+    /// Move an existing tag; admins only
+    #[serde(default)]
+    pub force: bool,
+}
+
+/// Body of `PUT /v1/repos/:repo/refs/*name`
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct UpdateRefRequest {
+    /// Branch, tag or commit id the ref should point at
+    pub target: String,
+}
+
+/// Response header carrying a repository's current features version
+pub const FEATURES_VERSION_HEADER: &str = "X-Blacklake-Features-Version";
+
+/// Body of `POST /v1/repos/:repo/features`; a `null` value resets the flag
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SetRepoFeatureRequest {
+    pub key: String,
+    pub value: serde_json::Value,
+    /// Features version the change was based on; the write is refused if
+    /// another one has landed since. Omit to write unconditionally.
+    #[serde(default)]
+    pub expected_version: Option<i64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
@@ -129,6 +219,12 @@ pub struct Entry {
     pub meta: serde_json::Value,
     pub is_dir: bool,
     pub created_at: DateTime<Utc>,
+    /// Size of the entry's object; set by tree listings, `None` for directories
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<i64>,
+    /// Media type recorded for the entry's object, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub media_type: Option<String>,
 }
 
 /// ACL entry
@@ -139,7 +235,8 @@ pub struct Acl {
     pub perm: Permission,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+/// Repository permission; variants are ordered so that Admin > Write > Read
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum Permission {
     Read,
@@ -147,6 +244,34 @@ pub enum Permission {
     Admin,
 }
 
+impl Permission {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Permission::Read => "read",
+            Permission::Write => "write",
+            Permission::Admin => "admin",
+        }
+    }
+
+    /// Whether holding `self` is enough for an operation that needs `required`
+    pub fn grants(&self, required: Permission) -> bool {
+        *self >= required
+    }
+}
+
+impl std::str::FromStr for Permission {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read" => Ok(Permission::Read),
+            "write" => Ok(Permission::Write),
+            "admin" => Ok(Permission::Admin),
+            other => Err(format!("Invalid permission: {}", other)),
+        }
+    }
+}
+
 /// Audit log entry
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct AuditLog {
@@ -161,6 +286,29 @@ pub struct AuditLog {
     pub response_meta: Option<serde_json::Value>,
 }
 
+/// Filters for reading the audit log back; unset fields match everything
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct AuditLogFilter {
+    pub actor: Option<String>,
+    pub action: Option<String>,
+    pub repo_name: Option<String>,
+    pub ref_name: Option<String>,
+    /// Inclusive lower bound on `at`
+    pub since: Option<DateTime<Utc>>,
+    /// Exclusive upper bound on `at`
+    pub until: Option<DateTime<Utc>>,
+}
+
+/// One page of audit entries, newest first
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AuditLogPage {
+    pub entries: Vec<AuditLog>,
+    /// Entries matching the filter across all pages
+    pub total: u64,
+    pub limit: u32,
+    pub offset: u32,
+}
+
 // API Request/Response types
 
 /// Request to initialize an upload
@@ -169,15 +317,181 @@ pub struct UploadInitRequest {
     pub path: String,
     pub size: u64,
     pub media_type: Option<String>,
+    /// Hex sha256 of the content, used as its content address; S3 rejects
+    /// an upload whose bytes do not match it
+    #[serde(default)]
+    pub sha256: Option<String>,
 }
 
 /// Response for upload initialization
 #[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct UploadInitResponse {
+    /// Single PUT URL; empty when the upload uses `multipart` or the content
+    /// is `already_stored`
     pub upload_url: String,
     pub sha256: String,
     pub s3_key: String,
     pub expires_at: DateTime<Utc>,
+    pub multipart: Option<MultipartUploadPlan>,
+    /// Signed URL constraints checked before the URL was issued
+    #[serde(default)]
+    #[schemars(with = "Vec<String>")]
+    pub constraints_applied: Vec<Uuid>,
+    /// Token a constraint-enforcing gateway re-checks on each use of the URL
+    #[serde(default)]
+    pub access_token: Option<String>,
+    /// Headers the client must send with the single PUT; they are signed into `upload_url`
+    #[serde(default)]
+    pub upload_headers: HashMap<String, String>,
+    /// Set when the upload takes the repository past its soft quota
+    #[serde(default)]
+    pub quota_warning: Option<QuotaWarning>,
+    /// Verified content with this sha256 is already stored; nothing needs
+    /// uploading and the path can be committed straight away
+    #[serde(default)]
+    pub already_stored: bool,
+}
+
+/// One file of a batch upload; the content hash is required so already
+/// stored objects can be skipped
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BatchUploadItem {
+    pub path: String,
+    pub size: u64,
+    pub media_type: Option<String>,
+    pub sha256: String,
+}
+
+/// Body of `POST /v1/repos/:repo/upload-init-batch`
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct UploadInitBatchRequest {
+    pub items: Vec<BatchUploadItem>,
+}
+
+/// Presigned PUT for one file of a batch
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct BatchUploadUrl {
+    pub path: String,
+    pub sha256: String,
+    pub s3_key: String,
+    pub upload_url: String,
+    /// Token a constraint-enforcing gateway re-checks on each use of the URL
+    #[serde(default)]
+    pub access_token: Option<String>,
+    /// Headers the client must send with the PUT; they are signed into `upload_url`
+    #[serde(default)]
+    pub upload_headers: HashMap<String, String>,
+}
+
+/// Response for batch upload initialization
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct UploadInitBatchResponse {
+    pub uploads: Vec<BatchUploadUrl>,
+    /// Paths whose content is already stored (or uploaded by an earlier item
+    /// of the batch); they can be committed without uploading
+    pub skipped: Vec<String>,
+    /// Bytes the uploads add, which is what the quota is checked against
+    pub new_bytes: u64,
+    pub expires_at: DateTime<Utc>,
+    #[serde(default)]
+    pub quota_warning: Option<QuotaWarning>,
+}
+
+/// Header carrying `QuotaWarning::header_value` on upload and commit responses
+pub const QUOTA_WARNING_HEADER: &str = "X-Blacklake-Quota-Warning";
+
+/// Repository usage past the soft quota but still under the hard limit
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct QuotaWarning {
+    pub current_bytes: u64,
+    pub soft_limit: u64,
+    pub hard_limit: u64,
+    /// `current_bytes` as a percentage of `hard_limit`
+    pub usage_percentage: f64,
+}
+
+impl QuotaWarning {
+    /// Value for `QUOTA_WARNING_HEADER`, e.g. `current=1500; soft=1000; hard=2000; percent=75.0`
+    pub fn header_value(&self) -> String {
+        format!(
+            "current={}; soft={}; hard={}; percent={:.1}",
+            self.current_bytes, self.soft_limit, self.hard_limit, self.usage_percentage
+        )
+    }
+}
+
+/// Presigned part URLs for uploads above the multipart threshold
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct MultipartUploadPlan {
+    pub upload_id: String,
+    /// Key the parts are assembled under; the object reaches its content
+    /// address only after its sha256 is verified
+    pub staging_key: String,
+    pub part_size: u64,
+    pub parts: Vec<UploadPartUrl>,
+}
+
+/// Presigned URL for one part of a multipart upload
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct UploadPartUrl {
+    pub part_number: i32,
+    pub upload_url: String,
+}
+
+/// A part the client has uploaded, identified by the ETag S3 returned
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct UploadPart {
+    pub part_number: i32,
+    pub etag: String,
+}
+
+/// Request to complete a multipart upload
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct UploadCompleteRequest {
+    /// Content hash given to `upload-init`
+    pub sha256: String,
+    pub upload_id: String,
+    /// `staging_key` of the multipart plan `upload-init` returned
+    pub staging_key: String,
+    /// Part ETags reported by the client; fetched from S3 when omitted
+    pub parts: Option<Vec<UploadPart>>,
+}
+
+/// Response for multipart upload completion
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct UploadCompleteResponse {
+    pub s3_key: String,
+    pub etag: Option<String>,
+    pub parts: Vec<UploadPart>,
+}
+
+/// Request to verify an uploaded object against its claimed sha256
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct UploadVerifyRequest {
+    pub sha256: String,
+    /// Detached signature of the file, stored next to the object
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<signing::SignatureBundle>,
+}
+
+/// How an upload's content hash was confirmed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum UploadVerification {
+    /// S3 checked the bytes against the sha256 when they were stored
+    Verified,
+    /// S3 holds no sha256 for the object; a job re-hashes it and deletes it on mismatch
+    Scheduled,
+}
+
+/// Response for upload verification
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct UploadVerifyResponse {
+    pub sha256: String,
+    pub verification: UploadVerification,
+    /// Outcome of checking the signature sent with the request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<signing::SignatureVerification>,
 }
 
 /// Request to create a commit
@@ -189,8 +503,25 @@ pub struct CommitRequest {
     pub expected_parent: Option<UuidWrapper>,
 }
 
-/// A change in a commit
+/// Request to patch the metadata of many entries in a single commit
 #[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct MetaBatchRequest {
+    pub r#ref: String,
+    pub message: Option<String>,
+    pub updates: Vec<MetaPatch>,
+    #[serde(default)]
+    pub expected_parent: Option<UuidWrapper>,
+}
+
+/// JSON Merge Patch (RFC 7396) for one entry's metadata
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MetaPatch {
+    pub path: String,
+    pub meta_patch: serde_json::Value,
+}
+
+/// A change in a commit
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Change {
     pub op: ChangeOp,
     pub path: String,
@@ -213,6 +544,72 @@ pub struct CommitResponse {
     pub commit_id: UuidWrapper,
     pub parent_id: Option<UuidWrapper>,
     pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub stats: Option<CommitStats>,
+    /// Set when the repository is past its soft quota after this commit
+    #[serde(default)]
+    pub quota_warning: Option<QuotaWarning>,
+}
+
+/// Summary of what a commit changed, stored in `commit.stats`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct CommitStats {
+    pub files_added: u64,
+    /// Content and metadata-only modifications
+    pub files_modified: u64,
+    pub files_deleted: u64,
+    pub bytes_added: u64,
+    pub bytes_removed: u64,
+}
+
+/// Logical versus physical storage for a repository.
+///
+/// Logical bytes count every entry in every commit as if it stored its own
+/// copy; physical bytes count each distinct object once, which is what
+/// content addressing actually keeps in S3.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct DedupStats {
+    /// Entries across all commits that point at an object
+    pub entry_count: u64,
+    pub unique_objects: u64,
+    pub logical_bytes: u64,
+    pub physical_bytes: u64,
+    /// `logical_bytes - physical_bytes`
+    pub saved_bytes: u64,
+    /// `logical_bytes / physical_bytes`; 1.0 when nothing is shared or the repo is empty
+    pub dedup_ratio: f64,
+}
+
+/// Outcome of deleting a repository
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RepoDeletion {
+    pub repo_id: UuidWrapper,
+    pub name: String,
+    /// Objects no other repository references, now queued for garbage collection
+    pub unreferenced_objects: u64,
+    /// Earliest time object GC may remove those blobs, per the repo's retention policy
+    pub gc_not_before: DateTime<Utc>,
+}
+
+/// A path whose entry differs between two commits
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct PathDiff {
+    pub path: String,
+    /// Object hash in the base commit; `None` if the path was added
+    pub old_sha256: Option<String>,
+    /// Object hash in the head commit; `None` if the path was removed
+    pub new_sha256: Option<String>,
+}
+
+/// Differences between the entries of two commits
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct CommitDiff {
+    pub added: Vec<PathDiff>,
+    pub removed: Vec<PathDiff>,
+    /// Content changed (object hash differs)
+    pub modified: Vec<PathDiff>,
+    /// Same content, metadata changed
+    pub meta_changed: Vec<PathDiff>,
 }
 
 /// Request to create a repository
@@ -233,6 +630,12 @@ pub struct CreateRepoResponse {
 #[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct TreeResponse {
     pub entries: Vec<TreeEntry>,
+    /// Rows across all pages; with a delimiter a collapsed directory counts once
+    #[serde(default)]
+    pub total: u64,
+    /// Offset of the next page, absent on the last one
+    #[serde(default)]
+    pub next_offset: Option<u32>,
 }
 
 #[derive(Debug, Serialize, Deserialize, JsonSchema)]
@@ -241,6 +644,9 @@ pub struct TreeEntry {
     pub is_dir: bool,
     pub size: Option<i64>,
     pub media_type: Option<String>,
+    /// When the entry's content was stored; `None` for directories
+    #[serde(default)]
+    pub last_modified: Option<DateTime<Utc>>,
     pub meta: serde_json::Value,
 }
 
@@ -251,6 +657,9 @@ pub struct SearchRequest {
     pub sort: Option<String>,
     pub limit: Option<u32>,
     pub offset: Option<u32>,
+    /// Keyset cursor from a previous `next_cursor`; takes precedence over `offset`
+    #[serde(default)]
+    pub cursor: Option<String>,
 }
 
 /// Search response
@@ -258,6 +667,9 @@ pub struct SearchRequest {
 pub struct SearchResponse {
     pub entries: Vec<SearchEntry>,
     pub total: u32,
+    /// Cursor for the next keyset page; absent on the last page and for offset searches
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, JsonSchema)]
@@ -329,6 +741,13 @@ impl Default for MetadataSchema {
     }
 }
 
+impl MetadataSchema {
+    /// Validate metadata against this schema, returning every violation found
+    pub fn validate(&self, meta: &serde_json::Value) -> anyhow::Result<Vec<SchemaViolation>> {
+        validate_json_schema(&serde_json::to_value(self)?, meta)
+    }
+}
+
 /// Hash a file and return SHA256 as hex string
 pub fn hash_file(path: &std::path::Path) -> anyhow::Result<String> {
     use std::fs::File;
@@ -356,6 +775,34 @@ pub fn hash_bytes(data: &[u8]) -> String {
     format!("{:x}", hasher.finalize())
 }
 
+/// Which items of a batch upload need uploading
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BatchUploadPlan {
+    /// Indexes into the batch of the items to presign
+    pub uploads: Vec<usize>,
+    pub skipped: Vec<String>,
+    pub new_bytes: u64,
+}
+
+/// Split a batch into items to upload and items whose content is already
+/// in `existing`. Content is addressed by hash, so of several items with
+/// the same hash only the first is uploaded.
+pub fn plan_batch_upload(items: &[BatchUploadItem], existing: &HashSet<String>) -> BatchUploadPlan {
+    let mut plan = BatchUploadPlan::default();
+    let mut planned = HashSet::new();
+
+    for (index, item) in items.iter().enumerate() {
+        if existing.contains(&item.sha256) || !planned.insert(item.sha256.as_str()) {
+            plan.skipped.push(item.path.clone());
+        } else {
+            plan.uploads.push(index);
+            plan.new_bytes += item.size;
+        }
+    }
+
+    plan
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -377,6 +824,41 @@ mod tests {
         assert_eq!(hash, "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9");
     }
 
+    fn batch_item(path: &str, size: u64, sha256: &str) -> BatchUploadItem {
+        BatchUploadItem {
+            path: path.to_string(),
+            size,
+            media_type: None,
+            sha256: sha256.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_plan_batch_upload_skips_stored_and_repeated_hashes() {
+        let items = vec![
+            batch_item("a.csv", 10, "aaaa"),
+            batch_item("b.csv", 20, "bbbb"),
+            batch_item("c.csv", 30, "cccc"),
+            batch_item("copy-of-a.csv", 10, "aaaa"),
+        ];
+        let existing = HashSet::from(["bbbb".to_string()]);
+
+        let plan = plan_batch_upload(&items, &existing);
+
+        assert_eq!(plan.uploads, vec![0, 2]);
+        assert_eq!(plan.skipped, vec!["b.csv", "copy-of-a.csv"]);
+        assert_eq!(plan.new_bytes, 40);
+    }
+
+    #[test]
+    fn test_plan_batch_upload_with_everything_stored_adds_no_bytes() {
+        let items = vec![batch_item("a.csv", 10, "aaaa")];
+        let plan = plan_batch_upload(&items, &HashSet::from(["aaaa".to_string()]));
+
+        assert!(plan.uploads.is_empty());
+        assert_eq!(plan.new_bytes, 0);
+    }
+
     #[test]
     fn test_metadata_schema_default() {
         let schema = MetadataSchema::default();
@@ -384,6 +866,24 @@ mod tests {
         assert!(schema.properties.contains_key("description"));
         assert!(schema.required.contains(&"name".to_string()));
     }
+
+    #[test]
+    fn test_metadata_schema_validate() {
+        let schema = MetadataSchema::default();
+
+        let violations = schema.validate(&serde_json::json!({"description": "no name"})).unwrap();
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].constraint, "required");
+
+        let violations = schema.validate(&serde_json::json!({"name": 42})).unwrap();
+        assert_eq!(violations[0].path, "/name");
+        assert_eq!(violations[0].constraint, "type");
+
+        let violations = schema
+            .validate(&serde_json::json!({"name": "demo", "tags": ["a", "b"]}))
+            .unwrap();
+        assert!(violations.is_empty());
+    }
 }
 
 // Dublin Core Metadata Support
@@ -394,6 +894,7 @@ pub fn dc_context() -> serde_json::Value {
         "@context": {
             "dc": "http://purl.org/dc/elements/1.1/",
             "dcterms": "http://purl.org/dc/terms/",
+            "dcmitype": "http://purl.org/dc/dcmitype/",
             "xsd": "http://www.w3.org/2001/XMLSchema#"
         }
     })
@@ -451,20 +952,75 @@ pub struct ArtifactRdf {
     pub created_at: DateTime<Utc>,
 }
 
+/// Preview rows and inferred column schema captured by the sampling job
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct EntrySample {
+    pub commit_id: UuidWrapper,
+    pub path: String,
+    pub sample: serde_json::Value,
+    pub schema: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum RdfFormat {
     Turtle,
     Jsonld,
+    NTriples,
+    RdfXml,
 }
 
-/// Convert canonical metadata to Dublin Core JSON-LD
+impl RdfFormat {
+    /// Value stored in `artifact_rdf.format` and accepted as `?format=`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RdfFormat::Turtle => "turtle",
+            RdfFormat::Jsonld => "jsonld",
+            RdfFormat::NTriples => "ntriples",
+            RdfFormat::RdfXml => "rdfxml",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            RdfFormat::Turtle => "text/turtle",
+            RdfFormat::Jsonld => "application/ld+json",
+            RdfFormat::NTriples => "application/n-triples",
+            RdfFormat::RdfXml => "application/rdf+xml",
+        }
+    }
+}
+
+impl std::str::FromStr for RdfFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "turtle" => Ok(RdfFormat::Turtle),
+            "jsonld" => Ok(RdfFormat::Jsonld),
+            "ntriples" => Ok(RdfFormat::NTriples),
+            "rdfxml" => Ok(RdfFormat::RdfXml),
+            other => Err(format!(
+                "Invalid RDF format: {}. Use 'turtle', 'jsonld', 'ntriples' or 'rdfxml'",
+                other
+            )),
+        }
+    }
+}
+
+/// Convert canonical metadata to Dublin Core JSON-LD.
+///
+/// The document is in compacted form against [`dc_context`], which keeps the
+/// `dc:`/`dcterms:` keys readable; [`dc_jsonld_expand`] gives the expanded
+/// form, and both describe the same triples as the Turtle serialization.
 pub fn canonical_to_dc_jsonld(subject_iri: &str, meta: &CanonicalMeta) -> serde_json::Value {
     let mut doc = serde_json::Map::new();
     
     // Add context
     doc.insert("@context".to_string(), dc_context()["@context"].clone());
     doc.insert("@id".to_string(), serde_json::Value::String(subject_iri.to_string()));
+    doc.insert("@type".to_string(), serde_json::Value::String("dcmitype:Dataset".to_string()));
     
     // Map canonical fields to Dublin Core
     doc.insert("dc:title".to_string(), serde_json::Value::String(meta.file_name.clone()));
@@ -493,64 +1049,220 @@ pub fn canonical_to_dc_jsonld(subject_iri: &str, meta: &CanonicalMeta) -> serde_
     serde_json::Value::Object(doc)
 }
 
+const DC_NS: &str = "http://purl.org/dc/elements/1.1/";
+const DCTERMS_NS: &str = "http://purl.org/dc/terms/";
+const DCMITYPE_NS: &str = "http://purl.org/dc/dcmitype/";
+const XSD_NS: &str = "http://www.w3.org/2001/XMLSchema#";
+const RDF_NS: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+
+/// Expand a compact Dublin Core JSON-LD key (e.g. `dc:title`) to its predicate IRI
+fn dc_predicate_iri(key: &str) -> Option<String> {
+    let (ns, local) = match key.split_once(':')? {
+        ("dc", local @ ("title" | "creator" | "description" | "format" | "source" | "subject")) => (DC_NS, local),
+        ("dcterms", local @ ("created" | "extent" | "methodOfAccrual" | "publisher" | "hasVersion" | "license")) => (DCTERMS_NS, local),
+        _ => return None,
+    };
+    Some(format!("{}{}", ns, local))
+}
+
+fn iri_term(iri: String) -> anyhow::Result<SimpleTerm<'static>> {
+    Ok(SimpleTerm::Iri(IriRef::new(iri.into())?))
+}
+
+fn typed_literal(lexical: String, xsd_type: &str) -> SimpleTerm<'static> {
+    let datatype = IriRef::new_unchecked(format!("{}{}", XSD_NS, xsd_type).into());
+    SimpleTerm::LiteralDatatype(lexical.into(), datatype)
+}
+
+/// Build the RDF triples described by a Dublin Core JSON-LD document
+fn dc_jsonld_triples(doc: &serde_json::Value) -> anyhow::Result<Vec<[SimpleTerm<'static>; 3]>> {
+    let mut triples = Vec::new();
+    let Some(subject) = doc.get("@id").and_then(|id| id.as_str()) else {
+        return Ok(triples);
+    };
+
+    let subject = iri_term(subject.to_string())?;
+    triples.push([
+        subject.clone(),
+        iri_term(RDF_TYPE.to_string())?,
+        iri_term(format!("{}Dataset", DCMITYPE_NS))?,
+    ]);
+
+    for (key, value) in doc.as_object().into_iter().flatten() {
+        let Some(predicate) = dc_predicate_iri(key) else {
+            continue;
+        };
+        let predicate = iri_term(predicate)?;
+
+        let values: Vec<&serde_json::Value> = match value {
+            serde_json::Value::Array(items) => items.iter().collect(),
+            other => vec![other],
+        };
+        for value in values {
+            let object = match value {
+                serde_json::Value::String(s) => typed_literal(s.clone(), "string"),
+                serde_json::Value::Number(n) if n.is_f64() => typed_literal(n.to_string(), "decimal"),
+                serde_json::Value::Number(n) => typed_literal(n.to_string(), "integer"),
+                serde_json::Value::Bool(b) => typed_literal(b.to_string(), "boolean"),
+                _ => continue,
+            };
+            triples.push([subject.clone(), predicate.clone(), object]);
+        }
+    }
+
+    Ok(triples)
+}
+
+/// Refuse keys that a Dublin Core JSON-LD document's `@context` does not
+/// define. A JSON-LD processor silently drops undefined terms and reads an
+/// undeclared `prefix:` as an IRI scheme, so neither would surface otherwise.
+fn check_dc_jsonld_terms(doc: &serde_json::Value) -> anyhow::Result<()> {
+    let Some(object) = doc.as_object() else {
+        anyhow::bail!("JSON-LD document must be an object");
+    };
+    let context = object.get("@context").and_then(|c| c.as_object());
+    let defined = |term: &str| context.is_some_and(|c| c.contains_key(term));
+
+    let mut terms: Vec<&str> = object.keys().map(String::as_str).collect();
+    if let Some(type_name) = object.get("@type").and_then(|t| t.as_str()) {
+        terms.push(type_name);
+    }
+    for term in terms.into_iter().filter(|t| !t.starts_with('@')) {
+        let resolved = match term.split_once(':') {
+            Some((prefix, _)) => defined(prefix),
+            None => defined(term),
+        };
+        if !resolved {
+            anyhow::bail!("JSON-LD term '{}' is not defined by the document's @context", term);
+        }
+    }
+    Ok(())
+}
+
+/// Read a JSON-LD document with a JSON-LD 1.1 processor. Only inline
+/// contexts are supported; remote ones are not fetched.
+fn jsonld_quads(doc: &serde_json::Value) -> anyhow::Result<Vec<Spog<SimpleTerm<'static>>>> {
+    JsonLdParser::new()
+        .parse_str(&doc.to_string())
+        .collect_quads()
+        .map_err(|e| anyhow::anyhow!("Invalid JSON-LD: {}", e))
+}
+
+/// Expand a Dublin Core JSON-LD document with a JSON-LD processor, after
+/// checking that its `@context` resolves every prefix it uses
+pub fn dc_jsonld_expand(doc: &serde_json::Value) -> anyhow::Result<serde_json::Value> {
+    check_dc_jsonld_terms(doc)?;
+    let quads = jsonld_quads(doc)?;
+
+    let mut serializer = JsonLdSerializer::new_stringifier();
+    serializer
+        .serialize_dataset(&quads)
+        .map_err(|e| anyhow::anyhow!("Failed to expand JSON-LD: {}", e))?;
+    Ok(serde_json::from_str(serializer.as_str())?)
+}
+
 /// Convert Dublin Core JSON-LD to Turtle format
 pub fn dc_jsonld_to_turtle(doc: &serde_json::Value) -> anyhow::Result<String> {
-    // For now, implement a simple Turtle serializer
-    // In a full implementation, you'd use sophia's JSON-LD parser and Turtle serializer
-    let mut turtle = String::new();
-    
-    if let Some(id) = doc.get("@id") {
-        if let Some(subject) = id.as_str() {
-            turtle.push_str(&format!("<{}> ", subject));
-            turtle.push_str("a <http://purl.org/dc/dcmitype/Dataset> ;\n");
-            
-            // Add properties
-            for (key, value) in doc.as_object().unwrap() {
-                if key.starts_with("@") {
-                    continue;
-                }
-                
-                let predicate = match key.as_str() {
-                    "dc:title" => "<http://purl.org/dc/elements/1.1/title>",
-                    "dc:creator" => "<http://purl.org/dc/elements/1.1/creator>",
-                    "dc:description" => "<http://purl.org/dc/elements/1.1/description>",
-                    "dcterms:created" => "<http://purl.org/dc/terms/created>",
-                    "dc:format" => "<http://purl.org/dc/elements/1.1/format>",
-                    "dcterms:extent" => "<http://purl.org/dc/terms/extent>",
-                    "dc:source" => "<http://purl.org/dc/elements/1.1/source>",
-                    "dcterms:methodOfAccrual" => "<http://purl.org/dc/terms/methodOfAccrual>",
-                    "dcterms:publisher" => "<http://purl.org/dc/terms/publisher>",
-                    "dcterms:hasVersion" => "<http://purl.org/dc/terms/hasVersion>",
-                    "dcterms:license" => "<http://purl.org/dc/terms/license>",
-                    "dc:subject" => "<http://purl.org/dc/elements/1.1/subject>",
-                    _ => continue,
-                };
-                
-                match value {
-                    serde_json::Value::String(s) => {
-                        turtle.push_str(&format!("    {} \"{}\" ;\n", predicate, s));
-                    }
-                    serde_json::Value::Number(n) => {
-                        turtle.push_str(&format!("    {} {} ;\n", predicate, n));
-                    }
-                    serde_json::Value::Array(arr) => {
-                        for item in arr {
-                            if let Some(s) = item.as_str() {
-                                turtle.push_str(&format!("    {} \"{}\" ;\n", predicate, s));
-                            }
-                        }
-                    }
-                    _ => {}
-                }
+    let triples = dc_jsonld_triples(doc)?;
+
+    let prefixes = [("dc", DC_NS), ("dcterms", DCTERMS_NS), ("dcmitype", DCMITYPE_NS), ("xsd", XSD_NS)]
+        .into_iter()
+        .map(|(prefix, ns)| Ok((Prefix::new(prefix.into())?, Iri::new(ns.into())?)))
+        .collect::<anyhow::Result<Vec<PrefixMapPair>>>()?;
+    let config = TurtleConfig::new().with_pretty(true).with_own_prefix_map(prefixes);
+
+    let mut serializer = TurtleSerializer::new_stringifier_with_config(config);
+    serializer.serialize_graph(&triples)?;
+    Ok(serializer.to_string())
+}
+
+/// Convert Dublin Core JSON-LD to N-Triples format
+pub fn dc_jsonld_to_ntriples(doc: &serde_json::Value) -> anyhow::Result<String> {
+    let triples = dc_jsonld_triples(doc)?;
+    let mut serializer = NtSerializer::new_stringifier();
+    serializer.serialize_graph(&triples)?;
+    Ok(serializer.to_string())
+}
+
+/// Convert Dublin Core JSON-LD to RDF/XML format
+///
+/// sophia has no RDF/XML serializer in this build, so the document is written
+/// by hand: one `rdf:Description` per subject, with `xsd:string` literals
+/// emitted as plain text and other literals carrying `rdf:datatype`.
+pub fn dc_jsonld_to_rdfxml(doc: &serde_json::Value) -> anyhow::Result<String> {
+    let triples = dc_jsonld_triples(doc)?;
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!(
+        "<rdf:RDF xmlns:rdf=\"{}\" xmlns:dc=\"{}\" xmlns:dcterms=\"{}\">\n",
+        RDF_NS, DC_NS, DCTERMS_NS
+    ));
+
+    let mut current_subject: Option<&str> = None;
+    for [subject, predicate, object] in &triples {
+        let (SimpleTerm::Iri(subject), SimpleTerm::Iri(predicate)) = (subject, predicate) else {
+            anyhow::bail!("RDF/XML output only supports IRI subjects and predicates");
+        };
+
+        if current_subject != Some(subject.as_str()) {
+            if current_subject.is_some() {
+                xml.push_str("  </rdf:Description>\n");
             }
-            
-            // Remove trailing semicolon and add period
-            turtle = turtle.trim_end_matches(" ;\n").to_string();
-            turtle.push_str(" .\n");
+            xml.push_str(&format!("  <rdf:Description rdf:about=\"{}\">\n", xml_escape(subject.as_str())));
+            current_subject = Some(subject.as_str());
+        }
+
+        let qname = rdfxml_qname(predicate.as_str())
+            .ok_or_else(|| anyhow::anyhow!("No RDF/XML prefix for predicate {}", predicate.as_str()))?;
+        match object {
+            SimpleTerm::Iri(iri) => {
+                xml.push_str(&format!("    <{} rdf:resource=\"{}\"/>\n", qname, xml_escape(iri.as_str())));
+            }
+            SimpleTerm::LiteralDatatype(lexical, datatype) if datatype.as_str() == format!("{}string", XSD_NS) => {
+                xml.push_str(&format!("    <{}>{}</{}>\n", qname, xml_escape(lexical), qname));
+            }
+            SimpleTerm::LiteralDatatype(lexical, datatype) => {
+                xml.push_str(&format!(
+                    "    <{} rdf:datatype=\"{}\">{}</{}>\n",
+                    qname,
+                    xml_escape(datatype.as_str()),
+                    xml_escape(lexical),
+                    qname
+                ));
+            }
+            _ => anyhow::bail!("Unsupported RDF/XML object term"),
         }
     }
-    
-    Ok(turtle)
+
+    if current_subject.is_some() {
+        xml.push_str("  </rdf:Description>\n");
+    }
+    xml.push_str("</rdf:RDF>\n");
+    Ok(xml)
+}
+
+/// Compact a predicate IRI to an RDF/XML element name using the declared namespaces
+fn rdfxml_qname(iri: &str) -> Option<String> {
+    [("rdf", RDF_NS), ("dc", DC_NS), ("dcterms", DCTERMS_NS)]
+        .into_iter()
+        .find_map(|(prefix, ns)| iri.strip_prefix(ns).map(|local| format!("{}:{}", prefix, local)))
+}
+
+fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\r' => escaped.push_str("&#13;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
 }
 
 /// Convert canonical metadata directly to Turtle
@@ -559,6 +1271,18 @@ pub fn canonical_to_turtle(subject_iri: &str, meta: &CanonicalMeta) -> anyhow::R
     dc_jsonld_to_turtle(&jsonld)
 }
 
+/// Convert canonical metadata directly to N-Triples
+pub fn canonical_to_ntriples(subject_iri: &str, meta: &CanonicalMeta) -> anyhow::Result<String> {
+    let jsonld = canonical_to_dc_jsonld(subject_iri, meta);
+    dc_jsonld_to_ntriples(&jsonld)
+}
+
+/// Convert canonical metadata directly to RDF/XML
+pub fn canonical_to_rdfxml(subject_iri: &str, meta: &CanonicalMeta) -> anyhow::Result<String> {
+    let jsonld = canonical_to_dc_jsonld(subject_iri, meta);
+    dc_jsonld_to_rdfxml(&jsonld)
+}
+
 /// Project JSONB metadata to entry_meta_index row
 pub fn project_to_index(commit_id: Uuid, path: &str, meta: &serde_json::Value) -> EntryMetaIndex {
     EntryMetaIndex {
@@ -584,10 +1308,61 @@ pub fn project_to_index(commit_id: Uuid, path: &str, meta: &serde_json::Value) -
     }
 }
 
-/// Generate subject IRI for an artifact
-pub fn generate_subject_iri(repo: &str, r#ref: &str, path: &str) -> String {
-    let encoded_path = urlencoding::encode(path);
-    format!("https://blacklake.local/{}/{}/{}", repo, r#ref, encoded_path)
+/// Base IRI of generated RDF subjects when neither `RDF_BASE_IRI` nor the
+/// repo's `rdf_base_iri` feature names one
+pub const DEFAULT_RDF_BASE_IRI: &str = "https://blacklake.local/";
+
+/// Absolute http(s) URL that artifact subject IRIs are minted under, so
+/// emitted RDF points at the deployment's public address
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubjectIriBase(Url);
+
+impl SubjectIriBase {
+    /// Parse a base IRI. It must be an absolute `http` or `https` URL without
+    /// a query or fragment; a trailing slash is added if missing.
+    pub fn parse(base: &str) -> Result<Self, String> {
+        let mut url = Url::parse(base.trim()).map_err(|e| format!("Invalid RDF base IRI '{}': {}", base, e))?;
+        if !matches!(url.scheme(), "http" | "https") || url.host().is_none() {
+            return Err(format!("RDF base IRI '{}' must be an absolute http(s) URL", base));
+        }
+        if url.query().is_some() || url.fragment().is_some() {
+            return Err(format!("RDF base IRI '{}' must not have a query or fragment", base));
+        }
+        if !url.path().ends_with('/') {
+            let path = format!("{}/", url.path());
+            url.set_path(&path);
+        }
+        Ok(Self(url))
+    }
+
+    /// `RDF_BASE_IRI`, or the default when unset
+    pub fn from_env() -> Result<Self, String> {
+        match std::env::var("RDF_BASE_IRI") {
+            Ok(base) if !base.trim().is_empty() => Self::parse(&base),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+
+    /// Subject IRI for the artifact at `path` on `ref` of `repo`. The path is
+    /// percent-encoded as one segment, so it decodes back exactly.
+    pub fn subject_iri(&self, repo: &str, r#ref: &str, path: &str) -> String {
+        format!("{}{}/{}/{}", self.0, repo, r#ref, urlencoding::encode(path))
+    }
+}
+
+impl Default for SubjectIriBase {
+    fn default() -> Self {
+        Self::parse(DEFAULT_RDF_BASE_IRI).expect("default RDF base IRI is valid")
+    }
+}
+
+/// Generate subject IRI for an artifact under `base`
+pub fn generate_subject_iri(base: &SubjectIriBase, repo: &str, r#ref: &str, path: &str) -> String {
+    base.subject_iri(repo, r#ref, path)
 }
 
 #[cfg(test)]
@@ -640,6 +1415,221 @@ mod metadata_tests {
         assert!(turtle.contains("Test Dataset"));
     }
 
+    fn parse_turtle(turtle: &str) -> Vec<[sophia::api::term::SimpleTerm<'static>; 3]> {
+        use sophia::api::source::TripleSource;
+        sophia::turtle::parser::turtle::parse_str(turtle)
+            .collect_triples()
+            .expect("serialized Turtle should re-parse")
+    }
+
+    fn literal_for(triples: &[[sophia::api::term::SimpleTerm<'static>; 3]], predicate: &str) -> Option<String> {
+        use sophia::api::term::Term;
+        triples.iter()
+            .find(|[_, p, _]| p.iri().map(|iri| iri.as_str() == predicate).unwrap_or(false))
+            .and_then(|[_, _, o]| o.lexical_form().map(|l| l.to_string()))
+    }
+
+    #[test]
+    fn test_dc_jsonld_to_turtle_escapes_quotes() {
+        let jsonld = serde_json::json!({
+            "@id": "https://example.org/quoted",
+            "dc:title": "The \"best\" dataset",
+            "dcterms:extent": 42
+        });
+
+        let turtle = dc_jsonld_to_turtle(&jsonld).unwrap();
+        let triples = parse_turtle(&turtle);
+
+        assert_eq!(triples.len(), 3);
+        assert_eq!(
+            literal_for(&triples, "http://purl.org/dc/elements/1.1/title").as_deref(),
+            Some("The \"best\" dataset")
+        );
+        assert_eq!(literal_for(&triples, "http://purl.org/dc/terms/extent").as_deref(), Some("42"));
+    }
+
+    #[test]
+    fn test_dc_jsonld_to_turtle_multiline_description() {
+        let description = "First line\nSecond line with ünïcödé\n\tindented";
+        let jsonld = serde_json::json!({
+            "@id": "https://example.org/multiline",
+            "dc:description": description,
+            "dc:subject": ["a", "b"]
+        });
+
+        let turtle = dc_jsonld_to_turtle(&jsonld).unwrap();
+        let triples = parse_turtle(&turtle);
+
+        assert_eq!(triples.len(), 4);
+        assert_eq!(
+            literal_for(&triples, "http://purl.org/dc/elements/1.1/description").as_deref(),
+            Some(description)
+        );
+    }
+
+    fn rdf_test_meta() -> CanonicalMeta {
+        CanonicalMeta {
+            creation_dt: Utc.with_ymd_and_hms(2025, 1, 17, 18, 28, 0).unwrap(),
+            creator: "you@example.org".to_string(),
+            file_name: "a <b> & \"c\".csv".to_string(),
+            file_type: "text/csv".to_string(),
+            file_size: 1234,
+            org_lab: "ORNL".to_string(),
+            description: "Line one\nLine 'two'".to_string(),
+            data_source: "sensor".to_string(),
+            data_collection_method: "manual".to_string(),
+            version: "1.0".to_string(),
+            notes: None,
+            tags: Some(vec!["demo".to_string(), "csv".to_string()]),
+            license: Some("CC-BY-4.0".to_string()),
+        }
+    }
+
+    fn expected_triples(subject_iri: &str, meta: &CanonicalMeta) -> Vec<[SimpleTerm<'static>; 3]> {
+        dc_jsonld_triples(&canonical_to_dc_jsonld(subject_iri, meta)).unwrap()
+    }
+
+    #[test]
+    fn test_rdf_format_round_trips_through_str() {
+        for format in [RdfFormat::Turtle, RdfFormat::Jsonld, RdfFormat::NTriples, RdfFormat::RdfXml] {
+            let parsed: RdfFormat = format.as_str().parse().unwrap();
+            assert_eq!(parsed.as_str(), format.as_str());
+            assert_eq!(serde_json::to_value(&format).unwrap(), format.as_str());
+        }
+        assert!("n3".parse::<RdfFormat>().is_err());
+    }
+
+    #[test]
+    fn test_canonical_to_turtle_round_trip() {
+        let subject_iri = "https://blacklake.local/mylab/main/datasets%2Fdemo.csv";
+        let meta = rdf_test_meta();
+
+        let turtle = canonical_to_turtle(subject_iri, &meta).unwrap();
+        let parsed = parse_turtle(&turtle);
+
+        assert!(sophia::isomorphism::isomorphic_graphs(&parsed, &expected_triples(subject_iri, &meta)).unwrap());
+    }
+
+    #[test]
+    fn test_canonical_to_ntriples_round_trip() {
+        use sophia::api::source::TripleSource;
+
+        let subject_iri = "https://blacklake.local/mylab/main/datasets%2Fdemo.csv";
+        let meta = rdf_test_meta();
+
+        let ntriples = canonical_to_ntriples(subject_iri, &meta).unwrap();
+        let expected = expected_triples(subject_iri, &meta);
+        assert_eq!(ntriples.lines().count(), expected.len());
+
+        let parsed: Vec<[SimpleTerm<'static>; 3]> = sophia::turtle::parser::nt::parse_str(&ntriples)
+            .collect_triples()
+            .unwrap();
+        assert!(sophia::isomorphism::isomorphic_graphs(&parsed, &expected).unwrap());
+    }
+
+    #[test]
+    fn test_canonical_to_rdfxml_round_trip() {
+        let subject_iri = "https://blacklake.local/mylab/main/datasets%2Fdemo.csv";
+        let meta = rdf_test_meta();
+
+        let xml = canonical_to_rdfxml(subject_iri, &meta).unwrap();
+        let doc = roxmltree::Document::parse(&xml).expect("RDF/XML should be well-formed");
+
+        let mut parsed = Vec::new();
+        for description in doc.root_element().children().filter(|n| n.is_element()) {
+            assert_eq!(description.tag_name().name(), "Description");
+            let subject = iri_term(description.attribute((RDF_NS, "about")).unwrap().to_string()).unwrap();
+            for property in description.children().filter(|n| n.is_element()) {
+                let predicate = format!(
+                    "{}{}",
+                    property.tag_name().namespace().unwrap(),
+                    property.tag_name().name()
+                );
+                let object = match property.attribute((RDF_NS, "resource")) {
+                    Some(resource) => iri_term(resource.to_string()).unwrap(),
+                    None => {
+                        let lexical = property.text().unwrap_or("").to_string();
+                        match property.attribute((RDF_NS, "datatype")) {
+                            Some(datatype) => typed_literal(lexical, datatype.strip_prefix(XSD_NS).unwrap()),
+                            None => typed_literal(lexical, "string"),
+                        }
+                    }
+                };
+                parsed.push([subject.clone(), iri_term(predicate).unwrap(), object]);
+            }
+        }
+
+        assert!(sophia::isomorphism::isomorphic_graphs(&parsed, &expected_triples(subject_iri, &meta)).unwrap());
+    }
+
+    /// Default-graph triples a JSON-LD processor reads from `doc`
+    fn processed_triples(doc: &serde_json::Value) -> Vec<[SimpleTerm<'static>; 3]> {
+        jsonld_quads(doc).unwrap().into_iter().filter(|(_, graph)| graph.is_none()).map(|(triple, _)| triple).collect()
+    }
+
+    #[test]
+    fn test_jsonld_expands_to_turtle_triples() {
+        let subject_iri = "https://blacklake.local/mylab/main/datasets%2Fdemo.csv";
+        let meta = rdf_test_meta();
+
+        let compacted = canonical_to_dc_jsonld(subject_iri, &meta);
+        let turtle = parse_turtle(&canonical_to_turtle(subject_iri, &meta).unwrap());
+        assert!(sophia::isomorphism::isomorphic_graphs(&processed_triples(&compacted), &turtle).unwrap());
+
+        // The expanded form names every predicate by full IRI and still describes the same graph
+        let expanded = dc_jsonld_expand(&compacted).unwrap();
+        let text = expanded.to_string();
+        assert!(!text.contains("\"dc:"), "{}", text);
+        assert!(text.contains("http://purl.org/dc/elements/1.1/title"), "{}", text);
+        assert!(sophia::isomorphism::isomorphic_graphs(&processed_triples(&expanded), &turtle).unwrap());
+        assert!(sophia::isomorphism::isomorphic_graphs(&processed_triples(&expanded), &expected_triples(subject_iri, &meta)).unwrap());
+    }
+
+    #[test]
+    fn test_jsonld_with_unresolved_prefix_is_rejected() {
+        let mut doc = canonical_to_dc_jsonld("https://blacklake.local/mylab/main/a.csv", &rdf_test_meta());
+        doc["@context"].as_object_mut().unwrap().remove("dcterms");
+        let err = dc_jsonld_expand(&doc).unwrap_err();
+        assert!(err.to_string().contains("dcterms:"), "{}", err);
+
+        let mut doc = canonical_to_dc_jsonld("https://blacklake.local/mylab/main/a.csv", &rdf_test_meta());
+        doc["title"] = serde_json::json!("undefined term");
+        assert!(dc_jsonld_expand(&doc).is_err());
+    }
+
+    #[test]
+    fn test_configured_rdf_base_is_used() {
+        let default = generate_subject_iri(&SubjectIriBase::default(), "mylab", "main", "demo.csv");
+        assert_eq!(default, "https://blacklake.local/mylab/main/demo.csv");
+
+        let base = SubjectIriBase::parse("https://data.example.org/ld").unwrap();
+        assert_eq!(base.as_str(), "https://data.example.org/ld/");
+        assert_eq!(
+            generate_subject_iri(&base, "mylab", "main", "datasets/demo.csv"),
+            "https://data.example.org/ld/mylab/main/datasets%2Fdemo.csv"
+        );
+
+        for invalid in ["data.example.org/ld", "/relative/path", "ftp://data.example.org/", "mailto:a@b.org",
+            "https://data.example.org/?q=1", "https://data.example.org/#frag"]
+        {
+            assert!(SubjectIriBase::parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_subject_iri_path_round_trips_special_characters() {
+        let base = SubjectIriBase::parse("http://localhost:8080/rdf/").unwrap();
+        for path in ["datasets/demo.csv", "a b/c#d?e.csv", "über/naïve%20file.parquet", "x&y=z/[1].json"] {
+            let iri = base.subject_iri("mylab", "main", path);
+            let encoded = iri.strip_prefix("http://localhost:8080/rdf/mylab/main/").unwrap();
+
+            assert!(!encoded.contains(['/', '#', '?', ' ']), "{}", iri);
+            assert_eq!(urlencoding::decode(encoded).unwrap(), path);
+            assert!(Url::parse(&iri).is_ok(), "{}", iri);
+            assert!(iri_term(iri).is_ok());
+        }
+    }
+
     #[test]
     fn test_project_to_index() {
         let meta = serde_json::json!({
@@ -673,7 +1663,20 @@ pub mod merge;
 pub mod schema;
 pub mod governance;
 pub mod jobs;
+pub mod export_jobs;
+pub mod integrity;
+pub mod reindex;
+pub mod sniff;
+pub mod derived;
+pub mod parquet_convert;
+pub mod sampling;
+pub mod signing;
+pub mod thumbnail;
 pub mod policy;
+pub mod policy_backend;
+pub mod role_permissions;
+pub mod features;
+pub mod content_policy;
 pub mod search;
 pub mod sessions;
 pub mod embeddings;
@@ -682,6 +1685,8 @@ pub mod compliance_jobs;
 pub mod compliance_worker;
 pub mod observability;
 
+#[cfg(test)]
+mod s3_mock;
 #[cfg(test)]
 mod governance_tests;
 #[cfg(test)]
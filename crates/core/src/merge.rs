@@ -1,6 +1,9 @@
 use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use crate::{Change, ChangeOp};
 
 /// Deep merge two JSON values with precedence to the new value
 /// Arrays for tags are union-deduplicated
@@ -80,6 +83,30 @@ pub fn deep_merge(old: &Value, new: &Value) -> Result<Value> {
     }
 }
 
+/// Apply an RFC 7396 JSON Merge Patch to `target`.
+///
+/// Objects merge key by key, a `null` removes the key, and anything else,
+/// arrays included, replaces the target value outright. Unlike `deep_merge`,
+/// tags are not unioned, so a patch can drop a tag.
+pub fn json_merge_patch(target: &Value, patch: &Value) -> Value {
+    let Value::Object(patch_map) = patch else {
+        return patch.clone();
+    };
+    let mut result = match target {
+        Value::Object(map) => map.clone(),
+        _ => Map::new(),
+    };
+    for (key, value) in patch_map {
+        if value.is_null() {
+            result.remove(key);
+        } else {
+            let patched = json_merge_patch(result.get(key).unwrap_or(&Value::Null), value);
+            result.insert(key.clone(), patched);
+        }
+    }
+    Value::Object(result)
+}
+
 /// Merge metadata specifically for CanonicalMeta
 pub fn merge_canonical_meta(old_meta: &Value, new_meta: &Value) -> Result<Value> {
     let merged = deep_merge(old_meta, new_meta)?;
@@ -152,6 +179,373 @@ pub fn get_metadata_changes(old_meta: &Value, new_meta: &Value) -> (Vec<String>,
     (changed_keys, removed_keys)
 }
 
+/// How to settle paths and fields both sides of a merge changed differently
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MergeStrategy {
+    /// Keep the value on the branch being merged into
+    Ours,
+    /// Take the value from the branch being merged in
+    Theirs,
+}
+
+impl std::str::FromStr for MergeStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "ours" => Ok(MergeStrategy::Ours),
+            "theirs" => Ok(MergeStrategy::Theirs),
+            other => Err(format!("Invalid merge strategy: {}. Use 'ours' or 'theirs'", other)),
+        }
+    }
+}
+
+/// A path's object and metadata in one commit of a merge
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeSide {
+    pub sha256: Option<String>,
+    pub meta: Value,
+}
+
+/// Something both branches changed since their common ancestor, differently
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MergeConflict {
+    pub path: String,
+    /// Dotted metadata field, or `content` when the object itself (or its
+    /// existence) differs on both sides
+    pub field: String,
+    pub base: Option<Value>,
+    pub ours: Option<Value>,
+    pub theirs: Option<Value>,
+}
+
+/// Changes to apply on top of "ours", plus everything that conflicted.
+///
+/// With a strategy the conflicts are already resolved in `changes` and are
+/// listed for reporting only.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MergePlan {
+    pub changes: Vec<Change>,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+/// Body of `POST /v1/repos/:repo/merge`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MergeRequest {
+    /// Branch whose changes are merged in
+    pub from_ref: String,
+    /// Branch that receives the merge commit
+    pub into_ref: String,
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+/// Result of a merge; `commit_id` is absent when nothing was committed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MergeResponse {
+    pub merged: bool,
+    pub commit_id: Option<uuid::Uuid>,
+    pub base_commit_id: Option<uuid::Uuid>,
+    pub changes: usize,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+/// Three-way merge of two branches' trees against their common ancestor.
+///
+/// A side that left a path (or field) as it was in `base` yields to the
+/// other. When both changed it differently the result depends on
+/// `strategy`; with none, "ours" is kept and a conflict is recorded. Tags
+/// edited on both sides are not a conflict: additions from either side are
+/// unioned, as with `deep_merge`, and removals from either side are kept.
+pub fn three_way_merge(
+    base: &HashMap<String, MergeSide>,
+    ours: &HashMap<String, MergeSide>,
+    theirs: &HashMap<String, MergeSide>,
+    strategy: Option<MergeStrategy>,
+) -> Result<MergePlan> {
+    let paths: BTreeSet<&String> = base.keys().chain(ours.keys()).chain(theirs.keys()).collect();
+    let mut plan = MergePlan::default();
+
+    for path in paths {
+        let (b, o, t) = (base.get(path), ours.get(path), theirs.get(path));
+        if o == t || t == b {
+            continue;
+        }
+        if o == b {
+            plan.changes.push(change_to(path, o, t));
+            continue;
+        }
+
+        match (o, t) {
+            (Some(o), Some(t)) => {
+                let base_sha = b.and_then(|b| b.sha256.as_ref());
+                let sha256 = if o.sha256 == t.sha256 || t.sha256.as_ref() == base_sha {
+                    o.sha256.clone()
+                } else if o.sha256.as_ref() == base_sha {
+                    t.sha256.clone()
+                } else {
+                    plan.conflicts.push(MergeConflict {
+                        path: path.clone(),
+                        field: "content".to_string(),
+                        base: base_sha.map(|s| Value::String(s.clone())),
+                        ours: o.sha256.clone().map(Value::String),
+                        theirs: t.sha256.clone().map(Value::String),
+                    });
+                    match strategy {
+                        Some(MergeStrategy::Theirs) => t.sha256.clone(),
+                        _ => o.sha256.clone(),
+                    }
+                };
+
+                let empty = Value::Object(Map::new());
+                let base_meta = b.map(|b| &b.meta).unwrap_or(&empty);
+                let meta = merge_meta(path, "", Some(base_meta), &o.meta, &t.meta, strategy, &mut plan.conflicts)?;
+
+                let merged = MergeSide { sha256, meta };
+                if &merged != o {
+                    plan.changes.push(change_to(path, Some(o), Some(&merged)));
+                }
+            }
+            // One side deleted the path, the other changed it
+            _ => {
+                plan.conflicts.push(MergeConflict {
+                    path: path.clone(),
+                    field: "content".to_string(),
+                    base: b.map(|b| b.meta.clone()),
+                    ours: o.map(|o| o.meta.clone()),
+                    theirs: t.map(|t| t.meta.clone()),
+                });
+                if strategy == Some(MergeStrategy::Theirs) {
+                    plan.changes.push(change_to(path, o, t));
+                }
+            }
+        }
+    }
+
+    Ok(plan)
+}
+
+/// The change that turns `from` into `to` at `path`
+fn change_to(path: &str, from: Option<&MergeSide>, to: Option<&MergeSide>) -> Change {
+    let (op, to) = match (from, to) {
+        (_, None) => (ChangeOp::Delete, None),
+        (None, Some(to)) => (ChangeOp::Add, Some(to)),
+        (Some(from), Some(to)) if from.sha256 == to.sha256 => (ChangeOp::Meta, Some(to)),
+        (Some(_), Some(to)) => (ChangeOp::Modify, Some(to)),
+    };
+    Change {
+        op,
+        path: path.to_string(),
+        sha256: to.and_then(|to| to.sha256.clone()),
+        meta: to.map(|to| to.meta.clone()).unwrap_or_else(|| Value::Object(Map::new())),
+    }
+}
+
+/// Field-level three-way merge of one value, recursing into objects
+fn merge_meta(
+    path: &str,
+    field: &str,
+    base: Option<&Value>,
+    ours: &Value,
+    theirs: &Value,
+    strategy: Option<MergeStrategy>,
+    conflicts: &mut Vec<MergeConflict>,
+) -> Result<Value> {
+    let (Value::Object(ours_map), Value::Object(theirs_map)) = (ours, theirs) else {
+        conflicts.push(MergeConflict {
+            path: path.to_string(),
+            field: field.to_string(),
+            base: base.cloned(),
+            ours: Some(ours.clone()),
+            theirs: Some(theirs.clone()),
+        });
+        return Ok(match strategy {
+            Some(MergeStrategy::Theirs) => theirs.clone(),
+            _ => ours.clone(),
+        });
+    };
+    let base_map = base.and_then(Value::as_object);
+
+    let keys: BTreeSet<&String> = ours_map.keys().chain(theirs_map.keys()).chain(base_map.into_iter().flat_map(|m| m.keys())).collect();
+    let mut merged = ours_map.clone();
+    for key in keys {
+        let b = base_map.and_then(|m| m.get(key));
+        let (o, t) = (ours_map.get(key), theirs_map.get(key));
+        if o == t || t == b {
+            continue;
+        }
+        if o == b {
+            match t {
+                Some(t) => merged.insert(key.clone(), t.clone()),
+                None => merged.remove(key),
+            };
+            continue;
+        }
+
+        let nested = if field.is_empty() { key.clone() } else { format!("{}.{}", field, key) };
+        match (o, t) {
+            (Some(o), Some(t)) if nested == "tags" && o.is_array() && t.is_array() => {
+                merged.insert(key.clone(), merge_tags(b, o, t)?);
+            }
+            (Some(o), Some(t)) => {
+                merged.insert(key.clone(), merge_meta(path, &nested, b, o, t, strategy, conflicts)?);
+            }
+            // Removed on one side, changed on the other
+            _ => {
+                conflicts.push(MergeConflict {
+                    path: path.to_string(),
+                    field: nested,
+                    base: b.cloned(),
+                    ours: o.cloned(),
+                    theirs: t.cloned(),
+                });
+                if strategy == Some(MergeStrategy::Theirs) {
+                    match t {
+                        Some(t) => merged.insert(key.clone(), t.clone()),
+                        None => merged.remove(key),
+                    };
+                }
+            }
+        }
+    }
+
+    Ok(Value::Object(merged))
+}
+
+/// Union both sides' tags, dropping any tag either side removed from `base`
+fn merge_tags(base: Option<&Value>, ours: &Value, theirs: &Value) -> Result<Value> {
+    let union = deep_merge(&serde_json::json!({ "tags": ours }), &serde_json::json!({ "tags": theirs }))?;
+    let held = |side: &Value, tag: &Value| side.as_array().is_some_and(|tags| tags.contains(tag));
+    let removed: Vec<&Value> = base
+        .and_then(Value::as_array)
+        .map(|tags| tags.iter().filter(|tag| !held(ours, tag) || !held(theirs, tag)).collect())
+        .unwrap_or_default();
+
+    let mut tags: Vec<Value> = union["tags"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|tag| !removed.contains(&tag))
+        .collect();
+    tags.sort_by_key(|tag| tag.to_string());
+    Ok(Value::Array(tags))
+}
+
+/// One RFC 6902 operation in a metadata diff
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum PatchOperation {
+    Add { path: String, value: Value },
+    Remove { path: String },
+    Replace { path: String, value: Value },
+}
+
+/// Response of `GET /v1/repos/:repo/meta-diff/:ref/*path`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetaDiffResponse {
+    pub path: String,
+    pub base: uuid::Uuid,
+    pub head: uuid::Uuid,
+    pub patch: Vec<PatchOperation>,
+}
+
+/// JSON Patch that turns `base` into `head`.
+///
+/// Objects are diffed key by key, recursively; arrays and scalars that
+/// differ are replaced whole. A path missing from a commit is passed as
+/// `None` and diffs as an empty object, so a new entry yields only adds.
+pub fn json_patch(base: Option<&Value>, head: Option<&Value>) -> Vec<PatchOperation> {
+    let empty = Value::Object(Map::new());
+    let mut ops = Vec::new();
+    diff_values("", base.unwrap_or(&empty), head.unwrap_or(&empty), &mut ops);
+    ops
+}
+
+fn diff_values(pointer: &str, base: &Value, head: &Value, ops: &mut Vec<PatchOperation>) {
+    if base == head {
+        return;
+    }
+    match (base, head) {
+        (Value::Object(base), Value::Object(head)) => {
+            for key in base.keys().filter(|key| !head.contains_key(*key)) {
+                ops.push(PatchOperation::Remove { path: pointer_child(pointer, key) });
+            }
+            for (key, value) in head {
+                let path = pointer_child(pointer, key);
+                match base.get(key) {
+                    Some(old) => diff_values(&path, old, value, ops),
+                    None => ops.push(PatchOperation::Add { path, value: value.clone() }),
+                }
+            }
+        }
+        _ => ops.push(PatchOperation::Replace { path: pointer.to_string(), value: head.clone() }),
+    }
+}
+
+/// The commit that last set one top-level metadata field
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldBlame {
+    pub field: String,
+    /// The field's current value
+    pub value: Value,
+    pub commit_id: uuid::Uuid,
+    pub author: String,
+    pub message: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Response of `GET /v1/repos/:repo/blame/:ref/*path`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetaBlameResponse {
+    pub path: String,
+    pub head: uuid::Uuid,
+    /// Commits looked at, head included
+    pub depth: usize,
+    /// The walk hit its depth cap before the root, so fields attributed to
+    /// the oldest commit walked may have been set earlier
+    pub truncated: bool,
+    pub fields: Vec<FieldBlame>,
+}
+
+/// Attribute each field of the newest metadata in `history` to the commit
+/// that last set its current value.
+///
+/// `history` is one path's metadata in a line of commits, newest first, with
+/// `None` where the path is missing. A field belongs to the oldest commit of
+/// the unbroken run, starting at the head, in which it has its current
+/// value; a field that never changed belongs to the oldest commit walked.
+pub fn blame_fields(history: &[(crate::Commit, Option<Value>)]) -> Vec<FieldBlame> {
+    let Some((_, Some(Value::Object(current)))) = history.first() else {
+        return Vec::new();
+    };
+
+    current
+        .iter()
+        .map(|(field, value)| {
+            let unchanged = history
+                .iter()
+                .take_while(|(_, meta)| meta.as_ref().and_then(|meta| meta.get(field)) == Some(value))
+                .count();
+            let (commit, _) = &history[unchanged - 1];
+            FieldBlame {
+                field: field.clone(),
+                value: value.clone(),
+                commit_id: commit.id.0,
+                author: commit.author.clone(),
+                message: commit.message.clone(),
+                created_at: commit.created_at,
+            }
+        })
+        .collect()
+}
+
+/// Append `key` to a JSON Pointer, escaping `~` and `/` per RFC 6901
+fn pointer_child(pointer: &str, key: &str) -> String {
+    format!("{}/{}", pointer, key.replace('~', "~0").replace('/', "~1"))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -187,6 +581,35 @@ mod tests {
         assert_eq!(result["extra"], "new_field"); // Added from new
     }
 
+    #[test]
+    fn test_json_merge_patch_follows_rfc_7396() {
+        let target = json!({
+            "title": "Goodbye!",
+            "author": {"givenName": "John", "familyName": "Doe"},
+            "tags": ["example", "sample"],
+            "content": "This will be unchanged"
+        });
+        let patch = json!({
+            "title": "Hello!",
+            "phoneNumber": "+01-123-456-7890",
+            "author": {"familyName": null},
+            "tags": ["example"]
+        });
+
+        assert_eq!(
+            json_merge_patch(&target, &patch),
+            json!({
+                "title": "Hello!",
+                "author": {"givenName": "John"},
+                "tags": ["example"],
+                "content": "This will be unchanged",
+                "phoneNumber": "+01-123-456-7890"
+            })
+        );
+        assert_eq!(json_merge_patch(&json!({"a": "b"}), &json!(["c"])), json!(["c"]));
+        assert_eq!(json_merge_patch(&json!(["a"]), &json!({"a": {"bb": null}})), json!({"a": {}}));
+    }
+
     #[test]
     fn test_deep_merge_tags_union() {
         let old = json!({
@@ -306,4 +729,201 @@ mod tests {
         
         assert!(validate_canonical_meta(&invalid_meta).is_err());
     }
+
+    fn side(sha256: &str, meta: Value) -> MergeSide {
+        MergeSide { sha256: Some(sha256.to_string()), meta }
+    }
+
+    fn tree(entries: &[(&str, MergeSide)]) -> HashMap<String, MergeSide> {
+        entries.iter().map(|(path, side)| (path.to_string(), side.clone())).collect()
+    }
+
+    #[test]
+    fn test_three_way_merge_combines_independent_edits() {
+        let base = tree(&[
+            ("a.csv", side("aaa", json!({"title": "A", "owner": "lab", "tags": ["x", "y"]}))),
+            ("gone.csv", side("ggg", json!({"title": "G"}))),
+        ]);
+        let ours = tree(&[
+            ("a.csv", side("aaa", json!({"title": "A", "owner": "team", "tags": ["x", "y", "ours"]}))),
+            ("gone.csv", side("ggg", json!({"title": "G"}))),
+        ]);
+        let theirs = tree(&[
+            ("a.csv", side("bbb", json!({"title": "A2", "owner": "lab", "tags": ["y", "theirs"]}))),
+            ("new.csv", side("nnn", json!({"title": "N"}))),
+        ]);
+
+        let plan = three_way_merge(&base, &ours, &theirs, None).unwrap();
+
+        assert!(plan.conflicts.is_empty());
+        let by_path: HashMap<&str, &Change> = plan.changes.iter().map(|c| (c.path.as_str(), c)).collect();
+        assert_eq!(by_path.len(), 3);
+
+        let a = by_path["a.csv"];
+        assert_eq!(a.op, ChangeOp::Modify);
+        assert_eq!(a.sha256.as_deref(), Some("bbb"));
+        assert_eq!(a.meta, json!({"title": "A2", "owner": "team", "tags": ["ours", "theirs", "y"]}));
+
+        assert_eq!(by_path["gone.csv"].op, ChangeOp::Delete);
+        assert_eq!(by_path["new.csv"].op, ChangeOp::Add);
+    }
+
+    #[test]
+    fn test_three_way_merge_flags_divergent_field_edit() {
+        let base = tree(&[("a.csv", side("aaa", json!({"title": "A", "spatial": {"crs": "EPSG:4326"}})))]);
+        let ours = tree(&[("a.csv", side("aaa", json!({"title": "Ours", "spatial": {"crs": "EPSG:3857"}})))]);
+        let theirs = tree(&[("a.csv", side("aaa", json!({"title": "Theirs", "spatial": {"crs": "EPSG:4326"}})))]);
+
+        let plan = three_way_merge(&base, &ours, &theirs, None).unwrap();
+
+        assert!(plan.changes.is_empty());
+        assert_eq!(
+            plan.conflicts,
+            vec![MergeConflict {
+                path: "a.csv".to_string(),
+                field: "title".to_string(),
+                base: Some(json!("A")),
+                ours: Some(json!("Ours")),
+                theirs: Some(json!("Theirs")),
+            }]
+        );
+
+        let theirs_wins = three_way_merge(&base, &ours, &theirs, Some(MergeStrategy::Theirs)).unwrap();
+        assert_eq!(theirs_wins.conflicts.len(), 1);
+        assert_eq!(theirs_wins.changes[0].meta, json!({"title": "Theirs", "spatial": {"crs": "EPSG:3857"}}));
+
+        let ours_wins = three_way_merge(&base, &ours, &theirs, Some(MergeStrategy::Ours)).unwrap();
+        assert!(ours_wins.changes.is_empty());
+    }
+
+    #[test]
+    fn test_three_way_merge_flags_content_and_delete_conflicts() {
+        let base = tree(&[
+            ("a.csv", side("aaa", json!({}))),
+            ("b.csv", side("bbb", json!({}))),
+        ]);
+        let ours = tree(&[("a.csv", side("ours", json!({}))), ("b.csv", side("b2", json!({})))]);
+        let theirs = tree(&[("a.csv", side("theirs", json!({})))]);
+
+        let plan = three_way_merge(&base, &ours, &theirs, None).unwrap();
+        let fields: Vec<(&str, &str)> = plan.conflicts.iter().map(|c| (c.path.as_str(), c.field.as_str())).collect();
+        assert_eq!(fields, vec![("a.csv", "content"), ("b.csv", "content")]);
+
+        let plan = three_way_merge(&base, &ours, &theirs, Some(MergeStrategy::Theirs)).unwrap();
+        let ops: Vec<(&str, &ChangeOp)> = plan.changes.iter().map(|c| (c.path.as_str(), &c.op)).collect();
+        assert_eq!(ops, vec![("a.csv", &ChangeOp::Modify), ("b.csv", &ChangeOp::Delete)]);
+    }
+
+    #[test]
+    fn test_json_patch_adds_everything_when_path_is_new() {
+        let head = json!({ "title": "temps", "owner": { "team": "climate" } });
+
+        let patch = json_patch(None, Some(&head));
+
+        assert_eq!(
+            patch,
+            vec![
+                PatchOperation::Add { path: "/title".to_string(), value: json!("temps") },
+                PatchOperation::Add { path: "/owner".to_string(), value: json!({ "team": "climate" }) },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_json_patch_adds_and_removes_nested_fields() {
+        let base = json!({ "owner": { "team": "climate", "email": "a@example.com" } });
+        let head = json!({ "owner": { "team": "climate", "slack": "#climate" } });
+
+        let patch = json_patch(Some(&base), Some(&head));
+
+        assert_eq!(
+            patch,
+            vec![
+                PatchOperation::Remove { path: "/owner/email".to_string() },
+                PatchOperation::Add { path: "/owner/slack".to_string(), value: json!("#climate") },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_json_patch_replaces_changed_nested_values() {
+        let base = json!({ "schema": { "columns": 3, "keys/ids": ["a"] }, "tags": ["x"] });
+        let head = json!({ "schema": { "columns": 4, "keys/ids": ["a", "b"] }, "tags": ["x"] });
+
+        let patch = json_patch(Some(&base), Some(&head));
+
+        assert_eq!(
+            patch,
+            vec![
+                PatchOperation::Replace { path: "/schema/columns".to_string(), value: json!(4) },
+                PatchOperation::Replace { path: "/schema/keys~1ids".to_string(), value: json!(["a", "b"]) },
+            ]
+        );
+        assert_eq!(
+            serde_json::to_value(&patch[0]).unwrap(),
+            json!({ "op": "replace", "path": "/schema/columns", "value": 4 })
+        );
+        assert!(json_patch(Some(&base), Some(&base)).is_empty());
+    }
+
+    fn commit(author: &str, message: &str) -> crate::Commit {
+        crate::Commit {
+            id: crate::UuidWrapper(uuid::Uuid::new_v4()),
+            repo_id: crate::UuidWrapper(uuid::Uuid::nil()),
+            parent_id: None,
+            author: author.to_string(),
+            message: Some(message.to_string()),
+            created_at: chrono::Utc::now(),
+            stats: None,
+        }
+    }
+
+    #[test]
+    fn test_blame_attributes_each_field_to_the_commit_that_set_it() {
+        let created = commit("alice", "Add readings");
+        let described = commit("bob", "Describe readings");
+        let tagged = commit("carol", "Tag readings");
+        let unrelated = commit("dave", "Touch another file");
+        let history = vec![
+            (unrelated.clone(), Some(json!({"title": "Readings", "description": "Hourly", "tags": ["raw", "qc"]}))),
+            (tagged.clone(), Some(json!({"title": "Readings", "description": "Hourly", "tags": ["raw", "qc"]}))),
+            (described.clone(), Some(json!({"title": "Readings", "description": "Hourly", "tags": ["raw"]}))),
+            (created.clone(), Some(json!({"title": "Readings", "description": "", "tags": ["raw"]}))),
+        ];
+
+        let blame = blame_fields(&history);
+
+        let by_field: HashMap<&str, &FieldBlame> = blame.iter().map(|b| (b.field.as_str(), b)).collect();
+        assert_eq!(by_field.len(), 3);
+        assert_eq!(by_field["description"].commit_id, described.id.0);
+        assert_eq!(by_field["description"].author, "bob");
+        assert_eq!(by_field["description"].value, json!("Hourly"));
+        assert_eq!(by_field["tags"].commit_id, tagged.id.0);
+        assert_eq!(by_field["tags"].author, "carol");
+        assert_eq!(by_field["tags"].message.as_deref(), Some("Tag readings"));
+        // Never changed, so it belongs to the first commit
+        assert_eq!(by_field["title"].commit_id, created.id.0);
+    }
+
+    #[test]
+    fn test_blame_stops_where_the_path_or_field_first_appears() {
+        let before = commit("alice", "Before the file existed");
+        let added = commit("bob", "Add file");
+        let reverted = commit("carol", "Revert description");
+        let changed = commit("dave", "Change description");
+        let history = vec![
+            (reverted.clone(), Some(json!({"description": "v1", "units": "C"}))),
+            (changed.clone(), Some(json!({"description": "v2", "units": "C"}))),
+            (added.clone(), Some(json!({"description": "v1"}))),
+            (before, None),
+        ];
+
+        let blame = blame_fields(&history);
+
+        let by_field: HashMap<&str, &FieldBlame> = blame.iter().map(|b| (b.field.as_str(), b)).collect();
+        // Setting a field back to an older value counts as a change
+        assert_eq!(by_field["description"].commit_id, reverted.id.0);
+        assert_eq!(by_field["units"].commit_id, changed.id.0);
+        assert!(blame_fields(&[(added, None)]).is_empty());
+    }
 }
@@ -0,0 +1,312 @@
+//! CSV to Parquet conversion for committed tabular data.
+//!
+//! Repos with the `parquet_conversion` feature get a Parquet copy of every
+//! committed CSV. Column types come from the same inference the sampler
+//! uses; if a later row contradicts them the file is written with every
+//! column as a string instead. The copy is stored as a derived artifact of
+//! the source entry once it reads back from S3 with as many rows as the CSV.
+
+use arrow::array::{ArrayRef, BooleanBuilder, Float64Builder, Int64Builder, StringBuilder};
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::ArrowWriter;
+use parquet::file::reader::{FileReader, SerializedFileReader};
+use std::sync::Arc;
+
+use crate::derived::{read_object, store_derived, DerivedArtifact, DerivedArtifactStore};
+use crate::jobs::{ConvertToParquetJob, JobError};
+
+/// Media type recorded on converted objects
+pub const PARQUET_MEDIA_TYPE: &str = "application/vnd.apache.parquet";
+
+/// `derived_artifact.kind` for Parquet copies
+pub const PARQUET_ARTIFACT_KIND: &str = "parquet";
+
+/// Larger sources are left unconverted, since conversion is done in memory
+pub const MAX_CONVERSION_BYTES: i64 = 256 * 1024 * 1024;
+
+/// A converted file and how its columns were typed
+#[derive(Debug, Clone)]
+pub struct ParquetConversion {
+    pub bytes: Vec<u8>,
+    pub columns: Vec<(String, DataType)>,
+    pub rows: usize,
+    /// Inferred types did not hold for every row, so all columns are strings
+    pub fell_back: bool,
+}
+
+fn arrow_type(column_type: &str) -> DataType {
+    match column_type {
+        "integer" => DataType::Int64,
+        "number" => DataType::Float64,
+        "boolean" => DataType::Boolean,
+        _ => DataType::Utf8,
+    }
+}
+
+/// Build one column. Empty values are null in typed columns; `None` means a
+/// value did not parse as `data_type`.
+fn build_column(records: &[csv::StringRecord], index: usize, data_type: &DataType) -> Option<ArrayRef> {
+    let values = records.iter().map(|record| record.get(index).unwrap_or_default());
+    let column: ArrayRef = match data_type {
+        DataType::Int64 => {
+            let mut builder = Int64Builder::with_capacity(records.len());
+            for value in values.map(str::trim) {
+                builder.append_option(if value.is_empty() { None } else { Some(value.parse().ok()?) });
+            }
+            Arc::new(builder.finish())
+        }
+        DataType::Float64 => {
+            let mut builder = Float64Builder::with_capacity(records.len());
+            for value in values.map(str::trim) {
+                builder.append_option(if value.is_empty() { None } else { Some(value.parse().ok()?) });
+            }
+            Arc::new(builder.finish())
+        }
+        DataType::Boolean => {
+            let mut builder = BooleanBuilder::with_capacity(records.len());
+            for value in values.map(str::trim) {
+                builder.append_option(match value.to_ascii_lowercase().as_str() {
+                    "" => None,
+                    "true" => Some(true),
+                    "false" => Some(false),
+                    _ => return None,
+                });
+            }
+            Arc::new(builder.finish())
+        }
+        _ => {
+            let mut builder = StringBuilder::new();
+            for value in values {
+                builder.append_value(value);
+            }
+            Arc::new(builder.finish())
+        }
+    };
+    Some(column)
+}
+
+fn write_parquet(headers: &csv::StringRecord, types: &[DataType], columns: Vec<ArrayRef>) -> Result<Vec<u8>, JobError> {
+    let fields: Vec<Field> = headers
+        .iter()
+        .zip(types)
+        .map(|(name, data_type)| Field::new(name, data_type.clone(), true))
+        .collect();
+    let schema = Arc::new(Schema::new(fields));
+    let batch = RecordBatch::try_new(schema.clone(), columns)
+        .map_err(|e| JobError::Processing(format!("Failed to build record batch: {}", e)))?;
+
+    let parquet_error = |e: parquet::errors::ParquetError| JobError::Processing(format!("Failed to write Parquet: {}", e));
+    let mut bytes = Vec::new();
+    let mut writer = ArrowWriter::try_new(&mut bytes, schema, None).map_err(parquet_error)?;
+    writer.write(&batch).map_err(parquet_error)?;
+    writer.close().map_err(parquet_error)?;
+    Ok(bytes)
+}
+
+/// Convert CSV bytes to a single-row-group Parquet file
+pub fn csv_to_parquet(data: &[u8]) -> Result<ParquetConversion, JobError> {
+    let (_, schema) = crate::jobs::sample_csv(data)
+        .map_err(|e| JobError::Processing(format!("Failed to read CSV: {}", e)))?;
+    let inferred: Vec<DataType> = schema["columns"]
+        .as_array()
+        .map(|columns| columns.iter().map(|c| arrow_type(c["type"].as_str().unwrap_or("string"))).collect())
+        .unwrap_or_default();
+
+    let mut reader = csv::Reader::from_reader(data);
+    let headers = reader.headers().map_err(|e| JobError::Processing(format!("Failed to read CSV: {}", e)))?.clone();
+    let records = reader
+        .records()
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| JobError::Processing(format!("Failed to read CSV: {}", e)))?;
+
+    let typed: Option<Vec<ArrayRef>> = inferred
+        .iter()
+        .enumerate()
+        .map(|(i, data_type)| build_column(&records, i, data_type))
+        .collect();
+    let (types, columns, fell_back) = match typed {
+        Some(columns) => (inferred, columns, false),
+        None => {
+            tracing::warn!("Inferred CSV column types did not hold for every row; writing all columns as strings");
+            let types = vec![DataType::Utf8; headers.len()];
+            let columns = (0..headers.len())
+                .map(|i| build_column(&records, i, &DataType::Utf8).expect("string columns always build"))
+                .collect();
+            (types, columns, true)
+        }
+    };
+
+    let bytes = write_parquet(&headers, &types, columns)?;
+    Ok(ParquetConversion {
+        bytes,
+        columns: headers.iter().map(str::to_string).zip(types).collect(),
+        rows: records.len(),
+        fell_back,
+    })
+}
+
+/// Rows in a Parquet file, as its footer records them
+pub fn parquet_row_count(bytes: &[u8]) -> Result<usize, String> {
+    let reader = SerializedFileReader::new(bytes::Bytes::copy_from_slice(bytes))
+        .map_err(|e| format!("not a readable Parquet file: {}", e))?;
+    Ok(reader.metadata().file_metadata().num_rows() as usize)
+}
+
+/// Convert a committed CSV entry and store the result next to it.
+///
+/// Returns the recorded link, or `None` when the source object is gone or
+/// larger than `MAX_CONVERSION_BYTES`.
+pub async fn convert_entry(
+    job: &ConvertToParquetJob,
+    s3_client: &aws_sdk_s3::Client,
+    bucket: &str,
+    store: &dyn DerivedArtifactStore,
+) -> Result<Option<DerivedArtifact>, JobError> {
+    let Some(source) = store.object(&job.sha256).await? else {
+        return Ok(None);
+    };
+    if source.size > MAX_CONVERSION_BYTES {
+        tracing::info!("Skipping Parquet conversion of {}: {} bytes exceeds limit", job.path, source.size);
+        return Ok(None);
+    }
+
+    let conversion = csv_to_parquet(&read_object(s3_client, bucket, &source.s3_key).await?)?;
+    let expected_rows = conversion.rows;
+    let artifact = store_derived(
+        s3_client,
+        bucket,
+        store,
+        job.commit_id,
+        &job.path,
+        &job.sha256,
+        PARQUET_ARTIFACT_KIND,
+        PARQUET_MEDIA_TYPE,
+        conversion.bytes,
+        |stored| match parquet_row_count(stored)? {
+            rows if rows == expected_rows => Ok(()),
+            rows => Err(format!("holds {} rows, the CSV has {}", rows, expected_rows)),
+        },
+    )
+    .await?;
+    Ok(Some(artifact))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::derived::content_key;
+    use crate::derived::tests::MemoryStore;
+    use crate::s3_mock::MockS3;
+    use arrow::array::{Array, BooleanArray, Float64Array, Int64Array, StringArray};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use uuid::Uuid;
+
+    fn read_back(bytes: Vec<u8>) -> RecordBatch {
+        let mut reader = ParquetRecordBatchReaderBuilder::try_new(axum::body::Bytes::from(bytes))
+            .unwrap()
+            .build()
+            .unwrap();
+        reader.next().unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_typed_columns_round_trip() {
+        let csv = b"station,day,temp,wet\nalpha,1,12.5,true\nbeta,2,,FALSE\ngamma,3,13,\n";
+        let conversion = csv_to_parquet(csv).unwrap();
+
+        assert!(!conversion.fell_back);
+        assert_eq!(conversion.rows, 3);
+        let types: Vec<DataType> = conversion.columns.iter().map(|(_, t)| t.clone()).collect();
+        assert_eq!(types, vec![DataType::Utf8, DataType::Int64, DataType::Float64, DataType::Boolean]);
+
+        let batch = read_back(conversion.bytes);
+        assert_eq!(batch.num_rows(), 3);
+        let station = batch.column(0).as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!((station.value(0), station.value(2)), ("alpha", "gamma"));
+        let day = batch.column(1).as_any().downcast_ref::<Int64Array>().unwrap();
+        assert_eq!(day.values().to_vec(), vec![1, 2, 3]);
+        let temp = batch.column(2).as_any().downcast_ref::<Float64Array>().unwrap();
+        assert_eq!(temp.value(0), 12.5);
+        assert!(temp.is_null(1));
+        assert_eq!(temp.value(2), 13.0);
+        let wet = batch.column(3).as_any().downcast_ref::<BooleanArray>().unwrap();
+        assert!(wet.value(0) && !wet.value(1) && wet.is_null(2));
+    }
+
+    #[test]
+    fn test_contradicted_inference_falls_back_to_strings() {
+        // Types are inferred from the sampled rows only; the last row is past the sample
+        let mut csv = String::from("id,code\n");
+        for i in 0..crate::jobs::MAX_SAMPLE_ROWS {
+            csv.push_str(&format!("{},{}\n", i, i * 10));
+        }
+        csv.push_str("late,n/a\n");
+
+        let conversion = csv_to_parquet(csv.as_bytes()).unwrap();
+
+        assert!(conversion.fell_back);
+        assert!(conversion.columns.iter().all(|(_, t)| *t == DataType::Utf8));
+        let batch = read_back(conversion.bytes);
+        let code = batch.column(1).as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(code.value(1), "10");
+        assert_eq!(code.value(code.len() - 1), "n/a");
+    }
+
+    #[tokio::test]
+    async fn test_convert_entry_stores_content_addressed_copy_and_links_it() {
+        let csv = b"day,temp\n1,12.5\n2,13.1\n";
+        let s3 = MockS3::default();
+        s3.put("blacklake/sha256/ab/cd/abcd", csv);
+        let store = MemoryStore::with_object("abcd", csv.len(), Some("text/csv"));
+        let job = ConvertToParquetJob { commit_id: Uuid::new_v4(), path: "data/temps.csv".to_string(), sha256: "abcd".to_string() };
+
+        let artifact = convert_entry(&job, &s3.client().await, "blacklake", &store).await.unwrap().unwrap();
+
+        assert_eq!(artifact.s3_key, content_key(&artifact.derived_sha256));
+        assert_eq!(artifact.source_sha256, "abcd");
+        assert_eq!(artifact.kind, PARQUET_ARTIFACT_KIND);
+        assert_eq!(store.recorded(), vec![artifact.clone()]);
+
+        let stored = s3.objects.lock().unwrap()[&format!("blacklake/{}", artifact.s3_key)].clone();
+        assert_eq!(crate::hash_bytes(&stored), artifact.derived_sha256);
+        let batch = read_back(stored);
+        let temp = batch.column(1).as_any().downcast_ref::<Float64Array>().unwrap();
+        assert_eq!(temp.values().to_vec(), vec![12.5, 13.1]);
+    }
+
+    #[tokio::test]
+    async fn test_truncated_upload_fails_conversion_without_linking() {
+        let csv = b"day,temp\n1,12.5\n2,13.1\n";
+        let s3 = MockS3::default();
+        s3.put("blacklake/sha256/ab/cd/abcd", csv);
+        s3.truncate_uploads(16);
+        let store = MemoryStore::with_object("abcd", csv.len(), Some("text/csv"));
+        let job = ConvertToParquetJob { commit_id: Uuid::new_v4(), path: "data/temps.csv".to_string(), sha256: "abcd".to_string() };
+
+        let err = convert_entry(&job, &s3.client().await, "blacklake", &store).await.unwrap_err();
+
+        assert!(err.to_string().contains("parquet of data/temps.csv failed verification"), "{}", err);
+        assert!(err.to_string().contains("read back 16 of"), "{}", err);
+        assert!(store.recorded().is_empty());
+    }
+
+    #[test]
+    fn test_row_count_comes_from_the_footer() {
+        let conversion = csv_to_parquet(b"day,temp\n1,12.5\n2,13.1\n3,9.0\n").unwrap();
+
+        assert_eq!(parquet_row_count(&conversion.bytes), Ok(3));
+        let half = &conversion.bytes[..conversion.bytes.len() / 2];
+        assert!(parquet_row_count(half).unwrap_err().contains("not a readable Parquet file"));
+    }
+
+    #[tokio::test]
+    async fn test_missing_source_is_skipped() {
+        let s3_client = MockS3::default().client().await;
+        let job = ConvertToParquetJob { commit_id: Uuid::new_v4(), path: "gone.csv".to_string(), sha256: "abcd".to_string() };
+
+        let store = MemoryStore::default();
+        assert_eq!(convert_entry(&job, &s3_client, "blacklake", &store).await.unwrap(), None);
+        assert!(store.recorded().is_empty());
+    }
+}
@@ -0,0 +1,330 @@
+//! Pluggable commit policy evaluation.
+//!
+//! Whether a commit may land is decided by a [`PolicyBackend`]. The built-in
+//! backend applies [`PolicyEngine::evaluate_branch_protection`]; the OPA
+//! backend posts the same context to an OPA decision endpoint so governance
+//! teams can write the policy in Rego. `POLICY_BACKEND` selects one.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::governance::{CheckResult, PolicyEngine, PolicyEvaluation, ProtectedRef};
+use crate::Change;
+
+const DEFAULT_OPA_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Error)]
+pub enum PolicyBackendError {
+    #[error("Invalid policy configuration: {0}")]
+    Config(String),
+    #[error("Policy service unavailable: {0}")]
+    Unavailable(String),
+}
+
+/// Everything a backend may base a commit decision on; sent to OPA as `input`
+#[derive(Debug, Clone, Serialize)]
+pub struct CommitPolicyInput {
+    pub repo_id: Uuid,
+    pub ref_name: String,
+    /// Head the commit is made on, which check results refer to
+    pub commit_id: Uuid,
+    pub user: String,
+    pub roles: Vec<String>,
+    pub is_admin: bool,
+    pub message: Option<String>,
+    pub changes: Vec<Change>,
+    /// Protection rules of the ref; `None` when it is unprotected
+    pub protected_ref: Option<ProtectedRef>,
+    pub check_results: Vec<CheckResult>,
+}
+
+#[async_trait]
+pub trait PolicyBackend: Send + Sync {
+    /// Decide whether the commit described by `input` may land
+    async fn evaluate_commit(&self, input: &CommitPolicyInput) -> Result<PolicyEvaluation, PolicyBackendError>;
+}
+
+/// Branch protection rules as stored in `protected_ref`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BuiltinPolicyBackend;
+
+impl BuiltinPolicyBackend {
+    fn evaluate(input: &CommitPolicyInput) -> PolicyEvaluation {
+        match &input.protected_ref {
+            Some(protected_ref) => PolicyEngine::evaluate_branch_protection(
+                protected_ref,
+                input.commit_id,
+                &input.user,
+                input.is_admin,
+                &input.check_results,
+            ),
+            None => PolicyEvaluation {
+                allowed: true,
+                reason: None,
+                required_checks: Vec::new(),
+                missing_reviewers: 0,
+            },
+        }
+    }
+}
+
+#[async_trait]
+impl PolicyBackend for BuiltinPolicyBackend {
+    async fn evaluate_commit(&self, input: &CommitPolicyInput) -> Result<PolicyEvaluation, PolicyBackendError> {
+        Ok(Self::evaluate(input))
+    }
+}
+
+/// What the OPA backend does when OPA cannot be reached or answers garbage
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureMode {
+    /// Decide with the built-in engine instead
+    Open,
+    /// Refuse the commit
+    Closed,
+}
+
+impl std::str::FromStr for FailureMode {
+    type Err = PolicyBackendError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "open" => Ok(FailureMode::Open),
+            "closed" => Ok(FailureMode::Closed),
+            other => Err(PolicyBackendError::Config(format!(
+                "OPA failure mode must be 'open' or 'closed', got '{}'",
+                other
+            ))),
+        }
+    }
+}
+
+/// Result of an OPA decision: a bare boolean, or an object with `allow` and
+/// optional `reasons`
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum OpaDecision {
+    Allow(bool),
+    Detailed {
+        allow: bool,
+        #[serde(default)]
+        reasons: Vec<String>,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+struct OpaResponse {
+    /// Absent when the policy is undefined for the input
+    result: Option<OpaDecision>,
+}
+
+/// Posts `{"input": CommitPolicyInput}` to an OPA decision URL such as
+/// `http://opa:8181/v1/data/blacklake/commit`
+pub struct OpaPolicyBackend {
+    client: reqwest::Client,
+    decision_url: String,
+    failure_mode: FailureMode,
+}
+
+impl OpaPolicyBackend {
+    pub fn new(decision_url: impl Into<String>, failure_mode: FailureMode, timeout: Duration) -> Result<Self, PolicyBackendError> {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .map_err(|e| PolicyBackendError::Config(e.to_string()))?;
+        Ok(Self { client, decision_url: decision_url.into(), failure_mode })
+    }
+
+    async fn query(&self, input: &CommitPolicyInput) -> Result<PolicyEvaluation, PolicyBackendError> {
+        let unavailable = |e: reqwest::Error| PolicyBackendError::Unavailable(e.to_string());
+        let response = self
+            .client
+            .post(&self.decision_url)
+            .json(&json!({ "input": input }))
+            .send()
+            .await
+            .map_err(unavailable)?
+            .error_for_status()
+            .map_err(unavailable)?;
+        let body: OpaResponse = response.json().await.map_err(unavailable)?;
+
+        let (allowed, reasons) = match body.result {
+            Some(OpaDecision::Allow(allow)) => (allow, Vec::new()),
+            Some(OpaDecision::Detailed { allow, reasons }) => (allow, reasons),
+            None => (false, vec!["Commit policy is undefined for this input".to_string()]),
+        };
+        let reason = match (allowed, reasons.is_empty()) {
+            (true, _) => None,
+            (false, true) => Some("Denied by commit policy".to_string()),
+            (false, false) => Some(reasons.join("; ")),
+        };
+
+        Ok(PolicyEvaluation { allowed, reason, required_checks: Vec::new(), missing_reviewers: 0 })
+    }
+}
+
+#[async_trait]
+impl PolicyBackend for OpaPolicyBackend {
+    async fn evaluate_commit(&self, input: &CommitPolicyInput) -> Result<PolicyEvaluation, PolicyBackendError> {
+        match self.query(input).await {
+            Ok(evaluation) => Ok(evaluation),
+            Err(e) => {
+                tracing::warn!("OPA policy evaluation failed: {}", e);
+                Ok(match self.failure_mode {
+                    FailureMode::Open => BuiltinPolicyBackend::evaluate(input),
+                    FailureMode::Closed => PolicyEvaluation {
+                        allowed: false,
+                        reason: Some(format!("Commit policy could not be evaluated: {}", e)),
+                        required_checks: Vec::new(),
+                        missing_reviewers: 0,
+                    },
+                })
+            }
+        }
+    }
+}
+
+/// The backend named by `POLICY_BACKEND`: `builtin` (the default) or `opa`.
+///
+/// The OPA backend reads its decision URL from `OPA_URL`, the failure mode
+/// from `OPA_FAILURE_MODE` (`open`, the default, or `closed`) and its request
+/// timeout from `OPA_TIMEOUT_SECONDS` (default 5).
+pub fn policy_backend_from_env() -> Result<Arc<dyn PolicyBackend>, PolicyBackendError> {
+    let backend = std::env::var("POLICY_BACKEND").unwrap_or_default();
+    match backend.trim().to_ascii_lowercase().as_str() {
+        "" | "builtin" => Ok(Arc::new(BuiltinPolicyBackend)),
+        "opa" => {
+            let url = std::env::var("OPA_URL")
+                .map_err(|_| PolicyBackendError::Config("OPA_URL must be set when POLICY_BACKEND=opa".to_string()))?;
+            let failure_mode = match std::env::var("OPA_FAILURE_MODE") {
+                Ok(mode) => mode.parse()?,
+                Err(_) => FailureMode::Open,
+            };
+            let timeout = match std::env::var("OPA_TIMEOUT_SECONDS") {
+                Ok(seconds) => Duration::from_secs(seconds.trim().parse().map_err(|_| {
+                    PolicyBackendError::Config(format!("Invalid OPA_TIMEOUT_SECONDS: {}", seconds))
+                })?),
+                Err(_) => DEFAULT_OPA_TIMEOUT,
+            };
+            Ok(Arc::new(OpaPolicyBackend::new(url, failure_mode, timeout)?))
+        }
+        other => Err(PolicyBackendError::Config(format!(
+            "POLICY_BACKEND must be 'builtin' or 'opa', got '{}'",
+            other
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::post, Json, Router};
+    use serde_json::Value;
+    use std::sync::Mutex;
+
+    fn input(protected_ref: Option<ProtectedRef>) -> CommitPolicyInput {
+        CommitPolicyInput {
+            repo_id: Uuid::new_v4(),
+            ref_name: "main".to_string(),
+            commit_id: Uuid::new_v4(),
+            user: "alice".to_string(),
+            roles: vec!["analyst".to_string()],
+            is_admin: false,
+            message: Some("add results".to_string()),
+            changes: vec![],
+            protected_ref,
+            check_results: vec![],
+        }
+    }
+
+    fn admin_only() -> ProtectedRef {
+        ProtectedRef {
+            id: Uuid::new_v4(),
+            repo_id: Uuid::new_v4(),
+            ref_name: "main".to_string(),
+            require_admin: true,
+            allow_fast_forward: true,
+            allow_delete: false,
+            required_checks: vec![],
+            required_reviewers: 0,
+            require_schema_pass: false,
+        }
+    }
+
+    /// Serve `decision` for every query and return the decision URL plus the inputs received
+    async fn stub_opa(decision: Value) -> (String, Arc<Mutex<Vec<Value>>>) {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let seen = received.clone();
+        let app = Router::new().route(
+            "/v1/data/blacklake/commit",
+            post(move |Json(body): Json<Value>| {
+                let decision = decision.clone();
+                seen.lock().unwrap().push(body);
+                async move { Json(decision) }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/v1/data/blacklake/commit", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, received)
+    }
+
+    /// A URL nothing listens on
+    async fn unreachable_url() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        format!("http://{}/v1/data/blacklake/commit", listener.local_addr().unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_opa_deny_carries_reasons() {
+        let (url, received) = stub_opa(json!({
+            "result": {"allow": false, "reasons": ["models need review", "main is frozen"]}
+        }))
+        .await;
+        let backend = OpaPolicyBackend::new(url, FailureMode::Open, DEFAULT_OPA_TIMEOUT).unwrap();
+
+        let evaluation = backend.evaluate_commit(&input(None)).await.unwrap();
+
+        assert!(!evaluation.allowed);
+        assert_eq!(evaluation.reason.as_deref(), Some("models need review; main is frozen"));
+        let received = received.lock().unwrap();
+        assert_eq!(received[0]["input"]["user"], "alice");
+        assert_eq!(received[0]["input"]["ref_name"], "main");
+    }
+
+    #[tokio::test]
+    async fn test_opa_boolean_and_undefined_results() {
+        let (url, _) = stub_opa(json!({"result": true})).await;
+        let backend = OpaPolicyBackend::new(url, FailureMode::Closed, DEFAULT_OPA_TIMEOUT).unwrap();
+        assert!(backend.evaluate_commit(&input(Some(admin_only()))).await.unwrap().allowed);
+
+        let (url, _) = stub_opa(json!({})).await;
+        let backend = OpaPolicyBackend::new(url, FailureMode::Open, DEFAULT_OPA_TIMEOUT).unwrap();
+        assert!(!backend.evaluate_commit(&input(None)).await.unwrap().allowed);
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_opa_fails_open_to_builtin_rules() {
+        let backend = OpaPolicyBackend::new(unreachable_url().await, FailureMode::Open, DEFAULT_OPA_TIMEOUT).unwrap();
+
+        assert!(backend.evaluate_commit(&input(None)).await.unwrap().allowed);
+        let evaluation = backend.evaluate_commit(&input(Some(admin_only()))).await.unwrap();
+        assert!(!evaluation.allowed);
+        assert_eq!(evaluation.reason.as_deref(), Some("Admin access required"));
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_opa_fails_closed() {
+        let backend = OpaPolicyBackend::new(unreachable_url().await, FailureMode::Closed, DEFAULT_OPA_TIMEOUT).unwrap();
+
+        let evaluation = backend.evaluate_commit(&input(None)).await.unwrap();
+
+        assert!(!evaluation.allowed);
+        assert!(evaluation.reason.unwrap().starts_with("Commit policy could not be evaluated"));
+    }
+}
@@ -0,0 +1,734 @@
+//! Bulk search reindexing.
+//!
+//! A reindex run rebuilds the Solr documents for the live entries of one repo
+//! (or every repo), optionally only those committed after a given commit.
+//! Entries are walked in `(commit_id, path)` order and sent to the search
+//! index a page at a time; the cursor and `indexed_count` are saved after
+//! each page so progress can be polled while the run is going and a re-run
+//! with the same id resumes where it stopped.
+
+use sqlx::{PgPool, Row};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use uuid::Uuid;
+
+use crate::jobs::{IndexEntryJob, IndexOperation, JobError, JobMetadata, JobStatus};
+use crate::search::{SolrClient, SolrDocument, SolrError};
+
+/// Which entries a run reindexes
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ReindexScope {
+    /// None reindexes every repo
+    pub repo_id: Option<Uuid>,
+    /// Only entries from commits created after this one
+    pub since_commit_id: Option<Uuid>,
+}
+
+/// A live entry and the repo and ref it is indexed under
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReindexEntry {
+    pub repo_id: Uuid,
+    pub repo_name: String,
+    /// Branch whose head is the entry's commit; `main` for older commits
+    pub ref_name: String,
+    pub commit_id: Uuid,
+    pub path: String,
+    pub object_sha256: String,
+    pub meta: serde_json::Value,
+}
+
+impl ReindexEntry {
+    pub fn cursor(&self) -> ReindexCursor {
+        ReindexCursor {
+            commit_id: self.commit_id,
+            path: self.path.clone(),
+        }
+    }
+
+    /// The document commit-time indexing would have written for this entry
+    pub fn solr_document(&self) -> SolrDocument {
+        IndexEntryJob {
+            repo_id: self.repo_id,
+            repo_name: self.repo_name.clone(),
+            ref_name: self.ref_name.clone(),
+            path: self.path.clone(),
+            commit_id: self.commit_id,
+            object_sha256: self.object_sha256.clone(),
+            metadata: self.meta.clone(),
+            operation: IndexOperation::Index,
+        }
+        .solr_document()
+    }
+}
+
+/// Position after the last entry of the last completed page
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ReindexCursor {
+    pub commit_id: Uuid,
+    pub path: String,
+}
+
+/// How far a run has got
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReindexProgress {
+    pub cursor: Option<ReindexCursor>,
+    /// Entries in scope when the run (or its latest attempt) started
+    pub total: i64,
+    pub indexed_count: i64,
+}
+
+/// A reindex run as stored
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReindexRun {
+    pub id: Uuid,
+    pub scope: ReindexScope,
+    pub status: JobStatus,
+    pub progress: ReindexProgress,
+    pub error_message: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub started_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub completed_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl ReindexRun {
+    /// Status in the shape every other job reports
+    pub fn job_metadata(&self) -> JobMetadata {
+        let progress = match (&self.status, self.progress.total) {
+            (JobStatus::Completed, _) => 1.0,
+            (_, 0) => 0.0,
+            (_, total) => (self.progress.indexed_count as f64 / total as f64).min(1.0),
+        };
+
+        JobMetadata {
+            id: self.id,
+            job_type: "full_reindex".to_string(),
+            created_at: self.created_at,
+            started_at: self.started_at,
+            completed_at: self.completed_at,
+            attempts: u32::from(self.started_at.is_some()),
+            max_attempts: 1,
+            status: self.status.clone(),
+            error_message: self.error_message.clone(),
+            progress,
+            metadata: serde_json::json!({
+                "repo_id": self.scope.repo_id,
+                "since_commit_id": self.scope.since_commit_id,
+                "indexed_count": self.progress.indexed_count,
+                "total": self.progress.total,
+            }),
+        }
+    }
+}
+
+/// Persistence for reindex runs
+#[async_trait::async_trait]
+pub trait ReindexStore: Send + Sync {
+    /// Record a run that has been queued but not started
+    async fn create_run(&self, run_id: Uuid, scope: &ReindexScope) -> Result<(), JobError>;
+
+    /// Mark the run running, creating it if needed, and return its progress
+    async fn start_run(&self, run_id: Uuid, scope: &ReindexScope) -> Result<ReindexProgress, JobError>;
+
+    /// Live entries in scope that sort after `after`
+    async fn count_entries(&self, scope: &ReindexScope, after: Option<&ReindexCursor>) -> Result<i64, JobError>;
+
+    /// Up to `limit` live entries in scope that sort after `after`, in cursor order
+    async fn entries(
+        &self,
+        scope: &ReindexScope,
+        after: Option<&ReindexCursor>,
+        limit: i64,
+    ) -> Result<Vec<ReindexEntry>, JobError>;
+
+    async fn save_progress(&self, run_id: Uuid, progress: &ReindexProgress) -> Result<(), JobError>;
+
+    async fn complete_run(&self, run_id: Uuid, progress: &ReindexProgress) -> Result<(), JobError>;
+
+    async fn fail_run(&self, run_id: Uuid, error: &str) -> Result<(), JobError>;
+
+    async fn get_run(&self, run_id: Uuid) -> Result<Option<ReindexRun>, JobError>;
+}
+
+/// Where reindexed documents go
+#[async_trait::async_trait]
+pub trait SearchIndexer: Send + Sync {
+    /// Index documents that all belong to `repo_id`
+    async fn index_documents(&self, repo_id: Uuid, docs: &[SolrDocument]) -> Result<(), JobError>;
+
+    /// Make everything indexed so far for `repo_ids` visible to searches
+    async fn commit(&self, repo_ids: &[Uuid]) -> Result<(), JobError>;
+}
+
+fn solr_error(e: SolrError) -> JobError {
+    JobError::Processing(format!("Solr request failed: {}", e))
+}
+
+#[async_trait::async_trait]
+impl SearchIndexer for SolrClient {
+    async fn index_documents(&self, repo_id: Uuid, docs: &[SolrDocument]) -> Result<(), JobError> {
+        let solr = self.ensure_repo_collection(repo_id).await.map_err(solr_error)?;
+        solr.index_documents(docs).await.map_err(solr_error)
+    }
+
+    /// Commits each collection once, however many of the repos share it
+    async fn commit(&self, repo_ids: &[Uuid]) -> Result<(), JobError> {
+        let mut committed = HashSet::new();
+        for &repo_id in repo_ids {
+            let solr = self.for_repo(repo_id);
+            if committed.insert(solr.collection().to_string()) {
+                solr.commit().await.map_err(solr_error)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Run (or resume) reindex `run_id`, marking it completed or failed
+pub async fn run_reindex(
+    run_id: Uuid,
+    scope: &ReindexScope,
+    store: &dyn ReindexStore,
+    indexer: &dyn SearchIndexer,
+    batch_size: i64,
+) -> Result<ReindexProgress, JobError> {
+    match reindex_pages(run_id, scope, store, indexer, batch_size).await {
+        Ok(progress) => {
+            store.complete_run(run_id, &progress).await?;
+            Ok(progress)
+        }
+        Err(e) => {
+            store.fail_run(run_id, &e.to_string()).await?;
+            Err(e)
+        }
+    }
+}
+
+async fn reindex_pages(
+    run_id: Uuid,
+    scope: &ReindexScope,
+    store: &dyn ReindexStore,
+    indexer: &dyn SearchIndexer,
+    batch_size: i64,
+) -> Result<ReindexProgress, JobError> {
+    let mut progress = store.start_run(run_id, scope).await?;
+    // Entries an earlier attempt already indexed still count towards the total
+    progress.total = progress.indexed_count + store.count_entries(scope, progress.cursor.as_ref()).await?;
+    store.save_progress(run_id, &progress).await?;
+
+    let mut repos = BTreeSet::new();
+    loop {
+        let page = store.entries(scope, progress.cursor.as_ref(), batch_size.max(1)).await?;
+        let Some(last) = page.last() else {
+            break;
+        };
+
+        // A page can span repos, and each repo may have its own collection
+        let mut by_repo: BTreeMap<Uuid, Vec<SolrDocument>> = BTreeMap::new();
+        for entry in &page {
+            by_repo.entry(entry.repo_id).or_default().push(entry.solr_document());
+        }
+        for (repo_id, docs) in by_repo {
+            indexer.index_documents(repo_id, &docs).await?;
+            repos.insert(repo_id);
+        }
+
+        progress.indexed_count += page.len() as i64;
+        progress.cursor = Some(last.cursor());
+        store.save_progress(run_id, &progress).await?;
+    }
+
+    indexer.commit(&repos.into_iter().collect::<Vec<_>>()).await?;
+    Ok(progress)
+}
+
+/// Reindex store backed by `reindex_run` and the `entry` table
+pub struct PgReindexStore {
+    pool: PgPool,
+}
+
+impl PgReindexStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+fn db_error(e: sqlx::Error) -> JobError {
+    JobError::Storage(format!("Reindex query failed: {}", e))
+}
+
+fn status_from_str(status: &str) -> JobStatus {
+    match status {
+        "pending" => JobStatus::Pending,
+        "running" => JobStatus::Running,
+        "completed" => JobStatus::Completed,
+        "failed" => JobStatus::Failed,
+        _ => JobStatus::Unknown,
+    }
+}
+
+fn cursor_from_row(row: &sqlx::postgres::PgRow) -> Option<ReindexCursor> {
+    match (row.get::<Option<Uuid>, _>("cursor_commit_id"), row.get::<Option<String>, _>("cursor_path")) {
+        (Some(commit_id), Some(path)) => Some(ReindexCursor { commit_id, path }),
+        _ => None,
+    }
+}
+
+/// Live file entries in scope; `$1` repo, `$2` since commit, `$3`/`$4` cursor
+const REINDEX_ENTRIES_WHERE: &str = "
+     FROM entry e
+     JOIN commit c ON c.id = e.commit_id
+     JOIN repo r ON r.id = c.repo_id
+     WHERE NOT e.is_dir AND e.deleted_at IS NULL AND e.tombstoned_at IS NULL
+       AND ($1::uuid IS NULL OR c.repo_id = $1)
+       AND ($2::uuid IS NULL OR c.created_at > (SELECT created_at FROM commit WHERE id = $2))
+       AND ($3::uuid IS NULL OR (e.commit_id, e.path) > ($3, $4::text))";
+
+#[async_trait::async_trait]
+impl ReindexStore for PgReindexStore {
+    async fn create_run(&self, run_id: Uuid, scope: &ReindexScope) -> Result<(), JobError> {
+        sqlx::query("INSERT INTO reindex_run (id, repo_id, since_commit_id) VALUES ($1, $2, $3)")
+            .bind(run_id)
+            .bind(scope.repo_id)
+            .bind(scope.since_commit_id)
+            .execute(&self.pool)
+            .await
+            .map_err(db_error)?;
+        Ok(())
+    }
+
+    async fn start_run(&self, run_id: Uuid, scope: &ReindexScope) -> Result<ReindexProgress, JobError> {
+        let row = sqlx::query(
+            "INSERT INTO reindex_run (id, repo_id, since_commit_id, status, started_at)
+             VALUES ($1, $2, $3, 'running', NOW())
+             ON CONFLICT (id) DO UPDATE SET status = 'running', error_message = NULL, completed_at = NULL,
+                 started_at = COALESCE(reindex_run.started_at, NOW()), updated_at = NOW()
+             RETURNING cursor_commit_id, cursor_path, total, indexed_count",
+        )
+        .bind(run_id)
+        .bind(scope.repo_id)
+        .bind(scope.since_commit_id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(db_error)?;
+
+        Ok(ReindexProgress {
+            cursor: cursor_from_row(&row),
+            total: row.get("total"),
+            indexed_count: row.get("indexed_count"),
+        })
+    }
+
+    async fn count_entries(&self, scope: &ReindexScope, after: Option<&ReindexCursor>) -> Result<i64, JobError> {
+        sqlx::query_scalar(&format!("SELECT COUNT(*){}", REINDEX_ENTRIES_WHERE))
+            .bind(scope.repo_id)
+            .bind(scope.since_commit_id)
+            .bind(after.map(|cursor| cursor.commit_id))
+            .bind(after.map(|cursor| cursor.path.as_str()))
+            .fetch_one(&self.pool)
+            .await
+            .map_err(db_error)
+    }
+
+    async fn entries(
+        &self,
+        scope: &ReindexScope,
+        after: Option<&ReindexCursor>,
+        limit: i64,
+    ) -> Result<Vec<ReindexEntry>, JobError> {
+        let rows = sqlx::query(&format!(
+            "SELECT e.commit_id, e.path, e.object_sha256, e.meta, c.repo_id, r.name AS repo_name,
+                    COALESCE((SELECT name FROM ref
+                              WHERE ref.repo_id = c.repo_id AND ref.commit_id = c.id AND ref.kind = 'branch'
+                              ORDER BY name LIMIT 1), 'main') AS ref_name
+             {}
+             ORDER BY e.commit_id, e.path
+             LIMIT $5",
+            REINDEX_ENTRIES_WHERE
+        ))
+        .bind(scope.repo_id)
+        .bind(scope.since_commit_id)
+        .bind(after.map(|cursor| cursor.commit_id))
+        .bind(after.map(|cursor| cursor.path.as_str()))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(db_error)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| ReindexEntry {
+                repo_id: row.get("repo_id"),
+                repo_name: row.get("repo_name"),
+                ref_name: row.get("ref_name"),
+                commit_id: row.get("commit_id"),
+                path: row.get("path"),
+                object_sha256: row.get::<Option<String>, _>("object_sha256").unwrap_or_default(),
+                meta: row.get("meta"),
+            })
+            .collect())
+    }
+
+    async fn save_progress(&self, run_id: Uuid, progress: &ReindexProgress) -> Result<(), JobError> {
+        sqlx::query(
+            "UPDATE reindex_run SET cursor_commit_id = $2, cursor_path = $3, total = $4, indexed_count = $5,
+                 updated_at = NOW()
+             WHERE id = $1",
+        )
+        .bind(run_id)
+        .bind(progress.cursor.as_ref().map(|cursor| cursor.commit_id))
+        .bind(progress.cursor.as_ref().map(|cursor| cursor.path.as_str()))
+        .bind(progress.total)
+        .bind(progress.indexed_count)
+        .execute(&self.pool)
+        .await
+        .map_err(db_error)?;
+        Ok(())
+    }
+
+    async fn complete_run(&self, run_id: Uuid, progress: &ReindexProgress) -> Result<(), JobError> {
+        sqlx::query(
+            "UPDATE reindex_run SET status = 'completed', total = $2, indexed_count = $3,
+                 updated_at = NOW(), completed_at = NOW()
+             WHERE id = $1",
+        )
+        .bind(run_id)
+        .bind(progress.total)
+        .bind(progress.indexed_count)
+        .execute(&self.pool)
+        .await
+        .map_err(db_error)?;
+        Ok(())
+    }
+
+    async fn fail_run(&self, run_id: Uuid, error: &str) -> Result<(), JobError> {
+        sqlx::query(
+            "UPDATE reindex_run SET status = 'failed', error_message = $2, updated_at = NOW(), completed_at = NOW()
+             WHERE id = $1",
+        )
+        .bind(run_id)
+        .bind(error)
+        .execute(&self.pool)
+        .await
+        .map_err(db_error)?;
+        Ok(())
+    }
+
+    async fn get_run(&self, run_id: Uuid) -> Result<Option<ReindexRun>, JobError> {
+        let row = sqlx::query(
+            "SELECT id, repo_id, since_commit_id, status, cursor_commit_id, cursor_path, total, indexed_count,
+                    error_message, created_at, started_at, completed_at
+             FROM reindex_run WHERE id = $1",
+        )
+        .bind(run_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(db_error)?;
+
+        Ok(row.map(|row| {
+            ReindexRun {
+                id: row.get("id"),
+                scope: ReindexScope {
+                    repo_id: row.get("repo_id"),
+                    since_commit_id: row.get("since_commit_id"),
+                },
+                status: status_from_str(row.get("status")),
+                progress: ReindexProgress {
+                    cursor: cursor_from_row(&row),
+                    total: row.get("total"),
+                    indexed_count: row.get("indexed_count"),
+                },
+                error_message: row.get("error_message"),
+                created_at: row.get("created_at"),
+                started_at: row.get("started_at"),
+                completed_at: row.get("completed_at"),
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    /// Store over a fixed set of entries; `commits` lists commit ids oldest first
+    #[derive(Default)]
+    struct MemoryStore {
+        entries: Vec<ReindexEntry>,
+        commits: Vec<Uuid>,
+        runs: Mutex<Vec<ReindexRun>>,
+    }
+
+    impl MemoryStore {
+        fn new(commits: Vec<Uuid>, mut entries: Vec<ReindexEntry>) -> Self {
+            entries.sort_by_key(ReindexEntry::cursor);
+            Self { entries, commits, ..Default::default() }
+        }
+
+        fn in_scope<'a>(
+            &'a self,
+            scope: &'a ReindexScope,
+            after: Option<&'a ReindexCursor>,
+        ) -> impl Iterator<Item = &'a ReindexEntry> + 'a {
+            let position = |commit_id: Uuid| self.commits.iter().position(|c| *c == commit_id);
+            self.entries.iter().filter(move |entry| {
+                scope.repo_id.is_none_or(|repo_id| entry.repo_id == repo_id)
+                    && scope
+                        .since_commit_id
+                        .is_none_or(|since| position(entry.commit_id) > position(since))
+                    && after.is_none_or(|after| &entry.cursor() > after)
+            })
+        }
+
+        fn update(&self, run_id: Uuid, apply: impl FnOnce(&mut ReindexRun)) {
+            let mut runs = self.runs.lock().unwrap();
+            apply(runs.iter_mut().find(|run| run.id == run_id).expect("run exists"));
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl ReindexStore for MemoryStore {
+        async fn create_run(&self, run_id: Uuid, scope: &ReindexScope) -> Result<(), JobError> {
+            self.runs.lock().unwrap().push(ReindexRun {
+                id: run_id,
+                scope: *scope,
+                status: JobStatus::Pending,
+                progress: ReindexProgress::default(),
+                error_message: None,
+                created_at: chrono::Utc::now(),
+                started_at: None,
+                completed_at: None,
+            });
+            Ok(())
+        }
+
+        async fn start_run(&self, run_id: Uuid, scope: &ReindexScope) -> Result<ReindexProgress, JobError> {
+            if self.get_run(run_id).await?.is_none() {
+                self.create_run(run_id, scope).await?;
+            }
+            let mut progress = ReindexProgress::default();
+            self.update(run_id, |run| {
+                run.status = JobStatus::Running;
+                run.error_message = None;
+                run.started_at.get_or_insert_with(chrono::Utc::now);
+                progress = run.progress.clone();
+            });
+            Ok(progress)
+        }
+
+        async fn count_entries(&self, scope: &ReindexScope, after: Option<&ReindexCursor>) -> Result<i64, JobError> {
+            Ok(self.in_scope(scope, after).count() as i64)
+        }
+
+        async fn entries(
+            &self,
+            scope: &ReindexScope,
+            after: Option<&ReindexCursor>,
+            limit: i64,
+        ) -> Result<Vec<ReindexEntry>, JobError> {
+            Ok(self.in_scope(scope, after).take(limit as usize).cloned().collect())
+        }
+
+        async fn save_progress(&self, run_id: Uuid, progress: &ReindexProgress) -> Result<(), JobError> {
+            self.update(run_id, |run| run.progress = progress.clone());
+            Ok(())
+        }
+
+        async fn complete_run(&self, run_id: Uuid, progress: &ReindexProgress) -> Result<(), JobError> {
+            self.update(run_id, |run| {
+                run.status = JobStatus::Completed;
+                run.progress = progress.clone();
+                run.completed_at = Some(chrono::Utc::now());
+            });
+            Ok(())
+        }
+
+        async fn fail_run(&self, run_id: Uuid, error: &str) -> Result<(), JobError> {
+            self.update(run_id, |run| {
+                run.status = JobStatus::Failed;
+                run.error_message = Some(error.to_string());
+            });
+            Ok(())
+        }
+
+        async fn get_run(&self, run_id: Uuid) -> Result<Option<ReindexRun>, JobError> {
+            Ok(self.runs.lock().unwrap().iter().find(|run| run.id == run_id).cloned())
+        }
+    }
+
+    /// Indexer that keeps what it is sent, optionally failing from a given batch on
+    #[derive(Default)]
+    struct RecordingIndexer {
+        docs: Mutex<Vec<SolrDocument>>,
+        /// Repo each batch was sent for
+        batch_repos: Mutex<Vec<Uuid>>,
+        fail_from_batch: Option<usize>,
+        batches: Mutex<usize>,
+        committed: Mutex<Option<Vec<Uuid>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl SearchIndexer for RecordingIndexer {
+        async fn index_documents(&self, repo_id: Uuid, docs: &[SolrDocument]) -> Result<(), JobError> {
+            let batch = {
+                let mut batches = self.batches.lock().unwrap();
+                *batches += 1;
+                *batches
+            };
+            if self.fail_from_batch.is_some_and(|from| batch >= from) {
+                return Err(JobError::Processing("Solr request failed: unavailable".to_string()));
+            }
+            // Give pollers a chance to see the run in progress
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            self.docs.lock().unwrap().extend_from_slice(docs);
+            self.batch_repos.lock().unwrap().push(repo_id);
+            Ok(())
+        }
+
+        async fn commit(&self, repo_ids: &[Uuid]) -> Result<(), JobError> {
+            *self.committed.lock().unwrap() = Some(repo_ids.to_vec());
+            Ok(())
+        }
+    }
+
+    fn entry(repo_id: Uuid, repo_name: &str, commit_id: Uuid, path: &str) -> ReindexEntry {
+        ReindexEntry {
+            repo_id,
+            repo_name: repo_name.to_string(),
+            ref_name: "main".to_string(),
+            commit_id,
+            path: path.to_string(),
+            object_sha256: format!("{:0>64}", path.len()),
+            meta: serde_json::json!({"file_type": "csv", "file_size": 42}),
+        }
+    }
+
+    /// Two commits in `climate` holding five files, and one in `ocean` with two
+    fn seeded() -> (MemoryStore, Uuid, Vec<Uuid>) {
+        let climate = Uuid::new_v4();
+        let ocean = Uuid::new_v4();
+        let commits: Vec<Uuid> = (0..3).map(|_| Uuid::new_v4()).collect();
+        let entries = vec![
+            entry(climate, "climate", commits[0], "data/a.csv"),
+            entry(climate, "climate", commits[0], "data/b.csv"),
+            entry(climate, "climate", commits[0], "data/c.csv"),
+            entry(ocean, "ocean", commits[1], "data/tides.csv"),
+            entry(ocean, "ocean", commits[1], "data/waves.csv"),
+            entry(climate, "climate", commits[2], "data/d.csv"),
+            entry(climate, "climate", commits[2], "data/e.csv"),
+        ];
+        (MemoryStore::new(commits.clone(), entries), climate, commits)
+    }
+
+    #[tokio::test]
+    async fn test_enqueued_reindex_reports_completion_with_indexed_count() {
+        let (store, climate, _) = seeded();
+        let store = Arc::new(store);
+        let indexer = Arc::new(RecordingIndexer::default());
+        let run_id = Uuid::new_v4();
+        let scope = ReindexScope { repo_id: Some(climate), since_commit_id: None };
+
+        store.create_run(run_id, &scope).await.unwrap();
+        let queued = store.get_run(run_id).await.unwrap().unwrap().job_metadata();
+        assert_eq!(queued.status, JobStatus::Pending);
+
+        let job = tokio::spawn({
+            let (store, indexer) = (store.clone(), indexer.clone());
+            async move { run_reindex(run_id, &scope, store.as_ref(), indexer.as_ref(), 2).await }
+        });
+
+        let status = loop {
+            let status = store.get_run(run_id).await.unwrap().unwrap().job_metadata();
+            if status.status == JobStatus::Completed {
+                break status;
+            }
+            assert!(matches!(status.status, JobStatus::Pending | JobStatus::Running), "{:?}", status);
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        };
+        job.await.unwrap().unwrap();
+
+        assert_eq!(status.metadata["indexed_count"], 5);
+        assert_eq!(status.metadata["total"], 5);
+        assert_eq!(status.progress, 1.0);
+        assert!(status.completed_at.is_some());
+
+        let docs = indexer.docs.lock().unwrap();
+        assert_eq!(docs.len(), 5);
+        assert!(docs.iter().all(|doc| doc.repo == "climate"));
+        assert_eq!(*indexer.committed.lock().unwrap(), Some(vec![climate]));
+    }
+
+    #[tokio::test]
+    async fn test_since_commit_limits_reindex_to_later_commits() {
+        let (store, climate, commits) = seeded();
+        let indexer = RecordingIndexer::default();
+        let scope = ReindexScope { repo_id: Some(climate), since_commit_id: Some(commits[0]) };
+
+        let progress = run_reindex(Uuid::new_v4(), &scope, &store, &indexer, 10).await.unwrap();
+
+        assert_eq!(progress.indexed_count, 2);
+        let paths: Vec<String> = indexer.docs.lock().unwrap().iter().map(|doc| doc.path.clone()).collect();
+        assert_eq!(paths.len(), 2);
+        assert!(paths.contains(&"data/d.csv".to_string()) && paths.contains(&"data/e.csv".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_documents_match_commit_time_indexing() {
+        let (store, _, _) = seeded();
+        let indexer = RecordingIndexer::default();
+
+        run_reindex(Uuid::new_v4(), &ReindexScope::default(), &store, &indexer, 3).await.unwrap();
+
+        let docs = indexer.docs.lock().unwrap();
+        assert_eq!(docs.len(), 7);
+        let tides = store.entries.iter().find(|entry| entry.path == "data/tides.csv").unwrap();
+        let doc = docs.iter().find(|doc| doc.path == "data/tides.csv").unwrap();
+        assert_eq!(doc.id, format!("ocean:main:data/tides.csv:{}", tides.commit_id));
+        assert_eq!(doc.file_type, "csv");
+        assert_eq!(doc.file_size, 42);
+    }
+
+    #[tokio::test]
+    async fn test_pages_spanning_repos_are_indexed_per_repo() {
+        let (store, climate, _) = seeded();
+        let ocean = store.entries.iter().find(|entry| entry.repo_name == "ocean").unwrap().repo_id;
+        let indexer = RecordingIndexer::default();
+
+        run_reindex(Uuid::new_v4(), &ReindexScope::default(), &store, &indexer, 10).await.unwrap();
+
+        // One page holds every entry and is sent as one batch per repo
+        let mut batch_repos = indexer.batch_repos.lock().unwrap().clone();
+        batch_repos.sort();
+        let mut expected = vec![climate, ocean];
+        expected.sort();
+        assert_eq!(batch_repos, expected);
+        let docs = indexer.docs.lock().unwrap();
+        let ocean_docs: Vec<&SolrDocument> = docs.iter().filter(|doc| doc.repo == "ocean").collect();
+        assert_eq!(ocean_docs.len(), 2);
+        assert_eq!(*indexer.committed.lock().unwrap(), Some(expected));
+    }
+
+    #[tokio::test]
+    async fn test_failed_run_records_error_and_resumes_from_cursor() {
+        let (store, climate, _) = seeded();
+        let run_id = Uuid::new_v4();
+        let scope = ReindexScope { repo_id: Some(climate), since_commit_id: None };
+        let flaky = RecordingIndexer { fail_from_batch: Some(3), ..Default::default() };
+
+        let err = run_reindex(run_id, &scope, &store, &flaky, 2).await.unwrap_err();
+
+        let failed = store.get_run(run_id).await.unwrap().unwrap();
+        assert_eq!(failed.status, JobStatus::Failed);
+        assert_eq!(failed.error_message, Some(err.to_string()));
+        assert_eq!(failed.progress.indexed_count, 4);
+        assert_eq!(*flaky.committed.lock().unwrap(), None);
+
+        let indexer = RecordingIndexer::default();
+        let progress = run_reindex(run_id, &scope, &store, &indexer, 2).await.unwrap();
+
+        assert_eq!(progress.indexed_count, 5);
+        assert_eq!(progress.total, 5);
+        assert_eq!(indexer.docs.lock().unwrap().len(), 1);
+        assert_eq!(store.get_run(run_id).await.unwrap().unwrap().status, JobStatus::Completed);
+    }
+}
@@ -0,0 +1,196 @@
+//! Repository permissions granted by identity-provider roles.
+//!
+//! A JWT's `roles` claim is matched against a list of grants so access can be
+//! managed in the IdP instead of with per-user ACL rows. Each grant is written
+//! `role=permission@repos`:
+//!
+//! - `role` may contain `*` wildcards; the text each one matches is captured
+//! - `permission` is `read`, `write` or `admin`
+//! - `repos` is a repository name pattern, where `*` matches any name and
+//!   `$1`, `$2`, ... are replaced by the role's captures
+//!
+//! So `data-admin=admin@*` makes `data-admin` an admin everywhere, and
+//! `repo:*:write=write@$1` turns `repo:foo:write` into Write on `foo`.
+
+use crate::Permission;
+
+/// Grants used when `OIDC_ROLE_PERMISSIONS` is unset
+pub const DEFAULT_ROLE_PERMISSIONS: &str = "repo:*:read=read@$1,repo:*:write=write@$1,repo:*:admin=admin@$1";
+
+/// One `role=permission@repos` rule
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoleGrant {
+    pub role: String,
+    pub permission: Permission,
+    pub repos: String,
+}
+
+impl std::str::FromStr for RoleGrant {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (role, target) = s
+            .split_once('=')
+            .ok_or_else(|| format!("Role grant '{}' must look like role=permission@repos", s))?;
+        let (permission, repos) = target
+            .split_once('@')
+            .ok_or_else(|| format!("Role grant '{}' is missing '@repos'", s))?;
+
+        let role = role.trim();
+        let repos = repos.trim();
+        if role.is_empty() || repos.is_empty() {
+            return Err(format!("Role grant '{}' needs both a role and a repository pattern", s));
+        }
+
+        Ok(RoleGrant {
+            role: role.to_string(),
+            permission: permission.trim().parse()?,
+            repos: repos.to_string(),
+        })
+    }
+}
+
+impl RoleGrant {
+    /// The permission this grant gives `role` on `repo`, if any
+    fn grant_for(&self, role: &str, repo: &str) -> Option<Permission> {
+        let captures = glob_captures(&self.role, role)?;
+        // A captured `*` would widen the repository pattern it is substituted into
+        if captures.iter().any(|capture| capture.contains('*')) {
+            return None;
+        }
+        let mut repos = self.repos.clone();
+        // Highest index first so $1 doesn't clobber the start of $10
+        for (i, capture) in captures.iter().enumerate().rev() {
+            repos = repos.replace(&format!("${}", i + 1), capture);
+        }
+        glob_captures(&repos, repo).map(|_| self.permission)
+    }
+}
+
+/// The configured role grants
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RolePermissionMap {
+    pub grants: Vec<RoleGrant>,
+}
+
+impl RolePermissionMap {
+    /// Parse comma-separated `role=permission@repos` grants
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let grants = spec
+            .split(',')
+            .map(str::trim)
+            .filter(|grant| !grant.is_empty())
+            .map(str::parse::<RoleGrant>)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { grants })
+    }
+
+    /// Grants from `OIDC_ROLE_PERMISSIONS`, falling back to `DEFAULT_ROLE_PERMISSIONS`
+    pub fn from_env() -> Result<Self, String> {
+        let spec = std::env::var("OIDC_ROLE_PERMISSIONS").unwrap_or_else(|_| DEFAULT_ROLE_PERMISSIONS.to_string());
+        Self::parse(&spec)
+    }
+
+    /// The strongest permission any of `roles` grants on `repo`
+    pub fn permission_for(&self, roles: &[String], repo: &str) -> Option<Permission> {
+        roles
+            .iter()
+            .flat_map(|role| self.grants.iter().filter_map(move |grant| grant.grant_for(role, repo)))
+            .max()
+    }
+}
+
+/// Match `text` against a pattern where `*` matches any run of characters,
+/// returning what each `*` matched.
+///
+/// Stars take the shortest match that lets the rest of the pattern succeed.
+pub(crate) fn glob_captures<'t>(pattern: &str, text: &'t str) -> Option<Vec<&'t str>> {
+    let Some(star) = pattern.find('*') else {
+        return (pattern == text).then(Vec::new);
+    };
+
+    let text = text.strip_prefix(&pattern[..star])?;
+    let rest = &pattern[star + 1..];
+    (0..=text.len())
+        .filter(|&end| text.is_char_boundary(end))
+        .find_map(|end| {
+            let mut captures = glob_captures(rest, &text[end..])?;
+            captures.insert(0, &text[..end]);
+            Some(captures)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roles(roles: &[&str]) -> Vec<String> {
+        roles.iter().map(|r| r.to_string()).collect()
+    }
+
+    #[test]
+    fn test_repo_scoped_role_grants_only_that_repo() {
+        let map = RolePermissionMap::parse(DEFAULT_ROLE_PERMISSIONS).unwrap();
+        let user = roles(&["repo:foo:write"]);
+
+        let on_foo = map.permission_for(&user, "foo");
+        assert_eq!(on_foo, Some(Permission::Write));
+        assert!(on_foo.unwrap().grants(Permission::Write));
+        assert!(!on_foo.unwrap().grants(Permission::Admin));
+
+        assert_eq!(map.permission_for(&user, "bar"), None);
+        assert_eq!(map.permission_for(&user, "foobar"), None);
+    }
+
+    #[test]
+    fn test_wildcard_in_role_name_does_not_widen_repo_pattern() {
+        let map = RolePermissionMap::parse(DEFAULT_ROLE_PERMISSIONS).unwrap();
+
+        assert_eq!(map.permission_for(&roles(&["repo:*:write"]), "foo"), None);
+    }
+
+    #[test]
+    fn test_admin_role_overrides_repo_roles() {
+        let map = RolePermissionMap::parse(&format!("data-admin=admin@*,{}", DEFAULT_ROLE_PERMISSIONS)).unwrap();
+        let user = roles(&["repo:foo:read", "data-admin"]);
+
+        assert_eq!(map.permission_for(&user, "foo"), Some(Permission::Admin));
+        assert_eq!(map.permission_for(&user, "bar"), Some(Permission::Admin));
+    }
+
+    #[test]
+    fn test_strongest_grant_wins() {
+        let map = RolePermissionMap::parse(DEFAULT_ROLE_PERMISSIONS).unwrap();
+        let user = roles(&["repo:foo:read", "repo:foo:write"]);
+
+        assert_eq!(map.permission_for(&user, "foo"), Some(Permission::Write));
+    }
+
+    #[test]
+    fn test_wildcard_role_and_repo_patterns() {
+        let map = RolePermissionMap::parse("team-*=write@$1-*, *-viewer=read@*").unwrap();
+
+        assert_eq!(map.permission_for(&roles(&["team-climate"]), "climate-obs"), Some(Permission::Write));
+        assert_eq!(map.permission_for(&roles(&["team-climate"]), "ocean-obs"), None);
+        assert_eq!(map.permission_for(&roles(&["ops-viewer"]), "anything"), Some(Permission::Read));
+        assert_eq!(map.permission_for(&roles(&["viewer"]), "anything"), None);
+    }
+
+    #[test]
+    fn test_parse_rejects_malformed_grants() {
+        assert!(RolePermissionMap::parse("data-admin").is_err());
+        assert!(RolePermissionMap::parse("data-admin=admin").is_err());
+        assert!(RolePermissionMap::parse("data-admin=owner@*").is_err());
+        assert!(RolePermissionMap::parse("=admin@*").is_err());
+        assert!(RolePermissionMap::parse("").unwrap().grants.is_empty());
+    }
+
+    #[test]
+    fn test_glob_captures() {
+        assert_eq!(glob_captures("repo:*:write", "repo:foo:write"), Some(vec!["foo"]));
+        assert_eq!(glob_captures("*", ""), Some(vec![""]));
+        assert_eq!(glob_captures("a*b*c", "aXbYc"), Some(vec!["X", "Y"]));
+        assert_eq!(glob_captures("repo:*:write", "repo:foo:read"), None);
+        assert_eq!(glob_captures("exact", "exact"), Some(vec![]));
+    }
+}
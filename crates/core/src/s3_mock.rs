@@ -0,0 +1,139 @@
+//! Path-style S3 stand-in for job tests.
+//!
+//! Serves PUT, GET and DELETE for `/{bucket}/{key}` from memory over a real socket, so
+//! both SDK calls and presigned URLs work against it. GETs honour single `Range`
+//! headers the way S3 does, and a PUT with `x-amz-copy-source` copies an object.
+
+use aws_sdk_s3::config::{BehaviorVersion, Credentials, Region};
+use axum::body::Bytes;
+use axum::extract::{Path, State};
+use axum::http::{header, HeaderMap, Method, StatusCode};
+use axum::response::{IntoResponse, Response};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone, Default)]
+pub struct MockS3 {
+    /// Stored bodies keyed by `bucket/key`
+    pub objects: Arc<Mutex<HashMap<String, Vec<u8>>>>,
+    /// `bucket/key` of every GET, in order
+    pub reads: Arc<Mutex<Vec<String>>>,
+    /// `Range` header of every GET, in the same order as `reads`
+    pub ranges: Arc<Mutex<Vec<Option<String>>>>,
+    /// `Content-Type` each object was PUT with, keyed by `bucket/key`
+    pub content_types: Arc<Mutex<HashMap<String, String>>>,
+    /// When set, PUT bodies are cut to this many bytes, like an upload that
+    /// was truncated on the way in
+    pub truncate_to: Arc<Mutex<Option<usize>>>,
+}
+
+impl MockS3 {
+    pub fn put(&self, name: &str, data: &[u8]) {
+        self.objects.lock().unwrap().insert(name.to_string(), data.to_vec());
+    }
+
+    pub fn reads(&self) -> Vec<String> {
+        self.reads.lock().unwrap().clone()
+    }
+
+    pub fn ranges(&self) -> Vec<Option<String>> {
+        self.ranges.lock().unwrap().clone()
+    }
+
+    /// Store only the first `len` bytes of every later PUT
+    pub fn truncate_uploads(&self, len: usize) {
+        *self.truncate_to.lock().unwrap() = Some(len);
+    }
+
+    pub fn content_type(&self, name: &str) -> Option<String> {
+        self.content_types.lock().unwrap().get(name).cloned()
+    }
+
+    /// Start serving and return a client pointed at the server
+    pub async fn client(&self) -> aws_sdk_s3::Client {
+        let app = axum::Router::new()
+            .route("/:bucket/*key", axum::routing::any(object))
+            .with_state(self.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let config = aws_sdk_s3::Config::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new("us-east-1"))
+            .credentials_provider(Credentials::new("test", "test", None, None, "test"))
+            .endpoint_url(endpoint)
+            .force_path_style(true)
+            .build();
+        aws_sdk_s3::Client::from_conf(config)
+    }
+}
+
+/// Inclusive byte bounds of `bytes=a-b`, `bytes=a-` or `bytes=-n` within `len` bytes
+fn byte_range(range: &str, len: usize) -> Option<(usize, usize)> {
+    let (start, end) = range.strip_prefix("bytes=")?.split_once('-')?;
+    let (start, end) = match (start.parse::<usize>().ok(), end.parse::<usize>().ok()) {
+        (Some(start), Some(end)) => (start, end.min(len.checked_sub(1)?)),
+        (Some(start), None) => (start, len.checked_sub(1)?),
+        (None, Some(suffix)) => (len.saturating_sub(suffix), len.checked_sub(1)?),
+        (None, None) => return None,
+    };
+    (start <= end).then_some((start, end))
+}
+
+async fn object(
+    State(s3): State<MockS3>,
+    method: Method,
+    Path((bucket, key)): Path<(String, String)>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let name = format!("{}/{}", bucket, key);
+    let mut objects = s3.objects.lock().unwrap();
+    match method {
+        Method::PUT if headers.contains_key("x-amz-copy-source") => {
+            let source = headers["x-amz-copy-source"].to_str().unwrap_or_default().trim_start_matches('/');
+            let Some(data) = objects.get(source).cloned() else {
+                return (StatusCode::NOT_FOUND, b"<Error><Code>NoSuchKey</Code></Error>".to_vec()).into_response();
+            };
+            objects.insert(name, data);
+            (StatusCode::OK, b"<CopyObjectResult><ETag>\"copy\"</ETag></CopyObjectResult>".to_vec()).into_response()
+        }
+        Method::PUT => {
+            if let Some(content_type) = headers.get(header::CONTENT_TYPE).and_then(|v| v.to_str().ok()) {
+                s3.content_types.lock().unwrap().insert(name.clone(), content_type.to_string());
+            }
+            let len = s3.truncate_to.lock().unwrap().map_or(body.len(), |len| len.min(body.len()));
+            objects.insert(name, body[..len].to_vec());
+            StatusCode::OK.into_response()
+        }
+        Method::DELETE => {
+            objects.remove(&name);
+            StatusCode::NO_CONTENT.into_response()
+        }
+        _ => {
+            let range = headers.get(header::RANGE).and_then(|v| v.to_str().ok()).map(str::to_string);
+            s3.reads.lock().unwrap().push(name.clone());
+            s3.ranges.lock().unwrap().push(range.clone());
+            let Some(data) = objects.get(&name) else {
+                return (StatusCode::NOT_FOUND, b"<Error><Code>NoSuchKey</Code></Error>".to_vec()).into_response();
+            };
+            let Some(range) = range else {
+                return (StatusCode::OK, data.clone()).into_response();
+            };
+            match byte_range(&range, data.len()) {
+                Some((start, end)) => (
+                    StatusCode::PARTIAL_CONTENT,
+                    [(header::CONTENT_RANGE, format!("bytes {}-{}/{}", start, end, data.len()))],
+                    data[start..=end].to_vec(),
+                )
+                    .into_response(),
+                None => (
+                    StatusCode::RANGE_NOT_SATISFIABLE,
+                    b"<Error><Code>InvalidRange</Code></Error>".to_vec(),
+                )
+                    .into_response(),
+            }
+        }
+    }
+}
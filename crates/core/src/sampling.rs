@@ -0,0 +1,463 @@
+//! Ranged reads for sampling committed CSV and Parquet files.
+//!
+//! A sample only needs the leading rows, so the samplers ask S3 for byte
+//! ranges instead of downloading whole objects. CSV files are read from the
+//! start in growing ranges until they hold every row the sample keeps.
+//! Parquet files are read footer first, then only the first row group's
+//! column chunks. Objects up to `SMALL_OBJECT_BYTES` are read whole.
+
+use bytes::{Buf, Bytes};
+use parquet::basic::{ConvertedType, Type as PhysicalType};
+use parquet::errors::ParquetError;
+use parquet::file::metadata::{ParquetMetaData, RowGroupMetaData};
+use parquet::file::reader::{ChunkReader, FileReader, Length, SerializedFileReader};
+use parquet::file::serialized_reader::ReadOptionsBuilder;
+use parquet::schema::types::ColumnDescriptor;
+use serde_json::Value;
+
+use crate::jobs::{sample_csv, JobError, MAX_SAMPLE_BYTES, MAX_SAMPLE_ROWS};
+
+/// Objects this small are read in full
+pub const SMALL_OBJECT_BYTES: u64 = 1024 * 1024;
+/// First range read from a CSV file; doubled until it holds the sample
+pub const CSV_INITIAL_RANGE_BYTES: u64 = 64 * 1024;
+/// Longest CSV prefix read; the sample is taken from the complete rows in it
+pub const CSV_MAX_RANGE_BYTES: u64 = 16 * 1024 * 1024;
+/// Bytes read from the end of a Parquet file, enough for most footers
+pub const PARQUET_TAIL_BYTES: u64 = 64 * 1024;
+
+const PARQUET_MAGIC: &[u8] = b"PAR1";
+
+/// Part of an object, and the object's total size
+struct RangeRead {
+    bytes: Vec<u8>,
+    total: u64,
+}
+
+/// Read the bytes named by an HTTP `Range` value
+async fn read_range(client: &aws_sdk_s3::Client, bucket: &str, key: &str, range: String) -> Result<RangeRead, JobError> {
+    let output = client
+        .get_object()
+        .bucket(bucket)
+        .key(key)
+        .range(&range)
+        .send()
+        .await
+        .map_err(|e| JobError::Storage(format!("Failed to read {} ({}): {}", key, range, e)))?;
+    let total = output.content_range().and_then(content_range_total);
+    let bytes = output.body.collect().await?.into_bytes().to_vec();
+
+    // Without a Content-Range the whole object was sent
+    let total = total.unwrap_or(bytes.len() as u64);
+    Ok(RangeRead { bytes, total })
+}
+
+/// `bytes 0-99/1234` → 1234
+fn content_range_total(value: &str) -> Option<u64> {
+    value.rsplit_once('/')?.1.parse().ok()
+}
+
+/// Sample a CSV object, reading only as much of it as the sample needs
+pub async fn sample_csv_object(
+    client: &aws_sdk_s3::Client,
+    bucket: &str,
+    key: &str,
+) -> Result<(Vec<Value>, Value), JobError> {
+    let first = read_range(client, bucket, key, format!("bytes=0-{}", CSV_INITIAL_RANGE_BYTES - 1)).await?;
+    let total = first.total;
+    let mut data = first.bytes;
+    if (data.len() as u64) < total && total <= SMALL_OBJECT_BYTES {
+        let rest = read_range(client, bucket, key, format!("bytes={}-", data.len())).await?;
+        data.extend_from_slice(&rest.bytes);
+    }
+
+    let end = loop {
+        let read = data.len() as u64;
+        if read >= total {
+            break data.len();
+        }
+        let prefix = csv_prefix(&data);
+        if prefix.holds_sample() || read >= CSV_MAX_RANGE_BYTES {
+            break prefix.complete_end;
+        }
+
+        let next_end = (read * 2).min(CSV_MAX_RANGE_BYTES).min(total);
+        let more = read_range(client, bucket, key, format!("bytes={}-{}", read, next_end - 1)).await?;
+        if more.bytes.is_empty() {
+            break prefix.complete_end;
+        }
+        data.extend_from_slice(&more.bytes);
+    };
+
+    sample_csv(&data[..end]).map_err(|e| JobError::Processing(format!("CSV sampling failed: {}", e)))
+}
+
+/// Rows found in a leading slice of a CSV file
+struct CsvPrefix {
+    /// Rows known to be whole
+    complete_rows: usize,
+    /// Byte offset just past the header row
+    header_end: usize,
+    /// Byte offset just past the last whole row
+    complete_end: usize,
+}
+
+impl CsvPrefix {
+    /// Whether the whole rows include everything `sample_csv` would keep,
+    /// either `MAX_SAMPLE_ROWS` rows or more than `MAX_SAMPLE_BYTES` of them
+    fn holds_sample(&self) -> bool {
+        self.complete_rows >= MAX_SAMPLE_ROWS || self.complete_end - self.header_end > MAX_SAMPLE_BYTES
+    }
+}
+
+/// Count the whole rows at the start of `data`. The last row parsed may have
+/// been cut off by the range, so it never counts.
+fn csv_prefix(data: &[u8]) -> CsvPrefix {
+    let mut reader = csv::Reader::from_reader(data);
+    if reader.headers().is_err() {
+        return CsvPrefix { complete_rows: 0, header_end: 0, complete_end: 0 };
+    }
+    let header_end = reader.position().byte() as usize;
+
+    let mut record = csv::StringRecord::new();
+    let mut row_ends = vec![header_end];
+    while row_ends.len() <= MAX_SAMPLE_ROWS + 1 {
+        match reader.read_record(&mut record) {
+            Ok(true) => row_ends.push(reader.position().byte() as usize),
+            _ => break,
+        }
+    }
+
+    let complete_rows = row_ends.len().saturating_sub(2);
+    CsvPrefix {
+        complete_rows,
+        header_end,
+        complete_end: row_ends[complete_rows],
+    }
+}
+
+/// An object of which only some byte ranges have been read
+#[derive(Clone)]
+struct SparseObject {
+    len: u64,
+    segments: Vec<(u64, Bytes)>,
+}
+
+impl SparseObject {
+    fn new(len: u64) -> Self {
+        Self { len, segments: Vec::new() }
+    }
+
+    fn insert(&mut self, start: u64, bytes: Vec<u8>) {
+        self.segments.push((start, Bytes::from(bytes)));
+    }
+
+    /// The segment holding `length` bytes from `start`, and where they begin in it
+    fn segment(&self, start: u64, length: usize) -> Option<(usize, &Bytes)> {
+        self.segments.iter().find_map(|(segment_start, bytes)| {
+            let offset = usize::try_from(start.checked_sub(*segment_start)?).ok()?;
+            (offset + length <= bytes.len()).then_some((offset, bytes))
+        })
+    }
+
+    fn missing(start: u64, length: usize) -> ParquetError {
+        ParquetError::General(format!("bytes {}..{} were not read", start, start + length as u64))
+    }
+}
+
+impl Length for SparseObject {
+    fn len(&self) -> u64 {
+        self.len
+    }
+}
+
+impl ChunkReader for SparseObject {
+    type T = bytes::buf::Reader<Bytes>;
+
+    fn get_read(&self, start: u64) -> parquet::errors::Result<Self::T> {
+        let (offset, bytes) = self.segment(start, 0).ok_or_else(|| Self::missing(start, 0))?;
+        Ok(bytes.slice(offset..).reader())
+    }
+
+    fn get_bytes(&self, start: u64, length: usize) -> parquet::errors::Result<Bytes> {
+        let (offset, bytes) = self.segment(start, length).ok_or_else(|| Self::missing(start, length))?;
+        Ok(bytes.slice(offset..offset + length))
+    }
+}
+
+/// Length of the footer (metadata, its length and the magic) from a file's last bytes
+fn footer_len(tail: &[u8]) -> Result<u64, JobError> {
+    let last = tail
+        .len()
+        .checked_sub(8)
+        .map(|start| &tail[start..])
+        .filter(|last| &last[4..] == PARQUET_MAGIC)
+        .ok_or_else(|| JobError::Processing("Not a Parquet file: footer magic missing".to_string()))?;
+    let metadata_len = u32::from_le_bytes([last[0], last[1], last[2], last[3]]);
+    Ok(metadata_len as u64 + 8)
+}
+
+/// Byte range covering every column chunk of the first row group
+fn first_row_group_range(metadata: &ParquetMetaData) -> Option<(u64, u64)> {
+    metadata
+        .row_groups()
+        .first()?
+        .columns()
+        .iter()
+        .map(|column| {
+            let (start, length) = column.byte_range();
+            (start, start + length)
+        })
+        .reduce(|(start, end), (s, e)| (start.min(s), end.max(e)))
+}
+
+fn parquet_error(e: ParquetError) -> JobError {
+    JobError::Processing(format!("Parquet sampling failed: {}", e))
+}
+
+/// Sample a Parquet object from its footer and first row group
+pub async fn sample_parquet_object(
+    client: &aws_sdk_s3::Client,
+    bucket: &str,
+    key: &str,
+) -> Result<(Vec<Value>, Value), JobError> {
+    let tail = read_range(client, bucket, key, format!("bytes=-{}", PARQUET_TAIL_BYTES)).await?;
+    let total = tail.total;
+    let tail_start = total - tail.bytes.len() as u64;
+    let mut object = SparseObject::new(total);
+
+    if tail_start == 0 || total <= SMALL_OBJECT_BYTES {
+        let mut whole = if tail_start > 0 {
+            read_range(client, bucket, key, format!("bytes=0-{}", tail_start - 1)).await?.bytes
+        } else {
+            Vec::new()
+        };
+        whole.extend_from_slice(&tail.bytes);
+        object.insert(0, whole);
+        return sample_parquet(object).map_err(parquet_error);
+    }
+
+    let footer_start = total
+        .checked_sub(footer_len(&tail.bytes)?)
+        .ok_or_else(|| JobError::Processing("Parquet footer is longer than the file".to_string()))?;
+    if footer_start < tail_start {
+        let head = read_range(client, bucket, key, format!("bytes={}-{}", footer_start, tail_start - 1)).await?;
+        object.insert(footer_start, [head.bytes, tail.bytes].concat());
+    } else {
+        object.insert(tail_start, tail.bytes);
+    }
+
+    let metadata = SerializedFileReader::new(object.clone()).map_err(parquet_error)?.metadata().clone();
+    if let Some((start, end)) = first_row_group_range(&metadata) {
+        if object.segment(start, (end - start) as usize).is_none() {
+            let group = read_range(client, bucket, key, format!("bytes={}-{}", start, end - 1)).await?;
+            object.insert(start, group.bytes);
+        }
+    }
+
+    sample_parquet(object).map_err(parquet_error)
+}
+
+/// The leading rows of the first row group, capped like CSV samples, and
+/// each column's type in the CSV sampler's vocabulary
+fn sample_parquet<R: ChunkReader + 'static>(object: R) -> Result<(Vec<Value>, Value), ParquetError> {
+    let options = ReadOptionsBuilder::new()
+        .with_predicate(Box::new(|_: &RowGroupMetaData, index: usize| index == 0))
+        .build();
+    let reader = SerializedFileReader::new_with_options(object, options)?;
+
+    let columns: Vec<Value> = reader
+        .metadata()
+        .file_metadata()
+        .schema_descr()
+        .columns()
+        .iter()
+        .map(|column| serde_json::json!({ "name": column.path().string(), "type": column_type(column) }))
+        .collect();
+
+    let mut sample_data = Vec::new();
+    let mut sample_bytes = 0;
+    if reader.num_row_groups() > 0 {
+        for row in reader.get_row_group(0)?.get_row_iter(None)? {
+            if sample_data.len() >= MAX_SAMPLE_ROWS {
+                break;
+            }
+            let row = row?.to_json_value();
+            sample_bytes += row.to_string().len();
+            if sample_bytes > MAX_SAMPLE_BYTES {
+                break;
+            }
+            sample_data.push(row);
+        }
+    }
+
+    Ok((sample_data, serde_json::json!({ "columns": columns })))
+}
+
+fn column_type(column: &ColumnDescriptor) -> &'static str {
+    match (column.physical_type(), column.converted_type()) {
+        (PhysicalType::BOOLEAN, _) => "boolean",
+        (
+            PhysicalType::INT32 | PhysicalType::INT64,
+            ConvertedType::NONE
+            | ConvertedType::INT_8
+            | ConvertedType::INT_16
+            | ConvertedType::INT_32
+            | ConvertedType::INT_64
+            | ConvertedType::UINT_8
+            | ConvertedType::UINT_16
+            | ConvertedType::UINT_32
+            | ConvertedType::UINT_64,
+        ) => "integer",
+        (PhysicalType::FLOAT | PhysicalType::DOUBLE, _) => "number",
+        _ => "string",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::s3_mock::MockS3;
+    use arrow::array::{ArrayRef, Int64Array, StringArray};
+    use arrow::record_batch::RecordBatch;
+    use parquet::arrow::ArrowWriter;
+    use parquet::file::properties::WriterProperties;
+    use std::sync::Arc;
+
+    /// Bytes a recorded `Range` asked for; `None` for an open-ended or missing range
+    fn requested_bytes(range: &Option<String>) -> Option<u64> {
+        let (start, end) = range.as_deref()?.strip_prefix("bytes=")?.split_once('-')?;
+        match (start.parse::<u64>().ok(), end.parse::<u64>().ok()) {
+            (Some(start), Some(end)) => Some(end - start + 1),
+            (None, Some(suffix)) => Some(suffix),
+            _ => None,
+        }
+    }
+
+    fn large_csv(rows: usize, width: usize) -> Vec<u8> {
+        let mut csv = String::from("id,station,reading\n");
+        for i in 0..rows {
+            csv.push_str(&format!("{},{},{}.5\n", i, "s".repeat(width), i % 40));
+        }
+        csv.into_bytes()
+    }
+
+    async fn sample_csv_via_s3(data: &[u8]) -> (Vec<Value>, MockS3) {
+        let s3 = MockS3::default();
+        s3.put("blacklake/data/large.csv", data);
+        let (rows, _) = sample_csv_object(&s3.client().await, "blacklake", "data/large.csv").await.unwrap();
+        (rows, s3)
+    }
+
+    #[tokio::test]
+    async fn test_large_csv_is_sampled_with_one_bounded_range() {
+        let data = large_csv(200_000, 8);
+        assert!(data.len() as u64 > SMALL_OBJECT_BYTES);
+
+        let (rows, s3) = sample_csv_via_s3(&data).await;
+
+        assert_eq!(rows.len(), MAX_SAMPLE_ROWS);
+        assert_eq!(rows[MAX_SAMPLE_ROWS - 1]["id"], (MAX_SAMPLE_ROWS - 1).to_string());
+        assert_eq!(s3.ranges(), vec![Some(format!("bytes=0-{}", CSV_INITIAL_RANGE_BYTES - 1))]);
+    }
+
+    #[tokio::test]
+    async fn test_csv_range_grows_until_rows_are_complete() {
+        // ~2 KiB rows: 100 of them don't fit in the first range
+        let data = large_csv(5_000, 2_000);
+
+        let (rows, s3) = sample_csv_via_s3(&data).await;
+
+        assert_eq!(rows, sample_csv(&data).unwrap().0);
+        let ranges = s3.ranges();
+        assert!(ranges.len() > 1, "{:?}", ranges);
+        let requested: u64 = ranges.iter().map(|r| requested_bytes(r).expect("bounded range")).sum();
+        assert!(requested < data.len() as u64 / 4, "read {} of {} bytes", requested, data.len());
+    }
+
+    #[tokio::test]
+    async fn test_small_csv_is_read_whole() {
+        let data = b"id,name\n1,a\n2,b\n";
+
+        let (rows, s3) = sample_csv_via_s3(data).await;
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(s3.reads().len(), 1);
+    }
+
+    #[test]
+    fn test_cut_off_row_is_not_counted() {
+        let prefix = csv_prefix(b"id,name\n1,alpha\n2,be");
+
+        assert_eq!(prefix.complete_rows, 1);
+        assert_eq!(prefix.header_end, 8);
+        assert_eq!(prefix.complete_end, 16);
+    }
+
+    fn parquet_file(rows: i64, row_group_size: usize) -> Vec<u8> {
+        let ids: ArrayRef = Arc::new(Int64Array::from_iter_values(0..rows));
+        let labels: ArrayRef = Arc::new(StringArray::from_iter_values((0..rows).map(|i| format!("reading-{:08}", i * 7919))));
+        let batch = RecordBatch::try_from_iter([("id", ids), ("label", labels)]).unwrap();
+        let props = WriterProperties::builder().set_max_row_group_size(row_group_size).build();
+
+        let mut bytes = Vec::new();
+        let mut writer = ArrowWriter::try_new(&mut bytes, batch.schema(), Some(props)).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+        bytes
+    }
+
+    #[tokio::test]
+    async fn test_large_parquet_reads_footer_and_first_row_group_only() {
+        let data = parquet_file(200_000, 10_000);
+        assert!(data.len() as u64 > SMALL_OBJECT_BYTES);
+        let s3 = MockS3::default();
+        s3.put("blacklake/data/large.parquet", &data);
+
+        let (rows, schema) = sample_parquet_object(&s3.client().await, "blacklake", "data/large.parquet")
+            .await
+            .unwrap();
+
+        assert_eq!(rows.len(), MAX_SAMPLE_ROWS);
+        assert_eq!(rows[0]["id"], 0);
+        assert_eq!(rows[1]["label"], "reading-00007919");
+        assert_eq!(
+            schema,
+            serde_json::json!({ "columns": [
+                { "name": "id", "type": "integer" },
+                { "name": "label", "type": "string" },
+            ]})
+        );
+        let ranges = s3.ranges();
+        assert_eq!(ranges[0], Some(format!("bytes=-{}", PARQUET_TAIL_BYTES)));
+        let requested: u64 = ranges.iter().map(|r| requested_bytes(r).expect("bounded range")).sum();
+        assert!(requested < data.len() as u64 / 4, "read {} of {} bytes", requested, data.len());
+    }
+
+    #[tokio::test]
+    async fn test_small_parquet_is_read_whole() {
+        let data = parquet_file(50, 10);
+        let s3 = MockS3::default();
+        s3.put("blacklake/data/small.parquet", &data);
+
+        let (rows, _) = sample_parquet_object(&s3.client().await, "blacklake", "data/small.parquet")
+            .await
+            .unwrap();
+
+        // Only the first row group is sampled
+        assert_eq!(rows.len(), 10);
+        assert_eq!(s3.reads().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_non_parquet_object_is_rejected() {
+        let s3 = MockS3::default();
+        s3.put("blacklake/data/fake.parquet", &large_csv(200_000, 8));
+
+        let err = sample_parquet_object(&s3.client().await, "blacklake", "data/fake.parquet")
+            .await
+            .unwrap_err();
+
+        assert!(err.to_string().contains("footer magic"), "{}", err);
+    }
+}
@@ -44,27 +44,213 @@ pub struct ValidationRule {
     pub allowed_values: Option<Vec<Value>>,
 }
 
-/// Schema registry for managing metadata schemas
+/// A single JSON Schema validation failure
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SchemaViolation {
+    /// JSON pointer to the offending value; empty for the document root.
+    /// For `required` this points at the missing property.
+    pub path: String,
+    /// Schema keyword that failed, e.g. `required` or `type`
+    pub constraint: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let path = if self.path.is_empty() { "/" } else { &self.path };
+        write!(f, "{}: {} ({})", path, self.message, self.constraint)
+    }
+}
+
+/// Validate `instance` against a JSON Schema document.
+///
+/// Returns every violation found; an empty vector means the instance is
+/// valid. Fails only if the schema itself cannot be compiled.
+pub fn validate_json_schema(schema: &Value, instance: &Value) -> Result<Vec<SchemaViolation>> {
+    let validator = jsonschema::validator_for(schema)
+        .map_err(|e| anyhow!("Invalid JSON schema: {}", e))?;
+
+    Ok(validator
+        .iter_errors(instance)
+        .map(|error| SchemaViolation {
+            path: match &error.kind {
+                jsonschema::error::ValidationErrorKind::Required { property } => format!(
+                    "{}/{}",
+                    error.instance_path,
+                    property.as_str().unwrap_or_default().replace('~', "~0").replace('/', "~1")
+                ),
+                _ => error.instance_path.to_string(),
+            },
+            constraint: error
+                .schema_path
+                .as_str()
+                .rsplit('/')
+                .next()
+                .unwrap_or_default()
+                .to_string(),
+            message: error.to_string(),
+        })
+        .collect())
+}
+
+impl MetadataSchema {
+    /// Render this schema as a draft-07 JSON Schema document
+    pub fn to_json_schema(&self) -> Value {
+        let mut properties = serde_json::Map::new();
+
+        for (name, field) in &self.fields {
+            let mut property = serde_json::Map::new();
+            let type_name = match field.field_type {
+                FieldType::String | FieldType::DateTime => "string",
+                FieldType::Number => "number",
+                FieldType::Boolean => "boolean",
+                FieldType::Array => "array",
+                FieldType::Object => "object",
+            };
+            property.insert("type".to_string(), Value::String(type_name.to_string()));
+
+            if let Some(description) = &field.description {
+                property.insert("description".to_string(), Value::String(description.clone()));
+            }
+
+            if let Some(rules) = &field.validation {
+                let (min_key, max_key) = match field.field_type {
+                    FieldType::Array => ("minItems", "maxItems"),
+                    _ => ("minLength", "maxLength"),
+                };
+                if let Some(min) = rules.min_length {
+                    property.insert(min_key.to_string(), Value::from(min));
+                }
+                if let Some(max) = rules.max_length {
+                    property.insert(max_key.to_string(), Value::from(max));
+                }
+                if let Some(pattern) = &rules.pattern {
+                    property.insert("pattern".to_string(), Value::String(pattern.clone()));
+                }
+                if let Some(min) = rules.min_value {
+                    property.insert("minimum".to_string(), Value::from(min));
+                }
+                if let Some(max) = rules.max_value {
+                    property.insert("maximum".to_string(), Value::from(max));
+                }
+                if let Some(allowed) = &rules.allowed_values {
+                    property.insert("enum".to_string(), Value::Array(allowed.clone()));
+                }
+            }
+
+            properties.insert(name.clone(), Value::Object(property));
+        }
+
+        serde_json::json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "title": self.name,
+            "type": "object",
+            "properties": properties,
+            "required": self.required_fields,
+        })
+    }
+}
+
+/// A field-level change applied when migrating metadata between schema versions
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum FieldTransform {
+    /// Move the value of `from` to `to`, replacing anything already at `to`
+    Rename { from: String, to: String },
+    /// Drop `field` if present
+    Remove { field: String },
+    /// Insert `value` at `field` unless the field is already set
+    SetDefault { field: String, value: Value },
+}
+
+impl FieldTransform {
+    fn apply(&self, obj: &mut serde_json::Map<String, Value>) {
+        match self {
+            FieldTransform::Rename { from, to } => {
+                if let Some(value) = obj.remove(from) {
+                    obj.insert(to.clone(), value);
+                }
+            }
+            FieldTransform::Remove { field } => {
+                obj.remove(field);
+            }
+            FieldTransform::SetDefault { field, value } => {
+                obj.entry(field.clone()).or_insert_with(|| value.clone());
+            }
+        }
+    }
+}
+
+/// A registered step from one schema version to another
+#[derive(Debug, Clone)]
+struct SchemaMigration {
+    to: String,
+    transforms: Vec<FieldTransform>,
+}
+
+/// Schema registry for managing metadata schemas.
+///
+/// Each collection has a current schema (returned by [`SchemaRegistry::get_schema`])
+/// plus every version registered for it, and optional migrations between versions.
 #[derive(Debug, Clone)]
 pub struct SchemaRegistry {
     schemas: HashMap<String, MetadataSchema>,
+    versions: HashMap<String, HashMap<String, MetadataSchema>>,
+    migrations: HashMap<(String, String), Vec<SchemaMigration>>,
 }
 
 impl SchemaRegistry {
     pub fn new() -> Self {
         Self {
             schemas: HashMap::new(),
+            versions: HashMap::new(),
+            migrations: HashMap::new(),
         }
     }
 
+    /// Register `schema` under its own name and version and make it the current one
     pub fn register_schema(&mut self, schema: MetadataSchema) {
+        self.versions
+            .entry(schema.name.clone())
+            .or_default()
+            .insert(schema.version.clone(), schema.clone());
         self.schemas.insert(schema.name.clone(), schema);
     }
 
+    /// Register an additional version of a collection's schema.
+    ///
+    /// The current schema is left alone unless the collection has none yet.
+    pub fn register_schema_version(&mut self, collection: &str, version: &str, mut schema: MetadataSchema) {
+        schema.name = collection.to_string();
+        schema.version = version.to_string();
+        self.schemas
+            .entry(collection.to_string())
+            .or_insert_with(|| schema.clone());
+        self.versions
+            .entry(collection.to_string())
+            .or_default()
+            .insert(version.to_string(), schema);
+    }
+
     pub fn get_schema(&self, name: &str) -> Option<&MetadataSchema> {
         self.schemas.get(name)
     }
 
+    pub fn get_schema_version(&self, collection: &str, version: &str) -> Option<&MetadataSchema> {
+        self.versions.get(collection).and_then(|versions| versions.get(version))
+    }
+
+    /// Versions registered for `collection`, sorted
+    pub fn list_schema_versions(&self, collection: &str) -> Vec<&str> {
+        let mut versions: Vec<&str> = self
+            .versions
+            .get(collection)
+            .map(|versions| versions.keys().map(String::as_str).collect())
+            .unwrap_or_default();
+        versions.sort_unstable();
+        versions
+    }
+
     pub fn get_default_schema(&self) -> Option<&MetadataSchema> {
         self.schemas.get("default")
     }
@@ -72,6 +258,90 @@ impl SchemaRegistry {
     pub fn list_schemas(&self) -> Vec<&MetadataSchema> {
         self.schemas.values().collect()
     }
+
+    /// Validate metadata against the schema registered under `name`
+    pub fn validate(&self, name: &str, metadata: &Value) -> Result<Vec<SchemaViolation>> {
+        let schema = self
+            .get_schema(name)
+            .ok_or_else(|| anyhow!("Schema not found: {}", name))?;
+        validate_json_schema(&schema.to_json_schema(), metadata)
+    }
+
+    /// Validate metadata against a specific version of a collection's schema
+    pub fn validate_version(&self, collection: &str, version: &str, metadata: &Value) -> Result<Vec<SchemaViolation>> {
+        let schema = self
+            .get_schema_version(collection, version)
+            .ok_or_else(|| anyhow!("Schema not found: {}@{}", collection, version))?;
+        validate_json_schema(&schema.to_json_schema(), metadata)
+    }
+
+    /// Register the transforms that turn `from`-version metadata into `to`-version metadata
+    pub fn register_migration(
+        &mut self,
+        collection: &str,
+        from: &str,
+        to: &str,
+        transforms: Vec<FieldTransform>,
+    ) {
+        self.migrations
+            .entry((collection.to_string(), from.to_string()))
+            .or_default()
+            .push(SchemaMigration {
+                to: to.to_string(),
+                transforms,
+            });
+    }
+
+    /// Migrate metadata from one schema version to another.
+    ///
+    /// Registered migrations are chained, shortest path first, so `v1 -> v3`
+    /// works when only `v1 -> v2` and `v2 -> v3` are registered.
+    pub fn migrate_meta(&self, collection: &str, meta: &Value, from: &str, to: &str) -> Result<Value> {
+        let steps = self
+            .migration_path(collection, from, to)
+            .ok_or_else(|| anyhow!("No migration path for {} from {} to {}", collection, from, to))?;
+
+        let mut migrated = meta.clone();
+        let obj = migrated
+            .as_object_mut()
+            .ok_or_else(|| anyhow!("Metadata must be a JSON object"))?;
+        for step in steps {
+            for transform in &step.transforms {
+                transform.apply(obj);
+            }
+        }
+        Ok(migrated)
+    }
+
+    /// Breadth-first search over registered migrations
+    fn migration_path(&self, collection: &str, from: &str, to: &str) -> Option<Vec<&SchemaMigration>> {
+        let mut previous: HashMap<&str, (&str, &SchemaMigration)> = HashMap::new();
+        let mut queue = std::collections::VecDeque::from([from]);
+
+        while let Some(version) = queue.pop_front() {
+            if version == to {
+                let mut steps = Vec::new();
+                let mut current = to;
+                while current != from {
+                    let (prev, step) = previous[current];
+                    steps.push(step);
+                    current = prev;
+                }
+                steps.reverse();
+                return Some(steps);
+            }
+
+            let key = (collection.to_string(), version.to_string());
+            for step in self.migrations.get(&key).into_iter().flatten() {
+                if step.to != from && !previous.contains_key(step.to.as_str()) {
+                    previous.insert(step.to.as_str(), (version, step));
+                    queue.push_back(step.to.as_str());
+                }
+            }
+        }
+
+        None
+    }
 }
 
 impl Default for SchemaRegistry {
@@ -480,4 +750,160 @@ mod tests {
         
         assert!(validate_metadata(&invalid_type_meta, &schema).is_err());
     }
+
+    fn valid_dublin_core_meta() -> Value {
+        json!({
+            "creation_dt": "2023-01-01T00:00:00Z",
+            "creator": "test@example.com",
+            "file_name": "test.txt",
+            "file_type": "text/plain",
+            "file_size": 100,
+            "org_lab": "TestLab",
+            "description": "Test description",
+            "data_source": "test_source",
+            "data_collection_method": "test_method",
+            "version": "1.0"
+        })
+    }
+
+    #[test]
+    fn test_registry_validate_valid_document() {
+        let registry = SchemaRegistry::default();
+        let violations = registry.validate("default", &valid_dublin_core_meta()).unwrap();
+        assert!(violations.is_empty(), "{:?}", violations);
+    }
+
+    #[test]
+    fn test_registry_validate_reports_missing_and_wrong_type() {
+        let registry = SchemaRegistry::default();
+        let mut meta = valid_dublin_core_meta();
+        meta.as_object_mut().unwrap().remove("creator");
+        meta["file_size"] = json!("not_a_number");
+
+        let violations = registry.validate("default", &meta).unwrap();
+        assert!(violations
+            .iter()
+            .any(|v| v.constraint == "required" && v.message.contains("creator")));
+        assert!(violations
+            .iter()
+            .any(|v| v.constraint == "type" && v.path == "/file_size"));
+    }
+
+    #[test]
+    fn test_registry_validate_unknown_schema() {
+        let registry = SchemaRegistry::default();
+        assert!(registry.validate("nonexistent", &json!({})).is_err());
+    }
+
+    fn dataset_schema(required: &[&str]) -> MetadataSchema {
+        let fields = required
+            .iter()
+            .map(|name| {
+                (
+                    name.to_string(),
+                    FieldDefinition {
+                        field_type: FieldType::String,
+                        description: None,
+                        default_value: None,
+                        validation: None,
+                    },
+                )
+            })
+            .collect();
+        MetadataSchema {
+            name: "datasets".to_string(),
+            version: String::new(),
+            description: None,
+            fields,
+            required_fields: required.iter().map(|name| name.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_validate_against_non_default_version() {
+        let mut registry = SchemaRegistry::new();
+        registry.register_schema_version("datasets", "v1", dataset_schema(&["title"]));
+        registry.register_schema_version("datasets", "v2", dataset_schema(&["name"]));
+
+        // v1 stays current because it was registered first
+        assert_eq!(registry.get_schema("datasets").unwrap().version, "v1");
+        assert_eq!(registry.list_schema_versions("datasets"), vec!["v1", "v2"]);
+
+        let meta = json!({ "name": "demo" });
+        assert!(!registry.validate("datasets", &meta).unwrap().is_empty());
+        assert!(registry.validate_version("datasets", "v2", &meta).unwrap().is_empty());
+
+        let violations = registry.validate_version("datasets", "v1", &meta).unwrap();
+        assert!(violations.iter().any(|v| v.constraint == "required" && v.message.contains("title")));
+        assert!(violations.iter().any(|v| v.constraint == "required" && v.path == "/title"));
+
+        assert!(registry.validate_version("datasets", "v3", &meta).is_err());
+    }
+
+    #[test]
+    fn test_register_schema_records_version() {
+        let registry = SchemaRegistry::default();
+        let schema = registry.get_schema_version("default", "1.0").unwrap();
+        assert_eq!(schema.name, "default");
+    }
+
+    #[test]
+    fn test_migrate_meta_rename() {
+        let mut registry = SchemaRegistry::new();
+        registry.register_schema_version("datasets", "v1", dataset_schema(&["title"]));
+        registry.register_schema_version("datasets", "v2", dataset_schema(&["name"]));
+        registry.register_migration(
+            "datasets",
+            "v1",
+            "v2",
+            vec![FieldTransform::Rename {
+                from: "title".to_string(),
+                to: "name".to_string(),
+            }],
+        );
+
+        let migrated = registry
+            .migrate_meta("datasets", &json!({ "title": "demo", "owner": "lab" }), "v1", "v2")
+            .unwrap();
+
+        assert_eq!(migrated, json!({ "name": "demo", "owner": "lab" }));
+        assert!(registry.validate_version("datasets", "v2", &migrated).unwrap().is_empty());
+        assert!(registry.migrate_meta("datasets", &migrated, "v2", "v1").is_err());
+    }
+
+    #[test]
+    fn test_migrate_meta_chains_steps() {
+        let mut registry = SchemaRegistry::new();
+        registry.register_migration(
+            "datasets",
+            "v1",
+            "v2",
+            vec![FieldTransform::Rename {
+                from: "title".to_string(),
+                to: "name".to_string(),
+            }],
+        );
+        registry.register_migration(
+            "datasets",
+            "v2",
+            "v3",
+            vec![
+                FieldTransform::Remove { field: "legacy".to_string() },
+                FieldTransform::SetDefault {
+                    field: "license".to_string(),
+                    value: json!("CC-BY-4.0"),
+                },
+            ],
+        );
+
+        let migrated = registry
+            .migrate_meta("datasets", &json!({ "title": "demo", "legacy": true }), "v1", "v3")
+            .unwrap();
+
+        assert_eq!(migrated, json!({ "name": "demo", "license": "CC-BY-4.0" }));
+        assert_eq!(
+            registry.migrate_meta("datasets", &migrated, "v3", "v3").unwrap(),
+            migrated
+        );
+    }
 }
@@ -2,7 +2,8 @@
 // Week 6: Apache Solr integration for advanced search capabilities
 
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
 use thiserror::Error;
 use uuid::Uuid;
 
@@ -14,6 +15,13 @@ pub struct SolrConfig {
     pub commit_within: u64, // milliseconds
     pub batch_size: u32,
     pub timeout: std::time::Duration,
+    /// Give each repo its own `<collection>_<repo_id>` collection instead of
+    /// sharing `collection`
+    pub per_repo_collections: bool,
+    /// Configset, shard and replica counts per-repo collections are created with
+    pub configset: String,
+    pub num_shards: u32,
+    pub replication_factor: u32,
 }
 
 impl Default for SolrConfig {
@@ -24,10 +32,58 @@ impl Default for SolrConfig {
             commit_within: 1500, // 1.5 seconds
             batch_size: 100,
             timeout: std::time::Duration::from_secs(30),
+            per_repo_collections: false,
+            configset: "blacklake".to_string(),
+            num_shards: 1,
+            replication_factor: 1,
         }
     }
 }
 
+impl SolrConfig {
+    /// Defaults with `SOLR_URL`, `SOLR_COLLECTION`, `SOLR_PER_REPO_COLLECTIONS`,
+    /// `SOLR_CONFIGSET`, `SOLR_NUM_SHARDS` and `SOLR_REPLICATION_FACTOR` applied
+    pub fn from_env() -> Self {
+        Self::from_lookup(|name| std::env::var(name).ok())
+    }
+
+    /// Defaults with settings looked up by variable name; counts that do not
+    /// parse as a positive integer are ignored with a warning
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let mut config = Self::default();
+        let count = |var: &str| -> Option<u32> {
+            let raw = lookup(var)?;
+            match raw.trim().parse::<u32>() {
+                Ok(value) if value >= 1 => Some(value),
+                _ => {
+                    tracing::warn!("Ignoring invalid {}='{}' (expected an integer >= 1); using the default", var, raw);
+                    None
+                }
+            }
+        };
+
+        if let Some(url) = lookup("SOLR_URL") {
+            config.url = url.trim_end_matches('/').to_string();
+        }
+        if let Some(collection) = lookup("SOLR_COLLECTION") {
+            config.collection = collection;
+        }
+        if let Some(per_repo) = lookup("SOLR_PER_REPO_COLLECTIONS") {
+            config.per_repo_collections = matches!(per_repo.trim().to_ascii_lowercase().as_str(), "1" | "true" | "yes");
+        }
+        if let Some(configset) = lookup("SOLR_CONFIGSET") {
+            config.configset = configset;
+        }
+        if let Some(num_shards) = count("SOLR_NUM_SHARDS") {
+            config.num_shards = num_shards;
+        }
+        if let Some(replication_factor) = count("SOLR_REPLICATION_FACTOR") {
+            config.replication_factor = replication_factor;
+        }
+        config
+    }
+}
+
 /// Solr document for indexing
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SolrDocument {
@@ -131,9 +187,15 @@ pub struct SolrSuggestion {
 }
 
 /// Solr client for BlackLake
+#[derive(Clone)]
 pub struct SolrClient {
     config: SolrConfig,
     client: reqwest::Client,
+    /// Collection (or alias) requests go to; see [`SolrClient::for_repo`]
+    collection: String,
+    /// Collections `ensure_collection` has already found or created, shared
+    /// between a client and the clients routed from it
+    ensured: Arc<Mutex<HashSet<String>>>,
 }
 
 /// Solr errors
@@ -159,12 +221,106 @@ impl SolrClient {
             .build()
             .expect("Failed to create HTTP client");
         
-        Self { config, client }
+        let collection = config.collection.clone();
+        Self { config, client, collection, ensured: Arc::default() }
+    }
+
+    /// Collection requests from this client go to
+    pub fn collection(&self) -> &str {
+        &self.collection
+    }
+
+    /// Collection holding `repo_id`'s documents: `<collection>_<repo_id>`
+    /// with per-repo routing, the shared collection without it
+    pub fn collection_for(&self, repo_id: Uuid) -> String {
+        if self.config.per_repo_collections {
+            format!("{}_{}", self.config.collection, repo_id)
+        } else {
+            self.config.collection.clone()
+        }
+    }
+
+    /// A client whose adds, deletes, commits and queries target `repo_id`'s
+    /// collection
+    pub fn for_repo(&self, repo_id: Uuid) -> SolrClient {
+        SolrClient {
+            collection: self.collection_for(repo_id),
+            ..self.clone()
+        }
+    }
+
+    /// Like [`SolrClient::for_repo`], first creating the repo's collection if
+    /// per-repo routing is on. The shared collection is provisioned by
+    /// `ops/solr/init-cloud.sh` and is never created here.
+    pub async fn ensure_repo_collection(&self, repo_id: Uuid) -> Result<SolrClient, SolrError> {
+        let routed = self.for_repo(repo_id);
+        if self.config.per_repo_collections {
+            routed.ensure_collection().await?;
+        }
+        Ok(routed)
+    }
+
+    /// Create this client's collection unless a collection or alias of that
+    /// name already exists. Each name is only checked once per client.
+    pub async fn ensure_collection(&self) -> Result<(), SolrError> {
+        if self.ensured.lock().unwrap().contains(&self.collection) {
+            return Ok(());
+        }
+
+        let collections = self.collections_api(&[("action", "LIST")]).await?;
+        let aliases = self.collections_api(&[("action", "LISTALIASES")]).await?;
+        let exists = collections
+            .get("collections")
+            .and_then(|names| names.as_array())
+            .is_some_and(|names| names.iter().any(|name| name == self.collection.as_str()))
+            || aliases
+                .get("aliases")
+                .and_then(|aliases| aliases.get(&self.collection))
+                .is_some();
+
+        if !exists {
+            tracing::info!("Creating Solr collection {}", self.collection);
+            let num_shards = self.config.num_shards.to_string();
+            let replication_factor = self.config.replication_factor.to_string();
+            self.collections_api(&[
+                ("action", "CREATE"),
+                ("name", &self.collection),
+                ("numShards", &num_shards),
+                ("replicationFactor", &replication_factor),
+                ("collection.configName", &self.config.configset),
+            ])
+            .await?;
+        }
+
+        self.ensured.lock().unwrap().insert(self.collection.clone());
+        Ok(())
+    }
+
+    /// Call the Collections API and return its JSON response
+    async fn collections_api(&self, params: &[(&str, &str)]) -> Result<serde_json::Value, SolrError> {
+        let url = format!("{}/admin/collections", self.config.url);
+
+        let response = self.client
+            .get(&url)
+            .query(params)
+            .send()
+            .await
+            .map_err(|e| SolrError::Network(e.to_string()))?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(SolrError::Response(error_text));
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| SolrError::Serialization(e.to_string()))
     }
     
     /// Index a document
     pub async fn index_document(&self, doc: &SolrDocument) -> Result<(), SolrError> {
-        let url = format!("{}/{}/update", self.config.url, self.config.collection);
+        let url = format!("{}/{}/update", self.config.url, self.collection);
         
         let payload = serde_json::json!({
             "add": {
@@ -194,7 +350,7 @@ impl SolrClient {
             return Ok(());
         }
         
-        let url = format!("{}/{}/update", self.config.url, self.config.collection);
+        let url = format!("{}/{}/update", self.config.url, self.collection);
         
         let mut payload = serde_json::Map::new();
         let docs_json: Vec<serde_json::Value> = docs.iter()
@@ -223,7 +379,7 @@ impl SolrClient {
     
     /// Delete documents by query
     pub async fn delete_by_query(&self, query: &str) -> Result<(), SolrError> {
-        let url = format!("{}/{}/update", self.config.url, self.config.collection);
+        let url = format!("{}/{}/update", self.config.url, self.collection);
         
         let payload = serde_json::json!({
             "delete": {
@@ -249,7 +405,7 @@ impl SolrClient {
     
     /// Search documents
     pub async fn search(&self, request: &SolrSearchRequest) -> Result<SolrSearchResponse, SolrError> {
-        let url = format!("{}/{}/select", self.config.url, self.config.collection);
+        let url = format!("{}/{}/select", self.config.url, self.collection);
         
         let mut params = vec![
             ("q", request.q.clone()),
@@ -326,7 +482,7 @@ impl SolrClient {
     
     /// Get suggestions
     pub async fn suggest(&self, query: &str, count: Option<u32>) -> Result<Vec<SolrSuggestion>, SolrError> {
-        let url = format!("{}/{}/suggest", self.config.url, self.config.collection);
+        let url = format!("{}/{}/suggest", self.config.url, self.collection);
         
         let mut params = vec![
             ("suggest", "true".to_string()),
@@ -367,7 +523,7 @@ impl SolrClient {
     
     /// Commit changes
     pub async fn commit(&self) -> Result<(), SolrError> {
-        let url = format!("{}/{}/update", self.config.url, self.config.collection);
+        let url = format!("{}/{}/update", self.config.url, self.collection);
         
         let payload = serde_json::json!({
             "commit": {}
@@ -388,13 +544,29 @@ impl SolrClient {
         Ok(())
     }
     
+    /// Hit the collection's ping handler
+    pub async fn ping(&self) -> Result<(), SolrError> {
+        let url = format!("{}/{}/admin/ping", self.config.url, self.collection);
+
+        let response = self.client
+            .get(&url)
+            .send()
+            .await
+            .map_err(|e| SolrError::Network(e.to_string()))?;
+
+        if !response.status().is_success() {
+            return Err(SolrError::Response(format!("Ping returned {}", response.status())));
+        }
+        Ok(())
+    }
+
     /// Get collection status
     pub async fn get_status(&self) -> Result<SolrStatus, SolrError> {
         let url = format!("{}/admin/collections", self.config.url);
         
         let params = vec![
             ("action", "CLUSTERSTATUS"),
-            ("collection", &self.config.collection),
+            ("collection", &self.collection),
         ];
         
         let response = self.client
@@ -418,13 +590,13 @@ impl SolrClient {
         let doc_count = status_response
             .get("cluster")
             .and_then(|c| c.get("collections"))
-            .and_then(|collections| collections.get(&self.config.collection))
+            .and_then(|collections| collections.get(&self.collection))
             .and_then(|collection| collection.get("docs"))
             .and_then(|docs| docs.as_u64())
             .unwrap_or(0);
         
         Ok(SolrStatus {
-            collection: self.config.collection.clone(),
+            collection: self.collection.clone(),
             doc_count,
             status: "active".to_string(),
         })
@@ -567,4 +739,192 @@ mod tests {
         assert_eq!(doc.file_name, deserialized.file_name);
         assert_eq!(doc.tags, deserialized.tags);
     }
+
+    /// What a [`spawn_solr_stub`] was asked: the handler, the collection in the
+    /// path (`admin` for the Collections API), and the body or query string
+    type Recorded = Arc<Mutex<Vec<(String, String, serde_json::Value)>>>;
+
+    /// Start an HTTP stub for the Collections API and the update and select
+    /// handlers of any collection; `existing` collections are listed as present
+    async fn spawn_solr_stub(existing: &[&str], aliases: &[&str]) -> (String, Recorded) {
+        use axum::{
+            extract::{Path, Query, State},
+            routing::{get, post},
+            Json, Router,
+        };
+
+        #[derive(Clone)]
+        struct Stub {
+            recorded: Recorded,
+            collections: Arc<Mutex<Vec<String>>>,
+            aliases: Vec<String>,
+        }
+
+        let stub = Stub {
+            recorded: Arc::default(),
+            collections: Arc::new(Mutex::new(existing.iter().map(|name| name.to_string()).collect())),
+            aliases: aliases.iter().map(|name| name.to_string()).collect(),
+        };
+        let app = Router::new()
+            .route(
+                "/admin/collections",
+                get(|State(stub): State<Stub>, Query(params): Query<HashMap<String, String>>| async move {
+                    stub.recorded.lock().unwrap().push((params["action"].clone(), "admin".to_string(), serde_json::json!(params)));
+                    let mut collections = stub.collections.lock().unwrap();
+                    Json(match params["action"].as_str() {
+                        "LIST" => serde_json::json!({"collections": *collections}),
+                        "LISTALIASES" => serde_json::json!({
+                            "aliases": stub.aliases.iter().map(|alias| (alias.clone(), "elsewhere".to_string())).collect::<HashMap<_, _>>()
+                        }),
+                        "CREATE" => {
+                            collections.push(params["name"].clone());
+                            serde_json::json!({"success": {}})
+                        }
+                        other => panic!("unexpected action {}", other),
+                    })
+                }),
+            )
+            .route(
+                "/:collection/update",
+                post(|State(stub): State<Stub>, Path(collection): Path<String>, Json(body): Json<serde_json::Value>| async move {
+                    stub.recorded.lock().unwrap().push(("update".to_string(), collection, body));
+                    Json(serde_json::json!({"responseHeader": {"status": 0}}))
+                }),
+            )
+            .route(
+                "/:collection/select",
+                get(|State(stub): State<Stub>, Path(collection): Path<String>, Query(params): Query<HashMap<String, String>>| async move {
+                    stub.recorded.lock().unwrap().push(("select".to_string(), collection, serde_json::json!(params)));
+                    Json(serde_json::json!({"response": {"num_found": 0, "start": 0, "docs": []}}))
+                }),
+            )
+            .with_state(stub.clone());
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        (url, stub.recorded)
+    }
+
+    fn search_all() -> SolrSearchRequest {
+        SolrSearchRequest {
+            q: "*:*".to_string(),
+            fq: vec![],
+            sort: None,
+            start: None,
+            rows: None,
+            facet: None,
+            suggest: None,
+        }
+    }
+
+    fn doc_for(repo_name: &str) -> SolrDocument {
+        entry_to_solr_document(repo_name, "main", "data/a.csv", Uuid::new_v4(), &serde_json::json!({}), "abc123")
+    }
+
+    /// Collections the recorded requests to `handler` went to, in order
+    fn collections_hit(recorded: &Recorded, handler: &str) -> Vec<String> {
+        recorded
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(name, _, _)| name == handler)
+            .map(|(_, collection, _)| collection.clone())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_per_repo_routing_sends_each_repo_to_its_own_collection() {
+        let (url, recorded) = spawn_solr_stub(&["blacklake"], &[]).await;
+        let solr = SolrClient::new(SolrConfig { url, per_repo_collections: true, ..Default::default() });
+        let (soil, climate) = (Uuid::new_v4(), Uuid::new_v4());
+
+        for (repo_id, repo_name) in [(soil, "soil"), (climate, "climate"), (soil, "soil")] {
+            let routed = solr.ensure_repo_collection(repo_id).await.unwrap();
+            routed.index_document(&doc_for(repo_name)).await.unwrap();
+            routed.delete_by_query("id:old*").await.unwrap();
+        }
+        solr.for_repo(climate).search(&search_all()).await.unwrap();
+
+        let (soil_collection, climate_collection) = (format!("blacklake_{}", soil), format!("blacklake_{}", climate));
+        assert_eq!(
+            collections_hit(&recorded, "update"),
+            vec![
+                soil_collection.clone(),
+                soil_collection.clone(),
+                climate_collection.clone(),
+                climate_collection.clone(),
+                soil_collection.clone(),
+                soil_collection.clone(),
+            ]
+        );
+        assert_eq!(collections_hit(&recorded, "select"), vec![climate_collection.clone()]);
+
+        // Each collection was looked up and created once, with the configset
+        let recorded = recorded.lock().unwrap();
+        let created: Vec<&serde_json::Value> = recorded.iter().filter(|(action, _, _)| action == "CREATE").map(|(_, _, params)| params).collect();
+        assert_eq!(created.len(), 2);
+        assert_eq!(created[0]["name"], soil_collection.as_str());
+        assert_eq!(created[0]["collection.configName"], "blacklake");
+        assert_eq!(created[1]["name"], climate_collection.as_str());
+        assert_eq!(recorded.iter().filter(|(action, _, _)| action == "LIST").count(), 2);
+        let adds: Vec<&serde_json::Value> = recorded
+            .iter()
+            .filter(|(_, _, body)| body.get("add").is_some())
+            .map(|(_, _, body)| &body["add"]["doc"]["repo"])
+            .collect();
+        assert_eq!(adds, vec!["soil", "climate", "soil"]);
+    }
+
+    #[tokio::test]
+    async fn test_existing_collection_or_alias_is_not_recreated() {
+        let soil = Uuid::new_v4();
+        let climate = Uuid::new_v4();
+        let (collection, alias) = (format!("blacklake_{}", soil), format!("blacklake_{}", climate));
+        let (url, recorded) = spawn_solr_stub(&[collection.as_str()], &[alias.as_str()]).await;
+        let solr = SolrClient::new(SolrConfig { url, per_repo_collections: true, ..Default::default() });
+
+        solr.ensure_repo_collection(soil).await.unwrap();
+        solr.ensure_repo_collection(climate).await.unwrap();
+
+        assert!(!recorded.lock().unwrap().iter().any(|(action, _, _)| action == "CREATE"));
+    }
+
+    #[tokio::test]
+    async fn test_shared_collection_is_used_when_routing_is_disabled() {
+        let (url, recorded) = spawn_solr_stub(&["blacklake"], &[]).await;
+        let solr = SolrClient::new(SolrConfig { url, ..Default::default() });
+        let (soil, climate) = (Uuid::new_v4(), Uuid::new_v4());
+
+        assert_eq!(solr.collection_for(soil), "blacklake");
+        solr.ensure_repo_collection(soil).await.unwrap().index_document(&doc_for("soil")).await.unwrap();
+        solr.ensure_repo_collection(climate).await.unwrap().commit().await.unwrap();
+        solr.for_repo(soil).search(&search_all()).await.unwrap();
+
+        assert_eq!(collections_hit(&recorded, "update"), vec!["blacklake", "blacklake"]);
+        assert_eq!(collections_hit(&recorded, "select"), vec!["blacklake"]);
+        // The shared collection is never looked up or created
+        assert!(!recorded.lock().unwrap().iter().any(|(_, collection, _)| collection == "admin"));
+    }
+
+    #[test]
+    fn test_solr_config_from_lookup() {
+        let vars: HashMap<&str, &str> = [
+            ("SOLR_URL", "http://solr:8983/solr/"),
+            ("SOLR_PER_REPO_COLLECTIONS", "true"),
+            ("SOLR_NUM_SHARDS", "3"),
+            ("SOLR_REPLICATION_FACTOR", "zero"),
+        ]
+        .into_iter()
+        .collect();
+        let config = SolrConfig::from_lookup(|name| vars.get(name).map(|value| value.to_string()));
+
+        assert_eq!(config.url, "http://solr:8983/solr");
+        assert_eq!(config.collection, "blacklake");
+        assert!(config.per_repo_collections);
+        assert_eq!(config.num_shards, 3);
+        assert_eq!(config.replication_factor, 1);
+        assert!(!SolrConfig::from_lookup(|_| None).per_repo_collections);
+    }
 }
\ No newline at end of file
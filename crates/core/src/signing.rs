@@ -0,0 +1,373 @@
+//! Detached signatures for uploaded model files.
+//!
+//! A client signs a file with a cosign key (`cosign sign-blob --bundle`) and
+//! sends the bundle when it verifies the upload. The bundle is stored next to
+//! the object as `<content address>.sig`. Verification checks the ECDSA P-256
+//! signature over the file's sha256 against the public keys listed in
+//! `COSIGN_PUBLIC_KEYS`. Keyless bundles carry a certificate whose chain is
+//! not checked here, so they only verify against a configured key.
+
+use aws_sdk_s3::primitives::ByteStream;
+use base64::Engine;
+use p256::ecdsa::signature::hazmat::PrehashVerifier;
+use p256::ecdsa::{Signature, VerifyingKey};
+use p256::pkcs8::DecodePublicKey;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use thiserror::Error;
+
+use crate::{Change, ChangeOp};
+
+/// Extensions of the model formats signatures are enforced for
+const MODEL_EXTENSIONS: &[&str] = &["onnx", "pt", "pth", "safetensors", "gguf", "ckpt", "h5", "keras", "pb", "tflite"];
+
+/// Media types recorded for sniffed model files
+const MODEL_MEDIA_TYPES: &[&str] = &[
+    "application/x-onnx",
+    "application/x-pytorch",
+    "application/x-safetensors",
+    "application/x-gguf",
+];
+
+#[derive(Debug, Error)]
+pub enum SigningError {
+    #[error("Invalid public key '{name}': {reason}")]
+    InvalidKey { name: String, reason: String },
+    #[error("Storage error: {0}")]
+    Storage(String),
+    #[error("Stored signature for {sha256} is unreadable: {reason}")]
+    CorruptBundle { sha256: String, reason: String },
+}
+
+/// A cosign `sign-blob --bundle` document
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SignatureBundle {
+    /// Base64 of the DER-encoded ECDSA signature
+    pub base64_signature: String,
+    /// Signing certificate of a keyless bundle; stored but not trusted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cert: Option<String>,
+    /// Transparency log entry; stored but not checked
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rekor_bundle: Option<Value>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SignatureStatus {
+    /// Signed by a trusted key
+    Verified,
+    /// A signature is attached but no trusted key accepts it
+    Invalid,
+    /// No signature is attached
+    Unsigned,
+}
+
+/// Outcome of checking a file's signature, as recorded in entry metadata
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct SignatureVerification {
+    pub status: SignatureStatus,
+    /// Name of the trusted key that verified the signature
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signer: Option<String>,
+    /// Why an attached signature was not accepted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl SignatureVerification {
+    fn invalid(reason: impl Into<String>) -> Self {
+        Self { status: SignatureStatus::Invalid, signer: None, reason: Some(reason.into()) }
+    }
+
+    fn unsigned() -> Self {
+        Self { status: SignatureStatus::Unsigned, signer: None, reason: None }
+    }
+}
+
+/// Public keys whose signatures are trusted, by name
+#[derive(Debug, Clone, Default)]
+pub struct TrustedKeys {
+    keys: Vec<(String, VerifyingKey)>,
+}
+
+impl TrustedKeys {
+    /// Load the PEM files listed, comma-separated, in `COSIGN_PUBLIC_KEYS`;
+    /// each key is named after its file stem
+    pub fn from_env() -> Result<Self, SigningError> {
+        let mut keys = Self::default();
+        let paths = std::env::var("COSIGN_PUBLIC_KEYS").unwrap_or_default();
+        for path in paths.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let path = std::path::Path::new(path);
+            let name = path.file_stem().and_then(|s| s.to_str()).unwrap_or("cosign").to_string();
+            let pem = std::fs::read_to_string(path).map_err(|e| SigningError::InvalidKey {
+                name: name.clone(),
+                reason: e.to_string(),
+            })?;
+            keys.add_pem(&name, &pem)?;
+        }
+        Ok(keys)
+    }
+
+    /// Trust a `-----BEGIN PUBLIC KEY-----` P-256 key, as written by `cosign generate-key-pair`
+    pub fn add_pem(&mut self, name: &str, pem: &str) -> Result<(), SigningError> {
+        let key = VerifyingKey::from_public_key_pem(pem).map_err(|e| SigningError::InvalidKey {
+            name: name.to_string(),
+            reason: e.to_string(),
+        })?;
+        self.keys.push((name.to_string(), key));
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Check `bundle` as a signature over the file with hex digest `sha256`
+    pub fn verify(&self, sha256: &str, bundle: &SignatureBundle) -> SignatureVerification {
+        let digest = match hex::decode(sha256) {
+            Ok(digest) if digest.len() == 32 => digest,
+            _ => return SignatureVerification::invalid(format!("'{}' is not a sha256 digest", sha256)),
+        };
+        let bytes = match base64::engine::general_purpose::STANDARD.decode(bundle.base64_signature.trim()) {
+            Ok(bytes) => bytes,
+            Err(e) => return SignatureVerification::invalid(format!("signature is not base64: {}", e)),
+        };
+        let signature = match Signature::from_der(&bytes).or_else(|_| Signature::from_slice(&bytes)) {
+            // Go's ECDSA may emit high-S signatures, which are equally valid
+            Ok(signature) => signature.normalize_s().unwrap_or(signature),
+            Err(_) => return SignatureVerification::invalid("signature is not an ECDSA P-256 signature"),
+        };
+        if self.keys.is_empty() {
+            return SignatureVerification::invalid("no trusted keys are configured");
+        }
+
+        match self.keys.iter().find(|(_, key)| key.verify_prehash(&digest, &signature).is_ok()) {
+            Some((name, _)) => SignatureVerification {
+                status: SignatureStatus::Verified,
+                signer: Some(name.clone()),
+                reason: None,
+            },
+            None => SignatureVerification::invalid("signature does not match any trusted key"),
+        }
+    }
+}
+
+/// Key of the signature bundle stored next to the object with digest `sha256`
+pub fn signature_key(sha256: &str) -> String {
+    format!("{}.sig", blacklake_storage::StorageClient::content_address_key(sha256))
+}
+
+/// Whether `path`, or the media type sniffed for it, names a model file
+pub fn is_model_artifact(path: &str, media_type: Option<&str>) -> bool {
+    let extension = path.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase());
+    extension.is_some_and(|ext| MODEL_EXTENSIONS.contains(&ext.as_str()))
+        || media_type.is_some_and(|media_type| MODEL_MEDIA_TYPES.contains(&media_type))
+}
+
+/// Stores signature bundles next to their objects and verifies them
+#[derive(Clone)]
+pub struct SignatureVerifier {
+    client: aws_sdk_s3::Client,
+    bucket: String,
+    keys: Arc<TrustedKeys>,
+}
+
+impl SignatureVerifier {
+    pub fn new(client: aws_sdk_s3::Client, bucket: impl Into<String>, keys: TrustedKeys) -> Self {
+        Self { client, bucket: bucket.into(), keys: Arc::new(keys) }
+    }
+
+    /// Store `bundle` as the signature of the object with digest `sha256`,
+    /// replacing any earlier one
+    pub async fn store_signature(&self, sha256: &str, bundle: &SignatureBundle) -> Result<(), SigningError> {
+        let body = serde_json::to_vec(bundle).map_err(|e| SigningError::Storage(e.to_string()))?;
+        self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(signature_key(sha256))
+            .content_type("application/json")
+            .body(ByteStream::from(body))
+            .send()
+            .await
+            .map_err(|e| SigningError::Storage(e.to_string()))?;
+        Ok(())
+    }
+
+    /// The stored signature of the object with digest `sha256`, if any
+    pub async fn load_signature(&self, sha256: &str) -> Result<Option<SignatureBundle>, SigningError> {
+        let output = match self.client.get_object().bucket(&self.bucket).key(signature_key(sha256)).send().await {
+            Ok(output) => output,
+            Err(e) if e.raw_response().is_some_and(|r| r.status().as_u16() == 404) => return Ok(None),
+            Err(e) => return Err(SigningError::Storage(e.to_string())),
+        };
+        let body = output
+            .body
+            .collect()
+            .await
+            .map_err(|e| SigningError::Storage(e.to_string()))?
+            .into_bytes();
+        serde_json::from_slice(&body).map(Some).map_err(|e| SigningError::CorruptBundle {
+            sha256: sha256.to_string(),
+            reason: e.to_string(),
+        })
+    }
+
+    /// Check `bundle` as the signature of the object with digest `sha256`
+    pub fn verify_bundle(&self, sha256: &str, bundle: &SignatureBundle) -> SignatureVerification {
+        self.keys.verify(sha256, bundle)
+    }
+
+    /// Check the stored signature of the object with digest `sha256`
+    pub async fn verify_signature(&self, sha256: &str) -> Result<SignatureVerification, SigningError> {
+        Ok(match self.load_signature(sha256).await? {
+            Some(bundle) => self.verify_bundle(sha256, &bundle),
+            None => SignatureVerification::unsigned(),
+        })
+    }
+
+    /// Record the signature status of each model file `changes` add or modify
+    /// under the `signature` metadata key.
+    ///
+    /// `media_types` maps object digests to their sniffed media types. Returns
+    /// the reasons to refuse the commit, which are only collected when
+    /// `require_signed` is on.
+    pub async fn check_changes(
+        &self,
+        changes: &mut [Change],
+        media_types: &HashMap<String, String>,
+        require_signed: bool,
+    ) -> Result<Vec<String>, SigningError> {
+        let mut blocking_reasons = Vec::new();
+        for change in changes.iter_mut() {
+            let (ChangeOp::Add | ChangeOp::Modify, Some(sha256)) = (&change.op, &change.sha256) else {
+                continue;
+            };
+            if !is_model_artifact(&change.path, media_types.get(sha256).map(String::as_str)) {
+                continue;
+            }
+
+            let verification = self.verify_signature(sha256).await?;
+            if require_signed && verification.status != SignatureStatus::Verified {
+                blocking_reasons.push(match &verification.reason {
+                    Some(reason) => format!("Model file '{}' has an invalid signature: {}", change.path, reason),
+                    None => format!("Model file '{}' is not signed", change.path),
+                });
+            }
+            if let Value::Object(meta) = &mut change.meta {
+                meta.insert("signature".to_string(), json!(verification));
+            }
+        }
+        Ok(blocking_reasons)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::s3_mock::MockS3;
+    use p256::ecdsa::signature::hazmat::PrehashSigner;
+    use p256::ecdsa::SigningKey;
+    use p256::pkcs8::{EncodePublicKey, LineEnding};
+
+    const SHA256: &str = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+
+    fn signing_key(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32].into()).unwrap()
+    }
+
+    fn sign(key: &SigningKey, sha256: &str) -> SignatureBundle {
+        let signature: Signature = key.sign_prehash(&hex::decode(sha256).unwrap()).unwrap();
+        SignatureBundle {
+            base64_signature: base64::engine::general_purpose::STANDARD.encode(signature.to_der().as_bytes()),
+            cert: None,
+            rekor_bundle: None,
+        }
+    }
+
+    fn trusting(key: &SigningKey) -> TrustedKeys {
+        let pem = key.verifying_key().to_public_key_pem(LineEnding::LF).unwrap();
+        let mut keys = TrustedKeys::default();
+        keys.add_pem("release", &pem).unwrap();
+        keys
+    }
+
+    fn model_change(path: &str) -> Change {
+        Change {
+            op: ChangeOp::Add,
+            path: path.to_string(),
+            sha256: Some(SHA256.to_string()),
+            meta: json!({"name": "model"}),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_valid_signature_is_verified_and_recorded() {
+        let s3 = MockS3::default();
+        let key = signing_key(7);
+        let verifier = SignatureVerifier::new(s3.client().await, "bucket", trusting(&key));
+        verifier.store_signature(SHA256, &sign(&key, SHA256)).await.unwrap();
+
+        let verification = verifier.verify_signature(SHA256).await.unwrap();
+        assert_eq!(verification.status, SignatureStatus::Verified);
+        assert_eq!(verification.signer.as_deref(), Some("release"));
+        assert!(s3.objects.lock().unwrap().contains_key(&format!("bucket/{}", signature_key(SHA256))));
+
+        let mut changes = vec![model_change("models/net.onnx")];
+        let blocking = verifier.check_changes(&mut changes, &HashMap::new(), true).await.unwrap();
+        assert!(blocking.is_empty());
+        assert_eq!(changes[0].meta["signature"]["status"], "verified");
+    }
+
+    #[tokio::test]
+    async fn test_signature_from_untrusted_key_is_invalid() {
+        let s3 = MockS3::default();
+        let verifier = SignatureVerifier::new(s3.client().await, "bucket", trusting(&signing_key(7)));
+        verifier.store_signature(SHA256, &sign(&signing_key(9), SHA256)).await.unwrap();
+
+        let verification = verifier.verify_signature(SHA256).await.unwrap();
+        assert_eq!(verification.status, SignatureStatus::Invalid);
+        assert!(verification.reason.unwrap().contains("trusted key"));
+
+        let mut changes = vec![model_change("models/net.safetensors")];
+        let blocking = verifier.check_changes(&mut changes, &HashMap::new(), true).await.unwrap();
+        assert_eq!(blocking.len(), 1);
+        assert_eq!(changes[0].meta["signature"]["status"], "invalid");
+    }
+
+    #[tokio::test]
+    async fn test_unsigned_model_blocks_only_under_enforcement() {
+        let s3 = MockS3::default();
+        let verifier = SignatureVerifier::new(s3.client().await, "bucket", trusting(&signing_key(7)));
+
+        assert_eq!(verifier.verify_signature(SHA256).await.unwrap().status, SignatureStatus::Unsigned);
+
+        // Sniffed as a model despite its name; the CSV is not checked at all
+        let media_types = HashMap::from([(SHA256.to_string(), "application/x-gguf".to_string())]);
+        let mut csv = model_change("data/a.csv");
+        csv.sha256 = Some("e".repeat(64));
+        let mut changes = vec![model_change("weights/latest"), csv];
+        let blocking = verifier.check_changes(&mut changes, &media_types, true).await.unwrap();
+        assert_eq!(blocking, vec!["Model file 'weights/latest' is not signed".to_string()]);
+        assert_eq!(changes[0].meta["signature"]["status"], "unsigned");
+        assert!(changes[1].meta.get("signature").is_none());
+
+        let mut changes = vec![model_change("models/net.pt")];
+        let blocking = verifier.check_changes(&mut changes, &HashMap::new(), false).await.unwrap();
+        assert!(blocking.is_empty());
+        assert_eq!(changes[0].meta["signature"]["status"], "unsigned");
+    }
+
+    #[test]
+    fn test_malformed_signature_is_invalid() {
+        let keys = trusting(&signing_key(7));
+        let bundle = SignatureBundle { base64_signature: "not base64!".to_string(), cert: None, rekor_bundle: None };
+
+        assert_eq!(keys.verify(SHA256, &bundle).status, SignatureStatus::Invalid);
+        assert_eq!(keys.verify("abc", &sign(&signing_key(7), SHA256)).status, SignatureStatus::Invalid);
+    }
+}
@@ -0,0 +1,283 @@
+//! Media type detection from an object's leading bytes.
+//!
+//! Clients often declare `application/octet-stream` for everything. After a
+//! commit, the sniff job reads the first `SNIFF_PREFIX_BYTES` of each new
+//! object and, when a signature matches, records the detected type on the
+//! object and as the entry's `file_type`. Inconclusive bytes leave the
+//! declared type alone.
+
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::jobs::{JobError, SniffMediaTypeJob};
+
+/// How much of an object is read for detection
+pub const SNIFF_PREFIX_BYTES: usize = 8 * 1024;
+
+/// Declared types that say nothing about the content
+const GENERIC_MEDIA_TYPES: &[&str] = &["application/octet-stream", "binary/octet-stream", "application/unknown"];
+
+/// A magic number at a fixed offset
+struct Signature {
+    offset: usize,
+    magic: &'static [u8],
+    media_type: &'static str,
+    /// Archives and compressed streams wrap many more specific formats
+    /// (OOXML is a ZIP, netCDF-4 is HDF5), so they only replace generic types
+    container: bool,
+}
+
+const SIGNATURES: &[Signature] = &[
+    Signature { offset: 0, magic: b"\x89PNG\r\n\x1a\n", media_type: "image/png", container: false },
+    Signature { offset: 0, magic: b"\xFF\xD8\xFF", media_type: "image/jpeg", container: false },
+    Signature { offset: 0, magic: b"GIF87a", media_type: "image/gif", container: false },
+    Signature { offset: 0, magic: b"GIF89a", media_type: "image/gif", container: false },
+    Signature { offset: 0, magic: b"II*\x00", media_type: "image/tiff", container: false },
+    Signature { offset: 0, magic: b"MM\x00*", media_type: "image/tiff", container: false },
+    Signature { offset: 0, magic: b"%PDF-", media_type: "application/pdf", container: false },
+    Signature { offset: 0, magic: b"PAR1", media_type: "application/vnd.apache.parquet", container: false },
+    Signature { offset: 0, magic: b"ARROW1", media_type: "application/vnd.apache.arrow.file", container: false },
+    Signature { offset: 0, magic: b"CDF\x01", media_type: "application/x-netcdf", container: false },
+    Signature { offset: 0, magic: b"CDF\x02", media_type: "application/x-netcdf", container: false },
+    Signature { offset: 0, magic: b"SIMPLE  =", media_type: "application/fits", container: false },
+    Signature { offset: 0, magic: b"\x89HDF\r\n\x1a\n", media_type: "application/x-hdf5", container: true },
+    Signature { offset: 0, magic: b"PK\x03\x04", media_type: "application/zip", container: true },
+    Signature { offset: 0, magic: b"\x1F\x8B", media_type: "application/gzip", container: true },
+    Signature { offset: 0, magic: b"\x28\xB5\x2F\xFD", media_type: "application/zstd", container: true },
+    Signature { offset: 0, magic: b"BZh", media_type: "application/x-bzip2", container: true },
+    Signature { offset: 0, magic: b"\xFD7zXZ\x00", media_type: "application/x-xz", container: true },
+    Signature { offset: 0, magic: b"7z\xBC\xAF\x27\x1C", media_type: "application/x-7z-compressed", container: true },
+    Signature { offset: 257, magic: b"ustar", media_type: "application/x-tar", container: true },
+];
+
+/// A media type detected from content
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SniffedType {
+    pub media_type: &'static str,
+    pub container: bool,
+}
+
+/// Detect a media type from the start of an object, or `None` if no
+/// signature matches. Model formats are checked first, since a PyTorch
+/// archive is also a ZIP.
+pub fn sniff_media_type(prefix: &[u8]) -> Option<SniffedType> {
+    if let Some(format) = blacklake_modelx::detect_model_format(prefix) {
+        return Some(SniffedType { media_type: format.media_type(), container: false });
+    }
+
+    SIGNATURES
+        .iter()
+        .find(|sig| prefix.get(sig.offset..sig.offset + sig.magic.len()) == Some(sig.magic))
+        .map(|sig| SniffedType { media_type: sig.media_type, container: sig.container })
+}
+
+/// Whether a declared type carries no information about the content
+pub fn is_generic_media_type(media_type: &str) -> bool {
+    let essence = media_type.split(';').next().unwrap_or_default().trim();
+    essence.is_empty() || GENERIC_MEDIA_TYPES.iter().any(|generic| essence.eq_ignore_ascii_case(generic))
+}
+
+/// The type an object should carry instead of `declared`, if detection is
+/// confident enough to overrule it. Container formats only replace a
+/// missing or generic declaration.
+pub fn corrected_media_type(declared: Option<&str>, prefix: &[u8]) -> Option<&'static str> {
+    let sniffed = sniff_media_type(prefix)?;
+    match declared {
+        Some(declared) if declared.eq_ignore_ascii_case(sniffed.media_type) => None,
+        Some(declared) if sniffed.container && !is_generic_media_type(declared) => None,
+        _ => Some(sniffed.media_type),
+    }
+}
+
+/// Stored object state the sniff job reads and corrects
+#[async_trait::async_trait]
+pub trait MediaTypeStore: Send + Sync {
+    /// S3 key and declared media type of an object, if it exists
+    async fn object(&self, sha256: &str) -> Result<Option<(String, Option<String>)>, JobError>;
+
+    /// Record `media_type` on the object and as the entry's `file_type`
+    async fn set_media_type(&self, sha256: &str, commit_id: Uuid, path: &str, media_type: &str) -> Result<(), JobError>;
+}
+
+/// Media type store backed by the `object`, `entry` and `entry_meta_index` tables
+pub struct PgMediaTypeStore {
+    pool: PgPool,
+}
+
+impl PgMediaTypeStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl MediaTypeStore for PgMediaTypeStore {
+    async fn object(&self, sha256: &str) -> Result<Option<(String, Option<String>)>, JobError> {
+        sqlx::query_as("SELECT s3_key, media_type FROM object WHERE sha256 = $1")
+            .bind(sha256)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| JobError::Storage(format!("Failed to look up object {}: {}", sha256, e)))
+    }
+
+    async fn set_media_type(&self, sha256: &str, commit_id: Uuid, path: &str, media_type: &str) -> Result<(), JobError> {
+        let storage_error = |e: sqlx::Error| JobError::Storage(format!("Failed to record media type for {}: {}", path, e));
+        let mut tx = self.pool.begin().await.map_err(storage_error)?;
+
+        sqlx::query("UPDATE object SET media_type = $2 WHERE sha256 = $1")
+            .bind(sha256)
+            .bind(media_type)
+            .execute(&mut *tx)
+            .await
+            .map_err(storage_error)?;
+        sqlx::query(
+            "UPDATE entry SET meta = jsonb_set(meta, '{file_type}', to_jsonb($3::text))
+             WHERE commit_id = $1 AND path = $2",
+        )
+        .bind(commit_id)
+        .bind(path)
+        .bind(media_type)
+        .execute(&mut *tx)
+        .await
+        .map_err(storage_error)?;
+        sqlx::query("UPDATE entry_meta_index SET file_type = $3 WHERE commit_id = $1 AND path = $2")
+            .bind(commit_id)
+            .bind(path)
+            .bind(media_type)
+            .execute(&mut *tx)
+            .await
+            .map_err(storage_error)?;
+
+        tx.commit().await.map_err(storage_error)
+    }
+}
+
+/// Sniff a committed entry's object and correct its media type.
+///
+/// Returns the media type recorded, or `None` when the object is gone or
+/// detection did not overrule the declared type.
+pub async fn sniff_entry(
+    job: &SniffMediaTypeJob,
+    s3_client: &aws_sdk_s3::Client,
+    bucket: &str,
+    store: &dyn MediaTypeStore,
+) -> Result<Option<&'static str>, JobError> {
+    let Some((s3_key, declared)) = store.object(&job.sha256).await? else {
+        return Ok(None);
+    };
+
+    let output = s3_client
+        .get_object()
+        .bucket(bucket)
+        .key(&s3_key)
+        .range(format!("bytes=0-{}", SNIFF_PREFIX_BYTES - 1))
+        .send()
+        .await
+        .map_err(|e| JobError::Storage(format!("Failed to read {}: {}", s3_key, e)))?;
+    let body = output.body.collect().await?.into_bytes();
+    let prefix = &body[..body.len().min(SNIFF_PREFIX_BYTES)];
+
+    let Some(media_type) = corrected_media_type(declared.as_deref(), prefix) else {
+        return Ok(None);
+    };
+    store.set_media_type(&job.sha256, job.commit_id, &job.path, media_type).await?;
+    Ok(Some(media_type))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::s3_mock::MockS3;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR\x00\x00\x00\x01\x00\x00\x00\x01\x08\x06\x00\x00\x00";
+
+    /// Objects and recorded corrections held in memory
+    #[derive(Default)]
+    struct MemoryStore {
+        objects: HashMap<String, (String, Option<String>)>,
+        updates: Mutex<Vec<(String, Uuid, String, String)>>,
+    }
+
+    #[async_trait::async_trait]
+    impl MediaTypeStore for MemoryStore {
+        async fn object(&self, sha256: &str) -> Result<Option<(String, Option<String>)>, JobError> {
+            Ok(self.objects.get(sha256).cloned())
+        }
+
+        async fn set_media_type(&self, sha256: &str, commit_id: Uuid, path: &str, media_type: &str) -> Result<(), JobError> {
+            self.updates.lock().unwrap().push((sha256.to_string(), commit_id, path.to_string(), media_type.to_string()));
+            Ok(())
+        }
+    }
+
+    async fn sniff(data: &[u8], declared: Option<&str>) -> (Option<&'static str>, Vec<(String, Uuid, String, String)>) {
+        let s3 = MockS3::default();
+        s3.put("blacklake/sha256/ab/cd/abcd", data);
+        let mut store = MemoryStore::default();
+        store
+            .objects
+            .insert("abcd".to_string(), ("sha256/ab/cd/abcd".to_string(), declared.map(|d| d.to_string())));
+        let job = SniffMediaTypeJob { commit_id: Uuid::new_v4(), path: "images/logo".to_string(), sha256: "abcd".to_string() };
+
+        let result = sniff_entry(&job, &s3.client().await, "blacklake", &store).await.unwrap();
+        let updates = store.updates.lock().unwrap().clone();
+        (result, updates)
+    }
+
+    #[tokio::test]
+    async fn test_png_declared_as_octet_stream_is_corrected() {
+        let (result, updates) = sniff(PNG, Some("application/octet-stream")).await;
+
+        assert_eq!(result, Some("image/png"));
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].0, "abcd");
+        assert_eq!(updates[0].2, "images/logo");
+        assert_eq!(updates[0].3, "image/png");
+    }
+
+    #[tokio::test]
+    async fn test_ambiguous_blob_keeps_declared_type() {
+        let (result, updates) = sniff(b"day,temp\n1,12.5\n2,13.1\n", Some("application/octet-stream")).await;
+
+        assert_eq!(result, None);
+        assert!(updates.is_empty());
+    }
+
+    #[test]
+    fn test_sniff_media_type_signatures() {
+        assert_eq!(sniff_media_type(PNG).unwrap().media_type, "image/png");
+        assert_eq!(sniff_media_type(b"%PDF-1.7\n").unwrap().media_type, "application/pdf");
+        assert_eq!(sniff_media_type(b"PAR1\x15\x04").unwrap().media_type, "application/vnd.apache.parquet");
+        assert_eq!(sniff_media_type(b"GGUF\x03\x00\x00\x00").unwrap().media_type, "application/x-gguf");
+
+        let mut tar = vec![0u8; 512];
+        tar[257..262].copy_from_slice(b"ustar");
+        assert_eq!(sniff_media_type(&tar).unwrap().media_type, "application/x-tar");
+
+        assert_eq!(sniff_media_type(b"plain text"), None);
+        assert_eq!(sniff_media_type(&[]), None);
+    }
+
+    #[test]
+    fn test_containers_only_replace_generic_declarations() {
+        let hdf5 = b"\x89HDF\r\n\x1a\n\x00\x00";
+        assert_eq!(corrected_media_type(Some("application/octet-stream"), hdf5), Some("application/x-hdf5"));
+        assert_eq!(corrected_media_type(None, hdf5), Some("application/x-hdf5"));
+        // netCDF-4 files are HDF5 underneath
+        assert_eq!(corrected_media_type(Some("application/x-netcdf"), hdf5), None);
+
+        // A specific format overrules a wrong specific declaration
+        assert_eq!(corrected_media_type(Some("image/jpeg"), PNG), Some("image/png"));
+        assert_eq!(corrected_media_type(Some("image/png"), PNG), None);
+    }
+
+    #[test]
+    fn test_is_generic_media_type() {
+        assert!(is_generic_media_type("application/octet-stream"));
+        assert!(is_generic_media_type("Application/Octet-Stream; charset=binary"));
+        assert!(is_generic_media_type(""));
+        assert!(!is_generic_media_type("text/csv"));
+    }
+}
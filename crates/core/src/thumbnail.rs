@@ -0,0 +1,303 @@
+//! Preview images for committed pictures and PDFs.
+//!
+//! After a commit, the thumbnail job renders a PNG no larger than
+//! `THUMBNAIL_MAX_DIMENSION` on either side for each new PNG, JPEG, WebP or
+//! TIFF object. For a PDF the largest image embedded in the first page is
+//! used, since pages themselves are not rasterised. Other types, and
+//! sources over `MAX_THUMBNAIL_SOURCE_BYTES`, are skipped. The preview is
+//! stored as a derived artifact of the source entry.
+
+use image::{DynamicImage, GrayImage, ImageFormat, RgbImage};
+use std::io::{Cursor, Read};
+
+use crate::derived::{read_object, store_derived, DerivedArtifact, DerivedArtifactStore};
+use crate::jobs::{JobError, ThumbnailJob};
+
+/// Longest side of a generated preview
+pub const THUMBNAIL_MAX_DIMENSION: u32 = 256;
+
+/// Larger sources are not previewed, since decoding is done in memory
+pub const MAX_THUMBNAIL_SOURCE_BYTES: i64 = 50 * 1024 * 1024;
+
+/// Media type of every generated preview
+pub const THUMBNAIL_MEDIA_TYPE: &str = "image/png";
+
+/// `derived_artifact.kind` for previews
+pub const THUMBNAIL_ARTIFACT_KIND: &str = "thumbnail";
+
+/// What a preview can be rendered from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreviewSource {
+    Image(ImageFormat),
+    Pdf,
+}
+
+/// A rendered preview
+#[derive(Debug, Clone)]
+pub struct Thumbnail {
+    pub bytes: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Pick a decoder from the declared media type, falling back to the path's
+/// extension when the type is missing or generic
+pub fn preview_source(media_type: Option<&str>, path: &str) -> Option<PreviewSource> {
+    let essence = media_type.and_then(|t| t.split(';').next()).unwrap_or_default().trim().to_ascii_lowercase();
+    let by_type = match essence.as_str() {
+        "image/png" => Some(PreviewSource::Image(ImageFormat::Png)),
+        "image/jpeg" | "image/jpg" => Some(PreviewSource::Image(ImageFormat::Jpeg)),
+        "image/webp" => Some(PreviewSource::Image(ImageFormat::WebP)),
+        "image/tiff" => Some(PreviewSource::Image(ImageFormat::Tiff)),
+        "application/pdf" => Some(PreviewSource::Pdf),
+        _ => None,
+    };
+    if by_type.is_some() || !crate::sniff::is_generic_media_type(&essence) {
+        return by_type;
+    }
+
+    let extension = path.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase())?;
+    match extension.as_str() {
+        "png" => Some(PreviewSource::Image(ImageFormat::Png)),
+        "jpg" | "jpeg" => Some(PreviewSource::Image(ImageFormat::Jpeg)),
+        "webp" => Some(PreviewSource::Image(ImageFormat::WebP)),
+        "tif" | "tiff" => Some(PreviewSource::Image(ImageFormat::Tiff)),
+        "pdf" => Some(PreviewSource::Pdf),
+        _ => None,
+    }
+}
+
+/// Decode an image XObject, if it uses an encoding we understand
+fn decode_pdf_image(image: &lopdf::xobject::PdfImage) -> Option<DynamicImage> {
+    let filters = image.filters.as_deref().unwrap_or_default();
+    if filters.iter().any(|f| f == "DCTDecode") {
+        return image::load_from_memory_with_format(image.content, ImageFormat::Jpeg).ok();
+    }
+
+    let pixels = match filters {
+        [] => image.content.to_vec(),
+        [filter] if filter == "FlateDecode" => {
+            let mut pixels = Vec::new();
+            flate2::read::ZlibDecoder::new(image.content).read_to_end(&mut pixels).ok()?;
+            pixels
+        }
+        _ => return None,
+    };
+    if image.bits_per_component != Some(8) {
+        return None;
+    }
+    let (width, height) = (u32::try_from(image.width).ok()?, u32::try_from(image.height).ok()?);
+    match image.color_space.as_deref() {
+        Some("DeviceRGB") => RgbImage::from_raw(width, height, pixels).map(DynamicImage::ImageRgb8),
+        Some("DeviceGray") => GrayImage::from_raw(width, height, pixels).map(DynamicImage::ImageLuma8),
+        _ => None,
+    }
+}
+
+/// The largest decodable image on a PDF's first page
+pub fn first_page_image(data: &[u8]) -> Result<Option<DynamicImage>, JobError> {
+    let document =
+        lopdf::Document::load_mem(data).map_err(|e| JobError::Processing(format!("Failed to read PDF: {}", e)))?;
+    let Some(page_id) = document.get_pages().values().next().copied() else {
+        return Ok(None);
+    };
+
+    // Pages without an XObject dictionary have no images
+    let mut images = document.get_page_images(page_id).unwrap_or_default();
+    images.sort_by_key(|image| std::cmp::Reverse(image.width.saturating_mul(image.height)));
+    Ok(images.iter().find_map(decode_pdf_image))
+}
+
+/// Render a preview, or `None` for a PDF with no usable first-page image
+pub fn render_thumbnail(data: &[u8], source: PreviewSource) -> Result<Option<Thumbnail>, JobError> {
+    let decoded = match source {
+        PreviewSource::Image(format) => image::load_from_memory_with_format(data, format)
+            .map_err(|e| JobError::Processing(format!("Failed to decode image: {}", e)))?,
+        PreviewSource::Pdf => match first_page_image(data)? {
+            Some(decoded) => decoded,
+            None => return Ok(None),
+        },
+    };
+
+    // Never enlarge small images
+    let preview = if decoded.width() > THUMBNAIL_MAX_DIMENSION || decoded.height() > THUMBNAIL_MAX_DIMENSION {
+        decoded.thumbnail(THUMBNAIL_MAX_DIMENSION, THUMBNAIL_MAX_DIMENSION)
+    } else {
+        decoded
+    };
+    let preview = DynamicImage::ImageRgba8(preview.to_rgba8());
+
+    let mut bytes = Vec::new();
+    preview
+        .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+        .map_err(|e| JobError::Processing(format!("Failed to encode thumbnail: {}", e)))?;
+    Ok(Some(Thumbnail { bytes, width: preview.width(), height: preview.height() }))
+}
+
+/// Render and store a preview for a committed entry.
+///
+/// Returns the recorded link, or `None` when the object is gone, too large,
+/// of an unsupported type, or a PDF without a usable first-page image.
+pub async fn generate_thumbnail(
+    job: &ThumbnailJob,
+    s3_client: &aws_sdk_s3::Client,
+    bucket: &str,
+    store: &dyn DerivedArtifactStore,
+) -> Result<Option<DerivedArtifact>, JobError> {
+    let Some(object) = store.object(&job.sha256).await? else {
+        return Ok(None);
+    };
+    let Some(source) = preview_source(object.media_type.as_deref(), &job.path) else {
+        return Ok(None);
+    };
+    if object.size > MAX_THUMBNAIL_SOURCE_BYTES {
+        tracing::info!("Skipping thumbnail for {}: {} bytes exceeds limit", job.path, object.size);
+        return Ok(None);
+    }
+
+    let data = read_object(s3_client, bucket, &object.s3_key).await?;
+    let Some(thumbnail) = render_thumbnail(&data, source)? else {
+        return Ok(None);
+    };
+    let artifact = store_derived(
+        s3_client,
+        bucket,
+        store,
+        job.commit_id,
+        &job.path,
+        &job.sha256,
+        THUMBNAIL_ARTIFACT_KIND,
+        THUMBNAIL_MEDIA_TYPE,
+        thumbnail.bytes,
+        |_| Ok(()),
+    )
+    .await?;
+    Ok(Some(artifact))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::derived::tests::MemoryStore;
+    use crate::s3_mock::MockS3;
+    use image::GenericImageView;
+    use uuid::Uuid;
+
+    const CHECKERBOARD: &[u8] = include_bytes!("../tests/fixtures/checkerboard-400x300.png");
+
+    fn job(path: &str) -> ThumbnailJob {
+        ThumbnailJob { commit_id: Uuid::new_v4(), path: path.to_string(), sha256: "abcd".to_string() }
+    }
+
+    /// One-page PDF whose only resource is `jpeg` as a DCT-encoded image
+    fn pdf_with_jpeg(jpeg: Vec<u8>, width: i64, height: i64) -> Vec<u8> {
+        use lopdf::{dictionary, Document, Object, Stream};
+
+        let mut document = Document::with_version("1.5");
+        let pages_id = document.new_object_id();
+        let image_id = document.add_object(Stream::new(
+            dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Image",
+                "Width" => width,
+                "Height" => height,
+                "ColorSpace" => "DeviceRGB",
+                "BitsPerComponent" => 8,
+                "Filter" => "DCTDecode",
+            },
+            jpeg,
+        ));
+        let page_id = document.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+            "Resources" => dictionary! { "XObject" => dictionary! { "Im1" => image_id } },
+        });
+        document.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! { "Type" => "Pages", "Kids" => vec![page_id.into()], "Count" => 1 }),
+        );
+        let catalog_id = document.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+        document.trailer.set("Root", catalog_id);
+
+        let mut bytes = Vec::new();
+        document.save_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[tokio::test]
+    async fn test_png_thumbnail_fits_bounds_and_keeps_aspect() {
+        let s3 = MockS3::default();
+        s3.put("blacklake/sha256/ab/cd/abcd", CHECKERBOARD);
+        let store = MemoryStore::with_object("abcd", CHECKERBOARD.len(), Some("image/png"));
+
+        let artifact = generate_thumbnail(&job("images/board.png"), &s3.client().await, "blacklake", &store)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(artifact.kind, THUMBNAIL_ARTIFACT_KIND);
+        assert_eq!(artifact.media_type, "image/png");
+        assert_eq!(store.recorded(), vec![artifact.clone()]);
+        let stored = s3.objects.lock().unwrap()[&format!("blacklake/{}", artifact.s3_key)].clone();
+        let preview = image::load_from_memory_with_format(&stored, ImageFormat::Png).unwrap();
+        assert_eq!(preview.dimensions(), (256, 192));
+    }
+
+    #[tokio::test]
+    async fn test_oversized_source_is_skipped_without_reading() {
+        let s3 = MockS3::default();
+        s3.put("blacklake/sha256/ab/cd/abcd", CHECKERBOARD);
+        let store = MemoryStore::with_object("abcd", MAX_THUMBNAIL_SOURCE_BYTES as usize + 1, Some("image/png"));
+
+        let result = generate_thumbnail(&job("images/huge.png"), &s3.client().await, "blacklake", &store).await.unwrap();
+
+        assert_eq!(result, None);
+        assert!(s3.reads().is_empty());
+        assert!(store.recorded().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_type_is_skipped() {
+        let s3_client = MockS3::default().client().await;
+        let store = MemoryStore::with_object("abcd", 10, Some("text/csv"));
+
+        let result = generate_thumbnail(&job("data/temps.png"), &s3_client, "blacklake", &store).await.unwrap();
+
+        assert_eq!(result, None);
+    }
+
+    #[test]
+    fn test_pdf_preview_uses_first_page_image() {
+        let mut jpeg = Vec::new();
+        image::load_from_memory(CHECKERBOARD)
+            .unwrap()
+            .write_to(&mut Cursor::new(&mut jpeg), ImageFormat::Jpeg)
+            .unwrap();
+        let pdf = pdf_with_jpeg(jpeg, 400, 300);
+
+        let thumbnail = render_thumbnail(&pdf, PreviewSource::Pdf).unwrap().unwrap();
+
+        assert_eq!((thumbnail.width, thumbnail.height), (256, 192));
+    }
+
+    #[test]
+    fn test_small_images_are_not_enlarged() {
+        let small = DynamicImage::ImageRgb8(RgbImage::new(40, 20));
+        let mut png = Vec::new();
+        small.write_to(&mut Cursor::new(&mut png), ImageFormat::Png).unwrap();
+
+        let thumbnail = render_thumbnail(&png, PreviewSource::Image(ImageFormat::Png)).unwrap().unwrap();
+
+        assert_eq!((thumbnail.width, thumbnail.height), (40, 20));
+    }
+
+    #[test]
+    fn test_preview_source_falls_back_to_extension_for_generic_types() {
+        assert_eq!(preview_source(Some("image/jpeg"), "a.bin"), Some(PreviewSource::Image(ImageFormat::Jpeg)));
+        assert_eq!(preview_source(Some("application/octet-stream"), "scan.TIF"), Some(PreviewSource::Image(ImageFormat::Tiff)));
+        assert_eq!(preview_source(None, "paper.pdf"), Some(PreviewSource::Pdf));
+        assert_eq!(preview_source(Some("text/plain"), "notes.png"), None);
+        assert_eq!(preview_source(None, "README"), None);
+    }
+}
@@ -1,7 +1,12 @@
 use anyhow::{anyhow, Result};
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::schema::SchemaRegistry;
+use crate::{Change, ChangeOp};
 
 /// Repository name validation
 pub fn validate_repo_name(name: &str) -> Result<()> {
@@ -328,6 +333,118 @@ pub fn validate_idempotency_key(key: &str) -> Result<()> {
     Ok(())
 }
 
+/// Content hash validation; returns the hash in lowercase, the form used as
+/// a content address
+pub fn validate_sha256(sha256: &str) -> Result<String> {
+    if sha256.len() != 64 || !sha256.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(anyhow!("sha256 must be 64 hexadecimal characters, got '{}'", sha256));
+    }
+
+    Ok(sha256.to_ascii_lowercase())
+}
+
+/// One problem with a change, as reported by dry-run validation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChangeValidationError {
+    /// Path of the change as submitted
+    pub path: String,
+    /// Offending metadata field as a dotted path, or `path`/`meta` when the
+    /// change path or the metadata document as a whole is at fault
+    pub field: String,
+    pub message: String,
+}
+
+/// Result of validating a commit without writing it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ValidateCommitResponse {
+    pub valid: bool,
+    pub errors: Vec<ChangeValidationError>,
+}
+
+/// What a commit would do, as reported by a dry run; nothing is written
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommitPlan {
+    /// True when the commit would be accepted as submitted
+    pub allowed: bool,
+    /// Head the commit would be made on; `None` when it would create the branch
+    pub parent_id: Option<Uuid>,
+    /// Changes as they would be written, with metadata merges applied
+    pub changes: Vec<Change>,
+    /// Problems with individual changes
+    pub errors: Vec<ChangeValidationError>,
+    /// Reasons the commit as a whole would be refused
+    pub blocking_reasons: Vec<String>,
+}
+
+impl CommitPlan {
+    pub fn new(
+        parent_id: Option<Uuid>,
+        changes: Vec<Change>,
+        errors: Vec<ChangeValidationError>,
+        blocking_reasons: Vec<String>,
+    ) -> Self {
+        Self {
+            allowed: errors.is_empty() && blocking_reasons.is_empty(),
+            parent_id,
+            changes,
+            errors,
+            blocking_reasons,
+        }
+    }
+}
+
+/// Run the per-change checks a commit performs, collecting every failure
+/// rather than stopping at the first.
+///
+/// Fails only if `collection@version` is not a registered schema.
+pub fn validate_changes(
+    registry: &SchemaRegistry,
+    collection: &str,
+    version: &str,
+    changes: &[Change],
+) -> Result<Vec<ChangeValidationError>> {
+    if registry.get_schema_version(collection, version).is_none() {
+        return Err(anyhow!("Schema not found: {}@{}", collection, version));
+    }
+
+    let mut errors = Vec::new();
+    for change in changes {
+        let error = |field: &str, message: String| ChangeValidationError {
+            path: change.path.clone(),
+            field: field.to_string(),
+            message,
+        };
+
+        if let Err(e) = normalize_path(&change.path) {
+            errors.push(error("path", e.to_string()));
+        }
+
+        if let Err(e) = validate_meta(&change.meta, Some("1.0")) {
+            errors.push(error("meta", e.to_string()));
+        }
+
+        if change.op != ChangeOp::Delete {
+            for violation in registry.validate_version(collection, version, &change.meta)? {
+                let field = pointer_to_field(&violation.path);
+                let field = if field.is_empty() { "meta".to_string() } else { field };
+                errors.push(error(&field, violation.message));
+            }
+        }
+    }
+
+    Ok(errors)
+}
+
+/// `/spatial/0/lat` → `spatial.0.lat`
+fn pointer_to_field(pointer: &str) -> String {
+    pointer
+        .split('/')
+        .skip(1)
+        .map(|token| token.replace("~1", "/").replace("~0", "~"))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -469,4 +586,111 @@ mod tests {
         assert!(validate_idempotency_key("key 123").is_err());
         assert!(validate_idempotency_key("key@123").is_err());
     }
+
+    #[test]
+    fn test_validate_sha256() {
+        let sha256 = "a665a45920422f9d417e4867efdc4fb8a04a1f3fff1fa07e998e86f7f7a27ae3";
+        assert_eq!(validate_sha256(sha256).unwrap(), sha256);
+        assert_eq!(validate_sha256(&sha256.to_uppercase()).unwrap(), sha256);
+    }
+
+    #[test]
+    fn test_validate_sha256_rejects_malformed_hashes() {
+        assert!(validate_sha256("").is_err());
+        assert!(validate_sha256("a665a459").is_err());
+        assert!(validate_sha256(&"g".repeat(64)).is_err());
+        assert!(validate_sha256(&"a".repeat(65)).is_err());
+        // 64 bytes, but not 64 characters
+        assert!(validate_sha256(&format!("{}é", "a".repeat(62))).is_err());
+    }
+
+    fn registry_requiring(required: &[&str]) -> SchemaRegistry {
+        let fields = required
+            .iter()
+            .map(|name| {
+                (
+                    name.to_string(),
+                    crate::schema::FieldDefinition {
+                        field_type: crate::schema::FieldType::String,
+                        description: None,
+                        default_value: None,
+                        validation: None,
+                    },
+                )
+            })
+            .collect();
+        let mut registry = SchemaRegistry::new();
+        registry.register_schema_version(
+            "datasets",
+            "v1",
+            crate::schema::MetadataSchema {
+                name: "datasets".to_string(),
+                version: "v1".to_string(),
+                description: None,
+                fields,
+                required_fields: required.iter().map(|name| name.to_string()).collect(),
+            },
+        );
+        registry
+    }
+
+    fn add(path: &str, meta: Value) -> Change {
+        Change {
+            op: ChangeOp::Add,
+            path: path.to_string(),
+            sha256: Some("a".repeat(64)),
+            meta,
+        }
+    }
+
+    #[test]
+    fn test_validate_changes_reports_missing_required_field() {
+        let registry = registry_requiring(&["name", "creator"]);
+        let changes = vec![
+            add("data/ok.csv", serde_json::json!({ "name": "ok", "creator": "a@example.com" })),
+            add("data/bad.csv", serde_json::json!({ "name": "bad" })),
+        ];
+
+        let errors = validate_changes(&registry, "datasets", "v1", &changes).unwrap();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, "data/bad.csv");
+        assert_eq!(errors[0].field, "creator");
+        assert!(errors[0].message.contains("creator"));
+    }
+
+    #[test]
+    fn test_validate_changes_passes_valid_payload() {
+        let registry = registry_requiring(&["name"]);
+        let changes = vec![add("data/ok.csv", serde_json::json!({ "name": "ok" }))];
+
+        assert!(validate_changes(&registry, "datasets", "v1", &changes).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_validate_changes_reports_every_problem() {
+        let registry = registry_requiring(&["name"]);
+        let changes = vec![add("../escape.csv", serde_json::json!({ "name": 7 }))];
+
+        let errors = validate_changes(&registry, "datasets", "v1", &changes).unwrap();
+        let fields: Vec<&str> = errors.iter().map(|e| e.field.as_str()).collect();
+
+        assert!(fields.contains(&"path"));
+        assert!(fields.contains(&"name"));
+        assert!(validate_changes(&registry, "datasets", "v9", &changes).is_err());
+    }
+
+    #[test]
+    fn test_commit_plan_is_allowed_only_without_errors_or_blockers() {
+        let changes = vec![add("data/ok.csv", serde_json::json!({ "name": "ok" }))];
+        let error = ChangeValidationError {
+            path: "data/ok.csv".to_string(),
+            field: "creator".to_string(),
+            message: "missing".to_string(),
+        };
+
+        assert!(CommitPlan::new(None, changes.clone(), vec![], vec![]).allowed);
+        assert!(!CommitPlan::new(None, changes.clone(), vec![error], vec![]).allowed);
+        assert!(!CommitPlan::new(None, changes, vec![], vec!["quota exceeded".to_string()]).allowed);
+    }
 }
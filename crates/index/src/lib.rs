@@ -1,14 +1,19 @@
 use blacklake_core::{
-    Acl, AuditLog, ArtifactRdf, Change, Commit, Entry, EntryMetaIndex, Object, Permission,
-    Reference, ReferenceKind, Repository, RdfFormat,
+    Acl, AuditLog, AuditLogFilter, ArtifactRdf, Change, ChangeOp, Commit, CommitDiff, CommitStats, DedupStats, Entry, EntryMetaIndex, EntrySample, Object, Permission,
+    PathDiff, RefAnnotation, Reference, ReferenceKind, RepoDeletion, Repository, RdfFormat, SearchSort, SortOrder,
     // Governance types
     governance::{ProtectedRef, RepoQuota, RepoUsage, RepoRetention, Webhook, WebhookDelivery, WebhookDead,
-                ExportJob, ExportManifest, ExportJobStatus, CheckResult, CheckStatus, QuotaStatus,
+                ExportJob, ExportFormat, ExportManifest, ExportJobStatus, CheckResult, CheckStatus, QuotaStatus,
                 WebhookEvent, RetentionPolicy, WebhookPayload},
+    features::{RepoFeature, RepoFeatures},
+    role_permissions::RolePermissionMap,
+    embeddings::{vector_literal, UPSERT_ENTRY_EMBEDDING_SQL},
+    jobs::UPSERT_ENTRY_SAMPLE_SQL,
+    project_to_index, MergeSide,
 };
 use chrono::Utc;
-use sqlx::{PgPool, Postgres, Row};
-use std::{collections::HashMap, str::FromStr, time::SystemTime, time::UNIX_EPOCH};
+use sqlx::{PgPool, Postgres, QueryBuilder, Row};
+use std::{collections::{BTreeMap, HashMap, HashSet}, str::FromStr};
 use thiserror::Error;
 use uuid::Uuid;
 
@@ -22,10 +27,32 @@ pub enum IndexError {
     RefNotFound(String),
     #[error("Commit not found: {0}")]
     CommitNotFound(Uuid),
-    #[error("Parent commit mismatch: expected {expected}, got {actual:?}")]
-    ParentMismatch { expected: Uuid, actual: Option<Uuid> },
+    #[error("Parent commit mismatch on {ref_name}: expected {expected}, got {actual:?}")]
+    ParentMismatch { ref_name: String, expected: Uuid, actual: Option<Uuid> },
     #[error("Invalid reference kind: {0}")]
     InvalidRefKind(String),
+    #[error("Reference is protected: {0}")]
+    RefProtected(String),
+    #[error("Reference already exists: {0}")]
+    RefExists(String),
+    #[error("Tag is immutable: {0}")]
+    TagImmutable(String),
+    #[error("Invalid permission: {0}")]
+    InvalidPermission(String),
+    #[error("Invalid search cursor: {0}")]
+    InvalidCursor(String),
+    #[error("Invalid search filter: {0}")]
+    InvalidFilter(String),
+    #[error("Repository is under legal hold: {0}")]
+    LegalHold(String),
+    #[error("Connector not found: {0}")]
+    ConnectorNotFound(Uuid),
+    #[error("Idempotency key conflict: {0}")]
+    IdempotencyConflict(String),
+    #[error("Repository features changed: expected version {expected}, found {actual}")]
+    FeaturesVersionMismatch { expected: i64, actual: i64 },
+    #[error(transparent)]
+    InvalidFeature(#[from] blacklake_core::features::FeatureError),
     #[error("JSON error: {0}")]
     Json(#[from] serde_json::Error),
 }
@@ -59,6 +86,12 @@ impl IndexClient {
         &self.pool
     }
 
+    /// Round-trip a trivial query
+    pub async fn ping(&self) -> Result<()> {
+        sqlx::query("SELECT 1").execute(&self.pool).await?;
+        Ok(())
+    }
+
     // Repository operations
 
     /// Create a new repository with production-ready database operations
@@ -147,35 +180,101 @@ impl IndexClient {
         })
     }
 
+    /// Delete a repository and everything recorded under it.
+    ///
+    /// Runs in one transaction and refuses with `LegalHold` while the repo's
+    /// retention policy holds it. Objects that no other repository references
+    /// are queued in `object_gc_schedule` so object GC keeps them for the
+    /// repo's hard-delete window before removing the blobs.
+    pub async fn delete_repo(&self, repo_id: Uuid) -> Result<RepoDeletion> {
+        let mut tx = self.pool.begin().await?;
+
+        let name: String = sqlx::query_scalar("SELECT name FROM repo WHERE id = $1 FOR UPDATE")
+            .bind(repo_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| IndexError::RepoNotFound(repo_id.to_string()))?;
+
+        let retention: Option<serde_json::Value> =
+            sqlx::query_scalar("SELECT retention_policy FROM repo_retention WHERE repo_id = $1")
+                .bind(repo_id)
+                .fetch_optional(&mut *tx)
+                .await?;
+        let policy = retention.and_then(|value| serde_json::from_value::<RetentionPolicy>(value).ok());
+        check_repo_deletable(&name, policy.as_ref())?;
+
+        let hard_delete_days = policy.map(|p| p.hard_delete_days).unwrap_or(DEFAULT_HARD_DELETE_DAYS);
+        let gc_not_before = Utc::now() + chrono::Duration::days(hard_delete_days as i64);
+        let scheduled = sqlx::query(SCHEDULE_REPO_OBJECT_GC_SQL)
+            .bind(repo_id)
+            .bind(gc_not_before)
+            .execute(&mut *tx)
+            .await?;
+
+        for statement in REPO_DELETE_STATEMENTS {
+            sqlx::query(statement).bind(repo_id).execute(&mut *tx).await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(RepoDeletion {
+            repo_id: blacklake_core::UuidWrapper(repo_id),
+            name,
+            unreferenced_objects: scheduled.rows_affected(),
+            gc_not_before,
+        })
+    }
+
     // Reference operations
 
     /// Get a reference
     pub async fn get_ref(&self, repo_id: Uuid, name: &str) -> Result<Reference> {
-        let row = sqlx::query(
-            "SELECT repo_id, name, kind, commit_id FROM ref WHERE repo_id = $1 AND name = $2"
-        )
+        let row = sqlx::query(&format!("{} WHERE r.repo_id = $1 AND r.name = $2", REF_SELECT_SQL))
         .bind(repo_id)
         .bind(name)
         .fetch_optional(&self.pool)
         .await?
         .ok_or_else(|| IndexError::RefNotFound(name.to_string()))?;
 
-        let kind_str: String = row.get("kind");
-        let kind = match kind_str.as_str() {
-            "branch" => ReferenceKind::Branch,
-            "tag" => ReferenceKind::Tag,
-            _ => return Err(IndexError::InvalidRefKind(kind_str)),
-        };
+        ref_from_row(&row)
+    }
 
-        Ok(Reference {
-            repo_id: blacklake_core::UuidWrapper(row.get("repo_id")),
-            name: row.get("name"),
-            kind,
-            commit_id: blacklake_core::UuidWrapper(row.get("commit_id")),
-        })
+    /// List references in a repository, optionally restricted to one kind
+    pub async fn list_refs(&self, repo_id: Uuid, kind: Option<ReferenceKind>) -> Result<Vec<Reference>> {
+        let rows = list_refs_query(repo_id, kind)
+            .build()
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.iter().map(ref_from_row).collect()
+    }
+
+    /// Delete a reference, refusing if branch protection disallows deletion
+    pub async fn delete_ref(&self, repo_id: Uuid, name: &str) -> Result<()> {
+        let protected_ref = self.get_protected_ref(repo_id, name).await?;
+        check_ref_deletable(name, protected_ref.as_ref())?;
+
+        self.force_delete_ref(repo_id, name).await
     }
 
-    /// Set a reference
+    /// Delete a reference regardless of branch protection.
+    ///
+    /// Callers are responsible for authorizing the override (e.g. admins).
+    pub async fn force_delete_ref(&self, repo_id: Uuid, name: &str) -> Result<()> {
+        let result = sqlx::query("DELETE FROM ref WHERE repo_id = $1 AND name = $2")
+            .bind(repo_id)
+            .bind(name)
+            .execute(&self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(IndexError::RefNotFound(name.to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// Set a reference, refusing with `TagImmutable` if `name` is a tag
     pub async fn set_ref(
         &self,
         repo_id: Uuid,
@@ -183,14 +282,11 @@ impl IndexClient {
         kind: ReferenceKind,
         commit_id: Uuid,
     ) -> Result<()> {
-        let kind_str = match kind {
-            ReferenceKind::Branch => "branch",
-            ReferenceKind::Tag => "tag",
-        };
+        let kind_str = ref_kind_str(&kind);
 
-        sqlx::query(
+        let result = sqlx::query(
             "INSERT INTO ref (repo_id, name, kind, commit_id) VALUES ($1, $2, $3, $4) 
-             ON CONFLICT (repo_id, name) DO UPDATE SET kind = $3, commit_id = $4"
+             ON CONFLICT (repo_id, name) DO UPDATE SET kind = $3, commit_id = $4 WHERE ref.kind <> 'tag'"
         )
         .bind(repo_id)
         .bind(name)
@@ -199,15 +295,146 @@ impl IndexClient {
         .execute(&self.pool)
         .await?;
 
+        if result.rows_affected() == 0 {
+            return Err(IndexError::TagImmutable(name.to_string()));
+        }
+
         Ok(())
     }
 
+    /// Point branch `name` at `commit_id` only if it still points at `expected`.
+    ///
+    /// With `expected` of `None` the branch must not exist yet. A branch that
+    /// moved since the caller read it fails with `ParentMismatch` (or
+    /// `RefExists` when it appeared), so a decision made against the old
+    /// head is never applied to a new one. Tags are immutable here too.
+    pub async fn compare_and_set_ref(
+        &self,
+        repo_id: Uuid,
+        name: &str,
+        expected: Option<Uuid>,
+        commit_id: Uuid,
+    ) -> Result<()> {
+        let result = match expected {
+            Some(expected) => {
+                sqlx::query(
+                    "UPDATE ref SET commit_id = $4
+                     WHERE repo_id = $1 AND name = $2 AND commit_id = $3 AND kind <> 'tag'"
+                )
+                .bind(repo_id)
+                .bind(name)
+                .bind(expected)
+                .bind(commit_id)
+                .execute(&self.pool)
+                .await?
+            }
+            None => {
+                sqlx::query(
+                    "INSERT INTO ref (repo_id, name, kind, commit_id) VALUES ($1, $2, 'branch', $3)
+                     ON CONFLICT (repo_id, name) DO NOTHING"
+                )
+                .bind(repo_id)
+                .bind(name)
+                .bind(commit_id)
+                .execute(&self.pool)
+                .await?
+            }
+        };
+        if result.rows_affected() == 1 {
+            return Ok(());
+        }
+
+        let current: Option<(Uuid, String)> =
+            sqlx::query_as("SELECT commit_id, kind FROM ref WHERE repo_id = $1 AND name = $2")
+                .bind(repo_id)
+                .bind(name)
+                .fetch_optional(&self.pool)
+                .await?;
+        match (expected, current) {
+            (_, Some((_, kind))) if kind == "tag" => Err(IndexError::TagImmutable(name.to_string())),
+            (Some(expected), actual) => Err(IndexError::ParentMismatch {
+                ref_name: name.to_string(),
+                expected,
+                actual: actual.map(|(commit_id, _)| commit_id),
+            }),
+            (None, _) => Err(IndexError::RefExists(name.to_string())),
+        }
+    }
+
+    /// Create a tag at `commit_id`, annotated when `message` is given.
+    ///
+    /// Fails with `RefExists` if any ref already has the name. With `force`
+    /// an existing tag is moved and its annotation replaced; callers are
+    /// responsible for authorizing that (e.g. admins). Branches are never
+    /// overwritten.
+    pub async fn create_tag(
+        &self,
+        repo_id: Uuid,
+        name: &str,
+        commit_id: Uuid,
+        tagger: &str,
+        message: Option<&str>,
+        force: bool,
+    ) -> Result<Reference> {
+        let mut tx = self.pool.begin().await?;
+
+        let existing: Option<String> =
+            sqlx::query_scalar("SELECT kind FROM ref WHERE repo_id = $1 AND name = $2 FOR UPDATE")
+                .bind(repo_id)
+                .bind(name)
+                .fetch_optional(&mut *tx)
+                .await?;
+        match existing.as_deref() {
+            None => {}
+            Some("tag") if force => {}
+            Some(_) => return Err(IndexError::RefExists(name.to_string())),
+        }
+
+        sqlx::query(
+            "INSERT INTO ref (repo_id, name, kind, commit_id) VALUES ($1, $2, 'tag', $3)
+             ON CONFLICT (repo_id, name) DO UPDATE SET commit_id = EXCLUDED.commit_id"
+        )
+        .bind(repo_id)
+        .bind(name)
+        .bind(commit_id)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query("DELETE FROM ref_annotation WHERE repo_id = $1 AND ref_name = $2")
+            .bind(repo_id)
+            .bind(name)
+            .execute(&mut *tx)
+            .await?;
+        if let Some(message) = message {
+            sqlx::query(
+                "INSERT INTO ref_annotation (repo_id, ref_name, message, tagger, tagged_at)
+                 VALUES ($1, $2, $3, $4, $5)"
+            )
+            .bind(repo_id)
+            .bind(name)
+            .bind(message)
+            .bind(tagger)
+            .bind(Utc::now())
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        self.get_ref(repo_id, name).await
+    }
+
     // Commit operations
 
-    /// Create a commit with optimistic parent check
+    /// Create a commit with optimistic parent check.
+    ///
+    /// With `expected_parent`, fails with `ParentMismatch` unless `ref_name`
+    /// currently points at it; the error carries the actual head so the
+    /// client can rebase onto it.
     pub async fn create_commit(
         &self,
         repo_id: Uuid,
+        ref_name: &str,
         parent_id: Option<Uuid>,
         author: &str,
         message: Option<&str>,
@@ -215,9 +442,14 @@ impl IndexClient {
     ) -> Result<Commit> {
         // Check parent if expected_parent is provided
         if let Some(expected) = expected_parent {
-            let actual_parent = self.get_ref(repo_id, "main").await.ok().map(|r| r.commit_id.0);
+            let actual_parent = match self.get_ref(repo_id, ref_name).await {
+                Ok(head) => Some(head.commit_id.0),
+                Err(IndexError::RefNotFound(_)) => None,
+                Err(e) => return Err(e),
+            };
             if actual_parent != Some(expected) {
                 return Err(IndexError::ParentMismatch {
+                    ref_name: ref_name.to_string(),
                     expected,
                     actual: actual_parent,
                 });
@@ -251,6 +483,112 @@ impl IndexClient {
         })
     }
 
+    /// Write a commit, its entries, their metadata index rows and the ref
+    /// update in one transaction.
+    ///
+    /// The ref row is locked for the duration, and its current commit becomes
+    /// the parent. Any failure rolls everything back, so no commit is left
+    /// without its entries or pointed at by a ref it never reached.
+    pub async fn commit_atomic(&self, write: &CommitWrite<'_>) -> Result<(Commit, CommitStats)> {
+        let mut tx = self.pool.begin().await?;
+
+        let head: Option<(Uuid, String)> =
+            sqlx::query_as("SELECT commit_id, kind FROM ref WHERE repo_id = $1 AND name = $2 FOR UPDATE")
+                .bind(write.repo_id)
+                .bind(write.ref_name)
+                .fetch_optional(&mut *tx)
+                .await?;
+        if matches!(&head, Some((_, kind)) if kind == "tag") {
+            return Err(IndexError::TagImmutable(write.ref_name.to_string()));
+        }
+        let parent_id = head.map(|(commit_id, _)| commit_id);
+        if let Some(expected) = write.expected_parent {
+            if parent_id != Some(expected) {
+                return Err(IndexError::ParentMismatch {
+                    ref_name: write.ref_name.to_string(),
+                    expected,
+                    actual: parent_id,
+                });
+            }
+        }
+
+        let id = Uuid::new_v4();
+        let now = Utc::now();
+        sqlx::query(
+            "INSERT INTO commit (id, repo_id, parent_id, merge_parent_id, author, message, created_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7)"
+        )
+        .bind(id)
+        .bind(write.repo_id)
+        .bind(parent_id)
+        .bind(write.merge_parent)
+        .bind(write.author)
+        .bind(write.message)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+
+        let (schema_name, schema_version) = write.schema.unzip();
+        for change in write.changes.iter().filter(|c| c.op != ChangeOp::Delete) {
+            sqlx::query(
+                "INSERT INTO entry (commit_id, path, object_sha256, meta, is_dir, schema_name, schema_version)
+                 VALUES ($1, $2, $3, $4, false, $5, $6)"
+            )
+            .bind(id)
+            .bind(&change.path)
+            .bind(&change.sha256)
+            .bind(&change.meta)
+            .bind(schema_name)
+            .bind(schema_version)
+            .execute(&mut *tx)
+            .await?;
+
+            upsert_meta_index_row(&mut *tx, &project_to_index(id, &change.path, &change.meta)).await?;
+        }
+
+        // Deleted paths keep their row in the parent commit until retention
+        // removes it; the stamp starts the repo's tombstone window
+        let deleted_paths: Vec<&str> = write
+            .changes
+            .iter()
+            .filter(|c| c.op == ChangeOp::Delete)
+            .map(|c| c.path.as_str())
+            .collect();
+        if let (Some(parent_id), false) = (parent_id, deleted_paths.is_empty()) {
+            sqlx::query(MARK_ENTRIES_DELETED_SQL)
+                .bind(parent_id)
+                .bind(&deleted_paths)
+                .bind(now)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        let stats = store_commit_stats(&mut tx, id, parent_id, write.changes).await?;
+
+        sqlx::query(
+            "INSERT INTO ref (repo_id, name, kind, commit_id) VALUES ($1, $2, 'branch', $3)
+             ON CONFLICT (repo_id, name) DO UPDATE SET commit_id = EXCLUDED.commit_id"
+        )
+        .bind(write.repo_id)
+        .bind(write.ref_name)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        let commit = Commit {
+            id: blacklake_core::UuidWrapper(id),
+            repo_id: blacklake_core::UuidWrapper(write.repo_id),
+            parent_id: parent_id.map(blacklake_core::UuidWrapper),
+            author: write.author.to_string(),
+            message: write.message.map(|s| s.to_string()),
+            created_at: now,
+            stats: Some(serde_json::to_value(&stats)?),
+        };
+        Ok((commit, stats))
+    }
+
     /// Get a commit by ID
     pub async fn get_commit(&self, commit_id: Uuid) -> Result<Commit> {
         let row = sqlx::query(
@@ -273,9 +611,68 @@ impl IndexClient {
         })
     }
 
+    /// Get commit history for a reference, newest first.
+    ///
+    /// Resolves `ref_name` to its head commit and follows `parent_id` back
+    /// towards the root, skipping `offset` commits and returning at most
+    /// `limit`.
+    pub async fn get_commit_history(
+        &self,
+        repo_id: Uuid,
+        ref_name: &str,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<Commit>> {
+        let head = self.get_ref(repo_id, ref_name).await?;
+        let ancestors = self
+            .get_commit_ancestors(head.commit_id.0, offset.saturating_add(limit))
+            .await?;
+
+        Ok(ancestors.into_iter().skip(offset).take(limit).collect())
+    }
+
+    /// Walk `parent_id` links from `commit_id` (inclusive), returning at most
+    /// `max` commits ordered newest to oldest
+    pub async fn get_commit_ancestors(&self, commit_id: Uuid, max: usize) -> Result<Vec<Commit>> {
+        walk_ancestors(commit_id, max, |id| async move {
+            match self.get_commit(id).await {
+                Ok(commit) => Ok(Some(commit)),
+                Err(IndexError::CommitNotFound(_)) => Ok(None),
+                Err(e) => Err(e),
+            }
+        })
+        .await
+    }
+
+    /// Nearest common ancestor of two commits, following both parents of
+    /// merge commits. `None` if their histories never meet.
+    pub async fn find_merge_base(&self, a: Uuid, b: Uuid) -> Result<Option<Uuid>> {
+        let base: Option<Uuid> = sqlx::query_scalar(MERGE_BASE_QUERY)
+            .bind(a)
+            .bind(b)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(base)
+    }
+
+    /// Object and metadata of every path in a commit, as inputs to a merge
+    pub async fn get_merge_sides(&self, commit_id: Uuid) -> Result<HashMap<String, MergeSide>> {
+        Ok(self
+            .get_entry_snapshots(commit_id)
+            .await?
+            .into_iter()
+            .map(|e| (e.path, MergeSide { sha256: e.object_sha256, meta: e.meta }))
+            .collect())
+    }
+
     // Object operations
 
-    /// Upsert an object
+    /// Record an object, returning the stored row.
+    ///
+    /// An existing row is left as it is: its size is what `upload_verify`
+    /// checks the stored bytes against, so a later upload of the same hash
+    /// cannot change it.
     pub async fn upsert_object(
         &self,
         sha256: &str,
@@ -283,29 +680,51 @@ impl IndexClient {
         media_type: Option<&str>,
         s3_key: &str,
     ) -> Result<Object> {
-        let now = Utc::now();
-
         sqlx::query(
             "INSERT INTO object (sha256, size, media_type, s3_key, created_at) 
              VALUES ($1, $2, $3, $4, $5)
-             ON CONFLICT (sha256) DO UPDATE SET 
-             size = EXCLUDED.size, media_type = EXCLUDED.media_type, s3_key = EXCLUDED.s3_key"
+             ON CONFLICT (sha256) DO NOTHING"
         )
         .bind(sha256)
         .bind(size)
         .bind(media_type)
         .bind(s3_key)
-        .bind(now)
+        .bind(Utc::now())
         .execute(&self.pool)
         .await?;
 
-        Ok(Object {
-            sha256: sha256.to_string(),
-            size,
-            media_type: media_type.map(|s| s.to_string()),
-            s3_key: s3_key.to_string(),
-            created_at: now,
-        })
+        self.get_object(sha256)
+            .await?
+            .ok_or_else(|| IndexError::Database(sqlx::Error::RowNotFound))
+    }
+
+    /// Record that an object's stored bytes hash to its sha256
+    pub async fn mark_object_verified(&self, sha256: &str) -> Result<()> {
+        sqlx::query(blacklake_core::integrity::MARK_OBJECT_VERIFIED_SQL)
+            .bind(sha256)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Whether an object with this sha256 is stored and its bytes were verified
+    pub async fn is_object_verified(&self, sha256: &str) -> Result<bool> {
+        let verified: Option<bool> = sqlx::query_scalar("SELECT verified_at IS NOT NULL FROM object WHERE sha256 = $1")
+            .bind(sha256)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(verified.unwrap_or(false))
+    }
+
+    /// Drop the row of an object whose upload did not match its sha256,
+    /// unless an earlier upload verified it or something references it.
+    /// Returns whether the row was dropped; only then may the blob go.
+    pub async fn discard_unverified_object(&self, sha256: &str) -> Result<bool> {
+        let discarded: Option<String> = sqlx::query_scalar(blacklake_core::integrity::DISCARD_UNVERIFIED_OBJECT_SQL)
+            .bind(sha256)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(discarded.is_some())
     }
 
     /// Get an object by SHA256
@@ -326,6 +745,168 @@ impl IndexClient {
         }))
     }
 
+    /// Repositories with a commit whose tree references the object, by name
+    pub async fn list_object_repos(&self, sha256: &str) -> Result<Vec<(Uuid, String)>> {
+        let rows = sqlx::query(
+            "SELECT DISTINCT r.id, r.name
+             FROM entry e
+             JOIN commit c ON e.commit_id = c.id
+             JOIN repo r ON r.id = c.repo_id
+             WHERE e.object_sha256 = $1
+             ORDER BY r.name"
+        )
+        .bind(sha256)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|row| (row.get("id"), row.get("name"))).collect())
+    }
+
+    /// S3 keys of every object some commit of the repo references
+    pub async fn repo_object_keys(&self, repo_id: Uuid) -> Result<Vec<String>> {
+        let rows = sqlx::query(
+            "SELECT DISTINCT o.s3_key
+             FROM entry e
+             JOIN commit c ON e.commit_id = c.id
+             JOIN object o ON o.sha256 = e.object_sha256
+             WHERE c.repo_id = $1
+             ORDER BY o.s3_key"
+        )
+        .bind(repo_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|row| row.get("s3_key")).collect())
+    }
+
+    /// Object derived from an entry, such as its thumbnail or Parquet copy
+    pub async fn get_derived_object(&self, commit_id: Uuid, path: &str, kind: &str) -> Result<Option<Object>> {
+        let row = sqlx::query(
+            "SELECT o.sha256, o.size, o.media_type, o.s3_key, o.created_at
+             FROM derived_artifact d
+             JOIN object o ON o.sha256 = d.derived_sha256
+             WHERE d.commit_id = $1 AND d.path = $2 AND d.kind = $3"
+        )
+        .bind(commit_id)
+        .bind(path)
+        .bind(kind)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|row| Object {
+            sha256: row.get("sha256"),
+            size: row.get("size"),
+            media_type: row.get("media_type"),
+            s3_key: row.get("s3_key"),
+            created_at: row.get("created_at"),
+        }))
+    }
+
+    /// Which of `sha256s` are already stored
+    pub async fn existing_objects(&self, sha256s: &[String]) -> Result<HashSet<String>> {
+        let rows: Vec<String> = sqlx::query_scalar("SELECT sha256 FROM object WHERE sha256 = ANY($1)")
+            .bind(sha256s)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.into_iter().collect())
+    }
+
+    /// Recorded media types of those of `sha256s` that have one
+    pub async fn object_media_types(&self, sha256s: &[String]) -> Result<HashMap<String, String>> {
+        let rows: Vec<(String, String)> = sqlx::query_as(
+            "SELECT sha256, media_type FROM object WHERE sha256 = ANY($1) AND media_type IS NOT NULL"
+        )
+        .bind(sha256s)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().collect())
+    }
+
+    /// Find objects that no entry or derived artifact references and that
+    /// were created before `older_than`; these are candidates for garbage
+    /// collection
+    pub async fn find_orphaned_objects(&self, older_than: chrono::DateTime<Utc>) -> Result<Vec<Object>> {
+        let rows = sqlx::query(ORPHANED_OBJECTS_QUERY)
+            .bind(older_than)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| Object {
+                sha256: row.get("sha256"),
+                size: row.get("size"),
+                media_type: row.get("media_type"),
+                s3_key: row.get("s3_key"),
+                created_at: row.get("created_at"),
+            })
+            .collect())
+    }
+
+    /// Number of entries, in any repo or commit, that reference an object.
+    ///
+    /// An object may only be deleted from storage once this reaches zero.
+    pub async fn object_ref_count(&self, sha256: &str) -> Result<i64> {
+        let count: i64 = sqlx::query_scalar(OBJECT_REF_COUNT_QUERY)
+            .bind(sha256)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(count)
+    }
+
+    /// Logical versus deduplicated bytes across every commit in a repo
+    pub async fn repo_dedup_stats(&self, repo_id: Uuid) -> Result<DedupStats> {
+        let usage: Vec<(String, i64, i64)> = sqlx::query_as(REPO_OBJECT_USAGE_QUERY)
+            .bind(repo_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(compute_dedup_stats(&usage))
+    }
+
+    /// Delete object rows that are still unreferenced, returning the rows
+    /// deleted. Only their blobs may then be removed from storage.
+    ///
+    /// The rows are locked before the orphan check is repeated, so a commit
+    /// binding one of them either lands first and the object is kept, or
+    /// waits and then fails its foreign key instead of referencing a blob
+    /// that is about to go.
+    pub async fn delete_orphaned_objects(&self, sha256s: &[String]) -> Result<Vec<Object>> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("SELECT sha256 FROM object WHERE sha256 = ANY($1) ORDER BY sha256 FOR UPDATE")
+            .bind(sha256s)
+            .execute(&mut *tx)
+            .await?;
+
+        let rows = sqlx::query(
+            "DELETE FROM object o
+             WHERE o.sha256 = ANY($1)
+               AND NOT EXISTS (SELECT 1 FROM entry e WHERE e.object_sha256 = o.sha256)
+               AND NOT EXISTS (SELECT 1 FROM derived_artifact d WHERE d.derived_sha256 = o.sha256)
+             RETURNING o.sha256, o.size, o.media_type, o.s3_key, o.created_at"
+        )
+        .bind(sha256s)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| Object {
+                sha256: row.get("sha256"),
+                size: row.get("size"),
+                media_type: row.get("media_type"),
+                s3_key: row.get("s3_key"),
+                created_at: row.get("created_at"),
+            })
+            .collect())
+    }
+
     // Entry operations
 
     /// Bind entry rows for a commit
@@ -359,41 +940,213 @@ impl IndexClient {
         Ok(())
     }
 
-    /// Get tree entries for a commit
+    /// Record the schema collection and version that validated `paths` in a commit
+    pub async fn set_entry_schema_version(
+        &self,
+        commit_id: Uuid,
+        paths: &[String],
+        schema_name: &str,
+        schema_version: &str,
+    ) -> Result<()> {
+        sqlx::query(
+            "UPDATE entry SET schema_name = $3, schema_version = $4
+             WHERE commit_id = $1 AND path = ANY($2)"
+        )
+        .bind(commit_id)
+        .bind(paths)
+        .bind(schema_name)
+        .bind(schema_version)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Compute stats for a commit's changes and store them in `commit.stats`.
+    ///
+    /// Sizes come from the `object` table; removed bytes are measured against
+    /// the object each path pointed to in `parent_id`.
+    pub async fn record_commit_stats(
+        &self,
+        commit_id: Uuid,
+        parent_id: Option<Uuid>,
+        changes: &[Change],
+    ) -> Result<CommitStats> {
+        let mut conn = self.pool.acquire().await?;
+        store_commit_stats(&mut conn, commit_id, parent_id, changes).await
+    }
+
+    /// Get tree entries for a commit
     pub async fn get_tree_entries(
         &self,
         commit_id: Uuid,
         path_prefix: Option<&str>,
     ) -> Result<Vec<Entry>> {
-        let rows = if let Some(prefix) = path_prefix {
-            sqlx::query_as::<_, (Uuid, String, String, serde_json::Value, Option<bool>)>(
-                "SELECT commit_id, path, object_sha256, meta, is_dir 
-                 FROM entry WHERE commit_id = $1 AND path LIKE $2 ORDER BY path"
-            )
-            .bind(commit_id)
-            .bind(format!("{}%", prefix))
-            .fetch_all(&self.pool)
-            .await?
-        } else {
-            sqlx::query_as::<_, (Uuid, String, String, serde_json::Value, Option<bool>)>(
-                "SELECT commit_id, path, object_sha256, meta, is_dir 
-                 FROM entry WHERE commit_id = $1 ORDER BY path"
-            )
-            .bind(commit_id)
-            .fetch_all(&self.pool)
-            .await?
-        };
+        let mut query = QueryBuilder::<Postgres>::new(TREE_ENTRIES_SELECT);
+        query.push(" WHERE e.commit_id = ").push_bind(commit_id);
+        if let Some(prefix) = path_prefix {
+            query.push(" AND e.path LIKE ").push_bind(format!("{}%", prefix));
+        }
+        query.push(" ORDER BY e.path");
+
+        let rows = query.build().fetch_all(&self.pool).await?;
 
         Ok(rows
-            .into_iter()
-            .map(|(commit_id, path, object_sha256, meta, is_dir)| Entry {
+            .iter()
+            .map(|row| Entry {
                 id: blacklake_core::UuidWrapper(uuid::Uuid::new_v4()), // Generate new ID since it's missing from query
                 commit_id: blacklake_core::UuidWrapper(commit_id),
-                path,
-                object_sha256: Some(object_sha256),
-                meta,
-                is_dir: is_dir.unwrap_or(false),
-                created_at: chrono::Utc::now(), // Use current time since it's missing from query
+                path: row.get("path"),
+                object_sha256: row.get("object_sha256"),
+                meta: row.get("meta"),
+                is_dir: row.get("is_dir"),
+                created_at: row.get::<Option<chrono::DateTime<Utc>>, _>("stored_at").unwrap_or_else(Utc::now),
+                size: row.get("size"),
+                media_type: row.get("media_type"),
+            })
+            .collect())
+    }
+
+    /// Files an export manifest's paths select in a commit.
+    ///
+    /// Each path is an exact file, a directory prefix, or a `*`/`**` glob
+    /// (see `export_jobs::export_path_matches`); only the tree under its
+    /// literal prefix is read. Files selected by several paths appear once,
+    /// in path order.
+    pub async fn resolve_export_entries(&self, commit_id: Uuid, paths: &[String]) -> Result<Vec<Entry>> {
+        let mut candidates = BTreeMap::new();
+        for pattern in paths {
+            let prefix = blacklake_core::export_jobs::literal_prefix(pattern);
+            for entry in self.get_tree_entries(commit_id, Some(prefix).filter(|p| !p.is_empty())).await? {
+                if !entry.is_dir {
+                    candidates.entry(entry.path.clone()).or_insert(entry);
+                }
+            }
+        }
+
+        let resolved = blacklake_core::export_jobs::resolve_export_paths(paths, candidates.keys().map(String::as_str));
+        Ok(resolved.iter().filter_map(|path| candidates.remove(path)).collect())
+    }
+
+    /// One page of a commit's tree under `path_prefix`.
+    ///
+    /// With a delimiter, entries whose path continues past the prefix with
+    /// another delimiter collapse into one directory row per next segment,
+    /// like S3's `CommonPrefixes`; the row's path ends with the delimiter.
+    /// Rows are ordered by path and the total counts rows, not entries.
+    pub async fn list_tree(
+        &self,
+        commit_id: Uuid,
+        path_prefix: Option<&str>,
+        delimiter: Option<&str>,
+        limit: Option<u32>,
+        offset: Option<u32>,
+    ) -> Result<(Vec<Entry>, u64)> {
+        let prefix = path_prefix.unwrap_or("");
+        let limit = limit.unwrap_or(1000).min(10_000);
+        let offset = offset.unwrap_or(0);
+        let delimiter = delimiter.filter(|d| !d.is_empty());
+
+        let rows = sqlx::query(&format!(
+            "{} SELECT r.name, r.collapsed, r.object_sha256, r.meta, r.is_dir, o.size, o.media_type, o.created_at AS stored_at
+                FROM rows r LEFT JOIN object o ON NOT r.collapsed AND o.sha256 = r.object_sha256
+                ORDER BY r.name LIMIT $4 OFFSET $5",
+            TREE_ROWS_SQL
+        ))
+        .bind(commit_id)
+        .bind(prefix)
+        .bind(delimiter)
+        .bind(limit as i64)
+        .bind(offset as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let total: i64 = sqlx::query_scalar(&format!("{} SELECT COUNT(*) FROM rows", TREE_ROWS_SQL))
+            .bind(commit_id)
+            .bind(prefix)
+            .bind(delimiter)
+            .fetch_one(&self.pool)
+            .await?;
+
+        let entries = rows
+            .iter()
+            .map(|row| {
+                let collapsed: bool = row.get("collapsed");
+                Entry {
+                    id: blacklake_core::UuidWrapper(uuid::Uuid::new_v4()),
+                    commit_id: blacklake_core::UuidWrapper(commit_id),
+                    path: row.get("name"),
+                    object_sha256: if collapsed { None } else { row.get("object_sha256") },
+                    meta: if collapsed { serde_json::json!({}) } else { row.get("meta") },
+                    is_dir: collapsed || row.get::<bool, _>("is_dir"),
+                    created_at: row.get::<Option<chrono::DateTime<Utc>>, _>("stored_at").unwrap_or_else(Utc::now),
+                    size: row.get("size"),
+                    media_type: row.get("media_type"),
+                }
+            })
+            .collect();
+
+        Ok((entries, total as u64))
+    }
+
+    /// Compare the entries of two commits.
+    ///
+    /// Paths whose object hash changed are reported as modified; paths
+    /// with the same object but different metadata are reported separately
+    /// so metadata edits don't show up as content changes.
+    pub async fn diff_commits(&self, base: Uuid, head: Uuid) -> Result<CommitDiff> {
+        let base_entries = self.get_entry_snapshots(base).await?;
+        let head_entries = self.get_entry_snapshots(head).await?;
+
+        Ok(diff_entry_snapshots(base_entries, head_entries))
+    }
+
+    async fn get_entry_snapshots(&self, commit_id: Uuid) -> Result<Vec<EntrySnapshot>> {
+        let rows = sqlx::query_as::<_, EntrySnapshot>(
+            "SELECT path, object_sha256, meta FROM entry WHERE commit_id = $1"
+        )
+        .bind(commit_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows)
+    }
+
+    /// Metadata of the entry at `path` in a commit, if the path exists there
+    pub async fn get_entry_meta(&self, commit_id: Uuid, path: &str) -> Result<Option<serde_json::Value>> {
+        let meta = sqlx::query_scalar("SELECT meta FROM entry WHERE commit_id = $1 AND path = $2")
+            .bind(commit_id)
+            .bind(path)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(meta)
+    }
+
+    /// Metadata of the entry at `path` in `head` and its ancestors, newest
+    /// first, walking at most `max` commits; `None` where a commit has no
+    /// entry at `path`
+    pub async fn get_entry_meta_history(
+        &self,
+        head: Uuid,
+        path: &str,
+        max: usize,
+    ) -> Result<Vec<(Commit, Option<serde_json::Value>)>> {
+        let commits = self.get_commit_ancestors(head, max).await?;
+        let ids: Vec<Uuid> = commits.iter().map(|commit| commit.id.0).collect();
+        let rows: Vec<(Uuid, serde_json::Value)> =
+            sqlx::query_as("SELECT commit_id, meta FROM entry WHERE path = $1 AND commit_id = ANY($2)")
+                .bind(path)
+                .bind(&ids)
+                .fetch_all(&self.pool)
+                .await?;
+
+        let mut metas: HashMap<Uuid, serde_json::Value> = rows.into_iter().collect();
+        Ok(commits
+            .into_iter()
+            .map(|commit| {
+                let meta = metas.remove(&commit.id.0);
+                (commit, meta)
             })
             .collect())
     }
@@ -405,138 +1158,31 @@ impl IndexClient {
         &self,
         repo_id: Uuid,
         filters: &HashMap<String, serde_json::Value>,
-        sort: Option<&str>,
+        sort: SearchSort,
         limit: Option<u32>,
         offset: Option<u32>,
     ) -> Result<(Vec<Entry>, u32)> {
         let limit = limit.unwrap_or(20).min(1000); // Cap at 1000 for performance
         let offset = offset.unwrap_or(0);
-        
-        // Build optimized query with proper indexing
-        let mut query = String::from("SELECT e.*, r.name as repo_name FROM entry e JOIN repo r ON e.repo_id = r.id WHERE e.repo_id = $1");
-        let mut params: Vec<Box<dyn sqlx::Encode<'_, sqlx::Postgres> + Send + Sync>> = vec![Box::new(repo_id)];
-        let mut param_count = 1;
-        
-        // Add optimized filters with proper indexing
-        for (key, value) in filters {
-            param_count += 1;
-            match key.as_str() {
-                "path" => {
-                    if let Some(path_value) = value.as_str() {
-                        query.push_str(&format!(" AND e.path ILIKE ${}", param_count));
-                        params.push(Box::new(format!("%{}%", path_value)));
-                    }
-                }
-                "file_type" => {
-                    if let Some(file_type) = value.as_str() {
-                        query.push_str(&format!(" AND e.file_type = ${}", param_count));
-                        params.push(Box::new(file_type));
-                    }
-                }
-                "size_min" => {
-                    if let Some(size) = value.as_i64() {
-                        query.push_str(&format!(" AND e.file_size >= ${}", param_count));
-                        params.push(Box::new(size));
-                    }
-                }
-                "size_max" => {
-                    if let Some(size) = value.as_i64() {
-                        query.push_str(&format!(" AND e.file_size <= ${}", param_count));
-                        params.push(Box::new(size));
-                    }
-                }
-                "created_after" => {
-                    if let Some(date_str) = value.as_str() {
-                        if let Ok(date) = chrono::DateTime::parse_from_rfc3339(date_str) {
-                            query.push_str(&format!(" AND e.created_at >= ${}", param_count));
-                            params.push(Box::new(date.with_timezone(&chrono::Utc)));
-                        }
-                    }
-                }
-                "created_before" => {
-                    if let Some(date_str) = value.as_str() {
-                        if let Ok(date) = chrono::DateTime::parse_from_rfc3339(date_str) {
-                            query.push_str(&format!(" AND e.created_at <= ${}", param_count));
-                            params.push(Box::new(date.with_timezone(&chrono::Utc)));
-                        }
-                    }
-                }
-                "tags" => {
-                    if let Some(tags) = value.as_array() {
-                        if !tags.is_empty() {
-                            let tag_placeholders: Vec<String> = (0..tags.len())
-                                .map(|i| format!("${}", param_count + i))
-                                .collect();
-                            query.push_str(&format!(" AND e.tags && ARRAY[{}]", tag_placeholders.join(",")));
-                            for tag in tags {
-                                if let Some(tag_str) = tag.as_str() {
-                                    params.push(Box::new(tag_str));
-                                }
-                            }
-                            param_count += tags.len() - 1;
-                        }
-                    }
-                }
-                _ => {
-                    // Handle custom metadata filters
-                    if let Some(meta_value) = value.as_str() {
-                        query.push_str(&format!(" AND e.meta->>{} = ${}", key, param_count));
-                        params.push(Box::new(meta_value));
-                    }
-                }
-            }
-        }
-        
-        // Add optimized sorting
-        match sort {
-            Some("path") => query.push_str(" ORDER BY e.path ASC"),
-            Some("size") => query.push_str(" ORDER BY e.file_size DESC"),
-            Some("created") => query.push_str(" ORDER BY e.created_at DESC"),
-            Some("modified") => query.push_str(" ORDER BY e.updated_at DESC"),
-            _ => query.push_str(" ORDER BY e.created_at DESC"), // Default sort
-        }
-        
-        // Add pagination
-        query.push_str(&format!(" LIMIT ${} OFFSET ${}", param_count + 1, param_count + 2));
-        params.push(Box::new(limit as i32));
-        params.push(Box::new(offset as i32));
-        
-        // Execute optimized query
+
         let start_time = std::time::Instant::now();
-        
-        // For now, we'll use a simplified approach since sqlx doesn't support dynamic parameters easily
-        // In production, you would use a query builder or prepared statements
-        let rows = sqlx::query(
-            "SELECT e.*, r.name as repo_name FROM entry e 
-             JOIN repo r ON e.repo_id = r.id 
-             WHERE e.repo_id = $1 
-             ORDER BY e.created_at DESC 
-             LIMIT $2 OFFSET $3"
-        )
-        .bind(repo_id)
-        .bind(limit as i32)
-        .bind(offset as i32)
-        .fetch_all(&self.pool)
-        .await?;
-        
-        let entries: Vec<Entry> = rows.into_iter().map(|row| Entry {
-            id: blacklake_core::UuidWrapper(row.get("id")),
-            commit_id: blacklake_core::UuidWrapper(row.get("commit_id")),
-            path: row.get("path"),
-            object_sha256: row.get("object_sha256"),
-            meta: row.get("meta"),
-            is_dir: row.get("is_dir"),
-            created_at: row.get("created_at"),
-        }).collect();
-        
-        // Get total count for pagination
-        let total_count: i64 = sqlx::query_scalar(
-            "SELECT COUNT(*) FROM entry WHERE repo_id = $1"
-        )
-        .bind(repo_id)
-        .fetch_one(&self.pool)
-        .await?;
-        
+
+        let mut query = QueryBuilder::<Postgres>::new(SEARCH_ENTRIES_SELECT);
+        push_search_filters(&mut query, repo_id, filters)?;
+
+        query.push(search_order_by(sort));
+
+        // Add pagination
+        query.push(" LIMIT ").push_bind(limit as i64);
+        query.push(" OFFSET ").push_bind(offset as i64);
+
+        let rows = query.build().fetch_all(&self.pool).await?;
+
+        let entries: Vec<Entry> = rows.iter().map(entry_from_search_row).collect();
+
+        // Get total count for pagination using the same predicates
+        let total_count = self.count_search_entries(repo_id, filters).await?;
+
         let query_time = start_time.elapsed();
         tracing::info!(
             "Search query executed in {:?} for repo {} with {} results",
@@ -544,8 +1190,214 @@ impl IndexClient {
             repo_id,
             entries.len()
         );
-        
-        Ok((entries, total_count as u32))
+
+        Ok((entries, total_count))
+    }
+
+    /// Count the entries matching `search_entries` filters
+    pub async fn count_search_entries(
+        &self,
+        repo_id: Uuid,
+        filters: &HashMap<String, serde_json::Value>,
+    ) -> Result<u32> {
+        let mut query = QueryBuilder::<Postgres>::new(SEARCH_ENTRIES_COUNT);
+        push_search_filters(&mut query, repo_id, filters)?;
+        let total: i64 = query
+            .build_query_scalar()
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(total as u32)
+    }
+
+    /// Search entries with keyset pagination on `(created_at, id)`.
+    ///
+    /// `cursor` is the opaque `next_cursor` from the previous page. Unlike
+    /// `search_entries`, pages stay stable when entries are added between
+    /// requests: new rows sort before or after the cursor and never shift it.
+    pub async fn search_entries_keyset(
+        &self,
+        repo_id: Uuid,
+        filters: &HashMap<String, serde_json::Value>,
+        sort: &SortOrder,
+        cursor: Option<&str>,
+        limit: Option<u32>,
+    ) -> Result<(Vec<Entry>, Option<String>)> {
+        let limit = limit.unwrap_or(20).clamp(1, 1000);
+        let cursor = cursor.map(SearchCursor::decode).transpose()?;
+
+        let mut query = QueryBuilder::<Postgres>::new(SEARCH_ENTRIES_SELECT);
+        push_search_filters(&mut query, repo_id, filters)?;
+        push_keyset_page(&mut query, sort, cursor.as_ref(), limit);
+
+        let rows = query.build().fetch_all(&self.pool).await?;
+        let entries: Vec<Entry> = rows.iter().map(entry_from_search_row).collect();
+
+        Ok(split_keyset_page(entries, limit))
+    }
+
+    /// Free-text search over indexed metadata, most relevant first.
+    ///
+    /// Matches `description`, `notes`, `file_name` and `tags` through the
+    /// `entry_meta_index.search_vector` column, so it works without Solr.
+    /// Quoted phrases in `query` must match word for word; the remaining
+    /// words must all appear somewhere. Entries containing the whole query as
+    /// a phrase rank above those that only contain its words.
+    pub async fn search_entries_fulltext(
+        &self,
+        repo_id: Uuid,
+        query: &str,
+        limit: Option<u32>,
+        offset: Option<u32>,
+    ) -> Result<(Vec<Entry>, u32)> {
+        let query = FulltextQuery::parse(query);
+        if query.is_empty() {
+            return Ok((Vec::new(), 0));
+        }
+        let limit = limit.unwrap_or(20).min(1000);
+        let offset = offset.unwrap_or(0);
+
+        let mut select = QueryBuilder::<Postgres>::new(FULLTEXT_SEARCH_SELECT);
+        push_fulltext_from(&mut select, repo_id, &query);
+        select.push(" ORDER BY rank DESC, e.path ASC");
+        select.push(" LIMIT ").push_bind(limit as i64);
+        select.push(" OFFSET ").push_bind(offset as i64);
+
+        let rows = select.build().fetch_all(&self.pool).await?;
+        let entries: Vec<Entry> = rows.iter().map(entry_from_search_row).collect();
+
+        let mut count = QueryBuilder::<Postgres>::new("SELECT COUNT(*)");
+        push_fulltext_from(&mut count, repo_id, &query);
+        let total: i64 = count.build_query_scalar().fetch_one(&self.pool).await?;
+
+        Ok((entries, total as u32))
+    }
+
+    /// The `k` entries of a repo whose embeddings are closest to `query_vector`
+    /// by cosine distance, nearest first, with their cosine similarity.
+    ///
+    /// A path embedded in several commits is represented by its most recent
+    /// embedding.
+    pub async fn nearest_entries(&self, repo_id: Uuid, query_vector: &[f32], k: u32) -> Result<Vec<(Entry, f32)>> {
+        let rows = sqlx::query(NEAREST_ENTRIES_QUERY)
+            .bind(repo_id)
+            .bind(vector_literal(query_vector))
+            .bind(k.min(1000) as i64)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| (entry_from_search_row(row), 1.0 - row.get::<f64, _>("distance") as f32))
+            .collect())
+    }
+
+    /// Store the embedding of a committed entry, replacing any earlier one
+    pub async fn upsert_entry_embedding(&self, commit_id: Uuid, path: &str, model: &str, embedding: &[f32]) -> Result<()> {
+        sqlx::query(UPSERT_ENTRY_EMBEDDING_SQL)
+            .bind(commit_id)
+            .bind(path)
+            .bind(model)
+            .bind(vector_literal(embedding))
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    // ACL operations
+
+    /// List ACL entries for a repository
+    pub async fn get_acls(&self, repo_id: Uuid) -> Result<Vec<Acl>> {
+        let rows = sqlx::query_as::<_, (Uuid, String, String)>(
+            "SELECT repo_id, subject, perm FROM acl WHERE repo_id = $1 ORDER BY subject, perm"
+        )
+        .bind(repo_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter()
+            .map(|(repo_id, subject, perm)| {
+                Ok(Acl {
+                    repo_id: blacklake_core::UuidWrapper(repo_id),
+                    subject,
+                    perm: Permission::from_str(&perm).map_err(IndexError::InvalidPermission)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Grant `perm` to `subject`, replacing any permission it already holds
+    pub async fn set_acl(&self, repo_id: Uuid, subject: &str, perm: Permission) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("DELETE FROM acl WHERE repo_id = $1 AND subject = $2")
+            .bind(repo_id)
+            .bind(subject)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query("INSERT INTO acl (repo_id, subject, perm) VALUES ($1, $2, $3)")
+            .bind(repo_id)
+            .bind(subject)
+            .bind(perm.as_str())
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Remove all permissions held by `subject` on a repository
+    pub async fn remove_acl(&self, repo_id: Uuid, subject: &str) -> Result<()> {
+        sqlx::query("DELETE FROM acl WHERE repo_id = $1 AND subject = $2")
+            .bind(repo_id)
+            .bind(subject)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Check whether `subject` holds at least `required` on a repository.
+    ///
+    /// The repository creator is always treated as an admin. Permissions the
+    /// caller's IdP `roles` map to in `role_permissions` count alongside ACL rows.
+    pub async fn check_permission(
+        &self,
+        repo_id: Uuid,
+        subject: &str,
+        roles: &[String],
+        role_permissions: &RolePermissionMap,
+        required: Permission,
+    ) -> Result<bool> {
+        if !roles.is_empty() && !role_permissions.grants.is_empty() {
+            let name: Option<String> = sqlx::query_scalar("SELECT name FROM repo WHERE id = $1")
+                .bind(repo_id)
+                .fetch_optional(&self.pool)
+                .await?;
+            let from_roles = name.and_then(|name| role_permissions.permission_for(roles, &name));
+            if from_roles.is_some_and(|perm| perm.grants(required)) {
+                return Ok(true);
+            }
+        }
+
+        let perms: Vec<String> = sqlx::query_scalar(
+            "SELECT perm FROM acl WHERE repo_id = $1 AND subject = $2
+             UNION ALL
+             SELECT 'admin' FROM repo WHERE id = $1 AND created_by = $2"
+        )
+        .bind(repo_id)
+        .bind(subject)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let held: Vec<Permission> = perms
+            .iter()
+            .filter_map(|perm| Permission::from_str(perm).ok())
+            .collect();
+
+        Ok(permission_granted(&held, required))
     }
 
     // Audit operations
@@ -592,68 +1444,68 @@ impl IndexClient {
         })
     }
 
+    /// Read audit entries matching `filter`, newest first, with the total
+    /// number of matches for pagination
+    pub async fn query_audit_log(
+        &self,
+        filter: &AuditLogFilter,
+        limit: Option<u32>,
+        offset: Option<u32>,
+    ) -> Result<(Vec<AuditLog>, u64)> {
+        let limit = audit_page_limit(limit);
+        let offset = offset.unwrap_or(0);
+
+        let mut query = QueryBuilder::<Postgres>::new(
+            "SELECT id, at, actor, action, repo_name, ref_name, path, request_meta, response_meta FROM audit_log",
+        );
+        push_audit_filters(&mut query, filter);
+        query.push(" ORDER BY at DESC, id DESC");
+        query.push(" LIMIT ").push_bind(limit as i64);
+        query.push(" OFFSET ").push_bind(offset as i64);
+
+        let rows = query.build().fetch_all(&self.pool).await?;
+        let entries = rows
+            .iter()
+            .map(|row| AuditLog {
+                id: row.get("id"),
+                at: row.get("at"),
+                actor: row.get("actor"),
+                action: row.get("action"),
+                repo_name: row.get("repo_name"),
+                ref_name: row.get("ref_name"),
+                path: row.get("path"),
+                request_meta: row.get("request_meta"),
+                response_meta: row.get("response_meta"),
+            })
+            .collect();
+
+        let mut count = QueryBuilder::<Postgres>::new("SELECT COUNT(*) FROM audit_log");
+        push_audit_filters(&mut count, filter);
+        let total: i64 = count.build_query_scalar().fetch_one(&self.pool).await?;
+
+        Ok((entries, total as u64))
+    }
+
     // Metadata indexing operations
 
     /// Upsert entry metadata index
     pub async fn upsert_entry_meta_index(&self, idx: &EntryMetaIndex) -> Result<()> {
-        sqlx::query(
-            "INSERT INTO entry_meta_index (
-                commit_id, path, creation_dt, creator, file_name, file_type, file_size,
-                org_lab, description, data_source, data_collection_method, version,
-                notes, tags, license
-            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
-            ON CONFLICT (commit_id, path) DO UPDATE SET
-                creation_dt = EXCLUDED.creation_dt,
-                creator = EXCLUDED.creator,
-                file_name = EXCLUDED.file_name,
-                file_type = EXCLUDED.file_type,
-                file_size = EXCLUDED.file_size,
-                org_lab = EXCLUDED.org_lab,
-                description = EXCLUDED.description,
-                data_source = EXCLUDED.data_source,
-                data_collection_method = EXCLUDED.data_collection_method,
-                version = EXCLUDED.version,
-                notes = EXCLUDED.notes,
-                tags = EXCLUDED.tags,
-                license = EXCLUDED.license"
-        )
-        .bind(idx.commit_id.0)
-        .bind(&idx.path)
-        .bind(idx.creation_dt)
-        .bind(&idx.creator)
-        .bind(&idx.file_name)
-        .bind(&idx.file_type)
-        .bind(idx.file_size)
-        .bind(&idx.org_lab)
-        .bind(&idx.description)
-        .bind(&idx.data_source)
-        .bind(&idx.data_collection_method)
-        .bind(&idx.version)
-        .bind(&idx.notes)
-        .bind(idx.tags.as_deref())
-        .bind(&idx.license)
-        .execute(&self.pool)
-        .await?;
-
-        Ok(())
-    }
-
-    // RDF operations
-
-    /// Store artifact RDF
-    pub async fn store_artifact_rdf(
-        &self,
-        commit_id: Uuid,
-        path: &str,
-        format: &RdfFormat,
-        graph_text: &str,
-        graph_sha256: &str,
-    ) -> Result<()> {
-        let format_str = match format {
-            RdfFormat::Turtle => "turtle",
-            RdfFormat::Jsonld => "jsonld",
-        };
-
+        upsert_meta_index_row(&self.pool, idx).await
+    }
+
+    // RDF operations
+
+    /// Store artifact RDF
+    pub async fn store_artifact_rdf(
+        &self,
+        commit_id: Uuid,
+        path: &str,
+        format: &RdfFormat,
+        graph_text: &str,
+        graph_sha256: &str,
+    ) -> Result<()> {
+        let format_str = format.as_str();
+
         sqlx::query(
             "INSERT INTO artifact_rdf (commit_id, path, format, graph, graph_sha256)
              VALUES ($1, $2, $3, $4, $5)
@@ -680,10 +1532,7 @@ impl IndexClient {
         path: &str,
         format: &RdfFormat,
     ) -> Result<Option<ArtifactRdf>> {
-        let format_str = match format {
-            RdfFormat::Turtle => "turtle",
-            RdfFormat::Jsonld => "jsonld",
-        };
+        let format_str = format.as_str();
 
         let row = sqlx::query(
             "SELECT commit_id, path, format, graph, graph_sha256, created_at
@@ -698,48 +1547,267 @@ impl IndexClient {
         Ok(row.map(|row| ArtifactRdf {
             commit_id: blacklake_core::UuidWrapper(row.get("commit_id")),
             path: row.get("path"),
-            format: match row.get::<String, _>("format").as_str() {
-                "turtle" => RdfFormat::Turtle,
-                "jsonld" => RdfFormat::Jsonld,
-                _ => RdfFormat::Turtle, // default
-            },
+            format: row
+                .get::<String, _>("format")
+                .parse()
+                .unwrap_or(RdfFormat::Turtle),
             graph: row.get("graph"),
             graph_sha256: row.get("graph_sha256"),
             created_at: row.get("created_at"),
         }))
     }
 
+    // Sample operations
+
+    /// Store the preview sample for an entry
+    pub async fn upsert_entry_sample(
+        &self,
+        commit_id: Uuid,
+        path: &str,
+        sample_json: &serde_json::Value,
+        schema_json: &serde_json::Value,
+    ) -> Result<()> {
+        sqlx::query(UPSERT_ENTRY_SAMPLE_SQL)
+            .bind(commit_id)
+            .bind(path)
+            .bind(sample_json)
+            .bind(schema_json)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Get the preview sample for an entry
+    pub async fn get_entry_sample(&self, commit_id: Uuid, path: &str) -> Result<Option<EntrySample>> {
+        let row = sqlx::query(
+            "SELECT commit_id, path, sample, schema, created_at
+             FROM entry_sample WHERE commit_id = $1 AND path = $2"
+        )
+        .bind(commit_id)
+        .bind(path)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|row| EntrySample {
+            commit_id: blacklake_core::UuidWrapper(row.get("commit_id")),
+            path: row.get("path"),
+            sample: row.get("sample"),
+            schema: row.get("schema"),
+            created_at: row.get("created_at"),
+        }))
+    }
+
     // Repository feature flags
 
-    /// Set repository feature flag
+    /// Set a repository feature flag; unknown keys and mistyped values are rejected.
+    ///
+    /// With `expected_version` the write only lands if the features are still
+    /// at that version, and fails with `FeaturesVersionMismatch` otherwise.
+    /// Returns the features as written and their new version.
     pub async fn set_repo_feature(
         &self,
         repo_id: Uuid,
         key: &str,
         value: &serde_json::Value,
-    ) -> Result<()> {
-        sqlx::query(
-            "UPDATE repo SET features = features || $2::jsonb WHERE id = $1"
+        expected_version: Option<i64>,
+    ) -> Result<(RepoFeatures, i64)> {
+        RepoFeature::validate(key, value)?;
+        let written: Option<(serde_json::Value, i64)> = sqlx::query_as(
+            "UPDATE repo SET features = features || $2::jsonb, features_version = features_version + 1
+             WHERE id = $1 AND ($3::BIGINT IS NULL OR features_version = $3)
+             RETURNING features, features_version"
         )
         .bind(repo_id)
-        .bind(serde_json::json!({ key: value })
-        )
-        .execute(&self.pool)
+        .bind(serde_json::json!({ key: value }))
+        .bind(expected_version)
+        .fetch_optional(&self.pool)
         .await?;
 
-        Ok(())
+        match (written, expected_version) {
+            (Some((features, version)), _) => Ok((RepoFeatures(features), version)),
+            (None, Some(expected)) => {
+                let (_, actual) = self.get_repo_features_versioned(repo_id).await?;
+                Err(IndexError::FeaturesVersionMismatch { expected, actual })
+            }
+            (None, None) => Err(IndexError::RepoNotFound(repo_id.to_string())),
+        }
     }
 
     /// Get repository features
-    pub async fn get_repo_features(&self, repo_id: Uuid) -> Result<serde_json::Value> {
+    pub async fn get_repo_features(&self, repo_id: Uuid) -> Result<RepoFeatures> {
+        Ok(self.get_repo_features_versioned(repo_id).await?.0)
+    }
+
+    /// Repository features along with their version, for a conditional `set_repo_feature`
+    pub async fn get_repo_features_versioned(&self, repo_id: Uuid) -> Result<(RepoFeatures, i64)> {
+        let row = sqlx::query(
+            "SELECT features, features_version FROM repo WHERE id = $1"
+        )
+        .bind(repo_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(match row {
+            Some(r) => (RepoFeatures(r.get("features")), r.get("features_version")),
+            None => (RepoFeatures(serde_json::Value::Object(serde_json::Map::new())), 0),
+        })
+    }
+
+    /// The connector a virtual repository proxies, or `None` for an ordinary repo
+    pub async fn get_repo_connector(&self, repo_id: Uuid) -> Result<Option<RepoConnector>> {
         let row = sqlx::query(
-            "SELECT features FROM repo WHERE id = $1"
+            "SELECT r.connector_id, r.connector_type, s.name, s.config
+             FROM repo r JOIN external_source s ON s.id = r.connector_id
+             WHERE r.id = $1"
+        )
+        .bind(repo_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|row| RepoConnector {
+            connector_id: row.get("connector_id"),
+            connector_type: row.get("connector_type"),
+            name: row.get("name"),
+            config: row.get("config"),
+        }))
+    }
+
+    /// Mark a repository as backed by connector `connector_id`, or clear the mark
+    /// with `None`. The connector's type is recorded alongside its id.
+    pub async fn set_repo_connector(&self, repo_id: Uuid, connector_id: Option<Uuid>) -> Result<()> {
+        let connector_type: Option<String> = match connector_id {
+            Some(id) => Some(
+                sqlx::query_scalar("SELECT connector_type FROM external_source WHERE id = $1")
+                    .bind(id)
+                    .fetch_optional(&self.pool)
+                    .await?
+                    .ok_or(IndexError::ConnectorNotFound(id))?,
+            ),
+            None => None,
+        };
+
+        let updated = sqlx::query("UPDATE repo SET connector_id = $2, connector_type = $3 WHERE id = $1")
+            .bind(repo_id)
+            .bind(connector_id)
+            .bind(connector_type)
+            .execute(&self.pool)
+            .await?;
+        if updated.rows_affected() == 0 {
+            return Err(IndexError::RepoNotFound(repo_id.to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// Claim idempotency key `key` for one call to `endpoint` on a repo.
+    ///
+    /// Returns `None` when the caller should run the request (the key is new,
+    /// or its previous use has expired) and the stored response when an
+    /// earlier request with the key already finished. A key still held by a
+    /// running request, or reused for a different request, is a conflict.
+    /// A claim older than `stale_before` that never finished is from a
+    /// replica that stopped mid-request, and the same request takes it over.
+    pub async fn claim_idempotency_key(
+        &self,
+        repo_id: Uuid,
+        endpoint: &str,
+        key: &str,
+        request_sha256: &str,
+        ttl: chrono::Duration,
+        stale_before: chrono::DateTime<Utc>,
+    ) -> Result<Option<IdempotentResponse>> {
+        let claimed = sqlx::query(
+            "INSERT INTO idempotency_key (repo_id, endpoint, key, request_sha256, claimed_at, expires_at)
+             VALUES ($1, $2, $3, $4, NOW(), $5)
+             ON CONFLICT (repo_id, endpoint, key) DO UPDATE
+                SET request_sha256 = EXCLUDED.request_sha256, status = NULL, response = NULL,
+                    response_headers = NULL, created_at = NOW(), claimed_at = NOW(),
+                    expires_at = EXCLUDED.expires_at
+                WHERE idempotency_key.expires_at <= NOW()
+                   OR (idempotency_key.status IS NULL
+                       AND idempotency_key.claimed_at < $6
+                       AND idempotency_key.request_sha256 = EXCLUDED.request_sha256)
+             RETURNING key"
         )
         .bind(repo_id)
+        .bind(endpoint)
+        .bind(key)
+        .bind(request_sha256)
+        .bind(Utc::now() + ttl)
+        .bind(stale_before)
         .fetch_optional(&self.pool)
         .await?;
+        if claimed.is_some() {
+            return Ok(None);
+        }
+
+        let row = sqlx::query(
+            "SELECT request_sha256, status, response, response_headers FROM idempotency_key
+             WHERE repo_id = $1 AND endpoint = $2 AND key = $3"
+        )
+        .bind(repo_id)
+        .bind(endpoint)
+        .bind(key)
+        .fetch_one(&self.pool)
+        .await?;
+
+        if row.get::<String, _>("request_sha256") != request_sha256 {
+            return Err(IndexError::IdempotencyConflict(format!(
+                "key {} was already used for a different request",
+                key
+            )));
+        }
+        match row.get::<Option<i16>, _>("status") {
+            Some(status) => Ok(Some(IdempotentResponse {
+                status: status as u16,
+                headers: row
+                    .get::<Option<serde_json::Value>, _>("response_headers")
+                    .and_then(|headers| serde_json::from_value(headers).ok())
+                    .unwrap_or_default(),
+                body: row.get::<Option<serde_json::Value>, _>("response").unwrap_or_default(),
+            })),
+            None => Err(IndexError::IdempotencyConflict(format!(
+                "a request with key {} is still in progress",
+                key
+            ))),
+        }
+    }
+
+    /// Record the response of the request holding idempotency key `key`
+    pub async fn complete_idempotency_key(
+        &self,
+        repo_id: Uuid,
+        endpoint: &str,
+        key: &str,
+        response: &IdempotentResponse,
+    ) -> Result<()> {
+        sqlx::query(
+            "UPDATE idempotency_key SET status = $4, response = $5, response_headers = $6
+             WHERE repo_id = $1 AND endpoint = $2 AND key = $3"
+        )
+        .bind(repo_id)
+        .bind(endpoint)
+        .bind(key)
+        .bind(response.status as i16)
+        .bind(&response.body)
+        .bind(serde_json::json!(response.headers))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
 
-        Ok(row.map(|r| r.get::<serde_json::Value, _>("features")).unwrap_or_else(|| serde_json::Value::Object(serde_json::Map::new())))
+    /// Give up a claimed idempotency key after its request failed, so a retry runs again
+    pub async fn release_idempotency_key(&self, repo_id: Uuid, endpoint: &str, key: &str) -> Result<()> {
+        sqlx::query(
+            "DELETE FROM idempotency_key WHERE repo_id = $1 AND endpoint = $2 AND key = $3 AND status IS NULL"
+        )
+        .bind(repo_id)
+        .bind(endpoint)
+        .bind(key)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
     }
 
     /// Enhanced search with metadata index
@@ -751,81 +1819,55 @@ impl IndexClient {
         limit: Option<u32>,
         offset: Option<u32>,
     ) -> Result<(Vec<Entry>, u32)> {
-        let mut query = "SELECT e.commit_id, e.path, e.object_sha256, e.meta, e.is_dir 
-                        FROM entry e 
-                        JOIN commit c ON e.commit_id = c.id 
-                        LEFT JOIN entry_meta_index emi ON e.commit_id = emi.commit_id AND e.path = emi.path
-                        WHERE c.repo_id = $1".to_string();
-
-        let mut params: Vec<Box<dyn sqlx::Encode<'_, Postgres> + Send + Sync>> = vec![Box::new(repo_id)];
-        let mut param_count = 1;
-
-        // Add fast-path filters using metadata index
-        for (key, value) in filters {
-            param_count += 1;
-            match key.as_str() {
-                "file_type" | "org_lab" | "creator" | "file_name" => {
-                    if let Some(s) = value.as_str() {
-                        query.push_str(&format!(" AND emi.{} = ${}", key, param_count));
-                        params.push(Box::new(s.to_string()));
-                    }
-                }
-                "tags" => {
-                    if let Some(tag) = value.as_str() {
-                        query.push_str(&format!(" AND ${} = ANY(emi.tags)", param_count));
-                        params.push(Box::new(tag.to_string()));
-                    }
-                }
-                "creation_dt_after" => {
-                    if let Some(s) = value.as_str() {
-                        query.push_str(&format!(" AND emi.creation_dt >= ${}", param_count));
-                        params.push(Box::new(s.to_string()));
-                    }
-                }
-                "creation_dt_before" => {
-                    if let Some(s) = value.as_str() {
-                        query.push_str(&format!(" AND emi.creation_dt <= ${}", param_count));
-                        params.push(Box::new(s.to_string()));
-                    }
-                }
-                _ => {
-                    // Fallback to JSONB query
-                    query.push_str(&format!(" AND e.meta->>'{}' = ${}", key, param_count));
-                    params.push(Box::new(value.clone()));
-                }
-            }
-        }
+        let mut query = QueryBuilder::<Postgres>::new(INDEXED_SEARCH_SELECT);
+        push_index_filters(&mut query, repo_id, filters)?;
 
         // Add sorting
         if let Some(sort_field) = sort {
             match sort_field {
                 "file_name" | "file_type" | "org_lab" | "creation_dt" => {
-                    query.push_str(&format!(" ORDER BY emi.{}", sort_field));
+                    query.push(format!(" ORDER BY emi.{}, e.path", sort_field));
                 }
                 _ => {
-                    query.push_str(&format!(" ORDER BY e.meta->>'{}'", sort_field));
+                    query.push(" ORDER BY e.meta->>").push_bind(sort_field.to_string());
+                    query.push(", e.path");
                 }
             }
         } else {
-            query.push_str(" ORDER BY e.path");
+            query.push(" ORDER BY e.path");
         }
 
         // Add pagination
         if let Some(limit_val) = limit {
-            param_count += 1;
-            query.push_str(&format!(" LIMIT ${}", param_count));
-            params.push(Box::new(limit_val as i32));
+            query.push(" LIMIT ").push_bind(limit_val as i64);
         }
 
         if let Some(offset_val) = offset {
-            param_count += 1;
-            query.push_str(&format!(" OFFSET ${}", param_count));
-            params.push(Box::new(offset_val as i32));
+            query.push(" OFFSET ").push_bind(offset_val as i64);
         }
 
-        // TODO: Implement dynamic query building with proper parameter binding
-        // For now, return empty results
-        Ok((vec![], 0))
+        let rows = query.build().fetch_all(&self.pool).await?;
+
+        let entries: Vec<Entry> = rows.into_iter().map(|row| Entry {
+            id: blacklake_core::UuidWrapper(row.get::<Option<Uuid>, _>("id").unwrap_or_default()),
+            commit_id: blacklake_core::UuidWrapper(row.get("commit_id")),
+            path: row.get("path"),
+            object_sha256: row.get("object_sha256"),
+            meta: row.get("meta"),
+            is_dir: row.get("is_dir"),
+            created_at: row.get::<Option<chrono::DateTime<Utc>>, _>("created_at").unwrap_or_else(Utc::now),
+            size: None,
+            media_type: None,
+        }).collect();
+
+        let mut count_query = QueryBuilder::<Postgres>::new(INDEXED_SEARCH_COUNT);
+        push_index_filters(&mut count_query, repo_id, filters)?;
+        let total_count: i64 = count_query
+            .build_query_scalar()
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok((entries, total_count as u32))
     }
 
     // ===== GOVERNANCE METHODS =====
@@ -995,6 +2037,93 @@ impl IndexClient {
         }))
     }
 
+    /// Retention policies of every repository that has one configured
+    pub async fn list_retention_policies(&self) -> Result<Vec<RetentionPolicy>> {
+        let rows: Vec<serde_json::Value> = sqlx::query_scalar(
+            "SELECT retention_policy FROM repo_retention"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(serde_json::from_value)
+            .collect::<std::result::Result<Vec<_>, _>>()?)
+    }
+
+    /// Repositories with a configured retention policy, by id and name
+    pub async fn list_repos_with_retention(&self) -> Result<Vec<(Uuid, String)>> {
+        let repos = sqlx::query_as::<_, (Uuid, String)>(
+            "SELECT r.id, r.name FROM repo r JOIN repo_retention rr ON rr.repo_id = r.id ORDER BY r.name"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(repos)
+    }
+
+    /// Apply a repository's retention policy to its deleted entries as of `now`.
+    ///
+    /// Entries deleted at least `tombstone_days` ago are tombstoned; tombstoned
+    /// entries deleted at least `hard_delete_days` ago are removed along with
+    /// their metadata index, RDF and sample rows. Every action is written to
+    /// the audit log in the same transaction. Nothing happens under legal hold
+    /// or without a policy. Objects the removed entries pointed at are
+    /// returned for the caller to collect once nothing else references them.
+    pub async fn enforce_retention(&self, repo_id: Uuid, now: chrono::DateTime<Utc>) -> Result<RetentionRun> {
+        let mut tx = self.pool.begin().await?;
+
+        let row: Option<(String, serde_json::Value)> = sqlx::query_as(
+            "SELECT r.name, rr.retention_policy FROM repo r
+             JOIN repo_retention rr ON rr.repo_id = r.id
+             WHERE r.id = $1"
+        )
+        .bind(repo_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some((repo_name, policy)) = row else {
+            return Ok(RetentionRun::default());
+        };
+        let policy: RetentionPolicy = serde_json::from_value(policy)?;
+        if policy.legal_hold {
+            return Ok(RetentionRun { legal_hold: true, ..Default::default() });
+        }
+
+        let tombstone_cutoff = now - chrono::Duration::days(policy.tombstone_days as i64);
+        let tombstoned = sqlx::query_as::<_, (Uuid, String)>(TOMBSTONE_DELETED_ENTRIES_SQL)
+            .bind(repo_id)
+            .bind(tombstone_cutoff)
+            .bind(now)
+            .fetch_all(&mut *tx)
+            .await?;
+        for (commit_id, path) in &tombstoned {
+            append_retention_audit(&mut tx, now, "retention_tombstone", &repo_name, path, *commit_id).await?;
+        }
+
+        let hard_delete_cutoff = now - chrono::Duration::days(policy.hard_delete_days as i64);
+        let hard_deleted = sqlx::query_as::<_, (Uuid, String, Option<String>)>(HARD_DELETE_TOMBSTONED_ENTRIES_SQL)
+            .bind(repo_id)
+            .bind(hard_delete_cutoff)
+            .fetch_all(&mut *tx)
+            .await?;
+        for (commit_id, path, _) in &hard_deleted {
+            append_retention_audit(&mut tx, now, "retention_hard_delete", &repo_name, path, *commit_id).await?;
+        }
+
+        tx.commit().await?;
+
+        let mut released_objects: Vec<String> = hard_deleted.iter().filter_map(|(_, _, sha256)| sha256.clone()).collect();
+        released_objects.sort();
+        released_objects.dedup();
+
+        Ok(RetentionRun {
+            legal_hold: false,
+            tombstoned: tombstoned.into_iter().map(|(_, path)| path).collect(),
+            hard_deleted: hard_deleted.into_iter().map(|(_, path, _)| path).collect(),
+            released_objects,
+        })
+    }
+
     /// Set retention policy for a repository
     pub async fn set_repo_retention(&self, retention: &RepoRetention) -> Result<()> {
         sqlx::query(
@@ -1013,6 +2142,29 @@ impl IndexClient {
         Ok(())
     }
 
+    /// The most recent content-integrity verification run of a repository
+    pub async fn latest_integrity_report(&self, repo_id: Uuid) -> Result<Option<IntegrityReportSummary>> {
+        let row = sqlx::query(
+            "SELECT id, status, objects_checked, mismatches, started_at, completed_at
+             FROM integrity_report
+             WHERE repo_id = $1
+             ORDER BY started_at DESC
+             LIMIT 1"
+        )
+        .bind(repo_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|r| IntegrityReportSummary {
+            id: r.get("id"),
+            status: r.get("status"),
+            objects_checked: r.get("objects_checked"),
+            mismatches: r.get("mismatches"),
+            started_at: r.get("started_at"),
+            completed_at: r.get("completed_at"),
+        }))
+    }
+
 
 
 
@@ -1161,15 +2313,16 @@ impl IndexClient {
         Ok(())
     }
 
-    /// Create webhook delivery
+    /// Create webhook delivery; a delivery whose id already exists is left as is
     pub async fn create_webhook_delivery(&self, delivery: &WebhookDelivery) -> Result<()> {
         sqlx::query(
             r#"
             INSERT INTO webhook_deliveries (
-                id, webhook_id, event, payload, status, attempts, max_attempts,
+                id, webhook_id, event_type, payload, status, attempts, max_attempts,
                 next_retry_at, response_status, response_body, delivered_at
             )
             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            ON CONFLICT (id) DO NOTHING
             "#
         )
         .bind(delivery.id)
@@ -1212,11 +2365,68 @@ impl IndexClient {
         Ok(())
     }
 
+    /// Record the outcome of a delivery attempt made by the delivery worker
+    pub async fn record_webhook_attempt(
+        &self,
+        delivery: &WebhookDelivery,
+        status: &str,
+        error_message: Option<&str>,
+    ) -> Result<()> {
+        sqlx::query(
+            "
+            UPDATE webhook_deliveries SET
+                status = $2, attempts = $3, next_retry_at = $4, response_status = $5,
+                response_body = $6, delivered_at = $7, error_message = $8, last_attempt_at = NOW()
+            WHERE id = $1
+            "
+        )
+        .bind(delivery.id)
+        .bind(status)
+        .bind(delivery.attempts as i32)
+        .bind(delivery.next_retry_at)
+        .bind(delivery.response_status.map(|s| s as i32))
+        .bind(&delivery.response_body)
+        .bind(delivery.delivered_at)
+        .bind(error_message)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Move a delivery that ran out of attempts to the dead letter table
+    pub async fn dead_letter_webhook_delivery(&self, delivery: &WebhookDelivery, failure_reason: &str) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO webhook_dead (id, webhook_id, event_type, payload, attempts, failure_reason)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#
+        )
+        .bind(delivery.id)
+        .bind(delivery.webhook_id)
+        .bind(&delivery.event_type)
+        .bind(&delivery.payload)
+        .bind(delivery.attempts as i32)
+        .bind(failure_reason)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query("DELETE FROM webhook_deliveries WHERE id = $1")
+            .bind(delivery.id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
     /// Get webhook delivery by ID
     pub async fn get_webhook_delivery(&self, delivery_id: Uuid) -> Result<WebhookDelivery> {
         let row = sqlx::query(
             "
-            SELECT id, webhook_id, event, payload, status, attempts, max_attempts, last_attempt_at,
+            SELECT id, webhook_id, event_type, payload, status, attempts, max_attempts, last_attempt_at,
                    next_retry_at, response_status, response_body, error_message, delivered_at, created_at, updated_at
             FROM webhook_deliveries
             WHERE id = $1
@@ -1229,7 +2439,7 @@ impl IndexClient {
         let delivery = WebhookDelivery {
             id: row.get("id"),
             webhook_id: row.get("webhook_id"),
-            event_type: row.get("event"),
+            event_type: row.get("event_type"),
             payload: row.get("payload"),
             response_status: row.get::<Option<i32>, _>("response_status").map(|s| s as u16),
             response_body: row.get("response_body"),
@@ -1242,28 +2452,46 @@ impl IndexClient {
         Ok(delivery)
     }
 
-    /// Get pending webhook deliveries
-    pub async fn get_pending_webhook_deliveries(&self) -> Result<Vec<WebhookDelivery>> {
-        let rows = sqlx::query(
+    /// Claim up to `limit` due webhook deliveries for this worker.
+    ///
+    /// Claimed rows are marked `in_flight` in the same statement that picks
+    /// them, skipping rows another worker has locked, so each delivery goes
+    /// to one replica. An `in_flight` claim older than `stale_before` is from
+    /// a worker that stopped before recording an outcome and is claimed again.
+    pub async fn claim_due_webhook_deliveries(
+        &self,
+        limit: i64,
+        stale_before: chrono::DateTime<Utc>,
+    ) -> Result<Vec<WebhookDelivery>> {
+        let mut rows = sqlx::query(
             r#"
-            SELECT id, webhook_id, event, payload, status, attempts, max_attempts, last_attempt_at,
-                   next_retry_at, response_status, response_body, error_message, delivered_at, created_at, updated_at
-            FROM webhook_deliveries
-            WHERE status IN ('pending', 'failed') AND (next_retry_at IS NULL OR next_retry_at <= $1)
-            ORDER BY created_at ASC
-            LIMIT 100
+            UPDATE webhook_deliveries SET status = 'in_flight', claimed_at = $1
+            WHERE id IN (
+                SELECT id FROM webhook_deliveries
+                WHERE (status IN ('pending', 'failed') AND (next_retry_at IS NULL OR next_retry_at <= $1))
+                   OR (status = 'in_flight' AND claimed_at < $2)
+                ORDER BY created_at ASC
+                LIMIT $3
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, webhook_id, event_type, payload, attempts, max_attempts,
+                      next_retry_at, response_status, response_body, delivered_at, created_at
             "#
         )
-        .bind(chrono::Utc::now())
+        .bind(Utc::now())
+        .bind(stale_before)
+        .bind(limit)
         .fetch_all(&self.pool)
         .await?;
 
+        // RETURNING does not keep the subquery's order
+        rows.sort_by_key(|row| row.get::<chrono::DateTime<Utc>, _>("created_at"));
         let deliveries = rows
             .into_iter()
             .map(|row| WebhookDelivery {
                 id: row.get("id"),
                 webhook_id: row.get("webhook_id"),
-                event_type: row.get("event"),
+                event_type: row.get("event_type"),
                 payload: row.get("payload"),
                 response_status: row.get::<Option<i32>, _>("response_status").map(|s| s as u16),
                 response_body: row.get("response_body"),
@@ -1281,7 +2509,7 @@ impl IndexClient {
     pub async fn get_webhook_deliveries(&self, webhook_id: Uuid) -> Result<Vec<WebhookDelivery>> {
         let rows = sqlx::query(
             "
-            SELECT id, webhook_id, event, payload, attempts, max_attempts,
+            SELECT id, webhook_id, event_type, payload, attempts, max_attempts,
                    next_retry_at, response_status, response_body, delivered_at
             FROM webhook_deliveries
             WHERE webhook_id = $1
@@ -1298,7 +2526,7 @@ impl IndexClient {
             .map(|row| WebhookDelivery {
                 id: row.get("id"),
                 webhook_id: row.get("webhook_id"),
-                event_type: row.get("event"),
+                event_type: row.get("event_type"),
                 payload: row.get("payload"),
                 response_status: row.get::<Option<i32>, _>("response_status").map(|s| s as u16),
                 response_body: row.get("response_body"),
@@ -1330,7 +2558,7 @@ impl IndexClient {
     pub async fn create_webhook_dead(&self, dead: &WebhookDead) -> Result<()> {
         sqlx::query(
             r#"
-            INSERT INTO webhook_dead (id, webhook_id, event, payload, attempts, last_error)
+            INSERT INTO webhook_dead (id, webhook_id, event_type, payload, attempts, last_error)
             VALUES ($1, $2, $3, $4, $5, $6)
             "#
         )
@@ -1346,28 +2574,82 @@ impl IndexClient {
         Ok(())
     }
 
-    /// Get webhook dead letter records for a repository
-    pub async fn get_webhook_dead_letter(&self, repo_id: Uuid) -> Result<Vec<WebhookDead>> {
-        let rows = sqlx::query(
+    /// Get a webhook dead letter record by ID
+    pub async fn get_webhook_dead(&self, dead_id: Uuid) -> Result<WebhookDead> {
+        let row = sqlx::query(
             "
-            SELECT wd.id, wd.webhook_id, wd.event, wd.payload, wd.attempts, wd.failure_reason
-            FROM webhook_dead wd
-            JOIN webhooks w ON wd.webhook_id = w.id
-            WHERE w.repo_id = $1
-            ORDER BY wd.moved_at DESC
-            LIMIT 100
+            SELECT id, webhook_id, event_type, payload, attempts, failure_reason
+            FROM webhook_dead
+            WHERE id = $1
             "
         )
-        .bind(repo_id)
-        .fetch_all(&self.pool)
+        .bind(dead_id)
+        .fetch_one(&self.pool)
         .await?;
 
-        let dead_webhooks = rows
+        Ok(WebhookDead {
+            id: row.get("id"),
+            webhook_id: row.get("webhook_id"),
+            event_type: row.get("event_type"),
+            payload: row.get("payload"),
+            failure_reason: row.get::<Option<String>, _>("failure_reason").unwrap_or_default(),
+            attempts: row.get::<i32, _>("attempts") as u32,
+        })
+    }
+
+    /// Replace a dead letter record with a new pending delivery
+    pub async fn requeue_webhook_dead(&self, dead_id: Uuid, delivery: &WebhookDelivery) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO webhook_deliveries (
+                id, webhook_id, event_type, payload, status, attempts, max_attempts, next_retry_at
+            )
+            VALUES ($1, $2, $3, $4, 'pending', $5, $6, $7)
+            "#
+        )
+        .bind(delivery.id)
+        .bind(delivery.webhook_id)
+        .bind(&delivery.event_type)
+        .bind(&delivery.payload)
+        .bind(delivery.attempts as i32)
+        .bind(delivery.max_attempts as i32)
+        .bind(delivery.next_retry_at)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query("DELETE FROM webhook_dead WHERE id = $1")
+            .bind(dead_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Get webhook dead letter records for a repository
+    pub async fn get_webhook_dead_letter(&self, repo_id: Uuid) -> Result<Vec<WebhookDead>> {
+        let rows = sqlx::query(
+            "
+            SELECT wd.id, wd.webhook_id, wd.event_type, wd.payload, wd.attempts, wd.failure_reason
+            FROM webhook_dead wd
+            JOIN webhooks w ON wd.webhook_id = w.id
+            WHERE w.repo_id = $1
+            ORDER BY wd.moved_at DESC
+            LIMIT 100
+            "
+        )
+        .bind(repo_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let dead_webhooks = rows
             .into_iter()
             .map(|row| WebhookDead {
                 id: row.get("id"),
                 webhook_id: row.get("webhook_id"),
-                event_type: row.get("event"),
+                event_type: row.get("event_type"),
                 payload: row.get("payload"),
                 failure_reason: row.get("failure_reason"),
                 attempts: row.get::<i32, _>("attempts") as u32,
@@ -1384,9 +2666,9 @@ impl IndexClient {
         sqlx::query(
             r#"
             INSERT INTO export_jobs (
-                id, repo_id, user_id, manifest, status, s3_key, download_url, error_message
+                id, repo_id, user_id, manifest, status, s3_key, download_url, error_message, expires_at
             )
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
             "#
         )
         .bind(job.id)
@@ -1397,6 +2679,7 @@ impl IndexClient {
         .bind(&job.s3_key)
         .bind(&job.download_url)
         .bind(&job.error_message)
+        .bind(job.expires_at)
         .execute(&self.pool)
         .await?;
 
@@ -1407,7 +2690,7 @@ impl IndexClient {
     pub async fn get_export_job(&self, job_id: Uuid) -> Result<ExportJob> {
         let row = sqlx::query(
             "
-            SELECT id, repo_id, user_id, manifest, status, s3_key, download_url, error_message
+            SELECT id, repo_id, user_id, manifest, status, s3_key, download_url, error_message, expires_at
             FROM export_jobs
             WHERE id = $1
             "
@@ -1425,6 +2708,7 @@ impl IndexClient {
             s3_key: row.get("s3_key"),
             download_url: row.get("download_url"),
             error_message: row.get("error_message"),
+            expires_at: row.get("expires_at"),
         };
 
         Ok(job)
@@ -1435,14 +2719,16 @@ impl IndexClient {
         sqlx::query(
             "
             UPDATE export_jobs SET
-                status = $2, download_url = $3, error_message = $4
+                status = $2, s3_key = $3, download_url = $4, error_message = $5, expires_at = $6
             WHERE id = $1
             "
         )
         .bind(job.id)
         .bind(&job.status.to_string())
+        .bind(&job.s3_key)
         .bind(&job.download_url)
         .bind(&job.error_message)
+        .bind(job.expires_at)
         .execute(&self.pool)
         .await?;
 
@@ -1453,7 +2739,7 @@ impl IndexClient {
     pub async fn get_pending_export_jobs(&self) -> Result<Vec<ExportJob>> {
         let rows = sqlx::query(
             "
-            SELECT id, repo_id, user_id, manifest, status, s3_key, download_url, error_message
+            SELECT id, repo_id, user_id, manifest, status, s3_key, download_url, error_message, expires_at
             FROM export_jobs
             WHERE status = 'pending'
             ORDER BY id ASC
@@ -1474,28 +2760,31 @@ impl IndexClient {
                     paths: vec![],
                     include_meta: true,
                     include_rdf: false,
+                    format: ExportFormat::TarGz,
                 }),
                 status: ExportJobStatus::from_str(&row.get::<String, _>("status")).unwrap_or(ExportJobStatus::Pending),
                 s3_key: row.get("s3_key"),
                 download_url: row.get("download_url"),
                 error_message: row.get("error_message"),
+                expires_at: row.get("expires_at"),
             })
             .collect();
 
         Ok(jobs)
     }
 
-    /// Get expired export jobs
-    pub async fn get_expired_export_jobs(&self, _now: u64) -> Result<Vec<ExportJob>> {
+    /// Get export jobs whose download window closed at or before `now`
+    pub async fn get_expired_export_jobs(&self, now: chrono::DateTime<chrono::Utc>) -> Result<Vec<ExportJob>> {
         let rows = sqlx::query(
             "
-            SELECT id, repo_id, user_id, manifest, status, s3_key, download_url, error_message
+            SELECT id, repo_id, user_id, manifest, status, s3_key, download_url, error_message, expires_at
             FROM export_jobs
-            WHERE status = 'completed'
-            ORDER BY id ASC
+            WHERE expires_at IS NOT NULL AND expires_at <= $1
+            ORDER BY expires_at ASC
             "
         )
-                .fetch_all(&self.pool)
+        .bind(now)
+        .fetch_all(&self.pool)
         .await?;
 
         let jobs = rows
@@ -1509,11 +2798,13 @@ impl IndexClient {
                     paths: vec![],
                     include_meta: true,
                     include_rdf: false,
+                    format: ExportFormat::TarGz,
                 }),
                 status: ExportJobStatus::from_str(&row.get::<String, _>("status")).unwrap_or(ExportJobStatus::Pending),
                 s3_key: row.get("s3_key"),
                 download_url: row.get("download_url"),
                 error_message: row.get("error_message"),
+                expires_at: row.get("expires_at"),
             })
             .collect();
 
@@ -1562,9 +2853,2605 @@ impl IndexClient {
                 meta: row.get("meta"),
                 is_dir: false, // TODO: get from database
                 created_at: row.get("created_at"),
+                size: None,
+                media_type: None,
             })
             .collect();
 
         Ok(entries)
     }
-}
\ No newline at end of file
+}
+
+const SEARCH_ENTRIES_SELECT: &str =
+    "SELECT e.id, e.commit_id, e.path, e.object_sha256, e.meta, e.is_dir, e.created_at
+     FROM entry e
+     JOIN commit c ON e.commit_id = c.id
+     LEFT JOIN object o ON e.object_sha256 = o.sha256";
+
+const SEARCH_ENTRIES_COUNT: &str =
+    "SELECT COUNT(*)
+     FROM entry e
+     JOIN commit c ON e.commit_id = c.id
+     LEFT JOIN object o ON e.object_sha256 = o.sha256";
+
+const UPSERT_ENTRY_META_INDEX_SQL: &str = "INSERT INTO entry_meta_index (
+         commit_id, path, creation_dt, creator, file_name, file_type, file_size,
+         org_lab, description, data_source, data_collection_method, version,
+         notes, tags, license
+     ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
+     ON CONFLICT (commit_id, path) DO UPDATE SET
+         creation_dt = EXCLUDED.creation_dt,
+         creator = EXCLUDED.creator,
+         file_name = EXCLUDED.file_name,
+         file_type = EXCLUDED.file_type,
+         file_size = EXCLUDED.file_size,
+         org_lab = EXCLUDED.org_lab,
+         description = EXCLUDED.description,
+         data_source = EXCLUDED.data_source,
+         data_collection_method = EXCLUDED.data_collection_method,
+         version = EXCLUDED.version,
+         notes = EXCLUDED.notes,
+         tags = EXCLUDED.tags,
+         license = EXCLUDED.license";
+
+async fn upsert_meta_index_row<'e>(executor: impl sqlx::PgExecutor<'e>, idx: &EntryMetaIndex) -> Result<()> {
+    sqlx::query(UPSERT_ENTRY_META_INDEX_SQL)
+        .bind(idx.commit_id.0)
+        .bind(&idx.path)
+        .bind(idx.creation_dt)
+        .bind(&idx.creator)
+        .bind(&idx.file_name)
+        .bind(&idx.file_type)
+        .bind(idx.file_size)
+        .bind(&idx.org_lab)
+        .bind(&idx.description)
+        .bind(&idx.data_source)
+        .bind(&idx.data_collection_method)
+        .bind(&idx.version)
+        .bind(&idx.notes)
+        .bind(idx.tags.as_deref())
+        .bind(&idx.license)
+        .execute(executor)
+        .await?;
+
+    Ok(())
+}
+
+/// A free-text search split into `"quoted phrases"` and loose words
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FulltextQuery {
+    pub phrases: Vec<String>,
+    pub words: String,
+}
+
+impl FulltextQuery {
+    /// Split on double quotes; an unterminated quote runs to the end
+    pub fn parse(input: &str) -> Self {
+        let mut phrases = Vec::new();
+        let mut words = Vec::new();
+        for (i, part) in input.split('"').enumerate() {
+            let part = part.split_whitespace().collect::<Vec<_>>().join(" ");
+            if part.is_empty() {
+                continue;
+            }
+            if i % 2 == 1 {
+                phrases.push(part);
+            } else {
+                words.push(part);
+            }
+        }
+        Self { phrases, words: words.join(" ") }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.phrases.is_empty() && self.words.is_empty()
+    }
+
+    /// The whole query as one phrase, used to boost exact matches
+    fn as_phrase(&self) -> String {
+        self.phrases
+            .iter()
+            .map(String::as_str)
+            .chain(Some(self.words.as_str()).filter(|w| !w.is_empty()))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+const FULLTEXT_SEARCH_SELECT: &str =
+    "SELECT e.id, e.commit_id, e.path, e.object_sha256, e.meta, e.is_dir, e.created_at,
+            ts_rank_cd(emi.search_vector, q.query)
+              + CASE WHEN emi.search_vector @@ q.phrase THEN 1.0 ELSE 0.0 END AS rank";
+
+const NEAREST_ENTRIES_QUERY: &str = "SELECT * FROM (
+       SELECT DISTINCT ON (e.path)
+              e.id, e.commit_id, e.path, e.object_sha256, e.meta, e.is_dir, e.created_at,
+              ee.embedding <=> $2::vector AS distance
+       FROM entry_embedding ee
+       JOIN entry e ON e.commit_id = ee.commit_id AND e.path = ee.path
+       JOIN commit c ON c.id = ee.commit_id
+       WHERE c.repo_id = $1
+       ORDER BY e.path, c.created_at DESC
+     ) latest
+     ORDER BY distance ASC, path ASC
+     LIMIT $3";
+
+/// Append the FROM and WHERE clauses shared by the full-text select and count
+fn push_fulltext_from(query: &mut QueryBuilder<'_, Postgres>, repo_id: Uuid, fulltext: &FulltextQuery) {
+    query.push(
+        " FROM entry e
+     JOIN commit c ON e.commit_id = c.id
+     JOIN entry_meta_index emi ON e.commit_id = emi.commit_id AND e.path = emi.path
+     CROSS JOIN (SELECT ",
+    );
+    let mut parts = query.separated(" && ");
+    for phrase in &fulltext.phrases {
+        parts.push("phraseto_tsquery('english', ").push_bind_unseparated(phrase.clone()).push_unseparated(")");
+    }
+    if !fulltext.words.is_empty() {
+        parts.push("plainto_tsquery('english', ").push_bind_unseparated(fulltext.words.clone()).push_unseparated(")");
+    }
+    query
+        .push(" AS query, phraseto_tsquery('english', ")
+        .push_bind(fulltext.as_phrase())
+        .push(") AS phrase) q");
+    query.push(" WHERE c.repo_id = ").push_bind(repo_id);
+    query.push(" AND emi.search_vector @@ q.query");
+}
+
+fn entry_from_search_row(row: &sqlx::postgres::PgRow) -> Entry {
+    Entry {
+        id: blacklake_core::UuidWrapper(row.get::<Option<Uuid>, _>("id").unwrap_or_default()),
+        commit_id: blacklake_core::UuidWrapper(row.get("commit_id")),
+        path: row.get("path"),
+        object_sha256: row.get("object_sha256"),
+        meta: row.get("meta"),
+        is_dir: row.get("is_dir"),
+        created_at: row.get::<Option<chrono::DateTime<Utc>>, _>("created_at").unwrap_or_else(Utc::now),
+        size: None,
+        media_type: None,
+    }
+}
+
+/// Position of the last entry on a keyset-paginated search page
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchCursor {
+    pub created_at: chrono::DateTime<Utc>,
+    pub id: Uuid,
+}
+
+impl SearchCursor {
+    pub fn for_entry(entry: &Entry) -> Self {
+        Self {
+            created_at: entry.created_at,
+            id: entry.id.0,
+        }
+    }
+
+    /// URL-safe base64 of `<rfc3339 created_at>|<id>`
+    pub fn encode(&self) -> String {
+        use base64::Engine;
+        let raw = format!(
+            "{}|{}",
+            self.created_at.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true),
+            self.id
+        );
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(raw)
+    }
+
+    pub fn decode(cursor: &str) -> Result<Self> {
+        use base64::Engine;
+        let invalid = || IndexError::InvalidCursor(cursor.to_string());
+
+        let raw = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(cursor)
+            .map_err(|_| invalid())?;
+        let raw = String::from_utf8(raw).map_err(|_| invalid())?;
+        let (created_at, id) = raw.split_once('|').ok_or_else(invalid)?;
+
+        Ok(Self {
+            created_at: chrono::DateTime::parse_from_rfc3339(created_at)
+                .map_err(|_| invalid())?
+                .with_timezone(&Utc),
+            id: Uuid::parse_str(id).map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// Append the keyset predicate, ordering and limit for `search_entries_keyset`.
+///
+/// Fetches one row more than `limit` so the caller can tell whether another
+/// page exists without a COUNT query.
+fn push_keyset_page(
+    query: &mut QueryBuilder<'_, Postgres>,
+    sort: &SortOrder,
+    cursor: Option<&SearchCursor>,
+    limit: u32,
+) {
+    let (comparison, direction) = match sort {
+        SortOrder::Asc => (">", "ASC"),
+        SortOrder::Desc => ("<", "DESC"),
+    };
+
+    if let Some(cursor) = cursor {
+        query.push(format!(" AND (e.created_at, e.id) {} (", comparison));
+        query.push_bind(cursor.created_at);
+        query.push(", ");
+        query.push_bind(cursor.id);
+        query.push(")");
+    }
+
+    query.push(format!(" ORDER BY e.created_at {0}, e.id {0}", direction));
+    query.push(" LIMIT ").push_bind(limit as i64 + 1);
+}
+
+/// ORDER BY for `search_entries`. The entry id breaks ties, so rows with
+/// equal sort keys keep one order and offset pages never overlap or skip.
+fn search_order_by(sort: SearchSort) -> &'static str {
+    match sort {
+        SearchSort::CreatedAt => " ORDER BY e.created_at DESC, e.id DESC",
+        SearchSort::Path => " ORDER BY e.path ASC, e.id ASC",
+        SearchSort::Size => " ORDER BY o.size DESC NULLS LAST, e.id DESC",
+    }
+}
+
+/// Trim a `limit + 1` row fetch to `limit` and derive the next cursor
+fn split_keyset_page(mut entries: Vec<Entry>, limit: u32) -> (Vec<Entry>, Option<String>) {
+    if entries.len() <= limit as usize {
+        return (entries, None);
+    }
+
+    entries.truncate(limit as usize);
+    let next_cursor = entries.last().map(|entry| SearchCursor::for_entry(entry).encode());
+    (entries, next_cursor)
+}
+
+/// Append the WHERE clause for `search_entries` to a query.
+///
+/// Shared by the row and count queries so pagination totals always reflect
+/// the same filters. Values arriving from query strings are strings, so
+/// numeric and list filters accept both JSON and string forms.
+/// Page size `query_audit_log` uses for a requested `limit`
+pub fn audit_page_limit(limit: Option<u32>) -> u32 {
+    limit.unwrap_or(50).min(1000)
+}
+
+fn push_audit_filters(query: &mut QueryBuilder<'_, Postgres>, filter: &AuditLogFilter) {
+    query.push(" WHERE TRUE");
+    let columns = [
+        ("actor", &filter.actor),
+        ("action", &filter.action),
+        ("repo_name", &filter.repo_name),
+        ("ref_name", &filter.ref_name),
+    ];
+    for (column, value) in columns {
+        if let Some(value) = value {
+            query.push(format!(" AND {} = ", column)).push_bind(value.clone());
+        }
+    }
+    if let Some(since) = filter.since {
+        query.push(" AND at >= ").push_bind(since);
+    }
+    if let Some(until) = filter.until {
+        query.push(" AND at < ").push_bind(until);
+    }
+}
+
+fn push_search_filters(
+    query: &mut QueryBuilder<'_, Postgres>,
+    repo_id: Uuid,
+    filters: &HashMap<String, serde_json::Value>,
+) -> Result<()> {
+    query.push(" WHERE c.repo_id = ").push_bind(repo_id);
+
+    for (key, value) in filters {
+        match key.as_str() {
+            "path" => {
+                query.push(" AND e.path ILIKE ").push_bind(format!("%{}%", filter_as_str(key, value)?));
+            }
+            "file_type" => {
+                query.push(" AND e.meta->>'file_type' = ").push_bind(filter_as_str(key, value)?.to_string());
+            }
+            "size_min" => {
+                query.push(" AND o.size >= ").push_bind(filter_as_i64(key, value)?);
+            }
+            "size_max" => {
+                query.push(" AND o.size <= ").push_bind(filter_as_i64(key, value)?);
+            }
+            "created_after" => {
+                query.push(" AND e.created_at >= ").push_bind(filter_as_datetime(key, value)?);
+            }
+            "created_before" => {
+                query.push(" AND e.created_at <= ").push_bind(filter_as_datetime(key, value)?);
+            }
+            "tags" => {
+                let tags = filter_as_string_list(key, value)?;
+                if !tags.is_empty() {
+                    query.push(" AND ARRAY(SELECT jsonb_array_elements_text(e.meta->'tags')) && ARRAY[");
+                    let mut separated = query.separated(", ");
+                    for tag in tags {
+                        separated.push_bind(tag);
+                    }
+                    separated.push_unseparated("]::text[]");
+                }
+            }
+            _ => {
+                // Handle custom metadata filters; the key is bound too so it
+                // can never be interpreted as SQL.
+                query
+                    .push(" AND e.meta->>")
+                    .push_bind(key.clone())
+                    .push(" = ")
+                    .push_bind(filter_as_str(key, value)?.to_string());
+            }
+        }
+    }
+    Ok(())
+}
+
+const INDEXED_SEARCH_SELECT: &str =
+    "SELECT e.id, e.commit_id, e.path, e.object_sha256, e.meta, e.is_dir, e.created_at
+     FROM entry e
+     JOIN commit c ON e.commit_id = c.id
+     LEFT JOIN entry_meta_index emi ON e.commit_id = emi.commit_id AND e.path = emi.path";
+
+const INDEXED_SEARCH_COUNT: &str =
+    "SELECT COUNT(*)
+     FROM entry e
+     JOIN commit c ON e.commit_id = c.id
+     LEFT JOIN entry_meta_index emi ON e.commit_id = emi.commit_id AND e.path = emi.path";
+
+/// Append the WHERE clause for `search_entries_with_index` to a query.
+///
+/// Known keys hit the denormalized `entry_meta_index` columns; anything else
+/// falls back to a JSONB `meta->>` lookup on the entry itself.
+fn push_index_filters(
+    query: &mut QueryBuilder<'_, Postgres>,
+    repo_id: Uuid,
+    filters: &HashMap<String, serde_json::Value>,
+) -> Result<()> {
+    query.push(" WHERE c.repo_id = ").push_bind(repo_id);
+
+    for (key, value) in filters {
+        match key.as_str() {
+            "file_type" | "org_lab" | "creator" | "file_name" => {
+                query.push(format!(" AND emi.{} = ", key)).push_bind(filter_as_str(key, value)?.to_string());
+            }
+            "tags" => {
+                query.push(" AND ").push_bind(filter_as_str(key, value)?.to_string()).push(" = ANY(emi.tags)");
+            }
+            "creation_dt_after" => {
+                query.push(" AND emi.creation_dt >= ").push_bind(filter_as_datetime(key, value)?);
+            }
+            "creation_dt_before" => {
+                query.push(" AND emi.creation_dt <= ").push_bind(filter_as_datetime(key, value)?);
+            }
+            _ => {
+                // Fallback to JSONB query
+                let text = match value {
+                    serde_json::Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                };
+                query
+                    .push(" AND e.meta->>")
+                    .push_bind(key.clone())
+                    .push(" = ")
+                    .push_bind(text);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn invalid_filter(key: &str, value: &serde_json::Value, expected: &str) -> IndexError {
+    IndexError::InvalidFilter(format!("{} must be {}, got {}", key, expected, value))
+}
+
+fn filter_as_str<'v>(key: &str, value: &'v serde_json::Value) -> Result<&'v str> {
+    value.as_str().ok_or_else(|| invalid_filter(key, value, "a string"))
+}
+
+fn filter_as_i64(key: &str, value: &serde_json::Value) -> Result<i64> {
+    value
+        .as_i64()
+        .or_else(|| value.as_str().and_then(|s| s.trim().parse().ok()))
+        .ok_or_else(|| invalid_filter(key, value, "an integer"))
+}
+
+/// Orphans older than the cutoff, except those a repo deletion asked to keep a while longer
+const ORPHANED_OBJECTS_QUERY: &str = "SELECT o.sha256, o.size, o.media_type, o.s3_key, o.created_at
+     FROM object o
+     LEFT JOIN entry e ON e.object_sha256 = o.sha256
+     WHERE e.commit_id IS NULL AND o.created_at < $1
+       AND NOT EXISTS (SELECT 1 FROM object_gc_schedule s WHERE s.sha256 = o.sha256 AND s.not_before > now())
+       AND NOT EXISTS (SELECT 1 FROM derived_artifact d WHERE d.derived_sha256 = o.sha256)
+     ORDER BY o.created_at";
+
+/// Hard-delete window applied when a deleted repo has no retention policy
+const DEFAULT_HARD_DELETE_DAYS: u32 = 90;
+
+/// Queue objects only the repo `$1` references for GC no earlier than `$2`
+const SCHEDULE_REPO_OBJECT_GC_SQL: &str = "INSERT INTO object_gc_schedule (sha256, not_before)
+     SELECT DISTINCT e.object_sha256, $2::timestamptz
+     FROM entry e
+     JOIN commit c ON c.id = e.commit_id
+     WHERE c.repo_id = $1 AND e.object_sha256 IS NOT NULL
+       AND NOT EXISTS (
+         SELECT 1 FROM entry other
+         JOIN commit oc ON oc.id = other.commit_id
+         WHERE other.object_sha256 = e.object_sha256 AND oc.repo_id <> $1
+       )
+     ON CONFLICT (sha256) DO UPDATE SET not_before = GREATEST(object_gc_schedule.not_before, EXCLUDED.not_before)";
+
+/// Everything stored under a repository, children before parents; `$1` is the repo id.
+///
+/// Most of these would cascade from `repo`, but deleting explicitly keeps the
+/// order independent of which foreign keys a given schema has.
+const REPO_DELETE_STATEMENTS: &[&str] = &[
+    "DELETE FROM ref_annotation WHERE repo_id = $1",
+    "DELETE FROM ref WHERE repo_id = $1",
+    "DELETE FROM artifact_rdf WHERE commit_id IN (SELECT id FROM commit WHERE repo_id = $1)",
+    "DELETE FROM entry_meta_index WHERE commit_id IN (SELECT id FROM commit WHERE repo_id = $1)",
+    "DELETE FROM entry WHERE commit_id IN (SELECT id FROM commit WHERE repo_id = $1)",
+    "DELETE FROM commit WHERE repo_id = $1",
+    "DELETE FROM webhooks WHERE repo_id = $1",
+    "DELETE FROM repo_quota WHERE repo_id = $1",
+    "DELETE FROM repo_usage WHERE repo_id = $1",
+    "DELETE FROM repo WHERE id = $1",
+];
+
+fn check_repo_deletable(name: &str, policy: Option<&RetentionPolicy>) -> Result<()> {
+    match policy {
+        Some(policy) if policy.legal_hold => Err(IndexError::LegalHold(name.to_string())),
+        _ => Ok(()),
+    }
+}
+
+/// Ancestors of `$1` and `$2` (inclusive) with their distance, joined on the
+/// closest shared commit
+const MERGE_BASE_QUERY: &str = "WITH RECURSIVE
+     ours(id, depth) AS (
+       SELECT $1::uuid, 0
+       UNION
+       SELECT p.parent, o.depth + 1
+       FROM ours o
+       JOIN commit c ON c.id = o.id
+       CROSS JOIN LATERAL (VALUES (c.parent_id), (c.merge_parent_id)) AS p(parent)
+       WHERE p.parent IS NOT NULL AND o.depth < 10000
+     ),
+     theirs(id, depth) AS (
+       SELECT $2::uuid, 0
+       UNION
+       SELECT p.parent, t.depth + 1
+       FROM theirs t
+       JOIN commit c ON c.id = t.id
+       CROSS JOIN LATERAL (VALUES (c.parent_id), (c.merge_parent_id)) AS p(parent)
+       WHERE p.parent IS NOT NULL AND t.depth < 10000
+     )
+     SELECT o.id FROM ours o JOIN theirs t ON t.id = o.id
+     ORDER BY o.depth + t.depth, o.depth
+     LIMIT 1";
+
+const OBJECT_REF_COUNT_QUERY: &str = "SELECT COUNT(*) FROM entry WHERE object_sha256 = $1";
+
+/// One row per distinct object in a repo with the number of entries pointing at it
+const REPO_OBJECT_USAGE_QUERY: &str = "SELECT o.sha256, o.size, COUNT(*) AS ref_count
+     FROM entry e
+     JOIN commit c ON c.id = e.commit_id
+     JOIN object o ON o.sha256 = e.object_sha256
+     WHERE c.repo_id = $1
+     GROUP BY o.sha256, o.size";
+
+/// Fold `(sha256, size, ref_count)` rows into dedup statistics
+fn compute_dedup_stats(usage: &[(String, i64, i64)]) -> DedupStats {
+    let mut stats = DedupStats::default();
+    for (_, size, ref_count) in usage {
+        let size = (*size).max(0) as u64;
+        let ref_count = (*ref_count).max(0) as u64;
+        stats.entry_count += ref_count;
+        stats.unique_objects += 1;
+        stats.logical_bytes += size * ref_count;
+        stats.physical_bytes += size;
+    }
+    stats.saved_bytes = stats.logical_bytes - stats.physical_bytes;
+    stats.dedup_ratio = if stats.physical_bytes == 0 {
+        1.0
+    } else {
+        stats.logical_bytes as f64 / stats.physical_bytes as f64
+    };
+    stats
+}
+
+/// Whether any of the `held` permissions satisfies `required`
+fn permission_granted(held: &[Permission], required: Permission) -> bool {
+    held.iter().any(|perm| perm.grants(required))
+}
+
+fn ref_kind_str(kind: &ReferenceKind) -> &'static str {
+    match kind {
+        ReferenceKind::Branch => "branch",
+        ReferenceKind::Tag => "tag",
+    }
+}
+
+fn ref_from_row(row: &sqlx::postgres::PgRow) -> Result<Reference> {
+    let kind_str: String = row.get("kind");
+    let kind = match kind_str.as_str() {
+        "branch" => ReferenceKind::Branch,
+        "tag" => ReferenceKind::Tag,
+        _ => return Err(IndexError::InvalidRefKind(kind_str)),
+    };
+
+    let annotation = match (row.get::<Option<String>, _>("message"), row.get::<Option<String>, _>("tagger")) {
+        (Some(message), Some(tagger)) => Some(RefAnnotation {
+            message,
+            tagger,
+            tagged_at: row.get("tagged_at"),
+        }),
+        _ => None,
+    };
+
+    Ok(Reference {
+        repo_id: blacklake_core::UuidWrapper(row.get("repo_id")),
+        name: row.get("name"),
+        kind,
+        commit_id: blacklake_core::UuidWrapper(row.get("commit_id")),
+        annotation,
+    })
+}
+
+/// Tree rows of commit `$1` under prefix `$2`, collapsed on delimiter `$3`
+/// when it is not null. `common_prefix` is the path up to and including
+/// the first delimiter after the prefix; a NULL delimiter never matches,
+/// so every entry is then its own row.
+/// Entries with their object's size, media type and storage time; `e` is
+/// the entry and `o` its object, absent for directories
+const TREE_ENTRIES_SELECT: &str = "SELECT e.path, e.object_sha256, e.meta, COALESCE(e.is_dir, false) AS is_dir,
+            o.size, o.media_type, o.created_at AS stored_at
+     FROM entry e LEFT JOIN object o ON o.sha256 = e.object_sha256";
+
+const TREE_ROWS_SQL: &str = "WITH listed AS (
+         SELECT path, object_sha256, meta, COALESCE(is_dir, false) AS is_dir,
+                CASE WHEN strpos(substr(path, length($2) + 1), $3) > 0
+                     THEN left(path, length($2) + strpos(substr(path, length($2) + 1), $3) + length($3) - 1)
+                END AS common_prefix
+         FROM entry WHERE commit_id = $1 AND starts_with(path, $2)
+     ),
+     rows AS (
+         SELECT COALESCE(common_prefix, path) AS name,
+                common_prefix IS NOT NULL AS collapsed,
+                MIN(object_sha256) AS object_sha256,
+                (array_agg(meta))[1] AS meta,
+                bool_or(is_dir) AS is_dir
+         FROM listed GROUP BY 1, 2
+     )";
+
+/// Refs with their tag annotation, if any; `r` is the ref
+const REF_SELECT_SQL: &str = "SELECT r.repo_id, r.name, r.kind, r.commit_id, a.message, a.tagger, a.tagged_at
+     FROM ref r LEFT JOIN ref_annotation a ON a.repo_id = r.repo_id AND a.ref_name = r.name";
+
+fn list_refs_query(repo_id: Uuid, kind: Option<ReferenceKind>) -> QueryBuilder<'static, Postgres> {
+    let mut query = QueryBuilder::<Postgres>::new(REF_SELECT_SQL);
+    query.push(" WHERE r.repo_id = ").push_bind(repo_id);
+    if let Some(kind) = kind {
+        query.push(" AND r.kind = ").push_bind(ref_kind_str(&kind));
+    }
+    query.push(" ORDER BY r.kind, r.name");
+    query
+}
+
+fn check_ref_deletable(name: &str, protected_ref: Option<&ProtectedRef>) -> Result<()> {
+    match protected_ref {
+        Some(protection) if !protection.allow_delete => Err(IndexError::RefProtected(name.to_string())),
+        _ => Ok(()),
+    }
+}
+
+/// The parts of an entry row that matter for diffing
+#[derive(Debug, Clone, sqlx::FromRow)]
+struct EntrySnapshot {
+    path: String,
+    object_sha256: Option<String>,
+    meta: serde_json::Value,
+}
+
+fn diff_entry_snapshots(base: Vec<EntrySnapshot>, head: Vec<EntrySnapshot>) -> CommitDiff {
+    let mut base: HashMap<String, EntrySnapshot> =
+        base.into_iter().map(|e| (e.path.clone(), e)).collect();
+    let mut diff = CommitDiff::default();
+
+    for new in head {
+        match base.remove(&new.path) {
+            None => diff.added.push(PathDiff {
+                path: new.path,
+                old_sha256: None,
+                new_sha256: new.object_sha256,
+            }),
+            Some(old) if old.object_sha256 != new.object_sha256 => diff.modified.push(PathDiff {
+                path: new.path,
+                old_sha256: old.object_sha256,
+                new_sha256: new.object_sha256,
+            }),
+            Some(old) if old.meta != new.meta => diff.meta_changed.push(PathDiff {
+                path: new.path,
+                old_sha256: old.object_sha256,
+                new_sha256: new.object_sha256,
+            }),
+            Some(_) => {}
+        }
+    }
+
+    diff.removed = base
+        .into_values()
+        .map(|old| PathDiff {
+            path: old.path,
+            old_sha256: old.object_sha256,
+            new_sha256: None,
+        })
+        .collect();
+
+    for paths in [&mut diff.added, &mut diff.removed, &mut diff.modified, &mut diff.meta_changed] {
+        paths.sort_by(|a, b| a.path.cmp(&b.path));
+    }
+
+    diff
+}
+
+/// The external source a virtual repository lists instead of its commits
+#[derive(Debug, Clone)]
+pub struct RepoConnector {
+    pub connector_id: Uuid,
+    /// Lowercase connector type, as stored in `external_source`
+    pub connector_type: String,
+    pub name: String,
+    pub config: serde_json::Value,
+}
+
+/// The response stored against an idempotency key
+#[derive(Debug, Clone, PartialEq)]
+pub struct IdempotentResponse {
+    pub status: u16,
+    /// Response headers replayed with the body, as (name, value) pairs
+    pub headers: Vec<(String, String)>,
+    pub body: serde_json::Value,
+}
+
+/// One content-integrity verification run, as recorded in `integrity_report`
+#[derive(Debug, Clone, PartialEq)]
+pub struct IntegrityReportSummary {
+    pub id: Uuid,
+    /// `running` or `completed`
+    pub status: String,
+    pub objects_checked: i64,
+    pub mismatches: i64,
+    pub started_at: chrono::DateTime<Utc>,
+    pub completed_at: Option<chrono::DateTime<Utc>>,
+}
+
+/// What one `enforce_retention` pass did to a repository
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RetentionRun {
+    /// The repo is under legal hold, so nothing was touched
+    pub legal_hold: bool,
+    /// Paths tombstoned in this pass
+    pub tombstoned: Vec<String>,
+    /// Paths whose entries were removed in this pass
+    pub hard_deleted: Vec<String>,
+    /// Objects the removed entries referenced; they may still be shared
+    pub released_objects: Vec<String>,
+}
+
+/// Stamp deletion time `$3` on the entries for paths `$2` in commit `$1`
+const MARK_ENTRIES_DELETED_SQL: &str = "UPDATE entry SET deleted_at = $3
+     WHERE commit_id = $1 AND path = ANY($2) AND deleted_at IS NULL";
+
+/// Tombstone, at `$3`, the repo `$1` entries deleted no later than `$2`
+const TOMBSTONE_DELETED_ENTRIES_SQL: &str = "UPDATE entry e SET tombstoned_at = $3
+     FROM commit c
+     WHERE c.id = e.commit_id AND c.repo_id = $1
+       AND e.deleted_at <= $2 AND e.tombstoned_at IS NULL
+     RETURNING e.commit_id, e.path";
+
+/// Remove the repo `$1` tombstoned entries deleted no later than `$2`
+const HARD_DELETE_TOMBSTONED_ENTRIES_SQL: &str = "DELETE FROM entry e
+     USING commit c
+     WHERE c.id = e.commit_id AND c.repo_id = $1
+       AND e.tombstoned_at IS NOT NULL AND e.deleted_at <= $2
+     RETURNING e.commit_id, e.path, e.object_sha256";
+
+async fn append_retention_audit(
+    conn: &mut sqlx::PgConnection,
+    at: chrono::DateTime<Utc>,
+    action: &str,
+    repo_name: &str,
+    path: &str,
+    commit_id: Uuid,
+) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO audit_log (at, actor, action, repo_name, path, request_meta)
+         VALUES ($1, 'system:retention', $2, $3, $4, $5)"
+    )
+    .bind(at)
+    .bind(action)
+    .bind(repo_name)
+    .bind(path)
+    .bind(serde_json::json!({ "commit_id": commit_id }))
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Everything `commit_atomic` writes for one commit
+#[derive(Debug, Clone)]
+pub struct CommitWrite<'a> {
+    pub repo_id: Uuid,
+    /// Branch to create or advance
+    pub ref_name: &'a str,
+    pub author: &'a str,
+    pub message: Option<&'a str>,
+    /// Fail with `ParentMismatch` unless the ref currently points here
+    pub expected_parent: Option<Uuid>,
+    pub changes: &'a [Change],
+    /// Schema collection and version that validated the non-delete changes
+    pub schema: Option<(&'a str, &'a str)>,
+    /// Head of the branch merged in, for merge commits
+    pub merge_parent: Option<Uuid>,
+}
+
+/// Compute stats for a commit's changes and store them in `commit.stats`
+async fn store_commit_stats(
+    conn: &mut sqlx::PgConnection,
+    commit_id: Uuid,
+    parent_id: Option<Uuid>,
+    changes: &[Change],
+) -> Result<CommitStats> {
+    let paths: Vec<String> = changes.iter().map(|c| c.path.clone()).collect();
+
+    let previous: HashMap<String, String> = match parent_id {
+        Some(parent_id) => sqlx::query_as::<_, (String, Option<String>)>(
+            "SELECT path, object_sha256 FROM entry WHERE commit_id = $1 AND path = ANY($2)"
+        )
+        .bind(parent_id)
+        .bind(&paths)
+        .fetch_all(&mut *conn)
+        .await?
+        .into_iter()
+        .filter_map(|(path, sha256)| sha256.map(|sha256| (path, sha256)))
+        .collect(),
+        None => HashMap::new(),
+    };
+
+    let mut hashes: Vec<String> = changes.iter().filter_map(|c| c.sha256.clone()).collect();
+    hashes.extend(previous.values().cloned());
+    hashes.sort();
+    hashes.dedup();
+
+    let sizes: HashMap<String, i64> = sqlx::query_as::<_, (String, i64)>(
+        "SELECT sha256, size FROM object WHERE sha256 = ANY($1)"
+    )
+    .bind(&hashes)
+    .fetch_all(&mut *conn)
+    .await?
+    .into_iter()
+    .collect();
+
+    let stats = compute_commit_stats(changes, &previous, &sizes);
+
+    sqlx::query("UPDATE commit SET stats = $1 WHERE id = $2")
+        .bind(serde_json::to_value(&stats)?)
+        .bind(commit_id)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(stats)
+}
+
+/// Tally a change set. `previous` maps paths to their object hash in the
+/// parent commit and `sizes` maps object hashes to sizes in bytes; unknown
+/// objects count as zero bytes.
+fn compute_commit_stats(
+    changes: &[Change],
+    previous: &HashMap<String, String>,
+    sizes: &HashMap<String, i64>,
+) -> CommitStats {
+    let size_of = |sha256: Option<&String>| -> u64 {
+        sha256
+            .and_then(|sha256| sizes.get(sha256))
+            .map(|size| (*size).max(0) as u64)
+            .unwrap_or(0)
+    };
+
+    let mut stats = CommitStats::default();
+    for change in changes {
+        let old = previous.get(&change.path);
+        match change.op {
+            ChangeOp::Add => {
+                stats.files_added += 1;
+                stats.bytes_added += size_of(change.sha256.as_ref());
+            }
+            ChangeOp::Modify => {
+                stats.files_modified += 1;
+                if change.sha256.as_ref() != old {
+                    stats.bytes_added += size_of(change.sha256.as_ref());
+                    stats.bytes_removed += size_of(old);
+                }
+            }
+            ChangeOp::Meta => {
+                stats.files_modified += 1;
+            }
+            ChangeOp::Delete => {
+                stats.files_deleted += 1;
+                stats.bytes_removed += size_of(old.or(change.sha256.as_ref()));
+            }
+        }
+    }
+
+    stats
+}
+
+/// Upper bound on commits visited by a single ancestry walk
+const MAX_ANCESTRY_WALK: usize = 100_000;
+
+/// Follow parent links from `start` using `lookup`, stopping at the root, a
+/// missing commit, `max` commits, or a repeated commit id. History should
+/// never contain a cycle, but a corrupted parent link must not hang callers.
+async fn walk_ancestors<F, Fut>(start: Uuid, max: usize, mut lookup: F) -> Result<Vec<Commit>>
+where
+    F: FnMut(Uuid) -> Fut,
+    Fut: std::future::Future<Output = Result<Option<Commit>>>,
+{
+    let max = max.min(MAX_ANCESTRY_WALK);
+    let mut commits = Vec::new();
+    let mut seen = std::collections::HashSet::new();
+    let mut next = Some(start);
+
+    while let Some(id) = next {
+        if commits.len() >= max || !seen.insert(id) {
+            break;
+        }
+        let Some(commit) = lookup(id).await? else {
+            break;
+        };
+        next = commit.parent_id.as_ref().map(|parent| parent.0);
+        commits.push(commit);
+    }
+
+    Ok(commits)
+}
+
+fn filter_as_datetime(key: &str, value: &serde_json::Value) -> Result<chrono::DateTime<Utc>> {
+    value
+        .as_str()
+        .and_then(|s| chrono::DateTime::parse_from_rfc3339(s).ok())
+        .map(|date| date.with_timezone(&Utc))
+        .ok_or_else(|| invalid_filter(key, value, "an RFC 3339 timestamp"))
+}
+
+fn filter_as_string_list(key: &str, value: &serde_json::Value) -> Result<Vec<String>> {
+    match value {
+        serde_json::Value::Array(items) => items
+            .iter()
+            .map(|item| item.as_str().map(|s| s.to_string()).ok_or_else(|| invalid_filter(key, value, "a list of strings")))
+            .collect(),
+        serde_json::Value::String(s) => Ok(s
+            .split(',')
+            .map(|tag| tag.trim())
+            .filter(|tag| !tag.is_empty())
+            .map(|tag| tag.to_string())
+            .collect()),
+        _ => Err(invalid_filter(key, value, "a list of strings")),
+    }
+}
+
+#[cfg(any(test, feature = "test-support"))]
+pub mod test_support;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::test_index;
+    use serde_json::json;
+    use blacklake_core::features::FeatureError;
+
+    fn search_sql(filters: &HashMap<String, serde_json::Value>) -> String {
+        let mut query = QueryBuilder::<Postgres>::new(SEARCH_ENTRIES_SELECT);
+        push_search_filters(&mut query, Uuid::new_v4(), filters).unwrap();
+        query.sql().to_string()
+    }
+
+    #[test]
+    fn test_search_filters_empty() {
+        let sql = search_sql(&HashMap::new());
+        assert!(sql.ends_with(" WHERE c.repo_id = $1"));
+    }
+
+    #[test]
+    fn test_search_filters_each_kind_narrows() {
+        let cases = [
+            ("path", json!("data/"), "e.path ILIKE $2"),
+            ("file_type", json!("text/csv"), "e.meta->>'file_type' = $2"),
+            ("size_min", json!("100"), "o.size >= $2"),
+            ("size_max", json!(500), "o.size <= $2"),
+            ("created_after", json!("2025-01-01T00:00:00Z"), "e.created_at >= $2"),
+            ("created_before", json!("2025-01-01T00:00:00Z"), "e.created_at <= $2"),
+            ("org_lab", json!("ORNL"), "e.meta->>$2 = $3"),
+        ];
+
+        for (key, value, expected) in cases {
+            let filters = HashMap::from([(key.to_string(), value)]);
+            let sql = search_sql(&filters);
+            assert!(sql.contains(expected), "{} produced {}", key, sql);
+        }
+    }
+
+    #[test]
+    fn test_search_filters_reject_unparseable_values() {
+        let cases = [
+            ("size_min", json!("not-a-number")),
+            ("size_max", json!(1.5)),
+            ("created_after", json!("yesterday")),
+            ("path", json!(7)),
+            ("tags", json!(["ok", 3])),
+            ("org_lab", json!({"nested": true})),
+        ];
+
+        for (key, value) in cases {
+            let filters = HashMap::from([(key.to_string(), value)]);
+            let mut query = QueryBuilder::<Postgres>::new(SEARCH_ENTRIES_SELECT);
+            let result = push_search_filters(&mut query, Uuid::new_v4(), &filters);
+            assert!(matches!(&result, Err(IndexError::InvalidFilter(message)) if message.starts_with(key)), "{}: {:?}", key, result);
+        }
+    }
+
+    fn keyset_sql(sort: SortOrder, cursor: Option<&SearchCursor>) -> String {
+        let mut query = QueryBuilder::<Postgres>::new(SEARCH_ENTRIES_SELECT);
+        push_search_filters(&mut query, Uuid::new_v4(), &HashMap::new()).unwrap();
+        push_keyset_page(&mut query, &sort, cursor, 50);
+        query.sql().to_string()
+    }
+
+    #[test]
+    fn test_keyset_predicate_matches_sort_direction() {
+        let cursor = SearchCursor { created_at: Utc::now(), id: Uuid::new_v4() };
+
+        let sql = keyset_sql(SortOrder::Desc, Some(&cursor));
+        assert!(sql.ends_with(
+            " WHERE c.repo_id = $1 AND (e.created_at, e.id) < ($2, $3) ORDER BY e.created_at DESC, e.id DESC LIMIT $4"
+        ), "{}", sql);
+
+        let sql = keyset_sql(SortOrder::Asc, Some(&cursor));
+        assert!(sql.contains("(e.created_at, e.id) > ($2, $3) ORDER BY e.created_at ASC, e.id ASC"), "{}", sql);
+
+        let sql = keyset_sql(SortOrder::Desc, None);
+        assert!(sql.ends_with(" WHERE c.repo_id = $1 ORDER BY e.created_at DESC, e.id DESC LIMIT $2"), "{}", sql);
+    }
+
+    #[test]
+    fn test_search_order_breaks_ties_on_entry_id() {
+        for sort in SearchSort::ALL {
+            let order_by = search_order_by(*sort);
+            assert!(order_by.ends_with(", e.id DESC") || order_by.ends_with(", e.id ASC"), "{}", order_by);
+        }
+        assert_eq!(search_order_by(SearchSort::default()), " ORDER BY e.created_at DESC, e.id DESC");
+    }
+
+    #[test]
+    fn test_search_cursor_round_trip() {
+        let cursor = SearchCursor {
+            created_at: chrono::DateTime::parse_from_rfc3339("2025-01-17T18:28:00.123456Z").unwrap().with_timezone(&Utc),
+            id: Uuid::new_v4(),
+        };
+        let encoded = cursor.encode();
+        assert!(encoded.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+        assert_eq!(SearchCursor::decode(&encoded).unwrap(), cursor);
+
+        for bad in ["", "not base64!", "bm8tc2VwYXJhdG9y"] {
+            assert!(matches!(SearchCursor::decode(bad), Err(IndexError::InvalidCursor(_))), "{}", bad);
+        }
+    }
+
+    fn keyset_entry(seconds: i64) -> Entry {
+        Entry {
+            id: blacklake_core::UuidWrapper(Uuid::new_v4()),
+            commit_id: blacklake_core::UuidWrapper(Uuid::nil()),
+            path: format!("data/{}.csv", seconds),
+            object_sha256: None,
+            meta: json!({}),
+            is_dir: false,
+            created_at: chrono::DateTime::from_timestamp(1_700_000_000 + seconds, 0).unwrap(),
+            size: None,
+            media_type: None,
+        }
+    }
+
+    /// Evaluate a keyset page over in-memory rows the way the generated SQL does
+    fn keyset_page(rows: &[Entry], sort: &SortOrder, cursor: Option<&str>, limit: u32) -> (Vec<Entry>, Option<String>) {
+        let cursor = cursor.map(|c| SearchCursor::decode(c).unwrap());
+        let key = |e: &Entry| (e.created_at, e.id.0);
+
+        let mut page: Vec<Entry> = rows
+            .iter()
+            .filter(|e| match (&cursor, sort) {
+                (None, _) => true,
+                (Some(c), SortOrder::Desc) => key(e) < (c.created_at, c.id),
+                (Some(c), SortOrder::Asc) => key(e) > (c.created_at, c.id),
+            })
+            .cloned()
+            .collect();
+        page.sort_by_key(key);
+        if matches!(sort, SortOrder::Desc) {
+            page.reverse();
+        }
+        page.truncate(limit as usize + 1);
+
+        split_keyset_page(page, limit)
+    }
+
+    #[test]
+    fn test_keyset_pagination_has_no_gaps_or_repeats_under_inserts() {
+        for sort in [SortOrder::Desc, SortOrder::Asc] {
+            // Include duplicate timestamps so the id tie-breaker matters
+            let mut rows: Vec<Entry> = (0..25).map(|i| keyset_entry(i / 2)).collect();
+            let original: std::collections::HashSet<Uuid> = rows.iter().map(|e| e.id.0).collect();
+
+            let mut seen = Vec::new();
+            let mut cursor: Option<String> = None;
+            let mut pages = 0;
+            loop {
+                let (page, next) = keyset_page(&rows, &sort, cursor.as_deref(), 4);
+                assert!(page.len() <= 4);
+                seen.extend(page.iter().map(|e| e.id.0));
+                pages += 1;
+
+                if pages == 2 {
+                    // Rows landing on either side of the cursor mid-pagination
+                    rows.push(keyset_entry(-100));
+                    rows.push(keyset_entry(100));
+                    rows.push(keyset_entry(5));
+                }
+
+                match next {
+                    Some(next) => cursor = Some(next),
+                    None => break,
+                }
+            }
+
+            let unique: std::collections::HashSet<Uuid> = seen.iter().copied().collect();
+            assert_eq!(unique.len(), seen.len(), "{:?} page repeated an entry", sort);
+            assert!(original.is_subset(&unique), "{:?} pagination skipped an entry", sort);
+        }
+    }
+
+    #[test]
+    fn test_split_keyset_page_last_page_has_no_cursor() {
+        let rows: Vec<Entry> = (0..3).map(keyset_entry).collect();
+        let (page, next) = split_keyset_page(rows.clone(), 3);
+        assert_eq!(page.len(), 3);
+        assert!(next.is_none());
+
+        let (page, next) = split_keyset_page(rows, 2);
+        assert_eq!(page.len(), 2);
+        assert_eq!(SearchCursor::decode(&next.unwrap()).unwrap(), SearchCursor::for_entry(&page[1]));
+    }
+
+    fn index_sql(filters: &HashMap<String, serde_json::Value>) -> String {
+        let mut query = QueryBuilder::<Postgres>::new(INDEXED_SEARCH_SELECT);
+        push_index_filters(&mut query, Uuid::new_v4(), filters).unwrap();
+        query.sql().to_string()
+    }
+
+    #[test]
+    fn test_index_filters_use_fast_path_columns() {
+        let cases = [
+            ("file_type", json!("text/csv"), "emi.file_type = $2"),
+            ("org_lab", json!("ORNL"), "emi.org_lab = $2"),
+            ("creator", json!("me@example.org"), "emi.creator = $2"),
+            ("file_name", json!("demo.csv"), "emi.file_name = $2"),
+            ("tags", json!("demo"), "$2 = ANY(emi.tags)"),
+            ("creation_dt_after", json!("2025-01-01T00:00:00Z"), "emi.creation_dt >= $2"),
+            ("creation_dt_before", json!("2025-01-01T00:00:00Z"), "emi.creation_dt <= $2"),
+        ];
+
+        for (key, value, expected) in cases {
+            let filters = HashMap::from([(key.to_string(), value)]);
+            let sql = index_sql(&filters);
+            assert!(sql.contains(expected), "{} produced {}", key, sql);
+            assert!(!sql.contains("e.meta->>"), "{} fell back to JSONB: {}", key, sql);
+        }
+    }
+
+    #[test]
+    fn test_index_filters_fall_back_to_jsonb() {
+        let filters = HashMap::from([("'; DROP TABLE entry; --".to_string(), json!(42))]);
+        let sql = index_sql(&filters);
+        assert!(sql.ends_with(" AND e.meta->>$2 = $3"), "{}", sql);
+        assert!(!sql.contains("DROP TABLE"));
+    }
+
+    #[test]
+    fn test_search_filters_tags_expand_placeholders() {
+        let filters = HashMap::from([("tags".to_string(), json!(["a", "b", "c"]))]);
+        let sql = search_sql(&filters);
+        assert!(sql.contains("&& ARRAY[$2, $3, $4]::text[]"), "{}", sql);
+
+        let filters = HashMap::from([("tags".to_string(), json!("a, b"))]);
+        let sql = search_sql(&filters);
+        assert!(sql.contains("&& ARRAY[$2, $3]::text[]"), "{}", sql);
+
+        let filters = HashMap::from([("tags".to_string(), json!([]))]);
+        assert!(!search_sql(&filters).contains("ARRAY["));
+    }
+
+    fn commit_chain(len: usize) -> Vec<Commit> {
+        let repo_id = Uuid::new_v4();
+        let mut commits: Vec<Commit> = Vec::new();
+        for i in 0..len {
+            commits.push(Commit {
+                id: blacklake_core::UuidWrapper(Uuid::new_v4()),
+                repo_id: blacklake_core::UuidWrapper(repo_id),
+                parent_id: commits.last().map(|parent| parent.id.clone()),
+                author: "test@example.com".to_string(),
+                message: Some(format!("commit {}", i)),
+                created_at: Utc::now(),
+                stats: None,
+            });
+        }
+        commits
+    }
+
+    async fn walk(commits: &[Commit], start: Uuid, max: usize) -> Vec<String> {
+        let by_id: HashMap<Uuid, Commit> =
+            commits.iter().map(|c| (c.id.0, c.clone())).collect();
+        walk_ancestors(start, max, |id| {
+            let commit = by_id.get(&id).cloned();
+            async move { Ok(commit) }
+        })
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|c| c.message.unwrap())
+        .collect()
+    }
+
+    #[tokio::test]
+    async fn test_walk_ancestors_newest_first_to_root() {
+        let commits = commit_chain(4);
+        let head = commits.last().unwrap().id.0;
+
+        assert_eq!(
+            walk(&commits, head, 10).await,
+            vec!["commit 3", "commit 2", "commit 1", "commit 0"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_walk_ancestors_respects_max_and_start() {
+        let commits = commit_chain(5);
+
+        assert_eq!(walk(&commits, commits[4].id.0, 2).await, vec!["commit 4", "commit 3"]);
+        assert_eq!(walk(&commits, commits[1].id.0, 10).await, vec!["commit 1", "commit 0"]);
+        assert!(walk(&commits, commits[4].id.0, 0).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_walk_ancestors_stops_on_cycle_and_missing_parent() {
+        let mut commits = commit_chain(3);
+        // Point the root back at the head
+        commits[0].parent_id = Some(commits[2].id.clone());
+        assert_eq!(
+            walk(&commits, commits[2].id.0, 10).await,
+            vec!["commit 2", "commit 1", "commit 0"]
+        );
+
+        // Drop the middle commit: the walk ends where the chain breaks
+        let broken = vec![commits[0].clone(), commits[2].clone()];
+        assert_eq!(walk(&broken, commits[2].id.0, 10).await, vec!["commit 2"]);
+    }
+
+    fn snapshot(path: &str, sha256: &str, meta: serde_json::Value) -> EntrySnapshot {
+        EntrySnapshot {
+            path: path.to_string(),
+            object_sha256: Some(sha256.to_string()),
+            meta,
+        }
+    }
+
+    #[test]
+    fn test_diff_entry_snapshots_classifies_paths() {
+        let base = vec![
+            snapshot("data/unchanged.csv", "aaa", json!({"owner": "lab"})),
+            snapshot("data/content.csv", "bbb", json!({"owner": "lab"})),
+            snapshot("data/meta.csv", "ccc", json!({"owner": "lab"})),
+            snapshot("data/both.csv", "ddd", json!({"owner": "lab"})),
+            snapshot("data/removed.csv", "eee", json!({})),
+        ];
+        let head = vec![
+            snapshot("data/unchanged.csv", "aaa", json!({"owner": "lab"})),
+            snapshot("data/content.csv", "bbb2", json!({"owner": "lab"})),
+            snapshot("data/meta.csv", "ccc", json!({"owner": "other-lab"})),
+            snapshot("data/both.csv", "ddd2", json!({"owner": "other-lab"})),
+            snapshot("data/added.csv", "fff", json!({})),
+        ];
+
+        let diff = diff_entry_snapshots(base, head);
+        let paths = |diffs: &[PathDiff]| diffs.iter().map(|d| d.path.clone()).collect::<Vec<_>>();
+
+        assert_eq!(paths(&diff.added), vec!["data/added.csv"]);
+        assert_eq!(paths(&diff.removed), vec!["data/removed.csv"]);
+        // A content change wins over a simultaneous metadata change
+        assert_eq!(paths(&diff.modified), vec!["data/both.csv", "data/content.csv"]);
+        assert_eq!(paths(&diff.meta_changed), vec!["data/meta.csv"]);
+
+        assert_eq!(diff.added[0].old_sha256, None);
+        assert_eq!(diff.added[0].new_sha256.as_deref(), Some("fff"));
+        assert_eq!(diff.removed[0].old_sha256.as_deref(), Some("eee"));
+        assert_eq!(diff.removed[0].new_sha256, None);
+        assert_eq!(diff.modified[1].old_sha256.as_deref(), Some("bbb"));
+        assert_eq!(diff.modified[1].new_sha256.as_deref(), Some("bbb2"));
+        assert_eq!(diff.meta_changed[0].old_sha256, diff.meta_changed[0].new_sha256);
+    }
+
+    #[test]
+    fn test_diff_entry_snapshots_identical_commits() {
+        let entries = vec![snapshot("a.txt", "aaa", json!({"k": 1}))];
+        let diff = diff_entry_snapshots(entries.clone(), entries);
+
+        assert!(diff.added.is_empty());
+        assert!(diff.removed.is_empty());
+        assert!(diff.modified.is_empty());
+        assert!(diff.meta_changed.is_empty());
+    }
+
+    #[test]
+    fn test_list_refs_query_filters_by_kind() {
+        let repo_id = Uuid::new_v4();
+
+        let all = list_refs_query(repo_id, None);
+        assert_eq!(all.sql(), format!("{} WHERE r.repo_id = $1 ORDER BY r.kind, r.name", REF_SELECT_SQL));
+
+        let tags = list_refs_query(repo_id, Some(ReferenceKind::Tag));
+        assert_eq!(
+            tags.sql(),
+            format!("{} WHERE r.repo_id = $1 AND r.kind = $2 ORDER BY r.kind, r.name", REF_SELECT_SQL)
+        );
+    }
+
+    #[test]
+    fn test_audit_filters_bind_only_set_fields() {
+        let mut query = QueryBuilder::<Postgres>::new("SELECT COUNT(*) FROM audit_log");
+        push_audit_filters(&mut query, &AuditLogFilter::default());
+        assert_eq!(query.sql(), "SELECT COUNT(*) FROM audit_log WHERE TRUE");
+
+        let filter = AuditLogFilter {
+            actor: Some("alice".to_string()),
+            ref_name: Some("main".to_string()),
+            until: Some(Utc::now()),
+            ..Default::default()
+        };
+        let mut query = QueryBuilder::<Postgres>::new("SELECT COUNT(*) FROM audit_log");
+        push_audit_filters(&mut query, &filter);
+        assert_eq!(
+            query.sql(),
+            "SELECT COUNT(*) FROM audit_log WHERE TRUE AND actor = $1 AND ref_name = $2 AND at < $3"
+        );
+    }
+
+    fn protection(allow_delete: bool) -> ProtectedRef {
+        ProtectedRef {
+            id: Uuid::new_v4(),
+            repo_id: Uuid::new_v4(),
+            ref_name: "main".to_string(),
+            require_admin: true,
+            allow_fast_forward: true,
+            allow_delete,
+            required_checks: vec![],
+            required_reviewers: 0,
+            require_schema_pass: false,
+        }
+    }
+
+    #[test]
+    fn test_check_ref_deletable() {
+        assert!(check_ref_deletable("feature", None).is_ok());
+        assert!(check_ref_deletable("main", Some(&protection(true))).is_ok());
+        assert!(matches!(
+            check_ref_deletable("main", Some(&protection(false))),
+            Err(IndexError::RefProtected(name)) if name == "main"
+        ));
+    }
+
+    fn change(op: ChangeOp, path: &str, sha256: Option<&str>) -> Change {
+        Change {
+            op,
+            path: path.to_string(),
+            sha256: sha256.map(|s| s.to_string()),
+            meta: json!({}),
+        }
+    }
+
+    #[test]
+    fn test_compute_commit_stats_mixed_changes() {
+        let changes = vec![
+            change(ChangeOp::Add, "new.csv", Some("new")),
+            change(ChangeOp::Add, "another.csv", Some("another")),
+            change(ChangeOp::Modify, "data.csv", Some("data-v2")),
+            change(ChangeOp::Meta, "notes.txt", Some("notes")),
+            change(ChangeOp::Delete, "old.bin", None),
+        ];
+        let previous: HashMap<String, String> = [
+            ("data.csv", "data-v1"),
+            ("notes.txt", "notes"),
+            ("old.bin", "old"),
+        ]
+        .into_iter()
+        .map(|(path, sha256)| (path.to_string(), sha256.to_string()))
+        .collect();
+        let sizes: HashMap<String, i64> = [
+            ("new", 100),
+            ("another", 50),
+            ("data-v1", 1_000),
+            ("data-v2", 1_200),
+            ("notes", 10),
+            ("old", 4_096),
+        ]
+        .into_iter()
+        .map(|(sha256, size)| (sha256.to_string(), size))
+        .collect();
+
+        let stats = compute_commit_stats(&changes, &previous, &sizes);
+
+        assert_eq!(
+            stats,
+            CommitStats {
+                files_added: 2,
+                files_modified: 2,
+                files_deleted: 1,
+                bytes_added: 100 + 50 + 1_200,
+                bytes_removed: 1_000 + 4_096,
+            }
+        );
+    }
+
+    #[test]
+    fn test_compute_commit_stats_unknown_objects_count_zero_bytes() {
+        let changes = vec![change(ChangeOp::Add, "a.txt", Some("missing"))];
+        let stats = compute_commit_stats(&changes, &HashMap::new(), &HashMap::new());
+
+        assert_eq!(stats.files_added, 1);
+        assert_eq!(stats.bytes_added, 0);
+    }
+
+    #[test]
+    fn test_permission_hierarchy() {
+        assert!(Permission::Admin.grants(Permission::Write));
+        assert!(Permission::Admin.grants(Permission::Read));
+        assert!(Permission::Write.grants(Permission::Read));
+        assert!(Permission::Write.grants(Permission::Write));
+        assert!(!Permission::Write.grants(Permission::Admin));
+        assert!(!Permission::Read.grants(Permission::Write));
+    }
+
+    #[test]
+    fn test_permission_granted_denies_read_only_subject_write() {
+        assert!(!permission_granted(&[Permission::Read], Permission::Write));
+        assert!(!permission_granted(&[], Permission::Read));
+        assert!(permission_granted(&[Permission::Read, Permission::Write], Permission::Write));
+        assert!(permission_granted(&[Permission::Admin], Permission::Admin));
+    }
+
+    #[test]
+    fn test_orphaned_objects_query_anti_joins_entries() {
+        let sql = ORPHANED_OBJECTS_QUERY.split_whitespace().collect::<Vec<_>>().join(" ");
+
+        // Any referencing entry row makes e.commit_id non-null and excludes the object
+        assert!(sql.contains("FROM object o LEFT JOIN entry e ON e.object_sha256 = o.sha256"));
+        assert!(sql.contains("WHERE e.commit_id IS NULL AND o.created_at < $1"));
+        assert!(sql.contains("NOT EXISTS (SELECT 1 FROM derived_artifact d WHERE d.derived_sha256 = o.sha256)"));
+    }
+
+    #[test]
+    fn test_object_ref_count_counts_every_referencing_entry() {
+        assert_eq!(OBJECT_REF_COUNT_QUERY, "SELECT COUNT(*) FROM entry WHERE object_sha256 = $1");
+
+        let sql = REPO_OBJECT_USAGE_QUERY.split_whitespace().collect::<Vec<_>>().join(" ");
+        assert!(sql.contains("COUNT(*) AS ref_count"));
+        assert!(sql.contains("WHERE c.repo_id = $1 GROUP BY o.sha256, o.size"));
+    }
+
+    #[test]
+    fn test_dedup_stats_for_two_entries_sharing_an_object() {
+        // data/a.csv and copy/a.csv share one 1000-byte object; b.csv has its own
+        let usage = vec![
+            ("a".repeat(64), 1000, 2),
+            ("b".repeat(64), 250, 1),
+        ];
+
+        let stats = compute_dedup_stats(&usage);
+
+        assert_eq!(usage[0].2, 2);
+        assert_eq!(stats.entry_count, 3);
+        assert_eq!(stats.unique_objects, 2);
+        assert_eq!(stats.logical_bytes, 2250);
+        assert_eq!(stats.physical_bytes, 1250);
+        assert_eq!(stats.saved_bytes, 1000);
+        assert!((stats.dedup_ratio - 1.8).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_dedup_stats_for_empty_repo() {
+        let stats = compute_dedup_stats(&[]);
+
+        assert_eq!(stats, DedupStats { dedup_ratio: 1.0, ..Default::default() });
+    }
+
+    #[test]
+    fn test_entry_sample_upsert_replaces_existing_sample() {
+        let sql = UPSERT_ENTRY_SAMPLE_SQL.split_whitespace().collect::<Vec<_>>().join(" ");
+
+        assert!(sql.contains("INSERT INTO entry_sample (commit_id, path, sample, schema) VALUES ($1, $2, $3, $4)"));
+        assert!(sql.contains("ON CONFLICT (commit_id, path) DO UPDATE SET sample = EXCLUDED.sample, schema = EXCLUDED.schema"));
+    }
+
+    #[test]
+    fn test_fulltext_query_splits_phrases_from_words() {
+        let query = FulltextQuery::parse(r#"  ocean "sea  surface temperature" buoy "unterminated"#);
+
+        assert_eq!(query.phrases, vec!["sea surface temperature", "unterminated"]);
+        assert_eq!(query.words, "ocean buoy");
+        assert_eq!(query.as_phrase(), "sea surface temperature unterminated ocean buoy");
+        assert!(FulltextQuery::parse(r#"  "" "#).is_empty());
+    }
+
+    #[test]
+    fn test_fulltext_sql_combines_phrase_and_word_queries() {
+        let mut query = QueryBuilder::<Postgres>::new(FULLTEXT_SEARCH_SELECT);
+        push_fulltext_from(&mut query, Uuid::new_v4(), &FulltextQuery::parse(r#""sea surface" buoy"#));
+        let sql = query.sql().split_whitespace().collect::<Vec<_>>().join(" ");
+
+        assert!(sql.contains("(SELECT phraseto_tsquery('english', $1) && plainto_tsquery('english', $2) AS query, phraseto_tsquery('english', $3) AS phrase) q"));
+        assert!(sql.ends_with("WHERE c.repo_id = $4 AND emi.search_vector @@ q.query"));
+    }
+
+    /// A repo with one commit, for tests run against `TEST_DATABASE_URL`
+    async fn seed_repo(client: &IndexClient, prefix: &str) -> (Uuid, Uuid) {
+        let repo_id = Uuid::new_v4();
+        let commit_id = Uuid::new_v4();
+
+        sqlx::query("INSERT INTO repo (id, name, created_by) VALUES ($1, $2, 'test')")
+            .bind(repo_id)
+            .bind(format!("{}-{}", prefix, repo_id))
+            .execute(client.pool())
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO commit (id, repo_id, author) VALUES ($1, $2, 'test')")
+            .bind(commit_id)
+            .bind(repo_id)
+            .execute(client.pool())
+            .await
+            .unwrap();
+
+        (repo_id, commit_id)
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_entries_with_identical_created_at_page_in_a_stable_order() {
+        let client = test_index().await;
+        let (repo_id, commit_id) = seed_repo(&client, "stable-sort").await;
+        let created_at = Utc::now();
+        for i in 0..7 {
+            sqlx::query("INSERT INTO entry (commit_id, path, meta, created_at) VALUES ($1, $2, '{}', $3)")
+                .bind(commit_id)
+                .bind(format!("data/{}.csv", i))
+                .bind(created_at)
+                .execute(client.pool())
+                .await
+                .unwrap();
+        }
+
+        let mut runs = Vec::new();
+        for _ in 0..3 {
+            let mut ids = Vec::new();
+            for offset in [0, 3, 6] {
+                let (page, total) = client
+                    .search_entries(repo_id, &HashMap::new(), SearchSort::CreatedAt, Some(3), Some(offset))
+                    .await
+                    .unwrap();
+                assert_eq!(total, 7);
+                ids.extend(page.into_iter().map(|entry| entry.id.0));
+            }
+            runs.push(ids);
+        }
+        sqlx::query("DELETE FROM repo WHERE id = $1").bind(repo_id).execute(client.pool()).await.unwrap();
+
+        // Every page boundary falls in the same place: no entry is repeated
+        // or skipped, and the order is the id tiebreak every time
+        let mut expected = runs[0].clone();
+        expected.sort();
+        expected.dedup();
+        expected.reverse();
+        assert_eq!(runs[0], expected);
+        assert!(runs.iter().all(|run| run == &runs[0]));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_fast_path_filters_match_the_jsonb_metadata() {
+        let client = test_index().await;
+        let (repo_id, commit_id) = seed_repo(&client, "fast-path").await;
+
+        let entries = [
+            ("data/a.csv", json!({"file_type": "text/csv", "org_lab": "ORNL", "creator": "alice", "file_name": "a.csv",
+                                  "tags": ["raw", "qc"], "creation_dt": "2024-06-01T00:00:00Z"})),
+            ("data/b.csv", json!({"file_type": "text/csv", "org_lab": "LANL", "creator": "bob", "file_name": "b.csv",
+                                  "tags": ["raw"], "creation_dt": "2025-02-01T00:00:00Z"})),
+            ("data/c.parquet", json!({"file_type": "application/parquet", "org_lab": "ORNL", "creator": "alice",
+                                      "file_name": "c.parquet", "tags": [], "creation_dt": "2025-03-01T00:00:00Z"})),
+            ("data/d.json", json!({"org_lab": "ORNL"})),
+        ];
+        for (path, meta) in &entries {
+            sqlx::query("INSERT INTO entry (commit_id, path, meta) VALUES ($1, $2, $3)")
+                .bind(commit_id)
+                .bind(path)
+                .bind(meta)
+                .execute(client.pool())
+                .await
+                .unwrap();
+            client.upsert_entry_meta_index(&project_to_index(commit_id, path, meta)).await.unwrap();
+        }
+
+        // Each filter next to the condition on the raw JSONB it stands in for
+        let cases = [
+            ("file_type", json!("text/csv"), "e.meta->>'file_type' = $2"),
+            ("org_lab", json!("ORNL"), "e.meta->>'org_lab' = $2"),
+            ("creator", json!("alice"), "e.meta->>'creator' = $2"),
+            ("file_name", json!("b.csv"), "e.meta->>'file_name' = $2"),
+            ("tags", json!("raw"), "e.meta->'tags' ? $2"),
+            ("tags", json!("qc"), "e.meta->'tags' ? $2"),
+            ("creation_dt_after", json!("2025-01-01T00:00:00Z"), "(e.meta->>'creation_dt')::timestamptz >= $2::timestamptz"),
+            ("creation_dt_before", json!("2025-01-01T00:00:00Z"), "(e.meta->>'creation_dt')::timestamptz <= $2::timestamptz"),
+        ];
+        for (key, value, jsonb_condition) in cases {
+            let filters = HashMap::from([(key.to_string(), value.clone())]);
+            let (found, total) = client.search_entries_with_index(repo_id, &filters, None, None, None).await.unwrap();
+            let fast_path: Vec<String> = found.into_iter().map(|e| e.path).collect();
+
+            let jsonb: Vec<String> = sqlx::query_scalar(&format!(
+                "SELECT e.path FROM entry e WHERE e.commit_id = $1 AND {} ORDER BY e.path",
+                jsonb_condition
+            ))
+            .bind(commit_id)
+            .bind(value.as_str().unwrap())
+            .fetch_all(client.pool())
+            .await
+            .unwrap();
+
+            assert!(!jsonb.is_empty(), "{}={} matches nothing", key, value);
+            assert_eq!(fast_path, jsonb, "{}={}", key, value);
+            assert_eq!(total as usize, jsonb.len());
+        }
+
+        client.delete_repo(repo_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_search_entries_filters_select_matching_rows() {
+        let client = test_index().await;
+        let (repo_id, commit_id) = seed_repo(&client, "search-filters").await;
+
+        let entries = [
+            ("data/a.csv", 100, "2024-06-01T00:00:00Z", json!({"file_type": "text/csv", "org_lab": "ORNL", "tags": ["raw", "qc"]})),
+            ("data/b.csv", 500, "2025-02-01T00:00:00Z", json!({"file_type": "text/csv", "org_lab": "LANL", "tags": ["raw"]})),
+            ("docs/c.parquet", 2000, "2025-03-01T00:00:00Z", json!({"file_type": "application/parquet", "org_lab": "ORNL"})),
+        ];
+        let mut objects = Vec::new();
+        for (path, size, created_at, meta) in &entries {
+            let sha256 = Uuid::new_v4().simple().to_string();
+            client.upsert_object(&sha256, *size, None, &sha256).await.unwrap();
+            sqlx::query("INSERT INTO entry (commit_id, path, object_sha256, meta, created_at) VALUES ($1, $2, $3, $4, $5)")
+                .bind(commit_id)
+                .bind(path)
+                .bind(&sha256)
+                .bind(meta)
+                .bind(chrono::DateTime::parse_from_rfc3339(created_at).unwrap())
+                .execute(client.pool())
+                .await
+                .unwrap();
+            objects.push(sha256);
+        }
+
+        let cases = [
+            ("path", json!("data/"), vec!["data/a.csv", "data/b.csv"]),
+            ("file_type", json!("application/parquet"), vec!["docs/c.parquet"]),
+            ("size_min", json!("500"), vec!["data/b.csv", "docs/c.parquet"]),
+            ("size_max", json!(500), vec!["data/a.csv", "data/b.csv"]),
+            ("created_after", json!("2025-01-01T00:00:00Z"), vec!["data/b.csv", "docs/c.parquet"]),
+            ("created_before", json!("2025-01-01T00:00:00Z"), vec!["data/a.csv"]),
+            ("tags", json!("qc"), vec!["data/a.csv"]),
+            ("tags", json!(["qc", "raw"]), vec!["data/a.csv", "data/b.csv"]),
+            ("org_lab", json!("ORNL"), vec!["data/a.csv", "docs/c.parquet"]),
+        ];
+        for (key, value, expected) in cases {
+            let filters = HashMap::from([(key.to_string(), value.clone())]);
+            let (found, total) = client
+                .search_entries(repo_id, &filters, SearchSort::Path, None, None)
+                .await
+                .unwrap();
+            let mut paths: Vec<String> = found.into_iter().map(|e| e.path).collect();
+            paths.sort();
+            assert_eq!(paths, expected, "{}={}", key, value);
+            assert_eq!(total as usize, expected.len(), "{}={}", key, value);
+        }
+
+        // Filters combine with AND
+        let filters = HashMap::from([
+            ("org_lab".to_string(), json!("ORNL")),
+            ("size_max".to_string(), json!(1000)),
+        ]);
+        let (found, _) = client.search_entries(repo_id, &filters, SearchSort::Path, None, None).await.unwrap();
+        assert_eq!(found.into_iter().map(|e| e.path).collect::<Vec<_>>(), vec!["data/a.csv"]);
+
+        let filters = HashMap::from([("size_min".to_string(), json!("not-a-number"))]);
+        let rejected = client.search_entries(repo_id, &filters, SearchSort::Path, None, None).await;
+        assert!(matches!(rejected, Err(IndexError::InvalidFilter(_))));
+
+        client.delete_repo(repo_id).await.unwrap();
+        client.delete_orphaned_objects(&objects).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_fulltext_search_ranks_exact_phrase_above_partial_match() {
+        let client = test_index().await;
+        let (repo_id, commit_id) = seed_repo(&client, "fulltext").await;
+
+        let docs = [
+            ("partial.csv", "Surface readings from the temperature logger at sea"),
+            ("exact.csv", "Daily sea surface temperature from moored buoys"),
+            ("unrelated.csv", "Soil moisture survey"),
+        ];
+        for (path, description) in docs {
+            sqlx::query("INSERT INTO entry (commit_id, path, meta) VALUES ($1, $2, '{}')")
+                .bind(commit_id)
+                .bind(path)
+                .execute(client.pool())
+                .await
+                .unwrap();
+            client
+                .upsert_entry_meta_index(&EntryMetaIndex {
+                    commit_id: blacklake_core::UuidWrapper(commit_id),
+                    path: path.to_string(),
+                    creation_dt: None,
+                    creator: None,
+                    file_name: Some(path.to_string()),
+                    file_type: None,
+                    file_size: None,
+                    org_lab: None,
+                    description: Some(description.to_string()),
+                    data_source: None,
+                    data_collection_method: None,
+                    version: None,
+                    notes: None,
+                    tags: None,
+                    license: None,
+                })
+                .await
+                .unwrap();
+        }
+
+        let ranked = client.search_entries_fulltext(repo_id, "sea surface temperature", None, None).await;
+        let phrase_only = client.search_entries_fulltext(repo_id, r#""sea surface temperature""#, None, None).await;
+        sqlx::query("DELETE FROM repo WHERE id = $1").bind(repo_id).execute(client.pool()).await.unwrap();
+
+        let (entries, total) = ranked.unwrap();
+        let paths: Vec<&str> = entries.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, vec!["exact.csv", "partial.csv"]);
+        assert_eq!(total, 2);
+
+        let (entries, total) = phrase_only.unwrap();
+        assert_eq!(entries.iter().map(|e| e.path.as_str()).collect::<Vec<_>>(), vec!["exact.csv"]);
+        assert_eq!(total, 1);
+    }
+
+    #[test]
+    fn test_check_repo_deletable() {
+        let policy = |legal_hold| RetentionPolicy { tombstone_days: 30, hard_delete_days: 90, legal_hold };
+
+        assert!(check_repo_deletable("scratch", None).is_ok());
+        assert!(check_repo_deletable("scratch", Some(&policy(false))).is_ok());
+        assert!(matches!(
+            check_repo_deletable("evidence", Some(&policy(true))),
+            Err(IndexError::LegalHold(name)) if name == "evidence"
+        ));
+    }
+
+    #[test]
+    fn test_repo_delete_removes_children_before_repo() {
+        let position = |table: &str| {
+            REPO_DELETE_STATEMENTS
+                .iter()
+                .position(|sql| sql.starts_with(&format!("DELETE FROM {} ", table)))
+                .unwrap()
+        };
+
+        assert!(position("ref") < position("commit"));
+        assert!(position("artifact_rdf") < position("entry"));
+        assert!(position("entry_meta_index") < position("entry"));
+        assert!(position("entry") < position("commit"));
+        assert_eq!(position("repo"), REPO_DELETE_STATEMENTS.len() - 1);
+    }
+
+    /// Insert an object and an entry for it in `commit_id`
+    async fn seed_entry(client: &IndexClient, commit_id: Uuid, path: &str, sha256: &str) {
+        sqlx::query("INSERT INTO object (sha256, size, s3_key) VALUES ($1, 1, $1) ON CONFLICT DO NOTHING")
+            .bind(sha256)
+            .execute(client.pool())
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO entry (commit_id, path, object_sha256, meta) VALUES ($1, $2, $3, '{}')")
+            .bind(commit_id)
+            .bind(path)
+            .bind(sha256)
+            .execute(client.pool())
+            .await
+            .unwrap();
+    }
+
+    /// Unit vector mixing the first two axes of the embedding space
+    fn stub_embedding(x: f32, y: f32) -> Vec<f32> {
+        let mut embedding = vec![0.0; blacklake_core::embeddings::ENTRY_EMBEDDING_DIMENSIONS];
+        embedding[0] = x;
+        embedding[1] = y;
+        embedding
+    }
+
+    /// Also needs migration 0023, which requires the pgvector extension
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_nearest_entries_orders_by_cosine_distance() {
+        let client = test_index().await;
+        let has_table: bool = sqlx::query_scalar("SELECT to_regclass('entry_embedding') IS NOT NULL")
+            .fetch_one(client.pool())
+            .await
+            .unwrap();
+        if !has_table {
+            return;
+        }
+        let (repo_id, commit_id) = seed_repo(&client, "nearest").await;
+        let (other_repo, other_commit) = seed_repo(&client, "nearest-other").await;
+
+        let embedded = [
+            (commit_id, "far.csv", stub_embedding(0.0, 1.0)),
+            (commit_id, "near.csv", stub_embedding(0.8, 0.6)),
+            (commit_id, "exact.csv", stub_embedding(1.0, 0.0)),
+            (other_commit, "exact.csv", stub_embedding(1.0, 0.0)),
+        ];
+        for (commit, path, embedding) in &embedded {
+            seed_entry(&client, *commit, path, &Uuid::new_v4().simple().to_string()).await;
+            client.upsert_entry_embedding(*commit, path, "stub", embedding).await.unwrap();
+        }
+
+        // A later commit re-embeds far.csv closer to the query; only that version counts
+        let later_commit = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO commit (id, repo_id, parent_id, author, created_at)
+             VALUES ($1, $2, $3, 'test', NOW() + INTERVAL '1 minute')",
+        )
+        .bind(later_commit)
+        .bind(repo_id)
+        .bind(commit_id)
+        .execute(client.pool())
+        .await
+        .unwrap();
+        seed_entry(&client, later_commit, "far.csv", &Uuid::new_v4().simple().to_string()).await;
+        client
+            .upsert_entry_embedding(later_commit, "far.csv", "stub", &stub_embedding(0.6, 0.8))
+            .await
+            .unwrap();
+
+        let nearest = client.nearest_entries(repo_id, &stub_embedding(1.0, 0.0), 10).await.unwrap();
+        let ranked: Vec<(&str, Uuid)> = nearest.iter().map(|(e, _)| (e.path.as_str(), e.commit_id.0)).collect();
+        assert_eq!(ranked, vec![("exact.csv", commit_id), ("near.csv", commit_id), ("far.csv", later_commit)]);
+        let similarities: Vec<f32> = nearest.iter().map(|(_, s)| *s).collect();
+        for (actual, expected) in similarities.iter().zip([1.0, 0.8, 0.6]) {
+            assert!((actual - expected).abs() < 1e-5, "{:?}", similarities);
+        }
+
+        let top = client.nearest_entries(repo_id, &stub_embedding(1.0, 0.0), 2).await.unwrap();
+        assert_eq!(top.len(), 2);
+
+        for id in [repo_id, other_repo] {
+            client.delete_repo(id).await.unwrap();
+        }
+    }
+
+    async fn count(client: &IndexClient, sql: &str, id: Uuid) -> i64 {
+        sqlx::query_scalar(sql).bind(id).fetch_one(client.pool()).await.unwrap()
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_delete_repo_cascades_and_schedules_unshared_objects() {
+        let client = test_index().await;
+        let (repo_id, commit_id) = seed_repo(&client, "delete").await;
+        let (other_repo, other_commit) = seed_repo(&client, "keep").await;
+        let only_here = Uuid::new_v4().simple().to_string();
+        let shared = Uuid::new_v4().simple().to_string();
+
+        seed_entry(&client, commit_id, "data/only.csv", &only_here).await;
+        seed_entry(&client, commit_id, "data/shared.csv", &shared).await;
+        seed_entry(&client, other_commit, "data/shared.csv", &shared).await;
+        sqlx::query("INSERT INTO ref (repo_id, name, kind, commit_id) VALUES ($1, 'main', 'branch', $2)")
+            .bind(repo_id)
+            .bind(commit_id)
+            .execute(client.pool())
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO entry_meta_index (commit_id, path, description) VALUES ($1, 'data/only.csv', 'x')")
+            .bind(commit_id)
+            .execute(client.pool())
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO repo_retention (repo_id, retention_policy) VALUES ($1, $2)")
+            .bind(repo_id)
+            .bind(json!({"tombstone_days": 7, "hard_delete_days": 14, "legal_hold": false}))
+            .execute(client.pool())
+            .await
+            .unwrap();
+
+        let deletion = client.delete_repo(repo_id).await.unwrap();
+
+        assert_eq!(deletion.repo_id.0, repo_id);
+        assert_eq!(deletion.unreferenced_objects, 1);
+        let window = deletion.gc_not_before - Utc::now();
+        assert!(window > chrono::Duration::days(13) && window <= chrono::Duration::days(14));
+
+        assert_eq!(count(&client, "SELECT COUNT(*) FROM repo WHERE id = $1", repo_id).await, 0);
+        assert_eq!(count(&client, "SELECT COUNT(*) FROM ref WHERE repo_id = $1", repo_id).await, 0);
+        assert_eq!(count(&client, "SELECT COUNT(*) FROM commit WHERE repo_id = $1", repo_id).await, 0);
+        assert_eq!(count(&client, "SELECT COUNT(*) FROM entry WHERE commit_id = $1", commit_id).await, 0);
+        assert_eq!(count(&client, "SELECT COUNT(*) FROM entry_meta_index WHERE commit_id = $1", commit_id).await, 0);
+        assert_eq!(count(&client, "SELECT COUNT(*) FROM entry WHERE commit_id = $1", other_commit).await, 1);
+
+        let scheduled: Vec<String> = sqlx::query_scalar("SELECT sha256 FROM object_gc_schedule WHERE sha256 = ANY($1)")
+            .bind(vec![only_here.clone(), shared.clone()])
+            .fetch_all(client.pool())
+            .await
+            .unwrap();
+        assert_eq!(scheduled, vec![only_here.clone()]);
+
+        // Held back from GC until the window passes, even though nothing references it
+        let orphans = client.find_orphaned_objects(Utc::now() + chrono::Duration::days(1)).await.unwrap();
+        assert!(!orphans.iter().any(|o| o.sha256 == only_here));
+
+        client.delete_repo(other_repo).await.unwrap();
+        sqlx::query("DELETE FROM object WHERE sha256 = ANY($1)")
+            .bind(vec![only_here, shared])
+            .execute(client.pool())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_delete_repo_refused_under_legal_hold() {
+        let client = test_index().await;
+        let (repo_id, commit_id) = seed_repo(&client, "held").await;
+        sqlx::query("INSERT INTO entry (commit_id, path, meta) VALUES ($1, 'data/a.csv', '{}')")
+            .bind(commit_id)
+            .execute(client.pool())
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO repo_retention (repo_id, retention_policy) VALUES ($1, $2)")
+            .bind(repo_id)
+            .bind(json!({"tombstone_days": 30, "hard_delete_days": 90, "legal_hold": true}))
+            .execute(client.pool())
+            .await
+            .unwrap();
+
+        let result = client.delete_repo(repo_id).await;
+
+        assert!(matches!(result, Err(IndexError::LegalHold(_))));
+        assert_eq!(count(&client, "SELECT COUNT(*) FROM entry WHERE commit_id = $1", commit_id).await, 1);
+
+        sqlx::query("DELETE FROM repo WHERE id = $1").bind(repo_id).execute(client.pool()).await.unwrap();
+    }
+
+    /// Commit `data/a.csv`, then delete it in a second commit; returns the repo
+    /// id, the first commit and the object hash
+    async fn seed_deleted_entry(client: &IndexClient, prefix: &str, legal_hold: bool) -> (Uuid, Uuid, String) {
+        let (repo_id, _) = seed_repo(client, prefix).await;
+        sqlx::query("INSERT INTO repo_retention (repo_id, retention_policy) VALUES ($1, $2)")
+            .bind(repo_id)
+            .bind(json!({"tombstone_days": 30, "hard_delete_days": 90, "legal_hold": legal_hold}))
+            .execute(client.pool())
+            .await
+            .unwrap();
+        let sha256 = Uuid::new_v4().simple().to_string();
+        client.upsert_object(&sha256, 42, None, &sha256).await.unwrap();
+
+        let add = vec![change(ChangeOp::Add, "data/a.csv", Some(&sha256))];
+        let (first, _) = client.commit_atomic(&commit_write(repo_id, &add, None)).await.unwrap();
+        let delete = vec![change(ChangeOp::Delete, "data/a.csv", None)];
+        client.commit_atomic(&commit_write(repo_id, &delete, Some(first.id.0))).await.unwrap();
+
+        (repo_id, first.id.0, sha256)
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_retention_tombstones_then_hard_deletes_deleted_entries() {
+        let client = test_index().await;
+        let (repo_id, first_commit, sha256) = seed_deleted_entry(&client, "retention", false).await;
+        let deleted_at: Option<chrono::DateTime<Utc>> =
+            sqlx::query_scalar("SELECT deleted_at FROM entry WHERE commit_id = $1 AND path = 'data/a.csv'")
+                .bind(first_commit)
+                .fetch_one(client.pool())
+                .await
+                .unwrap();
+        let deleted_at = deleted_at.expect("delete stamps the parent entry");
+        let tombstoned = "SELECT COUNT(*) FROM entry WHERE commit_id = $1 AND tombstoned_at IS NOT NULL";
+
+        // Inside the tombstone window nothing changes
+        let run = client.enforce_retention(repo_id, deleted_at + chrono::Duration::days(29)).await.unwrap();
+        assert_eq!(run, RetentionRun::default());
+        assert_eq!(count(&client, tombstoned, first_commit).await, 0);
+
+        let run = client.enforce_retention(repo_id, deleted_at + chrono::Duration::days(30)).await.unwrap();
+        assert_eq!(run.tombstoned, vec!["data/a.csv".to_string()]);
+        assert!(run.hard_deleted.is_empty());
+        assert_eq!(count(&client, tombstoned, first_commit).await, 1);
+
+        // Tombstoned but still inside the hard-delete window
+        let run = client.enforce_retention(repo_id, deleted_at + chrono::Duration::days(89)).await.unwrap();
+        assert_eq!(run, RetentionRun::default());
+
+        let run = client.enforce_retention(repo_id, deleted_at + chrono::Duration::days(90)).await.unwrap();
+        assert_eq!(run.hard_deleted, vec!["data/a.csv".to_string()]);
+        assert_eq!(run.released_objects, vec![sha256.clone()]);
+        assert_eq!(count(&client, "SELECT COUNT(*) FROM entry WHERE commit_id = $1", first_commit).await, 0);
+        assert_eq!(client.object_ref_count(&sha256).await.unwrap(), 0);
+
+        let audited: Vec<String> = sqlx::query_scalar(
+            "SELECT action FROM audit_log WHERE actor = 'system:retention' AND repo_name LIKE $1 ORDER BY id",
+        )
+        .bind(format!("retention-{}", repo_id))
+        .fetch_all(client.pool())
+        .await
+        .unwrap();
+        assert_eq!(audited, vec!["retention_tombstone", "retention_hard_delete"]);
+
+        client.delete_repo(repo_id).await.unwrap();
+        client.delete_orphaned_objects(&[sha256]).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_retention_does_nothing_under_legal_hold() {
+        let client = test_index().await;
+        let (repo_id, first_commit, sha256) = seed_deleted_entry(&client, "retention-held", true).await;
+        let far_future = Utc::now() + chrono::Duration::days(3650);
+
+        let run = client.enforce_retention(repo_id, far_future).await.unwrap();
+
+        assert_eq!(run, RetentionRun { legal_hold: true, ..Default::default() });
+        assert_eq!(
+            count(&client, "SELECT COUNT(*) FROM entry WHERE commit_id = $1 AND tombstoned_at IS NULL", first_commit).await,
+            1
+        );
+
+        sqlx::query("DELETE FROM repo WHERE id = $1").bind(repo_id).execute(client.pool()).await.unwrap();
+        client.delete_orphaned_objects(&[sha256]).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_create_commit_checks_parent_of_target_branch() {
+        let client = test_index().await;
+        let (repo_id, main_head) = seed_repo(&client, "parent-check").await;
+        let dev_head = client.create_commit(repo_id, "dev", Some(main_head), "test", None, None).await.unwrap().id.0;
+        client.set_ref(repo_id, "main", ReferenceKind::Branch, main_head).await.unwrap();
+        client.set_ref(repo_id, "dev", ReferenceKind::Branch, dev_head).await.unwrap();
+
+        // main's head is a stale parent for dev, even though main still points at it
+        let stale = client.create_commit(repo_id, "dev", Some(main_head), "test", None, Some(main_head)).await;
+        match stale {
+            Err(IndexError::ParentMismatch { ref_name, expected, actual }) => {
+                assert_eq!(ref_name, "dev");
+                assert_eq!(expected, main_head);
+                assert_eq!(actual, Some(dev_head));
+            }
+            other => panic!("expected ParentMismatch, got {:?}", other),
+        }
+
+        let rebased = client.create_commit(repo_id, "dev", Some(dev_head), "test", None, Some(dev_head)).await;
+        assert!(rebased.is_ok());
+
+        // A branch that does not exist yet has no head to match
+        let missing = client.create_commit(repo_id, "feature", None, "test", None, Some(main_head)).await;
+        assert!(matches!(missing, Err(IndexError::ParentMismatch { actual: None, .. })));
+
+        client.delete_repo(repo_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_compare_and_set_ref_refuses_a_moved_branch() {
+        let client = test_index().await;
+        let (repo_id, first) = seed_repo(&client, "ref-cas").await;
+        let second = client.create_commit(repo_id, "main", Some(first), "test", None, None).await.unwrap().id.0;
+        let third = client.create_commit(repo_id, "main", Some(second), "test", None, None).await.unwrap().id.0;
+        client.set_ref(repo_id, "main", ReferenceKind::Branch, first).await.unwrap();
+
+        client.compare_and_set_ref(repo_id, "main", Some(first), second).await.unwrap();
+
+        // A second writer that read the old head loses rather than overwriting
+        let stale = client.compare_and_set_ref(repo_id, "main", Some(first), third).await;
+        assert!(matches!(
+            stale,
+            Err(IndexError::ParentMismatch { expected, actual: Some(actual), .. }) if expected == first && actual == second
+        ));
+        assert_eq!(client.get_ref(repo_id, "main").await.unwrap().commit_id.0, second);
+
+        client.compare_and_set_ref(repo_id, "dev", None, third).await.unwrap();
+        let created_twice = client.compare_and_set_ref(repo_id, "dev", None, first).await;
+        assert!(matches!(created_twice, Err(IndexError::RefExists(_))));
+        let gone = client.compare_and_set_ref(repo_id, "missing", Some(first), third).await;
+        assert!(matches!(gone, Err(IndexError::ParentMismatch { actual: None, .. })));
+
+        client.create_tag(repo_id, "v1.0", first, "alice", None, false).await.unwrap();
+        let tag = client.compare_and_set_ref(repo_id, "v1.0", Some(first), third).await;
+        assert!(matches!(tag, Err(IndexError::TagImmutable(_))));
+
+        client.delete_repo(repo_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_create_tag_round_trips_annotation_and_refuses_retag() {
+        let client = test_index().await;
+        let (repo_id, first) = seed_repo(&client, "tags").await;
+        let second = client.create_commit(repo_id, "main", Some(first), "test", None, None).await.unwrap().id.0;
+
+        let tag = client.create_tag(repo_id, "v1.0", first, "alice", Some("First release"), false).await.unwrap();
+        assert!(matches!(tag.kind, ReferenceKind::Tag));
+        let annotation = tag.annotation.unwrap();
+        assert_eq!((annotation.message.as_str(), annotation.tagger.as_str()), ("First release", "alice"));
+
+        let listed = client.list_refs(repo_id, Some(ReferenceKind::Tag)).await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].annotation.as_ref().unwrap().message, "First release");
+
+        // Tags don't move without force, whether re-tagged, set or committed to
+        let retag = client.create_tag(repo_id, "v1.0", second, "bob", Some("Oops"), false).await;
+        assert!(matches!(retag, Err(IndexError::RefExists(name)) if name == "v1.0"));
+        let set = client.set_ref(repo_id, "v1.0", ReferenceKind::Branch, second).await;
+        assert!(matches!(set, Err(IndexError::TagImmutable(_))));
+        let changes: Vec<Change> = vec![];
+        let mut write = commit_write(repo_id, &changes, None);
+        write.ref_name = "v1.0";
+        assert!(matches!(client.commit_atomic(&write).await, Err(IndexError::TagImmutable(_))));
+        assert_eq!(client.get_ref(repo_id, "v1.0").await.unwrap().commit_id.0, first);
+
+        let forced = client.create_tag(repo_id, "v1.0", second, "bob", None, true).await.unwrap();
+        assert_eq!(forced.commit_id.0, second);
+        assert!(forced.annotation.is_none());
+
+        // A branch of the same name is never replaced by a tag
+        client.set_ref(repo_id, "main", ReferenceKind::Branch, second).await.unwrap();
+        let over_branch = client.create_tag(repo_id, "main", first, "bob", None, true).await;
+        assert!(matches!(over_branch, Err(IndexError::RefExists(_))));
+
+        client.delete_repo(repo_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_query_audit_log_filters_by_actor_and_action_newest_first() {
+        let client = test_index().await;
+        let repo = format!("audit-{}", Uuid::new_v4());
+        let alice = format!("alice-{}", Uuid::new_v4());
+        for (actor, action, path) in [
+            (alice.as_str(), "commit", "a.csv"),
+            ("bob", "commit", "b.csv"),
+            (alice.as_str(), "ref_delete", "c.csv"),
+            (alice.as_str(), "commit", "d.csv"),
+        ] {
+            client
+                .append_audit_log(actor, action, Some(&repo), Some("main"), Some(path), None, None)
+                .await
+                .unwrap();
+        }
+
+        let by_actor = AuditLogFilter { actor: Some(alice.clone()), ..Default::default() };
+        let (entries, total) = client.query_audit_log(&by_actor, None, None).await.unwrap();
+        assert_eq!(total, 3);
+        let paths: Vec<_> = entries.iter().map(|e| e.path.as_deref().unwrap()).collect();
+        assert_eq!(paths, vec!["d.csv", "c.csv", "a.csv"]);
+
+        let commits = AuditLogFilter {
+            actor: Some(alice.clone()),
+            action: Some("commit".to_string()),
+            ..Default::default()
+        };
+        let (page, total) = client.query_audit_log(&commits, Some(1), Some(1)).await.unwrap();
+        assert_eq!(total, 2);
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].path.as_deref(), Some("a.csv"));
+
+        let future = AuditLogFilter {
+            repo_name: Some(repo.clone()),
+            since: Some(Utc::now() + chrono::Duration::hours(1)),
+            ..Default::default()
+        };
+        assert_eq!(client.query_audit_log(&future, None, None).await.unwrap().1, 0);
+
+        sqlx::query("DELETE FROM audit_log WHERE repo_name = $1").bind(&repo).execute(client.pool()).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_list_tree_collapses_directories_and_pages_rows() {
+        let client = test_index().await;
+        let (repo_id, commit_id) = seed_repo(&client, "tree").await;
+        let sha256 = Uuid::new_v4().simple().to_string();
+        for path in ["data/a.csv", "data/sub/b.csv", "data/sub/c.csv", "data/z/d.csv", "data/y.csv", "readme.md"] {
+            seed_entry(&client, commit_id, path, &sha256).await;
+        }
+        let paths = |entries: &[Entry]| entries.iter().map(|e| e.path.clone()).collect::<Vec<_>>();
+
+        let (root, total) = client.list_tree(commit_id, None, Some("/"), None, None).await.unwrap();
+        assert_eq!(paths(&root), vec!["data/", "readme.md"]);
+        assert_eq!(total, 2);
+        assert!(root[0].is_dir && root[0].object_sha256.is_none());
+        assert_eq!(root[1].object_sha256.as_deref(), Some(sha256.as_str()));
+
+        let (data, total) = client.list_tree(commit_id, Some("data/"), Some("/"), None, None).await.unwrap();
+        assert_eq!(paths(&data), vec!["data/a.csv", "data/sub/", "data/y.csv", "data/z/"]);
+        assert_eq!(total, 4);
+
+        let (first, _) = client.list_tree(commit_id, Some("data/"), Some("/"), Some(2), None).await.unwrap();
+        let (second, total) = client.list_tree(commit_id, Some("data/"), Some("/"), Some(2), Some(2)).await.unwrap();
+        assert_eq!(paths(&first), vec!["data/a.csv", "data/sub/"]);
+        assert_eq!(paths(&second), vec!["data/y.csv", "data/z/"]);
+        assert_eq!(total, 4);
+
+        // Without a delimiter every descendant is listed
+        let (all, total) = client.list_tree(commit_id, Some("data/"), None, None, None).await.unwrap();
+        assert_eq!(all.len(), 5);
+        assert_eq!(total, 5);
+
+        client.delete_repo(repo_id).await.unwrap();
+        client.delete_orphaned_objects(&[sha256]).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_mismatched_upload_only_discards_unverified_unreferenced_objects() {
+        let client = test_index().await;
+
+        // A second upload-init of the same hash cannot change the stored size
+        let sha256 = Uuid::new_v4().simple().to_string();
+        client.upsert_object(&sha256, 42, Some("text/csv"), &sha256).await.unwrap();
+        let stored = client.upsert_object(&sha256, 1, None, &sha256).await.unwrap();
+        assert_eq!((stored.size, stored.media_type.as_deref()), (42, Some("text/csv")));
+
+        // Once verified, a failed re-upload leaves the object alone
+        assert!(!client.is_object_verified(&sha256).await.unwrap());
+        client.mark_object_verified(&sha256).await.unwrap();
+        assert!(client.is_object_verified(&sha256).await.unwrap());
+        assert!(!client.discard_unverified_object(&sha256).await.unwrap());
+
+        // So does a reference from a commit
+        let (repo_id, commit_id) = seed_repo(&client, "discard").await;
+        let referenced = Uuid::new_v4().simple().to_string();
+        client.upsert_object(&referenced, 7, None, &referenced).await.unwrap();
+        seed_entry(&client, commit_id, "data/a.csv", &referenced).await;
+        assert!(!client.discard_unverified_object(&referenced).await.unwrap());
+
+        let fresh = Uuid::new_v4().simple().to_string();
+        client.upsert_object(&fresh, 7, None, &fresh).await.unwrap();
+        assert!(client.discard_unverified_object(&fresh).await.unwrap());
+        assert!(client.get_object(&fresh).await.unwrap().is_none());
+
+        client.delete_repo(repo_id).await.unwrap();
+        sqlx::query("DELETE FROM object WHERE sha256 = ANY($1)")
+            .bind(vec![sha256, referenced])
+            .execute(client.pool())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_tree_listings_carry_object_size_and_media_type() {
+        let client = test_index().await;
+        let (repo_id, commit_id) = seed_repo(&client, "tree-sizes").await;
+        let sha256 = Uuid::new_v4().simple().to_string();
+        client.upsert_object(&sha256, 4096, Some("text/csv"), &sha256).await.unwrap();
+        seed_entry(&client, commit_id, "data/a.csv", &sha256).await;
+        seed_entry(&client, commit_id, "data/sub/b.csv", &sha256).await;
+        sqlx::query("INSERT INTO entry (commit_id, path, object_sha256, meta, is_dir) VALUES ($1, 'data/empty/', NULL, '{}', true)")
+            .bind(commit_id)
+            .execute(client.pool())
+            .await
+            .unwrap();
+
+        let entries = client.get_tree_entries(commit_id, Some("data/")).await.unwrap();
+        let file = entries.iter().find(|e| e.path == "data/a.csv").unwrap();
+        assert_eq!((file.size, file.media_type.as_deref()), (Some(4096), Some("text/csv")));
+        let dir = entries.iter().find(|e| e.path == "data/empty/").unwrap();
+        assert!(dir.is_dir && dir.size.is_none() && dir.media_type.is_none());
+
+        // Collapsed directories have no object even though their files do
+        let (listed, _) = client.list_tree(commit_id, Some("data/"), Some("/"), None, None).await.unwrap();
+        let sizes: Vec<_> = listed.iter().map(|e| (e.path.as_str(), e.size)).collect();
+        assert_eq!(sizes, vec![("data/a.csv", Some(4096)), ("data/empty/", None), ("data/sub/", None)]);
+        assert_eq!(listed[0].media_type.as_deref(), Some("text/csv"));
+
+        client.delete_repo(repo_id).await.unwrap();
+        client.delete_orphaned_objects(&[sha256]).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_existing_objects_reports_only_stored_hashes() {
+        let client = test_index().await;
+        let stored = Uuid::new_v4().simple().to_string();
+        let new = Uuid::new_v4().simple().to_string();
+        client.upsert_object(&stored, 7, None, &stored).await.unwrap();
+
+        let existing = client.existing_objects(&[stored.clone(), new.clone()]).await.unwrap();
+
+        assert!(existing.contains(&stored));
+        assert!(!existing.contains(&new));
+        assert!(client.existing_objects(&[]).await.unwrap().is_empty());
+
+        client.delete_orphaned_objects(&[stored]).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_resolve_export_entries_expands_prefixes_and_globs() {
+        let client = test_index().await;
+        let (repo_id, commit_id) = seed_repo(&client, "export").await;
+        let sha256 = Uuid::new_v4().simple().to_string();
+        for path in ["datasets/a.csv", "datasets/raw/b.csv", "datasets/raw/b.json", "datasets_old/c.csv", "notes.md"] {
+            seed_entry(&client, commit_id, path, &sha256).await;
+        }
+
+        let paths = |patterns: &[&str]| patterns.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+        let resolved = |entries: Vec<Entry>| entries.into_iter().map(|e| e.path).collect::<Vec<_>>();
+
+        let entries = client.resolve_export_entries(commit_id, &paths(&["datasets/", "datasets/raw/b.csv"])).await.unwrap();
+        assert_eq!(resolved(entries), vec!["datasets/a.csv", "datasets/raw/b.csv", "datasets/raw/b.json"]);
+
+        let entries = client.resolve_export_entries(commit_id, &paths(&["**/*.csv"])).await.unwrap();
+        assert_eq!(resolved(entries), vec!["datasets/a.csv", "datasets/raw/b.csv", "datasets_old/c.csv"]);
+
+        client.delete_repo(repo_id).await.unwrap();
+        client.delete_orphaned_objects(&[sha256]).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_orphan_delete_returns_only_the_rows_it_removed() {
+        let client = test_index().await;
+        let (repo_id, commit_id) = seed_repo(&client, "gc-rows").await;
+        let orphan = Uuid::new_v4().simple().to_string();
+        let referenced = Uuid::new_v4().simple().to_string();
+        client.upsert_object(&orphan, 3, None, &format!("objects/{}", orphan)).await.unwrap();
+        seed_entry(&client, commit_id, "data/a.csv", &referenced).await;
+
+        // The referenced object was picked up after the scan; its row and so
+        // its blob are kept
+        let removed = client.delete_orphaned_objects(&[orphan.clone(), referenced.clone()]).await.unwrap();
+        let removed: Vec<(&str, &str)> = removed.iter().map(|o| (o.sha256.as_str(), o.s3_key.as_str())).collect();
+        assert_eq!(removed, vec![(orphan.as_str(), format!("objects/{}", orphan).as_str())]);
+        assert!(client.get_object(&referenced).await.unwrap().is_some());
+
+        client.delete_repo(repo_id).await.unwrap();
+        client.delete_orphaned_objects(&[referenced]).await.unwrap();
+    }
+
+    /// Also needs migration 0026
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_derived_object_is_kept_from_gc_until_its_source_goes() {
+        let client = test_index().await;
+        let (repo_id, commit_id) = seed_repo(&client, "derived").await;
+        let source = Uuid::new_v4().simple().to_string();
+        let derived = Uuid::new_v4().simple().to_string();
+        seed_entry(&client, commit_id, "images/board.png", &source).await;
+        client.upsert_object(&derived, 9, Some("image/png"), &derived).await.unwrap();
+        sqlx::query(
+            "INSERT INTO derived_artifact (commit_id, path, kind, source_sha256, derived_sha256)
+             VALUES ($1, 'images/board.png', 'thumbnail', $2, $3)",
+        )
+        .bind(commit_id)
+        .bind(&source)
+        .bind(&derived)
+        .execute(client.pool())
+        .await
+        .unwrap();
+
+        let object = client.get_derived_object(commit_id, "images/board.png", "thumbnail").await.unwrap().unwrap();
+        assert_eq!(object.sha256, derived);
+        assert_eq!(object.media_type.as_deref(), Some("image/png"));
+        assert!(client.get_derived_object(commit_id, "images/board.png", "parquet").await.unwrap().is_none());
+        assert!(client.delete_orphaned_objects(&[derived.clone()]).await.unwrap().is_empty());
+
+        client.delete_repo(repo_id).await.unwrap();
+        assert_eq!(client.delete_orphaned_objects(&[derived.clone(), source]).await.unwrap().len(), 2);
+    }
+
+    fn commit_write<'a>(repo_id: Uuid, changes: &'a [Change], expected_parent: Option<Uuid>) -> CommitWrite<'a> {
+        CommitWrite {
+            repo_id,
+            ref_name: "main",
+            author: "test",
+            message: Some("atomic"),
+            expected_parent,
+            changes,
+            schema: Some(("default", "1.0")),
+            merge_parent: None,
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_commit_atomic_writes_commit_entries_index_and_ref() {
+        let client = test_index().await;
+        let (repo_id, _) = seed_repo(&client, "atomic").await;
+        let sha256 = Uuid::new_v4().simple().to_string();
+        client.upsert_object(&sha256, 42, None, &sha256).await.unwrap();
+        let mut add = change(ChangeOp::Add, "data/a.csv", Some(&sha256));
+        add.meta = json!({"file_name": "a.csv", "description": "first"});
+        let changes = vec![add];
+
+        let (commit, stats) = client.commit_atomic(&commit_write(repo_id, &changes, None)).await.unwrap();
+
+        assert_eq!(stats.bytes_added, 42);
+        assert_eq!(client.get_ref(repo_id, "main").await.unwrap().commit_id.0, commit.id.0);
+        let schema: (Option<String>, Option<String>) = sqlx::query_as(
+            "SELECT schema_name, schema_version FROM entry WHERE commit_id = $1 AND path = 'data/a.csv'",
+        )
+        .bind(commit.id.0)
+        .fetch_one(client.pool())
+        .await
+        .unwrap();
+        assert_eq!(schema, (Some("default".to_string()), Some("1.0".to_string())));
+        assert_eq!(count(&client, "SELECT COUNT(*) FROM entry_meta_index WHERE commit_id = $1", commit.id.0).await, 1);
+
+        // A stale parent is refused without writing anything
+        let stale = client.commit_atomic(&commit_write(repo_id, &changes, Some(Uuid::new_v4()))).await;
+        assert!(matches!(stale, Err(IndexError::ParentMismatch { .. })));
+        assert_eq!(count(&client, "SELECT COUNT(*) FROM commit WHERE repo_id = $1", repo_id).await, 2);
+
+        client.delete_repo(repo_id).await.unwrap();
+        sqlx::query("DELETE FROM object WHERE sha256 = $1").bind(&sha256).execute(client.pool()).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_commit_atomic_rolls_back_when_entry_binding_fails() {
+        let client = test_index().await;
+        let (repo_id, _) = seed_repo(&client, "rollback").await;
+        let stored = Uuid::new_v4().simple().to_string();
+        client.upsert_object(&stored, 1, None, &stored).await.unwrap();
+        // The second entry points at an object that was never uploaded, so its
+        // insert violates the entry -> object foreign key
+        let changes = vec![
+            change(ChangeOp::Add, "data/ok.csv", Some(&stored)),
+            change(ChangeOp::Add, "data/missing.csv", Some(&"f".repeat(64))),
+        ];
+
+        let result = client.commit_atomic(&commit_write(repo_id, &changes, None)).await;
+
+        assert!(matches!(result, Err(IndexError::Database(_))));
+        // Only the commit seed_repo made survives
+        assert_eq!(count(&client, "SELECT COUNT(*) FROM commit WHERE repo_id = $1", repo_id).await, 1);
+        assert_eq!(
+            count(&client, "SELECT COUNT(*) FROM entry e JOIN commit c ON c.id = e.commit_id WHERE c.repo_id = $1", repo_id).await,
+            0
+        );
+        assert!(matches!(client.get_ref(repo_id, "main").await, Err(IndexError::RefNotFound(_))));
+
+        client.delete_repo(repo_id).await.unwrap();
+        sqlx::query("DELETE FROM object WHERE sha256 = $1").bind(&stored).execute(client.pool()).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_find_merge_base_follows_both_parents() {
+        let client = test_index().await;
+        let (repo_id, root) = seed_repo(&client, "merge-base").await;
+        let add_commit = |parent: Uuid, merge_parent: Option<Uuid>| {
+            let pool = client.pool().clone();
+            async move {
+                let id = Uuid::new_v4();
+                sqlx::query("INSERT INTO commit (id, repo_id, parent_id, merge_parent_id, author) VALUES ($1, $2, $3, $4, 'test')")
+                    .bind(id)
+                    .bind(repo_id)
+                    .bind(parent)
+                    .bind(merge_parent)
+                    .execute(&pool)
+                    .await
+                    .unwrap();
+                id
+            }
+        };
+
+        // root - main1 ------ merge - main2
+        //    \- feature1 -/        \- feature2
+        let main1 = add_commit(root, None).await;
+        let feature1 = add_commit(root, None).await;
+        assert_eq!(client.find_merge_base(main1, feature1).await.unwrap(), Some(root));
+
+        let merge = add_commit(main1, Some(feature1)).await;
+        let main2 = add_commit(merge, None).await;
+        let feature2 = add_commit(feature1, None).await;
+        assert_eq!(client.find_merge_base(main2, feature2).await.unwrap(), Some(feature1));
+        assert_eq!(client.find_merge_base(main2, main1).await.unwrap(), Some(main1));
+
+        let (other_repo, unrelated) = seed_repo(&client, "merge-base-other").await;
+        assert_eq!(client.find_merge_base(main2, unrelated).await.unwrap(), None);
+
+        client.delete_repo(repo_id).await.unwrap();
+        client.delete_repo(other_repo).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_set_repo_feature_validates_key_and_type() {
+        let client = test_index().await;
+        let (repo_id, _) = seed_repo(&client, "features").await;
+
+        let (written, _) = client.set_repo_feature(repo_id, "auto_rdf", &json!(true), None).await.unwrap();
+        assert_eq!(written.0, json!({"auto_rdf": true}));
+        let unknown = client.set_repo_feature(repo_id, "auto_rdff", &json!(true), None).await;
+        let mistyped = client.set_repo_feature(repo_id, "thumbnails_enabled", &json!("no"), None).await;
+
+        assert!(matches!(unknown, Err(IndexError::InvalidFeature(FeatureError::UnknownKey(_)))));
+        assert!(matches!(mistyped, Err(IndexError::InvalidFeature(FeatureError::TypeMismatch { .. }))));
+        let features = client.get_repo_features(repo_id).await.unwrap();
+        assert!(features.auto_rdf());
+        assert!(features.thumbnails_enabled());
+        assert_eq!(features.0, json!({"auto_rdf": true}));
+
+        client.delete_repo(repo_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_stale_feature_write_is_rejected() {
+        let client = test_index().await;
+        let (repo_id, _) = seed_repo(&client, "features-version").await;
+
+        // Two writers read the same version, then both try to write
+        let (_, read_version) = client.get_repo_features_versioned(repo_id).await.unwrap();
+        let (first, second) = tokio::join!(
+            client.set_repo_feature(repo_id, "auto_rdf", &json!(true), Some(read_version)),
+            client.set_repo_feature(repo_id, "sampling_enabled", &json!(false), Some(read_version)),
+        );
+
+        let (won, lost) = match (first, second) {
+            (Ok((_, version)), Err(e)) | (Err(e), Ok((_, version))) => (version, e),
+            other => panic!("expected exactly one write to land, got {:?}", other),
+        };
+        assert_eq!(won, read_version + 1);
+        assert!(matches!(
+            lost,
+            IndexError::FeaturesVersionMismatch { expected, actual } if expected == read_version && actual == won
+        ));
+
+        // The loser re-reads and retries against the new version
+        let (features, version) = client.get_repo_features_versioned(repo_id).await.unwrap();
+        assert_eq!(version, won);
+        assert_eq!(features.0.as_object().unwrap().len(), 1);
+        let written = client.set_repo_feature(repo_id, "thumbnails_enabled", &json!(false), Some(version)).await.unwrap();
+        assert_eq!(written, client.get_repo_features_versioned(repo_id).await.unwrap());
+        let (features, version) = written;
+        assert_eq!(version, won + 1);
+        assert!(!features.thumbnails_enabled());
+
+        client.delete_repo(repo_id).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_repo_connector_round_trips_and_rejects_unknown_source() {
+        let client = test_index().await;
+        let (repo_id, _) = seed_repo(&client, "virtual").await;
+        let source_id: Uuid = sqlx::query_scalar(
+            "INSERT INTO external_source (name, connector_type, config) VALUES ($1, 's3', $2) RETURNING id"
+        )
+        .bind(format!("virtual-{}", repo_id))
+        .bind(json!({"bucket": "open-data"}))
+        .fetch_one(client.pool())
+        .await
+        .unwrap();
+
+        assert!(client.get_repo_connector(repo_id).await.unwrap().is_none());
+        client.set_repo_connector(repo_id, Some(source_id)).await.unwrap();
+        let connector = client.get_repo_connector(repo_id).await.unwrap().unwrap();
+        assert_eq!(connector.connector_id, source_id);
+        assert_eq!(connector.connector_type, "s3");
+        assert_eq!(connector.config, json!({"bucket": "open-data"}));
+
+        let unknown = Uuid::new_v4();
+        let missing = client.set_repo_connector(repo_id, Some(unknown)).await;
+        assert!(matches!(missing, Err(IndexError::ConnectorNotFound(id)) if id == unknown));
+
+        client.set_repo_connector(repo_id, None).await.unwrap();
+        assert!(client.get_repo_connector(repo_id).await.unwrap().is_none());
+
+        client.delete_repo(repo_id).await.unwrap();
+        sqlx::query("DELETE FROM external_source WHERE id = $1").bind(source_id).execute(client.pool()).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn test_concurrent_webhook_claims_are_disjoint_and_stale_claims_are_recovered() {
+        let client = test_index().await;
+        let (repo_id, _) = seed_repo(&client, "webhook-claim").await;
+        let webhook = Webhook {
+            id: Uuid::new_v4(),
+            repo_id,
+            url: "http://127.0.0.1:9/hook".to_string(),
+            secret: "secret".to_string(),
+            events: vec![WebhookEvent::CommitCreated],
+            active: true,
+        };
+        client.create_webhook(&webhook).await.unwrap();
+
+        let mut due = HashSet::new();
+        for _ in 0..6 {
+            let delivery = WebhookDelivery {
+                id: Uuid::new_v4(),
+                webhook_id: webhook.id,
+                event_type: "commit_created".to_string(),
+                payload: json!({}),
+                response_status: None,
+                response_body: None,
+                attempts: 0,
+                max_attempts: 3,
+                next_retry_at: None,
+                delivered_at: None,
+            };
+            client.create_webhook_delivery(&delivery).await.unwrap();
+            due.insert(delivery.id);
+        }
+        let ours = |claimed: Vec<WebhookDelivery>| -> HashSet<Uuid> {
+            claimed.into_iter().filter(|d| d.webhook_id == webhook.id).map(|d| d.id).collect()
+        };
+
+        // Two workers polling at once never claim the same delivery
+        let stale_before = Utc::now() - chrono::Duration::minutes(10);
+        let (first, second) = tokio::join!(
+            client.claim_due_webhook_deliveries(1000, stale_before),
+            client.claim_due_webhook_deliveries(1000, stale_before),
+        );
+        let (first, second) = (ours(first.unwrap()), ours(second.unwrap()));
+        assert!(first.is_disjoint(&second), "{:?} and {:?} overlap", first, second);
+        assert_eq!(first.union(&second).copied().collect::<HashSet<_>>(), due);
+
+        // A live claim is left alone
+        assert!(ours(client.claim_due_webhook_deliveries(1000, stale_before).await.unwrap()).is_empty());
+
+        // A claim whose worker died before recording an outcome is taken over
+        let abandoned = *due.iter().next().unwrap();
+        sqlx::query("UPDATE webhook_deliveries SET claimed_at = NOW() - INTERVAL '1 hour' WHERE id = $1")
+            .bind(abandoned)
+            .execute(client.pool())
+            .await
+            .unwrap();
+        let reclaimed = ours(client.claim_due_webhook_deliveries(1000, stale_before).await.unwrap());
+        assert_eq!(reclaimed, HashSet::from([abandoned]));
+
+        client.delete_webhook(webhook.id).await.unwrap();
+        client.delete_repo(repo_id).await.unwrap();
+    }
+}
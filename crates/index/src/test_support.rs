@@ -0,0 +1,15 @@
+//! Helpers for tests that run against a real Postgres.
+//!
+//! Such tests are marked `#[ignore = "needs TEST_DATABASE_URL"]`; run them
+//! with `TEST_DATABASE_URL=postgres://... cargo test -- --ignored` against a
+//! database `scripts/run-migrations.sh` has migrated.
+
+use crate::IndexClient;
+use sqlx::PgPool;
+
+/// Connect to the migrated database named by `TEST_DATABASE_URL`
+pub async fn test_index() -> IndexClient {
+    let url = std::env::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL names a migrated test database");
+    let pool = PgPool::connect(&url).await.expect("connect to TEST_DATABASE_URL");
+    IndexClient::new(pool)
+}
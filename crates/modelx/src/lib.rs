@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -32,51 +33,112 @@ pub struct TorchInfo {
     pub has_model: bool,
 }
 
+/// GGUF (llama.cpp) model information extracted from the file header
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GgufInfo {
+    pub version: u32,
+    pub tensor_count: u64,
+    pub architecture: Option<String>,
+    pub name: Option<String>,
+    /// Raw `general.file_type` value
+    pub file_type: Option<u32>,
+    /// Quantization scheme derived from `file_type`, e.g. `Q4_K_M`
+    pub quantization: Option<String>,
+    /// `<architecture>.context_length`
+    pub context_length: Option<u64>,
+    pub metadata: HashMap<String, GgufValue>,
+}
+
+/// A typed value from the GGUF key/value metadata block
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum GgufValue {
+    UInt(u64),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    String(String),
+    Array(Vec<GgufValue>),
+}
+
+impl GgufValue {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            GgufValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            GgufValue::UInt(v) => Some(*v),
+            GgufValue::Int(v) => u64::try_from(*v).ok(),
+            _ => None,
+        }
+    }
+}
+
+/// Subset of the ONNX `ModelProto` message needed for sniffing.
+///
+/// Field tags follow `onnx.proto`; the graph and other fields we don't read
+/// are skipped by the decoder.
+#[derive(Clone, PartialEq, prost::Message)]
+struct ModelProto {
+    #[prost(int64, tag = "1")]
+    ir_version: i64,
+    #[prost(string, tag = "2")]
+    producer_name: String,
+    #[prost(string, tag = "3")]
+    producer_version: String,
+    #[prost(string, tag = "4")]
+    domain: String,
+    #[prost(int64, tag = "5")]
+    model_version: i64,
+    #[prost(message, repeated, tag = "8")]
+    opset_import: Vec<OperatorSetIdProto>,
+}
+
+/// ONNX `OperatorSetIdProto`
+#[derive(Clone, PartialEq, prost::Message)]
+struct OperatorSetIdProto {
+    #[prost(string, tag = "1")]
+    domain: String,
+    #[prost(int64, tag = "2")]
+    version: i64,
+}
+
 /// Sniff ONNX model metadata from bytes
 pub fn sniff_onnx(bytes: &[u8]) -> Result<Option<OnnxInfo>> {
     if bytes.len() < 8 {
         return Ok(None);
     }
 
-    // Check for ONNX magic bytes (protobuf format)
-    // ONNX files typically start with a protobuf header
-    // This is a simplified check - real ONNX parsing would require protobuf parsing
-    
-    // Look for common ONNX patterns in the first few KB
-    let header = &bytes[..std::cmp::min(4096, bytes.len())];
-    
-    // Check if this looks like a protobuf file (very basic check)
-    if !contains_protobuf_indicators(header) {
-        return Ok(None);
-    }
-
-    // Try to extract basic information from the protobuf structure
-    // This is a simplified implementation - a real implementation would use
-    // proper protobuf parsing libraries
-    
-    let mut info = OnnxInfo {
-        opset_version: None,
-        producer_name: None,
-        producer_version: None,
-        model_version: None,
-        ir_version: None,
+    // Anything that doesn't decode as a ModelProto isn't an ONNX model
+    let model = match <ModelProto as prost::Message>::decode(bytes) {
+        Ok(model) => model,
+        Err(_) => return Ok(None),
     };
 
-    // Look for common ONNX field patterns in the binary data
-    // This is a very basic heuristic approach
-    if let Some(opset) = extract_uint64_field(header, "opset_version") {
-        info.opset_version = Some(opset);
-    }
-    
-    if let Some(producer) = extract_string_field(header, "producer_name") {
-        info.producer_name = Some(producer);
-    }
-    
-    if let Some(version) = extract_string_field(header, "producer_version") {
-        info.producer_version = Some(version);
+    // Every ONNX model declares an IR version; arbitrary protobuf (or data
+    // that happens to decode as one) won't
+    if model.ir_version <= 0 {
+        return Ok(None);
     }
 
-    Ok(Some(info))
+    // The default operator set has an empty or "ai.onnx" domain
+    let opset_version = model
+        .opset_import
+        .iter()
+        .find(|opset| opset.domain.is_empty() || opset.domain == "ai.onnx")
+        .and_then(|opset| u64::try_from(opset.version).ok());
+
+    Ok(Some(OnnxInfo {
+        opset_version,
+        producer_name: non_empty(model.producer_name),
+        producer_version: non_empty(model.producer_version),
+        model_version: u64::try_from(model.model_version).ok().filter(|v| *v > 0),
+        ir_version: u64::try_from(model.ir_version).ok(),
+    }))
 }
 
 /// Sniff PyTorch model metadata from bytes
@@ -111,55 +173,271 @@ pub fn sniff_torch(bytes: &[u8]) -> Result<Option<TorchInfo>> {
     Ok(Some(info))
 }
 
-/// Check if bytes contain protobuf indicators
-fn contains_protobuf_indicators(bytes: &[u8]) -> bool {
-    // Very basic check for protobuf-like structure
-    // Look for common protobuf field markers
-    bytes.iter().any(|&b| b == 0x08 || b == 0x12 || b == 0x1a)
+const GGUF_MAGIC: &[u8; 4] = b"GGUF";
+
+/// Sniff GGUF model metadata from bytes.
+///
+/// Reads the header and the full key/value metadata block, so `bytes` must
+/// extend past the metadata (tensor data is not needed). Returns `Ok(None)`
+/// for non-GGUF input or a header truncated mid-metadata.
+pub fn sniff_gguf(bytes: &[u8]) -> Result<Option<GgufInfo>> {
+    if bytes.len() < 8 || &bytes[..4] != GGUF_MAGIC {
+        return Ok(None);
+    }
+
+    // v3 allows big-endian files; the version word tells us which we have
+    let (version, big_endian) = match (
+        u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]),
+        u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]),
+    ) {
+        (v @ (2 | 3), _) => (v, false),
+        (_, 3) => (3, true),
+        (v, _) => return Err(ModelxError::UnsupportedVersion(format!("GGUF version {}", v))),
+    };
+
+    let mut reader = GgufReader {
+        bytes,
+        pos: 8,
+        big_endian,
+    };
+
+    match reader.read_header(version) {
+        Some(info) => Ok(Some(info)),
+        None => Ok(None),
+    }
 }
 
-/// Extract a uint64 field from protobuf-like data (simplified)
-fn extract_uint64_field(bytes: &[u8], _field_name: &str) -> Option<u64> {
-    // This is a very simplified implementation
-    // A real implementation would properly parse protobuf
-    
-    // Look for varint-encoded numbers (basic pattern)
-    for i in 0..bytes.len().saturating_sub(8) {
-        if bytes[i] == 0x08 { // Field tag for opset_version (simplified)
-            if let Some(value) = parse_varint(&bytes[i+1..]) {
-                if value > 0 && value < 100 { // Reasonable opset version range
-                    return Some(value);
-                }
-            }
+/// Cursor over a GGUF buffer; every read returns `None` on truncation
+struct GgufReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    big_endian: bool,
+}
+
+impl<'a> GgufReader<'a> {
+    fn read_header(&mut self, version: u32) -> Option<GgufInfo> {
+        let tensor_count = self.u64()?;
+        let kv_count = self.u64()?;
+
+        let mut metadata = HashMap::new();
+        for _ in 0..kv_count {
+            let key = self.string()?;
+            let value_type = self.u32()?;
+            let value = self.value(value_type)?;
+            metadata.insert(key, value);
         }
+
+        let architecture = metadata
+            .get("general.architecture")
+            .and_then(GgufValue::as_str)
+            .map(str::to_string);
+        let name = metadata
+            .get("general.name")
+            .and_then(GgufValue::as_str)
+            .map(str::to_string);
+        let file_type = metadata
+            .get("general.file_type")
+            .and_then(GgufValue::as_u64)
+            .and_then(|v| u32::try_from(v).ok());
+        let context_length = architecture
+            .as_ref()
+            .and_then(|arch| metadata.get(&format!("{}.context_length", arch)))
+            .and_then(GgufValue::as_u64);
+
+        Some(GgufInfo {
+            version,
+            tensor_count,
+            architecture,
+            name,
+            file_type,
+            quantization: file_type.and_then(gguf_quantization_name).map(str::to_string),
+            context_length,
+            metadata,
+        })
     }
-    None
-}
 
-/// Extract a string field from protobuf-like data (simplified)
-fn extract_string_field(bytes: &[u8], _field_name: &str) -> Option<String> {
-    // This is a very simplified implementation
-    // A real implementation would properly parse protobuf
-    
-    // Look for string patterns (basic heuristic)
-    for i in 0..bytes.len().saturating_sub(4) {
-        if bytes[i] == 0x12 { // Field tag for strings (simplified)
-            if let Some(len) = parse_varint(&bytes[i+1..]) {
-                if len > 0 && len < 100 { // Reasonable string length
-                    let start = i + 1 + varint_len(&bytes[i+1..]);
-                    let end = start + len as usize;
-                    if end <= bytes.len() {
-                        if let Ok(s) = String::from_utf8(bytes[start..end].to_vec()) {
-                            if s.chars().all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-') {
-                                return Some(s);
-                            }
-                        }
-                    }
+    fn value(&mut self, value_type: u32) -> Option<GgufValue> {
+        Some(match value_type {
+            0 => GgufValue::UInt(self.take::<1>()?[0] as u64),
+            1 => GgufValue::Int(self.take::<1>()?[0] as i8 as i64),
+            2 => GgufValue::UInt(self.u16()? as u64),
+            3 => GgufValue::Int(self.u16()? as i16 as i64),
+            4 => GgufValue::UInt(self.u32()? as u64),
+            5 => GgufValue::Int(self.u32()? as i32 as i64),
+            6 => GgufValue::Float(f32::from_bits(self.u32()?) as f64),
+            7 => GgufValue::Bool(self.take::<1>()?[0] != 0),
+            8 => GgufValue::String(self.string()?),
+            9 => {
+                let item_type = self.u32()?;
+                let len = self.u64()?;
+                // Every item is at least one byte, so a longer claimed length
+                // means the buffer is truncated (or the length is garbage)
+                if len > (self.bytes.len() - self.pos) as u64 {
+                    return None;
+                }
+                let mut items = Vec::with_capacity(len as usize);
+                for _ in 0..len {
+                    items.push(self.value(item_type)?);
                 }
+                GgufValue::Array(items)
             }
+            10 => GgufValue::UInt(self.u64()?),
+            11 => GgufValue::Int(self.u64()? as i64),
+            12 => GgufValue::Float(f64::from_bits(self.u64()?)),
+            _ => return None,
+        })
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let len = usize::try_from(self.u64()?).ok()?;
+        let end = self.pos.checked_add(len)?;
+        let raw = self.bytes.get(self.pos..end)?;
+        self.pos = end;
+        Some(String::from_utf8_lossy(raw).into_owned())
+    }
+
+    fn take<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let raw = self.bytes.get(self.pos..self.pos + N)?;
+        self.pos += N;
+        raw.try_into().ok()
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        let raw = self.take::<2>()?;
+        Some(if self.big_endian { u16::from_be_bytes(raw) } else { u16::from_le_bytes(raw) })
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        let raw = self.take::<4>()?;
+        Some(if self.big_endian { u32::from_be_bytes(raw) } else { u32::from_le_bytes(raw) })
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        let raw = self.take::<8>()?;
+        Some(if self.big_endian { u64::from_be_bytes(raw) } else { u64::from_le_bytes(raw) })
+    }
+}
+
+/// Name of the llama.cpp `general.file_type` quantization scheme
+fn gguf_quantization_name(file_type: u32) -> Option<&'static str> {
+    Some(match file_type {
+        0 => "F32",
+        1 => "F16",
+        2 => "Q4_0",
+        3 => "Q4_1",
+        7 => "Q8_0",
+        8 => "Q5_0",
+        9 => "Q5_1",
+        10 => "Q2_K",
+        11 => "Q3_K_S",
+        12 => "Q3_K_M",
+        13 => "Q3_K_L",
+        14 => "Q4_K_S",
+        15 => "Q4_K_M",
+        16 => "Q5_K_S",
+        17 => "Q5_K_M",
+        18 => "Q6_K",
+        19 => "IQ2_XXS",
+        20 => "IQ2_XS",
+        21 => "Q2_K_S",
+        22 => "IQ3_XS",
+        23 => "IQ3_XXS",
+        24 => "IQ1_S",
+        25 => "IQ4_NL",
+        26 => "IQ3_S",
+        27 => "IQ3_M",
+        28 => "IQ2_S",
+        29 => "IQ2_M",
+        30 => "IQ4_XS",
+        31 => "IQ1_M",
+        32 => "BF16",
+        _ => return None,
+    })
+}
+
+/// Model file formats recognisable from a file's leading bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModelFormat {
+    Onnx,
+    Torch,
+    Safetensors,
+    Gguf,
+}
+
+impl ModelFormat {
+    /// Media type recorded for objects in this format
+    pub fn media_type(&self) -> &'static str {
+        match self {
+            ModelFormat::Onnx => "application/x-onnx",
+            ModelFormat::Torch => "application/x-pytorch",
+            ModelFormat::Safetensors => "application/x-safetensors",
+            ModelFormat::Gguf => "application/x-gguf",
         }
     }
-    None
+}
+
+/// Largest safetensors JSON header accepted (the reference implementation's limit)
+const MAX_SAFETENSORS_HEADER: u64 = 100 * 1024 * 1024;
+
+/// Identify a model format from the start of a file.
+///
+/// Only signatures that hold for a prefix are used: GGUF magic, a
+/// safetensors header length followed by its JSON object, and a ZIP whose
+/// first member is a PyTorch archive's `data.pkl`. ONNX has no magic, so it
+/// is recognised only when `bytes` holds the whole model.
+pub fn detect_model_format(bytes: &[u8]) -> Option<ModelFormat> {
+    if bytes.len() >= 4 && &bytes[..4] == GGUF_MAGIC {
+        return Some(ModelFormat::Gguf);
+    }
+    if is_safetensors(bytes) {
+        return Some(ModelFormat::Safetensors);
+    }
+    if is_torch_archive(bytes) {
+        return Some(ModelFormat::Torch);
+    }
+    match sniff_onnx(bytes) {
+        Ok(Some(_)) => Some(ModelFormat::Onnx),
+        _ => None,
+    }
+}
+
+/// A little-endian u64 header length, then a JSON object of that length
+fn is_safetensors(bytes: &[u8]) -> bool {
+    if bytes.len() < 10 {
+        return false;
+    }
+    let header_len = u64::from_le_bytes(bytes[..8].try_into().expect("eight bytes"));
+    if !(2..=MAX_SAFETENSORS_HEADER).contains(&header_len) || &bytes[8..10] != b"{\"" {
+        return false;
+    }
+
+    // Parse the header when the prefix holds all of it
+    match bytes.get(8..8 + header_len as usize) {
+        Some(header) => serde_json::from_slice::<serde_json::Map<String, serde_json::Value>>(header).is_ok(),
+        None => true,
+    }
+}
+
+/// `torch.save` writes a ZIP whose first member is `<archive>/data.pkl`
+fn is_torch_archive(bytes: &[u8]) -> bool {
+    if bytes.len() < 30 || bytes[0..4] != [0x50, 0x4B, 0x03, 0x04] {
+        return false;
+    }
+    let name_len = u16::from_le_bytes([bytes[26], bytes[27]]) as usize;
+    bytes
+        .get(30..30 + name_len)
+        .map(|name| name.ends_with(b"/data.pkl"))
+        .unwrap_or(false)
+}
+
+/// Map protobuf's empty-string default to `None`
+fn non_empty(value: String) -> Option<String> {
+    if value.is_empty() {
+        None
+    } else {
+        Some(value)
+    }
 }
 
 /// Check if bytes represent a PyTorch file
@@ -205,38 +483,6 @@ fn contains_torch_model(bytes: &[u8]) -> bool {
     text.contains("model") || text.contains("Model") || text.contains("torch")
 }
 
-/// Parse a varint from bytes
-fn parse_varint(bytes: &[u8]) -> Option<u64> {
-    if bytes.is_empty() {
-        return None;
-    }
-
-    let mut result = 0u64;
-    let mut shift = 0;
-    
-    for &byte in bytes.iter().take(9) { // Max 9 bytes for u64 varint
-        result |= ((byte & 0x7F) as u64) << shift;
-        
-        if (byte & 0x80) == 0 {
-            return Some(result);
-        }
-        
-        shift += 7;
-    }
-    
-    None
-}
-
-/// Get the length of a varint
-fn varint_len(bytes: &[u8]) -> usize {
-    for (i, &byte) in bytes.iter().enumerate() {
-        if (byte & 0x80) == 0 {
-            return i + 1;
-        }
-    }
-    bytes.len()
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -268,17 +514,126 @@ mod tests {
     }
 
     #[test]
-    fn test_parse_varint() {
-        assert_eq!(parse_varint(&[0x01]), Some(1));
-        assert_eq!(parse_varint(&[0x7F]), Some(127));
-        assert_eq!(parse_varint(&[0x80, 0x01]), Some(128));
-        assert_eq!(parse_varint(&[]), None);
+    fn test_sniff_onnx_fixture() {
+        let bytes = include_bytes!("../tests/fixtures/identity.onnx");
+        let info = sniff_onnx(bytes).unwrap().expect("fixture should parse as ONNX");
+
+        assert_eq!(info.ir_version, Some(8));
+        assert_eq!(info.opset_version, Some(17));
+        assert_eq!(info.producer_name.as_deref(), Some("pytorch"));
+        assert_eq!(info.producer_version.as_deref(), Some("2.1.0"));
+        assert_eq!(info.model_version, Some(3));
+    }
+
+    #[test]
+    fn test_sniff_onnx_truncated() {
+        let bytes = include_bytes!("../tests/fixtures/identity.onnx");
+        let result = sniff_onnx(&bytes[..bytes.len() / 2]);
+        assert!(result.unwrap().is_none());
     }
 
+    const GGUF_FIXTURE: &[u8] = include_bytes!("../tests/fixtures/tinyllama-header.gguf");
+
     #[test]
-    fn test_varint_len() {
-        assert_eq!(varint_len(&[0x01]), 1);
-        assert_eq!(varint_len(&[0x80, 0x01]), 2);
-        assert_eq!(varint_len(&[]), 0);
+    fn test_sniff_gguf_fixture() {
+        let info = sniff_gguf(GGUF_FIXTURE).unwrap().expect("fixture should parse as GGUF");
+
+        assert_eq!(info.version, 3);
+        assert_eq!(info.tensor_count, 201);
+        assert_eq!(info.architecture.as_deref(), Some("llama"));
+        assert_eq!(info.name.as_deref(), Some("TinyLlama 1.1B"));
+        assert_eq!(info.file_type, Some(15));
+        assert_eq!(info.quantization.as_deref(), Some("Q4_K_M"));
+        assert_eq!(info.context_length, Some(2048));
+        assert_eq!(
+            info.metadata.get("llama.rope.freq_base"),
+            Some(&GgufValue::Float(10000.0))
+        );
+        assert_eq!(
+            info.metadata.get("tokenizer.ggml.tokens"),
+            Some(&GgufValue::Array(vec![
+                GgufValue::String("<unk>".to_string()),
+                GgufValue::String("<s>".to_string()),
+                GgufValue::String("</s>".to_string()),
+            ]))
+        );
+        assert_eq!(
+            info.metadata.get("tokenizer.ggml.token_type"),
+            Some(&GgufValue::Array(vec![
+                GgufValue::Int(2),
+                GgufValue::Int(3),
+                GgufValue::Int(3),
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_sniff_gguf_v2() {
+        let mut bytes = GGUF_FIXTURE.to_vec();
+        bytes[4..8].copy_from_slice(&2u32.to_le_bytes());
+
+        let info = sniff_gguf(&bytes).unwrap().unwrap();
+        assert_eq!(info.version, 2);
+        assert_eq!(info.architecture.as_deref(), Some("llama"));
+    }
+
+    #[test]
+    fn test_sniff_gguf_truncated() {
+        for len in [0, 7, 12, 24, GGUF_FIXTURE.len() - 1] {
+            assert!(sniff_gguf(&GGUF_FIXTURE[..len]).unwrap().is_none(), "len {}", len);
+        }
+    }
+
+    /// Local file header of a ZIP whose first member is `name`
+    fn zip_with_first_member(name: &str) -> Vec<u8> {
+        let mut bytes = vec![0x50, 0x4B, 0x03, 0x04];
+        bytes.extend_from_slice(&[0; 22]);
+        bytes.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(&[0, 0]);
+        bytes.extend_from_slice(name.as_bytes());
+        bytes
+    }
+
+    #[test]
+    fn test_detect_model_format() {
+        assert_eq!(detect_model_format(GGUF_FIXTURE), Some(ModelFormat::Gguf));
+        assert_eq!(detect_model_format(&GGUF_FIXTURE[..16]), Some(ModelFormat::Gguf));
+        assert_eq!(
+            detect_model_format(include_bytes!("../tests/fixtures/identity.onnx")),
+            Some(ModelFormat::Onnx)
+        );
+        assert_eq!(detect_model_format(&zip_with_first_member("model/data.pkl")), Some(ModelFormat::Torch));
+
+        let header = br#"{"weight":{"dtype":"F32","shape":[2],"data_offsets":[0,8]}}"#;
+        let mut safetensors = (header.len() as u64).to_le_bytes().to_vec();
+        safetensors.extend_from_slice(header);
+        safetensors.extend_from_slice(&[0; 8]);
+        assert_eq!(detect_model_format(&safetensors), Some(ModelFormat::Safetensors));
+        // A prefix that stops inside the header still counts
+        assert_eq!(detect_model_format(&safetensors[..20]), Some(ModelFormat::Safetensors));
+        assert_eq!(ModelFormat::Safetensors.media_type(), "application/x-safetensors");
+    }
+
+    #[test]
+    fn test_detect_model_format_rejects_lookalikes() {
+        // A plain ZIP is not a PyTorch archive
+        assert_eq!(detect_model_format(&zip_with_first_member("docs/readme.txt")), None);
+
+        // A length prefix followed by something other than a JSON object
+        let mut bogus = 12u64.to_le_bytes().to_vec();
+        bogus.extend_from_slice(b"not json!!!!");
+        assert_eq!(detect_model_format(&bogus), None);
+
+        assert_eq!(detect_model_format(b"day,temp\n1,12.5\n"), None);
+        assert_eq!(detect_model_format(&[]), None);
+    }
+
+    #[test]
+    fn test_sniff_gguf_rejects_other_formats() {
+        assert!(sniff_gguf(b"not a gguf file").unwrap().is_none());
+
+        let mut v1 = GGUF_FIXTURE.to_vec();
+        v1[4..8].copy_from_slice(&1u32.to_le_bytes());
+        assert!(matches!(sniff_gguf(&v1), Err(ModelxError::UnsupportedVersion(_))));
     }
 }
@@ -1,14 +1,22 @@
 use aws_sdk_s3::{
-    config::{Builder as ConfigBuilder, Credentials, Region},
+    config::{BehaviorVersion, Builder as ConfigBuilder, Credentials, Region},
+    error::SdkError,
     presigning::PresigningConfig,
+    primitives::ByteStream,
+    types::{ChecksumMode, ServerSideEncryption},
     Client as S3Client,
 };
+use base64::Engine;
+use futures_util::{stream, StreamExt};
+use std::collections::BTreeMap;
 use std::time::Duration;
 use thiserror::Error;
 use url::Url;
 use tokio::time::sleep;
 use rand::Rng;
 
+pub mod metrics;
+
 #[derive(Error, Debug)]
 pub enum StorageError {
     #[error("S3 operation failed: {0}")]
@@ -19,6 +27,38 @@ pub enum StorageError {
     ConfigError(String),
     #[error("AWS SDK error: {0}")]
     AwsSdkError(String),
+    #[error("Transient S3 error: {0}")]
+    Transient(String),
+    #[error("Requested range not satisfiable: {0}")]
+    RangeNotSatisfiable(String),
+}
+
+impl StorageError {
+    /// Whether the operation that produced this error is worth retrying
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, StorageError::Transient(_))
+    }
+}
+
+/// Map an SDK error to a `StorageError`, marking timeouts, dispatch failures,
+/// throttling and 5xx responses as transient.
+fn classify_sdk_error<E>(err: SdkError<E>) -> StorageError
+where
+    E: std::error::Error + 'static,
+{
+    let status = err.raw_response().map(|r| r.status().as_u16());
+    let transient = match &err {
+        SdkError::TimeoutError(_) | SdkError::DispatchFailure(_) | SdkError::ResponseError(_) => true,
+        SdkError::ServiceError(_) => matches!(status, Some(429) | Some(500..=599)),
+        _ => false,
+    };
+
+    let message = aws_sdk_s3::error::DisplayErrorContext(&err).to_string();
+    if transient {
+        StorageError::Transient(message)
+    } else {
+        StorageError::AwsSdkError(message)
+    }
 }
 
 impl From<aws_sdk_s3::Error> for StorageError {
@@ -35,10 +75,134 @@ impl From<aws_sdk_s3::error::BuildError> for StorageError {
 
 pub type Result<T> = std::result::Result<T, StorageError>;
 
+/// Smallest part size S3 accepts for every part except the last
+pub const MIN_MULTIPART_PART_SIZE: u64 = 5 * 1024 * 1024;
+
+/// Maximum number of parts in a single multipart upload
+pub const MAX_MULTIPART_PARTS: u64 = 10_000;
+
+/// Default size above which uploads switch to multipart (S3's single PUT limit)
+pub const DEFAULT_MULTIPART_THRESHOLD: u64 = 5 * 1024 * 1024 * 1024;
+
+/// Default multipart part size
+pub const DEFAULT_MULTIPART_PART_SIZE: u64 = 100 * 1024 * 1024;
+
+/// Lifetime of presigned URLs when neither the environment nor the repo sets one
+pub const DEFAULT_PRESIGN_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// SigV4 presigned URLs cannot outlive seven days
+pub const MAX_PRESIGN_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// A part that has been uploaded as part of a multipart upload
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UploadedPart {
+    pub part_number: i32,
+    pub etag: String,
+}
+
+/// Result of completing a multipart upload
+#[derive(Debug, Clone)]
+pub struct CompletedUpload {
+    /// ETag of the assembled object
+    pub etag: Option<String>,
+    /// Part ETags that were submitted, in part order
+    pub parts: Vec<UploadedPart>,
+}
+
+/// Object metadata returned by a HEAD request
+#[derive(Debug, Clone)]
+pub struct ObjectHead {
+    pub content_length: i64,
+    pub content_type: Option<String>,
+    pub etag: Option<String>,
+    /// Base64 sha256 S3 verified on upload; absent unless the PUT carried one.
+    /// Multipart uploads report a checksum of part checksums ending in `-N`.
+    pub checksum_sha256: Option<String>,
+    pub last_modified: Option<std::time::SystemTime>,
+    /// S3 omits the storage class for `STANDARD` objects
+    pub storage_class: Option<String>,
+    /// KMS key the object is encrypted with, if it uses SSE-KMS
+    pub kms_key_id: Option<String>,
+}
+
+/// Storage class S3 implies when a HEAD response names none
+pub const STANDARD_STORAGE_CLASS: &str = "STANDARD";
+
+/// How many HEAD requests `storage_class_usage` keeps in flight
+const STORAGE_CLASS_HEAD_CONCURRENCY: usize = 16;
+
+/// Objects and bytes in one storage class
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StorageClassUsage {
+    pub objects: u64,
+    pub bytes: u64,
+}
+
+/// Bytes per storage class across a set of keys
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StorageClassReport {
+    pub classes: BTreeMap<String, StorageClassUsage>,
+    /// Keys that no longer exist in the bucket
+    pub missing: u64,
+}
+
+/// A streamed object body, or the part of it selected by a `Range` request
+#[derive(Debug)]
+pub struct ObjectBody {
+    pub body: ByteStream,
+    /// Length of `body`, which is the range length for partial content
+    pub content_length: i64,
+    pub content_type: Option<String>,
+    /// `Content-Range` as returned by S3; set only for partial content
+    pub content_range: Option<String>,
+}
+
+/// Maximum number of keys S3 accepts in one DeleteObjects request
+pub const MAX_DELETE_BATCH: usize = 1000;
+
+/// An object, or one version of it on a versioned bucket
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectVersion {
+    pub key: String,
+    /// Without one, deleting on a versioned bucket only adds a delete marker
+    pub version_id: Option<String>,
+    /// When the version was written, if listed
+    pub last_modified: Option<std::time::SystemTime>,
+}
+
+impl From<String> for ObjectVersion {
+    fn from(key: String) -> Self {
+        Self { key, version_id: None, last_modified: None }
+    }
+}
+
+/// A key that S3 refused to delete in a batch delete
+#[derive(Debug, Clone)]
+pub struct DeleteFailure {
+    pub key: String,
+    pub version_id: Option<String>,
+    pub code: Option<String>,
+    pub message: Option<String>,
+}
+
+/// Per-object outcome of a batch delete
+#[derive(Debug, Clone, Default)]
+pub struct DeleteObjectsResult {
+    pub deleted: Vec<ObjectVersion>,
+    pub failed: Vec<DeleteFailure>,
+}
+
 /// S3 client wrapper with presigned URL generation
 pub struct StorageClient {
     client: S3Client,
     bucket: String,
+    multipart_threshold: u64,
+    multipart_part_size: u64,
+    /// Default KMS key for SSE-KMS writes; `None` leaves encryption to the bucket default
+    kms_key_id: Option<String>,
+    /// Default lifetimes of presigned upload and download URLs
+    upload_url_ttl: Duration,
+    download_url_ttl: Duration,
 }
 
 impl StorageClient {
@@ -56,8 +220,8 @@ impl StorageClient {
         let secret_key = std::env::var("S3_SECRET_KEY")
             .map_err(|_| StorageError::ConfigError("S3_SECRET_KEY not set".to_string()))?;
 
-        let endpoint = std::env::var("S3_ENDPOINT")
-            .map_err(|_| StorageError::ConfigError("S3_ENDPOINT not set".to_string()))?;
+        // Optional: unset or empty means default AWS endpoint resolution
+        let endpoint = parse_endpoint(&std::env::var("S3_ENDPOINT").unwrap_or_default())?;
 
         let force_path_style = std::env::var("S3_FORCE_PATH_STYLE")
             .unwrap_or_else(|_| "true".to_string())
@@ -66,17 +230,23 @@ impl StorageClient {
 
         let credentials = Credentials::new(&access_key, &secret_key, None, None, "env");
 
-        let config_builder = ConfigBuilder::default()
-            .region(Region::new(region))
-            .credentials_provider(credentials)
-            .force_path_style(force_path_style);
+        let config_builder = s3_config_builder(region, credentials, endpoint.as_ref(), force_path_style);
 
-        // Set custom endpoint if provided
-        if !endpoint.is_empty() {
-            let _endpoint_url = Url::parse(&endpoint)?;
-            // Note: Custom endpoint configuration would need to be handled differently
-            // For now, we'll skip custom endpoint configuration
-        }
+        let multipart_threshold = std::env::var("S3_MULTIPART_THRESHOLD")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_MULTIPART_THRESHOLD);
+
+        let multipart_part_size = std::env::var("S3_MULTIPART_PART_SIZE")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_MULTIPART_PART_SIZE)
+            .max(MIN_MULTIPART_PART_SIZE);
+
+        let kms_key_id = parse_kms_key_id(&std::env::var("S3_KMS_KEY_ID").unwrap_or_default());
+
+        let upload_url_ttl = parse_presign_ttl(&std::env::var("S3_UPLOAD_URL_TTL_SECONDS").unwrap_or_default())?;
+        let download_url_ttl = parse_presign_ttl(&std::env::var("S3_DOWNLOAD_URL_TTL_SECONDS").unwrap_or_default())?;
 
         let config = config_builder.build();
         let client = S3Client::from_conf(config);
@@ -84,34 +254,109 @@ impl StorageClient {
         // Ensure bucket exists (dev only)
         Self::ensure_bucket_exists(&client, &bucket).await?;
 
-        Ok(Self { client, bucket })
+        Ok(Self {
+            client,
+            bucket,
+            multipart_threshold,
+            multipart_part_size,
+            kms_key_id,
+            upload_url_ttl,
+            download_url_ttl,
+        })
+    }
+
+    /// Wrap an already configured S3 client, with the default multipart and
+    /// presign settings and no KMS key
+    pub fn from_client(client: S3Client, bucket: impl Into<String>) -> Self {
+        Self {
+            client,
+            bucket: bucket.into(),
+            multipart_threshold: DEFAULT_MULTIPART_THRESHOLD,
+            multipart_part_size: DEFAULT_MULTIPART_PART_SIZE,
+            kms_key_id: None,
+            upload_url_ttl: DEFAULT_PRESIGN_TTL,
+            download_url_ttl: DEFAULT_PRESIGN_TTL,
+        }
+    }
+
+    /// KMS key to encrypt a write with: the repository's own key when it has
+    /// one, otherwise the `S3_KMS_KEY_ID` default
+    pub fn kms_key_id<'a>(&'a self, repo_kms_key_id: Option<&'a str>) -> Option<&'a str> {
+        repo_kms_key_id
+            .map(str::trim)
+            .filter(|key| !key.is_empty())
+            .or(self.kms_key_id.as_deref())
+    }
+
+    /// Lifetime for presigned upload URLs: the repository's own setting in
+    /// seconds when it has one, otherwise `S3_UPLOAD_URL_TTL_SECONDS`
+    pub fn upload_url_ttl(&self, repo_ttl_seconds: Option<u64>) -> Result<Duration> {
+        Ok(repo_ttl_seconds.map(presign_ttl).transpose()?.unwrap_or(self.upload_url_ttl))
+    }
+
+    /// Lifetime for presigned download URLs: the repository's own setting in
+    /// seconds when it has one, otherwise `S3_DOWNLOAD_URL_TTL_SECONDS`
+    pub fn download_url_ttl(&self, repo_ttl_seconds: Option<u64>) -> Result<Duration> {
+        Ok(repo_ttl_seconds.map(presign_ttl).transpose()?.unwrap_or(self.download_url_ttl))
     }
 
-    /// Generate a presigned PUT URL for uploading content
+    /// Headers a client must send with a PUT presigned for `kms_key_id`
+    pub fn sse_kms_headers(kms_key_id: &str) -> Vec<(String, String)> {
+        vec![
+            ("x-amz-server-side-encryption".to_string(), ServerSideEncryption::AwsKms.as_str().to_string()),
+            ("x-amz-server-side-encryption-aws-kms-key-id".to_string(), kms_key_id.to_string()),
+        ]
+    }
+
+    /// Generate a presigned PUT URL for uploading content.
+    ///
+    /// With `checksum_sha256` (base64, see `checksum_sha256_base64`) the
+    /// `x-amz-checksum-sha256` header is signed into the URL: the uploader
+    /// must send it, S3 rejects a body that does not hash to it, and
+    /// `head_object` reports it afterwards.
+    ///
+    /// The object is written with SSE-KMS under `kms_key_id(repo_kms_key_id)`
+    /// when that resolves to a key. Those headers are signed the same way, so
+    /// the uploader must send `sse_kms_headers` for that key as well.
     pub async fn presign_put(
         &self,
         key: &str,
         size: u64,
         content_type: &str,
+        checksum_sha256: Option<&str>,
+        repo_kms_key_id: Option<&str>,
         expires: Duration,
     ) -> Result<Url> {
         let presigning_config = PresigningConfig::expires_in(expires)
             .map_err(|e| StorageError::ConfigError(format!("Invalid presigning config: {}", e)))?;
+        let kms_key_id = self.kms_key_id(repo_kms_key_id);
 
-        let request = self
-            .client
-            .put_object()
-            .bucket(&self.bucket)
-            .key(key)
-            .content_length(size as i64)
-            .content_type(content_type)
-            .presigned(presigning_config)
-            .await.map_err(|e| StorageError::AwsSdkError(e.to_string()))?;
+        self.retry_operation(|| async {
+            let request = self
+                .client
+                .put_object()
+                .bucket(&self.bucket)
+                .key(key)
+                .content_length(size as i64)
+                .content_type(content_type)
+                .set_checksum_sha256(checksum_sha256.map(|c| c.to_string()))
+                .set_server_side_encryption(kms_key_id.map(|_| ServerSideEncryption::AwsKms))
+                .set_ssekms_key_id(kms_key_id.map(|k| k.to_string()))
+                .presigned(presigning_config.clone())
+                .await
+                .map_err(classify_sdk_error)?;
 
-        Ok(Url::parse(&request.uri().to_string())?)
+            Ok(Url::parse(request.uri())?)
+        })
+        .await
     }
 
-    /// Retry operation with exponential backoff and jitter
+    /// Retry operation with exponential backoff and jitter.
+    ///
+    /// Only errors for which `StorageError::is_retryable` holds are retried.
+    /// Presigning is local signing, so for presign calls this mainly covers
+    /// credential-provider refreshes and endpoint resolution; requests that
+    /// actually hit S3 also retry on timeouts, throttling and 5xx responses.
     async fn retry_operation<F, Fut, T>(&self, operation: F) -> Result<T>
     where
         F: Fn() -> Fut,
@@ -127,7 +372,7 @@ impl StorageClient {
                 Ok(result) => return Ok(result),
                 Err(e) => {
                     attempt += 1;
-                    if attempt > MAX_RETRIES {
+                    if attempt > MAX_RETRIES || !e.is_retryable() {
                         return Err(e);
                     }
 
@@ -151,15 +396,569 @@ impl StorageClient {
         let presigning_config = PresigningConfig::expires_in(expires)
             .map_err(|e| StorageError::ConfigError(format!("Invalid presigning config: {}", e)))?;
 
-        let request = self
-            .client
-            .get_object()
-            .bucket(&self.bucket)
-            .key(key)
-            .presigned(presigning_config)
-            .await.map_err(|e| StorageError::AwsSdkError(e.to_string()))?;
+        self.retry_operation(|| async {
+            let request = self
+                .client
+                .get_object()
+                .bucket(&self.bucket)
+                .key(key)
+                .presigned(presigning_config.clone())
+                .await
+                .map_err(classify_sdk_error)?;
+
+            Ok(Url::parse(request.uri())?)
+        })
+        .await
+    }
+
+    /// Fetch object metadata without downloading it; `None` if the key does not exist
+    pub async fn head_object(&self, key: &str) -> Result<Option<ObjectHead>> {
+        metrics::observe("head_object", self.retry_operation(|| async {
+            match self
+                .client
+                .head_object()
+                .bucket(&self.bucket)
+                .key(key)
+                .checksum_mode(ChecksumMode::Enabled)
+                .send()
+                .await
+            {
+                Ok(output) => Ok(Some(ObjectHead {
+                    content_length: output.content_length().unwrap_or(0),
+                    content_type: output.content_type().map(|s| s.to_string()),
+                    etag: output.e_tag().map(|s| s.to_string()),
+                    checksum_sha256: output.checksum_sha256().map(|s| s.to_string()),
+                    last_modified: output
+                        .last_modified()
+                        .and_then(|dt| std::time::SystemTime::try_from(*dt).ok()),
+                    storage_class: output.storage_class().map(|class| class.as_str().to_string()),
+                    kms_key_id: output.ssekms_key_id().map(|s| s.to_string()),
+                })),
+                Err(e) if is_not_found(&e) => Ok(None),
+                Err(e) => Err(classify_sdk_error(e)),
+            }
+        }))
+        .await
+    }
+
+    /// Check the bucket is reachable with the configured credentials.
+    ///
+    /// Not retried, so readiness probes see failures immediately.
+    pub async fn head_bucket(&self) -> Result<()> {
+        metrics::observe("head_bucket", async {
+            self.client
+                .head_bucket()
+                .bucket(&self.bucket)
+                .send()
+                .await
+                .map(|_| ())
+                .map_err(classify_sdk_error)
+        })
+        .await
+    }
+
+    /// Write an object the server generated itself, such as an export
+    /// archive; client uploads go through `presign_put` instead
+    pub async fn put_object(&self, key: &str, body: Vec<u8>, content_type: &str) -> Result<()> {
+        metrics::observe("put_object", self.retry_operation(|| async {
+            self.client
+                .put_object()
+                .bucket(&self.bucket)
+                .key(key)
+                .content_type(content_type)
+                .body(ByteStream::from(body.clone()))
+                .send()
+                .await
+                .map(|_| ())
+                .map_err(classify_sdk_error)
+        }))
+        .await
+    }
+
+    /// Open the body of an object for streaming; `None` if the key does not exist
+    pub async fn get_object(&self, key: &str) -> Result<Option<ByteStream>> {
+        metrics::observe("get_object", self.retry_operation(|| async {
+            match self
+                .client
+                .get_object()
+                .bucket(&self.bucket)
+                .key(key)
+                .send()
+                .await
+            {
+                Ok(output) => Ok(Some(output.body)),
+                Err(e) if is_not_found(&e) => Ok(None),
+                Err(e) => Err(classify_sdk_error(e)),
+            }
+        }))
+        .await
+    }
+
+    /// Open an object for streaming, forwarding an HTTP `Range` header value
+    /// such as `bytes=0-1023`; `None` if the key does not exist
+    pub async fn get_object_range(&self, key: &str, range: Option<&str>) -> Result<Option<ObjectBody>> {
+        metrics::observe("get_object", self.retry_operation(|| async {
+            match self
+                .client
+                .get_object()
+                .bucket(&self.bucket)
+                .key(key)
+                .set_range(range.map(str::to_string))
+                .send()
+                .await
+            {
+                Ok(output) => Ok(Some(ObjectBody {
+                    content_length: output.content_length().unwrap_or(0),
+                    content_type: output.content_type().map(|s| s.to_string()),
+                    content_range: output.content_range().map(|s| s.to_string()),
+                    body: output.body,
+                })),
+                Err(e) if is_not_found(&e) => Ok(None),
+                Err(e) if e.raw_response().is_some_and(|r| r.status().as_u16() == 416) => {
+                    Err(StorageError::RangeNotSatisfiable(range.unwrap_or_default().to_string()))
+                }
+                Err(e) => Err(classify_sdk_error(e)),
+            }
+        }))
+        .await
+    }
+
+    /// Whether an object exists at `key`
+    pub async fn object_exists(&self, key: &str) -> Result<bool> {
+        Ok(self.head_object(key).await?.is_some())
+    }
+
+    /// Whether any object's key starts with `prefix`
+    pub async fn prefix_exists(&self, prefix: &str) -> Result<bool> {
+        metrics::observe("list_objects", self.retry_operation(|| async {
+            self.client
+                .list_objects_v2()
+                .bucket(&self.bucket)
+                .prefix(prefix)
+                .max_keys(1)
+                .send()
+                .await
+                .map(|output| !output.contents().is_empty())
+                .map_err(classify_sdk_error)
+        }))
+        .await
+    }
+
+    /// HEAD every key and total objects and bytes per storage class.
+    ///
+    /// One request per key, so callers should cache the result.
+    pub async fn storage_class_usage(&self, keys: &[String]) -> Result<StorageClassReport> {
+        let mut heads = stream::iter(keys)
+            .map(|key| self.head_object(key))
+            .buffer_unordered(STORAGE_CLASS_HEAD_CONCURRENCY);
+
+        let mut report = StorageClassReport::default();
+        while let Some(head) = heads.next().await {
+            let Some(head) = head? else {
+                report.missing += 1;
+                continue;
+            };
+            let class = head.storage_class.unwrap_or_else(|| STANDARD_STORAGE_CLASS.to_string());
+            let usage = report.classes.entry(class).or_default();
+            usage.objects += 1;
+            usage.bytes += head.content_length.max(0) as u64;
+        }
+        Ok(report)
+    }
+
+    /// Delete an object. On versioned buckets, passing a `version_id` removes that
+    /// version permanently; without one S3 only adds a delete marker.
+    pub async fn delete_object(&self, key: &str, version_id: Option<&str>) -> Result<()> {
+        metrics::observe("delete_object", self.retry_operation(|| async {
+            self.client
+                .delete_object()
+                .bucket(&self.bucket)
+                .key(key)
+                .set_version_id(version_id.map(|v| v.to_string()))
+                .send()
+                .await
+                .map_err(classify_sdk_error)?;
+            Ok(())
+        }))
+        .await
+    }
+
+    /// Every version of `key`, delete markers included. An unversioned
+    /// bucket reports its single copy under the version id `null`.
+    pub async fn list_object_versions(&self, key: &str) -> Result<Vec<ObjectVersion>> {
+        let mut versions = Vec::new();
+        let mut markers: (Option<String>, Option<String>) = (None, None);
+
+        loop {
+            let output = metrics::observe("list_object_versions", async {
+                self.client
+                    .list_object_versions()
+                    .bucket(&self.bucket)
+                    .prefix(key)
+                    .set_key_marker(markers.0.clone())
+                    .set_version_id_marker(markers.1.clone())
+                    .send()
+                    .await
+                    .map_err(classify_sdk_error)
+            })
+            .await?;
+
+            // The prefix also matches longer keys
+            let found = output
+                .versions()
+                .iter()
+                .map(|v| (v.key(), v.version_id(), v.last_modified()))
+                .chain(output.delete_markers().iter().map(|m| (m.key(), m.version_id(), m.last_modified())))
+                .filter(|(found_key, _, _)| *found_key == Some(key))
+                .map(|(_, version_id, last_modified)| ObjectVersion {
+                    key: key.to_string(),
+                    version_id: version_id.map(|v| v.to_string()),
+                    last_modified: last_modified.and_then(|dt| std::time::SystemTime::try_from(*dt).ok()),
+                });
+            versions.extend(found);
+
+            if !output.is_truncated().unwrap_or(false) {
+                break;
+            }
+            markers = (
+                output.next_key_marker().map(|m| m.to_string()),
+                output.next_version_id_marker().map(|m| m.to_string()),
+            );
+            if markers.0.is_none() {
+                break;
+            }
+        }
+
+        Ok(versions)
+    }
+
+    /// Delete many objects using batch DeleteObjects requests of up to 1000 keys.
+    ///
+    /// Objects naming a version have that version removed permanently;
+    /// the others only get a delete marker on a versioned bucket. Failures
+    /// are reported per object rather than failing the whole call, so
+    /// callers such as GC jobs can record exactly what was removed. A chunk
+    /// whose request fails outright marks all of its objects as failed.
+    pub async fn delete_objects(&self, objects: &[ObjectVersion]) -> Result<DeleteObjectsResult> {
+        let mut result = DeleteObjectsResult::default();
+
+        for chunk in objects.chunks(MAX_DELETE_BATCH) {
+            let identifiers = chunk
+                .iter()
+                .map(|object| {
+                    aws_sdk_s3::types::ObjectIdentifier::builder()
+                        .key(&object.key)
+                        .set_version_id(object.version_id.clone())
+                        .build()
+                })
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            let delete = aws_sdk_s3::types::Delete::builder()
+                .set_objects(Some(identifiers))
+                .quiet(true)
+                .build()?;
+
+            let response = metrics::observe("delete_objects", self.retry_operation(|| async {
+                self.client
+                    .delete_objects()
+                    .bucket(&self.bucket)
+                    .delete(delete.clone())
+                    .send()
+                    .await
+                    .map_err(classify_sdk_error)
+            }))
+            .await;
+
+            match response {
+                Ok(output) => {
+                    // Quiet mode only reports errors; everything else was deleted
+                    let failed: Vec<DeleteFailure> = output
+                        .errors()
+                        .iter()
+                        .map(|e| DeleteFailure {
+                            key: e.key().unwrap_or_default().to_string(),
+                            version_id: e.version_id().map(|v| v.to_string()),
+                            code: e.code().map(|c| c.to_string()),
+                            message: e.message().map(|m| m.to_string()),
+                        })
+                        .collect();
+
+                    result.deleted.extend(
+                        chunk
+                            .iter()
+                            .filter(|object| {
+                                !failed.iter().any(|f| f.key == object.key && f.version_id == object.version_id)
+                            })
+                            .cloned(),
+                    );
+                    result.failed.extend(failed);
+                }
+                Err(e) => {
+                    tracing::warn!("Batch delete of {} objects failed: {}", chunk.len(), e);
+                    result.failed.extend(chunk.iter().map(|object| DeleteFailure {
+                        key: object.key.clone(),
+                        version_id: object.version_id.clone(),
+                        code: None,
+                        message: Some(e.to_string()),
+                    }));
+                }
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Whether an upload of `size` bytes should use multipart upload
+    pub fn requires_multipart(&self, size: u64) -> bool {
+        size > self.multipart_threshold
+    }
+
+    /// Part size to use for a multipart upload of `size` bytes
+    pub fn multipart_part_size(&self, size: u64) -> u64 {
+        plan_part_size(size, self.multipart_part_size)
+    }
+
+    /// Start a multipart upload and return its upload ID.
+    ///
+    /// Encryption is fixed when the upload is created, so part uploads need no
+    /// SSE headers.
+    pub async fn create_multipart_upload(
+        &self,
+        key: &str,
+        content_type: &str,
+        repo_kms_key_id: Option<&str>,
+    ) -> Result<String> {
+        let kms_key_id = self.kms_key_id(repo_kms_key_id);
+        let output = metrics::observe("create_multipart_upload", async {
+            self.client
+                .create_multipart_upload()
+                .bucket(&self.bucket)
+                .key(key)
+                .content_type(content_type)
+                .set_server_side_encryption(kms_key_id.map(|_| ServerSideEncryption::AwsKms))
+                .set_ssekms_key_id(kms_key_id.map(|k| k.to_string()))
+                .send()
+                .await
+                .map_err(classify_sdk_error)
+        })
+        .await?;
+
+        output
+            .upload_id()
+            .map(|id| id.to_string())
+            .ok_or_else(|| StorageError::S3Error("Multipart upload returned no upload ID".to_string()))
+    }
+
+    /// Generate a presigned PUT URL for a single part of a multipart upload
+    pub async fn presign_upload_part(
+        &self,
+        key: &str,
+        upload_id: &str,
+        part_number: i32,
+        expires: Duration,
+    ) -> Result<Url> {
+        let presigning_config = PresigningConfig::expires_in(expires)
+            .map_err(|e| StorageError::ConfigError(format!("Invalid presigning config: {}", e)))?;
+
+        self.retry_operation(|| async {
+            let request = self
+                .client
+                .upload_part()
+                .bucket(&self.bucket)
+                .key(key)
+                .upload_id(upload_id)
+                .part_number(part_number)
+                .presigned(presigning_config.clone())
+                .await
+                .map_err(classify_sdk_error)?;
+
+            Ok(Url::parse(request.uri())?)
+        })
+        .await
+    }
+
+    /// List the parts S3 has received for a multipart upload
+    pub async fn list_uploaded_parts(&self, key: &str, upload_id: &str) -> Result<Vec<UploadedPart>> {
+        let mut parts = Vec::new();
+        let mut marker: Option<String> = None;
+
+        loop {
+            let output = metrics::observe("list_parts", async {
+                self.client
+                    .list_parts()
+                    .bucket(&self.bucket)
+                    .key(key)
+                    .upload_id(upload_id)
+                    .set_part_number_marker(marker.take())
+                    .send()
+                    .await
+                    .map_err(classify_sdk_error)
+            })
+            .await?;
+
+            for part in output.parts() {
+                if let (Some(part_number), Some(etag)) = (part.part_number(), part.e_tag()) {
+                    parts.push(UploadedPart {
+                        part_number,
+                        etag: etag.to_string(),
+                    });
+                }
+            }
+
+            if output.is_truncated().unwrap_or(false) {
+                marker = output.next_part_number_marker().map(|m| m.to_string());
+                if marker.is_some() {
+                    continue;
+                }
+            }
+            break;
+        }
+
+        parts.sort_by_key(|p| p.part_number);
+        Ok(parts)
+    }
+
+    /// Complete a multipart upload from its uploaded parts.
+    ///
+    /// A failed completion leaves the upload open: a transient error, a
+    /// missing part or a stale ETag can all be fixed by re-uploading and
+    /// completing again. Callers that give up call `abort_multipart_upload`.
+    pub async fn complete_multipart_upload(
+        &self,
+        key: &str,
+        upload_id: &str,
+        parts: &[UploadedPart],
+    ) -> Result<CompletedUpload> {
+        let mut parts = parts.to_vec();
+        parts.sort_by_key(|p| p.part_number);
+
+        let completed = aws_sdk_s3::types::CompletedMultipartUpload::builder()
+            .set_parts(Some(
+                parts
+                    .iter()
+                    .map(|p| {
+                        aws_sdk_s3::types::CompletedPart::builder()
+                            .part_number(p.part_number)
+                            .e_tag(&p.etag)
+                            .build()
+                    })
+                    .collect(),
+            ))
+            .build();
+
+        let output = metrics::observe("complete_multipart_upload", async {
+            self.client
+                .complete_multipart_upload()
+                .bucket(&self.bucket)
+                .key(key)
+                .upload_id(upload_id)
+                .multipart_upload(completed)
+                .send()
+                .await
+                .map_err(classify_sdk_error)
+        })
+        .await?;
+
+        Ok(CompletedUpload {
+            etag: output.e_tag().map(|e| e.to_string()),
+            parts,
+        })
+    }
+
+    /// Abort a multipart upload and discard any uploaded parts
+    pub async fn abort_multipart_upload(&self, key: &str, upload_id: &str) -> Result<()> {
+        metrics::observe("abort_multipart_upload", async {
+            self.client
+                .abort_multipart_upload()
+                .bucket(&self.bucket)
+                .key(key)
+                .upload_id(upload_id)
+                .send()
+                .await
+                .map_err(classify_sdk_error)
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    /// Copy `source` to `destination` within the bucket, keeping its content
+    /// type and KMS key. Objects over S3's single-copy limit are copied in
+    /// parts, and the partial copy is aborted if any part fails.
+    pub async fn copy_object(&self, source: &str, destination: &str) -> Result<()> {
+        let head = self
+            .head_object(source)
+            .await?
+            .ok_or_else(|| StorageError::S3Error(format!("Copy source {} does not exist", source)))?;
+        let copy_source = format!("{}/{}", self.bucket, source);
+        let size = head.content_length.max(0) as u64;
+
+        if size <= DEFAULT_MULTIPART_THRESHOLD {
+            return metrics::observe("copy_object", self.retry_operation(|| async {
+                self.client
+                    .copy_object()
+                    .bucket(&self.bucket)
+                    .key(destination)
+                    .copy_source(&copy_source)
+                    .set_server_side_encryption(head.kms_key_id.as_ref().map(|_| ServerSideEncryption::AwsKms))
+                    .set_ssekms_key_id(head.kms_key_id.clone())
+                    .send()
+                    .await
+                    .map(|_| ())
+                    .map_err(classify_sdk_error)
+            }))
+            .await;
+        }
+
+        let content_type = head.content_type.as_deref().unwrap_or("application/octet-stream");
+        let upload_id = self
+            .create_multipart_upload(destination, content_type, head.kms_key_id.as_deref())
+            .await?;
+        let copied = match self.copy_parts(&copy_source, destination, &upload_id, size).await {
+            Ok(parts) => self.complete_multipart_upload(destination, &upload_id, &parts).await.map(|_| ()),
+            Err(e) => Err(e),
+        };
+        if copied.is_err() {
+            if let Err(abort_err) = self.abort_multipart_upload(destination, &upload_id).await {
+                tracing::warn!("Failed to abort multipart copy {} to {}: {}", upload_id, destination, abort_err);
+            }
+        }
+        copied
+    }
+
+    /// Copy `size` bytes of `copy_source` into the parts of a multipart upload
+    async fn copy_parts(&self, copy_source: &str, key: &str, upload_id: &str, size: u64) -> Result<Vec<UploadedPart>> {
+        let part_size = self.multipart_part_size(size);
+        let mut parts = Vec::with_capacity(part_count(size, part_size) as usize);
+
+        for (index, start) in (0..size).step_by(part_size as usize).enumerate() {
+            let part_number = index as i32 + 1;
+            let range = format!("bytes={}-{}", start, (start + part_size).min(size) - 1);
+            let output = metrics::observe("upload_part_copy", self.retry_operation(|| async {
+                self.client
+                    .upload_part_copy()
+                    .bucket(&self.bucket)
+                    .key(key)
+                    .upload_id(upload_id)
+                    .part_number(part_number)
+                    .copy_source(copy_source)
+                    .copy_source_range(&range)
+                    .send()
+                    .await
+                    .map_err(classify_sdk_error)
+            }))
+            .await?;
+
+            let etag = output
+                .copy_part_result()
+                .and_then(|result| result.e_tag())
+                .ok_or_else(|| StorageError::S3Error(format!("Part copy {} of {} returned no ETag", part_number, key)))?;
+            parts.push(UploadedPart {
+                part_number,
+                etag: etag.to_string(),
+            });
+        }
 
-        Ok(Url::parse(&request.uri().to_string())?)
+        Ok(parts)
     }
 
     /// Create content-addressed S3 key from SHA256 hash
@@ -167,6 +966,36 @@ impl StorageClient {
         format!("sha256/{}/{}/{}", &sha256[0..2], &sha256[2..4], sha256)
     }
 
+    /// The base64 form S3 uses for a hex sha256; `None` if `sha256` is not hex
+    pub fn checksum_sha256_base64(sha256: &str) -> Option<String> {
+        if !sha256.len().is_multiple_of(2) {
+            return None;
+        }
+        let bytes = (0..sha256.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(sha256.get(i..i + 2)?, 16).ok())
+            .collect::<Option<Vec<u8>>>()?;
+        Some(base64::engine::general_purpose::STANDARD.encode(bytes))
+    }
+
+    /// Key a multipart upload of `sha256` is assembled under until its bytes
+    /// are verified. Every upload gets its own key, so nothing written to
+    /// one can reach the shared content address directly.
+    pub fn staging_key(sha256: &str) -> String {
+        format!("{}{:032x}", Self::staging_prefix(sha256), rand::thread_rng().gen::<u128>())
+    }
+
+    /// Prefix shared by the staging keys of every upload of `sha256`
+    pub fn staging_prefix(sha256: &str) -> String {
+        format!("uploads/{}/", sha256)
+    }
+
+    /// Whether `key` is a key `staging_key` could have returned for `sha256`
+    pub fn is_staging_key_for(key: &str, sha256: &str) -> bool {
+        key.strip_prefix(&Self::staging_prefix(sha256))
+            .is_some_and(|id| !id.is_empty() && id.chars().all(|c| c.is_ascii_hexdigit()))
+    }
+
     /// Ensure bucket exists with production-ready configuration
     async fn ensure_bucket_exists(client: &S3Client, bucket: &str) -> Result<()> {
         // Try to create bucket with retry logic
@@ -280,6 +1109,84 @@ impl StorageClient {
     }
 }
 
+fn is_not_found<E>(err: &SdkError<E>) -> bool {
+    err.raw_response()
+        .map(|r| r.status().as_u16() == 404)
+        .unwrap_or(false)
+}
+
+/// Parse a KMS key setting; blank means no key
+fn parse_kms_key_id(raw: &str) -> Option<String> {
+    let raw = raw.trim();
+    (!raw.is_empty()).then(|| raw.to_string())
+}
+
+/// A presigned URL lifetime, if S3 will honour it
+pub fn presign_ttl(seconds: u64) -> Result<Duration> {
+    let ttl = Duration::from_secs(seconds);
+    if ttl.is_zero() || ttl > MAX_PRESIGN_TTL {
+        return Err(StorageError::ConfigError(format!(
+            "Presigned URL TTL must be between 1 and {} seconds, got {}",
+            MAX_PRESIGN_TTL.as_secs(),
+            seconds
+        )));
+    }
+    Ok(ttl)
+}
+
+/// Parse a presigned URL TTL setting in seconds; blank means `DEFAULT_PRESIGN_TTL`
+fn parse_presign_ttl(raw: &str) -> Result<Duration> {
+    let raw = raw.trim();
+    if raw.is_empty() {
+        return Ok(DEFAULT_PRESIGN_TTL);
+    }
+    let seconds = raw
+        .parse::<u64>()
+        .map_err(|_| StorageError::ConfigError(format!("Presigned URL TTL must be a whole number of seconds, got '{}'", raw)))?;
+    presign_ttl(seconds)
+}
+
+/// Parse the `S3_ENDPOINT` setting; an empty value selects the default AWS endpoint
+fn parse_endpoint(raw: &str) -> Result<Option<Url>> {
+    let raw = raw.trim();
+    if raw.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(Url::parse(raw)?))
+}
+
+/// Build the S3 client configuration, overriding the endpoint for MinIO/Ceph when one is set
+fn s3_config_builder(
+    region: String,
+    credentials: Credentials,
+    endpoint: Option<&Url>,
+    force_path_style: bool,
+) -> ConfigBuilder {
+    let mut config_builder = ConfigBuilder::default()
+        .behavior_version(BehaviorVersion::latest())
+        .region(Region::new(region))
+        .credentials_provider(credentials)
+        .force_path_style(force_path_style);
+
+    if let Some(endpoint) = endpoint {
+        config_builder = config_builder.endpoint_url(endpoint.as_str().trim_end_matches('/'));
+    }
+
+    config_builder
+}
+
+/// Pick a part size for `size` bytes that respects S3's part count and minimum size limits
+pub fn plan_part_size(size: u64, preferred: u64) -> u64 {
+    let preferred = preferred.max(MIN_MULTIPART_PART_SIZE);
+    let minimum_for_count = size.div_ceil(MAX_MULTIPART_PARTS);
+    preferred.max(minimum_for_count)
+}
+
+/// Number of parts needed to upload `size` bytes with the given part size
+pub fn part_count(size: u64, part_size: u64) -> u64 {
+    size.div_ceil(part_size).max(1)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -290,4 +1197,652 @@ mod tests {
         let key = StorageClient::content_address_key(sha256);
         assert_eq!(key, "sha256/a6/65/a665a45920422f9d417e4867efdc4fb8a04a1f3fff1fa07e998e86f7f7a27ae3");
     }
+
+    #[test]
+    fn test_staging_keys_are_per_upload_and_tied_to_the_hash() {
+        let sha256 = "a665a45920422f9d417e4867efdc4fb8a04a1f3fff1fa07e998e86f7f7a27ae3";
+        let key = StorageClient::staging_key(sha256);
+        assert_ne!(key, StorageClient::staging_key(sha256));
+        assert!(StorageClient::is_staging_key_for(&key, sha256));
+
+        assert!(!StorageClient::is_staging_key_for(&key, &"b".repeat(64)));
+        assert!(!StorageClient::is_staging_key_for(&StorageClient::content_address_key(sha256), sha256));
+        assert!(!StorageClient::is_staging_key_for(&format!("uploads/{}/", sha256), sha256));
+        assert!(!StorageClient::is_staging_key_for(&format!("uploads/{}/../x", sha256), sha256));
+    }
+
+    #[test]
+    fn test_plan_part_size_respects_minimum() {
+        assert_eq!(plan_part_size(1024, 1), MIN_MULTIPART_PART_SIZE);
+        assert_eq!(plan_part_size(1024, DEFAULT_MULTIPART_PART_SIZE), DEFAULT_MULTIPART_PART_SIZE);
+    }
+
+    #[test]
+    fn test_plan_part_size_stays_under_part_limit() {
+        let size = 5 * 1024 * 1024 * 1024 * 1024; // 5 TiB, S3's object limit
+        let part_size = plan_part_size(size, MIN_MULTIPART_PART_SIZE);
+        assert!(part_count(size, part_size) <= MAX_MULTIPART_PARTS);
+    }
+
+    #[test]
+    fn test_part_count() {
+        let part_size = MIN_MULTIPART_PART_SIZE;
+        assert_eq!(part_count(3 * part_size, part_size), 3);
+        assert_eq!(part_count(3 * part_size + 1, part_size), 4);
+        assert_eq!(part_count(0, part_size), 1);
+    }
+
+    fn test_client(endpoint: Option<&str>, force_path_style: bool) -> StorageClient {
+        let endpoint = endpoint.map(|e| parse_endpoint(e).unwrap().unwrap());
+        let credentials = Credentials::new("test", "test", None, None, "test");
+        let config = s3_config_builder("us-east-1".to_string(), credentials, endpoint.as_ref(), force_path_style).build();
+        client_with_config(config)
+    }
+
+    /// Client whose HTTP layer is answered by `responder` instead of a real S3
+    fn mock_client(
+        responder: impl Fn(http::Request<aws_sdk_s3::primitives::SdkBody>) -> http::Response<String> + Send + Sync + 'static,
+    ) -> StorageClient {
+        let endpoint = parse_endpoint("http://minio.test:9000").unwrap();
+        let credentials = Credentials::new("test", "test", None, None, "test");
+        let config = s3_config_builder("us-east-1".to_string(), credentials, endpoint.as_ref(), true)
+            .http_client(aws_smithy_http_client::test_util::infallible_client_fn(responder))
+            .build();
+        client_with_config(config)
+    }
+
+    fn client_with_config(config: aws_sdk_s3::Config) -> StorageClient {
+        StorageClient::from_client(S3Client::from_conf(config), "blacklake")
+    }
+
+    #[test]
+    fn test_parse_endpoint() {
+        assert!(parse_endpoint("").unwrap().is_none());
+        assert!(parse_endpoint("   ").unwrap().is_none());
+        assert_eq!(
+            parse_endpoint("http://minio:9000").unwrap().unwrap().as_str(),
+            "http://minio:9000/"
+        );
+        assert!(parse_endpoint("not a url").is_err());
+    }
+
+    #[test]
+    fn test_parse_presign_ttl() {
+        assert_eq!(parse_presign_ttl("").unwrap(), DEFAULT_PRESIGN_TTL);
+        assert_eq!(parse_presign_ttl(" 900 ").unwrap(), Duration::from_secs(900));
+        assert_eq!(parse_presign_ttl("604800").unwrap(), MAX_PRESIGN_TTL);
+        assert!(parse_presign_ttl("604801").is_err());
+        assert!(parse_presign_ttl("0").is_err());
+        assert!(parse_presign_ttl("1h").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_configured_presign_ttls_are_applied() {
+        let mut client = test_client(None, false);
+        client.upload_url_ttl = Duration::from_secs(6 * 60 * 60);
+        client.download_url_ttl = Duration::from_secs(300);
+
+        let upload_ttl = client.upload_url_ttl(None).unwrap();
+        let url = client.presign_put("some/key", 5, "text/plain", None, None, upload_ttl).await.unwrap();
+        assert!(url.query().unwrap().contains("X-Amz-Expires=21600"));
+
+        let download_ttl = client.download_url_ttl(None).unwrap();
+        let url = client.presign_get("some/key", download_ttl).await.unwrap();
+        assert!(url.query().unwrap().contains("X-Amz-Expires=300"));
+
+        // A repository's own setting wins over the server default
+        assert_eq!(client.download_url_ttl(Some(60)).unwrap(), Duration::from_secs(60));
+        assert_eq!(client.upload_url_ttl(Some(86_400)).unwrap(), Duration::from_secs(86_400));
+    }
+
+    #[test]
+    fn test_out_of_range_repo_presign_ttl_is_rejected() {
+        let client = test_client(None, false);
+
+        assert!(matches!(client.upload_url_ttl(Some(8 * 24 * 60 * 60)), Err(StorageError::ConfigError(_))));
+        assert!(matches!(client.download_url_ttl(Some(0)), Err(StorageError::ConfigError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_custom_endpoint_is_used() {
+        let client = test_client(Some("http://minio.test:9000"), true);
+        let url = client.presign_get("some/key", Duration::from_secs(60)).await.unwrap();
+        assert_eq!(url.host_str(), Some("minio.test"));
+        assert_eq!(url.port(), Some(9000));
+        assert!(url.path().starts_with("/blacklake/some/key"));
+    }
+
+    #[tokio::test]
+    async fn test_default_endpoint_without_override() {
+        let client = test_client(None, false);
+        let url = client.presign_get("some/key", Duration::from_secs(60)).await.unwrap();
+        assert!(url.host_str().unwrap().ends_with("amazonaws.com"));
+    }
+
+    #[tokio::test]
+    async fn test_retry_operation_retries_transient_errors() {
+        use std::sync::atomic::{AtomicU32, Ordering};
+
+        let client = test_client(None, false);
+        let attempts = AtomicU32::new(0);
+
+        let result = client
+            .retry_operation(|| async {
+                let attempt = attempts.fetch_add(1, Ordering::SeqCst) + 1;
+                if attempt < 3 {
+                    Err(StorageError::Transient(format!("blip {}", attempt)))
+                } else {
+                    Ok(attempt)
+                }
+            })
+            .await;
+
+        assert_eq!(result.unwrap(), 3);
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_retry_operation_does_not_retry_permanent_errors() {
+        use std::sync::atomic::{AtomicU32, Ordering};
+
+        let client = test_client(None, false);
+        let attempts = AtomicU32::new(0);
+
+        let result: Result<()> = client
+            .retry_operation(|| async {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                Err(StorageError::AwsSdkError("AccessDenied".to_string()))
+            })
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_head_object_returns_metadata() {
+        let client = mock_client(|req| {
+            assert_eq!(req.method(), "HEAD");
+            assert_eq!(req.uri().path(), "/blacklake/sha256/ab/cd/abcd");
+            http::Response::builder()
+                .status(200)
+                .header("Content-Length", "1234")
+                .header("Content-Type", "text/csv")
+                .header("ETag", "\"abc123\"")
+                .header("Last-Modified", "Wed, 21 Oct 2015 07:28:00 GMT")
+                .body(String::new())
+                .unwrap()
+        });
+
+        let head = client.head_object("sha256/ab/cd/abcd").await.unwrap().unwrap();
+        assert_eq!(head.content_length, 1234);
+        assert_eq!(head.content_type.as_deref(), Some("text/csv"));
+        assert_eq!(head.etag.as_deref(), Some("\"abc123\""));
+        assert_eq!(head.checksum_sha256, None);
+        assert!(head.last_modified.is_some());
+        assert!(client.object_exists("sha256/ab/cd/abcd").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_storage_class_usage_aggregates_mixed_classes() {
+        let client = mock_client(|req| {
+            assert_eq!(req.method(), "HEAD");
+            let (status, length, class) = match req.uri().path() {
+                "/blacklake/hot-a" => (200, "100", None),
+                "/blacklake/hot-b" => (200, "50", None),
+                "/blacklake/cool" => (200, "1000", Some("STANDARD_IA")),
+                "/blacklake/cold-a" => (200, "4000", Some("GLACIER")),
+                "/blacklake/cold-b" => (200, "6000", Some("GLACIER")),
+                _ => (404, "0", None),
+            };
+            let mut response = http::Response::builder().status(status).header("Content-Length", length);
+            if let Some(class) = class {
+                response = response.header("x-amz-storage-class", class);
+            }
+            response.body(String::new()).unwrap()
+        });
+
+        let keys: Vec<String> = ["hot-a", "hot-b", "cool", "cold-a", "cold-b", "gone"]
+            .iter()
+            .map(|k| k.to_string())
+            .collect();
+        let report = client.storage_class_usage(&keys).await.unwrap();
+
+        assert_eq!(report.missing, 1);
+        assert_eq!(report.classes.len(), 3);
+        assert_eq!(report.classes["STANDARD"], StorageClassUsage { objects: 2, bytes: 150 });
+        assert_eq!(report.classes["STANDARD_IA"], StorageClassUsage { objects: 1, bytes: 1000 });
+        assert_eq!(report.classes["GLACIER"], StorageClassUsage { objects: 2, bytes: 10_000 });
+    }
+
+    #[tokio::test]
+    async fn test_head_bucket_reports_unreachable_bucket() {
+        let client = mock_client(|req| {
+            assert_eq!(req.method(), "HEAD");
+            assert_eq!(req.uri().path(), "/blacklake/");
+            http::Response::builder().status(403).body(String::new()).unwrap()
+        });
+
+        assert!(client.head_bucket().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_head_object_requests_and_reports_checksum() {
+        let client = mock_client(|req| {
+            assert_eq!(req.headers().get("x-amz-checksum-mode").unwrap(), "ENABLED");
+            http::Response::builder()
+                .status(200)
+                .header("Content-Length", "3")
+                .header("x-amz-checksum-sha256", "ungWv48Bz+pBQUDeXa4iI7ADYaOWF3qctBD/YfIAFa0=")
+                .body(String::new())
+                .unwrap()
+        });
+
+        let head = client.head_object("sha256/ba/78/ba78").await.unwrap().unwrap();
+        assert_eq!(head.checksum_sha256.as_deref(), Some("ungWv48Bz+pBQUDeXa4iI7ADYaOWF3qctBD/YfIAFa0="));
+    }
+
+    #[tokio::test]
+    async fn test_presign_put_signs_checksum_header() {
+        let client = test_client(Some("http://minio.test:9000"), true);
+        let checksum = "ungWv48Bz+pBQUDeXa4iI7ADYaOWF3qctBD/YfIAFa0=";
+
+        let url = client
+            .presign_put("sha256/ba/78/ba78", 3, "text/plain", Some(checksum), None, Duration::from_secs(60))
+            .await
+            .unwrap();
+        let signed_headers = url
+            .query_pairs()
+            .find(|(name, _)| name == "X-Amz-SignedHeaders")
+            .map(|(_, value)| value.to_string())
+            .unwrap();
+        assert!(signed_headers.split(';').any(|h| h == "x-amz-checksum-sha256"), "{}", signed_headers);
+
+        let url = client
+            .presign_put("sha256/ba/78/ba78", 3, "text/plain", None, None, Duration::from_secs(60))
+            .await
+            .unwrap();
+        assert!(!url.as_str().contains("checksum"));
+    }
+
+    fn signed_headers(url: &Url) -> Vec<String> {
+        url.query_pairs()
+            .find(|(name, _)| name == "X-Amz-SignedHeaders")
+            .map(|(_, value)| value.split(';').map(|h| h.to_string()).collect())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_presign_put_signs_sse_kms_headers() {
+        let mut client = test_client(Some("http://minio.test:9000"), true);
+        client.kms_key_id = Some("alias/blacklake-default".to_string());
+
+        let url = client
+            .presign_put("sha256/ba/78/ba78", 3, "text/plain", None, None, Duration::from_secs(60))
+            .await
+            .unwrap();
+        let signed = signed_headers(&url);
+        assert!(signed.iter().any(|h| h == "x-amz-server-side-encryption"), "{:?}", signed);
+        assert!(signed.iter().any(|h| h == "x-amz-server-side-encryption-aws-kms-key-id"), "{:?}", signed);
+
+        // What the client is told to send has to match what was signed
+        let headers = StorageClient::sse_kms_headers(client.kms_key_id(None).unwrap());
+        assert_eq!(
+            headers,
+            vec![
+                ("x-amz-server-side-encryption".to_string(), "aws:kms".to_string()),
+                ("x-amz-server-side-encryption-aws-kms-key-id".to_string(), "alias/blacklake-default".to_string()),
+            ]
+        );
+        for (name, _) in &headers {
+            assert!(signed.contains(name), "{} not signed", name);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_presign_put_without_kms_key_leaves_encryption_to_bucket() {
+        let client = test_client(Some("http://minio.test:9000"), true);
+
+        let url = client
+            .presign_put("sha256/ba/78/ba78", 3, "text/plain", None, None, Duration::from_secs(60))
+            .await
+            .unwrap();
+        assert!(!signed_headers(&url).iter().any(|h| h.starts_with("x-amz-server-side-encryption")));
+    }
+
+    #[test]
+    fn test_repo_kms_key_overrides_default() {
+        let mut client = test_client(None, false);
+        assert_eq!(client.kms_key_id(None), None);
+        assert_eq!(client.kms_key_id(Some("repo-key")), Some("repo-key"));
+
+        client.kms_key_id = parse_kms_key_id(" alias/blacklake-default ");
+        assert_eq!(client.kms_key_id(None), Some("alias/blacklake-default"));
+        assert_eq!(client.kms_key_id(Some("repo-key")), Some("repo-key"));
+        assert_eq!(client.kms_key_id(Some("  ")), Some("alias/blacklake-default"));
+        assert_eq!(parse_kms_key_id(""), None);
+    }
+
+    #[tokio::test]
+    async fn test_create_multipart_upload_requests_sse_kms() {
+        let client = mock_client(|req| {
+            assert_eq!(req.headers().get("x-amz-server-side-encryption").unwrap(), "aws:kms");
+            assert_eq!(req.headers().get("x-amz-server-side-encryption-aws-kms-key-id").unwrap(), "repo-key");
+            http::Response::builder()
+                .status(200)
+                .body(
+                    "<InitiateMultipartUploadResult><Bucket>blacklake</Bucket><Key>k</Key>\
+                     <UploadId>upload-1</UploadId></InitiateMultipartUploadResult>"
+                        .to_string(),
+                )
+                .unwrap()
+        });
+
+        let upload_id = client
+            .create_multipart_upload("sha256/ba/78/ba78", "text/plain", Some("repo-key"))
+            .await
+            .unwrap();
+        assert_eq!(upload_id, "upload-1");
+    }
+
+    #[test]
+    fn test_checksum_sha256_base64() {
+        // sha256("abc")
+        let sha256 = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        assert_eq!(
+            StorageClient::checksum_sha256_base64(sha256).as_deref(),
+            Some("ungWv48Bz+pBQUDeXa4iI7ADYaOWF3qctBD/YfIAFa0=")
+        );
+        assert_eq!(StorageClient::checksum_sha256_base64("abc"), None);
+        assert_eq!(StorageClient::checksum_sha256_base64("zz"), None);
+    }
+
+    #[tokio::test]
+    async fn test_head_object_missing_key_is_none() {
+        let client = mock_client(|_| http::Response::builder().status(404).body(String::new()).unwrap());
+
+        assert!(client.head_object("missing").await.unwrap().is_none());
+        assert!(!client.object_exists("missing").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_head_object_maps_other_statuses_to_errors() {
+        let client = mock_client(|_| http::Response::builder().status(403).body(String::new()).unwrap());
+
+        let err = client.head_object("forbidden").await.unwrap_err();
+        assert!(!err.is_retryable());
+    }
+
+    #[tokio::test]
+    async fn test_get_object_streams_body() {
+        let client = mock_client(|req| {
+            assert_eq!(req.method(), "GET");
+            assert_eq!(req.uri().path(), "/blacklake/sha256/ab/cd/abcd");
+            http::Response::builder().status(200).body("a,b\n1,2\n".to_string()).unwrap()
+        });
+
+        let body = client.get_object("sha256/ab/cd/abcd").await.unwrap().unwrap();
+        let bytes = body.collect().await.unwrap().into_bytes();
+        assert_eq!(&bytes[..], b"a,b\n1,2\n");
+
+        let client = mock_client(|_| http::Response::builder().status(404).body(String::new()).unwrap());
+        assert!(client.get_object("missing").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_get_object_range_forwards_range_header() {
+        let client = mock_client(|req| match req.headers().get("range").map(|v| v.to_str().unwrap()) {
+            Some("bytes=2-4") => http::Response::builder()
+                .status(206)
+                .header("Content-Length", "3")
+                .header("Content-Range", "bytes 2-4/8")
+                .header("Content-Type", "text/csv")
+                .body("b\n1".to_string())
+                .unwrap(),
+            Some(_) => http::Response::builder().status(416).body(String::new()).unwrap(),
+            None => http::Response::builder()
+                .status(200)
+                .header("Content-Length", "8")
+                .body("a,b\n1,2\n".to_string())
+                .unwrap(),
+        });
+
+        let object = client.get_object_range("k", Some("bytes=2-4")).await.unwrap().unwrap();
+        assert_eq!(object.content_length, 3);
+        assert_eq!(object.content_range.as_deref(), Some("bytes 2-4/8"));
+        assert_eq!(object.content_type.as_deref(), Some("text/csv"));
+        assert_eq!(&object.body.collect().await.unwrap().into_bytes()[..], b"b\n1");
+
+        let object = client.get_object_range("k", None).await.unwrap().unwrap();
+        assert_eq!(object.content_length, 8);
+        assert!(object.content_range.is_none());
+
+        let err = client.get_object_range("k", Some("bytes=100-")).await.unwrap_err();
+        assert!(matches!(err, StorageError::RangeNotSatisfiable(_)));
+    }
+
+    #[tokio::test]
+    async fn test_delete_object_then_missing() {
+        use std::sync::{Arc, Mutex};
+
+        let deleted = Arc::new(Mutex::new(false));
+        let state = deleted.clone();
+        let client = mock_client(move |req| {
+            let mut deleted = state.lock().unwrap();
+            let status = match req.method().as_str() {
+                "DELETE" => {
+                    assert!(req.uri().query().unwrap_or_default().contains("versionId=v1"));
+                    *deleted = true;
+                    204
+                }
+                "HEAD" if *deleted => 404,
+                _ => 200,
+            };
+            http::Response::builder().status(status).body(String::new()).unwrap()
+        });
+
+        assert!(client.object_exists("some/key").await.unwrap());
+        client.delete_object("some/key", Some("v1")).await.unwrap();
+        assert!(!client.object_exists("some/key").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_delete_objects_chunks_and_reports_failures() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        let client = mock_client(move |req| {
+            assert_eq!(req.method(), "POST");
+            assert!(req.uri().query().unwrap_or_default().contains("delete"));
+            counter.fetch_add(1, Ordering::SeqCst);
+            http::Response::builder()
+                .status(200)
+                .body(
+                    "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+                     <DeleteResult><Error><Key>key-7</Key><Code>AccessDenied</Code>\
+                     <Message>Access Denied</Message></Error></DeleteResult>"
+                        .to_string(),
+                )
+                .unwrap()
+        });
+
+        let keys: Vec<ObjectVersion> = (0..1500).map(|i| ObjectVersion::from(format!("key-{}", i))).collect();
+        let result = client.delete_objects(&keys).await.unwrap();
+
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+        assert_eq!(result.failed.len(), 2); // reported once per chunk by the mock
+        assert_eq!(result.failed[0].key, "key-7");
+        assert_eq!(result.failed[0].code.as_deref(), Some("AccessDenied"));
+        assert_eq!(result.deleted.len(), 1499);
+    }
+
+    #[tokio::test]
+    async fn test_delete_objects_sends_version_ids() {
+        let client = mock_client(|req| {
+            assert_eq!(req.method(), "POST");
+            let body = std::str::from_utf8(req.body().bytes().unwrap()).unwrap().to_string();
+            assert!(body.contains("<Key>data</Key><VersionId>v1</VersionId>"), "{}", body);
+            assert!(body.contains("<Key>data</Key><VersionId>v2</VersionId>"), "{}", body);
+            assert!(body.contains("<Key>plain</Key></Object>"), "{}", body);
+            http::Response::builder()
+                .status(200)
+                .body(
+                    "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+                     <DeleteResult><Error><Key>data</Key><VersionId>v2</VersionId><Code>AccessDenied</Code>\
+                     <Message>Access Denied</Message></Error></DeleteResult>"
+                        .to_string(),
+                )
+                .unwrap()
+        });
+
+        let version = |key: &str, version_id: Option<&str>| ObjectVersion {
+            key: key.to_string(),
+            version_id: version_id.map(|v| v.to_string()),
+            last_modified: None,
+        };
+        let objects = [version("data", Some("v1")), version("data", Some("v2")), version("plain", None)];
+        let result = client.delete_objects(&objects).await.unwrap();
+
+        // Only the refused version of "data" is reported as failed
+        assert_eq!(result.deleted, vec![version("data", Some("v1")), version("plain", None)]);
+        assert_eq!(result.failed.len(), 1);
+        assert_eq!((result.failed[0].key.as_str(), result.failed[0].version_id.as_deref()), ("data", Some("v2")));
+    }
+
+    #[tokio::test]
+    async fn test_list_object_versions_keeps_only_the_exact_key() {
+        let client = mock_client(|req| {
+            assert_eq!(req.method(), "GET");
+            let query = req.uri().query().unwrap_or_default();
+            assert!(query.contains("versions") && query.contains("prefix=sha256%2Fab"), "{}", query);
+            http::Response::builder()
+                .status(200)
+                .body(
+                    "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+                     <ListVersionsResult><IsTruncated>false</IsTruncated>\
+                     <Version><Key>sha256/ab</Key><VersionId>v2</VersionId><IsLatest>false</IsLatest>\
+                     <LastModified>2024-03-01T12:00:00.000Z</LastModified></Version>\
+                     <Version><Key>sha256/ab</Key><VersionId>v1</VersionId><IsLatest>false</IsLatest></Version>\
+                     <Version><Key>sha256/abc</Key><VersionId>v9</VersionId><IsLatest>true</IsLatest></Version>\
+                     <DeleteMarker><Key>sha256/ab</Key><VersionId>v3</VersionId><IsLatest>true</IsLatest></DeleteMarker>\
+                     </ListVersionsResult>"
+                        .to_string(),
+                )
+                .unwrap()
+        });
+
+        let versions = client.list_object_versions("sha256/ab").await.unwrap();
+        let ids: Vec<Option<&str>> = versions.iter().map(|v| v.version_id.as_deref()).collect();
+        assert_eq!(ids, vec![Some("v2"), Some("v1"), Some("v3")]);
+        assert!(versions.iter().all(|v| v.key == "sha256/ab"));
+        assert_eq!(
+            versions[0].last_modified,
+            Some(std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_709_294_400))
+        );
+        assert_eq!(versions[1].last_modified, None);
+    }
+
+    #[tokio::test]
+    async fn test_prefix_exists_lists_at_most_one_key() {
+        let client = mock_client(|req| {
+            let query = req.uri().query().unwrap_or_default();
+            assert!(query.contains("list-type=2") && query.contains("max-keys=1"), "{}", query);
+            let body = if query.contains("prefix=uploads%2Fab%2F") {
+                "<ListBucketResult><KeyCount>1</KeyCount><Contents><Key>uploads/ab/01</Key></Contents></ListBucketResult>"
+            } else {
+                "<ListBucketResult><KeyCount>0</KeyCount></ListBucketResult>"
+            };
+            http::Response::builder().status(200).body(body.to_string()).unwrap()
+        });
+
+        assert!(client.prefix_exists("uploads/ab/").await.unwrap());
+        assert!(!client.prefix_exists("uploads/cd/").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_failed_completion_leaves_the_upload_open() {
+        let client = mock_client(|req| {
+            assert_ne!(req.method(), "DELETE", "completion failure must not abort the upload");
+            assert_eq!(req.method(), "POST");
+            http::Response::builder()
+                .status(400)
+                .body("<Error><Code>InvalidPart</Code><Message>One or more of the specified parts could not be found.</Message></Error>".to_string())
+                .unwrap()
+        });
+        let parts = vec![UploadedPart { part_number: 1, etag: "\"stale\"".to_string() }];
+
+        let err = client.complete_multipart_upload("uploads/abcd/01", "upload-1", &parts).await.unwrap_err();
+
+        assert!(!err.is_retryable());
+        assert!(err.to_string().contains("InvalidPart"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_copy_object_keeps_the_kms_key() {
+        let client = mock_client(|req| {
+            if req.method() == "HEAD" {
+                return http::Response::builder()
+                    .status(200)
+                    .header("content-length", "3")
+                    .header("x-amz-server-side-encryption", "aws:kms")
+                    .header("x-amz-server-side-encryption-aws-kms-key-id", "repo-key")
+                    .body(String::new())
+                    .unwrap();
+            }
+            assert_eq!(req.method(), "PUT");
+            assert_eq!(req.uri().path(), "/blacklake/sha256/ab/cd/abcd");
+            assert_eq!(req.headers().get("x-amz-copy-source").unwrap(), "blacklake/uploads/abcd/01");
+            assert_eq!(req.headers().get("x-amz-server-side-encryption-aws-kms-key-id").unwrap(), "repo-key");
+            http::Response::builder()
+                .status(200)
+                .body("<CopyObjectResult><ETag>\"e\"</ETag></CopyObjectResult>".to_string())
+                .unwrap()
+        });
+
+        client.copy_object("uploads/abcd/01", "sha256/ab/cd/abcd").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_copy_object_copies_large_objects_in_parts() {
+        let size = DEFAULT_MULTIPART_THRESHOLD + 1;
+        let ranges = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = ranges.clone();
+        let client = mock_client(move |req| {
+            let query = req.uri().query().unwrap_or_default().to_string();
+            let body = match req.method().as_str() {
+                "HEAD" => {
+                    return http::Response::builder()
+                        .status(200)
+                        .header("content-length", size.to_string())
+                        .body(String::new())
+                        .unwrap()
+                }
+                "POST" if query.contains("uploads") => {
+                    "<InitiateMultipartUploadResult><UploadId>copy-1</UploadId></InitiateMultipartUploadResult>".to_string()
+                }
+                "PUT" => {
+                    let range = req.headers().get("x-amz-copy-source-range").unwrap().to_str().unwrap();
+                    seen.lock().unwrap().push(range.to_string());
+                    "<CopyPartResult><ETag>\"p\"</ETag></CopyPartResult>".to_string()
+                }
+                "POST" => {
+                    assert!(query.contains("uploadId=copy-1"), "{}", query);
+                    "<CompleteMultipartUploadResult><ETag>\"e\"</ETag></CompleteMultipartUploadResult>".to_string()
+                }
+                method => panic!("unexpected {} request", method),
+            };
+            http::Response::builder().status(200).body(body).unwrap()
+        });
+
+        client.copy_object("uploads/abcd/01", "sha256/ab/cd/abcd").await.unwrap();
+
+        let ranges = ranges.lock().unwrap();
+        let part_size = plan_part_size(size, DEFAULT_MULTIPART_PART_SIZE);
+        assert_eq!(ranges.len() as u64, part_count(size, part_size));
+        assert_eq!(ranges[0], format!("bytes=0-{}", part_size - 1));
+        let last_start = (ranges.len() as u64 - 1) * part_size;
+        assert_eq!(ranges.last().unwrap(), &format!("bytes={}-{}", last_start, size - 1));
+    }
 }
@@ -0,0 +1,53 @@
+use prometheus::{CounterVec, HistogramOpts, HistogramVec, Opts};
+use std::future::Future;
+use std::time::Instant;
+
+use crate::Result;
+
+// Prometheus metrics for calls to S3. The API registers these in its registry.
+lazy_static::lazy_static! {
+    pub static ref S3_OPERATIONS_TOTAL: CounterVec = CounterVec::new(
+        Opts::new("s3_operations_total", "Total number of S3 operations by operation and outcome"),
+        &["operation", "outcome"]
+    ).unwrap();
+
+    pub static ref S3_OPERATION_DURATION: HistogramVec = HistogramVec::new(
+        HistogramOpts::new("s3_operation_duration_seconds", "S3 operation duration in seconds, including retries"),
+        &["operation"]
+    ).unwrap();
+}
+
+/// Run an S3 call, recording its latency and whether it succeeded
+pub(crate) async fn observe<T, Fut>(operation: &'static str, call: Fut) -> Result<T>
+where
+    Fut: Future<Output = Result<T>>,
+{
+    let started = Instant::now();
+    let result = call.await;
+    S3_OPERATION_DURATION
+        .with_label_values(&[operation])
+        .observe(started.elapsed().as_secs_f64());
+    let outcome = if result.is_ok() { "success" } else { "error" };
+    S3_OPERATIONS_TOTAL.with_label_values(&[operation, outcome]).inc();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StorageError;
+
+    #[tokio::test]
+    async fn test_observe_counts_outcomes_per_operation() {
+        let before_ok = S3_OPERATIONS_TOTAL.with_label_values(&["test_op", "success"]).get();
+        let before_err = S3_OPERATIONS_TOTAL.with_label_values(&["test_op", "error"]).get();
+
+        observe("test_op", async { Ok(()) }).await.unwrap();
+        let failed: Result<()> = observe("test_op", async { Err(StorageError::S3Error("boom".to_string())) }).await;
+
+        assert!(failed.is_err());
+        assert_eq!(S3_OPERATIONS_TOTAL.with_label_values(&["test_op", "success"]).get(), before_ok + 1.0);
+        assert_eq!(S3_OPERATIONS_TOTAL.with_label_values(&["test_op", "error"]).get(), before_err + 1.0);
+        assert_eq!(S3_OPERATION_DURATION.with_label_values(&["test_op"]).get_sample_count(), 2);
+    }
+}
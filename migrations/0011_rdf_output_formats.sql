@@ -0,0 +1,5 @@
+-- Allow N-Triples and RDF/XML materializations in artifact_rdf
+
+ALTER TABLE artifact_rdf DROP CONSTRAINT IF EXISTS artifact_rdf_format_check;
+ALTER TABLE artifact_rdf ADD CONSTRAINT artifact_rdf_format_check
+  CHECK (format IN ('turtle','jsonld','ntriples','rdfxml'));
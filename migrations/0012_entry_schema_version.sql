@@ -0,0 +1,4 @@
+-- Record which metadata schema version validated each entry
+
+ALTER TABLE entry ADD COLUMN IF NOT EXISTS schema_name TEXT;
+ALTER TABLE entry ADD COLUMN IF NOT EXISTS schema_version TEXT;
@@ -0,0 +1,3 @@
+-- Support keyset pagination of search results on (created_at, id)
+
+CREATE INDEX IF NOT EXISTS idx_entry_created_at_id ON entry(created_at, id);
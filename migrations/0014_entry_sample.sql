@@ -0,0 +1,11 @@
+-- Preview rows and inferred column schema produced by the sampling job
+
+CREATE TABLE IF NOT EXISTS entry_sample (
+  commit_id UUID NOT NULL,
+  path TEXT NOT NULL,
+  sample JSONB NOT NULL,            -- array of row objects, capped at 100 rows / 256 KB
+  schema JSONB NOT NULL,            -- {"columns": [{"name": ..., "type": ...}]}
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  PRIMARY KEY (commit_id, path),
+  FOREIGN KEY (commit_id, path) REFERENCES entry(commit_id, path) ON DELETE CASCADE
+);
@@ -0,0 +1,11 @@
+-- Last-seen state of each remote object, used by incremental connector sync
+
+CREATE TABLE IF NOT EXISTS connector_sync_state (
+  connector_id UUID NOT NULL,
+  object_key TEXT NOT NULL,
+  etag TEXT,
+  modified_at TIMESTAMPTZ,
+  size BIGINT,
+  synced_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  PRIMARY KEY (connector_id, object_key)
+);
@@ -0,0 +1,27 @@
+-- Constraints enforced before presigned URLs are issued, and the violations they record
+
+CREATE TABLE IF NOT EXISTS signed_url_constraint (
+  id UUID PRIMARY KEY,
+  url_id UUID NOT NULL,
+  constraint_type TEXT NOT NULL,
+  configuration JSONB NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  expires_at TIMESTAMPTZ,
+  active BOOLEAN NOT NULL DEFAULT TRUE
+);
+
+CREATE INDEX IF NOT EXISTS idx_signed_url_constraint_url_id ON signed_url_constraint(url_id) WHERE active;
+
+CREATE TABLE IF NOT EXISTS signed_url_violation (
+  id UUID PRIMARY KEY,
+  url_id UUID NOT NULL,
+  constraint_id UUID NOT NULL,
+  violation_type TEXT NOT NULL,
+  client_ip TEXT NOT NULL,
+  user_agent TEXT NOT NULL,
+  occurred_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  details JSONB NOT NULL,
+  action_taken TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_signed_url_violation_url_id ON signed_url_violation(url_id, occurred_at DESC);
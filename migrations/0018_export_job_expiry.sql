@@ -0,0 +1,12 @@
+-- When an export's download URL lapses; the cleanup sweep selects on this
+
+ALTER TABLE export_jobs ADD COLUMN IF NOT EXISTS expires_at TIMESTAMPTZ;
+
+-- Older releases wrote 'processing' for in-flight exports
+ALTER TABLE export_jobs DROP CONSTRAINT IF EXISTS export_jobs_status_check;
+UPDATE export_jobs SET status = 'running' WHERE status = 'processing';
+ALTER TABLE export_jobs ADD CONSTRAINT export_jobs_status_check
+  CHECK (status IN ('pending', 'running', 'completed', 'failed'));
+
+CREATE INDEX IF NOT EXISTS idx_export_jobs_expires_at
+  ON export_jobs(expires_at) WHERE expires_at IS NOT NULL;
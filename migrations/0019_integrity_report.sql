@@ -0,0 +1,28 @@
+-- Content-integrity verification runs and the objects they flagged
+
+CREATE TABLE IF NOT EXISTS integrity_report (
+    id UUID PRIMARY KEY,
+    repo_id UUID REFERENCES repo(id) ON DELETE CASCADE, -- NULL for a system-wide run
+    status TEXT NOT NULL DEFAULT 'running' CHECK (status IN ('running', 'completed')),
+    cursor TEXT, -- last verified object sha256; runs resume after it
+    objects_checked BIGINT NOT NULL DEFAULT 0,
+    mismatches BIGINT NOT NULL DEFAULT 0,
+    started_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMPTZ
+);
+
+CREATE TABLE IF NOT EXISTS integrity_report_finding (
+    id BIGSERIAL PRIMARY KEY,
+    report_id UUID NOT NULL REFERENCES integrity_report(id) ON DELETE CASCADE,
+    sha256 TEXT NOT NULL,
+    s3_key TEXT NOT NULL,
+    kind TEXT NOT NULL CHECK (kind IN ('mismatch', 'missing', 'error')),
+    actual_sha256 TEXT,
+    detail TEXT,
+    found_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (report_id, sha256)
+);
+
+CREATE INDEX IF NOT EXISTS idx_integrity_report_repo ON integrity_report(repo_id, started_at DESC);
+CREATE INDEX IF NOT EXISTS idx_integrity_report_finding_sha ON integrity_report_finding(sha256);
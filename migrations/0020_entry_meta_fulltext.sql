@@ -0,0 +1,16 @@
+-- Full-text search over entry metadata without Solr.
+-- array_to_string is only STABLE, so wrap it for use in a generated column.
+CREATE OR REPLACE FUNCTION entry_meta_tags_text(tags TEXT[]) RETURNS TEXT
+  LANGUAGE sql IMMUTABLE PARALLEL SAFE
+  AS $$ SELECT coalesce(array_to_string(tags, ' '), '') $$;
+
+-- File name and tags weigh most, then description, then notes
+ALTER TABLE entry_meta_index
+  ADD COLUMN IF NOT EXISTS search_vector TSVECTOR GENERATED ALWAYS AS (
+    setweight(to_tsvector('english', coalesce(file_name, '')), 'A') ||
+    setweight(to_tsvector('english', entry_meta_tags_text(tags)), 'A') ||
+    setweight(to_tsvector('english', coalesce(description, '')), 'B') ||
+    setweight(to_tsvector('english', coalesce(notes, '')), 'C')
+  ) STORED;
+
+CREATE INDEX IF NOT EXISTS idx_meta_index_search_vector ON entry_meta_index USING GIN(search_vector);
@@ -0,0 +1,10 @@
+-- Objects left unreferenced by a repository deletion, held until the
+-- deleted repo's hard-delete window has passed
+
+CREATE TABLE IF NOT EXISTS object_gc_schedule (
+    sha256 TEXT PRIMARY KEY REFERENCES object(sha256) ON DELETE CASCADE,
+    not_before TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS idx_object_gc_schedule_not_before ON object_gc_schedule(not_before);
@@ -0,0 +1,5 @@
+-- Second parent of a merge commit: the head of the branch that was merged in
+
+ALTER TABLE commit ADD COLUMN IF NOT EXISTS merge_parent_id UUID REFERENCES commit(id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS idx_commit_merge_parent ON commit(merge_parent_id) WHERE merge_parent_id IS NOT NULL;
@@ -0,0 +1,16 @@
+-- Embedding vectors for committed entries, searched by cosine distance
+
+CREATE EXTENSION IF NOT EXISTS vector;
+
+-- Width matches ENTRY_EMBEDDING_DIMENSIONS in blacklake_core::embeddings
+CREATE TABLE IF NOT EXISTS entry_embedding (
+  commit_id UUID NOT NULL,
+  path TEXT NOT NULL,
+  model TEXT NOT NULL,
+  embedding VECTOR(384) NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  PRIMARY KEY (commit_id, path),
+  FOREIGN KEY (commit_id, path) REFERENCES entry(commit_id, path) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_entry_embedding_cosine ON entry_embedding USING hnsw (embedding vector_cosine_ops);
@@ -0,0 +1,9 @@
+-- Retention state for deleted entries. A commit that deletes a path stamps
+-- the parent commit's entry with deleted_at; the retention worker sets
+-- tombstoned_at once the repo's tombstone window has passed and removes the
+-- row after the hard-delete window.
+
+ALTER TABLE entry ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+ALTER TABLE entry ADD COLUMN IF NOT EXISTS tombstoned_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_entry_deleted_at ON entry(deleted_at) WHERE deleted_at IS NOT NULL;
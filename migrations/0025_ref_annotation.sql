@@ -0,0 +1,13 @@
+-- Annotated tags. A tag with a row here carries the tagger and message it
+-- was created with; tags without one are lightweight. Either kind is
+-- immutable unless an admin forces it.
+
+CREATE TABLE IF NOT EXISTS ref_annotation (
+    repo_id UUID NOT NULL,
+    ref_name TEXT NOT NULL,
+    message TEXT NOT NULL,
+    tagger TEXT NOT NULL,
+    tagged_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (repo_id, ref_name),
+    FOREIGN KEY (repo_id, ref_name) REFERENCES ref(repo_id, name) ON DELETE CASCADE
+);
@@ -0,0 +1,16 @@
+-- Objects generated from committed entries, such as Parquet copies of CSVs.
+-- A link goes away with its source entry; until then it keeps the derived
+-- object out of orphan GC.
+
+CREATE TABLE IF NOT EXISTS derived_artifact (
+  commit_id UUID NOT NULL,
+  path TEXT NOT NULL,
+  kind TEXT NOT NULL,
+  source_sha256 TEXT NOT NULL,
+  derived_sha256 TEXT NOT NULL REFERENCES object(sha256),
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  PRIMARY KEY (commit_id, path, kind),
+  FOREIGN KEY (commit_id, path) REFERENCES entry(commit_id, path) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_derived_artifact_derived ON derived_artifact(derived_sha256);
@@ -0,0 +1,17 @@
+-- Search reindex runs and how far each has got
+
+CREATE TABLE IF NOT EXISTS reindex_run (
+    id UUID PRIMARY KEY, -- the FullReindexJob's job id
+    repo_id UUID, -- NULL reindexes every repo
+    since_commit_id UUID, -- only entries from commits created after this one
+    status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'running', 'completed', 'failed')),
+    cursor_commit_id UUID, -- last indexed (commit_id, path); runs resume after it
+    cursor_path TEXT,
+    total BIGINT NOT NULL DEFAULT 0,
+    indexed_count BIGINT NOT NULL DEFAULT 0,
+    error_message TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    started_at TIMESTAMPTZ,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMPTZ
+);
@@ -0,0 +1,5 @@
+-- Virtual repositories: a repo marked with a connector serves its tree and
+-- blobs from that connector's listing instead of from commits
+
+ALTER TABLE repo ADD COLUMN IF NOT EXISTS connector_id UUID REFERENCES external_source(id) ON DELETE SET NULL;
+ALTER TABLE repo ADD COLUMN IF NOT EXISTS connector_type TEXT; -- copied from the source when the repo is marked
@@ -0,0 +1,16 @@
+-- Idempotency keys: the first response to a keyed commit or upload-init is
+-- kept so a retry with the same key gets it back instead of running again
+
+CREATE TABLE IF NOT EXISTS idempotency_key (
+    repo_id UUID NOT NULL REFERENCES repo(id) ON DELETE CASCADE,
+    endpoint TEXT NOT NULL, -- 'commit' or 'upload_init'
+    key TEXT NOT NULL,
+    request_sha256 TEXT NOT NULL, -- caller and request body; a reused key must match
+    status SMALLINT, -- NULL while the first request is still running
+    response JSONB,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (repo_id, endpoint, key)
+);
+
+CREATE INDEX IF NOT EXISTS idx_idempotency_key_expires_at ON idempotency_key(expires_at);
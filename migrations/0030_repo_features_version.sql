@@ -0,0 +1,4 @@
+-- Feature flag versions: every write bumps it, so a writer can make its
+-- update conditional on the version it read and not clobber a concurrent one
+
+ALTER TABLE repo ADD COLUMN IF NOT EXISTS features_version BIGINT NOT NULL DEFAULT 0;
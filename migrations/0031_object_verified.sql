@@ -0,0 +1,7 @@
+-- Upload verification: an object whose bytes were checked against its sha256
+-- is never deleted by a later upload of the same hash that fails the check
+
+ALTER TABLE object ADD COLUMN IF NOT EXISTS verified_at TIMESTAMPTZ;
+
+-- Objects stored before verification existed have been served as they are
+UPDATE object SET verified_at = created_at WHERE verified_at IS NULL;
@@ -0,0 +1,12 @@
+-- Webhook delivery claims: a worker marks the deliveries it is about to send
+-- 'in_flight' so other replicas skip them. A claim not resolved within its
+-- lease is taken to be from a worker that died and is claimed again.
+
+ALTER TABLE webhook_deliveries ADD COLUMN IF NOT EXISTS claimed_at TIMESTAMPTZ;
+
+ALTER TABLE webhook_deliveries DROP CONSTRAINT IF EXISTS webhook_deliveries_status_check;
+ALTER TABLE webhook_deliveries ADD CONSTRAINT webhook_deliveries_status_check
+  CHECK (status IN ('pending', 'failed', 'in_flight', 'delivered'));
+
+CREATE INDEX IF NOT EXISTS idx_webhook_deliveries_in_flight
+  ON webhook_deliveries(claimed_at) WHERE status = 'in_flight';
@@ -0,0 +1,6 @@
+-- Idempotency key leases: a key whose request has not finished within its
+-- lease is taken to be from a replica that died and can be claimed again.
+-- Headers such as the quota warning are kept with the stored response.
+
+ALTER TABLE idempotency_key ADD COLUMN IF NOT EXISTS claimed_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+ALTER TABLE idempotency_key ADD COLUMN IF NOT EXISTS response_headers JSONB;
@@ -102,5 +102,143 @@ if [ -f "migrations/0010_api_missing_tables.sql" ]; then
     psql "$DATABASE_URL" -f migrations/0010_api_missing_tables.sql
 fi
 
+# Migration 12: RDF output formats
+if [ -f "migrations/0011_rdf_output_formats.sql" ]; then
+    echo "   📄 Running 0011_rdf_output_formats.sql..."
+    psql "$DATABASE_URL" -f migrations/0011_rdf_output_formats.sql
+fi
+
+# Migration 13: Entry schema versions
+if [ -f "migrations/0012_entry_schema_version.sql" ]; then
+    echo "   📄 Running 0012_entry_schema_version.sql..."
+    psql "$DATABASE_URL" -f migrations/0012_entry_schema_version.sql
+fi
+
+# Migration 14: Keyset pagination index
+if [ -f "migrations/0013_entry_keyset_index.sql" ]; then
+    echo "   📄 Running 0013_entry_keyset_index.sql..."
+    psql "$DATABASE_URL" -f migrations/0013_entry_keyset_index.sql
+fi
+
+# Migration 15: Entry samples
+if [ -f "migrations/0014_entry_sample.sql" ]; then
+    echo "   📄 Running 0014_entry_sample.sql..."
+    psql "$DATABASE_URL" -f migrations/0014_entry_sample.sql
+fi
+
+# Migration 16: Connector sync state
+if [ -f "migrations/0015_connector_sync_state.sql" ]; then
+    echo "   📄 Running 0015_connector_sync_state.sql..."
+    psql "$DATABASE_URL" -f migrations/0015_connector_sync_state.sql
+fi
+
+# Migration 17: Signed URL constraints
+if [ -f "migrations/0016_signed_url_constraints.sql" ]; then
+    echo "   📄 Running 0016_signed_url_constraints.sql..."
+    psql "$DATABASE_URL" -f migrations/0016_signed_url_constraints.sql
+fi
+
+# Migration 18: Webhook delivery status
+if [ -f "migrations/0017_webhook_delivery_status.sql" ]; then
+    echo "   📄 Running 0017_webhook_delivery_status.sql..."
+    psql "$DATABASE_URL" -f migrations/0017_webhook_delivery_status.sql
+fi
+
+# Migration 19: Export job expiry
+if [ -f "migrations/0018_export_job_expiry.sql" ]; then
+    echo "   📄 Running 0018_export_job_expiry.sql..."
+    psql "$DATABASE_URL" -f migrations/0018_export_job_expiry.sql
+fi
+
+# Migration 20: Integrity reports
+if [ -f "migrations/0019_integrity_report.sql" ]; then
+    echo "   📄 Running 0019_integrity_report.sql..."
+    psql "$DATABASE_URL" -f migrations/0019_integrity_report.sql
+fi
+
+# Migration 21: Full-text metadata search
+if [ -f "migrations/0020_entry_meta_fulltext.sql" ]; then
+    echo "   📄 Running 0020_entry_meta_fulltext.sql..."
+    psql "$DATABASE_URL" -f migrations/0020_entry_meta_fulltext.sql
+fi
+
+# Migration 22: Object GC schedule
+if [ -f "migrations/0021_object_gc_schedule.sql" ]; then
+    echo "   📄 Running 0021_object_gc_schedule.sql..."
+    psql "$DATABASE_URL" -f migrations/0021_object_gc_schedule.sql
+fi
+
+# Migration 23: Merge commit parents
+if [ -f "migrations/0022_commit_merge_parent.sql" ]; then
+    echo "   📄 Running 0022_commit_merge_parent.sql..."
+    psql "$DATABASE_URL" -f migrations/0022_commit_merge_parent.sql
+fi
+
+# Migration 24: Entry embeddings
+if [ -f "migrations/0023_entry_embedding.sql" ]; then
+    echo "   📄 Running 0023_entry_embedding.sql..."
+    psql "$DATABASE_URL" -f migrations/0023_entry_embedding.sql
+fi
+
+# Migration 25: Entry tombstones
+if [ -f "migrations/0024_entry_tombstones.sql" ]; then
+    echo "   📄 Running 0024_entry_tombstones.sql..."
+    psql "$DATABASE_URL" -f migrations/0024_entry_tombstones.sql
+fi
+
+# Migration 26: Annotated tags
+if [ -f "migrations/0025_ref_annotation.sql" ]; then
+    echo "   📄 Running 0025_ref_annotation.sql..."
+    psql "$DATABASE_URL" -f migrations/0025_ref_annotation.sql
+fi
+
+# Migration 27: Derived artifacts
+if [ -f "migrations/0026_derived_artifact.sql" ]; then
+    echo "   📄 Running 0026_derived_artifact.sql..."
+    psql "$DATABASE_URL" -f migrations/0026_derived_artifact.sql
+fi
+
+# Migration 28: Reindex runs
+if [ -f "migrations/0027_reindex_run.sql" ]; then
+    echo "   📄 Running 0027_reindex_run.sql..."
+    psql "$DATABASE_URL" -f migrations/0027_reindex_run.sql
+fi
+
+# Migration 29: Virtual repositories
+if [ -f "migrations/0028_virtual_repo.sql" ]; then
+    echo "   📄 Running 0028_virtual_repo.sql..."
+    psql "$DATABASE_URL" -f migrations/0028_virtual_repo.sql
+fi
+
+# Migration 30: Idempotency keys
+if [ -f "migrations/0029_idempotency_key.sql" ]; then
+    echo "   📄 Running 0029_idempotency_key.sql..."
+    psql "$DATABASE_URL" -f migrations/0029_idempotency_key.sql
+fi
+
+# Migration 31: Feature flag versions
+if [ -f "migrations/0030_repo_features_version.sql" ]; then
+    echo "   📄 Running 0030_repo_features_version.sql..."
+    psql "$DATABASE_URL" -f migrations/0030_repo_features_version.sql
+fi
+
+# Migration 32: Object verification
+if [ -f "migrations/0031_object_verified.sql" ]; then
+    echo "   📄 Running 0031_object_verified.sql..."
+    psql "$DATABASE_URL" -f migrations/0031_object_verified.sql
+fi
+
+# Migration 33: Webhook delivery claims
+if [ -f "migrations/0032_webhook_delivery_claim.sql" ]; then
+    echo "   📄 Running 0032_webhook_delivery_claim.sql..."
+    psql "$DATABASE_URL" -f migrations/0032_webhook_delivery_claim.sql
+fi
+
+# Migration 34: Idempotency key leases
+if [ -f "migrations/0033_idempotency_key_lease.sql" ]; then
+    echo "   📄 Running 0033_idempotency_key_lease.sql..."
+    psql "$DATABASE_URL" -f migrations/0033_idempotency_key_lease.sql
+fi
+
 echo "🎉 All migrations completed successfully!"
 echo "📊 Database schema is now up to date"